@@ -0,0 +1,255 @@
+//! Planet administration module.
+//!
+//! The host controls a running planet AI through [`AdminCommand`]s sent over a
+//! channel, whose receiving end is passed to the planet with
+//! [`PlanetConfig::with_admin`](crate::PlanetConfig::with_admin).
+//!
+//! The planet run loop is owned by `common_game`, so the AI can't wait on the admin
+//! channel: pending commands are applied, in order, right before the AI handles the
+//! next orchestrator or explorer message.
+//!
+//! Commands sent one by one may be applied across several messages, interleaved with
+//! live traffic. Setup touching many explorers at once (ban lists, quotas, tags) sends
+//! an [`AdminCommand::Batch`] instead, built with [`AdminCommand::ban_list`],
+//! [`AdminCommand::assign_quotas`] or [`AdminCommand::import_tags`]: its commands are
+//! all applied before the next message.
+
+use crate::Quota;
+use crate::benchmark::BenchmarkReport;
+use crate::policy::Policy;
+use crate::stats::Stats;
+use crate::tags::Tag;
+use common_game::components::resource::BasicResourceType;
+use common_game::protocols::planet_explorer::PlanetToExplorer;
+use crossbeam_channel::Sender;
+use std::time::Duration;
+
+/// Command sent by the host to a running planet AI.
+#[derive(Debug, Clone)]
+pub enum AdminCommand {
+    /// Starts a new game epoch: per-epoch allowances (see
+    /// [`ExplorerRequestLimit::EpochBudget`](crate::ExplorerRequestLimit::EpochBudget))
+    /// and per-epoch statistics are reset. Credit balances (see
+    /// [`ExplorerRequestLimit::Credits`](crate::ExplorerRequestLimit::Credits)), and
+    /// the budgets and scores of policies configured with a carryover, keep their
+    /// carried over fraction.
+    AdvanceEpoch,
+    /// Moves every policy schedule to its next phase (see [`Policy::Schedule`](crate::policy::Policy::Schedule)).
+    /// Schedules already in their last phase are unaffected.
+    NextPhase,
+    /// Restores the allowances (quotas, scores, budgets) of an explorer,
+    /// or of every explorer if `explorer_id` is `None`.
+    ResetQuota {
+        /// The explorer to reset, or `None` for all of them.
+        explorer_id: Option<u32>,
+    },
+    /// Assigns an explorer an individual quota, replacing the policy otherwise limiting it.
+    SetQuota {
+        /// The explorer the quota is assigned to.
+        explorer_id: u32,
+        /// The individual quota.
+        quota: Quota,
+    },
+    /// Attaches a tag to an explorer.
+    Tag {
+        /// The tagged explorer.
+        explorer_id: u32,
+        /// The attached tag.
+        tag: Tag,
+    },
+    /// Detaches a tag from an explorer.
+    Untag {
+        /// The untagged explorer.
+        explorer_id: u32,
+        /// The detached tag.
+        tag: Tag,
+    },
+    /// Reserves the next charged cell for an explorer.
+    Reserve {
+        /// The explorer holding the reservation.
+        explorer_id: u32,
+    },
+    /// Leases the next charged cells to an explorer (see [`crate::lease`]). The outcome
+    /// is reported as an [`Event::LeaseGranted`](crate::events::Event::LeaseGranted) or
+    /// [`Event::LeaseRefused`](crate::events::Event::LeaseRefused).
+    Lease {
+        /// The explorer holding the lease.
+        explorer_id: u32,
+        /// The resource the leased cells are claimed for.
+        resource: BasicResourceType,
+        /// Number of leased cells.
+        cells: u32,
+        /// How long the explorer has to claim the cells.
+        duration: Duration,
+    },
+    /// Cancels the oldest reservation held by an explorer.
+    CancelReservation {
+        /// The explorer holding the reservation.
+        explorer_id: u32,
+    },
+    /// Lets the AI check whether an explorer channel is full before discharging a cell
+    /// for it, through a clone of the sender given to the planet for that explorer.
+    WatchExplorer {
+        /// The explorer the channel belongs to.
+        explorer_id: u32,
+        /// A clone of the sender the planet uses to answer the explorer.
+        sender: Sender<PlanetToExplorer>,
+    },
+    /// Reports an explorer as unreachable (e.g. its channel got disconnected):
+    /// its generation requests are refused without discharging cells.
+    ExplorerUnreachable {
+        /// The unreachable explorer.
+        explorer_id: u32,
+    },
+    /// Reports an explorer as reachable again.
+    ExplorerReachable {
+        /// The reachable explorer.
+        explorer_id: u32,
+    },
+    /// Sends again the fulfillments in the dead letters (see [`crate::delivery`]) of an
+    /// explorer, or of every explorer if `explorer_id` is `None`.
+    RedriveDeadLetters {
+        /// The explorer whose dead letters are sent again, or `None` for all of them.
+        explorer_id: Option<u32>,
+    },
+    /// Pauses the handling of generation requests, e.g. while the host settles a dispute.
+    /// Sunrays are still accepted, so no energy is lost.
+    Pause {
+        /// What happens to the generation requests received while paused.
+        mode: PauseMode,
+    },
+    /// Resumes the handling of generation requests.
+    Resume,
+    /// Replaces the planet-wide limit policy, carrying over the usage history of the
+    /// old one where meaningful, or after a warm-up if configured (see
+    /// [`AI::set_policy`](crate::planet::AI::set_policy)).
+    SetPolicy {
+        /// The new policy.
+        policy: Policy,
+    },
+    /// Bans an explorer: its generation and combination requests are refused with
+    /// [`DenialReason::Banned`](crate::policy::DenialReason::Banned) until unbanned, or
+    /// until the ban expires. Banning a banned explorer replaces its ban.
+    BanExplorer {
+        /// The banned explorer.
+        explorer_id: u32,
+        /// How long the ban lasts, `None` until unbanned.
+        duration: Option<Duration>,
+    },
+    /// Lifts the ban of an explorer.
+    Unban {
+        /// The unbanned explorer.
+        explorer_id: u32,
+    },
+    /// Disables the generation of a resource, e.g. while the storyline of the host
+    /// depletes it: requests for it are refused with
+    /// [`DenialReason::ResourceDisabled`](crate::policy::DenialReason::ResourceDisabled)
+    /// until enabled again, or until the duration elapses. Disabling a disabled resource
+    /// replaces its timer.
+    DisableResource {
+        /// The disabled resource.
+        resource: BasicResourceType,
+        /// How long the resource stays disabled, `None` until enabled.
+        duration: Option<Duration>,
+    },
+    /// Enables the generation of a disabled resource again.
+    EnableResource {
+        /// The enabled resource.
+        resource: BasicResourceType,
+    },
+    /// Performs the housekeeping of the AI right away (see
+    /// [`PlanetConfig::with_housekeeping`](crate::PlanetConfig::with_housekeeping)),
+    /// whether or not housekeeping is enabled or due.
+    Tick,
+    /// Sends a snapshot of the statistics, taken between two messages of the planet
+    /// AI, so it is consistent with the commands sent before.
+    SnapshotStats {
+        /// Where the snapshot is sent. The AI doesn't block on it.
+        reply: Sender<Stats>,
+    },
+    /// Measures the cost of the decisions of the planet on synthetic requests (see
+    /// [`crate::benchmark`]), holding the next message for about `duration`: hosts
+    /// send it while the planet is idle.
+    Benchmark {
+        /// How long the benchmark runs.
+        duration: Duration,
+        /// Where the report is sent. The AI doesn't block on it.
+        reply: Sender<BenchmarkReport>,
+    },
+    /// Applies commands in order, all of them before the next orchestrator or explorer
+    /// message.
+    Batch {
+        /// The commands applied, batches included.
+        commands: Vec<AdminCommand>,
+    },
+}
+
+impl AdminCommand {
+    /// Batch banning every explorer of `explorer_ids` for `duration`, `None` until
+    /// unbanned.
+    ///
+    /// # Examples
+    /// ```
+    /// use rustrelli::admin::AdminCommand;
+    ///
+    /// let command = AdminCommand::ban_list([3, 7, 12], None);
+    /// assert!(matches!(command, AdminCommand::Batch { commands } if commands.len() == 3));
+    /// ```
+    pub fn ban_list(
+        explorer_ids: impl IntoIterator<Item = u32>,
+        duration: Option<Duration>,
+    ) -> Self {
+        AdminCommand::batch(
+            explorer_ids
+                .into_iter()
+                .map(|explorer_id| AdminCommand::BanExplorer {
+                    explorer_id,
+                    duration,
+                }),
+        )
+    }
+
+    /// Batch assigning each explorer its individual quota.
+    pub fn assign_quotas(quotas: impl IntoIterator<Item = (u32, Quota)>) -> Self {
+        AdminCommand::batch(
+            quotas
+                .into_iter()
+                .map(|(explorer_id, quota)| AdminCommand::SetQuota { explorer_id, quota }),
+        )
+    }
+
+    /// Batch attaching each tag to its explorer.
+    pub fn import_tags(tags: impl IntoIterator<Item = (u32, Tag)>) -> Self {
+        AdminCommand::batch(
+            tags.into_iter()
+                .map(|(explorer_id, tag)| AdminCommand::Tag { explorer_id, tag }),
+        )
+    }
+
+    /// Batch applying `commands` in order.
+    pub fn batch(commands: impl IntoIterator<Item = AdminCommand>) -> Self {
+        AdminCommand::Batch {
+            commands: commands.into_iter().collect(),
+        }
+    }
+}
+
+/// What happens to the generation requests received while the planet is paused.
+///
+/// Whatever the mode, no cell is discharged. The requests of each mode are counted
+/// apart (see [`Stats::paused_requests`](crate::stats::Stats::paused_requests)), so
+/// that the effects of a pause can be told from the denials of the limit policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum PauseMode {
+    /// Requests are denied with
+    /// [`DenialReason::Paused`](crate::policy::DenialReason::Paused).
+    Reject,
+    /// Requests are denied, and kept in the pending queue to be served after resuming.
+    /// Behaves like [`PauseMode::Reject`] without deferred fulfillment (see
+    /// [`crate::pending`]).
+    Buffer,
+    /// Requests are left unanswered, as if they never arrived: they are neither
+    /// journaled nor counted as denials.
+    Drop,
+}
@@ -0,0 +1,666 @@
+//! Journal analyzer module.
+//!
+//! Digests a [`Journal`] recorded by a planet into fairness statistics and
+//! per-explorer timelines ([`Analysis`]), and replays its requests under other policies
+//! to tell how each explorer would have fared ([`counterfactual`]):
+//! ```
+//! use std::time::Duration;
+//! use rustrelli::analyzer::{Analysis, counterfactual};
+//! use rustrelli::journal::Journal;
+//! use rustrelli::{ExplorerId, ExplorerRequestLimit, Quota};
+//!
+//! let journal = Journal::read(
+//!     "rustrelli-journal 1 cells=5 charged=5\n\
+//!      1000 generate 7 Oxygen granted\n\
+//!      1001 generate 7 Oxygen granted\n\
+//!      1002 generate 8 Carbon granted\n"
+//!         .as_bytes(),
+//! )?;
+//! assert_eq!(Analysis::new(&journal).explorers[&ExplorerId::new(7)].grants, 2);
+//!
+//! let quota = Quota::new(1, Duration::from_secs(10));
+//! let replayed = counterfactual(&journal, ExplorerRequestLimit::Quota(quota));
+//! assert_eq!(replayed.extra_grants[&ExplorerId::new(7)], -1);
+//! # Ok::<(), rustrelli::error::RustrelliError>(())
+//! ```
+//! The `rustrelli-journal` binary prints both for a journal file.
+//!
+//! Two journals of the same scenario, e.g. played under two policies or two versions
+//! of the crate, are compared request by request with [`compare`], which reports the
+//! requests decided differently with their context ([`Comparison`]).
+
+use crate::ExplorerId;
+use crate::journal::{Journal, JournalEntry};
+use crate::policy::{DenialReason, Policy, Request};
+use crate::refusal::RefusalReason;
+use crate::timeline::Product;
+use common_game::components::resource::BasicResourceType;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What a journal tells about an explorer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExplorerReport {
+    /// Granted generation requests.
+    pub grants: u64,
+    /// Denied generation requests, by reason.
+    pub denials: BTreeMap<DenialReason, u64>,
+    /// Longest time between a denial and the next grant, or the end of the journal.
+    pub longest_wait: Duration,
+    /// Generation requests in time order, granted if the reason is `None`.
+    pub timeline: Vec<(SystemTime, Option<DenialReason>)>,
+}
+
+impl ExplorerReport {
+    /// Total denied generation requests.
+    pub fn total_denials(&self) -> u64 {
+        self.denials.values().sum()
+    }
+}
+
+/// Fairness statistics of a journal.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Analysis {
+    /// Report of each explorer that requested resources.
+    pub explorers: BTreeMap<ExplorerId, ExplorerReport>,
+    pub sunrays: u64,
+    /// [Jain's fairness index](https://en.wikipedia.org/wiki/Fairness_measure) of the
+    /// grants between the explorers: 1 if they all got the same number, down to
+    /// `1 / n` if a single one got everything.
+    pub fairness: f64,
+    /// Share of the grants each explorer was meant to get, if the game targets an
+    /// uneven distribution (see [`Policy::target_shares`]).
+    pub targets: BTreeMap<ExplorerId, f32>,
+}
+
+impl Analysis {
+    pub fn new(journal: &Journal) -> Self {
+        let mut analysis = Analysis::default();
+        let mut unserved_since = HashMap::new();
+        for entry in &journal.entries {
+            let JournalEntry::Generation {
+                at,
+                explorer_id,
+                denial,
+                ..
+            } = *entry
+            else {
+                if let JournalEntry::Sunray { .. } = entry {
+                    analysis.sunrays += 1;
+                }
+                continue;
+            };
+            let report = analysis.explorers.entry(explorer_id).or_default();
+            report.timeline.push((at, denial));
+            match denial {
+                None => {
+                    report.grants += 1;
+                    if let Some(since) = unserved_since.remove(&explorer_id) {
+                        report.longest_wait = report.longest_wait.max(elapsed(since, at));
+                    }
+                }
+                Some(reason) => {
+                    *report.denials.entry(reason).or_default() += 1;
+                    unserved_since.entry(explorer_id).or_insert(at);
+                }
+            }
+        }
+        if let Some(end) = journal.entries.last().map(JournalEntry::at) {
+            for (explorer_id, since) in unserved_since {
+                let report = analysis.explorers.get_mut(&explorer_id).unwrap();
+                report.longest_wait = report.longest_wait.max(elapsed(since, end));
+            }
+        }
+        analysis.fairness = jain_index(analysis.explorers.values().map(|report| report.grants));
+        analysis
+    }
+
+    /// Reports how far each explorer of `targets` is from its target share of the
+    /// grants, see [`Self::deviations`].
+    pub fn with_targets(mut self, targets: impl IntoIterator<Item = (u32, f32)>) -> Self {
+        self.targets = targets
+            .into_iter()
+            .map(|(explorer_id, share)| (ExplorerId::from(explorer_id), share))
+            .collect();
+        self
+    }
+
+    /// Share of the grants each targeted explorer got over its target share: negative
+    /// if it got less. Explorers that never requested resources got a share of 0.
+    pub fn deviations(&self) -> BTreeMap<ExplorerId, f64> {
+        let shares = self.shares();
+        self.targets
+            .iter()
+            .map(|(explorer_id, target)| {
+                let share = shares.get(explorer_id).copied().unwrap_or(0.0);
+                (*explorer_id, share - f64::from(*target))
+            })
+            .collect()
+    }
+
+    /// Share of the grants each explorer got.
+    pub fn shares(&self) -> BTreeMap<ExplorerId, f64> {
+        let total: u64 = self.explorers.values().map(|report| report.grants).sum();
+        self.explorers
+            .iter()
+            .map(|(explorer_id, report)| (*explorer_id, report.grants as f64 / total.max(1) as f64))
+            .collect()
+    }
+}
+
+impl fmt::Display for Analysis {
+    /// Renders a table of the explorers, followed by the fairness index. The deviations
+    /// from the targets follow the table, if any.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shares = self.shares();
+        writeln!(f, "explorer | grants | denials | share  | longest wait")?;
+        for (explorer_id, report) in &self.explorers {
+            writeln!(
+                f,
+                "{:>8} | {:>6} | {:>7} | {:.4} | {:?}",
+                explorer_id,
+                report.grants,
+                report.total_denials(),
+                shares[explorer_id],
+                report.longest_wait
+            )?;
+        }
+        for (explorer_id, deviation) in self.deviations() {
+            writeln!(
+                f,
+                "explorer {} deviates {:+.4} from its target share {:.4}",
+                explorer_id, deviation, self.targets[&explorer_id]
+            )?;
+        }
+        write!(
+            f,
+            "{} sunrays, fairness index {:.4}",
+            self.sunrays, self.fairness
+        )
+    }
+}
+
+/// Outcome of a journal replayed under another policy, see [`counterfactual`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Counterfactual {
+    /// Grants each explorer would have gotten.
+    pub grants: BTreeMap<ExplorerId, u64>,
+    /// Grants each explorer would have gotten over the journaled ones, negative if
+    /// fewer.
+    pub extra_grants: BTreeMap<ExplorerId, i64>,
+}
+
+impl fmt::Display for Counterfactual {
+    /// Renders a line per explorer whose grants would have changed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changed: Vec<_> = self
+            .extra_grants
+            .iter()
+            .filter(|(_, extra)| **extra != 0)
+            .collect();
+        if changed.is_empty() {
+            return write!(f, "every explorer would have gotten the same grants");
+        }
+        for (index, (explorer_id, extra)) in changed.into_iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            let more = if *extra > 0 { "more" } else { "fewer" };
+            let plural = if extra.unsigned_abs() == 1 { "" } else { "s" };
+            write!(
+                f,
+                "explorer {} would have gotten {} {} grant{}",
+                explorer_id,
+                extra.unsigned_abs(),
+                more,
+                plural
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Replays the generation requests of `journal` under `policy`, with the energy of the
+/// journaled sunrays.
+///
+/// The journal doesn't record the tags of the explorers nor the costs of the requests:
+/// every request is replayed with weight 1 and cost 1. Requests refused regardless of
+/// the policy (explorer banned, planet paused, resource unsupported or undeliverable,
+/// cells reserved) stay refused, and cells discharged by combinations aren't journaled.
+pub fn counterfactual(journal: &Journal, policy: impl Into<Policy>) -> Counterfactual {
+    let mut policy = policy.into().build();
+    if let Some(start) = journal.entries.first() {
+        policy.start(start.at());
+    }
+    let mut charged = journal.charged;
+    let mut counterfactual = Counterfactual::default();
+    let tags = Arc::new(BTreeSet::new());
+    for entry in &journal.entries {
+        match *entry {
+            JournalEntry::Sunray { .. } => charged = (charged + 1).min(journal.cells),
+            JournalEntry::Epoch { at } => policy.advance_epoch(at),
+            JournalEntry::Generation {
+                at,
+                explorer_id,
+                resource,
+                denial,
+                ..
+            } => {
+                let grants = counterfactual.grants.entry(explorer_id).or_default();
+                let fixed = matches!(
+                    denial,
+                    Some(
+                        DenialReason::Banned
+                            | DenialReason::Paused
+                            | DenialReason::UnsupportedResource
+                            | DenialReason::ResourceDisabled
+                            | DenialReason::Undeliverable
+                            | DenialReason::Reserved
+                            | DenialReason::Offered
+                    )
+                );
+                if fixed || charged == 0 {
+                    continue;
+                }
+                let request = Request {
+                    explorer_id,
+                    resource: Product::Basic(resource),
+                    now: at,
+                    tags: tags.clone(),
+                    weight: 1.0,
+                    units: 1,
+                    cost: 1.0,
+                    streak: 0,
+                };
+                if policy.admit(&request).is_grant() {
+                    charged -= 1;
+                    *grants += 1;
+                }
+            }
+        }
+    }
+    let journaled = Analysis::new(journal);
+    counterfactual.extra_grants = counterfactual
+        .grants
+        .iter()
+        .map(|(explorer_id, grants)| {
+            let before = journaled.explorers[explorer_id].grants;
+            (*explorer_id, *grants as i64 - before as i64)
+        })
+        .collect();
+    counterfactual
+}
+
+/// Generation request decided differently in two journals, see [`compare`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Divergence {
+    /// The explorer requesting the resource.
+    pub explorer_id: ExplorerId,
+    /// Position of the request among those of the explorer, from 0.
+    pub request: usize,
+    /// The requested resource, in the first journal.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource"))]
+    pub resource: BasicResourceType,
+    /// When the request was decided in each journal.
+    pub at: (SystemTime, SystemTime),
+    /// Decision in each journal: granted if `None`.
+    pub denial: (Option<DenialReason>, Option<DenialReason>),
+    /// Grants of the explorer before the request in each journal.
+    pub grants: (u64, u64),
+    /// Cells charged when the request was decided in each journal, as far as the
+    /// journals tell.
+    pub charged: (usize, usize),
+}
+
+impl fmt::Display for Divergence {
+    /// Renders the decisions side by side, followed by their context.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decision = |denial: Option<DenialReason>| {
+            denial.map_or("granted", |reason| RefusalReason::Denied(reason).code())
+        };
+        write!(
+            f,
+            "explorer {} request #{} for {:?} at {}ms: {} -> {} (grants {}/{}, charged {}/{})",
+            self.explorer_id,
+            self.request,
+            self.resource,
+            millis(self.at.0),
+            decision(self.denial.0),
+            decision(self.denial.1),
+            self.grants.0,
+            self.grants.1,
+            self.charged.0,
+            self.charged.1
+        )
+    }
+}
+
+/// Differences between the decisions of two journals, see [`compare`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Comparison {
+    /// Requests decided differently, in the time order of the first journal.
+    pub divergences: Vec<Divergence>,
+    /// Grants of each explorer in each journal.
+    pub grants: BTreeMap<ExplorerId, (u64, u64)>,
+    /// Requests of each explorer without counterpart in the other journal: positive if
+    /// the second journal has more. Explorers with as many requests in both are left
+    /// out.
+    pub extra_requests: BTreeMap<ExplorerId, i64>,
+    /// [Jain's fairness index](https://en.wikipedia.org/wiki/Fairness_measure) of the
+    /// grants in each journal.
+    pub fairness: (f64, f64),
+}
+
+impl Comparison {
+    /// Whether both journals decided every request the same way.
+    pub fn is_identical(&self) -> bool {
+        self.divergences.is_empty() && self.extra_requests.is_empty()
+    }
+}
+
+impl fmt::Display for Comparison {
+    /// Renders a line per divergence, then the explorers whose grants or requests
+    /// differ, followed by the fairness indexes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} divergent decisions", self.divergences.len())?;
+        for divergence in &self.divergences {
+            writeln!(f, "{divergence}")?;
+        }
+        for (explorer_id, (before, after)) in &self.grants {
+            if before != after {
+                writeln!(f, "explorer {explorer_id} got {before} -> {after} grants")?;
+            }
+        }
+        for (explorer_id, extra) in &self.extra_requests {
+            writeln!(f, "explorer {explorer_id} made {extra:+} requests")?;
+        }
+        write!(
+            f,
+            "fairness index {:.4} -> {:.4}",
+            self.fairness.0, self.fairness.1
+        )
+    }
+}
+
+/// Generation request of a journal, with its context.
+struct Decided {
+    at: SystemTime,
+    resource: BasicResourceType,
+    denial: Option<DenialReason>,
+    grants: u64,
+    charged: usize,
+}
+
+/// Generation requests of each explorer of `journal`, in time order.
+fn decided(journal: &Journal) -> BTreeMap<ExplorerId, Vec<Decided>> {
+    let mut charged = journal.charged;
+    let mut explorers: BTreeMap<ExplorerId, Vec<Decided>> = BTreeMap::new();
+    for entry in &journal.entries {
+        match *entry {
+            JournalEntry::Sunray { .. } => charged = (charged + 1).min(journal.cells),
+            JournalEntry::Epoch { .. } => {}
+            JournalEntry::Generation {
+                at,
+                explorer_id,
+                resource,
+                denial,
+                ..
+            } => {
+                let requests = explorers.entry(explorer_id).or_default();
+                let grants = requests
+                    .last()
+                    .map_or(0, |last| last.grants + u64::from(last.denial.is_none()));
+                requests.push(Decided {
+                    at,
+                    resource,
+                    denial,
+                    grants,
+                    charged,
+                });
+                if denial.is_none() {
+                    charged = charged.saturating_sub(1);
+                }
+            }
+        }
+    }
+    explorers
+}
+
+/// Compares the decisions of two journals, e.g. the same scenario played under two
+/// policies or two versions of the crate.
+///
+/// The requests of each explorer are matched by their order: the n-th request of an
+/// explorer in `before` with its n-th request in `after`, whatever their times.
+///
+/// # Examples
+/// ```
+/// use rustrelli::ExplorerId;
+/// use rustrelli::analyzer::compare;
+/// use rustrelli::journal::Journal;
+///
+/// let before = Journal::read(
+///     "rustrelli-journal 1 cells=5 charged=5\n\
+///      1000 generate 7 Oxygen granted\n\
+///      1001 generate 7 Oxygen granted\n"
+///         .as_bytes(),
+/// )?;
+/// let after = Journal::read(
+///     "rustrelli-journal 1 cells=5 charged=5\n\
+///      1000 generate 7 Oxygen granted\n\
+///      1001 generate 7 Oxygen quota_exceeded\n"
+///         .as_bytes(),
+/// )?;
+/// let comparison = compare(&before, &after);
+/// assert_eq!(comparison.divergences[0].request, 1);
+/// assert_eq!(comparison.grants[&ExplorerId::new(7)], (2, 1));
+/// # Ok::<(), rustrelli::error::RustrelliError>(())
+/// ```
+pub fn compare(before: &Journal, after: &Journal) -> Comparison {
+    let (first, second) = (decided(before), decided(after));
+    let mut comparison = Comparison::default();
+    for explorer_id in first.keys().chain(second.keys()).collect::<BTreeSet<_>>() {
+        let (first, second) = (
+            first.get(explorer_id).map_or(&[][..], Vec::as_slice),
+            second.get(explorer_id).map_or(&[][..], Vec::as_slice),
+        );
+        for (request, (a, b)) in first.iter().zip(second).enumerate() {
+            if a.denial != b.denial {
+                comparison.divergences.push(Divergence {
+                    explorer_id: *explorer_id,
+                    request,
+                    resource: a.resource,
+                    at: (a.at, b.at),
+                    denial: (a.denial, b.denial),
+                    grants: (a.grants, b.grants),
+                    charged: (a.charged, b.charged),
+                });
+            }
+        }
+        let grants = |requests: &[Decided]| {
+            requests
+                .iter()
+                .filter(|request| request.denial.is_none())
+                .count() as u64
+        };
+        comparison
+            .grants
+            .insert(*explorer_id, (grants(first), grants(second)));
+        if first.len() != second.len() {
+            comparison
+                .extra_requests
+                .insert(*explorer_id, second.len() as i64 - first.len() as i64);
+        }
+    }
+    comparison
+        .divergences
+        .sort_by_key(|divergence| (divergence.at.0, divergence.explorer_id));
+    comparison.fairness = (
+        jain_index(comparison.grants.values().map(|grants| grants.0)),
+        jain_index(comparison.grants.values().map(|grants| grants.1)),
+    );
+    comparison
+}
+
+/// Milliseconds since the Unix epoch of `at`, as written in journals.
+fn millis(at: SystemTime) -> u128 {
+    at.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// Time elapsed from `since` to `until`, zero if the clock went backwards.
+fn elapsed(since: SystemTime, until: SystemTime) -> Duration {
+    until.duration_since(since).unwrap_or_default()
+}
+
+/// Jain's fairness index of `values`, 1 if they're all zero.
+pub(crate) fn jain_index(values: impl Iterator<Item = u64>) -> f64 {
+    let (mut count, mut sum, mut squares) = (0.0, 0.0, 0.0);
+    for value in values {
+        count += 1.0;
+        sum += value as f64;
+        squares += (value as f64).powi(2);
+    }
+    if squares == 0.0 {
+        1.0
+    } else {
+        sum * sum / (count * squares)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the journal analysis.
+
+    use super::*;
+    use crate::{ExplorerRequestLimit, Quota};
+
+    /// Map of the explorers of raw IDs `entries`.
+    fn by_explorer<V, const N: usize>(entries: [(u32, V); N]) -> BTreeMap<ExplorerId, V> {
+        entries
+            .into_iter()
+            .map(|(explorer_id, value)| (ExplorerId::new(explorer_id), value))
+            .collect()
+    }
+
+    // ============================================================================
+    // Tests: Analysis
+    // ============================================================================
+
+    /// **Scenario:** Bot 7 takes every cell of a busy journal while explorer 8 waits,
+    /// then the journal is replayed under a quota of 3 grants every 10 seconds
+    /// **Validates:**
+    /// - Grants, denials, waits and the fairness index are computed per explorer
+    /// - The replay only grants what the quota and the journaled energy allow
+    #[test]
+    fn test_counterfactual_under_quota() {
+        let mut text = String::from("rustrelli-journal 1 cells=5 charged=5\n");
+        for i in 0..5 {
+            text.push_str(&format!("{} generate 7 Oxygen granted\n", 1000 + 2 * i));
+            text.push_str(&format!("{} generate 8 Carbon no_energy\n", 1001 + 2 * i));
+        }
+        text.push_str("2000 sunray\n2001 generate 8 Carbon granted\n");
+        let journal = Journal::read(text.as_bytes()).unwrap();
+
+        let analysis = Analysis::new(&journal);
+        assert_eq!(analysis.sunrays, 1);
+        assert_eq!(analysis.explorers[&ExplorerId::new(7)].grants, 5);
+        assert_eq!(
+            analysis.explorers[&ExplorerId::new(8)].denials[&DenialReason::NoEnergy],
+            5
+        );
+        assert_eq!(
+            analysis.explorers[&ExplorerId::new(8)].longest_wait,
+            Duration::from_secs(1)
+        );
+        assert!((analysis.fairness - 36.0 / 52.0).abs() < 1e-9);
+
+        let quota = Quota::new(3, Duration::from_secs(10));
+        let replayed = counterfactual(&journal, ExplorerRequestLimit::Quota(quota));
+        assert_eq!(replayed.grants, by_explorer([(7, 3), (8, 3)]));
+        assert_eq!(replayed.extra_grants, by_explorer([(7, -2), (8, 2)]));
+        assert_eq!(
+            replayed.to_string(),
+            "explorer 7 would have gotten 2 fewer grants\n\
+             explorer 8 would have gotten 2 more grants"
+        );
+    }
+
+    /// **Scenario:** Explorer 7 gets 3 grants and explorer 8 one, where the game
+    /// targets 50% for explorer 7, 25% for explorer 8 and 25% for explorer 9, who never
+    /// requests
+    /// **Validates:** Deviations from the targets are computed and rendered
+    #[test]
+    fn test_target_deviations() {
+        let journal = Journal::read(
+            "rustrelli-journal 1 cells=5 charged=5\n\
+             1000 generate 7 Oxygen granted\n\
+             1001 generate 7 Oxygen granted\n\
+             1002 generate 7 Oxygen granted\n\
+             1003 generate 8 Carbon granted\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        let analysis = Analysis::new(&journal).with_targets([(7, 0.5), (8, 0.25), (9, 0.25)]);
+        assert_eq!(
+            analysis.deviations(),
+            by_explorer([(7, 0.25), (8, 0.0), (9, -0.25)])
+        );
+        assert!(
+            analysis
+                .to_string()
+                .contains("explorer 9 deviates -0.2500 from its target share 0.2500")
+        );
+    }
+
+    // ============================================================================
+    // Tests: Comparison
+    // ============================================================================
+
+    /// **Scenario:** The same game journaled twice, the second time under a policy
+    /// denying the second request of explorer 7 and serving a third request of
+    /// explorer 8
+    /// **Validates:**
+    /// - The divergent request is reported with its context
+    /// - Grants and extra requests are compared per explorer
+    #[test]
+    fn test_compare_journals() {
+        let before = Journal::read(
+            "rustrelli-journal 1 cells=5 charged=3\n\
+             1000 generate 7 Oxygen granted\n\
+             1001 generate 8 Carbon granted\n\
+             1002 generate 7 Oxygen granted\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        let after = Journal::read(
+            "rustrelli-journal 1 cells=5 charged=3\n\
+             1000 generate 7 Oxygen granted\n\
+             1001 generate 8 Carbon granted\n\
+             1003 generate 7 Oxygen fair_share_exceeded\n\
+             1004 generate 8 Carbon granted\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        let comparison = compare(&before, &after);
+        assert!(!comparison.is_identical());
+        assert_eq!(comparison.divergences.len(), 1);
+        assert_eq!(
+            comparison.divergences[0].to_string(),
+            "explorer 7 request #1 for Oxygen at 1002ms: granted -> fair_share_exceeded \
+             (grants 1/1, charged 1/1)"
+        );
+        assert_eq!(comparison.grants, by_explorer([(7, (2, 1)), (8, (1, 2))]));
+        assert_eq!(comparison.extra_requests, by_explorer([(8, 1)]));
+        assert!(compare(&before, &before).is_identical());
+    }
+}
@@ -0,0 +1,231 @@
+//! Denial backoff module.
+//!
+//! After denying a generation request, the planet advises the explorer to retry no
+//! sooner than a retry-after time, read with
+//! [`AI::retry_after`](crate::planet::AI::retry_after) by hosts relaying it to their
+//! explorers. Advice alone doesn't stop badly written bots from hammering the planet:
+//! when backoff is enabled (see
+//! [`PlanetConfig::with_backoff`](crate::PlanetConfig::with_backoff)), a request
+//! arriving before the advised time is denied with [`DenialReason::RetriedTooEarly`],
+//! without being evaluated by the limit policy, and the next advised delay escalates
+//! along the [`BackoffConfig`] schedule.
+//!
+//! A granted request clears the advice, and restarts the schedule of the explorer.
+
+use crate::ExplorerId;
+use crate::policy::DenialReason;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// Escalation schedule of the advised retry-after delays.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::backoff::BackoffConfig;
+///
+/// // 100ms after a denial, then 200ms, 400ms... up to 5 seconds for each early retry
+/// let backoff = BackoffConfig::exponential(Duration::from_millis(100), Duration::from_secs(5));
+/// assert_eq!(backoff.delay(3), Duration::from_millis(800));
+///
+/// // 1 second, then 10 seconds for every early retry
+/// let backoff = BackoffConfig::new([Duration::from_secs(1), Duration::from_secs(10)]);
+/// assert_eq!(backoff.delay(5), Duration::from_secs(10));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackoffConfig {
+    /// Delay advised after a denial, then after each early retry in a row. The last
+    /// delay is repeated.
+    schedule: Vec<Duration>,
+}
+
+impl BackoffConfig {
+    /// Creates a backoff advising the delays of `schedule`, one step further after
+    /// each early retry.
+    ///
+    /// # Panics
+    /// Panics if `schedule` is empty.
+    pub fn new(schedule: impl IntoIterator<Item = Duration>) -> Self {
+        let schedule: Vec<Duration> = schedule.into_iter().collect();
+        assert!(
+            !schedule.is_empty(),
+            "Backoff schedule must have at least one delay"
+        );
+        BackoffConfig { schedule }
+    }
+
+    /// Creates a backoff advising `initial` after a denial, doubled after each early
+    /// retry, up to `max`.
+    ///
+    /// # Panics
+    /// Panics if `initial` is zero or greater than `max`.
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        assert!(
+            !initial.is_zero() && initial <= max,
+            "Initial delay must be greater than zero and at most the maximum delay"
+        );
+        let schedule = std::iter::successors(Some(initial), |delay| {
+            Some((*delay * 2).min(max)).filter(|next| next > delay)
+        });
+        BackoffConfig::new(schedule)
+    }
+
+    /// Delay advised after `escalations` early retries in a row.
+    pub fn delay(&self, escalations: usize) -> Duration {
+        self.schedule[escalations.min(self.schedule.len() - 1)]
+    }
+
+    /// Longest delay of the schedule.
+    fn max_delay(&self) -> Duration {
+        self.schedule.iter().copied().max().unwrap_or_default()
+    }
+}
+
+/// Advice given to an explorer after a denial.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Advice {
+    retry_after: SystemTime,
+    /// Early retries in a row.
+    escalations: usize,
+}
+
+/// Advised retry-after times of the explorers, and their enforcement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Backoffs {
+    config: BackoffConfig,
+    advice: HashMap<ExplorerId, Advice>,
+}
+
+impl Backoffs {
+    pub(crate) fn new(config: BackoffConfig) -> Self {
+        Backoffs {
+            config,
+            advice: HashMap::new(),
+        }
+    }
+
+    /// Delay advised after a first denial.
+    pub(crate) fn initial_delay(&self) -> Duration {
+        self.config.delay(0)
+    }
+
+    /// Time `explorer_id` is advised to retry at, if it was denied since its last grant.
+    pub(crate) fn retry_after(&self, explorer_id: ExplorerId) -> Option<SystemTime> {
+        self.advice
+            .get(&explorer_id)
+            .map(|advice| advice.retry_after)
+    }
+
+    /// Denies a request of `explorer_id` arriving at `now`, before its advised time.
+    /// The denial must then be recorded with [`Self::escalate`].
+    pub(crate) fn check(
+        &self,
+        explorer_id: ExplorerId,
+        now: SystemTime,
+    ) -> Result<(), DenialReason> {
+        match self.advice.get(&explorer_id) {
+            Some(advice) if now < advice.retry_after => Err(DenialReason::RetriedTooEarly),
+            _ => Ok(()),
+        }
+    }
+
+    /// Escalates the advice of `explorer_id`, denied by [`Self::check`] at `now`.
+    pub(crate) fn escalate(&mut self, explorer_id: ExplorerId, now: SystemTime) {
+        if let Some(advice) = self.advice.get_mut(&explorer_id) {
+            advice.escalations += 1;
+            advice.retry_after = now + self.config.delay(advice.escalations);
+        }
+    }
+
+    /// Records the outcome of a request of `explorer_id` allowed by [`Self::check`]: a
+    /// grant clears the advice, a denial advises the current delay of the schedule.
+    pub(crate) fn record(&mut self, explorer_id: ExplorerId, now: SystemTime, granted: bool) {
+        if granted {
+            self.advice.remove(&explorer_id);
+            return;
+        }
+        let advice = self.advice.entry(explorer_id).or_insert(Advice {
+            retry_after: now,
+            escalations: 0,
+        });
+        advice.retry_after = now + self.config.delay(advice.escalations);
+    }
+
+    /// Forgets the advice that expired longer than the longest delay ago, so that the
+    /// schedule of the explorers that stopped retrying restarts.
+    pub(crate) fn tick(&mut self, now: SystemTime) {
+        let max_delay = self.config.max_delay();
+        self.advice.retain(|_, advice| {
+            now.duration_since(advice.retry_after)
+                .map_or(true, |elapsed| elapsed < max_delay)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the denial backoff.
+
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn at(millis: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(millis)
+    }
+
+    // ============================================================================
+    // Tests: Schedule
+    // ============================================================================
+
+    /// **Scenario:** Exponential schedule from 100ms up to 500ms
+    /// **Validates:** Delays double, and stop at the maximum
+    #[test]
+    fn test_exponential_schedule() {
+        let config =
+            BackoffConfig::exponential(Duration::from_millis(100), Duration::from_millis(500));
+        let delays: Vec<_> = (0..5).map(|step| config.delay(step).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+    }
+
+    // ============================================================================
+    // Tests: Enforcement
+    // ============================================================================
+
+    /// **Scenario:** Explorer denied at 0, retries at 50 and 150, then waits until
+    /// its advised time and is granted
+    /// **Validates:**
+    /// - Early retries are denied and escalate the advised delay
+    /// - A grant clears the advice
+    #[test]
+    fn test_early_retries_escalate() {
+        let mut backoffs = Backoffs::new(BackoffConfig::exponential(
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+        ));
+        assert_eq!(backoffs.check(ExplorerId::new(1), at(0)), Ok(()));
+        backoffs.record(ExplorerId::new(1), at(0), false);
+        assert_eq!(backoffs.retry_after(ExplorerId::new(1)), Some(at(100)));
+
+        assert_eq!(
+            backoffs.check(ExplorerId::new(1), at(50)),
+            Err(DenialReason::RetriedTooEarly)
+        );
+        backoffs.escalate(ExplorerId::new(1), at(50));
+        assert_eq!(backoffs.retry_after(ExplorerId::new(1)), Some(at(250)));
+        assert_eq!(
+            backoffs.check(ExplorerId::new(1), at(150)),
+            Err(DenialReason::RetriedTooEarly)
+        );
+        backoffs.escalate(ExplorerId::new(1), at(150));
+        assert_eq!(backoffs.retry_after(ExplorerId::new(1)), Some(at(550)));
+        assert_eq!(
+            backoffs.check(ExplorerId::new(2), at(150)),
+            Ok(()),
+            "Other explorers aren't affected"
+        );
+
+        assert_eq!(backoffs.check(ExplorerId::new(1), at(550)), Ok(()));
+        backoffs.record(ExplorerId::new(1), at(550), true);
+        assert_eq!(backoffs.retry_after(ExplorerId::new(1)), None);
+    }
+}
@@ -0,0 +1,49 @@
+//! Batch grants module.
+//!
+//! An explorer often sends a rapid series of generation requests while several cells
+//! are charged. Deciding on each request separately lets competitors interleave and
+//! leaves every explorer with a partial series. When batch grants are enabled (see
+//! [`PlanetConfig::with_batch_grants`](crate::PlanetConfig::with_batch_grants)), the
+//! first request of a series is decided for the whole batch instead: up to
+//! [`BatchConfig::max_size`] charged cells, all granted or none.
+//!
+//! A granted batch reserves the cells for the rest of the series, which is then
+//! served without being evaluated again, as long as the requests arrive within
+//! [`BatchConfig::window`]. Cells not claimed in time are released, but stay
+//! accounted by the limit policy.
+
+use std::time::{Duration, SystemTime};
+
+/// Configuration of the batch grants.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::batch::BatchConfig;
+///
+/// // Series of up to 3 requests, completed within half a second
+/// let batch = BatchConfig::new(3, Duration::from_millis(500));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    /// Maximum number of cells granted by a single batch decision.
+    pub max_size: u32,
+    /// Time the rest of the series has to claim the cells granted to the batch.
+    pub window: Duration,
+}
+
+impl BatchConfig {
+    /// Creates a batch configuration of at most `max_size` cells claimed within `window`.
+    pub fn new(max_size: u32, window: Duration) -> Self {
+        BatchConfig { max_size, window }
+    }
+}
+
+/// Cells granted to an explorer by a batch decision and not claimed yet.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Batch {
+    /// Cells left to claim. Each of them is backed by a reservation.
+    pub(crate) remaining: u32,
+    /// Time the unclaimed cells are released at.
+    pub(crate) expires: SystemTime,
+}
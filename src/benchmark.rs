@@ -0,0 +1,96 @@
+//! Self-benchmark module.
+//!
+//! The cost of a decision depends on the policy configured and on the features stacked
+//! on it, and on the hardware the planet runs on: operators check it on the tournament
+//! machines themselves with [`AdminCommand::Benchmark`](crate::admin::AdminCommand::Benchmark)
+//! (or [`AI::benchmark`](crate::planet::AI::benchmark) before the planet runs). The
+//! benchmark times decisions on synthetic generation requests, spread over
+//! [`SYNTHETIC_EXPLORERS`] explorers and the basic resources:
+//! - made by a fresh copy of the planet-wide policy, on its own, admitting them as the
+//!   requests of a busy planet
+//! - evaluated against the whole admission logic of the planet, like
+//!   [`AI::would_grant`](crate::planet::AI::would_grant) does, features included
+//!
+//! Neither changes the state of the planet, but the planet handles no message while the
+//! benchmark runs: hosts run it while the planet is idle, e.g. between games.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Number of synthetic explorers the requests of a benchmark are spread over.
+pub const SYNTHETIC_EXPLORERS: u32 = 16;
+/// Maximum number of decisions timed by each part of a benchmark.
+pub const MAX_DECISIONS: u32 = 10_000;
+
+/// Cost of the decisions timed by a part of a benchmark.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DecisionCost {
+    /// Decisions timed.
+    pub decisions: u32,
+    /// Average time a decision took.
+    pub mean: Duration,
+    /// Longest time a decision took.
+    pub max: Duration,
+}
+
+/// Measured cost of the decisions of a planet, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BenchmarkReport {
+    /// Decisions of the planet-wide policy on its own.
+    pub policy: DecisionCost,
+    /// Decisions of the whole admission logic of the planet.
+    pub admission: DecisionCost,
+}
+
+/// Times `decide` on the synthetic requests numbered from 0, until `limit` elapsed or
+/// [`MAX_DECISIONS`] were timed, whichever comes first.
+pub(crate) fn measure<T>(limit: Duration, mut decide: impl FnMut(u32) -> T) -> DecisionCost {
+    let started = Instant::now();
+    let mut cost = DecisionCost::default();
+    let mut total = Duration::ZERO;
+    while cost.decisions < MAX_DECISIONS && started.elapsed() < limit {
+        let before = Instant::now();
+        black_box(decide(black_box(cost.decisions)));
+        let took = before.elapsed();
+        total += took;
+        cost.max = cost.max.max(took);
+        cost.decisions += 1;
+    }
+    if cost.decisions > 0 {
+        cost.mean = total / cost.decisions;
+    }
+    cost
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the benchmark runner.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Measure
+    // ============================================================================
+
+    /// **Scenario:** Instant decisions with a generous time limit, then decisions of
+    /// 2ms with a 10ms limit
+    /// **Validates:**
+    /// - No more than the maximum number of decisions are timed
+    /// - Timing stops once the limit elapsed, the cost covering each decision
+    #[test]
+    fn test_measure() {
+        let mut numbers = Vec::new();
+        let cost = measure(Duration::from_secs(10), |number| numbers.push(number));
+        assert_eq!(cost.decisions, MAX_DECISIONS);
+        assert_eq!(numbers.last(), Some(&(MAX_DECISIONS - 1)));
+
+        let cost = measure(Duration::from_millis(10), |_| {
+            std::thread::sleep(Duration::from_millis(2))
+        });
+        assert!((1..=5).contains(&cost.decisions), "{cost:?}");
+        assert!(cost.mean >= Duration::from_millis(2), "{cost:?}");
+        assert!(cost.max >= cost.mean);
+    }
+}
@@ -0,0 +1,158 @@
+//! Prints the fairness statistics of a planet journal, and how the explorers would have
+//! fared under other policies. Optionally exports the journal as a Chrome trace.
+//! Journals compressed with zstd are read when built with the `zstd` feature.
+//!
+//! With `--targets`, e.g. `--targets 1=0.4,2=0.3,3=0.3`, the deviation of each explorer
+//! from its target share is reported, and the journal replayed under
+//! [`Policy::target_shares`].
+//!
+//! With `--compare`, the journal is compared request by request with another journal
+//! of the same scenario, and the requests decided differently are listed.
+//!
+//! ```text
+//! rustrelli-journal <journal> [--fair-share] [--stride] [--quota N/SECS]
+//!                             [--global-cap N/SECS] [--epoch-budget N]
+//!                             [--targets ID=SHARE,...] [--chrome-trace FILE]
+//!                             [--compare JOURNAL]
+//! ```
+
+use rustrelli::analyzer::{Analysis, compare, counterfactual};
+use rustrelli::error::RustrelliError;
+use rustrelli::journal::Journal;
+use rustrelli::policy::Policy;
+use rustrelli::trace::ChromeTrace;
+use rustrelli::{ExplorerRequestLimit, Quota};
+use std::fs::File;
+#[cfg(feature = "zstd")]
+use std::io::BufRead;
+use std::io::BufReader;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const USAGE: &str = "usage: rustrelli-journal <journal> [--fair-share] [--stride] \
+                     [--quota N/SECS] [--global-cap N/SECS] [--epoch-budget N] \
+                     [--targets ID=SHARE,...] [--chrome-trace FILE] [--compare JOURNAL]";
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut args = args.into_iter();
+    let path = args.next().ok_or(USAGE)?;
+    let mut limits = Vec::new();
+    let mut chrome_trace = None;
+    let mut targets = Vec::new();
+    let mut compared = None;
+    while let Some(flag) = args.next() {
+        let limit = match flag.as_str() {
+            "--chrome-trace" => {
+                chrome_trace = Some(args.next().ok_or(USAGE)?);
+                continue;
+            }
+            "--compare" => {
+                compared = Some(args.next().ok_or(USAGE)?);
+                continue;
+            }
+            "--targets" => {
+                targets = parse_targets(args.next())?;
+                continue;
+            }
+            "--fair-share" => ExplorerRequestLimit::FairShare,
+            "--stride" => ExplorerRequestLimit::Stride,
+            "--quota" => ExplorerRequestLimit::Quota(parse_quota(args.next())?),
+            "--global-cap" => ExplorerRequestLimit::GlobalCap(parse_quota(args.next())?),
+            "--epoch-budget" => ExplorerRequestLimit::EpochBudget(
+                args.next()
+                    .and_then(|budget| budget.parse().ok())
+                    .ok_or(USAGE)?,
+            ),
+            _ => return Err(USAGE.to_string()),
+        };
+        limits.push(limit);
+    }
+
+    let journal = open_journal(&path)?;
+    if let Some(trace) = chrome_trace {
+        let json = ChromeTrace::default().with_journal(&journal).to_json();
+        std::fs::write(&trace, json).map_err(|error| format!("{trace}: {error}"))?;
+    }
+    println!("{}", Analysis::new(&journal).with_targets(targets.clone()));
+    for limit in limits {
+        println!("\nUnder {limit:?}:");
+        println!("{}", counterfactual(&journal, limit));
+    }
+    if !targets.is_empty() {
+        println!("\nUnder the target shares:");
+        println!(
+            "{}",
+            counterfactual(&journal, Policy::target_shares(targets))
+        );
+    }
+    if let Some(other) = compared {
+        println!("\nCompared with {other}:");
+        println!("{}", compare(&journal, &open_journal(&other)?));
+    }
+    Ok(())
+}
+
+/// Reads the journal at `path`.
+fn open_journal(path: &str) -> Result<Journal, String> {
+    let file = File::open(path).map_err(|error| format!("{path}: {error}"))?;
+    read_journal(BufReader::new(file)).map_err(|error| error.to_string())
+}
+
+/// Reads a plain journal, or a compressed one if it starts with the zstd magic number.
+fn read_journal(mut reader: BufReader<File>) -> Result<Journal, RustrelliError> {
+    #[cfg(feature = "zstd")]
+    {
+        const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+        let start = reader
+            .fill_buf()
+            .map_err(|error| RustrelliError::Journal(error.to_string()))?;
+        if start.starts_with(&ZSTD_MAGIC) {
+            return Journal::read_zstd(reader);
+        }
+    }
+    Journal::read(&mut reader)
+}
+
+/// Parses target shares written `ID=SHARE,...`, e.g. `1=0.4,2=0.6`.
+fn parse_targets(arg: Option<String>) -> Result<Vec<(u32, f32)>, String> {
+    let targets = arg
+        .as_deref()
+        .ok_or(USAGE)?
+        .split(',')
+        .map(|target| {
+            let (explorer_id, share) = target.split_once('=')?;
+            Some((explorer_id.parse().ok()?, share.parse().ok()?))
+        })
+        .collect::<Option<Vec<(u32, f32)>>>()
+        .ok_or(USAGE)?;
+    let valid = targets.iter().all(|(_, share)| (0.0..=1.0).contains(share))
+        && targets.iter().map(|(_, share)| share).sum::<f32>() <= 1.0 + 1e-4;
+    if valid {
+        Ok(targets)
+    } else {
+        Err("target shares must be between 0 and 1, and add up to at most 1".to_string())
+    }
+}
+
+/// Parses a quota written `N/SECS`, e.g. `3/10` for 3 grants every 10 seconds.
+fn parse_quota(arg: Option<String>) -> Result<Quota, String> {
+    arg.as_deref()
+        .and_then(|arg| arg.split_once('/'))
+        .and_then(|(grants, secs)| {
+            Some(Quota::new(
+                grants.parse().ok()?,
+                Duration::from_secs(secs.parse().ok()?),
+            ))
+        })
+        .ok_or_else(|| USAGE.to_string())
+}
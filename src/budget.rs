@@ -0,0 +1,152 @@
+//! Latency budget module.
+//!
+//! Every feature enabled adds work to the handling of each explorer message: the shadow
+//! policy is evaluated, the metrics exported, the events reported and the invariants
+//! checked. None of it changes the response, yet all of it delays it. Hosts bounding the
+//! response latency of their planets set a budget per explorer message (see
+//! [`PlanetConfig::with_latency_budget`](crate::PlanetConfig::with_latency_budget)):
+//! the optional work, an [`Extra`], is skipped when it would take the handling of the
+//! message past the budget.
+//!
+//! The cost of each extra is estimated from its past runs. An extra only runs if the
+//! time elapsed since the message arrived, plus its own cost, plus the cost of the
+//! extras of higher priority, fits in the budget: the extras of lowest priority are the
+//! first ones skipped. Warnings and critical events are never skipped, and messages from
+//! the orchestrator aren't budgeted.
+//!
+//! Skipped extras are counted in the statistics (see
+//! [`Stats::budget_skips`](crate::stats::Stats::budget_skips)).
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// Optional work done while handling an explorer message, by increasing priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Extra {
+    /// Evaluating the shadow policy (see
+    /// [`PlanetConfig::with_shadow_limit`](crate::PlanetConfig::with_shadow_limit)).
+    Speculation,
+    /// Exporting metrics and publishing the statistics to their watch.
+    Metrics,
+    /// Reporting events below [`Severity::Warn`](crate::events::Severity::Warn).
+    Events,
+    /// Checking the invariants (see [`crate::invariants`]).
+    Audit,
+}
+
+impl Extra {
+    /// Every extra, by increasing priority.
+    const ALL: [Extra; 4] = [
+        Extra::Speculation,
+        Extra::Metrics,
+        Extra::Events,
+        Extra::Audit,
+    ];
+}
+
+/// Weight of the latest run in the estimated cost of an extra.
+const SMOOTHING: u32 = 8;
+
+/// Latency budget of the explorer messages, and the estimated cost of the extras.
+#[derive(Debug)]
+pub(crate) struct LatencyBudget {
+    limit: Duration,
+    /// When the explorer message being handled arrived, if any.
+    started: Cell<Option<Instant>>,
+    /// Estimated cost of each extra, zero until it first runs.
+    costs: [Cell<Duration>; 4],
+}
+
+impl LatencyBudget {
+    pub(crate) fn new(limit: Duration) -> Self {
+        LatencyBudget {
+            limit,
+            started: Cell::new(None),
+            costs: Default::default(),
+        }
+    }
+
+    /// Budgets the handling of an explorer message arrived at `now`.
+    pub(crate) fn start(&self, now: Instant) {
+        self.started.set(Some(now));
+    }
+
+    /// Ends the handling of the explorer message: nothing is budgeted until the next one.
+    pub(crate) fn finish(&self) {
+        self.started.set(None);
+    }
+
+    /// Whether `extra`, starting at `now`, fits in the budget of the message being
+    /// handled along with the extras of higher priority. Always true between messages.
+    pub(crate) fn allows(&self, extra: Extra, now: Instant) -> bool {
+        let Some(started) = self.started.get() else {
+            return true;
+        };
+        let reserved: Duration = Extra::ALL[extra as usize..]
+            .iter()
+            .map(|extra| self.costs[*extra as usize].get())
+            .sum();
+        now.saturating_duration_since(started) + reserved <= self.limit
+    }
+
+    /// Records a run of `extra` that took `took`, into its estimated cost.
+    pub(crate) fn record(&self, extra: Extra, took: Duration) {
+        let cost = &self.costs[extra as usize];
+        cost.set(if cost.get().is_zero() {
+            took
+        } else {
+            (cost.get() * (SMOOTHING - 1) + took) / SMOOTHING
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the latency budget.
+
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    // ============================================================================
+    // Tests: Budget
+    // ============================================================================
+
+    /// **Scenario:** 10ms budget, extras costing 2ms each, checked 4ms into a message,
+    /// then after it
+    /// **Validates:**
+    /// - The extras of higher priority fit, their cost and the elapsed time within budget
+    /// - The lowest priority extra is skipped, to leave room for the others
+    /// - Nothing is skipped between messages
+    #[test]
+    fn test_lowest_priority_skipped_first() {
+        let budget = LatencyBudget::new(10 * MS);
+        for extra in Extra::ALL {
+            budget.record(extra, 2 * MS);
+        }
+        let started = Instant::now();
+        budget.start(started);
+
+        let now = started + 4 * MS;
+        assert!(budget.allows(Extra::Audit, now));
+        assert!(budget.allows(Extra::Events, now));
+        assert!(budget.allows(Extra::Metrics, now));
+        assert!(!budget.allows(Extra::Speculation, now));
+        assert!(!budget.allows(Extra::Audit, started + 9 * MS));
+
+        budget.finish();
+        assert!(budget.allows(Extra::Speculation, now));
+    }
+
+    /// **Scenario:** An extra taking 8ms, then 16ms
+    /// **Validates:** The first run sets its cost, the next ones are averaged into it
+    #[test]
+    fn test_cost_estimate() {
+        let budget = LatencyBudget::new(10 * MS);
+        budget.record(Extra::Events, 8 * MS);
+        assert_eq!(budget.costs[Extra::Events as usize].get(), 8 * MS);
+        budget.record(Extra::Events, 16 * MS);
+        assert_eq!(budget.costs[Extra::Events as usize].get(), 9 * MS);
+    }
+}
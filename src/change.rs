@@ -0,0 +1,294 @@
+//! State changes module.
+//!
+//! The changes of the planet AI state are expressed as [`Change`]s, and applied in a
+//! single place, which updates the state of the AI and derives everything else from
+//! the change: the statistics, the journal, the cell timeline, the events and the
+//! telemetry. They are the sunrays, the decisions on generation and combination
+//! requests, the game epochs, the charges and discharges of the cells, the commands
+//! of the host (pause, quotas, tags, reservations, reachability, bans and disabled
+//! resources), the pending queue and the backoffs. Features observing them hook into
+//! the application of the changes instead of mirroring the mutations in the handlers.
+//!
+//! Replaying the changes onto the state the AI started from rebuilds the state they
+//! cover, see [`Replica`]: debug builds check it after each message (see
+//! [`Invariant::ChangeReplay`](crate::invariants::Invariant::ChangeReplay)). The
+//! bookkeeping of the limit policies, the leases, the offers and the batches are
+//! still kept by the handlers.
+//!
+//! Only the sunrays, the generation decisions and the epochs are written to the
+//! journal (see [`Change::journal_entry`]), as the [journal analysis](crate::analyzer)
+//! needs them to replay the requests under other policies.
+
+use crate::ExplorerId;
+use crate::Quota;
+use crate::admin::PauseMode;
+use crate::backoff::Backoffs;
+use crate::journal::JournalEntry;
+use crate::pending::PendingQueue;
+use crate::policy::DenialReason;
+use crate::receipt::ReceiptId;
+use crate::tags::{Tag, TagRegistry};
+use crate::timeline::CellChange;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::SystemTime;
+
+/// A change of the state of the planet AI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Change {
+    /// A sunray reached the planet.
+    Sunray { at: SystemTime },
+    /// A generation request was decided on: granted if `denial` is `None`, with a
+    /// receipt.
+    Generation {
+        at: SystemTime,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+        denial: Option<DenialReason>,
+        receipt: Option<ReceiptId>,
+    },
+    /// A combination request was decided on: granted if `denial` is `None`.
+    Combination {
+        at: SystemTime,
+        explorer_id: ExplorerId,
+        resource: ComplexResourceType,
+        denial: Option<DenialReason>,
+    },
+    /// The host started a new game epoch.
+    Epoch { at: SystemTime },
+    /// An energy cell was charged or discharged.
+    Cell {
+        at: SystemTime,
+        cell: usize,
+        change: CellChange,
+    },
+    /// The host banned an explorer, until `until` if set.
+    Banned {
+        explorer_id: ExplorerId,
+        until: Option<SystemTime>,
+    },
+    /// The ban of an explorer was lifted or expired.
+    Unbanned { explorer_id: ExplorerId },
+    /// The host disabled the generation of a resource, until `until` if set.
+    ResourceDisabled {
+        resource: BasicResourceType,
+        until: Option<SystemTime>,
+    },
+    /// A disabled resource was enabled again.
+    ResourceEnabled { resource: BasicResourceType },
+    /// The host paused the handling of generation requests.
+    Paused { mode: PauseMode },
+    /// The host resumed the handling of generation requests.
+    Resumed,
+    /// The host assigned an individual quota to an explorer.
+    QuotaSet {
+        explorer_id: ExplorerId,
+        quota: Quota,
+    },
+    /// The host attached a tag to an explorer.
+    Tagged { explorer_id: ExplorerId, tag: Tag },
+    /// The host detached a tag from an explorer.
+    Untagged { explorer_id: ExplorerId, tag: Tag },
+    /// The next charged cell was reserved for an explorer.
+    Reserved { explorer_id: ExplorerId },
+    /// The oldest reservation of an explorer was used or cancelled.
+    ReservationReleased { explorer_id: ExplorerId },
+    /// The host marked an explorer as reachable or not.
+    Reachability {
+        explorer_id: ExplorerId,
+        reachable: bool,
+    },
+    /// A generation request was offered to the pending queue.
+    Queued {
+        at: SystemTime,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+    },
+    /// The pending request at `index` was taken from the queue to be served.
+    Unqueued { index: usize },
+    /// The pending requests that waited longer than the timeout were dropped.
+    PendingExpired { at: SystemTime },
+    /// The pending requests were dropped, as the planet stops.
+    QueueDrained,
+    /// Deferred fulfillment was disabled, as the host dropped the receiver of the
+    /// fulfillments.
+    QueueClosed,
+    /// A request of an explorer allowed by its backoff was granted or denied.
+    BackoffRecorded {
+        at: SystemTime,
+        explorer_id: ExplorerId,
+        granted: bool,
+    },
+    /// A request of an explorer arrived before its advised retry-after time.
+    RetriedEarly {
+        at: SystemTime,
+        explorer_id: ExplorerId,
+    },
+    /// The advice that expired longer than the longest delay ago was forgotten.
+    BackoffsExpired { at: SystemTime },
+}
+
+impl Change {
+    /// The journal entry recording the change, if the journal analysis needs it.
+    pub(crate) fn journal_entry(&self) -> Option<JournalEntry> {
+        match *self {
+            Change::Sunray { at } => Some(JournalEntry::Sunray { at }),
+            Change::Generation {
+                at,
+                explorer_id,
+                resource,
+                denial,
+                receipt,
+            } => Some(JournalEntry::Generation {
+                at,
+                explorer_id,
+                resource,
+                denial,
+                receipt,
+            }),
+            Change::Epoch { at } => Some(JournalEntry::Epoch { at }),
+            _ => None,
+        }
+    }
+}
+
+/// The state of the planet AI rebuilt by replaying the changes: the pause, the
+/// individual quotas, the tags, the reservations, the unreachable explorers, the bans,
+/// the disabled resources, the pending queue and the backoffs.
+#[derive(Clone)]
+pub(crate) struct Replica {
+    pub(crate) paused: Option<PauseMode>,
+    pub(crate) quotas: HashMap<ExplorerId, Quota>,
+    pub(crate) tags: TagRegistry,
+    pub(crate) reservations: VecDeque<ExplorerId>,
+    pub(crate) unreachable: HashSet<ExplorerId>,
+    pub(crate) banned: HashMap<ExplorerId, Option<SystemTime>>,
+    pub(crate) disabled_resources: HashMap<BasicResourceType, Option<SystemTime>>,
+    pub(crate) pending: Option<PendingQueue>,
+    pub(crate) backoffs: Option<Backoffs>,
+}
+
+impl Replica {
+    /// Applies `change` to the replica.
+    pub(crate) fn apply(&mut self, change: &Change) {
+        match change {
+            Change::Banned { explorer_id, until } => {
+                self.banned.insert(*explorer_id, *until);
+            }
+            Change::Unbanned { explorer_id } => {
+                self.banned.remove(explorer_id);
+            }
+            Change::ResourceDisabled { resource, until } => {
+                self.disabled_resources.insert(*resource, *until);
+            }
+            Change::ResourceEnabled { resource } => {
+                self.disabled_resources.remove(resource);
+            }
+            Change::Paused { mode } => self.paused = Some(*mode),
+            Change::Resumed => self.paused = None,
+            Change::QuotaSet { explorer_id, quota } => {
+                self.quotas.insert(*explorer_id, *quota);
+            }
+            Change::Tagged { explorer_id, tag } => self.tags.tag(*explorer_id, tag.clone()),
+            Change::Untagged { explorer_id, tag } => self.tags.untag(*explorer_id, tag),
+            Change::Reserved { explorer_id } => self.reservations.push_back(*explorer_id),
+            Change::ReservationReleased { explorer_id } => {
+                if let Some(index) = self.reservations.iter().position(|id| id == explorer_id) {
+                    self.reservations.remove(index);
+                }
+            }
+            Change::Reachability {
+                explorer_id,
+                reachable: true,
+            } => {
+                self.unreachable.remove(explorer_id);
+            }
+            Change::Reachability {
+                explorer_id,
+                reachable: false,
+            } => {
+                self.unreachable.insert(*explorer_id);
+            }
+            Change::Queued {
+                at,
+                explorer_id,
+                resource,
+            } => {
+                if let Some(queue) = self.pending.as_mut() {
+                    queue.push(*explorer_id, *resource, *at);
+                }
+            }
+            Change::Unqueued { index } => {
+                if let Some(queue) = self.pending.as_mut() {
+                    queue.take(*index);
+                }
+            }
+            Change::PendingExpired { at } => {
+                if let Some(queue) = self.pending.as_mut() {
+                    queue.expire(*at);
+                }
+            }
+            Change::QueueDrained => {
+                if let Some(queue) = self.pending.as_mut() {
+                    queue.clear();
+                }
+            }
+            Change::QueueClosed => self.pending = None,
+            Change::BackoffRecorded {
+                at,
+                explorer_id,
+                granted,
+            } => {
+                if let Some(backoffs) = self.backoffs.as_mut() {
+                    backoffs.record(*explorer_id, *at, *granted);
+                }
+            }
+            Change::RetriedEarly { at, explorer_id } => {
+                if let Some(backoffs) = self.backoffs.as_mut() {
+                    backoffs.escalate(*explorer_id, *at);
+                }
+            }
+            Change::BackoffsExpired { at } => {
+                if let Some(backoffs) = self.backoffs.as_mut() {
+                    backoffs.tick(*at);
+                }
+            }
+            Change::Sunray { .. }
+            | Change::Generation { .. }
+            | Change::Combination { .. }
+            | Change::Epoch { .. }
+            | Change::Cell { .. } => {}
+        }
+    }
+
+    /// Checks that the replica matches `live`, the state of the AI.
+    pub(crate) fn check(&self, live: &Replica) -> Result<(), String> {
+        let parts = [
+            ("pause", self.paused == live.paused),
+            ("quotas", self.quotas == live.quotas),
+            ("tags", self.tags == live.tags),
+            ("reservations", self.reservations == live.reservations),
+            ("reachability", self.unreachable == live.unreachable),
+            ("bans", self.banned == live.banned),
+            (
+                "disabled resources",
+                self.disabled_resources == live.disabled_resources,
+            ),
+            ("pending queue", self.pending == live.pending),
+            ("backoffs", self.backoffs == live.backoffs),
+        ];
+        let differing: Vec<&str> = parts
+            .iter()
+            .filter(|(_, same)| !same)
+            .map(|(part, _)| *part)
+            .collect();
+        if differing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "replayed changes differ on: {}",
+                differing.join(", ")
+            ))
+        }
+    }
+}
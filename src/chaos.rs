@@ -0,0 +1,214 @@
+//! Chaos testing module, available with the `chaos` feature.
+//!
+//! Makes the planet misbehave on purpose, so that hosts can check how their
+//! orchestrator copes with a slow, lossy or confused planet while still running this
+//! crate. Set with [`PlanetConfig::with_chaos`](crate::PlanetConfig::with_chaos), a
+//! [`ChaosConfig`] can:
+//! - Delay the handling of messages, from the orchestrator and the explorers alike
+//! - Drop the responses to explorer messages, as if they were lost
+//! - Skew the clock of the planet AI, which drives the policy windows, batches and
+//!   statistics
+//!
+//! Every injection is drawn from a pseudo-random generator seeded by the configuration,
+//! so a failing run is replayed with the same seed (as long as the planet receives the
+//! same messages).
+//!
+//! # Examples
+//! ```
+//! use rustrelli::PlanetConfig;
+//! use rustrelli::chaos::ChaosConfig;
+//! use std::time::Duration;
+//!
+//! let config = PlanetConfig::new(1).with_chaos(
+//!     ChaosConfig::new(42)
+//!         .with_delays(0.1, Duration::from_millis(20))
+//!         .with_dropped_responses(0.05)
+//!         .with_clock_skew(Duration::from_secs(2)),
+//! );
+//! ```
+
+use crate::rng::SplitMix64;
+use std::cell::RefCell;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Failures injected by the planet, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosConfig {
+    seed: u64,
+    delay_probability: f64,
+    max_delay: Duration,
+    drop_probability: f64,
+    max_clock_skew: Duration,
+}
+
+impl ChaosConfig {
+    /// Creates a configuration injecting nothing yet, drawing from `seed`.
+    pub fn new(seed: u64) -> Self {
+        ChaosConfig {
+            seed,
+            delay_probability: 0.0,
+            max_delay: Duration::ZERO,
+            drop_probability: 0.0,
+            max_clock_skew: Duration::ZERO,
+        }
+    }
+
+    /// Delays the handling of a message with the given `probability`, by up to
+    /// `max_delay`.
+    ///
+    /// # Panics
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn with_delays(mut self, probability: f64, max_delay: Duration) -> Self {
+        assert_probability(probability);
+        self.delay_probability = probability;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Drops the response to an explorer message with the given `probability`. The
+    /// message is still handled: a dropped generation response loses the resource.
+    ///
+    /// # Panics
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn with_dropped_responses(mut self, probability: f64) -> Self {
+        assert_probability(probability);
+        self.drop_probability = probability;
+        self
+    }
+
+    /// Shifts every clock reading of the planet AI by up to `max_skew`, ahead or
+    /// behind. Readings aren't monotonic anymore.
+    pub fn with_clock_skew(mut self, max_skew: Duration) -> Self {
+        self.max_clock_skew = max_skew;
+        self
+    }
+}
+
+fn assert_probability(probability: f64) {
+    assert!(
+        (0.0..=1.0).contains(&probability),
+        "Probability must be between 0 and 1"
+    );
+}
+
+/// Injects the failures of a [`ChaosConfig`].
+pub(crate) struct Chaos {
+    config: ChaosConfig,
+    /// Advanced through shared references, so that clock readings can be skewed
+    /// anywhere.
+    rng: RefCell<SplitMix64>,
+}
+
+impl Chaos {
+    pub(crate) fn new(config: ChaosConfig) -> Self {
+        Chaos {
+            rng: RefCell::new(SplitMix64::new(config.seed)),
+            config,
+        }
+    }
+
+    fn next_u64(&self) -> u64 {
+        self.rng.borrow_mut().next_u64()
+    }
+
+    /// Uniform draw in `[0, 1)`.
+    fn next_f64(&self) -> f64 {
+        self.rng.borrow_mut().next_f64()
+    }
+
+    /// Whether an event of the given `probability` happens. Draws nothing for
+    /// certain outcomes, so unused injections don't shift the others.
+    fn happens(&self, probability: f64) -> bool {
+        match probability {
+            0.0 => false,
+            1.0 => true,
+            _ => self.next_f64() < probability,
+        }
+    }
+
+    /// Uniform draw in `[0, max]`.
+    fn up_to(&self, max: Duration) -> Duration {
+        max.mul_f64(self.next_f64())
+    }
+
+    /// Sleeps before handling a message, if a delay is drawn.
+    pub(crate) fn delay(&self) {
+        if self.config.max_delay > Duration::ZERO && self.happens(self.config.delay_probability) {
+            thread::sleep(self.up_to(self.config.max_delay));
+        }
+    }
+
+    /// Whether to drop the response to an explorer message.
+    pub(crate) fn drops_response(&self) -> bool {
+        self.happens(self.config.drop_probability)
+    }
+
+    /// `now`, skewed.
+    pub(crate) fn skew(&self, now: SystemTime) -> SystemTime {
+        if self.config.max_clock_skew == Duration::ZERO {
+            return now;
+        }
+        let skew = self.up_to(self.config.max_clock_skew);
+        let skewed = match self.next_u64() & 1 {
+            0 => now.checked_add(skew),
+            _ => now.checked_sub(skew),
+        };
+        skewed.unwrap_or(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the failure injection.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Draws
+    // ============================================================================
+
+    /// **Scenario:** Two injectors with the same seed, one with another seed
+    /// **Validates:**
+    /// - The same seed replays the same decisions
+    /// - Another seed makes other decisions
+    #[test]
+    fn test_seed_replays_decisions() {
+        let config = |seed| ChaosConfig::new(seed).with_dropped_responses(0.5);
+        let decisions = |chaos: Chaos| (0..64).map(|_| chaos.drops_response()).collect::<Vec<_>>();
+
+        let first = decisions(Chaos::new(config(7)));
+        assert_eq!(first, decisions(Chaos::new(config(7))));
+        assert_ne!(first, decisions(Chaos::new(config(8))));
+        let dropped = first.iter().filter(|dropped| **dropped).count();
+        assert!((16..=48).contains(&dropped), "{dropped} responses dropped");
+    }
+
+    /// **Scenario:** Skew a fixed clock reading many times
+    /// **Validates:**
+    /// - Readings stay within the maximum skew, on both sides
+    /// - Without skew, readings are untouched
+    #[test]
+    fn test_clock_skew_within_bounds() {
+        let max = Duration::from_secs(5);
+        let chaos = Chaos::new(ChaosConfig::new(1).with_clock_skew(max));
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        let (mut ahead, mut behind) = (false, false);
+        for _ in 0..100 {
+            let skewed = chaos.skew(now);
+            match skewed.duration_since(now) {
+                Ok(skew) => {
+                    assert!(skew <= max);
+                    ahead |= skew > Duration::ZERO;
+                }
+                Err(error) => {
+                    assert!(error.duration() <= max);
+                    behind = true;
+                }
+            }
+        }
+        assert!(ahead && behind);
+        assert_eq!(Chaos::new(ChaosConfig::new(1)).skew(now), now);
+    }
+}
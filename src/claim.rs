@@ -0,0 +1,61 @@
+//! Two-phase grants module.
+//!
+//! An explorer may disconnect between its request and the delivery of the resource:
+//! the cell discharged for it is then wasted, which hurts the most when the cell was
+//! the last one available. When two-phase grants are enabled (see
+//! [`PlanetConfig::with_claims`](crate::PlanetConfig::with_claims)), a grant that
+//! would spend the last cell not reserved by other explorers is only offered: the
+//! explorer is answered with no resource and [`DenialReason::Offered`], and the cell
+//! is reserved for it. Its next request for the same resource within
+//! [`ClaimConfig::window`] confirms the claim: the cell is discharged without the
+//! request being evaluated again.
+//!
+//! An offer not confirmed in time, or withdrawn by a request of the explorer for
+//! another resource, releases its cell, which stays accounted by the limit policy. Uncontended grants, batches and pending requests are served right
+//! away, as without two-phase grants. Critical explorers may take an offered cell
+//! before it's confirmed (see
+//! [`PlanetConfig::with_preemption`](crate::PlanetConfig::with_preemption)).
+//!
+//! [`DenialReason::Offered`]: crate::policy::DenialReason::Offered
+
+use common_game::components::resource::BasicResourceType;
+use std::time::{Duration, SystemTime};
+
+/// Configuration of the two-phase grants.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::claim::ClaimConfig;
+///
+/// // Offers confirmed within 2 seconds
+/// let claims = ClaimConfig::new(Duration::from_secs(2));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClaimConfig {
+    /// Time the explorer has to confirm an offer.
+    pub window: Duration,
+}
+
+impl ClaimConfig {
+    /// Creates a claim configuration of offers confirmed within `window`.
+    pub fn new(window: Duration) -> Self {
+        ClaimConfig { window }
+    }
+}
+
+/// A cell offered to an explorer, backed by a reservation until confirmed or expired.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Offer {
+    /// The resource granted.
+    pub(crate) resource: BasicResourceType,
+    /// Time the cell is released at.
+    pub(crate) expires: SystemTime,
+}
+
+impl Offer {
+    /// Whether a request for `resource` at `now` confirms the offer.
+    pub(crate) fn confirmed_by(&self, resource: BasicResourceType, now: SystemTime) -> bool {
+        self.resource == resource && now < self.expires
+    }
+}
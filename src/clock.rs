@@ -0,0 +1,274 @@
+//! Clock module.
+//!
+//! The planet AI reads the current time from a [`Clock`], set with
+//! [`PlanetConfig::with_clock`](crate::PlanetConfig::with_clock). It drives the policy
+//! windows and schedules, the batches and the statistics buckets. The default
+//! [`SystemClock`] reads the system time, while tests and simulations drive a
+//! [`ManualClock`] to step through time windows without sleeping:
+//! ```
+//! use rustrelli::clock::{Clock, ManualClock};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+//! let planet_clock = clock.clone();
+//! clock.advance(Duration::from_secs(10));
+//! assert_eq!(planet_clock.now(), SystemTime::UNIX_EPOCH + Duration::from_secs(10));
+//! ```
+//!
+//! ## Anomalies
+//!
+//! Policies score explorers on the time elapsed between their requests: a clock
+//! failing, or going back in time as the system time does when it's corrected, would
+//! quietly mis-score them. The planet AI never lets its time go back, and reports each
+//! [`ClockAnomaly`] as an [`Event::Clock`](crate::events::Event::Clock) warning:
+//! - while the clock reads a time before the latest one, the planet time stands still,
+//!   freezing the decay of the scores and the windows, until the clock catches up
+//! - while the clock fails to read the time (see [`Clock::try_now`]), the planet counts
+//!   time with the monotonic ticks of the process, from the latest time read
+//!
+//! Its recovery is reported as well, once the clock reads a time past the planet time.
+
+use crate::RustrelliError;
+use std::cell::Cell;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of the current time of the planet AI.
+pub trait Clock: Send {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+
+    /// The current time, or why it couldn't be read. The planet AI reads the time with
+    /// it: clocks that can fail, like the ones synchronized with a game server,
+    /// override it to report their failures.
+    ///
+    /// # Errors
+    /// Returns [`RustrelliError::Clock`] if the time couldn't be read.
+    fn try_now(&self) -> Result<SystemTime, RustrelliError> {
+        Ok(self.now())
+    }
+}
+
+/// Clock reading the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Time of a [`ManualClock`], and the failure it reports if any.
+#[derive(Debug)]
+struct ManualTime {
+    now: SystemTime,
+    failure: Option<String>,
+}
+
+/// Clock only moving when told to. Clones share the same time, so a host keeps a
+/// clone to drive the clock given to the planet.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    time: Arc<Mutex<ManualTime>>,
+}
+
+impl ManualClock {
+    /// Creates a clock stopped at `start`.
+    pub fn new(start: SystemTime) -> Self {
+        ManualClock {
+            time: Arc::new(Mutex::new(ManualTime {
+                now: start,
+                failure: None,
+            })),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.lock().now += duration;
+    }
+
+    /// Sets the clock to `now`, possibly back in time.
+    pub fn set(&self, now: SystemTime) {
+        self.lock().now = now;
+    }
+
+    /// Makes [`try_now`](Clock::try_now) fail with `error` until [`repair`](Self::repair)
+    /// is called, to simulate a broken clock. [`now`](Clock::now) keeps reading the time.
+    pub fn fail(&self, error: impl Into<String>) {
+        self.lock().failure = Some(error.into());
+    }
+
+    /// Ends the failure started by [`fail`](Self::fail).
+    pub fn repair(&self) {
+        self.lock().failure = None;
+    }
+
+    /// Locks the time, even if a thread panicked while holding it.
+    fn lock(&self) -> MutexGuard<'_, ManualTime> {
+        self.time
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for ManualClock {
+    /// A clock stopped at the current system time.
+    fn default() -> Self {
+        Self::new(SystemTime::now())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.lock().now
+    }
+
+    fn try_now(&self) -> Result<SystemTime, RustrelliError> {
+        let time = self.lock();
+        match &time.failure {
+            Some(error) => Err(RustrelliError::Clock(error.clone())),
+            None => Ok(time.now),
+        }
+    }
+}
+
+/// Anomaly of the clock of the planet, see the [module documentation](self#anomalies).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ClockAnomaly {
+    /// The clock failed to read the time: the planet counts time with monotonic ticks.
+    Failed {
+        /// The error of the clock.
+        error: String,
+    },
+    /// The clock went back in time: the planet time stands still until it catches up.
+    WentBack {
+        /// How far behind the planet time the clock went.
+        by: Duration,
+    },
+    /// The clock reads a time past the planet time again, after an anomaly.
+    Recovered,
+}
+
+/// Anomaly a [`GuardedClock`] is going through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Degraded {
+    Failing,
+    Behind,
+}
+
+/// Clock of the planet AI, keeping its time from going back and from stopping when the
+/// clock fails.
+pub(crate) struct GuardedClock {
+    clock: Box<dyn Clock>,
+    /// Latest time read, and the instant it was read at.
+    latest: Cell<Option<(SystemTime, Instant)>>,
+    degraded: Cell<Option<Degraded>>,
+}
+
+impl GuardedClock {
+    pub(crate) fn new(clock: Box<dyn Clock>) -> Self {
+        GuardedClock {
+            clock,
+            latest: Cell::new(None),
+            degraded: Cell::new(None),
+        }
+    }
+
+    /// Reads the current time, along with the anomaly that started or ended with this
+    /// reading, if any.
+    pub(crate) fn now(&self) -> (SystemTime, Option<ClockAnomaly>) {
+        let instant = Instant::now();
+        let latest = self.latest.get();
+        let (now, degraded, anomaly) = match (self.clock.try_now(), latest) {
+            (Ok(now), Some((latest, _))) if now < latest => {
+                let by = latest.duration_since(now).unwrap_or_default();
+                (
+                    latest,
+                    Some(Degraded::Behind),
+                    ClockAnomaly::WentBack { by },
+                )
+            }
+            (Ok(now), _) => (now, None, ClockAnomaly::Recovered),
+            (Err(error), latest) => {
+                // Without any time read yet, the ticks start from the system time
+                let now = latest.map_or_else(SystemTime::now, |(latest, at)| {
+                    latest + instant.duration_since(at)
+                });
+                let error = error.to_string();
+                (now, Some(Degraded::Failing), ClockAnomaly::Failed { error })
+            }
+        };
+        self.latest.set(Some((now, instant)));
+        let changed = self.degraded.replace(degraded) != degraded;
+        (now, changed.then_some(anomaly))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the guarded clock.
+
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    // ============================================================================
+    // Tests: Anomalies
+    // ============================================================================
+
+    /// **Scenario:** Clock read at 100s, set back to 90s twice, then to 105s
+    /// **Validates:**
+    /// - The time stands still while the clock is behind, reported once
+    /// - The recovery is reported once the clock catches up
+    #[test]
+    fn test_clock_going_back_freezes_time() {
+        let clock = ManualClock::new(at(100));
+        let guarded = GuardedClock::new(Box::new(clock.clone()));
+        assert_eq!(guarded.now(), (at(100), None));
+
+        clock.set(at(90));
+        let went_back = ClockAnomaly::WentBack {
+            by: Duration::from_secs(10),
+        };
+        assert_eq!(guarded.now(), (at(100), Some(went_back)));
+        assert_eq!(guarded.now(), (at(100), None));
+
+        clock.set(at(105));
+        assert_eq!(guarded.now(), (at(105), Some(ClockAnomaly::Recovered)));
+    }
+
+    /// **Scenario:** Clock read at 100s, then failing for 20ms before being repaired
+    /// **Validates:**
+    /// - The failure is reported once, with the error of the clock
+    /// - The time keeps moving with the ticks of the process while the clock fails
+    #[test]
+    fn test_failing_clock_falls_back_to_ticks() {
+        let clock = ManualClock::new(at(100));
+        let guarded = GuardedClock::new(Box::new(clock.clone()));
+        guarded.now();
+
+        clock.fail("Time server unreachable");
+        let (now, anomaly) = guarded.now();
+        assert!(now >= at(100));
+        assert_eq!(
+            anomaly,
+            Some(ClockAnomaly::Failed {
+                error: "clock failed: Time server unreachable".to_string()
+            })
+        );
+        std::thread::sleep(Duration::from_millis(20));
+        let (later, anomaly) = guarded.now();
+        assert!(later >= now + Duration::from_millis(20));
+        assert_eq!(anomaly, None);
+
+        clock.repair();
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(guarded.now(), (at(101), Some(ClockAnomaly::Recovered)));
+    }
+}
@@ -0,0 +1,46 @@
+//! Compliance module.
+//!
+//! Some features of the crate give a planet more than the game rules strictly grant,
+//! like cells charged without sunrays. Tournaments may forbid them, and referees can't
+//! audit the configuration of every planet. In compliance mode (see
+//! [`PlanetConfig::with_compliance`](crate::PlanetConfig::with_compliance)), the
+//! features flagged as [`RuleBending`] are hard-disabled: the planet runs without them
+//! even if they're configured, and records an [`Attestation`] of it in its
+//! [`PlanetInfo`](crate::info::PlanetInfo), also reported with the fairness report of
+//! the `http-stats` feature.
+//!
+//! Referees check the attestation of each planet at a glance:
+//! ```
+//! use rustrelli::PlanetConfig;
+//! use rustrelli::stats::StatsHandle;
+//! # use crossbeam_channel::unbounded;
+//!
+//! let stats = StatsHandle::default();
+//! let config = PlanetConfig::new(1).with_stats(stats.clone()).with_compliance();
+//! # let (_, rx_orch) = unbounded();
+//! # let (tx_planet, _) = unbounded();
+//! # let (_, rx_expl) = unbounded();
+//! # let _planet = rustrelli::create_planet_with_config(config, rx_orch, tx_planet, rx_expl);
+//! let info = stats.snapshot().info().cloned().expect("Info recorded");
+//! assert!(info.compliance.is_some(), "Rules-clean configuration");
+//! ```
+
+/// A feature flagged as potentially bending the game rules, disabled in compliance
+/// mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum RuleBending {
+    /// Cells charged at startup without sunrays, and the grants served from them (see
+    /// [`PlanetConfig::with_stockpile`](crate::PlanetConfig::with_stockpile)).
+    Stockpile,
+}
+
+/// Attestation that a planet ran in compliance mode, without any [`RuleBending`]
+/// feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Attestation {
+    /// The flagged features that were configured, and disabled by compliance mode.
+    pub disabled: Vec<RuleBending>,
+}
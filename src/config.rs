@@ -0,0 +1,987 @@
+//! Planet configuration module.
+//!
+//! [`PlanetConfig`] gathers everything that can be customized when constructing a
+//! planet through [`create_planet_with_config`](crate::create_planet_with_config).
+//! Every option has a sensible default, so only the planet ID is required.
+
+use crate::ExplorerRequestLimit;
+use crate::RustrelliError;
+use crate::admin::AdminCommand;
+use crate::backoff::BackoffConfig;
+use crate::batch::BatchConfig;
+#[cfg(feature = "chaos")]
+use crate::chaos::ChaosConfig;
+use crate::claim::ClaimConfig;
+use crate::clock::{Clock, SystemClock};
+use crate::compliance::{Attestation, RuleBending};
+use crate::cost::{CostModel, FixedCosts, MessageKind};
+use crate::delivery::DeliveryConfig;
+use crate::events::{Event, EventFilter, EventSink};
+use crate::fallback::{FallbackHandler, NoResponse};
+use crate::gateway::ExplorerGateway;
+use crate::lease::LeaseConfig;
+use crate::misconduct::MisconductConfig;
+use crate::pending::{Fulfillment, Overflow, PendingQueue};
+use crate::policy::{Policy, PolicyArm};
+use crate::priority::OrchestratorPriority;
+use crate::refusal::{CodedRefusals, RefusalFormatter};
+use crate::registration::{Reregistration, Untracked};
+use crate::stats::{StatsHandle, StatsWatch};
+use crate::stockpile::StockpileVisibility;
+use crate::storage::StorageBackend;
+use crate::tags::{Tag, TagRegistry};
+use common_game::components::resource::BasicResourceType;
+use common_game::utils::ID;
+use crossbeam_channel::{Receiver, Sender};
+use std::collections::HashSet;
+use std::io::Write;
+use std::time::Duration;
+
+/// Configuration of a Type D planet.
+///
+/// # Examples
+/// ```
+/// use rustrelli::{ExplorerRequestLimit, PlanetConfig};
+/// use rustrelli::stats::{StatsConfig, StatsHandle};
+///
+/// let stats = StatsHandle::new(StatsConfig::default());
+/// let config = PlanetConfig::new(1)
+///     .with_request_limit(ExplorerRequestLimit::FairShare)
+///     .with_stats(stats.clone());
+/// ```
+pub struct PlanetConfig {
+    pub(crate) id: ID,
+    pub(crate) request_limit: Policy,
+    pub(crate) shadow_limit: Option<Policy>,
+    pub(crate) arms: Vec<PolicyArm>,
+    pub(crate) stats: StatsHandle,
+    pub(crate) stats_watch: Option<(StatsWatch, Duration)>,
+    pub(crate) housekeeping: Option<Duration>,
+    pub(crate) warm_up: Option<Duration>,
+    pub(crate) policy_warm_up: Option<Duration>,
+    pub(crate) stockpile: Vec<(BasicResourceType, u32)>,
+    pub(crate) stockpile_visibility: StockpileVisibility,
+    pub(crate) admin: Vec<Receiver<AdminCommand>>,
+    pub(crate) tags: TagRegistry,
+    pub(crate) pending: Option<PendingQueue>,
+    pub(crate) fulfillments: Option<Sender<Fulfillment>>,
+    pub(crate) delivery: DeliveryConfig,
+    pub(crate) coalesce_pending: bool,
+    pub(crate) pending_explorer_cap: Option<usize>,
+    pub(crate) pending_overflow: Overflow,
+    pub(crate) pending_timeout: Option<Duration>,
+    pub(crate) tie_break_seed: Option<u64>,
+    pub(crate) batch: Option<BatchConfig>,
+    pub(crate) claims: Option<ClaimConfig>,
+    pub(crate) preemption: Option<Tag>,
+    pub(crate) lease: Option<LeaseConfig>,
+    pub(crate) backoff: Option<BackoffConfig>,
+    pub(crate) misconduct: Option<MisconductConfig>,
+    pub(crate) events: EventSink,
+    pub(crate) drain_timeout: Option<Duration>,
+    pub(crate) refusals: Box<dyn RefusalFormatter>,
+    pub(crate) fallback: Box<dyn FallbackHandler>,
+    pub(crate) costs: FixedCosts,
+    pub(crate) cost_model: Option<Box<dyn CostModel>>,
+    pub(crate) energy_poll_interval: Option<Duration>,
+    pub(crate) load_window: Option<Duration>,
+    pub(crate) leaderboard_size: Option<usize>,
+    pub(crate) streak_alert: Option<u32>,
+    pub(crate) cell_timeline: Option<usize>,
+    pub(crate) demand_heatmap: Option<(Duration, usize)>,
+    pub(crate) journal: Option<Box<dyn Write + Send>>,
+    pub(crate) storage: Option<Box<dyn StorageBackend>>,
+    pub(crate) compliance: bool,
+    pub(crate) drop_spoofed: bool,
+    pub(crate) gateway: Option<ExplorerGateway>,
+    pub(crate) max_explorers: Option<(usize, Untracked)>,
+    pub(crate) latency_budget: Option<Duration>,
+    pub(crate) onboarding: bool,
+    pub(crate) receipt_events: bool,
+    pub(crate) reregistration: Reregistration,
+    pub(crate) query_workers: Option<usize>,
+    pub(crate) orchestrator_priority: OrchestratorPriority,
+    pub(crate) fair_interleaving: bool,
+    pub(crate) clock: Box<dyn Clock>,
+    #[cfg(feature = "chaos")]
+    pub(crate) chaos: Option<ChaosConfig>,
+}
+
+impl PlanetConfig {
+    /// Creates the default configuration for the planet with the given ID:
+    /// - No limit to explorer requests
+    /// - No shadow limit
+    /// - No policy arms
+    /// - Statistics aggregated with [`StatsConfig::default`](crate::stats::StatsConfig::default)
+    /// - No statistics published to a watch
+    /// - No periodic housekeeping
+    /// - No warm-up: the limit policy is enforced from the start, and policies replaced
+    ///   at runtime right away
+    /// - No seeded stockpile: only the sunrays charge the cells
+    /// - No admin channel
+    /// - No explorer tags
+    /// - No deferred fulfillment, no coalescing of pending requests
+    /// - No cap on the pending requests of each explorer, the newest request dropped
+    ///   when the pending queue is full, pending requests kept until served
+    /// - Ties between pending requests drawn from the planet ID as seed
+    /// - Fulfillments delivered with [`DeliveryConfig::default`]
+    /// - No batch grants
+    /// - Grants served right away, without two-phase grants
+    /// - No preemption of offered or batched cells
+    /// - No leases
+    /// - No retry-after advice after denials
+    /// - No misconduct scoring
+    /// - No events channel, every event sent over it if set, one in 10 while it's
+    ///   saturated
+    /// - Abrupt stop, without draining
+    /// - Combination refusals rendered by [`CodedRefusals`]
+    /// - Unhandled explorer messages left unanswered ([`NoResponse`])
+    /// - Messages priced by [`FixedCosts::default`]: generation requests cost 1, other
+    ///   messages are free
+    /// - Every energy poll answered
+    /// - No load events
+    /// - No leaderboard events
+    /// - No denial streak events
+    /// - No cell timeline
+    /// - No demand heatmap
+    /// - No journal
+    /// - No storage: nothing persisted
+    /// - No compliance mode: every configured feature enabled
+    /// - Requests claiming an unregistered explorer ID counted, but handled
+    /// - Every explorer tracked, without cap
+    /// - No latency budget: all the optional work done on every message
+    /// - Explorers registered again keep their statistics and allowances
+    ///   ([`Reregistration::Merge`])
+    /// - No onboarding events
+    /// - No receipt events
+    /// - Every explorer message handled by the planet loop
+    /// - [`OrchestratorPriority::Strict`] ordering of orchestrator and explorer messages,
+    ///   as `common_game` does
+    /// - Explorer messages handled in arrival order
+    /// - Time read from the [`SystemClock`]
+    /// - No chaos, with the `chaos` feature
+    pub fn new(id: ID) -> Self {
+        PlanetConfig {
+            id,
+            request_limit: ExplorerRequestLimit::None.into(),
+            shadow_limit: None,
+            arms: Vec::new(),
+            stats: StatsHandle::default(),
+            stats_watch: None,
+            housekeeping: None,
+            warm_up: None,
+            policy_warm_up: None,
+            stockpile: Vec::new(),
+            stockpile_visibility: StockpileVisibility::Visible,
+            admin: Vec::new(),
+            tags: TagRegistry::default(),
+            pending: None,
+            fulfillments: None,
+            delivery: DeliveryConfig::default(),
+            coalesce_pending: false,
+            pending_explorer_cap: None,
+            pending_overflow: Overflow::RejectNewest,
+            pending_timeout: None,
+            tie_break_seed: None,
+            batch: None,
+            claims: None,
+            preemption: None,
+            lease: None,
+            backoff: None,
+            misconduct: None,
+            events: EventSink::default(),
+            drain_timeout: None,
+            refusals: Box::new(CodedRefusals),
+            fallback: Box::new(NoResponse),
+            costs: FixedCosts::default(),
+            cost_model: None,
+            load_window: None,
+            leaderboard_size: None,
+            streak_alert: None,
+            energy_poll_interval: None,
+            cell_timeline: None,
+            demand_heatmap: None,
+            journal: None,
+            storage: None,
+            compliance: false,
+            drop_spoofed: false,
+            gateway: None,
+            max_explorers: None,
+            latency_budget: None,
+            onboarding: false,
+            receipt_events: false,
+            reregistration: Reregistration::default(),
+            query_workers: None,
+            orchestrator_priority: OrchestratorPriority::Strict,
+            fair_interleaving: false,
+            clock: Box::new(SystemClock),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// Sets the policy used to limit resource generation requests done by explorers.
+    ///
+    /// Either a single [`ExplorerRequestLimit`] mode or a [`Policy`] composition.
+    pub fn with_request_limit(mut self, request_limit: impl Into<Policy>) -> Self {
+        self.request_limit = request_limit.into();
+        self
+    }
+
+    /// Sets a secondary "shadow" limit policy, evaluated on every generation request
+    /// but never enforced.
+    ///
+    /// Its hypothetical decisions are recorded in the statistics (see
+    /// [`Stats::shadow`](crate::stats::Stats::shadow)), so a new fairness algorithm
+    /// can be trialed against live traffic before switching it on.
+    pub fn with_shadow_limit(mut self, shadow_limit: impl Into<Policy>) -> Self {
+        self.shadow_limit = Some(shadow_limit.into());
+        self
+    }
+
+    /// Adds an arm limiting its explorers with its own policy, for A/B experiments.
+    ///
+    /// Arms must have distinct names, different from [`PolicyArm::DEFAULT`],
+    /// and disjoint sets of explorers.
+    pub fn with_policy_arm(mut self, arm: PolicyArm) -> Self {
+        self.arms.push(arm);
+        self
+    }
+
+    /// Sets the handle the planet records its statistics into.
+    ///
+    /// Keep a clone of the handle to query the statistics while the planet is running.
+    pub fn with_stats(mut self, stats: StatsHandle) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Publishes a copy of the statistics to `watch` at most once every `interval` of
+    /// planet time, right before handling a message, and when the planet AI stops.
+    ///
+    /// Planets spawned with [`spawn_planet`](crate::spawn_planet) hand a clone of the
+    /// watch out with [`PlanetHandle::stats_watch`](crate::PlanetHandle::stats_watch).
+    pub fn with_stats_watch(mut self, watch: StatsWatch, interval: Duration) -> Self {
+        self.stats_watch = Some((watch, interval));
+        self
+    }
+
+    /// Performs the housekeeping of the AI at most once every `interval` of planet time,
+    /// right before handling a message: the policies forget the explorers and grants
+    /// out of their windows, expired batches release their cells and the distribution
+    /// of the usage scores is refreshed. Otherwise it's only done while handling the
+    /// requests it relates to.
+    ///
+    /// The planet loop is owned by `common_game`, so an idle planet can't do its
+    /// housekeeping: hosts can send [`AdminCommand::Tick`] to have it done at the next
+    /// message.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn with_housekeeping(mut self, interval: Duration) -> Self {
+        assert!(
+            !interval.is_zero(),
+            "Housekeeping interval must be greater than zero"
+        );
+        self.housekeeping = Some(interval);
+        self
+    }
+
+    /// Starts the planet AI with a warm-up period of `duration`: the limit policy only
+    /// observes the requests, which are all granted as long as a cell is charged, then
+    /// it's enforced against the demand observed meanwhile.
+    ///
+    /// Without warm-up, the first explorers to arrive define the average usage the
+    /// others are judged against. The grants of the warm-up are accounted like any
+    /// other, so an explorer that took many resources meanwhile may be limited right
+    /// after.
+    ///
+    /// # Panics
+    /// Panics if `duration` is zero.
+    pub fn with_warm_up(mut self, duration: Duration) -> Self {
+        assert!(
+            !duration.is_zero(),
+            "Warm-up duration must be greater than zero"
+        );
+        self.warm_up = Some(duration);
+        self
+    }
+
+    /// Warms up the planet-wide policies replaced at runtime (see
+    /// [`AI::set_policy`](crate::planet::AI::set_policy)) for `duration` before
+    /// enforcing them: the incoming policy is seeded with the grants of the last
+    /// `duration`, then observes the requests decided by the outgoing policy until it
+    /// takes over.
+    ///
+    /// Explorers in the middle of a burst when the policy is replaced are then judged
+    /// on their recent usage, rather than on whatever history the outgoing policy could
+    /// carry over.
+    ///
+    /// # Panics
+    /// Panics if `duration` is zero.
+    pub fn with_policy_warm_up(mut self, duration: Duration) -> Self {
+        assert!(
+            !duration.is_zero(),
+            "Policy warm-up duration must be greater than zero"
+        );
+        self.policy_warm_up = Some(duration);
+        self
+    }
+
+    /// Starts the planet pre-stocked with `composition`: each unit of a resource is a
+    /// cell charged before the first message is handled, and the first grants of each
+    /// resource are served from its stock.
+    ///
+    /// Only sunrays charge cells in the game, so seeding is a house rule: meant for
+    /// scenarios allowing it, like tutorial levels. Seeded cells aren't counted as
+    /// sunrays, and what's seeded and served is kept apart in the statistics (see
+    /// [`Stats::stockpile`](crate::stats::Stats::stockpile)). Compliance mode disables it
+    /// (see [`with_compliance`](Self::with_compliance)).
+    ///
+    /// The composition is validated when the planet is created: every amount must be
+    /// greater than zero, every resource listed once and part of the generation rules,
+    /// and the stockpile must fit in the cells of the planet.
+    ///
+    /// # Examples
+    /// ```
+    /// use common_game::components::resource::BasicResourceType;
+    /// use rustrelli::PlanetConfig;
+    ///
+    /// let config = PlanetConfig::new(1)
+    ///     .with_stockpile([(BasicResourceType::Oxygen, 2), (BasicResourceType::Carbon, 1)]);
+    /// ```
+    pub fn with_stockpile(
+        mut self,
+        composition: impl IntoIterator<Item = (BasicResourceType, u32)>,
+    ) -> Self {
+        self.stockpile = composition.into_iter().collect();
+        self
+    }
+
+    /// Sets which answers about the available energy count the stockpile not served
+    /// yet, every answer by default. See the [`stockpile`](crate::stockpile) module.
+    pub fn with_stockpile_visibility(mut self, visibility: StockpileVisibility) -> Self {
+        self.stockpile_visibility = visibility;
+        self
+    }
+
+    /// Adds a channel the planet receives [`AdminCommand`]s from.
+    pub fn with_admin(mut self, admin: Receiver<AdminCommand>) -> Self {
+        self.admin.push(admin);
+        self
+    }
+
+    /// Attaches `tag` to `explorer_id`. Tags can also be changed while the planet
+    /// is running, through the admin channel.
+    pub fn with_tag(mut self, explorer_id: u32, tag: Tag) -> Self {
+        self.tags.tag(explorer_id.into(), tag);
+        self
+    }
+
+    /// Attaches `pool` to each of `members`, so that they share an allowance of the
+    /// [`Policy::pooled`](crate::policy::Policy::pooled) policy keyed on `pool.key`.
+    pub fn with_pool(mut self, pool: Tag, members: impl IntoIterator<Item = u32>) -> Self {
+        for explorer_id in members {
+            self.tags.tag(explorer_id.into(), pool.clone());
+        }
+        self
+    }
+
+    /// Sets the weight of the explorers carrying `tag`: an explorer with weight 2 is
+    /// entitled to twice the fair share of an explorer with weight 1.
+    ///
+    /// # Panics
+    /// Panics if `weight` isn't a positive finite number.
+    pub fn with_tag_weight(mut self, tag: Tag, weight: f32) -> Self {
+        self.tags.set_weight(tag, weight);
+        self
+    }
+
+    /// Enables deferred fulfillment: requests arriving while no cell is charged are
+    /// kept in a pending queue of at most `capacity` requests, and served as soon as a
+    /// cell is charged. Resources produced for pending requests are sent to
+    /// `fulfillments`, for the host to deliver them.
+    ///
+    /// See the [`pending`](crate::pending) module.
+    pub fn with_deferred_fulfillment(
+        mut self,
+        capacity: usize,
+        fulfillments: Sender<Fulfillment>,
+    ) -> Self {
+        self.pending = Some(PendingQueue::new(capacity));
+        self.fulfillments = Some(fulfillments);
+        self
+    }
+
+    /// Enables or disables the coalescing of identical pending requests of the same
+    /// explorer. Has no effect without deferred fulfillment.
+    ///
+    /// See [`pending`](crate::pending#coalescing).
+    pub fn with_pending_coalescing(mut self, enabled: bool) -> Self {
+        self.coalesce_pending = enabled;
+        self
+    }
+
+    /// Limits the pending requests of each explorer to `cap`, so that a single explorer
+    /// can't take the whole pending queue. Has no effect without deferred fulfillment.
+    ///
+    /// See [`pending`](crate::pending#limits).
+    ///
+    /// # Panics
+    /// Panics if `cap` is zero.
+    pub fn with_pending_explorer_cap(mut self, cap: usize) -> Self {
+        assert!(cap > 0, "Pending cap must be greater than zero");
+        self.pending_explorer_cap = Some(cap);
+        self
+    }
+
+    /// Sets what happens to a request offered to a full pending queue, or beyond the
+    /// cap of its explorer. Has no effect without deferred fulfillment.
+    ///
+    /// See [`pending`](crate::pending#limits).
+    pub fn with_pending_overflow(mut self, overflow: Overflow) -> Self {
+        self.pending_overflow = overflow;
+        self
+    }
+
+    /// Drops the pending requests waiting for longer than `timeout`, so that explorers
+    /// that gave up don't get a resource long after. Has no effect without deferred
+    /// fulfillment.
+    ///
+    /// See [`pending`](crate::pending#timeout).
+    ///
+    /// # Panics
+    /// Panics if `timeout` is zero.
+    pub fn with_pending_timeout(mut self, timeout: Duration) -> Self {
+        assert!(
+            !timeout.is_zero(),
+            "Pending timeout must be greater than zero"
+        );
+        self.pending_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the seed of the draws breaking the ties between the explorers with pending
+    /// requests, the planet ID by default. Planets with the same seed serve the same
+    /// contended requests in the same order.
+    ///
+    /// See [`pending`](crate::pending#ordering).
+    pub fn with_tie_break_seed(mut self, seed: u64) -> Self {
+        self.tie_break_seed = Some(seed);
+        self
+    }
+
+    /// Sets how fulfillments are retried when the host doesn't keep up with them,
+    /// and how many undeliverable ones are kept. Has no effect without deferred
+    /// fulfillment.
+    ///
+    /// See the [`delivery`](crate::delivery) module.
+    pub fn with_delivery(mut self, delivery: DeliveryConfig) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    /// Enables batch grants: the first request of a series is decided for up to
+    /// `batch.max_size` charged cells at once, all or none.
+    ///
+    /// See the [`batch`](crate::batch) module.
+    pub fn with_batch_grants(mut self, batch: BatchConfig) -> Self {
+        self.batch = Some(batch);
+        self
+    }
+
+    /// Enables two-phase grants: a grant spending the last available cell is only
+    /// offered, and the cell discharged when the next request of the explorer within
+    /// `claims.window` confirms it.
+    ///
+    /// See the [`claim`](crate::claim) module.
+    pub fn with_claims(mut self, claims: ClaimConfig) -> Self {
+        self.claims = Some(claims);
+        self
+    }
+
+    /// Lets the explorers carrying `critical` preempt the cells decided for other
+    /// explorers but not discharged yet, e.g. for a rescue explorer.
+    ///
+    /// A request of a critical explorer finding every charged cell reserved is
+    /// evaluated anyway, for a single cell. If granted, the cell is taken from an
+    /// unconfirmed offer of a two-phase grant (see [`Self::with_claims`]) if any, or else
+    /// from the open batch with the most cells left (see [`Self::with_batch_grants`]),
+    /// of an explorer not carrying the tag. Leased cells and cells reserved by the host
+    /// aren't preempted. Preemptions are counted in the statistics (see
+    /// [`Stats::preemptions`](crate::stats::Stats::preemptions)).
+    pub fn with_preemption(mut self, critical: Tag) -> Self {
+        self.preemption = Some(critical);
+        self
+    }
+
+    /// Enables leases: the host can lease up to `lease.max_cells` charged cells to an
+    /// explorer for up to `lease.max_duration`.
+    ///
+    /// See the [`lease`](crate::lease) module.
+    pub fn with_leases(mut self, lease: LeaseConfig) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
+    /// Advises a retry-after time to the explorers denied a generation request, and
+    /// denies their retries before it, escalating the next advised delay along the
+    /// schedule of `backoff`.
+    ///
+    /// See the [`backoff`](crate::backoff) module.
+    pub fn with_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = Some(backoff);
+        self
+    }
+
+    /// Scores the misbehavior of each explorer (spoof attempts, early retries, bursts,
+    /// undeliverable requests) with the weights of `misconduct`.
+    ///
+    /// See the [`misconduct`](crate::misconduct) module.
+    pub fn with_misconduct(mut self, misconduct: MisconductConfig) -> Self {
+        self.misconduct = Some(misconduct);
+        self
+    }
+
+    /// Sets the channel the planet sends diagnostic [`Event`]s to.
+    ///
+    /// The planet never blocks on it: use a channel large enough for the host to keep up.
+    pub fn with_events(mut self, events: Sender<Event>) -> Self {
+        self.events.channel = Some(events);
+        self
+    }
+
+    /// Sets the filter of the events sent over the events channel. The host keeps a
+    /// clone of `filter` to mute the least severe events while the planet runs.
+    pub fn with_event_filter(mut self, filter: EventFilter) -> Self {
+        self.events.filter = filter;
+        self
+    }
+
+    /// Sends one event in `every` over the events channel once it's found full, until
+    /// the host drains half of it. Warnings and critical events are always sent if
+    /// there's room for them. The events dropped meanwhile are counted by the
+    /// [`EventFilter`] of the planet (see [`EventFilter::dropped`]).
+    ///
+    /// # Panics
+    /// Panics if `every` is zero.
+    pub fn with_event_sampling(mut self, every: u32) -> Self {
+        assert!(every > 0, "Sampling rate must be greater than zero");
+        self.events.sample_every = u64::from(every);
+        self
+    }
+
+    /// Enables graceful shutdown: when the planet AI is stopped, the pending admin
+    /// commands are applied and the fulfillments waiting for a retry are sent, waiting
+    /// at most `timeout` for the host to make room for them. Then an
+    /// [`Event::Stopped`] with the final report is emitted.
+    ///
+    /// Explorer messages still queued in the planet channel are owned by the
+    /// `common_game` run loop, which exits right after the AI is stopped.
+    pub fn with_shutdown_drain(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how the reasons of refused combinations are rendered for the explorers,
+    /// e.g. to localize them.
+    ///
+    /// See the [`refusal`](crate::refusal) module.
+    pub fn with_refusal_formatter(mut self, formatter: impl RefusalFormatter + 'static) -> Self {
+        self.refusals = Box::new(formatter);
+        self
+    }
+
+    /// Sets how the explorer messages the planet AI doesn't handle, added to the
+    /// protocol after this version, are answered. They're counted and reported
+    /// whatever the handler.
+    ///
+    /// See the [`fallback`](crate::fallback) module.
+    pub fn with_fallback_handler(mut self, handler: impl FallbackHandler + 'static) -> Self {
+        self.fallback = Box::new(handler);
+        self
+    }
+
+    /// Sets the cost charged to the limit policy of an explorer for each
+    /// `SupportedResourceRequest` and `SupportedCombinationRequest` it sends, in units of
+    /// the cost of a generation request.
+    ///
+    /// The answers never change for a planet, so explorers polling them instead of
+    /// caching them see their share of energy shrink. Only limits keeping a usage
+    /// score, like [`FairShare`](crate::ExplorerRequestLimit::FairShare), are affected.
+    ///
+    /// # Panics
+    /// Panics if `cost` isn't a non-negative finite number.
+    pub fn with_capability_poll_cost(mut self, cost: f32) -> Self {
+        assert!(
+            cost.is_finite() && cost >= 0.0,
+            "Capability poll cost must be a non-negative finite number"
+        );
+        self.costs.capability_poll = cost;
+        self
+    }
+
+    /// Sets the cost charged to the limit policy of an explorer for each
+    /// `AvailableEnergyCellRequest` it sends, in units of the cost of a generation
+    /// request.
+    ///
+    /// Explorers hammering the planet with energy polls then see their share of energy
+    /// shrink like explorers spamming generation requests do. As with
+    /// [`Self::with_capability_poll_cost`], only limits keeping a usage score are
+    /// affected.
+    ///
+    /// # Panics
+    /// Panics if `cost` isn't a non-negative finite number.
+    pub fn with_energy_poll_cost(mut self, cost: f32) -> Self {
+        assert!(
+            cost.is_finite() && cost >= 0.0,
+            "Energy poll cost must be a non-negative finite number"
+        );
+        self.costs.energy_poll = cost;
+        self
+    }
+
+    /// Sets how the explorer messages are priced for the limit policies, replacing the
+    /// costs set with [`Self::with_capability_poll_cost`] and
+    /// [`Self::with_energy_poll_cost`].
+    ///
+    /// See the [`cost`](crate::cost) module.
+    pub fn with_cost_model(mut self, model: impl CostModel + 'static) -> Self {
+        self.cost_model = Some(Box::new(model));
+        self
+    }
+
+    /// Answers at most one `AvailableEnergyCellRequest` of each explorer every
+    /// `interval`: the polls arriving sooner after an answer are coalesced into it, left
+    /// unanswered and uncharged, so that bots polling in a tight loop don't keep the
+    /// planet busy. They're counted in the statistics (see
+    /// [`Stats::throttled_polls`](crate::stats::Stats::throttled_polls)).
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn with_energy_poll_interval(mut self, interval: Duration) -> Self {
+        assert!(
+            !interval.is_zero(),
+            "Energy poll interval must be greater than zero"
+        );
+        self.energy_poll_interval = Some(interval);
+        self
+    }
+
+    /// Cost of a message of `kind`, as priced by the configured model.
+    pub(crate) fn cost(&self, kind: MessageKind) -> f32 {
+        match &self.cost_model {
+            Some(model) => model.cost(kind),
+            None => self.costs.cost(kind),
+        }
+    }
+
+    /// Enables the [`Event::Load`] events: after each sunray, the planet reports the
+    /// generation requests per charged cell over the last `window`, so monitoring
+    /// explorers can steer clear of an oversubscribed planet.
+    ///
+    /// Has no effect without an events channel or a logging backend (see
+    /// [`events`](crate::events)). The load can also be read from the statistics at
+    /// any time (see [`Stats::load`](crate::stats::Stats::load)).
+    pub fn with_load_events(mut self, window: Duration) -> Self {
+        self.load_window = Some(window);
+        self
+    }
+
+    /// Enables the [`Event::DenialStreak`] events: the planet reports each explorer
+    /// denied `threshold` requests in a row, e.g. for the host to check on a starving
+    /// explorer. Each streak is reported once.
+    ///
+    /// Has no effect without an events channel or a logging backend (see
+    /// [`events`](crate::events)). The streaks can also be read from the statistics at
+    /// any time (see [`Stats::streaks`](crate::stats::Stats::streaks)).
+    ///
+    /// # Panics
+    /// Panics if `threshold` is zero.
+    pub fn with_streak_alert(mut self, threshold: u32) -> Self {
+        assert!(threshold > 0, "Streak threshold must be greater than zero");
+        self.streak_alert = Some(threshold);
+        self
+    }
+
+    /// Enables the [`Event::Leaderboard`] events: after each sunray, the planet reports
+    /// the `size` explorers leading each ranking, for exhibition UIs to display beside
+    /// the planet.
+    ///
+    /// Has no effect without an events channel or a logging backend (see
+    /// [`events`](crate::events)). The leaderboard can also be read from the statistics
+    /// at any time (see [`Stats::leaderboard`](crate::stats::Stats::leaderboard)).
+    ///
+    /// # Panics
+    /// Panics if `size` is zero.
+    pub fn with_leaderboard_events(mut self, size: usize) -> Self {
+        assert!(size > 0, "Leaderboard size must be greater than zero");
+        self.leaderboard_size = Some(size);
+        self
+    }
+
+    /// Records the `capacity` most recent charges and discharges of the energy cells in
+    /// the statistics (see [`Stats::cell_timeline`](crate::stats::Stats::cell_timeline)).
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn with_cell_timeline(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "Timeline capacity must be greater than zero");
+        self.cell_timeline = Some(capacity);
+        self
+    }
+
+    /// Records the generation requests of each resource type in `buckets` buckets of
+    /// game time, each `bucket_width` long, since the planet AI started (see
+    /// [`Stats::demand_heatmap`](crate::stats::Stats::demand_heatmap)). The last bucket
+    /// also counts the requests after it.
+    ///
+    /// # Panics
+    /// Panics if `bucket_width` or `buckets` is zero.
+    pub fn with_demand_heatmap(mut self, bucket_width: Duration, buckets: usize) -> Self {
+        assert!(
+            !bucket_width.is_zero() && buckets > 0,
+            "Heatmap bucket width and buckets must be greater than zero"
+        );
+        self.demand_heatmap = Some((bucket_width, buckets));
+        self
+    }
+
+    /// Writes the sunrays, generation decisions and epoch changes of the planet to
+    /// `journal`, for offline analysis (see [`journal`](crate::journal)).
+    ///
+    /// Writes aren't buffered: wrap files in a [`BufWriter`](std::io::BufWriter). If a
+    /// write fails, the planet reports an [`Event::JournalFailed`] and stops journaling.
+    /// With the `zstd` feature, wrap them in a `journal::ZstdJournal` to compress them.
+    pub fn with_journal(mut self, journal: impl Write + Send + 'static) -> Self {
+        self.journal = Some(Box::new(journal));
+        self
+    }
+
+    /// Persists the journal and the final snapshots of the planet to `storage` (see
+    /// [`storage`](crate::storage)).
+    ///
+    /// The journal is appended to the [`JOURNAL`](crate::storage::JOURNAL) stream of the
+    /// storage when the planet starts, unless one was set with
+    /// [`with_journal`](Self::with_journal). The cell timeline, if recorded, and the
+    /// statistics, with the `serde` feature, are stored when the planet stops or is
+    /// destroyed by an asteroid. A planet killed while running stores nothing more.
+    ///
+    /// # Examples
+    /// ```
+    /// use rustrelli::PlanetConfig;
+    /// use rustrelli::storage::FileStorage;
+    ///
+    /// let config = PlanetConfig::new(1).with_storage(FileStorage::new("planets/1"));
+    /// ```
+    pub fn with_storage(mut self, storage: impl StorageBackend + 'static) -> Self {
+        self.storage = Some(Box::new(storage));
+        self
+    }
+
+    /// Runs the planet in compliance mode: the features flagged as
+    /// [`RuleBending`] are disabled, whether configured before or after, and an
+    /// attestation is recorded in the planet info (see
+    /// [`compliance`](crate::compliance)).
+    pub fn with_compliance(mut self) -> Self {
+        self.compliance = true;
+        self
+    }
+
+    /// Disables the rule-bending features in compliance mode.
+    ///
+    /// # Returns
+    /// The attestation of the planet, `None` outside compliance mode.
+    pub(crate) fn comply(&mut self) -> Option<Attestation> {
+        if !self.compliance {
+            return None;
+        }
+        let mut disabled = Vec::new();
+        if !self.stockpile.is_empty() {
+            self.stockpile.clear();
+            disabled.push(RuleBending::Stockpile);
+        }
+        Some(Attestation { disabled })
+    }
+
+    /// Drops the spoof attempts caught by the [explorer gateway](Self::with_explorer_gateway):
+    /// generation and combination requests claiming another ID than the one of their
+    /// channel, without answering or charging them to any limit policy. Requests
+    /// claiming the ID of an explorer whose channel the orchestrator didn't register on
+    /// the planet are dropped either way.
+    ///
+    /// Spoof attempts are counted in the statistics either way (see
+    /// [`Stats::spoof_attempts`](crate::stats::Stats::spoof_attempts)). Has no effect
+    /// without a gateway, as the planet can't tell the sender of a request on the
+    /// shared explorer channel.
+    ///
+    /// Requests claiming an explorer never registered, dropped by the gateway with or
+    /// without protection, may have raced its registration: once it's registered,
+    /// they're counted as its [early requests](crate::stats::Stats::early_requests)
+    /// instead, and their cost is charged to its limit policy.
+    pub fn with_spoof_protection(mut self) -> Self {
+        self.drop_spoofed = true;
+        self
+    }
+
+    /// Receives the explorer messages through `gateway`, which gives each explorer a
+    /// channel of its own to catch the requests claiming the ID of another explorer.
+    /// See the [`gateway`](crate::gateway) module.
+    pub fn with_explorer_gateway(mut self, gateway: ExplorerGateway) -> Self {
+        self.gateway = Some(gateway);
+        self
+    }
+
+    /// Tracks at most `max` explorers, the first ones to send a message, handling the
+    /// messages of the others as set by `untracked`.
+    ///
+    /// See the [`registration`](crate::registration#tracked-explorers) module.
+    ///
+    /// # Panics
+    /// Panics if `max` is zero.
+    pub fn with_max_explorers(mut self, max: usize, untracked: Untracked) -> Self {
+        assert!(max > 0, "Tracked explorers must be more than zero");
+        self.max_explorers = Some((max, untracked));
+        self
+    }
+
+    /// Skips the optional work (shadow policy, metrics, events, invariant checks) that
+    /// would take the handling of an explorer message past `budget`, lowest priority
+    /// first.
+    ///
+    /// See the [`budget`](crate::budget) module.
+    ///
+    /// # Panics
+    /// Panics if `budget` is zero.
+    pub fn with_latency_budget(mut self, budget: Duration) -> Self {
+        assert!(
+            !budget.is_zero(),
+            "Latency budget must be greater than zero"
+        );
+        self.latency_budget = Some(budget);
+        self
+    }
+
+    /// Sets what happens to the statistics and allowances of an explorer registered
+    /// again, after leaving the planet or while still registered.
+    ///
+    /// See the [`registration`](crate::registration) module.
+    pub fn with_reregistration(mut self, reregistration: Reregistration) -> Self {
+        self.reregistration = reregistration;
+        self
+    }
+
+    /// Enables the [`Event::Onboarding`] events: each time an explorer registers, the
+    /// planet reports the rules of engagement for it, so that hosts can tell bots the
+    /// limits they'll run into up front.
+    ///
+    /// Has no effect without an events channel or a logging backend (see
+    /// [`events`](crate::events)). See the [`registration`](crate::registration) module.
+    pub fn with_onboarding_events(mut self) -> Self {
+        self.onboarding = true;
+        self
+    }
+
+    /// Enables the [`Event::Granted`] events: each grant is reported with its receipt,
+    /// so that hosts can keep a delivery record to check against the dead letters.
+    ///
+    /// Has no effect without an events channel or a logging backend (see
+    /// [`events`](crate::events)). See the [`receipt`](crate::receipt) module.
+    pub fn with_receipt_events(mut self) -> Self {
+        self.receipt_events = true;
+        self
+    }
+
+    /// Answers the read-only queries of the explorers with a pool of `workers` threads,
+    /// while generation and combination requests stay on the planet loop.
+    ///
+    /// Only the queries of the explorers watched through
+    /// [`AdminCommand::WatchExplorer`] are answered by the pool, as the planet doesn't
+    /// share the explorer channels otherwise. See the [`workers`](crate::workers) module.
+    ///
+    /// # Panics
+    /// Panics if `workers` is zero.
+    pub fn with_query_workers(mut self, workers: usize) -> Self {
+        assert!(workers > 0, "The query pool needs at least one worker");
+        self.query_workers = Some(workers);
+        self
+    }
+
+    /// Sets how the planet orders the orchestrator messages (sunrays, asteroids, state
+    /// requests...) against the explorer ones, so that a busy orchestrator can't starve
+    /// the explorers. See the [`priority`](crate::priority) module.
+    ///
+    /// # Panics
+    /// Panics with [`OrchestratorPriority::Weighted`] of zero.
+    pub fn with_orchestrator_priority(mut self, priority: OrchestratorPriority) -> Self {
+        assert!(
+            priority != OrchestratorPriority::Weighted(0),
+            "Weighted priority needs a positive weight"
+        );
+        self.orchestrator_priority = priority;
+        self
+    }
+
+    /// Interleaves the messages of the explorers: each explorer with waiting messages
+    /// takes its turn, instead of the planet handling them in arrival order. An explorer
+    /// flooding the channel then can't crowd out the others before the request limit
+    /// even applies. See the [`priority`](crate::priority) module.
+    pub fn with_fair_interleaving(mut self) -> Self {
+        self.fair_interleaving = true;
+        self
+    }
+
+    /// Sets the clock the planet AI reads the current time from, e.g. a
+    /// [`ManualClock`](crate::clock::ManualClock) to step through policy windows in
+    /// tests and simulations.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Makes the planet inject the failures of `chaos`, to test how the host copes with
+    /// a misbehaving planet. See the [`chaos`](crate::chaos) module.
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: ChaosConfig) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Checks the configuration, as done when the planet is created.
+    ///
+    /// # Errors
+    /// Returns a [`RustrelliError::Config`] describing the problem if two policy arms
+    /// have the same name, an arm is named [`PolicyArm::DEFAULT`], an explorer is
+    /// assigned to more than one arm, or the stockpile lists a resource twice or with
+    /// an amount of zero.
+    pub fn validate(&self) -> Result<(), RustrelliError> {
+        let mut names = HashSet::new();
+        let mut assigned = HashSet::new();
+
+        for arm in &self.arms {
+            if arm.name == PolicyArm::DEFAULT || !names.insert(&arm.name) {
+                return Err(RustrelliError::Config(format!(
+                    "Invalid or duplicate policy arm name: {}",
+                    arm.name
+                )));
+            }
+            if let Some(explorer_id) = arm.explorers.iter().find(|id| !assigned.insert(**id)) {
+                return Err(RustrelliError::Config(format!(
+                    "Explorer {} assigned to more than one policy arm",
+                    explorer_id
+                )));
+            }
+        }
+
+        let mut stocked = HashSet::new();
+        for (resource, amount) in &self.stockpile {
+            if *amount == 0 || !stocked.insert(*resource) {
+                return Err(RustrelliError::Config(format!(
+                    "Empty or duplicate stockpile of {:?}",
+                    resource
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+//! Request cost module.
+//!
+//! Limits keeping a usage score, like
+//! [`FairShare`](crate::ExplorerRequestLimit::FairShare), charge each explorer message
+//! its cost, in units of the cost of a standard generation request. The planet AI reads
+//! the costs from a [`CostModel`], set with
+//! [`PlanetConfig::with_cost_model`](crate::PlanetConfig::with_cost_model).
+//!
+//! The default model, [`FixedCosts`], charges 1 per generation request and nothing for
+//! the other messages, unless set otherwise:
+//! ```
+//! use common_game::components::resource::BasicResourceType;
+//! use rustrelli::cost::{CostModel, FixedCosts, MessageKind};
+//!
+//! let costs = FixedCosts::default()
+//!     .with_resource(BasicResourceType::Silicon, 2.0)
+//!     .with_resource(BasicResourceType::Oxygen, 0.5)
+//!     .with_polls(0.1);
+//! assert_eq!(costs.cost(MessageKind::Generation(BasicResourceType::Silicon)), 2.0);
+//! assert_eq!(costs.cost(MessageKind::Generation(BasicResourceType::Carbon)), 1.0);
+//! assert_eq!(costs.cost(MessageKind::AvailableEnergy), 0.1);
+//! ```
+//!
+//! Hosts encode any other pricing with a `Fn(MessageKind) -> f32` closure.
+
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use std::collections::HashMap;
+
+/// Kind of explorer message, as priced by a [`CostModel`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    /// `SupportedResourceRequest`.
+    SupportedResources,
+    /// `SupportedCombinationRequest`.
+    SupportedCombinations,
+    /// `GenerateResourceRequest` for the given resource.
+    Generation(BasicResourceType),
+    /// `CombineResourceRequest` for the given resource.
+    Combination(ComplexResourceType),
+    /// `AvailableEnergyCellRequest`.
+    AvailableEnergy,
+}
+
+/// Prices the explorer messages charged to the limit policies.
+pub trait CostModel: Send {
+    /// Cost of a message of `kind`, in units of the cost of a standard generation
+    /// request. Must be a non-negative finite number.
+    ///
+    /// The cost of a message kind must not change over time: the query workers rely
+    /// on it to know which queries are free.
+    fn cost(&self, kind: MessageKind) -> f32;
+}
+
+impl<F: Fn(MessageKind) -> f32 + Send> CostModel for F {
+    fn cost(&self, kind: MessageKind) -> f32 {
+        self(kind)
+    }
+}
+
+/// Default [`CostModel`]: a fixed cost per message kind, generation requests priced
+/// per resource.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedCosts {
+    /// Cost of a generation request for a resource without its own price.
+    pub generation: f32,
+    /// Cost of a generation request, by resource.
+    pub resources: HashMap<BasicResourceType, f32>,
+    /// Cost of a combination request.
+    pub combination: f32,
+    /// Cost of a `SupportedResourceRequest` or a `SupportedCombinationRequest`.
+    pub capability_poll: f32,
+    /// Cost of an `AvailableEnergyCellRequest`.
+    pub energy_poll: f32,
+}
+
+impl Default for FixedCosts {
+    /// Generation and combination requests cost 1, as they spend a cell, every other
+    /// message is free.
+    fn default() -> Self {
+        FixedCosts {
+            generation: 1.0,
+            resources: HashMap::new(),
+            combination: 1.0,
+            capability_poll: 0.0,
+            energy_poll: 0.0,
+        }
+    }
+}
+
+impl FixedCosts {
+    /// Prices the generation requests for `resource` at `cost`.
+    ///
+    /// # Panics
+    /// Panics if `cost` isn't a non-negative finite number.
+    pub fn with_resource(mut self, resource: BasicResourceType, cost: f32) -> Self {
+        assert_cost(cost);
+        self.resources.insert(resource, cost);
+        self
+    }
+
+    /// Prices the capability and available energy queries at `cost`.
+    ///
+    /// # Panics
+    /// Panics if `cost` isn't a non-negative finite number.
+    pub fn with_polls(mut self, cost: f32) -> Self {
+        assert_cost(cost);
+        self.capability_poll = cost;
+        self.energy_poll = cost;
+        self
+    }
+}
+
+impl CostModel for FixedCosts {
+    fn cost(&self, kind: MessageKind) -> f32 {
+        match kind {
+            MessageKind::SupportedResources | MessageKind::SupportedCombinations => {
+                self.capability_poll
+            }
+            MessageKind::Generation(resource) => self
+                .resources
+                .get(&resource)
+                .copied()
+                .unwrap_or(self.generation),
+            MessageKind::Combination(_) => self.combination,
+            MessageKind::AvailableEnergy => self.energy_poll,
+        }
+    }
+}
+
+/// Asserts that `cost` is a non-negative finite number.
+fn assert_cost(cost: f32) {
+    assert!(
+        cost.is_finite() && cost >= 0.0,
+        "Cost must be a non-negative finite number"
+    );
+}
@@ -0,0 +1,261 @@
+//! Outbound delivery module.
+//!
+//! Resources produced for pending requests (see [`crate::pending`]) are sent to the
+//! host as [`Fulfillment`]s. A fulfillment that can't be sent right away because the
+//! channel is full is kept and retried before the AI handles each of the following
+//! messages, up to [`DeliveryConfig::max_attempts`] attempts.
+//!
+//! Fulfillments that can't be delivered at all (the host dropped the receiver, or the
+//! attempts ran out) are recorded in a bounded dead-letter buffer, so the host can
+//! tell which explorers missed a resource. Its content is listed by
+//! [`Stats::dead_letters`](crate::stats::Stats::dead_letters).
+//!
+//! Dead letters keep their resource: once the host can deliver it again (e.g. the
+//! orchestrator registered a new channel for the explorer), it re-drives them with
+//! [`AdminCommand::RedriveDeadLetters`](crate::admin::AdminCommand::RedriveDeadLetters).
+//! Re-driven fulfillments are sent again with a fresh number of attempts.
+
+use crate::ExplorerId;
+use crate::pending::Fulfillment;
+use crate::receipt::ReceiptId;
+use crate::stats::StatsHandle;
+use common_game::components::resource::BasicResourceType;
+use crossbeam_channel::{SendTimeoutError, Sender, TrySendError};
+use std::collections::VecDeque;
+use std::time::{Instant, SystemTime};
+
+/// Configuration of the fulfillments delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeliveryConfig {
+    /// Number of attempts to send a fulfillment before moving it to the dead letters.
+    pub max_attempts: u32,
+    /// Maximum number of dead letters kept. The oldest ones are discarded.
+    pub dead_letter_capacity: usize,
+}
+
+impl Default for DeliveryConfig {
+    /// Three attempts, 32 dead letters.
+    fn default() -> Self {
+        DeliveryConfig {
+            max_attempts: 3,
+            dead_letter_capacity: 32,
+        }
+    }
+}
+
+/// Why a fulfillment ended up in the dead letters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DeadLetterCause {
+    /// The host dropped the receiver of the fulfillments.
+    Disconnected,
+    /// The channel stayed full for all the attempts.
+    AttemptsExhausted,
+    /// The channel stayed full until the drain timeout of a graceful shutdown.
+    DrainTimedOut,
+}
+
+/// Description of a fulfillment in the dead-letter buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeadLetterInfo {
+    /// Identifier of the dead letter, unique for the planet.
+    pub id: u64,
+    /// The explorer the resource was produced for.
+    pub explorer_id: ExplorerId,
+    /// Type of the produced resource.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource"))]
+    pub resource: BasicResourceType,
+    /// Receipt of the grant the fulfillment was produced for (see [`crate::receipt`]).
+    pub receipt: ReceiptId,
+    /// Number of attempts made to send the fulfillment.
+    pub attempts: u32,
+    /// Why the fulfillment couldn't be delivered.
+    pub cause: DeadLetterCause,
+    /// Time the fulfillment was moved to the dead letters.
+    pub failed_at: SystemTime,
+}
+
+/// A fulfillment that couldn't be delivered, together with its description.
+struct DeadLetter {
+    info: DeadLetterInfo,
+    fulfillment: Fulfillment,
+}
+
+/// Sends the fulfillments to the host, retrying and dead-lettering them.
+pub(crate) struct Outbox {
+    sender: Sender<Fulfillment>,
+    config: DeliveryConfig,
+    /// Fulfillments waiting for another attempt, oldest first, with the attempts made.
+    retrying: VecDeque<(Fulfillment, u32)>,
+    dead_letters: VecDeque<DeadLetter>,
+    next_dead_letter_id: u64,
+    /// Whether the host dropped the receiver.
+    closed: bool,
+    stats: StatsHandle,
+}
+
+impl Outbox {
+    pub(crate) fn new(
+        sender: Sender<Fulfillment>,
+        config: DeliveryConfig,
+        stats: StatsHandle,
+    ) -> Self {
+        Outbox {
+            sender,
+            config,
+            retrying: VecDeque::new(),
+            dead_letters: VecDeque::new(),
+            next_dead_letter_id: 0,
+            closed: false,
+            stats,
+        }
+    }
+
+    /// Returns `true` if the host dropped the receiver of the fulfillments.
+    /// Sending again is pointless: the fulfillments go straight to the dead letters.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Returns `true` if fulfillments are waiting for another attempt: no request
+    /// should be served until the host catches up.
+    pub(crate) fn is_blocked(&self) -> bool {
+        self.closed || !self.retrying.is_empty()
+    }
+
+    /// Sends `fulfillment`, keeping it for a retry if the channel is full.
+    pub(crate) fn send(&mut self, fulfillment: Fulfillment) {
+        self.attempt(fulfillment, 0);
+        self.publish();
+    }
+
+    /// Retries sending the fulfillments that couldn't be sent before, in order.
+    pub(crate) fn flush(&mut self) {
+        if self.retrying.is_empty() {
+            return;
+        }
+        let retrying = std::mem::take(&mut self.retrying);
+        for (fulfillment, attempts) in retrying {
+            if attempts > 0 {
+                self.stats.update(|stats| stats.record_retry());
+            }
+            self.attempt(fulfillment, attempts);
+        }
+        self.publish();
+    }
+
+    /// Sends again the dead letters of `explorer_id`, or all of them if `None`,
+    /// after the fulfillments already waiting for a retry. Returns the number of
+    /// re-driven dead letters.
+    pub(crate) fn redrive(&mut self, explorer_id: Option<ExplorerId>) -> usize {
+        let (redriven, kept): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut self.dead_letters)
+            .into_iter()
+            .partition(|dead_letter| {
+                explorer_id.is_none_or(|id| dead_letter.info.explorer_id == id)
+            });
+        self.dead_letters = kept;
+
+        let count = redriven.len();
+        self.retrying.extend(
+            redriven
+                .into_iter()
+                .map(|dead_letter| (dead_letter.fulfillment, 0)),
+        );
+        self.stats
+            .update(|stats| stats.record_redriven(count as u64));
+        self.flush();
+        count
+    }
+
+    /// Sends the fulfillments waiting for a retry, blocking until the host makes room
+    /// for them or `deadline` passes. Fulfillments not sent by then are dead-lettered.
+    pub(crate) fn drain(&mut self, deadline: Instant) {
+        let retrying = std::mem::take(&mut self.retrying);
+        for (fulfillment, attempts) in retrying {
+            let attempts = attempts + 1;
+            let receipt = (fulfillment.explorer_id, fulfillment.resource.get_type());
+            let result = if self.closed {
+                Err(SendTimeoutError::Disconnected(fulfillment))
+            } else {
+                self.sender.send_deadline(fulfillment, deadline)
+            };
+
+            match result {
+                Ok(()) => self
+                    .stats
+                    .update(|stats| stats.record_delivered(receipt.0, receipt.1)),
+                Err(SendTimeoutError::Timeout(fulfillment)) => {
+                    self.dead_letter(fulfillment, attempts, DeadLetterCause::DrainTimedOut)
+                }
+                Err(SendTimeoutError::Disconnected(fulfillment)) => {
+                    self.closed = true;
+                    self.dead_letter(fulfillment, attempts, DeadLetterCause::Disconnected)
+                }
+            }
+        }
+        self.publish();
+    }
+
+    /// Makes one more attempt to send `fulfillment`, after `attempts` failed ones.
+    fn attempt(&mut self, fulfillment: Fulfillment, attempts: u32) {
+        let attempts = attempts + 1;
+        let receipt = (fulfillment.explorer_id, fulfillment.resource.get_type());
+        let result = if self.closed {
+            Err(TrySendError::Disconnected(fulfillment))
+        } else {
+            self.sender.try_send(fulfillment)
+        };
+
+        match result {
+            Ok(()) => self
+                .stats
+                .update(|stats| stats.record_delivered(receipt.0, receipt.1)),
+            Err(TrySendError::Full(fulfillment)) if attempts < self.config.max_attempts => {
+                self.retrying.push_back((fulfillment, attempts));
+            }
+            Err(TrySendError::Full(fulfillment)) => {
+                self.dead_letter(fulfillment, attempts, DeadLetterCause::AttemptsExhausted)
+            }
+            Err(TrySendError::Disconnected(fulfillment)) => {
+                self.closed = true;
+                self.dead_letter(fulfillment, attempts, DeadLetterCause::Disconnected)
+            }
+        }
+    }
+
+    /// Moves `fulfillment` to the dead letters, discarding the oldest one if full.
+    fn dead_letter(&mut self, fulfillment: Fulfillment, attempts: u32, cause: DeadLetterCause) {
+        if self.config.dead_letter_capacity == 0 {
+            return;
+        }
+        if self.dead_letters.len() >= self.config.dead_letter_capacity {
+            self.dead_letters.pop_front();
+        }
+
+        let info = DeadLetterInfo {
+            id: self.next_dead_letter_id,
+            explorer_id: fulfillment.explorer_id,
+            resource: fulfillment.resource.get_type(),
+            receipt: fulfillment.receipt,
+            attempts,
+            cause,
+            failed_at: SystemTime::now(),
+        };
+        self.next_dead_letter_id += 1;
+        self.dead_letters
+            .push_back(DeadLetter { info, fulfillment });
+        self.stats.update(|stats| stats.record_dead_lettered());
+    }
+
+    /// Publishes the content of the dead-letter buffer to the statistics.
+    fn publish(&self) {
+        let dead_letters = self
+            .dead_letters
+            .iter()
+            .map(|dead_letter| dead_letter.info.clone())
+            .collect();
+        self.stats
+            .update(|stats| stats.set_dead_letters(dead_letters));
+    }
+}
@@ -0,0 +1,30 @@
+//! Error module.
+//!
+//! Every fallible API of the crate reports its failures as a [`RustrelliError`], whose
+//! variant tells which part failed and whose message describes the problem.
+
+use thiserror::Error;
+
+/// Failure of a fallible API of the crate.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum RustrelliError {
+    /// The planet couldn't be built, or its threads couldn't be spawned.
+    #[error("planet construction failed: {0}")]
+    Construction(String),
+    /// The configuration of a planet or of a cluster of planets is invalid.
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    /// The state of a planet couldn't be saved or restored.
+    #[error("snapshot failed: {0}")]
+    Snapshot(String),
+    /// The journal of a planet couldn't be written or read.
+    #[error("journal failed: {0}")]
+    Journal(String),
+    /// An admin command couldn't be applied.
+    #[error("admin command failed: {0}")]
+    Admin(String),
+    /// The clock of a planet couldn't read the time (see [`crate::clock`]).
+    #[error("clock failed: {0}")]
+    Clock(String),
+}
@@ -0,0 +1,483 @@
+//! Planet events module.
+//!
+//! The planet AI reports noteworthy happenings as [`Event`]s sent to the host over the
+//! channel set with [`PlanetConfig::with_events`](crate::PlanetConfig::with_events).
+//!
+//! Events are diagnostics: the AI never blocks on the events channel, and drops the
+//! events the host isn't keeping up with. Once the channel is full, only one event in
+//! N is sent until the host drains half of it (see
+//! [`PlanetConfig::with_event_sampling`](crate::PlanetConfig::with_event_sampling)),
+//! warnings and critical events excepted, and the dropped events are counted (see
+//! [`EventFilter::dropped`]).
+//!
+//! Hosts collecting their diagnostics through a logging framework get the same events
+//! as records, whether or not they set an events channel:
+//! - with the `tracing` feature, as `tracing` events
+//! - otherwise with the `log` feature, as `log` records
+//!
+//! Both backends log under the `rustrelli::events` target, at the [`Severity`] of the
+//! event: warnings for the events the host should act on, info for the lifecycle of
+//! the planet and debug for the periodic reports. An [`EventFilter`] mutes the least
+//! severe events on the channel at runtime.
+//!
+//! With the `serde` feature, the events implement `Serialize`, like the
+//! [statistics](crate::stats::Stats), the [decision traces](crate::policy::DecisionTrace)
+//! and the [journal analyses](crate::analyzer::Analysis), so that dashboards can ship
+//! them as JSON.
+
+use crate::ExplorerId;
+use crate::admin::PauseMode;
+use crate::clock::ClockAnomaly;
+use crate::delivery::DeadLetterInfo;
+use crate::invariants::Invariant;
+use crate::lease::LeaseRefusal;
+use crate::misconduct::Signal;
+use crate::receipt::Receipt;
+use crate::registration::{Onboarding, Transition};
+use crate::stats::{Counters, EpochCounters, Leaderboard, Load, Receipts};
+use crossbeam_channel::{Sender, TrySendError};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Something noteworthy that happened on the planet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Event {
+    /// A generation request was refused without discharging a cell, because the
+    /// resource couldn't have been delivered to the explorer.
+    Undeliverable {
+        /// The explorer that requested the resource.
+        explorer_id: ExplorerId,
+        /// Why the resource couldn't have been delivered.
+        cause: DeliveryFailure,
+    },
+    /// The host dropped the receiver of the fulfillments: deferred fulfillment
+    /// is disabled.
+    FulfillmentChannelClosed,
+    /// The planet AI was stopped after draining its work (see
+    /// [`PlanetConfig::with_shutdown_drain`](crate::PlanetConfig::with_shutdown_drain)).
+    Stopped(ShutdownReport),
+    /// The planet thread panicked: the planet is gone. Reported by planets spawned
+    /// with [`spawn_planet`](crate::spawn_planet).
+    Panicked {
+        /// The panic message.
+        message: String,
+    },
+    /// The load of the planet after a sunray, reported if enabled with
+    /// [`PlanetConfig::with_load_events`](crate::PlanetConfig::with_load_events).
+    Load(Load),
+    /// The planet AI didn't handle any message for a while, although messages are
+    /// waiting for it. Reported by the [`Watchdog`](crate::watchdog::Watchdog).
+    Stalled {
+        /// Time since the planet AI last started handling a message.
+        idle_for: Duration,
+        /// Number of messages waiting in the watched channels.
+        queued_messages: usize,
+    },
+    /// The leaderboard after a sunray, reported if enabled with
+    /// [`PlanetConfig::with_leaderboard_events`](crate::PlanetConfig::with_leaderboard_events).
+    Leaderboard(Leaderboard),
+    /// The journal couldn't be written: the planet stopped journaling.
+    JournalFailed {
+        /// The write error.
+        error: String,
+    },
+    /// A generation request was granted, reported if enabled with
+    /// [`PlanetConfig::with_receipt_events`](crate::PlanetConfig::with_receipt_events).
+    Granted(Receipt),
+    /// The clock of the planet failed or went back in time, or recovered from it (see
+    /// [`crate::clock`](crate::clock#anomalies)).
+    Clock(ClockAnomaly),
+    /// A snapshot couldn't be written to the storage of the planet (see
+    /// [`crate::storage`]).
+    StorageFailed {
+        /// Key of the snapshot.
+        key: String,
+        /// The write error.
+        error: String,
+    },
+    /// An explorer sent a message the planet AI doesn't handle, answered by the
+    /// [`FallbackHandler`](crate::fallback::FallbackHandler).
+    UnhandledMessage {
+        /// The explorer that sent the message.
+        explorer_id: ExplorerId,
+        /// Name of the message variant.
+        message: String,
+    },
+    /// An explorer was registered again on the planet, or left it (see
+    /// [`crate::registration`]).
+    Registration {
+        /// The explorer.
+        explorer_id: ExplorerId,
+        /// What happened to its registration.
+        transition: Transition,
+    },
+    /// An explorer registered on the planet, reported with the rules of engagement if
+    /// enabled (see [`PlanetConfig::with_onboarding_events`](crate::PlanetConfig::with_onboarding_events)).
+    Onboarding(Onboarding),
+    /// The host leased cells to an explorer (see [`crate::lease`]).
+    LeaseGranted {
+        /// The explorer holding the lease.
+        explorer_id: ExplorerId,
+        /// Number of leased cells, after applying the configured bound.
+        cells: u32,
+        /// Time the unclaimed cells return to the common pool at.
+        expires: SystemTime,
+    },
+    /// The host tried to lease cells to an explorer, but the lease was refused.
+    LeaseRefused {
+        /// The explorer the cells were leased to.
+        explorer_id: ExplorerId,
+        /// Why the lease was refused.
+        refusal: LeaseRefusal,
+    },
+    /// A lease ended, because all its cells were claimed or because it expired.
+    LeaseEnded {
+        /// The explorer that held the lease.
+        explorer_id: ExplorerId,
+        /// Cells returned to the common pool.
+        unclaimed: u32,
+    },
+    /// The resources the planet can generate or combine changed, invalidating the
+    /// capability answers cached by integrators.
+    CapabilitiesChanged {
+        /// The new capability generation, see
+        /// [`AI::capability_generation`](crate::planet::AI::capability_generation).
+        generation: u64,
+    },
+    /// An explorer was denied requests in a row for as long as the threshold set with
+    /// [`PlanetConfig::with_streak_alert`](crate::PlanetConfig::with_streak_alert).
+    DenialStreak {
+        /// The denied explorer.
+        explorer_id: ExplorerId,
+        /// Requests denied in a row.
+        streak: u32,
+    },
+    /// The misconduct score of an explorer reached the alert threshold (see
+    /// [`crate::misconduct`]). The score itself is in the statistics.
+    Misconduct {
+        /// The misbehaving explorer.
+        explorer_id: ExplorerId,
+        /// The signal the threshold was reached with.
+        signal: Signal,
+    },
+    /// An invariant of the planet AI was broken: a bug (see [`crate::invariants`]).
+    InvariantViolated {
+        /// The broken invariant.
+        invariant: Invariant,
+        /// What was observed.
+        details: String,
+    },
+    /// A pending request was dropped after waiting longer than the timeout set with
+    /// [`PlanetConfig::with_pending_timeout`](crate::PlanetConfig::with_pending_timeout),
+    /// without being served.
+    QueueTimeout {
+        /// The explorer that requested the resource.
+        explorer_id: ExplorerId,
+        /// How long the request waited.
+        waited: Duration,
+    },
+    /// The planet AI moved to another stage of its lifecycle.
+    Lifecycle {
+        /// Time of the transition, as read from the planet clock.
+        at: SystemTime,
+        /// The stage the planet AI entered.
+        stage: LifecycleStage,
+    },
+}
+
+/// Stage of the lifecycle of a planet AI, reported as an [`Event::Lifecycle`].
+///
+/// A planet AI is constructed, started, then possibly paused and resumed any number of
+/// times, until it's stopped or destroyed. The terminal stages carry the all-time
+/// counters of the planet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LifecycleStage {
+    /// The planet AI was built from its configuration.
+    Constructed,
+    /// The planet AI was started by the orchestrator.
+    Started,
+    /// The handling of generation requests was paused.
+    Paused(PauseMode),
+    /// The handling of generation requests was resumed after a pause.
+    Resumed,
+    /// The planet AI is draining its work before stopping (see
+    /// [`PlanetConfig::with_shutdown_drain`](crate::PlanetConfig::with_shutdown_drain)).
+    Draining,
+    /// The planet AI was stopped by the orchestrator.
+    Stopped {
+        /// All-time counters.
+        totals: Counters,
+    },
+    /// An asteroid hit the planet, which can't build rockets to defend itself.
+    DestroyedByAsteroid {
+        /// All-time counters.
+        totals: Counters,
+    },
+}
+
+/// Final report of a planet AI stopped gracefully.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShutdownReport {
+    /// All-time counters.
+    pub totals: Counters,
+    /// Generation outcomes of each explorer in the last epoch, to judge the fairness
+    /// of the game.
+    pub epoch: EpochCounters,
+    /// Explorers whose pending requests were never served, in arrival order.
+    pub unserved_requests: Vec<ExplorerId>,
+    /// Fulfillments that couldn't be delivered to the host, oldest first.
+    pub dead_letters: Vec<DeadLetterInfo>,
+    /// Explorers still banned, and when their ban expires if it does.
+    pub bans: BTreeMap<ExplorerId, Option<SystemTime>>,
+    /// All-time resources each explorer received from the planet.
+    pub ledger: BTreeMap<ExplorerId, Receipts>,
+}
+
+/// Why a resource can't be delivered to an explorer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DeliveryFailure {
+    /// The explorer channel is full.
+    ChannelFull,
+    /// The host reported the explorer as unreachable.
+    Unreachable,
+}
+
+/// How much attention an [`Event`] deserves, from the least to the most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Severity {
+    /// Periodic reports, possibly high-volume.
+    Debug,
+    /// Lifecycle of the planet.
+    Info,
+    /// Something the host should act on.
+    Warn,
+    /// A bug of the planet AI.
+    Critical,
+}
+
+impl Severity {
+    fn from_u8(severity: u8) -> Self {
+        match severity {
+            0 => Severity::Debug,
+            1 => Severity::Info,
+            2 => Severity::Warn,
+            _ => Severity::Critical,
+        }
+    }
+}
+
+impl Event {
+    /// How much attention the event deserves.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Event::Undeliverable { .. }
+            | Event::FulfillmentChannelClosed
+            | Event::Panicked { .. }
+            | Event::Stalled { .. }
+            | Event::JournalFailed { .. }
+            | Event::StorageFailed { .. }
+            | Event::Clock(ClockAnomaly::Failed { .. } | ClockAnomaly::WentBack { .. })
+            | Event::UnhandledMessage { .. }
+            | Event::DenialStreak { .. }
+            | Event::Misconduct { .. }
+            | Event::Registration {
+                transition: Transition::Duplicate,
+                ..
+            } => Severity::Warn,
+            Event::Stopped(_)
+            | Event::Registration { .. }
+            | Event::Onboarding(_)
+            | Event::LeaseGranted { .. }
+            | Event::LeaseRefused { .. }
+            | Event::LeaseEnded { .. }
+            | Event::CapabilitiesChanged { .. }
+            | Event::QueueTimeout { .. }
+            | Event::Clock(ClockAnomaly::Recovered)
+            | Event::Lifecycle { .. } => Severity::Info,
+            Event::Load(_) | Event::Leaderboard(_) | Event::Granted(_) => Severity::Debug,
+            Event::InvariantViolated { .. } => Severity::Critical,
+        }
+    }
+}
+
+/// Minimum severity of the events sent over the events channel, set with
+/// [`PlanetConfig::with_event_filter`](crate::PlanetConfig::with_event_filter).
+///
+/// Clones share the same minimum, so a host keeps a clone to adjust the filter of a
+/// running planet. Warnings always pass, whatever the minimum. The filter doesn't apply
+/// to the logging backends, filtered by the logging framework of the host.
+///
+/// The filter also samples the events while the channel is saturated, and counts the
+/// events dropped meanwhile.
+///
+/// # Examples
+/// ```
+/// use rustrelli::events::{EventFilter, Severity};
+///
+/// let filter = EventFilter::default();
+/// let planet_filter = filter.clone();
+/// // Mutes the load reports in production
+/// filter.set_min_severity(Severity::Info);
+/// assert_eq!(planet_filter.min_severity(), Severity::Info);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EventFilter(Arc<FilterState>);
+
+#[derive(Debug)]
+struct FilterState {
+    min_severity: AtomicU8,
+    /// Whether the channel was found full, and not drained since.
+    saturated: AtomicBool,
+    /// Events filtered since the channel was found full.
+    sampled: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl EventFilter {
+    /// Creates a filter letting through the events of at least `min_severity`.
+    pub fn new(min_severity: Severity) -> Self {
+        EventFilter(Arc::new(FilterState {
+            min_severity: AtomicU8::new(min_severity as u8),
+            saturated: AtomicBool::new(false),
+            sampled: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }))
+    }
+
+    /// Lets through the events of at least `min_severity` from now on.
+    pub fn set_min_severity(&self, min_severity: Severity) {
+        self.0
+            .min_severity
+            .store(min_severity as u8, Ordering::Relaxed);
+    }
+
+    pub fn min_severity(&self) -> Severity {
+        Severity::from_u8(self.0.min_severity.load(Ordering::Relaxed))
+    }
+
+    /// Whether the events are sampled, the channel being saturated.
+    pub fn is_sampling(&self) -> bool {
+        self.0.saturated.load(Ordering::Relaxed)
+    }
+
+    /// Events let through by the filter but dropped so far, sampled out or found the
+    /// channel full.
+    pub fn dropped(&self) -> u64 {
+        self.0.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Whether `event` is sent over the events channel.
+    pub fn allows(&self, event: &Event) -> bool {
+        let severity = event.severity();
+        severity >= Severity::Warn || severity >= self.min_severity()
+    }
+}
+
+impl Default for EventFilter {
+    /// A filter letting every event through.
+    fn default() -> Self {
+        Self::new(Severity::Debug)
+    }
+}
+
+/// Sending end of the events channel, if the host is interested in events.
+#[derive(Debug, Clone)]
+pub(crate) struct EventSink {
+    pub(crate) channel: Option<Sender<Event>>,
+    pub(crate) filter: EventFilter,
+    /// One event in `sample_every` is sent while the channel is saturated.
+    pub(crate) sample_every: u64,
+}
+
+impl Default for EventSink {
+    fn default() -> Self {
+        EventSink {
+            channel: None,
+            filter: EventFilter::default(),
+            sample_every: Self::SAMPLE_EVERY,
+        }
+    }
+}
+
+impl EventSink {
+    const SAMPLE_EVERY: u64 = 10;
+
+    pub(crate) fn new(events: Sender<Event>) -> Self {
+        EventSink {
+            channel: Some(events),
+            ..EventSink::default()
+        }
+    }
+
+    /// Sends `event` to the host if the filter allows it, and logs it with the logging
+    /// backend enabled if any.
+    ///
+    /// Once the channel is found full, the events below [`Severity::Warn`] are sampled
+    /// until the host drains half of it. The events sampled out or finding the channel
+    /// full are dropped and counted, those finding it closed are dropped silently.
+    pub(crate) fn emit(&self, event: Event) {
+        log_event(&event);
+        let channel = self.channel.as_ref();
+        let Some(events) = channel.filter(|_| self.filter.allows(&event)) else {
+            return;
+        };
+        let state = &self.filter.0;
+        if state.saturated.load(Ordering::Relaxed) {
+            if events
+                .capacity()
+                .is_none_or(|capacity| events.len() * 2 <= capacity)
+            {
+                state.saturated.store(false, Ordering::Relaxed);
+            } else if event.severity() < Severity::Warn
+                && !state
+                    .sampled
+                    .fetch_add(1, Ordering::Relaxed)
+                    .is_multiple_of(self.sample_every)
+            {
+                state.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        if let Err(TrySendError::Full(_)) = events.try_send(event) {
+            state.dropped.fetch_add(1, Ordering::Relaxed);
+            if !state.saturated.swap(true, Ordering::Relaxed) {
+                state.sampled.store(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Target of the logged events.
+#[cfg(any(feature = "log", feature = "tracing"))]
+const TARGET: &str = "rustrelli::events";
+
+#[cfg(feature = "tracing")]
+fn log_event(event: &Event) {
+    match event.severity() {
+        Severity::Debug => tracing::debug!(target: TARGET, ?event),
+        Severity::Info => tracing::info!(target: TARGET, ?event),
+        Severity::Warn => tracing::warn!(target: TARGET, ?event),
+        Severity::Critical => tracing::error!(target: TARGET, ?event),
+    }
+}
+
+#[cfg(all(feature = "log", not(feature = "tracing")))]
+fn log_event(event: &Event) {
+    let level = match event.severity() {
+        Severity::Debug => log::Level::Debug,
+        Severity::Info => log::Level::Info,
+        Severity::Warn => log::Level::Warn,
+        Severity::Critical => log::Level::Error,
+    };
+    log::log!(target: TARGET, level, "{:?}", event);
+}
+
+#[cfg(not(any(feature = "log", feature = "tracing")))]
+fn log_event(_event: &Event) {}
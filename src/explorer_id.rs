@@ -0,0 +1,127 @@
+//! Explorer ID module.
+//!
+//! The protocols of `common_game` identify explorers with raw `u32`s, like they count
+//! cells, units and streaks. The planet AI converts them to an [`ExplorerId`] where they
+//! enter it, so that its per-explorer maps, queues and tags, its limit policies, and
+//! the statistics, events, journals and analyses it reports, can't be keyed by some
+//! other count by mistake.
+//!
+//! Every `u32` is the ID of an explorer as far as the protocols go, so the explorers
+//! past the cap of tracked explorers share a bucket of their own,
+//! [`ExplorerId::Overflow`], rather than the ID of some explorer (see
+//! [`Untracked::Share`](crate::registration::Untracked::Share)).
+//!
+//! Explorer IDs read from text, like the lines of a [journal](crate::journal), are
+//! validated when parsed: decimal digits fitting a `u32`, or `overflow` for the shared
+//! bucket. Hosts name explorers with raw IDs in the configuration and admin commands.
+//! ```
+//! use std::collections::BTreeMap;
+//! use rustrelli::ExplorerId;
+//!
+//! let grants = BTreeMap::from([(ExplorerId::new(7), 3), (ExplorerId::Overflow, 2)]);
+//! assert_eq!(grants[&ExplorerId::new(7)], 3);
+//! ```
+
+use crate::RustrelliError;
+use std::fmt;
+use std::str::FromStr;
+
+/// ID of an explorer, as registered by the orchestrator, or of the bucket shared by the
+/// explorers past the cap of tracked explorers.
+///
+/// Displayed as its raw ID, or `overflow`, and parsed back from them only.
+///
+/// # Examples
+/// ```
+/// use rustrelli::ExplorerId;
+///
+/// let explorer_id: ExplorerId = "42".parse()?;
+/// assert_eq!(explorer_id, ExplorerId::new(42));
+/// assert_eq!(explorer_id.to_string(), "42");
+/// assert_eq!("overflow".parse::<ExplorerId>()?, ExplorerId::Overflow);
+/// assert!("-1".parse::<ExplorerId>().is_err());
+/// assert!("+42".parse::<ExplorerId>().is_err());
+/// # Ok::<(), rustrelli::RustrelliError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExplorerId {
+    /// An explorer, by the raw ID carried by the protocol messages.
+    Explorer(u32),
+    /// Bucket shared by the explorers past the cap of tracked explorers, when they share
+    /// one (see [`Untracked::Share`](crate::registration::Untracked::Share)). Sorted
+    /// after every explorer.
+    Overflow,
+}
+
+impl ExplorerId {
+    /// Wraps the raw ID `id`, as carried by the protocol messages.
+    pub const fn new(id: u32) -> Self {
+        ExplorerId::Explorer(id)
+    }
+
+    /// Raw ID, as carried by the protocol messages, `None` for the overflow bucket.
+    pub const fn raw(self) -> Option<u32> {
+        match self {
+            ExplorerId::Explorer(id) => Some(id),
+            ExplorerId::Overflow => None,
+        }
+    }
+
+    /// Number unique to the ID, e.g. to hash it or number a track: the raw ID, or
+    /// `u32::MAX + 1` for the overflow bucket.
+    pub(crate) fn ordinal(self) -> u64 {
+        self.raw().map_or(1 << 32, u64::from)
+    }
+}
+
+impl From<u32> for ExplorerId {
+    fn from(id: u32) -> Self {
+        ExplorerId::Explorer(id)
+    }
+}
+
+impl PartialEq<u32> for ExplorerId {
+    fn eq(&self, other: &u32) -> bool {
+        self.raw() == Some(*other)
+    }
+}
+
+impl fmt::Display for ExplorerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExplorerId::Explorer(id) => id.fmt(f),
+            ExplorerId::Overflow => f.pad("overflow"),
+        }
+    }
+}
+
+impl FromStr for ExplorerId {
+    type Err = RustrelliError;
+
+    /// Parses decimal digits, refusing signs and whitespace that `u32` would accept or
+    /// report less clearly, or `overflow`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "overflow" {
+            return Ok(ExplorerId::Overflow);
+        }
+        if s.is_empty() || !s.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(RustrelliError::Config(format!(
+                "Invalid explorer ID: {s:?}"
+            )));
+        }
+        s.parse()
+            .map(ExplorerId::Explorer)
+            .map_err(|_| RustrelliError::Config(format!("Explorer ID out of range: {s}")))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ExplorerId {
+    /// Serializes an explorer as its raw ID, and the overflow bucket as `"overflow"`.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ExplorerId::Explorer(id) => serializer.serialize_u32(*id),
+            ExplorerId::Overflow => serializer.serialize_str("overflow"),
+        }
+    }
+}
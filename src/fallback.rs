@@ -0,0 +1,72 @@
+//! Unhandled message module.
+//!
+//! The `common_game` protocol grows with the game: a new explorer message variant
+//! reaches the planet before rustrelli knows how to handle it. Instead of dropping it
+//! without a trace, the planet AI counts it in
+//! [`Stats::unhandled_messages`](crate::stats::Stats::unhandled_messages), reports an
+//! [`Event::UnhandledMessage`](crate::events::Event::UnhandledMessage) (logged by the
+//! logging backends) and answers with a [`FallbackHandler`], set with
+//! [`PlanetConfig::with_fallback_handler`](crate::PlanetConfig::with_fallback_handler).
+//!
+//! The default handler, [`NoResponse`], leaves the explorer without an answer. Hosts
+//! running a newer game can answer the new variants with any
+//! `FnMut(&ExplorerToPlanet) -> Option<PlanetToExplorer>` closure until rustrelli
+//! handles them.
+//!
+//! Orchestrator messages are dispatched by the `common_game` planet loop itself, which
+//! handles the variants it knows.
+
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+
+/// Answers the explorer messages the planet AI doesn't handle.
+pub trait FallbackHandler: Send {
+    /// Response to `msg`, if any.
+    fn handle(&mut self, msg: &ExplorerToPlanet) -> Option<PlanetToExplorer>;
+}
+
+impl<F: FnMut(&ExplorerToPlanet) -> Option<PlanetToExplorer> + Send> FallbackHandler for F {
+    fn handle(&mut self, msg: &ExplorerToPlanet) -> Option<PlanetToExplorer> {
+        self(msg)
+    }
+}
+
+/// Default [`FallbackHandler`]: doesn't answer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoResponse;
+
+impl FallbackHandler for NoResponse {
+    fn handle(&mut self, _msg: &ExplorerToPlanet) -> Option<PlanetToExplorer> {
+        None
+    }
+}
+
+/// Name of the variant of `msg`, read from its `Debug` representation since the
+/// variant may be unknown to rustrelli.
+pub(crate) fn variant_name(msg: &ExplorerToPlanet) -> String {
+    let debug = format!("{msg:?}");
+    let end = debug
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(debug.len());
+    debug[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the unhandled message helpers.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Variant names
+    // ============================================================================
+
+    /// **Scenario:** A message with fields is named
+    /// **Validates:** The name is the variant name alone, without the fields
+    #[test]
+    fn test_variant_name() {
+        assert_eq!(
+            variant_name(&ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 7 }),
+            "AvailableEnergyCellRequest"
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Fleet module.
+//!
+//! Groups deploying many Type D planets in one game are interested in fleet-level
+//! numbers rather than per-planet ones. [`FleetStats`] merges the statistics of
+//! several planets, typically taken from their [`PlanetHandle`]s.
+//!
+//! Per-explorer figures are based on the generation outcomes of the current epoch of
+//! each planet (see [`Stats::epoch`]).
+//!
+//! ## Coordination
+//!
+//! Fleets also keep their planets in lockstep: a [`FleetCoordinator`] broadcasts epoch
+//! advancements, pauses and policy switches to every planet, behind a best-effort
+//! barrier. Planets apply admin commands right before their next message, so a command
+//! sent to each planet in turn would be applied by the busy ones long before the quiet
+//! ones. The coordinator first holds the planets, pausing them in
+//! [`PauseMode::Buffer`], and waits for each one to acknowledge the hold, up to a
+//! timeout. Only then does it send the command, followed by the resumption of the
+//! planets it held: no planet handles a request under the new epoch or policy while
+//! another one still handles requests under the old ones.
+//!
+//! Planets idle during the hold only acknowledge it with their next message, after the
+//! timeout: they're reported as [missed](Broadcast::missed), and still apply the hold,
+//! the command and the resumption in order before that message. Without deferred
+//! fulfillment, the requests received during the hold are denied as
+//! [paused](DenialReason::Paused) rather than buffered.
+//! ```no_run
+//! # fn spawned() -> Vec<rustrelli::PlanetHandle> { unimplemented!() }
+//! use rustrelli::fleet::FleetCoordinator;
+//!
+//! let planets = spawned();
+//! let broadcast = FleetCoordinator::new(&planets).advance_epoch();
+//! if !broadcast.missed.is_empty() {
+//!     eprintln!("Planets {:?} were idle during the barrier", broadcast.missed);
+//! }
+//! ```
+
+use crate::ExplorerId;
+use crate::PlanetHandle;
+use crate::admin::{AdminCommand, PauseMode};
+use crate::policy::{DenialReason, Policy};
+use crate::stats::{Counters, Stats};
+use common_game::utils::ID;
+use crossbeam_channel::bounded;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Statistics merged from several planets.
+///
+/// # Examples
+/// ```
+/// use rustrelli::fleet::FleetStats;
+/// use rustrelli::stats::Stats;
+///
+/// let fleet = FleetStats::aggregate([&Stats::default(), &Stats::default()]);
+/// assert_eq!(fleet.planets(), 2);
+/// assert_eq!(fleet.fairness_index(), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FleetStats {
+    planets: usize,
+    totals: Counters,
+    denials_by_reason: BTreeMap<DenialReason, u64>,
+    grants_by_explorer: BTreeMap<ExplorerId, u64>,
+}
+
+impl FleetStats {
+    /// Merges the statistics of several planets.
+    pub fn aggregate<'a>(snapshots: impl IntoIterator<Item = &'a Stats>) -> Self {
+        let mut fleet = FleetStats::default();
+
+        for stats in snapshots {
+            fleet.planets += 1;
+            let totals = stats.totals();
+            fleet.totals.grants += totals.grants;
+            fleet.totals.denials += totals.denials;
+            fleet.totals.sunrays += totals.sunrays;
+            for (reason, count) in stats.denials_by_reason() {
+                *fleet.denials_by_reason.entry(*reason).or_default() += count;
+            }
+            let epoch = stats.epoch();
+            for (explorer_id, grants) in &epoch.grants {
+                *fleet.grants_by_explorer.entry(*explorer_id).or_default() += grants;
+            }
+            // Explorers that were only denied count as getting nothing
+            for explorer_id in epoch.denials.keys() {
+                fleet.grants_by_explorer.entry(*explorer_id).or_default();
+            }
+        }
+        fleet
+    }
+
+    /// Merges the current statistics of the planets operated by `handles`.
+    pub fn from_handles<'a>(handles: impl IntoIterator<Item = &'a PlanetHandle>) -> Self {
+        let snapshots: Vec<Stats> = handles
+            .into_iter()
+            .map(PlanetHandle::metrics_snapshot)
+            .collect();
+        Self::aggregate(&snapshots)
+    }
+
+    /// Returns the number of merged planets.
+    pub fn planets(&self) -> usize {
+        self.planets
+    }
+
+    /// Returns the all-time totals of the fleet.
+    pub fn totals(&self) -> Counters {
+        self.totals
+    }
+
+    /// Returns the all-time denials of the fleet, by reason.
+    pub fn denials_by_reason(&self) -> &BTreeMap<DenialReason, u64> {
+        &self.denials_by_reason
+    }
+
+    /// Returns the resources granted by the whole fleet to each explorer that
+    /// requested any.
+    pub fn grants_by_explorer(&self) -> &BTreeMap<ExplorerId, u64> {
+        &self.grants_by_explorer
+    }
+
+    /// Returns the [Jain's fairness index](https://en.wikipedia.org/wiki/Fairness_measure)
+    /// of the resources granted to the explorers by the whole fleet: 1 when every
+    /// explorer got the same amount, down to `1 / n` when a single one of the `n`
+    /// explorers got everything.
+    ///
+    /// Returns `None` if no resource was granted.
+    pub fn fairness_index(&self) -> Option<f64> {
+        let n = self.grants_by_explorer.len() as f64;
+        let sum: f64 = self.grants_by_explorer.values().map(|g| *g as f64).sum();
+        let sum_of_squares: f64 = self
+            .grants_by_explorer
+            .values()
+            .map(|g| (*g as f64).powi(2))
+            .sum();
+        (sum > 0.0).then(|| sum * sum / (n * sum_of_squares))
+    }
+
+    /// Returns the resources granted to `explorer_id` by the whole fleet relative to
+    /// an equal split among the explorers: 1 for its exact share, 2 for twice as much.
+    ///
+    /// Returns `None` if no resource was granted.
+    pub fn share_ratio(&self, explorer_id: impl Into<ExplorerId>) -> Option<f64> {
+        let total: u64 = self.grants_by_explorer.values().sum();
+        if total == 0 {
+            return None;
+        }
+        let grants = self
+            .grants_by_explorer
+            .get(&explorer_id.into())
+            .copied()
+            .unwrap_or(0);
+        let equal_share = total as f64 / self.grants_by_explorer.len() as f64;
+        Some(grants as f64 / equal_share)
+    }
+}
+
+/// Outcome of a command broadcast by a [`FleetCoordinator`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Broadcast {
+    /// Planets that acknowledged the hold in time, in the order of the coordinator.
+    pub reached: Vec<ID>,
+    /// Planets that didn't acknowledge the hold in time, idle or stopped. The command
+    /// is applied before their next message, if any.
+    pub missed: Vec<ID>,
+}
+
+/// Broadcasts admin commands to the planets of a fleet behind a best-effort barrier,
+/// see the [module documentation](self#coordination).
+pub struct FleetCoordinator<'a> {
+    planets: Vec<&'a PlanetHandle>,
+    timeout: Duration,
+}
+
+impl<'a> FleetCoordinator<'a> {
+    /// Coordinates the planets operated by `handles`, waiting up to 100ms for them to
+    /// acknowledge a hold.
+    pub fn new(handles: impl IntoIterator<Item = &'a PlanetHandle>) -> Self {
+        FleetCoordinator {
+            planets: handles.into_iter().collect(),
+            timeout: Duration::from_millis(100),
+        }
+    }
+
+    /// Waits up to `timeout` for the planets to acknowledge a hold.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Starts a new epoch on every planet (see [`AdminCommand::AdvanceEpoch`]).
+    pub fn advance_epoch(&self) -> Broadcast {
+        self.broadcast(AdminCommand::AdvanceEpoch)
+    }
+
+    /// Moves the policy schedules of every planet to their next phase (see
+    /// [`AdminCommand::NextPhase`]).
+    pub fn next_phase(&self) -> Broadcast {
+        self.broadcast(AdminCommand::NextPhase)
+    }
+
+    /// Pauses the handling of generation requests on every planet (see
+    /// [`AdminCommand::Pause`]).
+    pub fn pause(&self, mode: PauseMode) -> Broadcast {
+        self.broadcast(AdminCommand::Pause { mode })
+    }
+
+    /// Resumes the handling of generation requests on every planet.
+    pub fn resume(&self) -> Broadcast {
+        self.broadcast(AdminCommand::Resume)
+    }
+
+    /// Replaces the planet-wide limit policy of every planet (see
+    /// [`AdminCommand::SetPolicy`]).
+    pub fn set_policy(&self, policy: impl Into<Policy>) -> Broadcast {
+        self.broadcast(AdminCommand::SetPolicy {
+            policy: policy.into(),
+        })
+    }
+
+    /// Holds the planets, waits for them to acknowledge the hold until the timeout,
+    /// then sends them `command` and resumes the planets held. Planets the host paused
+    /// itself aren't held, so they stay paused unless `command` resumes them.
+    pub fn broadcast(&self, command: AdminCommand) -> Broadcast {
+        let mut holds = Vec::with_capacity(self.planets.len());
+        for planet in &self.planets {
+            let held = !planet.stats().extended_state().paused;
+            let (reply, acknowledged) = bounded(1);
+            let hold = AdminCommand::Pause {
+                mode: PauseMode::Buffer,
+            };
+            let acknowledge = AdminCommand::SnapshotStats { reply };
+            planet.send(if held {
+                AdminCommand::batch([hold, acknowledge])
+            } else {
+                acknowledge
+            });
+            holds.push((held, acknowledged));
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        let mut broadcast = Broadcast::default();
+        for (planet, (_, acknowledged)) in self.planets.iter().zip(&holds) {
+            match acknowledged.recv_deadline(deadline) {
+                Ok(_) => broadcast.reached.push(planet.id()),
+                Err(_) => broadcast.missed.push(planet.id()),
+            }
+        }
+
+        // The command itself decides whether paused planets stay paused
+        let resumes = !matches!(command, AdminCommand::Pause { .. } | AdminCommand::Resume);
+        for (planet, (held, _)) in self.planets.iter().zip(holds) {
+            planet.send(if held && resumes {
+                AdminCommand::batch([command.clone(), AdminCommand::Resume])
+            } else {
+                command.clone()
+            });
+        }
+        broadcast
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the fleet-level aggregation.
+
+    use super::*;
+    use std::time::SystemTime;
+
+    // ============================================================================
+    // Test Helper
+    // ============================================================================
+
+    /// Statistics of a planet that granted `grants[i]` resources to explorer `i + 1`,
+    /// and denied one request of each explorer.
+    fn planet(grants: &[u64]) -> Stats {
+        let mut stats = Stats::default();
+        for (index, count) in grants.iter().enumerate() {
+            let explorer_id = index as u32 + 1;
+            for _ in 0..*count {
+                stats.record_grant(SystemTime::now());
+                stats.record_epoch(ExplorerId::from(explorer_id), true);
+            }
+            stats.record_denial(SystemTime::now(), DenialReason::NoEnergy);
+            stats.record_epoch(ExplorerId::from(explorer_id), false);
+        }
+        stats
+    }
+
+    // ============================================================================
+    // Tests: Aggregation
+    // ============================================================================
+
+    /// **Scenario:** Two planets each favor a different explorer, symmetrically
+    /// **Validates:**
+    /// - Totals and denials are summed
+    /// - The fleet is perfectly fair although each planet isn't
+    #[test]
+    fn test_fleet_evens_out_planets() {
+        let fleet = FleetStats::aggregate(&[planet(&[3, 1]), planet(&[1, 3])]);
+
+        assert_eq!(fleet.planets(), 2);
+        assert_eq!(fleet.totals().grants, 8);
+        assert_eq!(
+            fleet.denials_by_reason().get(&DenialReason::NoEnergy),
+            Some(&4)
+        );
+        assert_eq!(
+            fleet.grants_by_explorer().get(&ExplorerId::new(1)),
+            Some(&4)
+        );
+        assert_eq!(fleet.fairness_index(), Some(1.0));
+        assert_eq!(fleet.share_ratio(2), Some(1.0));
+    }
+
+    /// **Scenario:** Both planets grant everything to one of two explorers
+    /// **Validates:** Fairness index is 1/2, share ratios are 2 and 0
+    #[test]
+    fn test_fleet_detects_hog() {
+        let fleet = FleetStats::aggregate(&[planet(&[2, 0]), planet(&[2, 0])]);
+
+        assert_eq!(fleet.fairness_index(), Some(0.5));
+        assert_eq!(fleet.share_ratio(1), Some(2.0));
+        assert_eq!(fleet.share_ratio(2), Some(0.0));
+    }
+}
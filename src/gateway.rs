@@ -0,0 +1,284 @@
+//! Explorer gateway module.
+//!
+//! Explorers share a single channel to the planet, and every message claims the ID of
+//! its explorer: the planet loop can't tell an explorer claiming the ID of another one,
+//! and drops the messages claiming the ID of an explorer it doesn't know before the
+//! planet AI sees them. With
+//! [`PlanetConfig::with_explorer_gateway`](crate::PlanetConfig::with_explorer_gateway),
+//! a gateway thread receives the explorer messages in place of the planet, from the
+//! shared channel and from a channel of each explorer connected with
+//! [`ExplorerGateway::connect`], and forwards them to the planet loop.
+//!
+//! Generation and combination requests claiming another ID than the one of their
+//! channel, or the ID of an explorer whose channel the orchestrator didn't register on
+//! the planet, are reported to the planet AI as spoof attempts (see
+//! [`Stats::spoof_attempts`](crate::stats::Stats::spoof_attempts)), and dropped with
+//! [`PlanetConfig::with_spoof_protection`](crate::PlanetConfig::with_spoof_protection).
+//! Requests of unregistered explorers are dropped either way, as the planet loop would.
+//!
+//! ```
+//! use crossbeam_channel::unbounded;
+//! use rustrelli::gateway::ExplorerGateway;
+//! use rustrelli::{PlanetConfig, create_planet_with_config};
+//!
+//! let (_, rx_orch) = unbounded();
+//! let (tx_planet, _) = unbounded();
+//! let (_, rx_expl) = unbounded();
+//! let gateway = ExplorerGateway::new();
+//!
+//! let planet = create_planet_with_config(
+//!     PlanetConfig::new(1)
+//!         .with_explorer_gateway(gateway.clone())
+//!         .with_spoof_protection(),
+//!     rx_orch,
+//!     tx_planet,
+//!     rx_expl,
+//! );
+//! // Given to explorer 7 in place of a clone of the shared sender
+//! let to_planet = gateway.connect(7);
+//! ```
+
+use crate::ExplorerId;
+use crate::cost::MessageKind;
+use crate::planet::complex_type;
+use common_game::protocols::planet_explorer::ExplorerToPlanet;
+use common_game::utils::ID;
+use crossbeam_channel::{Receiver, Select, Sender, bounded, unbounded};
+use std::collections::HashSet;
+use std::thread;
+
+/// Capacity of the channel of each connected explorer.
+pub const CHANNEL_CAPACITY: usize = 64;
+
+/// Channel of a connected explorer, received by the gateway thread.
+type Connection = (ExplorerId, Receiver<ExplorerToPlanet>);
+
+/// Connects explorers to a planet through channels of their own, see the
+/// [module documentation](self).
+///
+/// Clones connect explorers to the same planet.
+#[derive(Debug, Clone)]
+pub struct ExplorerGateway {
+    to_gateway: Sender<Connection>,
+    connections: Receiver<Connection>,
+}
+
+impl ExplorerGateway {
+    /// Creates a gateway, to be given to a planet with
+    /// [`PlanetConfig::with_explorer_gateway`](crate::PlanetConfig::with_explorer_gateway).
+    pub fn new() -> Self {
+        let (to_gateway, connections) = unbounded();
+        ExplorerGateway {
+            to_gateway,
+            connections,
+        }
+    }
+
+    /// Connects `explorer_id` to the planet, through the returned sender, to be given
+    /// to the explorer in place of a clone of the shared sender. Connecting an explorer
+    /// again replaces its previous channel.
+    ///
+    /// Messages sent before the planet is built wait in the channel.
+    pub fn connect(&self, explorer_id: impl Into<ExplorerId>) -> Sender<ExplorerToPlanet> {
+        let (sender, receiver) = bounded(CHANNEL_CAPACITY);
+        // The gateway holds a receiver of its connections, so this never fails
+        let _ = self.to_gateway.send((explorer_id.into(), receiver));
+        sender
+    }
+}
+
+impl Default for ExplorerGateway {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Change to the explorers registered on the planet, notified by the planet AI.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Notice {
+    /// The orchestrator registered the channel of the explorer.
+    Arrived(ExplorerId),
+    /// The orchestrator removed the channel of the explorer.
+    Departed(ExplorerId),
+}
+
+/// Spoof attempt, reported to the planet AI.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SpoofReport {
+    /// The explorer ID claimed by the request.
+    pub(crate) claimed: ExplorerId,
+    /// Kind of the request.
+    pub(crate) kind: MessageKind,
+    /// Whether the claimed explorer wasn't registered, as far as the gateway knew: the
+    /// request was dropped, and may have raced the registration.
+    pub(crate) unregistered: bool,
+}
+
+/// Channels and thread of a gateway, ready to be started once the planet is built.
+pub(crate) struct Gateway {
+    planet_id: ID,
+    connections: Receiver<Connection>,
+    from_explorers: Receiver<ExplorerToPlanet>,
+    to_planet: Sender<ExplorerToPlanet>,
+    notices: Receiver<Notice>,
+    reports: Sender<SpoofReport>,
+    drop_spoofed: bool,
+}
+
+impl Gateway {
+    /// Prepares `gateway` to forward the messages of `from_explorers` and of the
+    /// connected explorers to the planet.
+    ///
+    /// # Returns
+    /// The gateway, the receiver to give to the planet in place of `from_explorers`,
+    /// with the same capacity so that explorers still feel the planet backpressure, and
+    /// the channels the planet AI notifies the registrations on and receives the spoof
+    /// reports from.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn new(
+        planet_id: ID,
+        gateway: &ExplorerGateway,
+        drop_spoofed: bool,
+        from_explorers: Receiver<ExplorerToPlanet>,
+    ) -> (
+        Self,
+        Receiver<ExplorerToPlanet>,
+        Sender<Notice>,
+        Receiver<SpoofReport>,
+    ) {
+        let (to_planet, from_gateway) = match from_explorers.capacity() {
+            Some(capacity) => bounded(capacity),
+            None => unbounded(),
+        };
+        let (to_gateway, notices) = unbounded();
+        let (reports, from_reports) = unbounded();
+        let gateway = Gateway {
+            planet_id,
+            connections: gateway.connections.clone(),
+            from_explorers,
+            to_planet,
+            notices,
+            reports,
+            drop_spoofed,
+        };
+        (gateway, from_gateway, to_gateway, from_reports)
+    }
+
+    /// Starts the gateway. It stops once the planet is gone, or every explorer channel
+    /// is disconnected.
+    ///
+    /// # Errors
+    /// Returns a description of the problem if the thread can't be spawned.
+    pub(crate) fn start(self) -> Result<(), String> {
+        thread::Builder::new()
+            .name(format!("rustrelli-gateway-{}", self.planet_id))
+            .spawn(move || self.run())
+            .map(drop)
+            .map_err(|error| format!("Can't spawn the explorer gateway: {error}"))
+    }
+
+    fn run(self) {
+        let mut connections = Some(&self.connections);
+        let mut shared = Some(&self.from_explorers);
+        let mut channels: Vec<Connection> = Vec::new();
+        let mut registered = HashSet::new();
+
+        loop {
+            if shared.is_none() && channels.is_empty() && connections.is_none() {
+                return;
+            }
+            let mut select = Select::new();
+            let notices_index = select.recv(&self.notices);
+            let connections_index = connections.map(|rx| select.recv(rx));
+            let shared_index = shared.map(|rx| select.recv(rx));
+            let channel_indices: Vec<usize> =
+                channels.iter().map(|(_, rx)| select.recv(rx)).collect();
+            let operation = select.select();
+            let index = operation.index();
+
+            let (owner, msg) = if index == notices_index {
+                match operation.recv(&self.notices) {
+                    Ok(notice) => apply(notice, &mut registered),
+                    // The planet AI is gone
+                    Err(_) => return,
+                }
+                continue;
+            } else if Some(index) == connections_index {
+                match operation.recv(&self.connections) {
+                    Ok((explorer_id, receiver)) => {
+                        channels.retain(|(owner, _)| *owner != explorer_id);
+                        channels.push((explorer_id, receiver));
+                    }
+                    Err(_) => connections = None,
+                }
+                continue;
+            } else if Some(index) == shared_index {
+                match operation.recv(&self.from_explorers) {
+                    Ok(msg) => (None, msg),
+                    Err(_) => {
+                        shared = None;
+                        continue;
+                    }
+                }
+            } else {
+                let position = channel_indices
+                    .iter()
+                    .position(|channel_index| *channel_index == index)
+                    .expect("Selected channel");
+                match operation.recv(&channels[position].1) {
+                    Ok(msg) => (Some(channels[position].0), msg),
+                    Err(_) => {
+                        channels.swap_remove(position);
+                        continue;
+                    }
+                }
+            };
+
+            // A request sent once its explorer is registered must find it registered
+            for notice in self.notices.try_iter() {
+                apply(notice, &mut registered);
+            }
+            if self.admits(owner, &msg, &registered) && self.to_planet.send(msg).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Whether `msg`, received on the channel of `owner` or on the shared channel if
+    /// `None`, is forwarded to the planet loop, reporting it if it's a spoof attempt.
+    fn admits(
+        &self,
+        owner: Option<ExplorerId>,
+        msg: &ExplorerToPlanet,
+        registered: &HashSet<ExplorerId>,
+    ) -> bool {
+        let kind = match msg {
+            ExplorerToPlanet::GenerateResourceRequest { resource, .. } => {
+                MessageKind::Generation(*resource)
+            }
+            ExplorerToPlanet::CombineResourceRequest { msg, .. } => {
+                MessageKind::Combination(complex_type(msg))
+            }
+            _ => return true,
+        };
+        let claimed = ExplorerId::from(msg.explorer_id());
+        let unregistered = !registered.contains(&claimed);
+        if !unregistered && owner.is_none_or(|owner| owner == claimed) {
+            return true;
+        }
+        let _ = self.reports.send(SpoofReport {
+            claimed,
+            kind,
+            unregistered,
+        });
+        !(unregistered || self.drop_spoofed)
+    }
+}
+
+/// Applies `notice` to the `registered` explorers.
+fn apply(notice: Notice, registered: &mut HashSet<ExplorerId>) {
+    match notice {
+        Notice::Arrived(explorer_id) => registered.insert(explorer_id),
+        Notice::Departed(explorer_id) => registered.remove(&explorer_id),
+    };
+}
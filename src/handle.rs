@@ -0,0 +1,210 @@
+//! Planet handle module.
+//!
+//! [`spawn_planet`](crate::spawn_planet) runs a planet on its own thread and returns a
+//! [`PlanetHandle`] to operate it, sparing hosts the thread plumbing.
+//!
+//! The handle is the entry point for operating a running planet: it carries its own
+//! admin channel (see [`crate::admin`]) and a handle to the statistics, next to the
+//! thread of the planet. Supervisors probe the planet with [`PlanetHandle::health`]
+//! to decide whether to restart or replace it.
+
+use crate::admin::{AdminCommand, PauseMode};
+use crate::info::PlanetInfo;
+use crate::policy::Policy;
+use crate::stats::{Stats, StatsHandle, StatsWatch};
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::ExplorerToPlanet;
+use common_game::utils::ID;
+use crossbeam_channel::{Receiver, Sender};
+use std::sync::{Arc, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+/// Channels connecting a planet to the game.
+pub struct PlanetChannels {
+    /// Receiver for messages from the orchestrator.
+    pub from_orchestrator: Receiver<OrchestratorToPlanet>,
+    /// Sender for messages to the orchestrator.
+    pub to_orchestrator: Sender<PlanetToOrchestrator>,
+    /// Receiver for messages from explorers.
+    pub from_explorers: Receiver<ExplorerToPlanet>,
+    /// A clone of the orchestrator sender, used by the handle to stop the planet.
+    pub control: Sender<OrchestratorToPlanet>,
+}
+
+/// Liveness of a spawned planet, returned by [`PlanetHandle::health`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Health {
+    /// Whether the planet run loop is still running.
+    pub running: bool,
+    /// Last time the planet AI started handling a message, if it did.
+    pub last_activity: Option<SystemTime>,
+    /// Messages waiting in the orchestrator channel of the planet.
+    pub orchestrator_queue: usize,
+    /// Messages waiting in the explorer channel of the planet, if watched with
+    /// [`PlanetHandle::watch_explorers`].
+    pub explorer_queue: Option<usize>,
+    /// Commands waiting in the admin channel of the handle.
+    pub admin_queue: usize,
+    /// The panic message, if the planet thread panicked.
+    pub panic: Option<String>,
+}
+
+impl Health {
+    /// Returns `true` if the run loop is running and handled a message within
+    /// `stall_after` of `now`, or has no message waiting. A planet failing this check
+    /// is gone or stuck, and should be replaced.
+    pub fn is_alive(&self, now: SystemTime, stall_after: Duration) -> bool {
+        let idle = self
+            .last_activity
+            .is_none_or(|at| now.duration_since(at).unwrap_or_default() >= stall_after);
+        let waiting = self.orchestrator_queue + self.explorer_queue.unwrap_or(0) > 0;
+        self.running && !(idle && waiting)
+    }
+}
+
+/// Handle to a planet running on its own thread.
+pub struct PlanetHandle {
+    id: ID,
+    thread: JoinHandle<Result<(), String>>,
+    control: Sender<OrchestratorToPlanet>,
+    admin: Sender<AdminCommand>,
+    stats: StatsHandle,
+    stats_watch: Option<StatsWatch>,
+    /// Set by the planet thread if it panics.
+    panic: Arc<OnceLock<String>>,
+    explorers: Option<Sender<ExplorerToPlanet>>,
+}
+
+impl PlanetHandle {
+    pub(crate) fn new(
+        id: ID,
+        thread: JoinHandle<Result<(), String>>,
+        control: Sender<OrchestratorToPlanet>,
+        admin: Sender<AdminCommand>,
+        stats: StatsHandle,
+        stats_watch: Option<StatsWatch>,
+        panic: Arc<OnceLock<String>>,
+    ) -> Self {
+        PlanetHandle {
+            id,
+            thread,
+            control,
+            admin,
+            stats,
+            stats_watch,
+            panic,
+            explorers: None,
+        }
+    }
+
+    /// Returns the ID of the planet.
+    pub fn id(&self) -> ID {
+        self.id
+    }
+
+    /// Stops the planet AI, as the orchestrator does with
+    /// [`OrchestratorToPlanet::StopPlanetAI`].
+    pub fn stop(&self) {
+        let _ = self.control.send(OrchestratorToPlanet::StopPlanetAI);
+    }
+
+    /// Destroys the planet, as the orchestrator does with
+    /// [`OrchestratorToPlanet::KillPlanet`].
+    pub fn kill(&self) {
+        let _ = self.control.send(OrchestratorToPlanet::KillPlanet);
+    }
+
+    /// Sends `command` to the planet AI. Commands are applied right before the AI
+    /// handles its next message.
+    pub fn send(&self, command: AdminCommand) {
+        let _ = self.admin.send(command);
+    }
+
+    /// Pauses the handling of generation requests (see [`AdminCommand::Pause`]).
+    pub fn pause(&self, mode: PauseMode) {
+        self.send(AdminCommand::Pause { mode });
+    }
+
+    /// Resumes the handling of generation requests.
+    pub fn resume(&self) {
+        self.send(AdminCommand::Resume);
+    }
+
+    /// Replaces the planet-wide limit policy (see [`AdminCommand::SetPolicy`]).
+    pub fn set_policy(&self, policy: impl Into<Policy>) {
+        self.send(AdminCommand::SetPolicy {
+            policy: policy.into(),
+        });
+    }
+
+    /// Returns the identity and configuration of the planet (see [`crate::info`]).
+    pub fn info(&self) -> PlanetInfo {
+        self.stats
+            .snapshot()
+            .info()
+            .cloned()
+            .expect("Spawned planets record their info")
+    }
+
+    /// Returns a copy of the current statistics of the planet.
+    pub fn metrics_snapshot(&self) -> Stats {
+        self.stats.snapshot()
+    }
+
+    /// Returns a handle to the statistics of the planet.
+    pub fn stats(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+
+    /// Returns a receiver of the copies of the statistics the planet publishes, if
+    /// configured with [`PlanetConfig::with_stats_watch`](crate::PlanetConfig::with_stats_watch).
+    pub fn stats_watch(&self) -> Option<StatsWatch> {
+        self.stats_watch.clone()
+    }
+
+    /// Reports the depth of the explorer channel of the planet in its
+    /// [`health`](Self::health), read through a clone of one of its senders.
+    pub fn watch_explorers(mut self, sender: Sender<ExplorerToPlanet>) -> Self {
+        self.explorers = Some(sender);
+        self
+    }
+
+    /// Returns the liveness of the planet, see [`Health::is_alive`].
+    pub fn health(&self) -> Health {
+        Health {
+            running: !self.thread.is_finished(),
+            last_activity: self.stats.last_activity(),
+            orchestrator_queue: self.control.len(),
+            explorer_queue: self.explorers.as_ref().map(Sender::len),
+            admin_queue: self.admin.len(),
+            panic: self.panic.get().cloned(),
+        }
+    }
+
+    /// Returns `true` if the planet run loop exited.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the planet run loop to exit, and returns its result.
+    ///
+    /// A panic of the planet thread is returned as an error with the panic message.
+    pub fn join(self) -> Result<(), String> {
+        self.thread
+            .join()
+            .unwrap_or_else(|payload| Err(panic_message(payload.as_ref())))
+    }
+}
+
+/// Extracts the message of a panic from its payload.
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unknown panic".to_string()
+    }
+}
@@ -0,0 +1,509 @@
+//! HTTP statistics module, available with the `http-stats` feature.
+//!
+//! Exhibition dashboards poll planets directly instead of going through the host. A
+//! [`StatsEndpoint`] answers `GET` requests with JSON documents:
+//! - `/stats`: the current [statistics](crate::stats::Stats) of the planet
+//! - `/fairness`: a [`FairnessReport`] of the current epoch
+//! - `/health`: the [`Health`] of the planet, if the endpoint probes it, with status
+//!   503 when the planet is gone or stuck
+//!
+//! Endpoints facing competing teams publish blurred per-explorer figures instead (see
+//! [`StatsEndpoint::with_privacy`]): `/stats` then serves [`PublicStats`], and the
+//! fairness report is computed from blurred grants.
+//!
+//! Hosts running their own HTTP server route the requests to [`StatsEndpoint::handle`].
+//! The others serve the endpoint on a listener of their own with
+//! [`StatsEndpoint::serve`], a minimal HTTP/1.1 server answering one request per
+//! connection on a thread of its own. Connections are answered one at a time, so the
+//! server gives up on clients silent for a [timeout](StatsEndpoint::with_timeout) or
+//! sending request lines longer than [`MAX_REQUEST_LINE`]:
+//! ```no_run
+//! use std::net::TcpListener;
+//! use rustrelli::http_stats::StatsEndpoint;
+//! use rustrelli::stats::StatsHandle;
+//!
+//! let stats = StatsHandle::default();
+//! // ... planet configured with the handle ...
+//! let listener = TcpListener::bind("127.0.0.1:8080")?;
+//! StatsEndpoint::new(stats).serve(listener);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::ExplorerId;
+use crate::analyzer::jain_index;
+use crate::compliance::Attestation;
+use crate::handle::Health;
+use crate::info::PlanetInfo;
+use crate::policy::DenialReason;
+use crate::privacy::Privacy;
+use crate::stats::{Counters, ExtendedState, Stats, StatsHandle, TimeBucket};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// Longest request line [`StatsEndpoint::serve`] reads, in bytes.
+pub const MAX_REQUEST_LINE: u64 = 8 * 1024;
+
+/// Default time [`StatsEndpoint::serve`] waits on a client to send its request or read
+/// the answer.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pause of [`StatsEndpoint::serve`] after failing to accept a connection, e.g. when out
+/// of file descriptors, before accepting the next ones.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+
+/// How the resources granted in the current epoch are shared between the explorers.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FairnessReport {
+    /// Number of the current epoch.
+    pub epoch: u64,
+    /// [Jain's fairness index](https://en.wikipedia.org/wiki/Fairness_measure) of the
+    /// grants between the explorers that requested resources in the epoch.
+    pub fairness: f64,
+    /// Resources granted to each explorer that requested any, denied ones included.
+    pub grants: BTreeMap<ExplorerId, u64>,
+    /// Share of the grants each explorer got.
+    pub shares: BTreeMap<ExplorerId, f64>,
+    /// All-time [satisfaction score](crate::stats::Satisfaction::score) of each explorer
+    /// that requested resources.
+    pub satisfaction: BTreeMap<ExplorerId, f64>,
+    /// Attestation of the planet, if it runs in compliance mode (see
+    /// [`crate::compliance`]).
+    pub compliance: Option<Attestation>,
+}
+
+impl FairnessReport {
+    /// Reports on the current epoch of `stats`.
+    pub fn new(stats: &Stats) -> Self {
+        let epoch = stats.epoch();
+        let mut grants = epoch.grants.clone();
+        for explorer_id in epoch.denials.keys() {
+            grants.entry(*explorer_id).or_default();
+        }
+        FairnessReport {
+            epoch: epoch.epoch,
+            fairness: 0.0,
+            shares: BTreeMap::new(),
+            grants,
+            satisfaction: stats
+                .satisfaction()
+                .iter()
+                .filter_map(|(explorer_id, satisfaction)| {
+                    satisfaction.score().map(|score| (*explorer_id, score))
+                })
+                .collect(),
+            compliance: stats.info().and_then(|info| info.compliance.clone()),
+        }
+        .with_shares()
+    }
+
+    /// Reports on the current epoch of `stats`, from grants and satisfaction scores
+    /// blurred by `privacy`.
+    pub fn blurred(stats: &Stats, privacy: &Privacy) -> Self {
+        let mut report = Self::new(stats);
+        for (explorer_id, grants) in &mut report.grants {
+            *grants = privacy.count("grants", *explorer_id, *grants);
+        }
+        for score in report.satisfaction.values_mut() {
+            *score = privacy.score(*score);
+        }
+        report.with_shares()
+    }
+
+    /// Computes the fairness and shares of the grants.
+    fn with_shares(mut self) -> Self {
+        let total: u64 = self.grants.values().sum();
+        self.fairness = jain_index(self.grants.values().copied());
+        self.shares = self
+            .grants
+            .iter()
+            .map(|(explorer_id, grants)| (*explorer_id, *grants as f64 / total.max(1) as f64))
+            .collect();
+        self
+    }
+}
+
+/// Statistics of the planet as a whole, served on `/stats` by endpoints with privacy
+/// (see [`StatsEndpoint::with_privacy`]).
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PublicStats {
+    /// All-time counters.
+    pub totals: Counters,
+    /// All-time denials, by reason.
+    pub denials_by_reason: BTreeMap<DenialReason, u64>,
+    /// Counters of the recent intervals, oldest first.
+    pub buckets: Vec<TimeBucket>,
+    /// Latest observed state.
+    pub state: ExtendedState,
+    /// Identity of the planet.
+    pub info: Option<PlanetInfo>,
+    /// All-time denials of each explorer, blurred.
+    pub denials: BTreeMap<ExplorerId, u64>,
+}
+
+impl PublicStats {
+    /// Publishes `stats`, blurring the per-explorer figures with `privacy`.
+    pub fn new(stats: &Stats, privacy: &Privacy) -> Self {
+        PublicStats {
+            totals: stats.totals(),
+            denials_by_reason: stats.denials_by_reason().clone(),
+            buckets: stats.buckets().copied().collect(),
+            state: stats.extended_state(),
+            info: stats.info().cloned(),
+            denials: stats
+                .explorer_denials()
+                .iter()
+                .map(|(explorer_id, reasons)| {
+                    let denials = reasons.values().sum();
+                    (
+                        *explorer_id,
+                        privacy.count("denials", *explorer_id, denials),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Answer of a [`StatsEndpoint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// HTTP status code.
+    pub status: u16,
+    /// JSON document.
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &impl serde::Serialize) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Response { status, body },
+            Err(error) => Self::error(500, &error.to_string()),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &BTreeMap::from([("error", message)]))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            414 => "URI Too Long",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Liveness probe of the planet, with the time after which it's considered stuck.
+type Probe = (Box<dyn Fn() -> Health + Send + Sync>, Duration);
+
+/// JSON endpoint serving the statistics of a planet, see the
+/// [module documentation](self).
+pub struct StatsEndpoint {
+    stats: StatsHandle,
+    health: Option<Probe>,
+    privacy: Option<Privacy>,
+    timeout: Duration,
+}
+
+impl StatsEndpoint {
+    /// Creates an endpoint serving the statistics of `stats`, without health.
+    pub fn new(stats: StatsHandle) -> Self {
+        StatsEndpoint {
+            stats,
+            health: None,
+            privacy: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Serves the health returned by `probe` on `/health`, typically
+    /// [`PlanetHandle::health`](crate::PlanetHandle::health), answering with status
+    /// 503 when the planet didn't handle a waiting message for `stall_after`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # fn spawned() -> rustrelli::PlanetHandle { unimplemented!() }
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use rustrelli::http_stats::StatsEndpoint;
+    ///
+    /// let planet = Arc::new(spawned());
+    /// let probed = planet.clone();
+    /// let endpoint = StatsEndpoint::new(planet.stats())
+    ///     .with_health(Duration::from_secs(5), move || probed.health());
+    /// ```
+    pub fn with_health(
+        mut self,
+        stall_after: Duration,
+        probe: impl Fn() -> Health + Send + Sync + 'static,
+    ) -> Self {
+        self.health = Some((Box::new(probe), stall_after));
+        self
+    }
+
+    /// Publishes the per-explorer figures blurred by `privacy`, for dashboards open to
+    /// competing teams: `/stats` serves the [`PublicStats`] of the planet, and
+    /// `/fairness` a [blurred](FairnessReport::blurred) report.
+    pub fn with_privacy(mut self, privacy: Privacy) -> Self {
+        self.privacy = Some(privacy);
+        self
+    }
+
+    /// Answers a `GET` request of `path`. The query string, if any, is ignored.
+    pub fn handle(&self, path: &str) -> Response {
+        let path = path.split_once('?').map_or(path, |(path, _)| path);
+        match path.trim_end_matches('/') {
+            "/stats" => match &self.privacy {
+                Some(privacy) => Response::json(
+                    200,
+                    &self.stats.read(|stats| PublicStats::new(stats, privacy)),
+                ),
+                None => Response::json(200, &*self.stats.shared()),
+            },
+            "/fairness" => match &self.privacy {
+                Some(privacy) => Response::json(
+                    200,
+                    &self
+                        .stats
+                        .read(|stats| FairnessReport::blurred(stats, privacy)),
+                ),
+                None => Response::json(200, &self.stats.read(FairnessReport::new)),
+            },
+            "/health" => match &self.health {
+                Some((probe, stall_after)) => {
+                    let health = probe();
+                    let alive = health.is_alive(SystemTime::now(), *stall_after);
+                    Response::json(if alive { 200 } else { 503 }, &health)
+                }
+                None => Response::error(404, "health isn't probed"),
+            },
+            _ => Response::error(404, "not found"),
+        }
+    }
+
+    /// Gives up on the clients of [`serve`](Self::serve) that take longer than
+    /// `timeout` to send their request or read the answer, [`DEFAULT_TIMEOUT`] unless
+    /// set.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Serves the endpoint on `listener`, on a new thread running as long as the
+    /// process. Connections are answered one at a time, and closed after their first
+    /// request.
+    pub fn serve(self, listener: TcpListener) -> JoinHandle<()> {
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    // A client hanging up midway only loses its own answer
+                    Ok(stream) => {
+                        let _ = self.answer(stream);
+                    }
+                    // Failing to accept a connection, e.g. out of file descriptors, only
+                    // loses that connection
+                    Err(_) => thread::sleep(ACCEPT_BACKOFF),
+                }
+            }
+        })
+    }
+
+    /// Reads the request line of `stream` and writes the answer, ignoring the headers.
+    fn answer(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        let mut request_line = String::new();
+        BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+        let truncated = request_line.len() as u64 == MAX_REQUEST_LINE;
+        let mut parts = request_line.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            _ if truncated && !request_line.ends_with('\n') => {
+                Response::error(414, "request line too long")
+            }
+            (Some("GET"), Some(path)) => self.handle(path),
+            _ => Response::error(405, "only GET is supported"),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            response.status,
+            response.reason(),
+            response.body.len(),
+            response.body
+        )?;
+        stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the HTTP statistics endpoint.
+
+    use super::*;
+    use std::io::Read;
+
+    fn healthy() -> Health {
+        Health {
+            running: true,
+            last_activity: None,
+            orchestrator_queue: 0,
+            explorer_queue: None,
+            admin_queue: 0,
+            panic: None,
+        }
+    }
+
+    // ============================================================================
+    // Tests: Routes
+    // ============================================================================
+
+    /// **Scenario:** Explorer 1 granted 3 resources and explorer 2 only denied, then
+    /// every route is requested
+    /// **Validates:**
+    /// - Statistics and fairness are served as JSON
+    /// - Health is served if probed, with status 503 for a stopped planet
+    /// - Unknown routes are answered with 404
+    #[test]
+    fn test_routes() {
+        let stats = StatsHandle::default();
+        stats.update(|stats| {
+            for _ in 0..3 {
+                stats.record_epoch(ExplorerId::new(1), true);
+                stats.record_wait(ExplorerId::new(1), SystemTime::UNIX_EPOCH, true);
+            }
+            stats.record_epoch(ExplorerId::new(2), false);
+            stats.record_wait(ExplorerId::new(2), SystemTime::UNIX_EPOCH, false);
+        });
+        let endpoint = StatsEndpoint::new(stats.clone());
+
+        let response = endpoint.handle("/stats");
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.body,
+            serde_json::to_string(&stats.snapshot()).unwrap()
+        );
+
+        let response = endpoint.handle("/fairness?epoch=current");
+        let report: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(report["grants"]["1"], 3);
+        assert_eq!(report["shares"]["2"], 0.0);
+        assert_eq!(report["fairness"], 0.5);
+        assert_eq!(report["satisfaction"]["1"], 1.0);
+        assert_eq!(report["satisfaction"]["2"], 0.0);
+        assert!(report["compliance"].is_null(), "Not in compliance mode");
+
+        assert_eq!(endpoint.handle("/health").status, 404);
+        assert_eq!(endpoint.handle("/energy").status, 404);
+
+        let endpoint = endpoint.with_health(Duration::from_secs(1), || Health {
+            running: false,
+            ..healthy()
+        });
+        let response = endpoint.handle("/health");
+        assert_eq!(response.status, 503);
+        assert!(response.body.contains("\"running\":false"));
+    }
+
+    /// **Scenario:** Explorer 1 granted 30 resources and explorer 2 denied 25 times,
+    /// published with privacy and buckets of 10
+    /// **Validates:**
+    /// - `/stats` serves the planet-wide totals exactly, without the per-explorer
+    ///   statistics
+    /// - The per-explorer figures are blurred to multiples of the bucket width, the
+    ///   shares computed from them
+    #[test]
+    fn test_privacy() {
+        let stats = StatsHandle::default();
+        stats.update(|stats| {
+            for _ in 0..30 {
+                stats.record_grant(SystemTime::UNIX_EPOCH);
+                stats.record_epoch(ExplorerId::new(1), true);
+            }
+            for _ in 0..25 {
+                stats.record_explorer_denial(ExplorerId::new(2), DenialReason::NoEnergy);
+                stats.record_epoch(ExplorerId::new(2), false);
+            }
+        });
+        let endpoint =
+            StatsEndpoint::new(stats).with_privacy(Privacy::new(1.0).with_buckets(10).with_seed(7));
+
+        let response = endpoint.handle("/stats");
+        let public: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(public["totals"]["grants"], 30);
+        assert!(public.get("ledger").is_none(), "Per-explorer statistics");
+        assert_eq!(public["denials"]["2"].as_u64().unwrap() % 10, 0);
+
+        let response = endpoint.handle("/fairness");
+        let report: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        let grants =
+            [&report["grants"]["1"], &report["grants"]["2"]].map(|grants| grants.as_u64().unwrap());
+        assert!(grants.iter().all(|grants| grants % 10 == 0), "{grants:?}");
+        let total = grants.iter().sum::<u64>().max(1) as f64;
+        assert_eq!(report["shares"]["1"], grants[0] as f64 / total);
+    }
+
+    /// **Scenario:** Endpoint served on a local port, polled over TCP
+    /// **Validates:** The answer is an HTTP response carrying the JSON health
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        StatsEndpoint::new(StatsHandle::default())
+            .with_health(Duration::from_secs(1), healthy)
+            .serve(listener);
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: planet\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/json"));
+        assert!(response.ends_with(&serde_json::to_string(&healthy()).unwrap()));
+    }
+
+    /// **Scenario:** Endpoint served with a short timeout; a client connects and stays
+    /// silent, another sends a request line reaching the limit unended, then a third
+    /// polls the health
+    /// **Validates:**
+    /// - The silent client is dropped after the timeout
+    /// - The unended request line is answered with status 414
+    /// - The third client is answered
+    #[test]
+    fn test_serve_bounds_slow_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        StatsEndpoint::new(StatsHandle::default())
+            .with_health(Duration::from_secs(1), healthy)
+            .with_timeout(Duration::from_millis(100))
+            .serve(listener);
+
+        let mut silent = TcpStream::connect(address).unwrap();
+        let mut endless = TcpStream::connect(address).unwrap();
+        endless
+            .write_all(&[b'a'; MAX_REQUEST_LINE as usize])
+            .unwrap();
+        let mut polling = TcpStream::connect(address).unwrap();
+        polling.write_all(b"GET /health HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut response = String::new();
+        silent.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "");
+        endless.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 414 URI Too Long\r\n"));
+        response.clear();
+        polling.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
@@ -0,0 +1,83 @@
+//! Planet identity module.
+//!
+//! Tournament operators running many planets need to verify what each instance runs:
+//! which build of rustrelli, which rules and which limit policy. Every planet built by
+//! this crate records its [`PlanetInfo`] in its statistics when created, so that it's
+//! part of every snapshot (see [`Stats::info`](crate::stats::Stats::info)), and
+//! spawned planets return it from [`PlanetHandle::info`](crate::handle::PlanetHandle::info).
+//!
+//! The limit policy is kept up to date when the host replaces it at runtime. Planets
+//! sharing the same statistics handle share the info of the last one created.
+
+use crate::compliance::Attestation;
+use crate::policy::Policy;
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use common_game::utils::ID;
+
+/// Cargo features this build of rustrelli was compiled with.
+const FEATURES: &[(&str, bool)] = &[
+    ("chaos", cfg!(feature = "chaos")),
+    ("http-stats", cfg!(feature = "http-stats")),
+    ("log", cfg!(feature = "log")),
+    ("metrics-facade", cfg!(feature = "metrics-facade")),
+    ("otel", cfg!(feature = "otel")),
+    ("profiling", cfg!(feature = "profiling")),
+    ("serde", cfg!(feature = "serde")),
+    ("strict-checks", cfg!(feature = "strict-checks")),
+    ("test-kit", cfg!(feature = "test-kit")),
+    ("tracing", cfg!(feature = "tracing")),
+    ("zstd", cfg!(feature = "zstd")),
+];
+
+/// Identity and configuration of a planet instance.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PlanetInfo {
+    /// ID of the planet.
+    pub id: ID,
+    /// Version of rustrelli running the planet.
+    pub version: &'static str,
+    /// Type of the planet.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::name"))]
+    pub planet_type: PlanetType,
+    /// Basic resources the planet can generate.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::names"))]
+    pub generation_rules: Vec<BasicResourceType>,
+    /// Complex resources the planet can combine.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::names"))]
+    pub combination_rules: Vec<ComplexResourceType>,
+    /// Planet-wide limit policy.
+    pub request_limit: Policy,
+    /// Cargo features rustrelli was compiled with.
+    pub features: Vec<&'static str>,
+    /// Attestation of a planet running in compliance mode, `None` otherwise (see
+    /// [`crate::compliance`]).
+    pub compliance: Option<Attestation>,
+}
+
+impl PlanetInfo {
+    pub(crate) fn new(
+        id: ID,
+        planet_type: PlanetType,
+        generation_rules: &[BasicResourceType],
+        combination_rules: &[ComplexResourceType],
+        request_limit: Policy,
+        compliance: Option<Attestation>,
+    ) -> Self {
+        PlanetInfo {
+            id,
+            version: env!("CARGO_PKG_VERSION"),
+            planet_type,
+            generation_rules: generation_rules.to_vec(),
+            combination_rules: combination_rules.to_vec(),
+            request_limit,
+            features: FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(feature, _)| *feature)
+                .collect(),
+            compliance,
+        }
+    }
+}
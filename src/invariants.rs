@@ -0,0 +1,145 @@
+//! Invariant checks module.
+//!
+//! Limiter bugs tend to show as energy appearing or vanishing long before anyone
+//! notices the unfair outcome. The planet AI checks a few invariants after handling
+//! each message:
+//! - [`Invariant::EnergyConservation`]: the charged cells plus the cells discharged
+//!   never exceed the cells charged at the start plus the cells charged by sunrays
+//! - [`Invariant::SingleDischarge`]: a generation request discharges a cell if granted,
+//!   and none otherwise
+//! - [`Invariant::QueueAccounting`]: the pending requests counted for each explorer add
+//!   up to the requests in the pending queue, which never exceed its capacity
+//! - [`Invariant::ChangeReplay`]: replaying the state changes applied since the start
+//!   onto the state the AI started from rebuilds its pause, quotas, tags,
+//!   reservations, unreachable explorers, bans, disabled resources, pending queue and
+//!   backoffs
+//!
+//! A violation is reported as an [`Event::InvariantViolated`](crate::events::Event::InvariantViolated),
+//! of [`Severity::Critical`](crate::events::Severity::Critical), then fails a debug
+//! assertion.
+//!
+//! The checks are debug assertions: release builds leave them out, unless built with
+//! the `strict-checks` feature, which keeps them and only reports the violations.
+
+/// An invariant of the planet AI, see the [module](self) documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Invariant {
+    /// No more energy is spent or stored than received.
+    EnergyConservation,
+    /// A generation request discharges a single cell if granted, none otherwise.
+    SingleDischarge,
+    /// The occupancy of the pending queue matches its requests.
+    QueueAccounting,
+    /// The changes rebuild the state of the AI.
+    ChangeReplay,
+}
+
+/// Whether the invariants are checked in this build.
+pub(crate) const ENABLED: bool = cfg!(any(debug_assertions, feature = "strict-checks"));
+
+/// Energy received and spent since the planet AI started, for
+/// [`Invariant::EnergyConservation`] and [`Invariant::SingleDischarge`].
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct EnergyLedger {
+    /// Cells charged when the AI started, `None` before.
+    initial: Option<u64>,
+    charged: u64,
+    discharged: u64,
+}
+
+impl EnergyLedger {
+    /// Starts over from `charged` cells.
+    pub(crate) fn start(&mut self, charged: usize) {
+        *self = EnergyLedger {
+            initial: Some(charged as u64),
+            ..EnergyLedger::default()
+        };
+    }
+
+    pub(crate) fn record_charge(&mut self) {
+        self.charged += 1;
+    }
+
+    pub(crate) fn record_discharge(&mut self) {
+        self.discharged += 1;
+    }
+
+    /// Cells discharged so far.
+    pub(crate) fn discharged(&self) -> u64 {
+        self.discharged
+    }
+
+    /// Checks that `stockpile` charged cells are accounted for.
+    pub(crate) fn check(&self, stockpile: usize) -> Result<(), String> {
+        let Some(initial) = self.initial else {
+            return Ok(());
+        };
+        let spent = stockpile as u64 + self.discharged;
+        let received = initial + self.charged;
+        if spent > received {
+            return Err(format!(
+                "{stockpile} charged and {} discharged cells, out of {initial} initial and {} charged",
+                self.discharged, self.charged
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a generation request `granted` or not discharged one cell if granted,
+/// none otherwise, given the cells discharged `before` and `after` it.
+pub(crate) fn check_discharge(before: u64, after: u64, granted: bool) -> Result<(), String> {
+    let expected = u64::from(granted);
+    let discharged = after - before;
+    if discharged != expected {
+        return Err(format!(
+            "{discharged} cells discharged for a request {}",
+            if granted { "granted" } else { "denied" }
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the invariant checks.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Energy
+    // ============================================================================
+
+    /// **Scenario:** 2 cells charged at the start, 3 sunrays, 4 discharges
+    /// **Validates:**
+    /// - Up to the 1 cell left charged, the energy is accounted for
+    /// - A second cell left charged breaks conservation
+    /// - Nothing is checked before the start
+    #[test]
+    fn test_energy_conservation() {
+        let mut ledger = EnergyLedger::default();
+        assert!(ledger.check(100).is_ok());
+
+        ledger.start(2);
+        for _ in 0..3 {
+            ledger.record_charge();
+        }
+        for _ in 0..4 {
+            ledger.record_discharge();
+        }
+        assert!(ledger.check(1).is_ok());
+        assert!(ledger.check(2).is_err());
+    }
+
+    /// **Scenario:** Requests discharging 0, 1 or 2 cells
+    /// **Validates:** Only a single discharge for a grant, or none for a denial, pass
+    #[test]
+    fn test_single_discharge() {
+        assert!(check_discharge(3, 4, true).is_ok());
+        assert!(check_discharge(3, 3, false).is_ok());
+        assert!(check_discharge(3, 5, true).is_err());
+        assert!(check_discharge(3, 4, false).is_err());
+        assert!(check_discharge(3, 3, true).is_err());
+    }
+}
@@ -0,0 +1,569 @@
+//! Planet journal module.
+//!
+//! When enabled with [`PlanetConfig::with_journal`](crate::PlanetConfig::with_journal),
+//! the planet AI writes every sunray, generation decision and epoch change to a
+//! journal, one line each, so that a game can be analyzed offline (see the
+//! [`analyzer`](crate::analyzer) module). A journal starts with a header giving the
+//! version of its format, the version of the crate that wrote it, the number of energy
+//! cells of the planet and how many were charged when it started:
+//! ```text
+//! rustrelli-journal 4 crate=0.1.0 cells=5 charged=0
+//! 1700000000000 sunray
+//! 1700000000250 generate 3 Oxygen granted receipt=1
+//! 1700000000300 generate 4 Carbon fair_share_exceeded
+//! 1700000001000 epoch
+//! ```
+//! Times are in milliseconds since the Unix epoch, on the planet clock. Explorers are
+//! written as their [`ExplorerId`], `overflow` for the bucket shared by the untracked
+//! explorers. Grants are written with their [receipt](crate::receipt), and denials as
+//! their [refusal code](crate::refusal::RefusalReason::code).
+//!
+//! Journals written by older versions of the crate are migrated to the current format
+//! when read, so that a tournament can upgrade the crate between games and still
+//! analyze them all. Version 1 had no `crate` field, versions 1 and 2 wrote grants
+//! without receipt, and versions before 4 wrote the shared bucket as explorer
+//! 4294967295, read back as such.
+//!
+//! With the `zstd` feature, long tournaments can keep their journals compressed: write
+//! them through a [`ZstdJournal`] and read them back with [`Journal::read_zstd`].
+
+use crate::policy::DenialReason;
+use crate::receipt::ReceiptId;
+use crate::refusal::RefusalReason;
+use crate::stats::BASIC_RESOURCES;
+use crate::{ExplorerId, RustrelliError};
+use common_game::components::resource::BasicResourceType;
+use std::io::{BufRead, Write};
+#[cfg(feature = "zstd")]
+use std::io::{BufReader, Read};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// First word of the header of a journal.
+const MAGIC: &str = "rustrelli-journal";
+/// Version of the journal format, bumped on any change to it, with a migration from
+/// the previous version in [`parse_header`] or [`parse_entry`].
+const VERSION: u32 = 4;
+
+/// Every denial reason, to parse their codes.
+const DENIAL_REASONS: [DenialReason; 19] = [
+    DenialReason::NoEnergy,
+    DenialReason::FairShareExceeded,
+    DenialReason::QuotaExceeded,
+    DenialReason::GlobalCapReached,
+    DenialReason::ResourceCapReached,
+    DenialReason::EpochBudgetExhausted,
+    DenialReason::Reserved,
+    DenialReason::Undeliverable,
+    DenialReason::Paused,
+    DenialReason::Banned,
+    DenialReason::UnsupportedResource,
+    DenialReason::NotItsTurn,
+    DenialReason::Outbid,
+    DenialReason::InsufficientCredits,
+    DenialReason::RetriedTooEarly,
+    DenialReason::Offered,
+    DenialReason::ResourceDisabled,
+    DenialReason::TargetShareExceeded,
+    DenialReason::Untracked,
+];
+
+/// Something recorded in a journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalEntry {
+    /// A sunray reached the planet.
+    Sunray { at: SystemTime },
+    /// A generation request was decided on: granted if `denial` is `None`, with a
+    /// receipt unless written before version 3.
+    Generation {
+        at: SystemTime,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+        denial: Option<DenialReason>,
+        receipt: Option<ReceiptId>,
+    },
+    /// The host started a new game epoch.
+    Epoch { at: SystemTime },
+}
+
+impl JournalEntry {
+    /// When the entry was recorded, on the planet clock.
+    pub fn at(&self) -> SystemTime {
+        match self {
+            JournalEntry::Sunray { at }
+            | JournalEntry::Generation { at, .. }
+            | JournalEntry::Epoch { at } => *at,
+        }
+    }
+}
+
+/// A journal read back with [`Journal::read`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journal {
+    /// Version of the format the journal was written in, before its migration.
+    pub version: u32,
+    /// Version of the crate that wrote the journal, if known.
+    pub written_by: Option<String>,
+    /// Number of energy cells of the planet.
+    pub cells: usize,
+    /// Charged cells when the journal started.
+    pub charged: usize,
+    /// The entries, in the order they were recorded.
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Reads a journal written by a planet, migrating it from older formats.
+    ///
+    /// # Errors
+    /// Returns [`RustrelliError::Journal`] if it can't be read, or isn't a journal of
+    /// a supported version.
+    pub fn read(reader: impl BufRead) -> Result<Self, RustrelliError> {
+        let mut lines = reader.lines().enumerate();
+        let header = match lines.next() {
+            Some((_, line)) => line.map_err(|error| RustrelliError::Journal(error.to_string()))?,
+            None => return Err(RustrelliError::Journal("Empty journal".to_string())),
+        };
+        let mut journal = parse_header(&header)
+            .ok_or_else(|| RustrelliError::Journal(format!("Invalid journal header: {header}")))?;
+        if journal.version > VERSION {
+            return Err(RustrelliError::Journal(format!(
+                "Journal version {} is newer than the supported version {VERSION}",
+                journal.version
+            )));
+        }
+        for (index, line) in lines {
+            let line = line.map_err(|error| RustrelliError::Journal(error.to_string()))?;
+            if line.is_empty() {
+                continue;
+            }
+            let entry = parse_entry(&line).ok_or_else(|| {
+                RustrelliError::Journal(format!("Invalid entry on line {}: {line}", index + 1))
+            })?;
+            journal.entries.push(entry);
+        }
+        Ok(journal)
+    }
+
+    /// Reads a journal compressed by a [`ZstdJournal`], up to its last sync point if
+    /// it wasn't finished, e.g. because the host crashed.
+    ///
+    /// # Errors
+    /// Returns [`RustrelliError::Journal`] if it can't be decompressed, or isn't a
+    /// journal of a supported version.
+    #[cfg(feature = "zstd")]
+    pub fn read_zstd(reader: impl Read) -> Result<Self, RustrelliError> {
+        let mut decoder = zstd::stream::read::Decoder::new(reader)
+            .map_err(|error| RustrelliError::Journal(error.to_string()))?;
+        let mut text = Vec::new();
+        if let Err(error) = decoder.read_to_end(&mut text) {
+            if error.kind() != std::io::ErrorKind::UnexpectedEof {
+                return Err(RustrelliError::Journal(error.to_string()));
+            }
+            // Unfinished frame: drop the line cut by the end of the last block.
+            let complete = text
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .map_or(0, |end| end + 1);
+            text.truncate(complete);
+        }
+        Self::read(BufReader::new(text.as_slice()))
+    }
+}
+
+/// Writes the journal of a planet AI.
+pub(crate) struct JournalWriter {
+    out: Box<dyn Write + Send>,
+    /// Whether the header was written.
+    started: bool,
+}
+
+impl JournalWriter {
+    pub(crate) fn new(out: Box<dyn Write + Send>) -> Self {
+        JournalWriter {
+            out,
+            started: false,
+        }
+    }
+
+    /// Writes the header, unless it was already written.
+    pub(crate) fn start(&mut self, cells: usize, charged: usize) -> std::io::Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        writeln!(
+            self.out,
+            "{MAGIC} {VERSION} crate={} cells={cells} charged={charged}",
+            env!("CARGO_PKG_VERSION")
+        )
+    }
+
+    pub(crate) fn write(&mut self, entry: &JournalEntry) -> std::io::Result<()> {
+        write!(self.out, "{}", millis(entry.at()))?;
+        match entry {
+            JournalEntry::Sunray { .. } => writeln!(self.out, " sunray"),
+            JournalEntry::Generation {
+                explorer_id,
+                resource,
+                denial,
+                receipt,
+                ..
+            } => {
+                let outcome =
+                    denial.map_or("granted", |reason| RefusalReason::Denied(reason).code());
+                write!(self.out, " generate {explorer_id} {resource:?} {outcome}")?;
+                if let Some(receipt) = receipt {
+                    write!(self.out, " receipt={receipt}")?;
+                }
+                writeln!(self.out)
+            }
+            JournalEntry::Epoch { .. } => writeln!(self.out, " epoch"),
+        }
+    }
+
+    pub(crate) fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Journal output compressing with zstd, for
+/// [`PlanetConfig::with_journal`](crate::PlanetConfig::with_journal). Read it back with
+/// [`Journal::read_zstd`].
+///
+/// Every 1000 lines by default, the compressor flushes a sync point, so that a journal
+/// cut short by a crash can be read up to it. The frame is finished when the writer is
+/// dropped, along with the planet AI.
+#[cfg(feature = "zstd")]
+pub struct ZstdJournal<W: Write> {
+    encoder: zstd::stream::AutoFinishEncoder<'static, W>,
+    /// Lines between sync points.
+    sync_every: usize,
+    /// Lines written since the last sync point.
+    unsynced: usize,
+}
+
+#[cfg(feature = "zstd")]
+impl<W: Write> ZstdJournal<W> {
+    /// Compresses to `out` at `level`, from 1 (fastest) to 22 (smallest), 0 for the
+    /// zstd default.
+    ///
+    /// # Errors
+    /// Returns the error of zstd if it can't be set up at `level`.
+    pub fn new(out: W, level: i32) -> std::io::Result<Self> {
+        Ok(ZstdJournal {
+            encoder: zstd::stream::Encoder::new(out, level)?.auto_finish(),
+            sync_every: 1000,
+            unsynced: 0,
+        })
+    }
+
+    /// Flushes a sync point every `lines` lines.
+    ///
+    /// # Panics
+    /// Panics if `lines` is zero.
+    pub fn with_sync_every(mut self, lines: usize) -> Self {
+        assert!(lines > 0, "Sync interval must be greater than zero");
+        self.sync_every = lines;
+        self
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<W: Write> Write for ZstdJournal<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.encoder.write(buf)?;
+        self.unsynced += buf[..written].iter().filter(|byte| **byte == b'\n').count();
+        if self.unsynced >= self.sync_every {
+            self.flush()?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.unsynced = 0;
+        self.encoder.flush()
+    }
+}
+
+/// Converts `time` to milliseconds since [`UNIX_EPOCH`], saturating before it.
+fn millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis())
+}
+
+/// Parses a header into an empty journal. The version isn't checked against
+/// [`VERSION`], the rest of the header is parsed as of version 2.
+fn parse_header(line: &str) -> Option<Journal> {
+    let mut words = line.split_whitespace().peekable();
+    if words.next()? != MAGIC {
+        return None;
+    }
+    let version = words.next()?.parse().ok()?;
+    // Version 1 didn't record the crate version.
+    let written_by = match words.peek()?.strip_prefix("crate=") {
+        Some(written_by) if version >= 2 => {
+            words.next();
+            Some(written_by.to_string())
+        }
+        Some(_) => return None,
+        None if version >= 2 => return None,
+        None => None,
+    };
+    let cells = words.next()?.strip_prefix("cells=")?.parse().ok()?;
+    let charged = words.next()?.strip_prefix("charged=")?.parse().ok()?;
+    words.next().is_none().then_some(Journal {
+        version,
+        written_by,
+        cells,
+        charged,
+        entries: Vec::new(),
+    })
+}
+
+/// Parses an entry. Grants had no receipt before version 3.
+fn parse_entry(line: &str) -> Option<JournalEntry> {
+    let mut words = line.split_whitespace();
+    let at = UNIX_EPOCH + Duration::from_millis(words.next()?.parse().ok()?);
+    let entry = match words.next()? {
+        "sunray" => JournalEntry::Sunray { at },
+        "epoch" => JournalEntry::Epoch { at },
+        "generate" => {
+            let explorer_id = words.next()?.parse().ok()?;
+            let resource = words.next()?;
+            let resource = BASIC_RESOURCES
+                .into_iter()
+                .find(|candidate| format!("{candidate:?}") == resource)?;
+            let (denial, receipt) = match words.next()? {
+                "granted" => match words.next() {
+                    Some(receipt) => {
+                        let receipt = receipt.strip_prefix("receipt=")?.parse().ok()?;
+                        (None, Some(ReceiptId::new(receipt)))
+                    }
+                    None => (None, None),
+                },
+                code => {
+                    let denial = DENIAL_REASONS
+                        .into_iter()
+                        .find(|reason| RefusalReason::Denied(*reason).code() == code)?;
+                    (Some(denial), None)
+                }
+            };
+            JournalEntry::Generation {
+                at,
+                explorer_id,
+                resource,
+                denial,
+                receipt,
+            }
+        }
+        _ => return None,
+    };
+    words.next().is_none().then_some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the journal format.
+
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Writer appending to a buffer shared with the test.
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // ============================================================================
+    // Tests: Format
+    // ============================================================================
+
+    /// **Scenario:** Write a journal with every kind of entry, then read it back
+    /// **Validates:**
+    /// - The header and entries round-trip, denials by their refusal code and the
+    ///   overflow bucket by name
+    /// - Malformed entries and explorer IDs are reported with their line number
+    #[test]
+    fn test_journal_round_trip() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let mut writer = JournalWriter::new(Box::new(Shared(buffer.clone())));
+        let at = |millis| UNIX_EPOCH + Duration::from_millis(millis);
+        let entries = vec![
+            JournalEntry::Sunray { at: at(1000) },
+            JournalEntry::Generation {
+                at: at(1250),
+                explorer_id: ExplorerId::new(3),
+                resource: BasicResourceType::Oxygen,
+                denial: None,
+                receipt: Some(ReceiptId::new(1)),
+            },
+            JournalEntry::Generation {
+                at: at(1300),
+                explorer_id: ExplorerId::new(4),
+                resource: BasicResourceType::Carbon,
+                denial: Some(DenialReason::FairShareExceeded),
+                receipt: None,
+            },
+            JournalEntry::Generation {
+                at: at(1350),
+                explorer_id: ExplorerId::Overflow,
+                resource: BasicResourceType::Oxygen,
+                denial: Some(DenialReason::Untracked),
+                receipt: None,
+            },
+            JournalEntry::Epoch { at: at(2000) },
+        ];
+        writer.start(5, 1).unwrap();
+        writer.start(5, 2).unwrap();
+        for entry in &entries {
+            writer.write(entry).unwrap();
+        }
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("1250 generate 3 Oxygen granted receipt=1\n"));
+        assert!(text.contains("1300 generate 4 Carbon fair_share_exceeded\n"));
+        assert!(text.contains("1350 generate overflow Oxygen untracked\n"));
+        let journal = Journal::read(text.as_bytes()).unwrap();
+        assert_eq!(
+            journal,
+            Journal {
+                version: VERSION,
+                written_by: Some(env!("CARGO_PKG_VERSION").to_string()),
+                cells: 5,
+                charged: 1,
+                entries,
+            }
+        );
+
+        let corrupted = format!("{text}1400 generate 4 Lava granted\n");
+        assert_eq!(
+            Journal::read(corrupted.as_bytes()),
+            Err(RustrelliError::Journal(
+                "Invalid entry on line 7: 1400 generate 4 Lava granted".to_string()
+            ))
+        );
+        let negative = format!("{text}1400 generate -4 Oxygen granted\n");
+        assert_eq!(
+            Journal::read(negative.as_bytes()),
+            Err(RustrelliError::Journal(
+                "Invalid entry on line 7: 1400 generate -4 Oxygen granted".to_string()
+            ))
+        );
+    }
+
+    /// **Scenario:** Read journals of version 1, of a future version, and with a
+    /// malformed header
+    /// **Validates:**
+    /// - A version 1 journal is migrated, without the crate version that wrote it
+    /// - The grants of a version 2 journal are migrated without receipt
+    /// - Version 3 journals read the shared bucket back as explorer 4294967295
+    /// - Newer versions and malformed headers are rejected
+    #[test]
+    fn test_journal_migrates_older_versions() {
+        let journal =
+            Journal::read("rustrelli-journal 1 cells=5 charged=2\n1000 sunray\n".as_bytes())
+                .unwrap();
+        assert_eq!(
+            journal,
+            Journal {
+                version: 1,
+                written_by: None,
+                cells: 5,
+                charged: 2,
+                entries: vec![JournalEntry::Sunray {
+                    at: UNIX_EPOCH + Duration::from_secs(1),
+                }],
+            }
+        );
+
+        let journal = Journal::read(
+            "rustrelli-journal 2 crate=0.1.0 cells=5 charged=2\n1000 generate 3 Oxygen granted\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            journal.entries,
+            vec![JournalEntry::Generation {
+                at: UNIX_EPOCH + Duration::from_secs(1),
+                explorer_id: ExplorerId::new(3),
+                resource: BasicResourceType::Oxygen,
+                denial: None,
+                receipt: None,
+            }]
+        );
+
+        let journal = Journal::read(
+            "rustrelli-journal 3 crate=8.0.0 cells=5 charged=2\n1000 generate 4294967295 Oxygen untracked\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            journal.entries[0],
+            JournalEntry::Generation {
+                at: UNIX_EPOCH + Duration::from_secs(1),
+                explorer_id: ExplorerId::new(u32::MAX),
+                resource: BasicResourceType::Oxygen,
+                denial: Some(DenialReason::Untracked),
+                receipt: None,
+            }
+        );
+
+        assert_eq!(
+            Journal::read("rustrelli-journal 5 crate=9.0.0 cells=5 charged=2\n".as_bytes()),
+            Err(RustrelliError::Journal(
+                "Journal version 5 is newer than the supported version 4".to_string()
+            ))
+        );
+        for header in [
+            "rustrelli-journal 1 crate=0.1.0 cells=5 charged=2",
+            "rustrelli-journal 2 cells=5 charged=2",
+        ] {
+            assert_eq!(
+                Journal::read(header.as_bytes()),
+                Err(RustrelliError::Journal(format!(
+                    "Invalid journal header: {header}"
+                )))
+            );
+        }
+    }
+
+    /// **Scenario:** Compress a journal with a sync point every 3 lines, and read it
+    /// back finished, then cut short before it was finished
+    /// **Validates:**
+    /// - A finished journal round-trips through zstd
+    /// - An unfinished one reads up to its last sync point
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_journal_reads_up_to_last_sync_point() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let at = |millis| UNIX_EPOCH + Duration::from_millis(millis);
+        let mut writer = JournalWriter::new(Box::new(
+            ZstdJournal::new(Shared(buffer.clone()), 3)
+                .unwrap()
+                .with_sync_every(3),
+        ));
+        writer.start(5, 0).unwrap();
+        for millis in [1000, 2000, 3000] {
+            writer
+                .write(&JournalEntry::Sunray { at: at(millis) })
+                .unwrap();
+        }
+        let unfinished = buffer.lock().unwrap().clone();
+        drop(writer);
+        let finished = buffer.lock().unwrap().clone();
+
+        let sunrays =
+            |journal: Journal| -> Vec<_> { journal.entries.iter().map(JournalEntry::at).collect() };
+        let journal = Journal::read_zstd(finished.as_slice()).unwrap();
+        assert_eq!(sunrays(journal), vec![at(1000), at(2000), at(3000)]);
+        let journal = Journal::read_zstd(unfinished.as_slice()).unwrap();
+        assert_eq!(sunrays(journal), vec![at(1000), at(2000)]);
+    }
+}
@@ -0,0 +1,93 @@
+//! Energy cell leases module.
+//!
+//! Some game missions need a guaranteed burst of resources: a series of requests
+//! decided one by one may be interrupted by competitors at any point. When leases are
+//! enabled (see [`PlanetConfig::with_leases`](crate::PlanetConfig::with_leases)), the
+//! host can lease the next charged cells to an explorer for a bounded time, with
+//! [`AdminCommand::Lease`](crate::admin::AdminCommand::Lease) or
+//! [`AI::lease`](crate::planet::AI::lease).
+//!
+//! The limit policy decides the lease as a single request for all its cells, and
+//! accounts them at once. While the lease lasts, the leased cells are reserved for the
+//! holder: its generation requests for the leased resource claim them without being
+//! evaluated again, and requests of other explorers that could only be served with a
+//! leased cell are denied with [`DenialReason::Reserved`]. At most one lease is active
+//! at a time. When the lease expires, the cells not claimed return to the common pool,
+//! but stay accounted by the policy.
+
+use crate::ExplorerId;
+use crate::policy::DenialReason;
+use common_game::components::resource::BasicResourceType;
+use std::time::{Duration, SystemTime};
+
+/// Bounds of the leases the host can grant.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::lease::LeaseConfig;
+///
+/// // Leases of up to 3 cells, claimed within 10 seconds
+/// let leases = LeaseConfig::new(3, Duration::from_secs(10));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaseConfig {
+    /// Maximum number of cells of a lease. Larger leases are shrunk to it.
+    pub max_cells: u32,
+    /// Maximum duration of a lease. Longer leases are shortened to it.
+    pub max_duration: Duration,
+}
+
+impl LeaseConfig {
+    /// Creates a lease configuration of at most `max_cells` cells for at most
+    /// `max_duration`.
+    pub fn new(max_cells: u32, max_duration: Duration) -> Self {
+        LeaseConfig {
+            max_cells,
+            max_duration,
+        }
+    }
+}
+
+/// Why a lease was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LeaseRefusal {
+    /// Leases aren't enabled on the planet.
+    Disabled,
+    /// Another lease is active.
+    Active {
+        /// The explorer holding the active lease.
+        holder: ExplorerId,
+    },
+    /// The explorer is banned, the planet is paused, or the limit policy denied the
+    /// lease.
+    Denied(DenialReason),
+}
+
+/// Cells leased to an explorer and not claimed yet.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Lease {
+    pub(crate) explorer_id: ExplorerId,
+    /// Resource the leased cells are claimed for.
+    pub(crate) resource: BasicResourceType,
+    /// Cells left to claim. Each of them is backed by a reservation.
+    pub(crate) remaining: u32,
+    /// Time the unclaimed cells return to the common pool at.
+    pub(crate) expires: SystemTime,
+}
+
+impl Lease {
+    /// Whether a request of `explorer_id` for `resource` at `now` claims a leased cell.
+    pub(crate) fn claimed_by(
+        &self,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+        now: SystemTime,
+    ) -> bool {
+        self.explorer_id == explorer_id
+            && self.resource == resource
+            && self.remaining > 0
+            && now < self.expires
+    }
+}
@@ -16,15 +16,90 @@
 //! let planet = create_planet(1, rx_orch, tx_planet, rx_expl, ExplorerRequestLimit::None);
 //! ```
 
+pub mod admin;
+pub mod analyzer;
+pub mod backoff;
+pub mod batch;
+pub mod benchmark;
+pub mod budget;
+mod change;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod claim;
+pub mod clock;
+pub mod compliance;
+pub mod config;
+pub mod cost;
+pub mod delivery;
+pub mod error;
+pub mod events;
+pub mod explorer_id;
+pub mod fallback;
+pub mod fleet;
+pub mod gateway;
+pub mod handle;
+#[cfg(feature = "http-stats")]
+pub mod http_stats;
+pub mod info;
+pub mod invariants;
+pub mod journal;
+pub mod lease;
+#[cfg(feature = "metrics-facade")]
+pub mod metrics_facade;
+pub mod misconduct;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod pending;
 pub mod planet;
+pub mod policy;
+#[cfg(any(test, feature = "test-kit"))]
+pub mod policy_test_kit;
+pub mod prelude;
+pub mod priority;
+pub mod privacy;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+pub mod receipt;
+pub mod refusal;
+pub mod registration;
+mod rng;
+#[cfg(feature = "serde")]
+mod ser;
+pub mod simulation;
+pub mod stats;
+pub mod stockpile;
+pub mod storage;
+pub mod sunrays;
+pub mod supply;
+pub mod tags;
+pub mod timeline;
+mod timers;
+pub mod trace;
+pub mod watchdog;
+pub mod workers;
+
+pub use config::PlanetConfig;
+pub use error::RustrelliError;
+pub use explorer_id::ExplorerId;
+pub use handle::{PlanetChannels, PlanetHandle};
 
 use common_game::components::planet::{Planet, PlanetType};
-use common_game::components::resource::BasicResourceType;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
 use common_game::protocols::*;
 use common_game::utils::ID;
+use gateway::Gateway;
+use info::PlanetInfo;
 use planet::AI;
+use policy::Policy;
+use priority::PriorityRouter;
+use workers::{QueryWorkers, SharedView};
 
 use crossbeam_channel::{Receiver, Sender};
+use events::Event;
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, OnceLock};
+use std::thread;
 
 /// Creates and configures a Type D planet.
 ///
@@ -36,7 +111,12 @@ use crossbeam_channel::{Receiver, Sender};
 /// The planet is created with a custom AI that handles incoming messages from
 /// the orchestrator and explorers according to the Type D planet specifications.
 ///
+/// This signature is stable: existing orchestrator integrations keep calling it as
+/// the configuration options grow, each of them added to [`PlanetConfig`] and used
+/// through [`create_planet_with_config`] instead.
+///
 /// # Arguments
+/// * `id` - ID of the planet
 /// * `rx_orchestrator` - Receiver for messages from the orchestrator
 /// * `tx_orchestrator` - Sender for messages to the orchestrator
 /// * `rx_explorer` - Receiver for messages from explorers
@@ -74,37 +154,534 @@ pub fn create_planet(
     rx_explorer: Receiver<planet_explorer::ExplorerToPlanet>,
     request_limit: ExplorerRequestLimit,
 ) -> Planet {
-    let ai = AI::new(request_limit);
+    create_planet_with_config(
+        PlanetConfig::new(id).with_request_limit(request_limit),
+        rx_orchestrator,
+        tx_orchestrator,
+        rx_explorer,
+    )
+}
+
+/// Creates and configures a Type D planet using a custom [`PlanetConfig`].
+///
+/// The planet has the same characteristics of the one built by [`create_planet`],
+/// while the AI behavior is customized by `config`.
+///
+/// # Arguments
+/// * `config` - Planet ID and AI configuration
+/// * `rx_orchestrator` - Receiver for messages from the orchestrator
+/// * `tx_orchestrator` - Sender for messages to the orchestrator
+/// * `rx_explorer` - Receiver for messages from explorers
+///
+/// # Panics
+/// Panics with the [`RustrelliError`] describing the problem if `config` is invalid,
+/// e.g. with an explorer in two policy arms or a stockpile larger than the cells of the
+/// planet. Hosts building planets from untrusted configurations get the error back
+/// with [`create_planets`] or [`spawn_planets`] instead.
+///
+/// # Examples
+/// ```
+/// use crossbeam_channel::bounded;
+/// use rustrelli::{create_planet_with_config, ExplorerRequestLimit, PlanetConfig};
+/// use rustrelli::stats::{StatsConfig, StatsHandle};
+///
+/// let (tx_orch_to_planet, rx_orch_to_planet) = bounded(20);
+/// let (tx_planet_to_orch, rx_planet_to_orch) = bounded(20);
+/// let (tx_expl_to_planet, rx_expl_to_planet) = bounded(20);
+/// let stats = StatsHandle::new(StatsConfig::default());
+///
+/// let planet = create_planet_with_config(
+///     PlanetConfig::new(1)
+///         .with_request_limit(ExplorerRequestLimit::FairShare)
+///         .with_stats(stats.clone()),
+///     rx_orch_to_planet,
+///     tx_planet_to_orch,
+///     rx_expl_to_planet,
+/// );
+///
+/// assert_eq!(stats.snapshot().totals().sunrays, 0);
+/// ```
+pub fn create_planet_with_config(
+    config: PlanetConfig,
+    rx_orchestrator: Receiver<orchestrator_planet::OrchestratorToPlanet>,
+    tx_orchestrator: Sender<orchestrator_planet::PlanetToOrchestrator>,
+    rx_explorer: Receiver<planet_explorer::ExplorerToPlanet>,
+) -> Planet {
+    match try_create_planet(config, rx_orchestrator, tx_orchestrator, rx_explorer) {
+        Ok(planet) => planet,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+/// Creates a Type D planet using a custom [`PlanetConfig`], reporting an invalid
+/// configuration as an error.
+fn try_create_planet(
+    config: PlanetConfig,
+    rx_orchestrator: Receiver<orchestrator_planet::OrchestratorToPlanet>,
+    tx_orchestrator: Sender<orchestrator_planet::PlanetToOrchestrator>,
+    rx_explorer: Receiver<planet_explorer::ExplorerToPlanet>,
+) -> Result<Planet, RustrelliError> {
     let gen_rules = vec![
         BasicResourceType::Carbon,
         BasicResourceType::Silicon,
         BasicResourceType::Oxygen,
         BasicResourceType::Hydrogen,
     ];
-    let comb_rules = vec![];
+    build_planet(
+        config,
+        PlanetType::D,
+        gen_rules,
+        vec![],
+        (rx_orchestrator, tx_orchestrator, rx_explorer),
+    )
+}
+
+/// Creates a planet of any type, driven by the same AI as the Type D planets built
+/// by [`create_planet`].
+///
+/// The AI never builds rockets, so planets of types that could have one are destroyed
+/// by the first asteroid like Type D planets. Combination requests are served with a
+/// charged cell when `comb_rules` isn't empty, and limited by `request_limit` like the
+/// generation requests.
+///
+/// # Arguments
+/// * `id` - Planet ID
+/// * `planet_type` - Type of the planet, deciding its number of energy cells and rules
+/// * `gen_rules` - Basic resources the planet can generate
+/// * `comb_rules` - Complex resources the planet can combine
+/// * `request_limit` - Policy limiting the generation and combination requests of explorers
+/// * `channels` - Receiver for messages from the orchestrator, sender for messages to
+///   the orchestrator and receiver for messages from explorers
+///
+/// # Errors
+/// Returns a [`RustrelliError::Config`] if a generation rule is duplicated, and a
+/// [`RustrelliError::Construction`] if the rules aren't legal for `planet_type`.
+///
+/// # Examples
+/// ```
+/// use crossbeam_channel::unbounded;
+/// use common_game::components::planet::PlanetType;
+/// use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+/// use rustrelli::{ExplorerRequestLimit, create_planet_custom};
+///
+/// let (tx_orch_to_planet, rx_orch_to_planet) = unbounded();
+/// let (tx_planet_to_orch, rx_planet_to_orch) = unbounded();
+/// let (tx_expl_to_planet, rx_expl_to_planet) = unbounded();
+///
+/// let planet = create_planet_custom(
+///     1,
+///     PlanetType::A,
+///     vec![BasicResourceType::Oxygen],
+///     vec![],
+///     ExplorerRequestLimit::FairShare,
+///     (rx_orch_to_planet, tx_planet_to_orch, rx_expl_to_planet),
+/// );
+/// assert!(planet.is_ok());
+/// ```
+pub fn create_planet_custom(
+    id: ID,
+    planet_type: PlanetType,
+    gen_rules: Vec<BasicResourceType>,
+    comb_rules: Vec<ComplexResourceType>,
+    request_limit: impl Into<Policy>,
+    channels: (
+        Receiver<orchestrator_planet::OrchestratorToPlanet>,
+        Sender<orchestrator_planet::PlanetToOrchestrator>,
+        Receiver<planet_explorer::ExplorerToPlanet>,
+    ),
+) -> Result<Planet, RustrelliError> {
+    build_planet(
+        PlanetConfig::new(id).with_request_limit(request_limit),
+        planet_type,
+        gen_rules,
+        comb_rules,
+        channels,
+    )
+}
 
-    // Constructs the planet and returns it
-    match Planet::new(
+/// Creates a planet of any type driven by the AI configured by `config`. The statistics
+/// are only set up once the planet is built, so that a rejected configuration leaves
+/// them untouched.
+fn build_planet(
+    mut config: PlanetConfig,
+    planet_type: PlanetType,
+    gen_rules: Vec<BasicResourceType>,
+    comb_rules: Vec<ComplexResourceType>,
+    (rx_orchestrator, tx_orchestrator, rx_explorer): (
+        Receiver<orchestrator_planet::OrchestratorToPlanet>,
+        Sender<orchestrator_planet::PlanetToOrchestrator>,
+        Receiver<planet_explorer::ExplorerToPlanet>,
+    ),
+) -> Result<Planet, RustrelliError> {
+    let compliance = config.comply();
+    config.validate()?;
+    let mut unique = HashSet::new();
+    if let Some(duplicate) = gen_rules.iter().find(|rule| !unique.insert(**rule)) {
+        return Err(RustrelliError::Config(format!(
+            "Duplicate generation rule: {:?}",
+            duplicate
+        )));
+    }
+    if let Some((resource, _)) = config
+        .stockpile
+        .iter()
+        .find(|(resource, _)| !gen_rules.contains(resource))
+    {
+        return Err(RustrelliError::Config(format!(
+            "Stockpile of {:?}, missing from the generation rules",
+            resource
+        )));
+    }
+    let stocked: u32 = config.stockpile.iter().map(|(_, amount)| amount).sum();
+    let id = config.id;
+    let (gateway, rx_explorer, gateway_link) = match &config.gateway {
+        Some(gateway) => {
+            let (gateway, rx_planet, notices, reports) =
+                Gateway::new(config.id, gateway, config.drop_spoofed, rx_explorer);
+            (Some(gateway), rx_planet, Some((notices, reports)))
+        }
+        None => (None, rx_explorer, None),
+    };
+    let query_view = Arc::new(SharedView::default());
+    let (workers, rx_explorer) = match config.query_workers {
+        Some(workers) => {
+            let (pool, rx_planet) = QueryWorkers::new(
+                &config,
+                workers,
+                rx_explorer,
+                &gen_rules,
+                &comb_rules,
+                query_view.clone(),
+            );
+            (Some(pool), rx_planet)
+        }
+        None => (None, rx_explorer),
+    };
+    let (router, rx_orchestrator, rx_explorer) = PriorityRouter::new(
         id,
-        PlanetType::D,
+        config.orchestrator_priority,
+        config.fair_interleaving,
+        rx_orchestrator,
+        rx_explorer,
+    );
+    let info = PlanetInfo::new(
+        id,
+        planet_type,
+        &gen_rules,
+        &comb_rules,
+        config.request_limit.clone(),
+        compliance,
+    );
+    let stats = config.stats.clone();
+    let (cell_timeline, demand_heatmap) = (config.cell_timeline, config.demand_heatmap);
+    let mut ai = AI::from_config(config);
+    if workers.is_some() {
+        ai.share_query_view(query_view);
+    }
+    if let Some((notices, reports)) = gateway_link {
+        ai.link_gateway(notices, reports);
+    }
+
+    let planet = Planet::new(
+        id,
+        planet_type,
         Box::new(ai),
         gen_rules,
         comb_rules,
         (rx_orchestrator, tx_orchestrator),
         rx_explorer,
-    ) {
-        Ok(planet) => planet,
+    )
+    .map_err(RustrelliError::Construction)?;
+    let cells = planet.state().cells_count();
+    if stocked as usize > cells {
+        return Err(RustrelliError::Config(format!(
+            "Stockpile of {} units, more than the {} cells of the planet",
+            stocked, cells
+        )));
+    }
+    if let Some(gateway) = gateway {
+        gateway.start().map_err(RustrelliError::Construction)?;
+    }
+    if let Some(workers) = workers {
+        workers.start().map_err(RustrelliError::Construction)?;
+    }
+    if let Some(router) = router {
+        router.start().map_err(RustrelliError::Construction)?;
+    }
+    stats.update(|stats| {
+        stats.record_info(info);
+        if let Some(capacity) = cell_timeline {
+            stats.enable_cell_timeline(capacity);
+        }
+        if let Some((bucket_width, buckets)) = demand_heatmap {
+            stats.enable_demand_heatmap(bucket_width, buckets);
+        }
+    });
+    Ok(planet)
+}
+
+/// Creates a Type D planet using a custom [`PlanetConfig`], and runs it on a new thread
+/// named `rustrelli-planet-<id>`.
+///
+/// A panic of the planet thread is caught and reported as an [`Event::Panicked`] on the
+/// events channel of the configuration, if any, before ending the thread.
+///
+/// # Arguments
+/// * `config` - Planet ID and AI configuration
+/// * `channels` - Channels connecting the planet to the game
+///
+/// # Returns
+/// A [`PlanetHandle`] to operate the planet and wait for its thread. The handle has its
+/// own admin channel, in addition to the ones of the configuration.
+///
+/// # Panics
+/// Panics if the planet construction fails due to invalid configuration,
+/// or if the thread can't be spawned.
+///
+/// # Examples
+/// ```
+/// use crossbeam_channel::unbounded;
+/// use rustrelli::{PlanetChannels, PlanetConfig, spawn_planet};
+///
+/// let (tx_orch_to_planet, rx_orch_to_planet) = unbounded();
+/// let (tx_planet_to_orch, rx_planet_to_orch) = unbounded();
+/// let (tx_expl_to_planet, rx_expl_to_planet) = unbounded();
+///
+/// let handle = spawn_planet(
+///     PlanetConfig::new(1),
+///     PlanetChannels {
+///         from_orchestrator: rx_orch_to_planet,
+///         to_orchestrator: tx_planet_to_orch,
+///         from_explorers: rx_expl_to_planet,
+///         control: tx_orch_to_planet.clone(),
+///     },
+/// );
+///
+/// handle.kill();
+/// assert!(handle.join().is_ok());
+/// ```
+pub fn spawn_planet(config: PlanetConfig, channels: PlanetChannels) -> PlanetHandle {
+    match try_spawn_planet(config, channels) {
+        Ok(handle) => handle,
         Err(error) => panic!("{}", error),
     }
 }
 
+/// Creates a Type D planet and runs it on a new thread, reporting an invalid
+/// configuration or a failed thread spawn as an error.
+fn try_spawn_planet(
+    config: PlanetConfig,
+    channels: PlanetChannels,
+) -> Result<PlanetHandle, RustrelliError> {
+    let id = config.id;
+    let events = config.events.clone();
+    let stats = config.stats.clone();
+    let stats_watch = config.stats_watch.as_ref().map(|(watch, _)| watch.clone());
+    let (admin, admin_receiver) = crossbeam_channel::unbounded();
+    let config = config.with_admin(admin_receiver);
+    let PlanetChannels {
+        from_orchestrator,
+        to_orchestrator,
+        from_explorers,
+        control,
+    } = channels;
+    let mut planet = try_create_planet(config, from_orchestrator, to_orchestrator, from_explorers)?;
+    let panic = Arc::new(OnceLock::new());
+    let panicked = panic.clone();
+
+    let thread = thread::Builder::new()
+        .name(format!("rustrelli-planet-{id}"))
+        .spawn(move || {
+            // The planet is dropped right after a panic: it can't be observed broken
+            match panic::catch_unwind(AssertUnwindSafe(|| planet.run())) {
+                Ok(result) => result,
+                Err(payload) => {
+                    let message = handle::panic_message(payload.as_ref());
+                    events.emit(Event::Panicked {
+                        message: message.clone(),
+                    });
+                    let _ = panicked.set(message.clone());
+                    Err(message)
+                }
+            }
+        })
+        .map_err(|error| RustrelliError::Construction(error.to_string()))?;
+
+    Ok(PlanetHandle::new(
+        id,
+        thread,
+        control,
+        admin,
+        stats,
+        stats_watch,
+        panic,
+    ))
+}
+
+/// Creates a cluster of Type D planets, one for each configuration in `configs`,
+/// connected to the game by the channels at the same position in `channel_sets`.
+///
+/// Each planet is created independently: an invalid configuration, a planet ID
+/// already used by a previous configuration or missing channels are reported as an
+/// error at the position of the planet, without affecting the others.
+///
+/// # Examples
+/// ```
+/// use crossbeam_channel::unbounded;
+/// use rustrelli::{PlanetChannels, PlanetConfig, create_planets};
+///
+/// let channels = || {
+///     let (tx_orch_to_planet, rx_orch_to_planet) = unbounded();
+///     let (tx_planet_to_orch, _rx_planet_to_orch) = unbounded();
+///     let (_tx_expl_to_planet, rx_expl_to_planet) = unbounded();
+///     PlanetChannels {
+///         from_orchestrator: rx_orch_to_planet,
+///         to_orchestrator: tx_planet_to_orch,
+///         from_explorers: rx_expl_to_planet,
+///         control: tx_orch_to_planet,
+///     }
+/// };
+///
+/// let planets = create_planets(
+///     vec![PlanetConfig::new(1), PlanetConfig::new(1)],
+///     vec![channels(), channels()],
+/// );
+/// assert!(planets[0].is_ok());
+/// assert!(planets[1].is_err(), "Duplicate ID");
+/// ```
+pub fn create_planets(
+    configs: Vec<PlanetConfig>,
+    channel_sets: Vec<PlanetChannels>,
+) -> Vec<Result<Planet, RustrelliError>> {
+    create_each(configs, channel_sets, |config, channels| {
+        try_create_planet(
+            config,
+            channels.from_orchestrator,
+            channels.to_orchestrator,
+            channels.from_explorers,
+        )
+    })
+}
+
+/// Creates a cluster of Type D planets like [`create_planets`], and runs each of
+/// them on its own thread like [`spawn_planet`].
+///
+/// Planets that can't be created or spawned are reported as an error at their
+/// position, without affecting the others.
+pub fn spawn_planets(
+    configs: Vec<PlanetConfig>,
+    channel_sets: Vec<PlanetChannels>,
+) -> Vec<Result<PlanetHandle, RustrelliError>> {
+    create_each(configs, channel_sets, try_spawn_planet)
+}
+
+/// Name of the kind of `msg`, as reported in the telemetry.
+#[cfg(any(feature = "otel", feature = "metrics-facade"))]
+pub(crate) fn message_name(msg: &planet_explorer::ExplorerToPlanet) -> &'static str {
+    use planet_explorer::ExplorerToPlanet;
+
+    match msg {
+        ExplorerToPlanet::SupportedResourceRequest { .. } => "SupportedResourceRequest",
+        ExplorerToPlanet::SupportedCombinationRequest { .. } => "SupportedCombinationRequest",
+        ExplorerToPlanet::GenerateResourceRequest { .. } => "GenerateResourceRequest",
+        ExplorerToPlanet::CombineResourceRequest { .. } => "CombineResourceRequest",
+        ExplorerToPlanet::AvailableEnergyCellRequest { .. } => "AvailableEnergyCellRequest",
+        // Variants added to the protocol after this version
+        #[allow(unreachable_patterns)]
+        _ => "Unhandled",
+    }
+}
+
+/// Pairs each configuration with its channels and applies `create` to them,
+/// checking that planet IDs are unique.
+fn create_each<T>(
+    configs: Vec<PlanetConfig>,
+    channel_sets: Vec<PlanetChannels>,
+    mut create: impl FnMut(PlanetConfig, PlanetChannels) -> Result<T, RustrelliError>,
+) -> Vec<Result<T, RustrelliError>> {
+    let mut ids = HashSet::new();
+    let mut channel_sets = channel_sets.into_iter();
+
+    configs
+        .into_iter()
+        .map(|config| {
+            let id = config.id;
+            let channels = channel_sets
+                .next()
+                .ok_or_else(|| RustrelliError::Config(format!("No channels for planet {}", id)))?;
+            if !ids.insert(id) {
+                return Err(RustrelliError::Config(format!(
+                    "Duplicate planet ID: {}",
+                    id
+                )));
+            }
+            create(config, channels)
+        })
+        .collect()
+}
+
 /// Available explorer limiting modes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ExplorerRequestLimit {
     /// No limit to explorer requests.
     None,
     /// Tries to share energy cells usage equally between active explorers.
     /// Uses an algorithm similar to [Token Bucket](https://en.wikipedia.org/wiki/Token_bucket).
     FairShare,
+    /// [`FairShare`](Self::FairShare) with custom settings.
+    FairShareWith(policy::FairShareConfig),
+    /// Grants each explorer at most a fixed number of resources over a sliding time window.
+    Quota(Quota),
+    /// Grants at most a fixed number of resources over a sliding time window,
+    /// shared by all explorers.
+    GlobalCap(Quota),
+    /// Grants at most a fixed number of resources of each type over a sliding
+    /// time window, shared by all explorers.
+    ResourceCap(Quota),
+    /// Grants each explorer at most the given number of resources per game epoch.
+    /// Epochs are advanced by the host with [`AdminCommand::AdvanceEpoch`](admin::AdminCommand::AdvanceEpoch).
+    EpochBudget(u32),
+    /// [`EpochBudget`](Self::EpochBudget) with custom settings.
+    EpochBudgetWith(policy::EpochBudgetConfig),
+    /// Serves active explorers in turn, like a
+    /// [stride scheduler](https://en.wikipedia.org/wiki/Stride_scheduling): each grant
+    /// moves the pass of the explorer forward by a stride inversely proportional to its
+    /// weight, and only the active explorer with the lowest pass is granted.
+    Stride,
+    /// Experimental: auctions each charged cell between the active explorers, each
+    /// bidding the credits it accumulated while idle. The highest bidder wins the cell
+    /// and pays its whole bid; ties go to the explorer with the highest weight.
+    Auction,
+    /// Grants a request if the explorer saved up the credits to pay for it, credits
+    /// being earned over time up to a cap.
+    Credits(policy::CreditConfig),
+}
+
+/// Allowance of resources granted over a sliding time window.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::Quota;
+///
+/// // At most 3 resources every 10 seconds.
+/// let quota = Quota::new(3, Duration::from_secs(10));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Quota {
+    /// Maximum number of resources granted in the window.
+    pub max_grants: u32,
+    /// Length of the sliding window.
+    pub window: std::time::Duration,
+}
+
+impl Quota {
+    /// Creates a quota of `max_grants` resources every `window`.
+    pub fn new(max_grants: u32, window: std::time::Duration) -> Self {
+        Quota { max_grants, window }
+    }
 }
 
 #[cfg(test)]
@@ -217,4 +794,99 @@ mod tests {
         );
         assert!(!planet.state().has_rocket(), "No initial rocket");
     }
+
+    // ============================================================================
+    // Tests: Custom Planets
+    // ============================================================================
+
+    /// **Scenario:** Create planets of other types
+    /// **Validates:**
+    /// - A Type B planet gets the requested rules and a single energy cell
+    /// - Rules illegal for the type and duplicate rules are refused
+    #[test]
+    fn test_custom_planet_rules() {
+        let planet = create_planet_custom(
+            2,
+            PlanetType::B,
+            vec![BasicResourceType::Carbon, BasicResourceType::Oxygen],
+            vec![ComplexResourceType::Water],
+            ExplorerRequestLimit::FairShare,
+            create_test_channels(),
+        )
+        .expect("Type B planet is legal");
+        assert_eq!(planet.generator().all_available_recipes().len(), 2);
+        assert_eq!(planet.combinator().all_available_recipes().len(), 1);
+        assert_eq!(planet.state().cells_count(), 1, "Type B has 1 energy cell");
+
+        let two_rules = vec![BasicResourceType::Carbon, BasicResourceType::Oxygen];
+        let custom = |planet_type, gen_rules, comb_rules| {
+            create_planet_custom(
+                2,
+                planet_type,
+                gen_rules,
+                comb_rules,
+                ExplorerRequestLimit::None,
+                create_test_channels(),
+            )
+        };
+        assert!(
+            custom(PlanetType::A, two_rules, vec![]).is_err(),
+            "Type A has 1 rule"
+        );
+        assert!(
+            custom(
+                PlanetType::D,
+                vec![BasicResourceType::Carbon, BasicResourceType::Carbon],
+                vec![]
+            )
+            .is_err()
+        );
+        assert!(
+            custom(
+                PlanetType::B,
+                vec![BasicResourceType::Carbon],
+                vec![ComplexResourceType::Water, ComplexResourceType::Diamond]
+            )
+            .is_err(),
+            "Type B has 1 combination rule"
+        );
+    }
+
+    /// **Scenario:** Type D planets configured with valid and invalid stockpiles
+    /// **Validates:**
+    /// - A stockpile fitting in the 5 cells is accepted
+    /// - Empty amounts, duplicate resources and stockpiles exceeding the cells are
+    ///   refused as invalid configurations
+    #[test]
+    fn test_stockpile_validation() {
+        let stocked = |composition: &[(BasicResourceType, u32)]| {
+            let (rx_orch, tx_orch, rx_expl) = create_test_channels();
+            let config = PlanetConfig::new(1).with_stockpile(composition.iter().copied());
+            try_create_planet(config, rx_orch, tx_orch, rx_expl)
+        };
+        assert!(
+            stocked(&[
+                (BasicResourceType::Oxygen, 3),
+                (BasicResourceType::Carbon, 2)
+            ])
+            .is_ok()
+        );
+
+        for composition in [
+            &[(BasicResourceType::Oxygen, 0)][..],
+            &[
+                (BasicResourceType::Oxygen, 1),
+                (BasicResourceType::Oxygen, 1),
+            ],
+            &[
+                (BasicResourceType::Oxygen, 4),
+                (BasicResourceType::Carbon, 2),
+            ],
+        ] {
+            assert!(
+                matches!(stocked(composition), Err(RustrelliError::Config(_))),
+                "{composition:?} is refused"
+            );
+        }
+    }
 }
@@ -0,0 +1,150 @@
+//! `metrics` facade module, available with the `metrics-facade` feature.
+//!
+//! The planet AI publishes its numbers through the [`metrics`] crate macros, so that
+//! hosts already exporting metrics with a `metrics` recorder (statsd, Prometheus...) get
+//! them without reading the [statistics](crate::stats::Stats):
+//! - `rustrelli.generation.grants` and `rustrelli.generation.denials` counters, the
+//!   denials by reason
+//! - a `rustrelli.sunrays` counter
+//! - a `rustrelli.explorers.untracked` counter of the messages of explorers past the
+//!   cap of tracked explorers
+//! - a `rustrelli.energy.charged_cells` gauge, updated whenever the cells are observed
+//! - a `rustrelli.explorer_request.duration` histogram of the time taken to handle each
+//!   explorer message, in seconds, by kind of message
+//!
+//! Every metric is labeled with the ID of the planet, when known. The metrics go to the
+//! recorder installed when they're published: without one, they're a no-op.
+
+use crate::policy::DenialReason;
+use metrics::{Label, counter, gauge, histogram};
+use std::time::Duration;
+
+/// Label holding the ID of the planet.
+pub const PLANET_ID: &str = "planet";
+/// Label holding the kind of explorer message.
+pub const MESSAGE: &str = "message";
+/// Label holding why a generation request was denied.
+pub const DENIAL_REASON: &str = "reason";
+
+/// Publisher of the metrics of a planet AI.
+pub(crate) struct Metrics {
+    /// The planet ID label, if the planet ID is known.
+    planet: Vec<Label>,
+}
+
+impl Metrics {
+    pub(crate) fn new(planet_id: Option<u32>) -> Self {
+        Metrics {
+            planet: planet_id
+                .map(|id| Label::new(PLANET_ID, id.to_string()))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Counts a generation request: granted if `denial` is `None`.
+    pub(crate) fn record_generation(&self, denial: Option<DenialReason>) {
+        match denial {
+            None => counter!("rustrelli.generation.grants", self.planet.iter()).increment(1),
+            Some(reason) => {
+                let mut labels = self.planet.clone();
+                labels.push(Label::new(DENIAL_REASON, format!("{reason:?}")));
+                counter!("rustrelli.generation.denials", labels).increment(1);
+            }
+        }
+    }
+
+    /// Counts a cell preempted by a critical explorer.
+    pub(crate) fn record_preemption(&self) {
+        counter!("rustrelli.generation.preemptions", self.planet.iter()).increment(1);
+    }
+
+    /// Counts a message of an explorer past the cap of tracked explorers.
+    pub(crate) fn record_untracked(&self) {
+        counter!("rustrelli.explorers.untracked", self.planet.iter()).increment(1);
+    }
+
+    pub(crate) fn record_sunray(&self) {
+        counter!("rustrelli.sunrays", self.planet.iter()).increment(1);
+    }
+
+    /// Records the number of charged cells.
+    pub(crate) fn observe_charged_cells(&self, charged_cells: usize) {
+        gauge!("rustrelli.energy.charged_cells", self.planet.iter()).set(charged_cells as f64);
+    }
+
+    /// Records the time taken to handle an explorer message of kind `message`.
+    pub(crate) fn record_request(&self, message: &'static str, elapsed: Duration) {
+        let mut labels = self.planet.clone();
+        labels.push(Label::new(MESSAGE, message));
+        histogram!("rustrelli.explorer_request.duration", labels).record(elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the published metrics, captured by a debugging recorder.
+
+    use super::*;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use std::collections::HashMap;
+
+    // ============================================================================
+    // Tests: Metrics
+    // ============================================================================
+
+    /// **Scenario:** Publish a grant, two denials, a sunray, the cells and a request
+    /// of planet 4
+    /// **Validates:**
+    /// - Grants, denials and sunrays are counted, the denials by reason
+    /// - The gauge holds the last number of charged cells
+    /// - Every metric is labeled with the planet
+    #[test]
+    fn test_metrics_are_published_with_the_planet_label() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            let metrics = Metrics::new(Some(4));
+            metrics.record_generation(None);
+            metrics.record_generation(Some(DenialReason::NoEnergy));
+            metrics.record_generation(Some(DenialReason::NoEnergy));
+            metrics.record_sunray();
+            metrics.observe_charged_cells(5);
+            metrics.observe_charged_cells(3);
+            metrics.record_request("GenerateResourceRequest", Duration::from_millis(2));
+        });
+
+        let mut published: HashMap<String, (Vec<String>, DebugValue)> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels = key
+                    .key()
+                    .labels()
+                    .map(|label| format!("{}={}", label.key(), label.value()))
+                    .collect();
+                (key.key().name().to_string(), (labels, value))
+            })
+            .collect();
+        let mut take = |name: &str| published.remove(name).unwrap();
+        assert_eq!(
+            take("rustrelli.generation.grants"),
+            (vec!["planet=4".to_string()], DebugValue::Counter(1))
+        );
+        assert_eq!(
+            take("rustrelli.generation.denials"),
+            (
+                vec!["planet=4".to_string(), "reason=NoEnergy".to_string()],
+                DebugValue::Counter(2)
+            )
+        );
+        assert_eq!(take("rustrelli.sunrays").1, DebugValue::Counter(1));
+        assert_eq!(
+            take("rustrelli.energy.charged_cells").1,
+            DebugValue::Gauge(3.0.into())
+        );
+        let (labels, _) = take("rustrelli.explorer_request.duration");
+        assert_eq!(labels, ["planet=4", "message=GenerateResourceRequest"]);
+    }
+}
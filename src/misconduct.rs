@@ -0,0 +1,195 @@
+//! Explorer misconduct module.
+//!
+//! The planet sees evidence of misbehaving explorers that the orchestrator doesn't:
+//! requests claiming the ID of an unregistered explorer, retries ignoring the advised
+//! backoff, bursts of requests and resources requested over a channel the explorer
+//! doesn't drain. When misconduct scoring is enabled (see
+//! [`PlanetConfig::with_misconduct`](crate::PlanetConfig::with_misconduct)), each
+//! [`Signal`] adds its weight to the misconduct score of the explorer, kept in the
+//! statistics (see [`Stats::misconduct`](crate::stats::Stats::misconduct)), and an
+//! [`Event::Misconduct`](crate::events::Event::Misconduct) is reported when a score
+//! reaches the alert threshold.
+//!
+//! Scores are evidence, not penalties: the planet keeps serving the explorer as its
+//! policy decides, and game-level penalties are left to the host. Requests that raced
+//! the registration of their explorer are forgiven once it lands, like they're no
+//! longer counted as spoof attempts.
+
+use crate::ExplorerId;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// Kind of misbehavior observed by the planet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Signal {
+    /// A request claimed the ID of an explorer the orchestrator didn't register.
+    Spoof,
+    /// A generation request arrived before the retry-after time advised to the
+    /// explorer (see [`crate::backoff`]).
+    EarlyRetry,
+    /// A generation request arrived too soon after the previous one of the explorer.
+    Burst,
+    /// A generation request was refused because the resource couldn't have been
+    /// delivered to the explorer.
+    Undeliverable,
+}
+
+/// Weights of the misconduct signals, and when to alert the host.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::misconduct::{MisconductConfig, Signal};
+///
+/// // Ignore bursts, alert once an explorer scores 50
+/// let config = MisconductConfig::default()
+///     .with_weight(Signal::Burst, 0.0)
+///     .with_alert(50.0);
+/// assert_eq!(config.weight(Signal::Spoof), 5.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MisconductConfig {
+    spoof: f64,
+    early_retry: f64,
+    burst: f64,
+    undeliverable: f64,
+    /// Requests of an explorer closer than this to its previous one are bursts.
+    pub burst_interval: Duration,
+    /// Score at which the misconduct of an explorer is reported, if any.
+    pub alert: Option<f64>,
+}
+
+impl Default for MisconductConfig {
+    /// Weights spoof attempts 5, undeliverable requests 2, early retries 1 and bursts
+    /// 0.5, requests closer than 10ms being bursts, without alert.
+    fn default() -> Self {
+        MisconductConfig {
+            spoof: 5.0,
+            early_retry: 1.0,
+            burst: 0.5,
+            undeliverable: 2.0,
+            burst_interval: Duration::from_millis(10),
+            alert: None,
+        }
+    }
+}
+
+impl MisconductConfig {
+    /// Sets the weight of `signal`, 0 to ignore it.
+    ///
+    /// # Panics
+    /// Panics if `weight` is negative or not finite.
+    pub fn with_weight(mut self, signal: Signal, weight: f64) -> Self {
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "Misconduct weight must be finite and non-negative"
+        );
+        *match signal {
+            Signal::Spoof => &mut self.spoof,
+            Signal::EarlyRetry => &mut self.early_retry,
+            Signal::Burst => &mut self.burst,
+            Signal::Undeliverable => &mut self.undeliverable,
+        } = weight;
+        self
+    }
+
+    /// Counts the requests of an explorer closer than `interval` to its previous one
+    /// as bursts.
+    pub fn with_burst_interval(mut self, interval: Duration) -> Self {
+        self.burst_interval = interval;
+        self
+    }
+
+    /// Reports the misconduct of an explorer when its score reaches `score`.
+    ///
+    /// # Panics
+    /// Panics if `score` isn't greater than zero.
+    pub fn with_alert(mut self, score: f64) -> Self {
+        assert!(score > 0.0, "Alert score must be greater than zero");
+        self.alert = Some(score);
+        self
+    }
+
+    /// Weight of `signal`.
+    pub fn weight(&self, signal: Signal) -> f64 {
+        match signal {
+            Signal::Spoof => self.spoof,
+            Signal::EarlyRetry => self.early_retry,
+            Signal::Burst => self.burst,
+            Signal::Undeliverable => self.undeliverable,
+        }
+    }
+}
+
+/// Misbehavior observed from an explorer, see
+/// [`Stats::misconduct`](crate::stats::Stats::misconduct).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Misconduct {
+    /// Requests claiming the ID of the explorer while it wasn't registered.
+    pub spoofs: u64,
+    /// Generation requests before the advised retry-after time.
+    pub early_retries: u64,
+    /// Generation requests too soon after the previous one.
+    pub bursts: u64,
+    /// Generation requests refused as undeliverable.
+    pub undeliverable: u64,
+    /// Sum of the weights of the signals.
+    pub score: f64,
+}
+
+impl Misconduct {
+    /// Records `signal`, of weight `weight`, returning the new score.
+    pub(crate) fn record(&mut self, signal: Signal, weight: f64) -> f64 {
+        *match signal {
+            Signal::Spoof => &mut self.spoofs,
+            Signal::EarlyRetry => &mut self.early_retries,
+            Signal::Burst => &mut self.bursts,
+            Signal::Undeliverable => &mut self.undeliverable,
+        } += 1;
+        self.score += weight;
+        self.score
+    }
+
+    /// Forgets the spoof attempts, of weight `weight` each.
+    pub(crate) fn forgive_spoofs(&mut self, weight: f64) {
+        self.score = (self.score - self.spoofs as f64 * weight).max(0.0);
+        self.spoofs = 0;
+    }
+}
+
+/// Misconduct signals detected by the planet AI itself.
+#[derive(Debug)]
+pub(crate) struct MisconductTracker {
+    pub(crate) config: MisconductConfig,
+    /// When the latest generation request of each explorer arrived.
+    last_request: HashMap<ExplorerId, SystemTime>,
+}
+
+impl MisconductTracker {
+    pub(crate) fn new(config: MisconductConfig) -> Self {
+        MisconductTracker {
+            config,
+            last_request: HashMap::new(),
+        }
+    }
+
+    /// Records a generation request of `explorer_id` arrived at `now`, returning
+    /// whether it's a burst.
+    pub(crate) fn arrival(&mut self, explorer_id: ExplorerId, now: SystemTime) -> bool {
+        self.last_request
+            .insert(explorer_id, now)
+            .is_some_and(|last| {
+                now.duration_since(last)
+                    .is_ok_and(|elapsed| elapsed < self.config.burst_interval)
+            })
+    }
+
+    /// Whether a score going from `before` to `after` reaches the alert threshold.
+    pub(crate) fn alerts(&self, before: f64, after: f64) -> bool {
+        self.config
+            .alert
+            .is_some_and(|alert| before < alert && after >= alert)
+    }
+}
@@ -0,0 +1,208 @@
+//! OpenTelemetry module, available with the `otel` feature.
+//!
+//! The planet AI reports through the OpenTelemetry API, so that a planet appears in
+//! the same trace pipeline as the orchestrator hosting it:
+//! - a `rustrelli.explorer_request` span per explorer message, with the planet, the
+//!   explorer, the kind of message and, for generation requests, whether it was granted
+//! - `rustrelli.generation.grants` and `rustrelli.generation.denials` counters, the
+//!   denials by reason
+//! - a `rustrelli.energy.charged_cells` gauge, updated whenever the cells are observed
+//!
+//! The crate only depends on the API: the host installs the SDK providers and
+//! exporters of its choice with [`opentelemetry::global`], **before** creating the
+//! planets, since the instruments are taken from the global providers when a planet AI
+//! is created. Without providers, the telemetry is a no-op.
+
+use crate::message_name;
+use crate::policy::DenialReason;
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use opentelemetry::KeyValue;
+use opentelemetry::global::{self, BoxedSpan, BoxedTracer};
+use opentelemetry::metrics::{Counter, Gauge, Meter};
+use opentelemetry::trace::{Span, Tracer};
+
+/// Name of the tracer and meter of the planets.
+pub const INSTRUMENTATION_SCOPE: &str = "rustrelli";
+
+/// Attribute holding the ID of the planet.
+pub const PLANET_ID: &str = "rustrelli.planet.id";
+/// Attribute holding the ID of the explorer.
+pub const EXPLORER_ID: &str = "rustrelli.explorer.id";
+/// Attribute holding the kind of explorer message.
+pub const MESSAGE: &str = "rustrelli.message";
+/// Attribute holding whether a generation request was granted.
+pub const GRANTED: &str = "rustrelli.granted";
+/// Attribute holding why a generation request was denied.
+pub const DENIAL_REASON: &str = "rustrelli.denial_reason";
+
+/// Instruments of a planet AI.
+pub(crate) struct Telemetry {
+    tracer: BoxedTracer,
+    grants: Counter<u64>,
+    denials: Counter<u64>,
+    charged_cells: Gauge<u64>,
+    /// The planet ID attribute, if the planet ID is known.
+    planet: Vec<KeyValue>,
+}
+
+impl Telemetry {
+    /// Creates the instruments of planet `planet_id` from the global providers.
+    pub(crate) fn global(planet_id: Option<u32>) -> Self {
+        Self::new(
+            planet_id,
+            global::tracer(INSTRUMENTATION_SCOPE),
+            global::meter(INSTRUMENTATION_SCOPE),
+        )
+    }
+
+    fn new(planet_id: Option<u32>, tracer: BoxedTracer, meter: Meter) -> Self {
+        Telemetry {
+            tracer,
+            grants: meter
+                .u64_counter("rustrelli.generation.grants")
+                .with_description("Generation requests granted")
+                .build(),
+            denials: meter
+                .u64_counter("rustrelli.generation.denials")
+                .with_description("Generation requests denied, by reason")
+                .build(),
+            charged_cells: meter
+                .u64_gauge("rustrelli.energy.charged_cells")
+                .with_description("Charged energy cells")
+                .build(),
+            planet: planet_id
+                .map(|id| KeyValue::new(PLANET_ID, i64::from(id)))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Starts the span of the handling of `msg`, ended by [`Telemetry::end_request`].
+    pub(crate) fn start_request(&self, msg: &ExplorerToPlanet) -> BoxedSpan {
+        let mut span = self.tracer.start("rustrelli.explorer_request");
+        span.set_attributes(self.planet.iter().cloned());
+        span.set_attribute(KeyValue::new(EXPLORER_ID, i64::from(msg.explorer_id())));
+        span.set_attribute(KeyValue::new(MESSAGE, message_name(msg)));
+        span
+    }
+
+    /// Ends the span of a request, answered with `response` if any.
+    pub(crate) fn end_request(&self, mut span: BoxedSpan, response: Option<&PlanetToExplorer>) {
+        if let Some(PlanetToExplorer::GenerateResourceResponse { resource }) = response {
+            span.set_attribute(KeyValue::new(GRANTED, resource.is_some()));
+        }
+        span.end();
+    }
+
+    /// Counts a generation request: granted if `denial` is `None`.
+    pub(crate) fn record_generation(&self, denial: Option<DenialReason>) {
+        match denial {
+            None => self.grants.add(1, &self.planet),
+            Some(reason) => {
+                let mut attributes = self.planet.clone();
+                attributes.push(KeyValue::new(DENIAL_REASON, format!("{:?}", reason)));
+                self.denials.add(1, &attributes);
+            }
+        }
+    }
+
+    /// Records the number of charged cells.
+    pub(crate) fn observe_charged_cells(&self, charged_cells: usize) {
+        self.charged_cells
+            .record(charged_cells as u64, &self.planet);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the OpenTelemetry instruments, exported in memory.
+
+    use super::*;
+    use common_game::components::resource::BasicResourceType;
+    use opentelemetry::Value;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    // ============================================================================
+    // Tests: Instruments
+    // ============================================================================
+
+    /// **Scenario:** Report a granted and a denied generation request, and the cells
+    /// **Validates:**
+    /// - Each request has its span, with the planet, explorer and grant attributes
+    /// - Grants and denials are counted, the denials by reason
+    /// - The gauge holds the last number of charged cells
+    #[test]
+    fn test_requests_are_traced_and_counted() {
+        let spans = InMemorySpanExporter::default();
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_simple_exporter(spans.clone())
+            .build();
+        let metrics = InMemoryMetricExporter::default();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(metrics.clone()).build())
+            .build();
+        let telemetry = Telemetry::new(
+            Some(7),
+            BoxedTracer::new(Box::new(tracer_provider.tracer(INSTRUMENTATION_SCOPE))),
+            meter_provider.meter(INSTRUMENTATION_SCOPE),
+        );
+
+        let request = ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 3,
+            resource: BasicResourceType::Oxygen,
+        };
+        for denial in [None, Some(DenialReason::NoEnergy)] {
+            let span = telemetry.start_request(&request);
+            telemetry.record_generation(denial);
+            let response = PlanetToExplorer::GenerateResourceResponse { resource: None };
+            telemetry.end_request(span, Some(&response));
+        }
+        telemetry.observe_charged_cells(2);
+        telemetry.observe_charged_cells(4);
+
+        let spans = spans.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 2);
+        let attribute = |key: &str| {
+            spans[0]
+                .attributes
+                .iter()
+                .find(|attribute| attribute.key.as_str() == key)
+                .map(|attribute| attribute.value.clone())
+        };
+        assert_eq!(attribute(PLANET_ID), Some(Value::I64(7)));
+        assert_eq!(attribute(EXPLORER_ID), Some(Value::I64(3)));
+        assert_eq!(attribute(MESSAGE), Some("GenerateResourceRequest".into()));
+        assert_eq!(attribute(GRANTED), Some(Value::Bool(false)));
+
+        meter_provider.force_flush().unwrap();
+        let exported = metrics.get_finished_metrics().unwrap();
+        let metric = |name: &str| {
+            exported
+                .iter()
+                .flat_map(|resource| resource.scope_metrics())
+                .flat_map(|scope| scope.metrics())
+                .find(|metric| metric.name() == name)
+                .map(|metric| metric.data())
+                .unwrap_or_else(|| panic!("Metric {name} not exported"))
+        };
+        let sum = |name: &str| match metric(name) {
+            AggregatedMetrics::U64(MetricData::Sum(sum)) => {
+                sum.data_points().map(|point| point.value()).sum::<u64>()
+            }
+            other => panic!("Unexpected {name}: {:?}", other),
+        };
+        assert_eq!(sum("rustrelli.generation.grants"), 1);
+        assert_eq!(sum("rustrelli.generation.denials"), 1);
+        match metric("rustrelli.energy.charged_cells") {
+            AggregatedMetrics::U64(MetricData::Gauge(gauge)) => {
+                let values: Vec<_> = gauge.data_points().map(|point| point.value()).collect();
+                assert_eq!(values, vec![4]);
+            }
+            other => panic!("Unexpected gauge: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Deferred fulfillment module.
+//!
+//! Without deferred fulfillment, a generation request arriving while no energy cell
+//! is charged is simply refused. When enabled (see
+//! [`PlanetConfig::with_deferred_fulfillment`](crate::PlanetConfig::with_deferred_fulfillment)),
+//! the request is still answered right away with no resource, but it's also kept in a
+//! bounded pending queue: as soon as a sunray charges a cell, a pending request is
+//! admitted through the limit policy again and, if granted, the produced resource is
+//! sent to the host as a [`Fulfillment`], for delivery to the explorer (see
+//! [`crate::delivery`]).
+//!
+//! ## Coalescing
+//!
+//! Optionally (see [`PlanetConfig::with_pending_coalescing`](crate::PlanetConfig::with_pending_coalescing)),
+//! a request for the same resource type as a request of the same explorer already
+//! pending is coalesced into the pending one instead of taking another slot, so a
+//! single bot can't fill the whole queue with duplicates.
+//!
+//! ## Limits
+//!
+//! The queue holds at most its capacity, and optionally at most a share of it for each
+//! explorer (see [`PlanetConfig::with_pending_explorer_cap`](crate::PlanetConfig::with_pending_explorer_cap)),
+//! so that one bot can't take the whole capacity. A request offered beyond either
+//! limit is handled as set with
+//! [`PlanetConfig::with_pending_overflow`](crate::PlanetConfig::with_pending_overflow):
+//! dropped, or queued in place of the oldest pending request of the explorer, if it
+//! has any: an explorer never evicts the requests of others. The occupancy of each explorer is reported in
+//! [`Stats::pending_occupancy`](crate::stats::Stats::pending_occupancy).
+//!
+//! ## Timeout
+//!
+//! An explorer doesn't wait forever for a pending request: it may have given up and
+//! moved to another planet. When a timeout is set (see
+//! [`PlanetConfig::with_pending_timeout`](crate::PlanetConfig::with_pending_timeout)),
+//! requests pending for longer are dropped before a cell is spent on them, and each is
+//! reported as an [`Event::QueueTimeout`](crate::events::Event::QueueTimeout). A
+//! request coalesced into a pending one doesn't restart its wait: repeating a request
+//! doesn't keep it pending for longer.
+//!
+//! ## Ordering
+//!
+//! Pending requests are not served first-come-first-served, which would let the
+//! fastest bot win every cell. The next request served is the one the active policy
+//! prioritizes (e.g. the least served explorer under fair share). Explorers tied at the
+//! highest priority are drawn at random, each with a chance proportional to its tag
+//! weight, from a seeded generator (see
+//! [`PlanetConfig::with_tie_break_seed`](crate::PlanetConfig::with_tie_break_seed)),
+//! so that contended outcomes are unbiased and reproducible; the oldest request of the
+//! drawn explorer is served. Priorities are computed when a cell becomes available, so
+//! they always reflect the latest policy state.
+
+use crate::ExplorerId;
+use crate::receipt::ReceiptId;
+use common_game::components::resource::{BasicResource, BasicResourceType};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// A resource produced for a request that was pending, to be delivered to the explorer.
+#[derive(Debug)]
+pub struct Fulfillment {
+    /// The explorer that requested the resource.
+    pub explorer_id: ExplorerId,
+    /// The produced resource.
+    pub resource: BasicResource,
+    /// Receipt of the grant (see [`crate::receipt`]).
+    pub receipt: ReceiptId,
+}
+
+/// What happens to a request offered to a full pending queue, or beyond the share of
+/// its explorer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// The offered request is dropped.
+    #[default]
+    RejectNewest,
+    /// The oldest pending request of the explorer is dropped to make room for the
+    /// offered one. The offered request is dropped if the explorer has none.
+    DropOldest,
+}
+
+/// A generation request waiting for a charged energy cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PendingRequest {
+    pub(crate) explorer_id: ExplorerId,
+    pub(crate) resource: BasicResourceType,
+    /// When the request was queued. Requests coalesced into it don't change it.
+    pub(crate) queued_at: SystemTime,
+}
+
+/// What happened to a request offered to the [`PendingQueue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Queued {
+    /// The request took a new slot.
+    Added,
+    /// The request was merged into an identical pending one.
+    Coalesced,
+    /// The request took the slot of an older pending request, dropped.
+    Evicted {
+        /// The explorer of the dropped request.
+        explorer_id: ExplorerId,
+    },
+    /// The queue, or the share of the explorer, is full: the request was dropped.
+    Dropped,
+}
+
+/// Bounded queue of the generation requests waiting for a charged energy cell.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct PendingQueue {
+    capacity: usize,
+    /// Whether identical requests of the same explorer are coalesced.
+    pub(crate) coalesce: bool,
+    /// Maximum pending requests of each explorer, if limited.
+    pub(crate) explorer_cap: Option<usize>,
+    pub(crate) overflow: Overflow,
+    /// Time after which a pending request is dropped, if any.
+    pub(crate) timeout: Option<Duration>,
+    /// Pending requests, oldest first.
+    entries: Vec<PendingRequest>,
+    /// Pending requests of each explorer with any.
+    occupancy: HashMap<ExplorerId, usize>,
+}
+
+impl PendingQueue {
+    pub(crate) fn new(capacity: usize) -> Self {
+        PendingQueue {
+            capacity,
+            coalesce: false,
+            explorer_cap: None,
+            overflow: Overflow::default(),
+            timeout: None,
+            entries: Vec::with_capacity(capacity),
+            occupancy: HashMap::new(),
+        }
+    }
+
+    /// Queues a request of `explorer_id` for `resource`, arrived at `now`.
+    pub(crate) fn push(
+        &mut self,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+        now: SystemTime,
+    ) -> Queued {
+        if self.coalesce
+            && self
+                .entries
+                .iter()
+                .any(|entry| entry.explorer_id == explorer_id && entry.resource == resource)
+        {
+            return Queued::Coalesced;
+        }
+        let share_full = self
+            .explorer_cap
+            .is_some_and(|cap| self.occupancy(explorer_id) >= cap);
+        let evicted = if share_full || self.entries.len() >= self.capacity {
+            let oldest = match self.overflow {
+                Overflow::RejectNewest => None,
+                Overflow::DropOldest => self
+                    .entries
+                    .iter()
+                    .position(|entry| entry.explorer_id == explorer_id),
+            };
+            let Some(index) = oldest else {
+                return Queued::Dropped;
+            };
+            Some(self.take(index).explorer_id)
+        } else {
+            None
+        };
+
+        self.entries.push(PendingRequest {
+            explorer_id,
+            resource,
+            queued_at: now,
+        });
+        *self.occupancy.entry(explorer_id).or_default() += 1;
+        match evicted {
+            Some(explorer_id) => Queued::Evicted { explorer_id },
+            None => Queued::Added,
+        }
+    }
+
+    /// Number of pending requests of `explorer_id`.
+    pub(crate) fn occupancy(&self, explorer_id: ExplorerId) -> usize {
+        self.occupancy.get(&explorer_id).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn entries(&self) -> &[PendingRequest] {
+        &self.entries
+    }
+
+    /// Removes all the entries.
+    pub(crate) fn clear(&mut self) {
+        self.occupancy.clear();
+        self.entries.clear();
+    }
+
+    /// Removes and returns the requests pending for longer than the timeout at `now`.
+    pub(crate) fn expire(&mut self, now: SystemTime) -> Vec<PendingRequest> {
+        let Some(timeout) = self.timeout else {
+            return Vec::new();
+        };
+        let mut expired = Vec::new();
+        let mut index = 0;
+        while index < self.entries.len() {
+            let waited = now
+                .duration_since(self.entries[index].queued_at)
+                .unwrap_or_default();
+            if waited > timeout {
+                expired.push(self.take(index));
+            } else {
+                index += 1;
+            }
+        }
+        expired
+    }
+
+    /// Checks that the occupancy of the explorers adds up to the pending requests, within
+    /// the capacity (see [`Invariant::QueueAccounting`](crate::invariants::Invariant::QueueAccounting)).
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let occupancy: usize = self.occupancy.values().sum();
+        if occupancy != self.entries.len() || self.entries.len() > self.capacity {
+            return Err(format!(
+                "{} pending requests, {occupancy} counted by explorer, capacity {}",
+                self.entries.len(),
+                self.capacity
+            ));
+        }
+        Ok(())
+    }
+
+    /// Removes and returns the entry at `index`, as returned by [`Self::entries`].
+    pub(crate) fn take(&mut self, index: usize) -> PendingRequest {
+        let entry = self.entries.remove(index);
+        if let Some(count) = self.occupancy.get_mut(&entry.explorer_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.occupancy.remove(&entry.explorer_id);
+            }
+        }
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the pending queue limits.
+
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn explorers(queue: &PendingQueue) -> Vec<ExplorerId> {
+        queue
+            .entries()
+            .iter()
+            .map(|entry| entry.explorer_id)
+            .collect()
+    }
+
+    // ============================================================================
+    // Tests: Limits
+    // ============================================================================
+
+    /// **Scenario:** Queue of 3 requests, at most 2 per explorer, rejecting the newest
+    /// **Validates:**
+    /// - Requests beyond the share of the explorer are dropped, leaving room for others
+    /// - Requests beyond the capacity are dropped
+    #[test]
+    fn test_reject_newest() {
+        let mut queue = PendingQueue::new(3);
+        queue.explorer_cap = Some(2);
+
+        assert_eq!(
+            queue.push(ExplorerId::new(1), BasicResourceType::Carbon, at(0)),
+            Queued::Added
+        );
+        assert_eq!(
+            queue.push(ExplorerId::new(1), BasicResourceType::Oxygen, at(0)),
+            Queued::Added
+        );
+        assert_eq!(
+            queue.push(ExplorerId::new(1), BasicResourceType::Silicon, at(0)),
+            Queued::Dropped
+        );
+        assert_eq!(
+            queue.push(ExplorerId::new(2), BasicResourceType::Carbon, at(0)),
+            Queued::Added
+        );
+        assert_eq!(
+            queue.push(ExplorerId::new(3), BasicResourceType::Carbon, at(0)),
+            Queued::Dropped
+        );
+        assert_eq!(explorers(&queue), [1, 1, 2]);
+        assert_eq!(queue.occupancy(ExplorerId::new(1)), 2);
+    }
+
+    /// **Scenario:** Queue of 3 requests, at most 2 per explorer, dropping the oldest
+    /// **Validates:**
+    /// - A request beyond the share of the explorer replaces its own oldest request
+    /// - A request beyond the capacity replaces the oldest request of its explorer, or
+    ///   is dropped if the explorer has none
+    #[test]
+    fn test_drop_oldest() {
+        let mut queue = PendingQueue::new(3);
+        queue.explorer_cap = Some(2);
+        queue.overflow = Overflow::DropOldest;
+
+        queue.push(ExplorerId::new(2), BasicResourceType::Carbon, at(0));
+        queue.push(ExplorerId::new(1), BasicResourceType::Carbon, at(0));
+        queue.push(ExplorerId::new(1), BasicResourceType::Oxygen, at(0));
+        assert_eq!(
+            queue.push(ExplorerId::new(1), BasicResourceType::Silicon, at(0)),
+            Queued::Evicted {
+                explorer_id: ExplorerId::new(1)
+            }
+        );
+        assert_eq!(explorers(&queue), [2, 1, 1]);
+        assert_eq!(queue.entries()[1].resource, BasicResourceType::Oxygen);
+
+        assert_eq!(
+            queue.push(ExplorerId::new(3), BasicResourceType::Carbon, at(0)),
+            Queued::Dropped
+        );
+        assert_eq!(
+            queue.push(ExplorerId::new(2), BasicResourceType::Oxygen, at(0)),
+            Queued::Evicted {
+                explorer_id: ExplorerId::new(2)
+            }
+        );
+        assert_eq!(explorers(&queue), [1, 1, 2]);
+        assert_eq!(queue.entries()[2].resource, BasicResourceType::Oxygen);
+        assert_eq!(queue.occupancy(ExplorerId::new(3)), 0);
+    }
+
+    // ============================================================================
+    // Tests: Timeout
+    // ============================================================================
+
+    /// **Scenario:** Requests queued at 0 and 5, coalesced into at 8, with a timeout
+    /// of 10 seconds
+    /// **Validates:**
+    /// - Only the requests pending for longer than the timeout expire
+    /// - Coalescing doesn't restart the wait
+    #[test]
+    fn test_expire() {
+        let mut queue = PendingQueue::new(3);
+        queue.coalesce = true;
+        queue.timeout = Some(Duration::from_secs(10));
+
+        queue.push(ExplorerId::new(1), BasicResourceType::Carbon, at(0));
+        queue.push(ExplorerId::new(2), BasicResourceType::Carbon, at(0));
+        queue.push(ExplorerId::new(3), BasicResourceType::Carbon, at(5));
+        assert_eq!(
+            queue.push(ExplorerId::new(2), BasicResourceType::Carbon, at(8)),
+            Queued::Coalesced
+        );
+
+        assert!(queue.expire(at(10)).is_empty());
+        let expired: Vec<ExplorerId> = queue
+            .expire(at(11))
+            .iter()
+            .map(|entry| entry.explorer_id)
+            .collect();
+        assert_eq!(expired, [1, 2]);
+        assert_eq!(explorers(&queue), [3]);
+        assert_eq!(queue.occupancy(ExplorerId::new(1)), 0);
+    }
+}
@@ -18,163 +18,2961 @@
 //! Planned enhancements include:
 //! - (TESTING) Fair-share resource generation between explorers + (WIP) Priority-based explorer request handling
 //! - (TO BE DEFINED) Speculative resource generation to prevent sunray waste
-//!   (e.g. in place resource generation when all cells are currently full based on the most requested type of resource by explorers to preemptively help them)
+//!   (e.g. in place resource generation when all cells are currently full based on the type of resource the active explorers want the most,
+//!   see [`Stats::wanted_resource`](crate::stats::Stats::wanted_resource), to preemptively help them)
+//!
+//! ## Ordering guarantees
+//!
+//! Explorers contending for the same cells get the same outcome from the same
+//! sequence of messages, across runs and releases. The AI handles one message at a
+//! time, to completion, and each decision only depends on the messages handled before
+//! it, the planet clock and the configured seeds, never on the iteration order of its
+//! maps:
+//! - **Messages** are handled in the order the planet loop hands them over. Which of
+//!   the orchestrator and explorer channels goes first is left to `common_game`,
+//!   unless a router orders them (see [`crate::priority`]), and the explorer messages
+//!   keep the order of the shared channel, unless interleaved fairly.
+//! - **Generation requests** are refused by the first failing check, in order: an
+//!   untracked explorer (see [`crate::registration`]), a retry before the advised time,
+//!   a ban, an unsupported or disabled resource, a pause, no cell left once the
+//!   reserved ones are set aside, an undeliverable response, then the limit policy. A
+//!   cell is spent by the first request handled that passes them.
+//! - **Reserved cells** go to their holder whatever its policy: the rest of a batch
+//!   first, then a confirmed offer, then the lease. Cells freed by an expired
+//!   reservation return to the first request handled after it expires.
+//! - **Composite policies** evaluate and record their members in the order they were
+//!   listed (see [`Policy::AllOf`](crate::policy::Policy::AllOf)).
+//! - **Pending requests** are served holders of a reservation first, oldest reservation
+//!   first, then by policy priority, the explorers tied at the highest priority drawn
+//!   by weight from the tie-break seed, the oldest request of an explorer first (see
+//!   [`pending`](crate::pending#ordering)).
+//! - **Expiries** of bans, batches, offers, leases and pending requests are handled
+//!   before the message they're due at, by deadline, ties in the order they were
+//!   scheduled.
+//! - **Events and journal entries** are emitted in the order of the changes they
+//!   report.
+//!
+//! [`Simulation`](crate::simulation::Simulation)s resolve simultaneous arrivals the
+//! same way on every run, and the integration tests check that repeated runs of
+//! random contended sequences serve the same explorers in the same order.
+
+use crate::ExplorerId;
+use crate::admin::{AdminCommand, PauseMode};
+use crate::backoff::Backoffs;
+use crate::batch::{Batch, BatchConfig};
+use crate::benchmark::{self, BenchmarkReport, SYNTHETIC_EXPLORERS};
+use crate::budget::{Extra, LatencyBudget};
+use crate::change::{Change, Replica};
+#[cfg(feature = "chaos")]
+use crate::chaos::Chaos;
+use crate::claim::{ClaimConfig, Offer};
+use crate::clock::{GuardedClock, SystemClock};
+use crate::cost::{CostModel, FixedCosts, MessageKind};
+use crate::delivery::Outbox;
+use crate::events::{DeliveryFailure, Event, EventSink, LifecycleStage, Severity, ShutdownReport};
+use crate::fallback::{self, FallbackHandler, NoResponse};
+use crate::gateway::{Notice, SpoofReport};
+use crate::invariants::{self, EnergyLedger, Invariant};
+use crate::journal::{JournalEntry, JournalWriter};
+use crate::lease::{Lease, LeaseConfig, LeaseRefusal};
+#[cfg(feature = "metrics-facade")]
+use crate::message_name;
+#[cfg(feature = "metrics-facade")]
+use crate::metrics_facade::Metrics;
+use crate::misconduct::{MisconductTracker, Signal};
+#[cfg(feature = "otel")]
+use crate::otel::Telemetry;
+use crate::pending::{Fulfillment, PendingQueue, PendingRequest, Queued};
+use crate::policy::{
+    Decision, DecisionTrace, DenialReason, Policy, PolicyArm, PolicyState, Request,
+    RequestLimitPolicy,
+};
+#[cfg(feature = "profiling")]
+use crate::profiling::{Handler, Timer};
+use crate::receipt::{Receipt, ReceiptId};
+use crate::refusal::{CodedRefusals, RefusalFormatter, RefusalReason};
+use crate::registration::{
+    ExplorerTracker, History, Onboarding, Reregistration, Transition, Untracked,
+};
+use crate::rng::SplitMix64;
+use crate::stats::{BASIC_RESOURCES, Receipts, ScoreHistogram, Stats, StatsHandle, StatsWatch};
+use crate::stockpile::StockpileVisibility;
+use crate::storage::{self, StorageBackend};
+use crate::supply::{SunrayEstimator, SunrayRate};
+use crate::tags::{Tag, TagRegistry};
+use crate::timeline::{CellChange, CellEvent, CellTimeline, Product, Provenance, Source};
+use crate::timers::{Expiry, TimerWheel};
+use crate::workers::{SharedView, ViewHandle};
+use crate::{ExplorerRequestLimit, PlanetConfig, Quota};
+use common_game::components::energy_cell::EnergyCell;
+use common_game::components::planet::{DummyPlanetState, PlanetAI, PlanetState};
+use common_game::components::resource::{
+    BasicResource, BasicResourceType, Combinator, ComplexResource, ComplexResourceRequest,
+    ComplexResourceType, Generator, GenericResource,
+};
+use common_game::components::rocket::Rocket;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use crossbeam_channel::{Receiver, Sender};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+// features:
+// - user of the planet can choose between: fair-share resource generation between explorers or
+//   explorers priority list to assign priority levels to each explorer -> planet tracks explorer requests to estimate resources usage
+// - [probably cheating by game rules] speculative resource generation to prevent sunray waste (all cells are full),
+//   based on generation requests history of specific explorers.
+
+/// Explorers limited by their own policy, built from a [`PolicyArm`].
+#[derive(Debug)]
+struct Arm {
+    name: String,
+    policy: Box<dyn RequestLimitPolicy>,
+}
+
+/// Individual quota assigned by the host, and the policy enforcing it.
+#[derive(Debug)]
+struct Override {
+    quota: Quota,
+    policy: Box<dyn RequestLimitPolicy>,
+}
+
+/// Planet-wide policy warming up before it replaces the enforced one, see
+/// [`PlanetConfig::with_policy_warm_up`](crate::PlanetConfig::with_policy_warm_up).
+#[derive(Debug)]
+struct Incoming {
+    limit_mode: Policy,
+    policy: Box<dyn RequestLimitPolicy>,
+    /// When the policy starts being enforced.
+    enforced_from: SystemTime,
+}
+
+/// Resources the planet can generate and combine. They never change once the planet
+/// is created, so they are computed once instead of on every capability query.
+#[derive(PartialEq)]
+struct Capabilities {
+    resources: HashSet<BasicResourceType>,
+    combinations: HashSet<ComplexResourceType>,
+}
+
+impl Capabilities {
+    fn new(generator: &Generator, combinator: &Combinator) -> Self {
+        Capabilities {
+            resources: generator.all_available_recipes(),
+            combinations: combinator.all_available_recipes(),
+        }
+    }
+}
+
+pub struct AI {
+    limit_mode: Policy,
+    policy: Box<dyn RequestLimitPolicy>,
+    /// Policy evaluated on every request without being enforced.
+    shadow: Option<Box<dyn RequestLimitPolicy>>,
+    /// Planet-wide policy observing the requests until it replaces `policy`.
+    incoming: Option<Incoming>,
+    arms: Vec<Arm>,
+    /// Index in `arms` of the arm each assigned explorer belongs to.
+    arm_of: HashMap<ExplorerId, usize>,
+    /// Individual quotas assigned by the host, replacing the arm policy of their explorer.
+    overrides: HashMap<ExplorerId, Override>,
+    tags: TagRegistry,
+    /// Requests waiting for a charged cell, if deferred fulfillment is enabled.
+    pending: Option<PendingQueue>,
+    /// Delivery of the resources produced for pending requests.
+    outbox: Option<Outbox>,
+    /// How generation requests are handled while paused by the host, if paused.
+    paused: Option<PauseMode>,
+    /// Time allowed to drain the work left when stopped, if graceful shutdown is enabled.
+    drain_timeout: Option<Duration>,
+    /// Explorers holding a reservation on a charged cell, oldest first.
+    /// An explorer holds one reservation per occurrence.
+    reservations: VecDeque<ExplorerId>,
+    batch_config: Option<BatchConfig>,
+    /// Open batches, by explorer.
+    batches: HashMap<ExplorerId, Batch>,
+    claim_config: Option<ClaimConfig>,
+    /// Cells offered and not confirmed yet, by explorer.
+    offers: HashMap<ExplorerId, Offer>,
+    /// Draws the pending requests served among the explorers tied at the same priority.
+    tie_break: SplitMix64,
+    /// Tag of the explorers preempting offered and batched cells, if enabled.
+    preemption: Option<Tag>,
+    lease_config: Option<LeaseConfig>,
+    /// The active lease, if any.
+    lease: Option<Lease>,
+    /// Retry-after advice given to the denied explorers, if backoff is enabled.
+    backoffs: Option<Backoffs>,
+    /// Misconduct signals detected so far, if misconduct is scored.
+    misconduct: Option<MisconductTracker>,
+    /// Clones of the explorer channels senders, to check whether they are full.
+    explorer_channels: HashMap<ExplorerId, Sender<PlanetToExplorer>>,
+    /// Explorers reported unreachable by the host.
+    unreachable: HashSet<ExplorerId>,
+    /// Explorers banned by the host, and when their ban expires if it does.
+    banned: HashMap<ExplorerId, Option<SystemTime>>,
+    /// Resources disabled by the host, and when they are enabled again if they are.
+    disabled_resources: HashMap<BasicResourceType, Option<SystemTime>>,
+    /// Explorers whose channel the orchestrator registered on the planet.
+    registered: HashSet<ExplorerId>,
+    /// Explorers the orchestrator registered at least once.
+    known: HashSet<ExplorerId>,
+    /// Cost of the requests dropped by the explorer gateway, by never registered
+    /// explorer, charged to its policy if it's registered later.
+    early_costs: HashMap<ExplorerId, f32>,
+    /// Where the registrations are notified to the explorer gateway, if any.
+    gateway_notices: Option<Sender<Notice>>,
+    /// Spoof attempts reported by the explorer gateway, if any.
+    spoof_reports: Option<Receiver<SpoofReport>>,
+    /// Explorers tracked so far, if their number is capped.
+    tracker: Option<ExplorerTracker>,
+    /// Time allowed for the optional work on each explorer message, if bounded.
+    budget: Option<LatencyBudget>,
+    /// Whether registrations are reported with the rules of engagement.
+    onboarding: bool,
+    /// Whether grants are reported with their receipt.
+    receipt_events: bool,
+    /// Receipts issued so far.
+    receipts: u64,
+    reregistration: Reregistration,
+    events: EventSink,
+    /// Charged energy cells, as last observed.
+    charged_cells: usize,
+    stats: StatsHandle,
+    admin: Vec<Receiver<AdminCommand>>,
+    /// Renders the reasons of refused combinations for the explorers.
+    refusals: Box<dyn RefusalFormatter>,
+    /// Answers the explorer messages the AI doesn't handle.
+    fallback: Box<dyn FallbackHandler>,
+    /// Prices the messages charged to the limit policies.
+    costs: Box<dyn CostModel>,
+    /// Minimum time between two answers to the energy polls of an explorer, if throttled.
+    energy_poll_interval: Option<Duration>,
+    /// When the latest energy poll of each explorer was answered, if throttled.
+    energy_polls: HashMap<ExplorerId, SystemTime>,
+    /// Window the load is reported over after each sunray, if load events are enabled.
+    load_window: Option<Duration>,
+    /// Size of the leaderboard reported after each sunray, if leaderboard events are
+    /// enabled.
+    leaderboard_size: Option<usize>,
+    /// Energy received and spent, to check its conservation.
+    energy: EnergyLedger,
+    /// State rebuilt from the changes applied since the start, to check that they
+    /// cover it, if the invariants are checked.
+    replica: Option<Replica>,
+    /// Denials in a row of each explorer whose latest request was denied.
+    streaks: HashMap<ExplorerId, u32>,
+    /// Streak length reported with an [`Event::DenialStreak`], if enabled.
+    streak_alert: Option<u32>,
+    /// Where the statistics are published, and how often.
+    stats_watch: Option<(StatsWatch, Duration)>,
+    /// When the statistics were last published.
+    last_published: Option<SystemTime>,
+    /// How often the housekeeping is done, if periodically.
+    housekeeping: Option<Duration>,
+    /// When the housekeeping was last done.
+    last_housekeeping: Option<SystemTime>,
+    /// Expiries of the bans, leases, offers... see the [`timers`](crate::timers) module.
+    timers: TimerWheel,
+    /// Duration of the warm-up, if any.
+    warm_up: Option<Duration>,
+    /// End of the warm-up, set when the AI starts.
+    warm_up_until: Option<SystemTime>,
+    /// Duration policies replaced at runtime warm up for, if any.
+    policy_warm_up: Option<Duration>,
+    /// Grants and epochs of the last `policy_warm_up`, seeding the incoming policies.
+    recent: VecDeque<JournalEntry>,
+    /// Stockpile to seed before the first message, emptied once seeded.
+    stockpile: Vec<(BasicResourceType, u32)>,
+    /// Which answers about the available energy count the stockpile not served yet.
+    stockpile_visibility: StockpileVisibility,
+    /// Seeded cells still charged, with the resource they were seeded for.
+    seeded_cells: HashMap<usize, BasicResourceType>,
+    /// When the game time started, with the AI.
+    game_start: Option<SystemTime>,
+    sunrays: SunrayEstimator,
+    /// Cached when the AI starts.
+    capabilities: Option<Capabilities>,
+    /// Bumped each time the capabilities are computed with a different outcome, zero
+    /// until they are first computed.
+    capability_generation: u64,
+    /// Watched explorer channels, running state and charged cells, shared with the
+    /// query workers if any.
+    query_view: Option<ViewHandle>,
+    /// Source of the current time.
+    clock: GuardedClock,
+    /// Whether the changes of the energy cells are recorded in the statistics.
+    cell_timeline: bool,
+    journal: Option<JournalWriter>,
+    /// Where the journal and the final snapshots are persisted.
+    storage: Option<Box<dyn StorageBackend>>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Chaos>,
+    #[cfg(feature = "otel")]
+    otel: Telemetry,
+    #[cfg(feature = "metrics-facade")]
+    metrics: Metrics,
+}
+
+impl fmt::Debug for AI {
+    /// Shows the limit policies with their current state, and the state of the planet
+    /// as seen by the AI. The hooks set by the host (clock, cost model, refusal
+    /// formatter, journal...) are left out.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AI")
+            .field("limit_mode", &self.limit_mode)
+            .field("policy", &self.policy)
+            .field("shadow", &self.shadow)
+            .field("incoming", &self.incoming)
+            .field("arms", &self.arms)
+            .field("overrides", &self.overrides)
+            .field("tags", &self.tags)
+            .field("paused", &self.paused)
+            .field("charged_cells", &self.charged_cells)
+            .field("pending", &self.pending.as_ref().map(PendingQueue::len))
+            .field("reservations", &self.reservations)
+            .field("batches", &self.batches)
+            .field("offers", &self.offers)
+            .field("lease", &self.lease)
+            .field("registered", &self.registered)
+            .field("banned", &self.banned)
+            .field("disabled_resources", &self.disabled_resources)
+            .field("unreachable", &self.unreachable)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AI {
+    /// Creates a new AI instance.
+    ///
+    /// This constructor initializes an empty AI struct that implements
+    /// the planet's behavior through the [`PlanetAI`] trait.
+    ///
+    /// # Returns
+    /// A new `AI` instance ready to be passed to [`Planet::new`](common_game::components::planet::Planet::new).
+    ///
+    /// # Examples
+    /// ```
+    /// use rustrelli::ExplorerRequestLimit;
+    /// use rustrelli::planet::AI;
+    ///
+    /// let ai = AI::new(ExplorerRequestLimit::None);
+    /// ```
+    pub fn new(limit_mode: ExplorerRequestLimit) -> Self {
+        Self::with_policy(limit_mode.into())
+    }
+
+    /// Creates a new AI instance limiting explorer requests with `policy`.
+    fn with_policy(policy: Policy) -> Self {
+        AI {
+            policy: policy.build(),
+            limit_mode: policy,
+            shadow: None,
+            incoming: None,
+            arms: Vec::new(),
+            arm_of: HashMap::new(),
+            overrides: HashMap::new(),
+            tags: TagRegistry::default(),
+            pending: None,
+            outbox: None,
+            paused: None,
+            drain_timeout: None,
+            reservations: VecDeque::new(),
+            batch_config: None,
+            batches: HashMap::new(),
+            claim_config: None,
+            offers: HashMap::new(),
+            tie_break: SplitMix64::new(0),
+            preemption: None,
+            lease_config: None,
+            lease: None,
+            backoffs: None,
+            misconduct: None,
+            explorer_channels: HashMap::new(),
+            unreachable: HashSet::new(),
+            banned: HashMap::new(),
+            disabled_resources: HashMap::new(),
+            registered: HashSet::new(),
+            known: HashSet::new(),
+            early_costs: HashMap::new(),
+            gateway_notices: None,
+            spoof_reports: None,
+            tracker: None,
+            budget: None,
+            onboarding: false,
+            receipt_events: false,
+            receipts: 0,
+            reregistration: Reregistration::default(),
+            events: EventSink::default(),
+            charged_cells: 0,
+            stats: StatsHandle::default(),
+            admin: Vec::new(),
+            refusals: Box::new(CodedRefusals),
+            fallback: Box::new(NoResponse),
+            costs: Box::new(FixedCosts::default()),
+            load_window: None,
+            leaderboard_size: None,
+            streaks: HashMap::new(),
+            streak_alert: None,
+            energy_poll_interval: None,
+            energy_polls: HashMap::new(),
+            energy: EnergyLedger::default(),
+            replica: None,
+            stats_watch: None,
+            last_published: None,
+            housekeeping: None,
+            last_housekeeping: None,
+            timers: TimerWheel::default(),
+            warm_up: None,
+            warm_up_until: None,
+            policy_warm_up: None,
+            recent: VecDeque::new(),
+            stockpile: Vec::new(),
+            stockpile_visibility: StockpileVisibility::Visible,
+            seeded_cells: HashMap::new(),
+            game_start: None,
+            sunrays: SunrayEstimator::default(),
+            capabilities: None,
+            capability_generation: 0,
+            query_view: None,
+            clock: GuardedClock::new(Box::new(SystemClock)),
+            cell_timeline: false,
+            journal: None,
+            storage: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+            #[cfg(feature = "otel")]
+            otel: Telemetry::global(None),
+            #[cfg(feature = "metrics-facade")]
+            metrics: Metrics::new(None),
+        }
+    }
+
+    /// Creates a new AI instance configured by `config`.
+    ///
+    /// # Panics
+    /// Panics if `config` is invalid (see [`PlanetConfig::validate`]).
+    pub(crate) fn from_config(config: PlanetConfig) -> Self {
+        if let Err(error) = config.validate() {
+            panic!("{}", error);
+        }
+        let arm_of = config
+            .arms
+            .iter()
+            .enumerate()
+            .flat_map(|(index, arm)| arm.explorers.iter().map(move |id| ((*id), index)))
+            .collect();
+        let arms = config
+            .arms
+            .into_iter()
+            .map(|arm| Arm {
+                name: arm.name,
+                policy: arm.policy.build(),
+            })
+            .collect();
+
+        let outbox = config
+            .fulfillments
+            .map(|sender| Outbox::new(sender, config.delivery, config.stats.clone()));
+
+        let ai = AI {
+            shadow: config.shadow_limit.map(|policy| policy.build()),
+            arms,
+            arm_of,
+            stats: config.stats,
+            admin: config.admin,
+            tags: config.tags,
+            batch_config: config.batch,
+            claim_config: config.claims,
+            preemption: config.preemption,
+            tie_break: SplitMix64::new(
+                config
+                    .tie_break_seed
+                    .unwrap_or_else(|| u64::from(config.id)),
+            ),
+            lease_config: config.lease,
+            backoffs: config.backoff.map(Backoffs::new),
+            misconduct: config.misconduct.map(MisconductTracker::new),
+            events: config.events,
+            pending: config.pending.map(|mut queue| {
+                queue.coalesce = config.coalesce_pending;
+                queue.explorer_cap = config.pending_explorer_cap;
+                queue.overflow = config.pending_overflow;
+                queue.timeout = config.pending_timeout;
+                queue
+            }),
+            outbox,
+            drain_timeout: config.drain_timeout,
+            refusals: config.refusals,
+            fallback: config.fallback,
+            costs: config.cost_model.unwrap_or_else(|| Box::new(config.costs)),
+            load_window: config.load_window,
+            energy_poll_interval: config.energy_poll_interval,
+            energy_polls: HashMap::new(),
+            leaderboard_size: config.leaderboard_size,
+            streak_alert: config.streak_alert,
+            stats_watch: config.stats_watch,
+            housekeeping: config.housekeeping,
+            warm_up: config.warm_up,
+            policy_warm_up: config.policy_warm_up,
+            stockpile: config.stockpile,
+            stockpile_visibility: config.stockpile_visibility,
+            clock: GuardedClock::new(config.clock),
+            cell_timeline: config.cell_timeline.is_some(),
+            journal: config.journal.map(JournalWriter::new),
+            storage: config.storage,
+            tracker: config
+                .max_explorers
+                .map(|(max, untracked)| ExplorerTracker::new(max, untracked)),
+            budget: config.latency_budget.map(LatencyBudget::new),
+            onboarding: config.onboarding,
+            receipt_events: config.receipt_events,
+            receipts: 0,
+            reregistration: config.reregistration,
+            #[cfg(feature = "chaos")]
+            chaos: config.chaos.map(Chaos::new),
+            #[cfg(feature = "otel")]
+            otel: Telemetry::global(Some(config.id)),
+            #[cfg(feature = "metrics-facade")]
+            metrics: Metrics::new(Some(config.id)),
+            ..Self::with_policy(config.request_limit)
+        };
+        ai.lifecycle(LifecycleStage::Constructed);
+        ai
+    }
+
+    /// Returns a handle to the statistics recorded by this AI.
+    ///
+    /// # Examples
+    /// ```
+    /// use rustrelli::ExplorerRequestLimit;
+    /// use rustrelli::planet::AI;
+    ///
+    /// let ai = AI::new(ExplorerRequestLimit::None);
+    /// assert_eq!(ai.stats().snapshot().totals().grants, 0);
+    /// ```
+    pub fn stats(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+
+    /// Replaces the planet-wide limit policy. Policy arms and individual quotas are kept.
+    ///
+    /// The usage history of the old policy is carried over where the new one can
+    /// account it, and dropped otherwise:
+    /// - the grants still in a per-explorer quota window are charged to the quota or
+    ///   global cap windows of the new policy, or replayed into fair share scores;
+    /// - fair share scores are carried over to fair share;
+    /// - the grants of the current epoch are carried over to epoch budgets.
+    ///
+    /// Policies shared between planets neither export nor import any history.
+    ///
+    /// With a policy warm-up (see
+    /// [`PlanetConfig::with_policy_warm_up`](crate::PlanetConfig::with_policy_warm_up)),
+    /// the new policy is seeded with the grants of the last warm-up duration instead,
+    /// and only enforced once it observed the requests for as long. Replacing a policy
+    /// still warming up restarts the warm-up.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::SetPolicy`] instead.
+    pub fn set_policy(&mut self, policy: impl Into<Policy>) {
+        let policy = policy.into();
+        let now = self.now();
+        let mut built = policy.build();
+        let Some(warm_up) = self.policy_warm_up else {
+            let mut state = PolicyState::default();
+            self.policy.export_state(now, &mut state);
+            built.start(now);
+            built.import_state(now, &state);
+            self.incoming = None;
+            self.enforce(policy, built);
+            return;
+        };
+
+        built.start(now.checked_sub(warm_up).unwrap_or(now));
+        for entry in &self.recent {
+            match *entry {
+                JournalEntry::Epoch { at } => built.advance_epoch(at),
+                JournalEntry::Generation {
+                    at,
+                    explorer_id,
+                    resource,
+                    denial: None,
+                    ..
+                } if self.is_planet_wide(explorer_id) => {
+                    let request = Request {
+                        now: at,
+                        ..self.request(explorer_id, resource)
+                    };
+                    built.record(&request, Decision::Grant);
+                }
+                _ => {}
+            }
+        }
+        self.timers.schedule(now + warm_up, Expiry::WarmUp);
+        self.incoming = Some(Incoming {
+            limit_mode: policy,
+            policy: built,
+            enforced_from: now + warm_up,
+        });
+    }
+
+    /// Enforces `built` as the planet-wide policy, built from `policy`.
+    fn enforce(&mut self, policy: Policy, built: Box<dyn RequestLimitPolicy>) {
+        self.policy = built;
+        self.stats.update(|stats| stats.record_policy(&policy));
+        self.limit_mode = policy;
+    }
+
+    /// Enforces the incoming policy if its warm-up ended at `now`.
+    fn activate_incoming(&mut self, now: SystemTime) {
+        if let Some(incoming) = self
+            .incoming
+            .take_if(|incoming| now >= incoming.enforced_from)
+        {
+            self.enforce(incoming.limit_mode, incoming.policy);
+        }
+    }
+
+    /// Starts a new game epoch, restoring the per-epoch allowances of all policies
+    /// and resetting the per-epoch statistics.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::AdvanceEpoch`] instead.
+    pub fn advance_epoch(&mut self) {
+        self.apply(Change::Epoch { at: self.now() });
+    }
+
+    /// Moves every policy schedule to its next phase.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::NextPhase`] instead.
+    pub fn next_phase(&mut self) {
+        let now = self.now();
+        self.for_each_policy(|policy| policy.next_phase(now));
+    }
+
+    /// Restores the allowances of `explorer_id` in every policy, or of every
+    /// explorer if `None`, e.g. after the host resolved a dispute or restarted a bot.
+    ///
+    /// Allowances shared between explorers (like [`ExplorerRequestLimit::GlobalCap`])
+    /// are only restored by a full reset.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::ResetQuota`] instead.
+    pub fn reset_quota(&mut self, explorer_id: Option<u32>) {
+        self.reset_allowances(explorer_id.map(ExplorerId::from));
+    }
+
+    /// Restores the allowances of `explorer_id` in every policy, or of every explorer
+    /// if `None`: see [`reset_quota`](Self::reset_quota).
+    fn reset_allowances(&mut self, explorer_id: Option<ExplorerId>) {
+        self.for_each_policy(|policy| policy.reset(explorer_id));
+    }
+
+    /// Applies `f` to every policy: the planet-wide one, the arms' ones and the shadow one.
+    fn for_each_policy(&mut self, mut f: impl FnMut(&mut dyn RequestLimitPolicy)) {
+        f(self.policy.as_mut());
+        for arm in self.arms.iter_mut() {
+            f(arm.policy.as_mut());
+        }
+        for quota in self.overrides.values_mut() {
+            f(quota.policy.as_mut());
+        }
+        if let Some(shadow) = self.shadow.as_mut() {
+            f(shadow.as_mut());
+        }
+        if let Some(incoming) = self.incoming.as_mut() {
+            f(incoming.policy.as_mut());
+        }
+    }
+
+    /// Current time of the planet AI, skewed by the chaos layer if any. Anomalies of the
+    /// clock are reported as they start and end.
+    fn now(&self) -> SystemTime {
+        let (now, anomaly) = self.clock.now();
+        if let Some(anomaly) = anomaly {
+            self.emit(Event::Clock(anomaly));
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            return chaos.skew(now);
+        }
+        now
+    }
+
+    /// Reports that the planet AI entered `stage`.
+    fn lifecycle(&self, stage: LifecycleStage) {
+        self.emit(Event::Lifecycle {
+            at: self.now(),
+            stage,
+        });
+    }
+
+    /// Runs the housekeeping due before handling any message: records the heartbeat,
+    /// seeds the stockpile, applies the admin commands and retries the fulfillments not
+    /// delivered yet.
+    fn before_message(&mut self, state: &mut PlanetState) {
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            chaos.delay();
+        }
+        let now = self.now();
+        self.stats.update(|stats| stats.record_activity(now));
+        self.seed_stockpile(state);
+        // Before any cell can be spent on them, and before the energy checks, so the
+        // unclaimed cells are available right away
+        self.expire_timers(now);
+        self.process_admin();
+        self.process_spoof_reports();
+        if let Some(outbox) = self.outbox.as_mut() {
+            outbox.flush();
+        }
+        self.check_outbox();
+        let housekeeping_due = self.housekeeping.is_some_and(|interval| {
+            self.last_housekeeping.is_none_or(|last| {
+                now.duration_since(last)
+                    .is_ok_and(|elapsed| elapsed >= interval)
+            })
+        });
+        if housekeeping_due {
+            self.housekeeping(now);
+        }
+        self.publish_stats(now, false);
+    }
+
+    /// Charges a cell for each unit of the stockpile, the first time a message is
+    /// handled: see [`PlanetConfig::with_stockpile`](crate::PlanetConfig::with_stockpile).
+    fn seed_stockpile(&mut self, state: &mut PlanetState) {
+        for (resource, amount) in std::mem::take(&mut self.stockpile) {
+            let mut seeded = 0;
+            while seeded < amount
+                && let Some(cell) = state.empty_cell().map(|(_, index)| index)
+            {
+                state.charge_cell(Sunray::default());
+                self.seeded_cells.insert(cell, resource);
+                self.record_cell(cell, CellChange::Seeded);
+                seeded += 1;
+            }
+            self.stats
+                .update(|stats| stats.record_seeded(resource, u64::from(seeded)));
+        }
+    }
+
+    /// Performs the bookkeeping otherwise done lazily while handling requests: see
+    /// [`PlanetConfig::with_housekeeping`](crate::PlanetConfig::with_housekeeping).
+    fn housekeeping(&mut self, now: SystemTime) {
+        self.expire_timers(now);
+        self.activate_incoming(now);
+        self.for_each_policy(|policy| policy.tick(now));
+        self.expire_batches(now);
+        self.expire_lease(now);
+        if self.backoffs.is_some() {
+            self.apply(Change::BackoffsExpired { at: now });
+        }
+        self.observe_scores();
+        self.last_housekeeping = Some(now);
+    }
+
+    /// Handles the expiries due at `now` on the timer wheel.
+    fn expire_timers(&mut self, now: SystemTime) {
+        for expiry in self.timers.advance(now) {
+            match expiry {
+                Expiry::Ban(explorer_id) => {
+                    if self
+                        .banned
+                        .get(&explorer_id)
+                        .is_some_and(|until| until.is_some_and(|until| until <= now))
+                    {
+                        self.unban(explorer_id);
+                    }
+                }
+                Expiry::Resource(resource) => {
+                    if self
+                        .disabled_resources
+                        .get(&resource)
+                        .is_some_and(|until| until.is_some_and(|until| until <= now))
+                    {
+                        self.enable_resource(resource);
+                    }
+                }
+                Expiry::Lease => self.expire_lease(now),
+                Expiry::Offer(explorer_id) => {
+                    if self
+                        .offers
+                        .get(&explorer_id)
+                        .is_some_and(|offer| now >= offer.expires)
+                    {
+                        self.withdraw_offer(explorer_id);
+                    }
+                }
+                Expiry::Batch(explorer_id) => {
+                    if let Some(batch) = self
+                        .batches
+                        .get(&explorer_id)
+                        .filter(|batch| now >= batch.expires)
+                    {
+                        let remaining = batch.remaining;
+                        self.close_batch(explorer_id, remaining);
+                    }
+                }
+                Expiry::Pending => self.expire_pending(now),
+                Expiry::WarmUp => self.activate_incoming(now),
+            }
+        }
+    }
+
+    /// Publishes a copy of the statistics to the watch, if enabled and due at `now` or
+    /// `forced`.
+    fn publish_stats(&mut self, now: SystemTime, forced: bool) {
+        let Some((watch, interval)) = &self.stats_watch else {
+            return;
+        };
+        let due = self.last_published.is_none_or(|last| {
+            now.duration_since(last)
+                .is_ok_and(|elapsed| elapsed >= *interval)
+        });
+        if (due || forced)
+            && let Some(started) = self.begin_extra(Extra::Metrics)
+        {
+            watch.publish(self.stats.shared());
+            self.end_extra(Extra::Metrics, started);
+            self.last_published = Some(now);
+        }
+    }
+
+    /// Drains the work of the planet AI for up to `timeout` before it stops, and
+    /// reports the [`Event::Stopped`].
+    fn drain(&mut self, timeout: Duration) {
+        self.lifecycle(LifecycleStage::Draining);
+        let deadline = Instant::now() + timeout;
+        self.process_admin();
+        if let Some(outbox) = self.outbox.as_mut() {
+            outbox.drain(deadline);
+        }
+
+        // No cell can be discharged anymore: pending requests stay unserved
+        let unserved_requests = self.pending.as_ref().map_or_else(Vec::new, |queue| {
+            queue
+                .entries()
+                .iter()
+                .map(|entry| entry.explorer_id)
+                .collect()
+        });
+        self.apply(Change::QueueDrained);
+        let stats = self.stats.snapshot();
+        self.emit(Event::Stopped(ShutdownReport {
+            totals: stats.totals(),
+            epoch: stats.epoch().clone(),
+            unserved_requests,
+            dead_letters: stats.dead_letters().to_vec(),
+            bans: stats.bans().clone(),
+            ledger: stats.ledger().clone(),
+        }));
+    }
+
+    /// Disables deferred fulfillment if the host dropped the receiver of the
+    /// fulfillments: no more requests are queued.
+    fn check_outbox(&mut self) {
+        if self.pending.is_some() && self.outbox.as_ref().is_some_and(Outbox::is_closed) {
+            self.apply(Change::QueueClosed);
+        }
+    }
+
+    /// Applies the commands pending on the admin channels, in order.
+    fn process_admin(&mut self) {
+        let commands: Vec<AdminCommand> = self
+            .admin
+            .iter()
+            .flat_map(|admin| admin.try_iter())
+            .collect();
+
+        for command in commands {
+            self.apply_admin(command);
+        }
+    }
+
+    /// Records the spoof attempts reported by the explorer gateway, if any.
+    fn process_spoof_reports(&mut self) {
+        let Some(reports) = &self.spoof_reports else {
+            return;
+        };
+        let reports: Vec<SpoofReport> = reports.try_iter().collect();
+        for SpoofReport {
+            claimed,
+            kind,
+            unregistered,
+        } in reports
+        {
+            if unregistered && self.registered.contains(&claimed) {
+                // Raced a registration the gateway wasn't notified of yet
+                self.stats
+                    .update(|stats| stats.record_early_request(claimed));
+                self.charge_early_cost(claimed, self.costs.cost(kind));
+                continue;
+            }
+            self.stats
+                .update(|stats| stats.record_spoof_attempt(claimed));
+            self.record_misconduct(claimed, Signal::Spoof);
+            if unregistered {
+                self.record_early_cost(claimed, kind);
+            }
+        }
+    }
+
+    /// Applies an admin command.
+    fn apply_admin(&mut self, command: AdminCommand) {
+        match command {
+            AdminCommand::AdvanceEpoch => self.advance_epoch(),
+            AdminCommand::NextPhase => self.next_phase(),
+            AdminCommand::ResetQuota { explorer_id } => self.reset_quota(explorer_id),
+            AdminCommand::SetQuota { explorer_id, quota } => self.set_quota(explorer_id, quota),
+            AdminCommand::Tag { explorer_id, tag } => self.tag_explorer(explorer_id, tag),
+            AdminCommand::Untag { explorer_id, tag } => self.untag_explorer(explorer_id, &tag),
+            AdminCommand::Reserve { explorer_id } => self.reserve(explorer_id),
+            AdminCommand::Lease {
+                explorer_id,
+                resource,
+                cells,
+                duration,
+            } => {
+                let _ = self.lease(explorer_id, resource, cells, duration);
+            }
+            AdminCommand::CancelReservation { explorer_id } => self.cancel_reservation(explorer_id),
+            AdminCommand::WatchExplorer {
+                explorer_id,
+                sender,
+            } => self.watch_explorer(explorer_id, sender),
+            AdminCommand::ExplorerUnreachable { explorer_id } => {
+                self.set_reachable(explorer_id, false)
+            }
+            AdminCommand::ExplorerReachable { explorer_id } => {
+                self.set_reachable(explorer_id, true)
+            }
+            AdminCommand::RedriveDeadLetters { explorer_id } => {
+                self.redrive_dead_letters(explorer_id);
+            }
+            AdminCommand::Pause { mode } => self.pause(mode),
+            AdminCommand::Resume => self.resume(),
+            AdminCommand::SetPolicy { policy } => self.set_policy(policy),
+            AdminCommand::BanExplorer {
+                explorer_id,
+                duration,
+            } => match duration {
+                Some(duration) => self.ban_for(explorer_id, duration),
+                None => self.ban(explorer_id),
+            },
+            AdminCommand::Unban { explorer_id } => self.unban(explorer_id),
+            AdminCommand::DisableResource { resource, duration } => match duration {
+                Some(duration) => self.disable_resource_for(resource, duration),
+                None => self.disable_resource(resource),
+            },
+            AdminCommand::EnableResource { resource } => self.enable_resource(resource),
+            AdminCommand::Tick => self.housekeeping(self.now()),
+            AdminCommand::SnapshotStats { reply } => {
+                let _ = reply.try_send(self.stats.snapshot());
+            }
+            AdminCommand::Benchmark { duration, reply } => {
+                let _ = reply.try_send(self.benchmark(duration));
+            }
+            AdminCommand::Batch { commands } => {
+                for command in commands {
+                    self.apply_admin(command);
+                }
+            }
+        }
+    }
+
+    /// Evaluates the full admission logic on a generation request, without consuming
+    /// energy nor changing the policy state.
+    ///
+    /// Energy availability is the one last observed while handling a message, since
+    /// the AI doesn't own the planet state.
+    ///
+    /// # Examples
+    /// ```
+    /// use common_game::components::resource::BasicResourceType;
+    /// use rustrelli::ExplorerRequestLimit;
+    /// use rustrelli::planet::AI;
+    /// use rustrelli::policy::{Decision, DenialReason};
+    ///
+    /// let ai = AI::new(ExplorerRequestLimit::FairShare);
+    /// let trace = ai.would_grant(1, BasicResourceType::Oxygen);
+    ///
+    /// // No sunray received yet
+    /// assert_eq!(trace.decision, Decision::Deny(DenialReason::NoEnergy));
+    /// assert_eq!(trace.verdicts[0].decision, Decision::Grant);
+    /// ```
+    pub fn would_grant(
+        &self,
+        explorer_id: impl Into<ExplorerId>,
+        resource: BasicResourceType,
+    ) -> DecisionTrace {
+        let explorer_id = explorer_id.into();
+        let request = Request {
+            units: self.batch_units(explorer_id, self.charged_cells),
+            ..self.request(explorer_id, resource)
+        };
+        let policy = self.policy_of(explorer_id);
+
+        let mut verdicts = Vec::new();
+        policy.explain(&request, &mut verdicts);
+        let available = self
+            .check_banned(explorer_id)
+            .and_then(|()| match self.retry_after(explorer_id) {
+                Some(retry_after) if request.now < retry_after => {
+                    Err(DenialReason::RetriedTooEarly)
+                }
+                _ => Ok(()),
+            })
+            .and_then(|()| self.check_supported(resource))
+            .and_then(|()| self.check_enabled(resource))
+            .and_then(|()| self.check_paused())
+            .and_then(|()| self.check_energy(explorer_id, self.charged_cells))
+            .and_then(|()| {
+                self.check_delivery(explorer_id)
+                    .map_err(|_| DenialReason::Undeliverable)
+            });
+        let decision = match available {
+            Ok(()) if self.open_batch(explorer_id, request.now).is_some() => Decision::Grant,
+            Ok(())
+                if self
+                    .offers
+                    .get(&explorer_id)
+                    .is_some_and(|offer| offer.confirmed_by(resource, request.now)) =>
+            {
+                Decision::Grant
+            }
+            Ok(())
+                if self
+                    .lease
+                    .is_some_and(|lease| lease.claimed_by(explorer_id, resource, request.now)) =>
+            {
+                Decision::Grant
+            }
+            Ok(()) if self.is_warming_up(request.now) => Decision::Grant,
+            Ok(()) => policy.evaluate(&request),
+            Err(reason) => Decision::Deny(reason),
+        };
+
+        DecisionTrace {
+            explorer_id,
+            resource,
+            arm: self.arm_name(explorer_id).to_string(),
+            charged_cells: self.charged_cells,
+            verdicts,
+            shadow: self.shadow.as_ref().map(|shadow| shadow.evaluate(&request)),
+            decision,
+        }
+    }
+
+    /// Measures the cost of the decisions of the planet on synthetic requests, for about
+    /// `duration`, split between the planet-wide policy alone and the whole admission
+    /// logic (see [`crate::benchmark`]). The state of the planet is left untouched.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::Benchmark`] instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use rustrelli::ExplorerRequestLimit;
+    /// use rustrelli::planet::AI;
+    ///
+    /// let ai = AI::new(ExplorerRequestLimit::FairShare);
+    /// let report = ai.benchmark(Duration::from_millis(20));
+    /// assert!(report.policy.decisions > 0 && report.admission.decisions > 0);
+    /// ```
+    pub fn benchmark(&self, duration: Duration) -> BenchmarkReport {
+        let synthetic = |number: u32| {
+            let explorer_id = ExplorerId::new(number % SYNTHETIC_EXPLORERS);
+            let resource = BASIC_RESOURCES[number as usize % BASIC_RESOURCES.len()];
+            (explorer_id, resource)
+        };
+        let now = self.now();
+        let mut policy = self.limit_mode.build();
+        policy.start(now);
+        BenchmarkReport {
+            policy: benchmark::measure(duration / 2, |number| {
+                let (explorer_id, resource) = synthetic(number);
+                let request = Request {
+                    now: now + Duration::from_millis(number.into()),
+                    ..self.request(explorer_id, resource)
+                };
+                policy.admit(&request)
+            }),
+            admission: benchmark::measure(duration / 2, |number| {
+                let (explorer_id, resource) = synthetic(number);
+                self.would_grant(explorer_id, resource)
+            }),
+        }
+    }
+
+    /// Assigns `explorer_id` an individual `quota`, consulted instead of the policy
+    /// of its arm (or the planet-wide one).
+    ///
+    /// Assigning a new quota to an explorer discards the grants counted by the
+    /// previous one.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::SetQuota`] instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use rustrelli::{ExplorerRequestLimit, Quota};
+    /// use rustrelli::planet::AI;
+    ///
+    /// let mut ai = AI::new(ExplorerRequestLimit::FairShare);
+    /// // Explorer 7 bought 10 resources per minute
+    /// ai.set_quota(7, Quota::new(10, Duration::from_secs(60)));
+    /// ```
+    pub fn set_quota(&mut self, explorer_id: impl Into<ExplorerId>, quota: Quota) {
+        let explorer_id = explorer_id.into();
+        self.apply(Change::QuotaSet { explorer_id, quota });
+    }
+
+    /// Attaches `tag` to `explorer_id`.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::Tag`] instead.
+    pub fn tag_explorer(&mut self, explorer_id: impl Into<ExplorerId>, tag: Tag) {
+        let explorer_id = explorer_id.into();
+        self.apply(Change::Tagged { explorer_id, tag });
+    }
+
+    /// Detaches `tag` from `explorer_id`.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::Untag`] instead.
+    pub fn untag_explorer(&mut self, explorer_id: impl Into<ExplorerId>, tag: &Tag) {
+        let explorer_id = explorer_id.into();
+        self.apply(Change::Untagged {
+            explorer_id,
+            tag: tag.clone(),
+        });
+    }
+
+    /// Sets the weight of the explorers carrying `tag`. Explorers carrying several
+    /// weighted tags get the product of their weights.
+    ///
+    /// # Panics
+    /// Panics if `weight` isn't a positive finite number.
+    pub fn set_tag_weight(&mut self, tag: Tag, weight: f32) {
+        self.tags.set_weight(tag, weight);
+    }
+
+    /// Reserves the next charged cell for `explorer_id`.
+    ///
+    /// Until the reservation is used, requests of other explorers that could only be
+    /// served with the reserved cell are denied with [`DenialReason::Reserved`], and a
+    /// pending request of the holder is served before any other one.
+    /// The reservation is used by the first request of the holder that is granted.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::Reserve`] instead.
+    pub fn reserve(&mut self, explorer_id: impl Into<ExplorerId>) {
+        let explorer_id = explorer_id.into();
+        self.apply(Change::Reserved { explorer_id });
+    }
+
+    /// Cancels the oldest reservation held by `explorer_id`, if any.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::CancelReservation`] instead.
+    pub fn cancel_reservation(&mut self, explorer_id: impl Into<ExplorerId>) {
+        let explorer_id = explorer_id.into();
+        if self.reservations.contains(&explorer_id) {
+            self.apply(Change::ReservationReleased { explorer_id });
+        }
+    }
+
+    /// Returns the estimated arrival rate of the sunrays, as of the latest one.
+    ///
+    /// See the [`supply`](crate::supply) module.
+    pub fn sunray_rate(&self) -> SunrayRate {
+        self.sunrays.rate()
+    }
+
+    /// Time `explorer_id` is advised to retry generation requests at, if backoff is
+    /// enabled and the explorer was denied since its last grant. Hosts relay it to
+    /// their explorers, since the protocol can't carry it.
+    ///
+    /// See the [`backoff`](crate::backoff) module.
+    pub fn retry_after(&self, explorer_id: impl Into<ExplorerId>) -> Option<SystemTime> {
+        let explorer_id = explorer_id.into();
+        self.backoffs.as_ref()?.retry_after(explorer_id)
+    }
+
+    /// Leases the next `cells` charged cells to `explorer_id`, to generate `resource`
+    /// within `duration`. The lease is bounded by the configured [`LeaseConfig`], and
+    /// decided by the limit policy as a single request for all its cells.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::Lease`] instead. Either way, the
+    /// outcome is reported as an [`Event::LeaseGranted`] or [`Event::LeaseRefused`].
+    /// See the [`lease`](crate::lease) module.
+    ///
+    /// # Returns
+    /// When the unclaimed cells return to the common pool, or why the lease was refused.
+    pub fn lease(
+        &mut self,
+        explorer_id: impl Into<ExplorerId>,
+        resource: BasicResourceType,
+        cells: u32,
+        duration: Duration,
+    ) -> Result<SystemTime, LeaseRefusal> {
+        let explorer_id = explorer_id.into();
+        let outcome = self.try_lease(explorer_id, resource, cells, duration);
+        self.emit(match outcome {
+            Ok(expires) => Event::LeaseGranted {
+                explorer_id,
+                cells: self.lease.map_or(0, |lease| lease.remaining),
+                expires,
+            },
+            Err(refusal) => Event::LeaseRefused {
+                explorer_id,
+                refusal,
+            },
+        });
+        outcome
+    }
+
+    fn try_lease(
+        &mut self,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+        cells: u32,
+        duration: Duration,
+    ) -> Result<SystemTime, LeaseRefusal> {
+        let config = self.lease_config.ok_or(LeaseRefusal::Disabled)?;
+        let now = self.now();
+        self.expire_lease(now);
+        if let Some(lease) = self.lease {
+            return Err(LeaseRefusal::Active {
+                holder: lease.explorer_id,
+            });
+        }
+        self.check_banned(explorer_id)
+            .and_then(|()| self.check_paused())
+            .map_err(LeaseRefusal::Denied)?;
+
+        let request = Request {
+            now,
+            units: cells.clamp(1, config.max_cells.max(1)),
+            ..self.request(explorer_id, resource)
+        };
+        if let Decision::Deny(reason) = self.policy_of_mut(explorer_id).admit(&request) {
+            return Err(LeaseRefusal::Denied(reason));
+        }
+        let expires = now + duration.min(config.max_duration);
+        for _ in 0..request.units {
+            self.reserve(explorer_id);
+        }
+        self.timers.schedule(expires, Expiry::Lease);
+        self.lease = Some(Lease {
+            explorer_id,
+            resource,
+            remaining: request.units,
+            expires,
+        });
+        Ok(expires)
+    }
+
+    /// Ends the lease expired at `now`, if any, returning its unclaimed cells to the
+    /// common pool.
+    fn expire_lease(&mut self, now: SystemTime) {
+        let Some(lease) = self.lease.filter(|lease| now >= lease.expires) else {
+            return;
+        };
+        self.lease = None;
+        for _ in 0..lease.remaining {
+            self.cancel_reservation(lease.explorer_id);
+        }
+        self.emit(Event::LeaseEnded {
+            explorer_id: lease.explorer_id,
+            unclaimed: lease.remaining,
+        });
+    }
+
+    /// Lets the AI check whether the channel of `explorer_id` is full before discharging
+    /// a cell for it. `sender` must be a clone of the sender given to the planet for
+    /// that explorer.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::WatchExplorer`] instead.
+    pub fn watch_explorer(
+        &mut self,
+        explorer_id: impl Into<ExplorerId>,
+        sender: Sender<PlanetToExplorer>,
+    ) {
+        let explorer_id = explorer_id.into();
+        if let Some(view) = &self.query_view {
+            view.watch(explorer_id, sender.clone());
+        }
+        self.explorer_channels.insert(explorer_id, sender);
+    }
+
+    /// Links the AI to the explorer gateway: it notifies the registrations on `notices`,
+    /// and records the spoof attempts received on `reports`.
+    pub(crate) fn link_gateway(&mut self, notices: Sender<Notice>, reports: Receiver<SpoofReport>) {
+        self.gateway_notices = Some(notices);
+        self.spoof_reports = Some(reports);
+    }
+
+    /// Shares the channels of the watched explorers, whether the AI is running and the
+    /// charged cells with the query workers.
+    pub(crate) fn share_query_view(&mut self, view: Arc<SharedView>) {
+        self.query_view = Some(ViewHandle::new(view));
+    }
+
+    /// Marks `explorer_id` as reachable or not. Generation requests of unreachable
+    /// explorers are refused without discharging cells.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::ExplorerUnreachable`] and
+    /// [`AdminCommand::ExplorerReachable`] instead.
+    pub fn set_reachable(&mut self, explorer_id: impl Into<ExplorerId>, reachable: bool) {
+        let explorer_id = explorer_id.into();
+        self.apply(Change::Reachability {
+            explorer_id,
+            reachable,
+        });
+    }
+
+    /// Sends again the fulfillments in the dead letters of `explorer_id`, or of every
+    /// explorer if `None`, typically once the orchestrator registered a new channel
+    /// for the explorer. Returns the number of re-driven fulfillments.
+    ///
+    /// Dead letters are listed by [`Stats::dead_letters`](crate::stats::Stats::dead_letters).
+    /// Hosts of a running planet send [`AdminCommand::RedriveDeadLetters`] instead.
+    pub fn redrive_dead_letters(&mut self, explorer_id: Option<u32>) -> usize {
+        let redriven = self.outbox.as_mut().map_or(0, |outbox| {
+            outbox.redrive(explorer_id.map(ExplorerId::from))
+        });
+        self.check_outbox();
+        redriven
+    }
+
+    /// Pauses the handling of generation requests: they are denied with
+    /// [`DenialReason::Paused`] and, with [`PauseMode::Buffer`], queued for after
+    /// [`Self::resume`], or left unanswered with [`PauseMode::Drop`]. Sunrays and
+    /// queries are still handled.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::Pause`] instead.
+    pub fn pause(&mut self, mode: PauseMode) {
+        self.apply(Change::Paused { mode });
+    }
+
+    /// Resumes the handling of generation requests. Requests buffered while paused
+    /// are served with the cells charged meanwhile, before the next sunray or explorer
+    /// message is handled.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::Resume`] instead.
+    pub fn resume(&mut self) {
+        if self.paused.is_some() {
+            self.apply(Change::Resumed);
+        }
+    }
+
+    /// Bans `explorer_id`: its generation and combination requests are refused with
+    /// [`DenialReason::Banned`] until [`Self::unban`].
+    ///
+    /// Hosts of a running planet send [`AdminCommand::BanExplorer`] instead.
+    pub fn ban(&mut self, explorer_id: impl Into<ExplorerId>) {
+        let explorer_id = explorer_id.into();
+        self.apply(Change::Banned {
+            explorer_id,
+            until: None,
+        });
+    }
+
+    /// Bans `explorer_id` like [`Self::ban`], for `duration` from now.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::BanExplorer`] instead.
+    pub fn ban_for(&mut self, explorer_id: impl Into<ExplorerId>, duration: Duration) {
+        let explorer_id = explorer_id.into();
+        self.apply(Change::Banned {
+            explorer_id,
+            until: Some(self.now() + duration),
+        });
+    }
+
+    /// Lifts the ban of `explorer_id`.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::Unban`] instead.
+    pub fn unban(&mut self, explorer_id: impl Into<ExplorerId>) {
+        let explorer_id = explorer_id.into();
+        if self.banned.contains_key(&explorer_id) {
+            self.apply(Change::Unbanned { explorer_id });
+        }
+    }
+
+    fn check_banned(&self, explorer_id: ExplorerId) -> Result<(), DenialReason> {
+        match self.banned.get(&explorer_id) {
+            Some(until) if until.is_none_or(|until| self.now() < until) => {
+                Err(DenialReason::Banned)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuses the resources missing from the generation rules of the planet, which
+    /// `make_basic_resource` can't produce. Every resource passes before the AI starts.
+    fn check_supported(&self, resource: BasicResourceType) -> Result<(), DenialReason> {
+        match &self.capabilities {
+            Some(capabilities) if !capabilities.resources.contains(&resource) => {
+                Err(DenialReason::UnsupportedResource)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Disables the generation of `resource`: requests for it are refused with
+    /// [`DenialReason::ResourceDisabled`] until [`Self::enable_resource`]. The resource
+    /// stays in the generation rules the planet reports.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::DisableResource`] instead.
+    pub fn disable_resource(&mut self, resource: BasicResourceType) {
+        self.apply(Change::ResourceDisabled {
+            resource,
+            until: None,
+        });
+    }
+
+    /// Disables the generation of `resource` like [`Self::disable_resource`], for
+    /// `duration` from now.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::DisableResource`] instead.
+    pub fn disable_resource_for(&mut self, resource: BasicResourceType, duration: Duration) {
+        self.apply(Change::ResourceDisabled {
+            resource,
+            until: Some(self.now() + duration),
+        });
+    }
+
+    /// Enables the generation of `resource` again.
+    ///
+    /// Hosts of a running planet send [`AdminCommand::EnableResource`] instead.
+    pub fn enable_resource(&mut self, resource: BasicResourceType) {
+        if self.disabled_resources.contains_key(&resource) {
+            self.apply(Change::ResourceEnabled { resource });
+        }
+    }
+
+    fn check_enabled(&self, resource: BasicResourceType) -> Result<(), DenialReason> {
+        match self.disabled_resources.get(&resource) {
+            Some(until) if until.is_none_or(|until| self.now() < until) => {
+                Err(DenialReason::ResourceDisabled)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// ID the message `msg` is handled as: the ID it claims, unless its explorer is past
+    /// the cap of tracked explorers (see [`registration`](crate::registration#tracked-explorers)).
+    ///
+    /// # Returns
+    /// `None` if the message is denied.
+    fn admit(&mut self, msg: &ExplorerToPlanet) -> Option<ExplorerId> {
+        let explorer_id = ExplorerId::from(msg.explorer_id());
+        let Some(untracked) = self
+            .tracker
+            .as_mut()
+            .and_then(|tracker| tracker.admit(explorer_id))
+        else {
+            return Some(explorer_id);
+        };
+        self.stats.update(Stats::record_untracked_message);
+        #[cfg(feature = "metrics-facade")]
+        self.metrics.record_untracked();
+        match untracked {
+            Untracked::Share => Some(ExplorerId::Overflow),
+            Untracked::Deny => None,
+        }
+    }
+
+    /// Answers `msg`, from an explorer past the cap of tracked explorers, denying the
+    /// generation and combination requests. Queries are answered as if the planet had
+    /// nothing to offer, so that explorers waiting on the answer move on.
+    fn deny_untracked(&self, msg: ExplorerToPlanet) -> Option<PlanetToExplorer> {
+        match msg {
+            ExplorerToPlanet::GenerateResourceRequest { .. } => {
+                Some(PlanetToExplorer::GenerateResourceResponse { resource: None })
+            }
+            ExplorerToPlanet::CombineResourceRequest { msg, .. } => {
+                let reason = RefusalReason::Denied(DenialReason::Untracked);
+                let (first, second) = extract_generic_resources(msg);
+                Some(PlanetToExplorer::CombineResourceResponse {
+                    complex_response: Err((self.refusals.format(&reason), first, second)),
+                })
+            }
+            ExplorerToPlanet::SupportedResourceRequest { .. } => {
+                Some(PlanetToExplorer::SupportedResourceResponse {
+                    resource_list: HashSet::new(),
+                })
+            }
+            ExplorerToPlanet::SupportedCombinationRequest { .. } => {
+                Some(PlanetToExplorer::SupportedCombinationResponse {
+                    combination_list: HashSet::new(),
+                })
+            }
+            ExplorerToPlanet::AvailableEnergyCellRequest { .. } => {
+                Some(PlanetToExplorer::AvailableEnergyCellResponse { available_cells: 0 })
+            }
+            // Variants added to the protocol after this version
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Adds the cost of a request of `kind`, dropped by the gateway, to the early costs
+    /// of `explorer_id`, the explorer it claimed, if that explorer was never registered:
+    /// the request may have raced its registration.
+    fn record_early_cost(&mut self, explorer_id: ExplorerId, kind: MessageKind) {
+        if !self.known.contains(&explorer_id) {
+            *self.early_costs.entry(explorer_id).or_default() += self.costs.cost(kind);
+        }
+    }
+
+    /// Registers the channel of `explorer_id`. On its first registration, the requests
+    /// that arrived before it are reconciled: they're no longer counted as spoof
+    /// attempts, and their cost is charged. On the
+    /// next ones, the explorer is handled as configured by the [`Reregistration`].
+    fn register(&mut self, explorer_id: ExplorerId) {
+        // The gateway reported the requests it received before the registration
+        self.process_spoof_reports();
+        if let Some(notices) = &self.gateway_notices {
+            let _ = notices.send(Notice::Arrived(explorer_id));
+        }
+        let transition = if self.registered.contains(&explorer_id) {
+            Transition::Duplicate
+        } else {
+            Transition::Returned
+        };
+        self.registered.insert(explorer_id);
+        let new = self.known.insert(explorer_id);
+        if self.onboarding {
+            self.onboard(explorer_id, new);
+        }
+        if new {
+            self.reconcile_registration(explorer_id);
+            return;
+        }
+        self.emit(Event::Registration {
+            explorer_id,
+            transition,
+        });
+
+        // The planet loop replaced the channel of the explorer
+        if let Some(view) = &self.query_view {
+            view.forget(explorer_id);
+        }
+        self.explorer_channels.remove(&explorer_id);
+        self.unreachable.remove(&explorer_id);
+        if self.reregistration == Reregistration::Reset {
+            self.reset_allowances(Some(explorer_id));
+            self.streaks.remove(&explorer_id);
+            self.energy_polls.remove(&explorer_id);
+            self.stats.update(|stats| stats.reset_explorer(explorer_id));
+        }
+    }
+
+    /// Reports the rules of engagement to `explorer_id`, registering for the first time
+    /// if `new`.
+    fn onboard(&self, explorer_id: ExplorerId, new: bool) {
+        let streak = self.streaks.get(&explorer_id).copied().unwrap_or(0);
+        let history = self.stats.read(|stats| {
+            let epoch = stats.epoch();
+            History {
+                epoch_grants: epoch.grants.get(&explorer_id).copied().unwrap_or(0),
+                epoch_denials: epoch.denials.get(&explorer_id).copied().unwrap_or(0),
+                received: stats.ledger().get(&explorer_id).map_or(0, Receipts::total),
+                streak,
+            }
+        });
+        // Explorers never registered may still have a history, e.g. imported by a
+        // shared policy or from requests that raced their registration
+        let history = (!new || history != History::default()).then_some(history);
+        self.emit(Event::Onboarding(Onboarding {
+            explorer_id,
+            policy: format!("{:?}", self.limit_mode),
+            arm: (!self.arms.is_empty()).then(|| self.arm_name(explorer_id).to_string()),
+            tags: self.tags.tags_of(explorer_id).iter().cloned().collect(),
+            retry_after: self.backoffs.as_ref().map(Backoffs::initial_delay),
+            batch_size: self.batch_config.map(|config| config.max_size),
+            claim_window: self.claim_config.map(|config| config.window),
+            energy_poll_interval: self.energy_poll_interval,
+            history,
+        }));
+    }
+
+    /// Reconciles the requests of `explorer_id` that arrived before its first
+    /// registration.
+    fn reconcile_registration(&mut self, explorer_id: ExplorerId) {
+        self.stats
+            .update(|stats| stats.reconcile_registration(explorer_id));
+        if let Some(tracker) = &self.misconduct {
+            let weight = tracker.config.weight(Signal::Spoof);
+            self.stats
+                .update(|stats| stats.forgive_spoofs(explorer_id, weight));
+        }
+        if let Some(cost) = self.early_costs.remove(&explorer_id) {
+            self.charge_early_cost(explorer_id, cost);
+        }
+    }
+
+    /// Charges `cost`, of requests that arrived before the registration of
+    /// `explorer_id`, to its limit policy.
+    fn charge_early_cost(&mut self, explorer_id: ExplorerId, cost: f32) {
+        if cost > 0.0 {
+            let tags = self.tags.tags_of(explorer_id);
+            let now = self.now();
+            self.policy_of_mut(explorer_id)
+                .charge(explorer_id, &tags, cost, now);
+        }
+    }
+
+    /// Adds `signal` to the misconduct score of `explorer_id`, if misconduct is scored,
+    /// reporting the score reaching the alert threshold.
+    fn record_misconduct(&self, explorer_id: ExplorerId, signal: Signal) {
+        let Some(tracker) = &self.misconduct else {
+            return;
+        };
+        let weight = tracker.config.weight(signal);
+        let mut scores = (0.0, 0.0);
+        self.stats.update(|stats| {
+            scores = stats.record_misconduct(explorer_id, signal, weight);
+        });
+        if tracker.alerts(scores.0, scores.1) {
+            self.emit(Event::Misconduct {
+                explorer_id,
+                signal,
+            });
+        }
+    }
+
+    /// When `extra` starts, unless it would take the explorer message being handled past
+    /// the latency budget: then it's counted as skipped (see [`crate::budget`]).
+    fn begin_extra(&self, extra: Extra) -> Option<Instant> {
+        let now = Instant::now();
+        if let Some(budget) = &self.budget
+            && !budget.allows(extra, now)
+        {
+            self.stats.update(|stats| stats.record_budget_skip(extra));
+            return None;
+        }
+        Some(now)
+    }
+
+    /// Records the cost of `extra`, begun at `started`, into the latency budget.
+    fn end_extra(&self, extra: Extra, started: Instant) {
+        if let Some(budget) = &self.budget {
+            budget.record(extra, started.elapsed());
+        }
+    }
+
+    /// Reports `event`, unless it's below [`Severity::Warn`] and would take the explorer
+    /// message being handled past the latency budget.
+    fn emit(&self, event: Event) {
+        if self.budget.is_none() || event.severity() >= Severity::Warn {
+            self.events.emit(event);
+        } else if let Some(started) = self.begin_extra(Extra::Events) {
+            self.events.emit(event);
+            self.end_extra(Extra::Events, started);
+        }
+    }
+
+    fn check_paused(&self) -> Result<(), DenialReason> {
+        match self.paused {
+            Some(_) => Err(DenialReason::Paused),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that a resource produced for `explorer_id` could be delivered.
+    fn check_delivery(&self, explorer_id: ExplorerId) -> Result<(), DeliveryFailure> {
+        if self.unreachable.contains(&explorer_id) {
+            return Err(DeliveryFailure::Unreachable);
+        }
+        match self.explorer_channels.get(&explorer_id) {
+            Some(sender) if sender.is_full() => Err(DeliveryFailure::ChannelFull),
+            _ => Ok(()),
+        }
+    }
+
+    /// Denies a request of `explorer_id` arriving at `now` before its advised retry-after
+    /// time, if backoff is enabled, escalating its advice.
+    fn check_backoff(
+        &mut self,
+        explorer_id: ExplorerId,
+        now: SystemTime,
+    ) -> Result<(), DenialReason> {
+        let Some(backoffs) = self.backoffs.as_ref() else {
+            return Ok(());
+        };
+        let checked = backoffs.check(explorer_id, now);
+        if checked.is_err() {
+            self.apply(Change::RetriedEarly {
+                at: now,
+                explorer_id,
+            });
+        }
+        checked
+    }
+
+    /// Records whether a request of `explorer_id` allowed by [`Self::check_backoff`] at
+    /// `now` was granted, if backoff is enabled.
+    fn record_backoff(&mut self, explorer_id: ExplorerId, now: SystemTime, granted: bool) {
+        if self.backoffs.is_some() {
+            self.apply(Change::BackoffRecorded {
+                at: now,
+                explorer_id,
+                granted,
+            });
+        }
+    }
+
+    /// Checks that a cell is available to `explorer_id` when `charged` cells are charged:
+    /// cells reserved by other explorers aren't.
+    fn check_energy(&self, explorer_id: ExplorerId, charged: usize) -> Result<(), DenialReason> {
+        if charged == 0 {
+            return Err(DenialReason::NoEnergy);
+        }
+        if self.reservations.contains(&explorer_id) {
+            return Ok(());
+        }
+        if charged <= self.reservations.len() {
+            return Err(DenialReason::Reserved);
+        }
+        Ok(())
+    }
+
+    /// Returns the batch of `explorer_id` still open at `now`, if any.
+    fn open_batch(&self, explorer_id: ExplorerId, now: SystemTime) -> Option<&Batch> {
+        self.batches
+            .get(&explorer_id)
+            .filter(|batch| batch.remaining > 0 && now < batch.expires)
+    }
+
+    /// Closes the batches expired at `now`, releasing their unclaimed cells.
+    fn expire_batches(&mut self, now: SystemTime) {
+        if self.batches.values().all(|batch| now < batch.expires) {
+            return;
+        }
+        let mut expired: Vec<(ExplorerId, u32)> = self
+            .batches
+            .iter()
+            .filter(|(_, batch)| now >= batch.expires)
+            .map(|(explorer_id, batch)| (*explorer_id, batch.remaining))
+            .collect();
+        // Whatever the iteration order of the map
+        expired.sort_unstable();
+
+        for (explorer_id, remaining) in expired {
+            self.close_batch(explorer_id, remaining);
+        }
+    }
+
+    /// Closes the batch of `explorer_id`, releasing its `remaining` cells.
+    fn close_batch(&mut self, explorer_id: ExplorerId, remaining: u32) {
+        self.batches.remove(&explorer_id);
+        for _ in 0..remaining {
+            self.cancel_reservation(explorer_id);
+        }
+    }
+
+    /// Withdraws the expired offer of `explorer_id`, releasing its cell.
+    fn withdraw_offer(&mut self, explorer_id: ExplorerId) {
+        self.offers.remove(&explorer_id);
+        self.cancel_reservation(explorer_id);
+        self.stats.update(|stats| stats.record_claim_expired());
+    }
+
+    /// Whether a grant to `explorer_id` when `charged` cells are charged is only offered:
+    /// two-phase grants are enabled, and it would spend the last cell not reserved by
+    /// other explorers.
+    fn is_contended(&self, explorer_id: ExplorerId, charged: usize) -> bool {
+        if self.claim_config.is_none() {
+            return false;
+        }
+        let reserved_by_others = self
+            .reservations
+            .iter()
+            .filter(|holder| **holder != explorer_id)
+            .count();
+        charged.saturating_sub(reserved_by_others) <= 1
+    }
+
+    /// Returns the explorer whose offered or batched cell `explorer_id` may preempt, if
+    /// it's a critical explorer: the holder of an offer if any, or else of the open
+    /// batch with the most cells left. Critical explorers aren't preempted.
+    fn preemptible(&self, explorer_id: ExplorerId) -> Option<ExplorerId> {
+        let critical = self.preemption.as_ref()?;
+        let is_critical = |id: ExplorerId| self.tags.tags_of(id).contains(critical);
+        if !is_critical(explorer_id) {
+            return None;
+        }
+        let offered = self
+            .offers
+            .keys()
+            .copied()
+            .filter(|id| !is_critical(*id))
+            .min();
+        offered.or_else(|| {
+            self.batches
+                .iter()
+                .filter(|(id, batch)| batch.remaining > 0 && !is_critical(**id))
+                .max_by_key(|(id, batch)| (batch.remaining, std::cmp::Reverse(**id)))
+                .map(|(id, _)| *id)
+        })
+    }
+
+    /// Releases a cell offered or batched for `victim`, preempted by a critical explorer.
+    fn preempt(&mut self, victim: ExplorerId) {
+        if self.offers.remove(&victim).is_none()
+            && let Some(batch) = self.batches.get_mut(&victim)
+        {
+            batch.remaining -= 1;
+            if batch.remaining == 0 {
+                self.batches.remove(&victim);
+            }
+        }
+        self.cancel_reservation(victim);
+        self.stats.update(|stats| stats.record_preemption());
+        #[cfg(feature = "metrics-facade")]
+        self.metrics.record_preemption();
+    }
+
+    /// Returns the number of cells a new batch of `explorer_id` would claim when
+    /// `charged` cells are charged: all the cells not reserved by other explorers,
+    /// up to the configured batch size. Always 1 if batch grants are disabled.
+    fn batch_units(&self, explorer_id: ExplorerId, charged: usize) -> u32 {
+        let Some(config) = self.batch_config else {
+            return 1;
+        };
+        let reserved_by_others = self
+            .reservations
+            .iter()
+            .filter(|holder| **holder != explorer_id)
+            .count();
+        let available = charged.saturating_sub(reserved_by_others).max(1);
+        config
+            .max_size
+            .clamp(1, available.try_into().unwrap_or(u32::MAX))
+    }
+
+    /// Whether the limit policy only observes the requests at `now`, see
+    /// [`PlanetConfig::with_warm_up`](crate::PlanetConfig::with_warm_up).
+    fn is_warming_up(&self, now: SystemTime) -> bool {
+        self.warm_up_until.is_some_and(|until| now < until)
+    }
+
+    /// Builds the request `explorer_id` makes for `resource` at the current time.
+    fn request(&self, explorer_id: ExplorerId, resource: impl Into<Product>) -> Request {
+        let resource = resource.into();
+        let kind = match resource {
+            Product::Basic(resource) => MessageKind::Generation(resource),
+            Product::Complex(resource) => MessageKind::Combination(resource),
+        };
+        Request {
+            explorer_id,
+            resource,
+            now: self.now(),
+            tags: self.tags.tags_of(explorer_id),
+            weight: self.tags.weight_of(explorer_id),
+            units: 1,
+            cost: self.costs.cost(kind),
+            streak: self.streaks.get(&explorer_id).copied().unwrap_or(0),
+        }
+    }
+
+    /// Returns the policy limiting `explorer_id`: its individual quota if assigned,
+    /// the policy of its arm otherwise.
+    fn policy_of(&self, explorer_id: ExplorerId) -> &dyn RequestLimitPolicy {
+        match (
+            self.overrides.get(&explorer_id),
+            self.arm_of.get(&explorer_id),
+        ) {
+            (Some(quota), _) => quota.policy.as_ref(),
+            (None, Some(&index)) => self.arms[index].policy.as_ref(),
+            (None, None) => self.policy.as_ref(),
+        }
+    }
+
+    /// Whether `explorer_id` is limited by the planet-wide policy.
+    fn is_planet_wide(&self, explorer_id: ExplorerId) -> bool {
+        !self.overrides.contains_key(&explorer_id) && !self.arm_of.contains_key(&explorer_id)
+    }
+
+    /// Mutable version of [`Self::policy_of`].
+    fn policy_of_mut(&mut self, explorer_id: ExplorerId) -> &mut dyn RequestLimitPolicy {
+        match (
+            self.overrides.get_mut(&explorer_id),
+            self.arm_of.get(&explorer_id),
+        ) {
+            (Some(quota), _) => quota.policy.as_mut(),
+            (None, Some(&index)) => self.arms[index].policy.as_mut(),
+            (None, None) => self.policy.as_mut(),
+        }
+    }
+
+    /// Returns the name of the policy arm `explorer_id` belongs to.
+    fn arm_name(&self, explorer_id: ExplorerId) -> &str {
+        match self.arm_of.get(&explorer_id) {
+            Some(&index) => &self.arms[index].name,
+            None => PolicyArm::DEFAULT,
+        }
+    }
+
+    /// Records the outcome of a generation request: granted if `denial` is `None`.
+    /// Returns the receipt issued for the grant.
+    fn record_generation(
+        &mut self,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+        denial: Option<DenialReason>,
+    ) -> Option<ReceiptId> {
+        let receipt = denial.is_none().then(|| {
+            self.receipts += 1;
+            ReceiptId::new(self.receipts)
+        });
+        self.apply(Change::Generation {
+            at: self.now(),
+            explorer_id,
+            resource,
+            denial,
+            receipt,
+        });
+        receipt
+    }
+
+    /// Applies `change` to the state of the AI and to everything derived from it: the
+    /// statistics, the journal, the cell timeline, the events and the telemetry, and to
+    /// the replica of the state, if any.
+    ///
+    /// See the [`change`](crate::change) module.
+    fn apply(&mut self, change: Change) {
+        if let Some(replica) = self.replica.as_mut() {
+            replica.apply(&change);
+        }
+        if let Some(entry) = change.journal_entry() {
+            self.remember(entry);
+            self.journal(entry);
+        }
+        match change {
+            Change::Sunray { at } => {
+                let rate = self.sunrays.record(at);
+                self.for_each_policy(|policy| policy.observe_supply(&rate));
+                self.stats.update(|stats| {
+                    stats.record_sunray(at);
+                    stats.record_sunray_rate(rate);
+                });
+                #[cfg(feature = "metrics-facade")]
+                self.metrics.record_sunray();
+            }
+            Change::Generation {
+                at,
+                explorer_id,
+                resource,
+                denial,
+                receipt,
+            } => {
+                if let Some(id) = receipt
+                    && self.receipt_events
+                {
+                    self.emit(Event::Granted(Receipt {
+                        id,
+                        explorer_id,
+                        resource,
+                        at,
+                    }));
+                }
+                self.record_streak(explorer_id, denial);
+                match denial {
+                    Some(DenialReason::RetriedTooEarly) => {
+                        self.record_misconduct(explorer_id, Signal::EarlyRetry)
+                    }
+                    Some(DenialReason::Undeliverable) => {
+                        self.record_misconduct(explorer_id, Signal::Undeliverable)
+                    }
+                    _ => {}
+                }
+                let arm = self.arm_name(explorer_id);
+                self.stats.update(|stats| {
+                    match denial {
+                        None => stats.record_grant(at),
+                        Some(reason) => {
+                            stats.record_denial(at, reason);
+                            stats.record_explorer_denial(explorer_id, reason);
+                        }
+                    }
+                    stats.record_arm(arm, denial.is_none());
+                    stats.record_epoch(explorer_id, denial.is_none());
+                    stats.record_wait(explorer_id, at, denial.is_none());
+                });
+                #[cfg(any(feature = "otel", feature = "metrics-facade"))]
+                if let Some(started) = self.begin_extra(Extra::Metrics) {
+                    #[cfg(feature = "otel")]
+                    self.otel.record_generation(denial);
+                    #[cfg(feature = "metrics-facade")]
+                    self.metrics.record_generation(denial);
+                    self.end_extra(Extra::Metrics, started);
+                }
+            }
+            Change::Epoch { at } => {
+                self.for_each_policy(|policy| policy.advance_epoch(at));
+                self.stats.update(|stats| stats.advance_epoch());
+            }
+            Change::Cell { at, cell, change } => {
+                match change {
+                    CellChange::Charged | CellChange::Seeded => self.energy.record_charge(),
+                    CellChange::Discharged(provenance) => {
+                        self.energy.record_discharge();
+                        if let Some(seeded) = self.seeded_cells.remove(&cell) {
+                            let granted = match provenance.resource {
+                                Product::Basic(resource) => Some(resource),
+                                Product::Complex(_) => None,
+                            };
+                            self.stats
+                                .update(|stats| stats.record_served(granted, seeded));
+                        }
+                    }
+                }
+                if self.cell_timeline {
+                    self.stats
+                        .update(|stats| stats.record_cell(CellEvent { at, cell, change }));
+                }
+            }
+            Change::Banned { explorer_id, until } => {
+                if let Some(until) = until {
+                    self.timers.schedule(until, Expiry::Ban(explorer_id));
+                }
+                self.banned.insert(explorer_id, until);
+                self.stats
+                    .update(|stats| stats.record_ban(explorer_id, until));
+            }
+            Change::Unbanned { explorer_id } => {
+                self.banned.remove(&explorer_id);
+                self.stats.update(|stats| stats.record_unban(explorer_id));
+            }
+            Change::ResourceDisabled { resource, until } => {
+                if let Some(until) = until {
+                    self.timers.schedule(until, Expiry::Resource(resource));
+                }
+                self.disabled_resources.insert(resource, until);
+                self.stats
+                    .update(|stats| stats.record_disabled(resource, until));
+            }
+            Change::ResourceEnabled { resource } => {
+                self.disabled_resources.remove(&resource);
+                self.stats.update(|stats| stats.record_enabled(resource));
+            }
+            Change::Combination {
+                explorer_id,
+                resource,
+                denial: None,
+                ..
+            } => {
+                self.stats
+                    .update(|stats| stats.record_received_complex(explorer_id, resource));
+            }
+            Change::Combination { .. } => {}
+            Change::Paused { mode } => {
+                self.paused = Some(mode);
+                self.lifecycle(LifecycleStage::Paused(mode));
+            }
+            Change::Resumed => {
+                self.paused = None;
+                self.lifecycle(LifecycleStage::Resumed);
+            }
+            Change::QuotaSet { explorer_id, quota } => {
+                let policy = ExplorerRequestLimit::Quota(quota).build();
+                self.overrides
+                    .insert(explorer_id, Override { quota, policy });
+            }
+            Change::Tagged { explorer_id, tag } => self.tags.tag(explorer_id, tag),
+            Change::Untagged { explorer_id, tag } => self.tags.untag(explorer_id, &tag),
+            Change::Reserved { explorer_id } => self.reservations.push_back(explorer_id),
+            Change::ReservationReleased { explorer_id } => {
+                if let Some(index) = self.reservations.iter().position(|id| *id == explorer_id) {
+                    self.reservations.remove(index);
+                }
+            }
+            Change::Reachability {
+                explorer_id,
+                reachable,
+            } => {
+                if reachable {
+                    self.unreachable.remove(&explorer_id);
+                } else {
+                    self.unreachable.insert(explorer_id);
+                }
+            }
+            Change::Queued {
+                at,
+                explorer_id,
+                resource,
+            } => {
+                let Some(queue) = self.pending.as_mut() else {
+                    return;
+                };
+                let queued = queue.push(explorer_id, resource, at);
+                if let Some(timeout) = queue.timeout {
+                    // Requests expire once they waited longer than the timeout
+                    self.timers
+                        .schedule(at + timeout + Duration::from_nanos(1), Expiry::Pending);
+                }
+                self.stats.update(|stats| stats.record_queued(queued));
+                if let Queued::Evicted { explorer_id } = queued {
+                    self.record_occupancy(explorer_id);
+                }
+                self.record_occupancy(explorer_id);
+            }
+            Change::Unqueued { index } => {
+                if let Some(entry) = self.pending.as_mut().map(|queue| queue.take(index)) {
+                    self.record_occupancy(entry.explorer_id);
+                }
+            }
+            Change::PendingExpired { at } => {
+                let expired = self
+                    .pending
+                    .as_mut()
+                    .map_or_else(Vec::new, |queue| queue.expire(at));
+                self.record_expired(at, expired);
+            }
+            Change::QueueDrained => {
+                if let Some(queue) = self.pending.as_mut() {
+                    queue.clear();
+                }
+                self.stats.update(|stats| stats.clear_pending_occupancy());
+            }
+            Change::QueueClosed => {
+                self.pending = None;
+                self.stats.update(|stats| stats.clear_pending_occupancy());
+                self.emit(Event::FulfillmentChannelClosed);
+            }
+            Change::BackoffRecorded {
+                at,
+                explorer_id,
+                granted,
+            } => {
+                if let Some(backoffs) = self.backoffs.as_mut() {
+                    backoffs.record(explorer_id, at, granted);
+                }
+            }
+            Change::RetriedEarly { at, explorer_id } => {
+                if let Some(backoffs) = self.backoffs.as_mut() {
+                    backoffs.escalate(explorer_id, at);
+                }
+            }
+            Change::BackoffsExpired { at } => {
+                if let Some(backoffs) = self.backoffs.as_mut() {
+                    backoffs.tick(at);
+                }
+            }
+        }
+    }
 
-use crate::ExplorerRequestLimit;
-use common_game::components::energy_cell::EnergyCell;
-use common_game::components::planet::{DummyPlanetState, PlanetAI, PlanetState};
-use common_game::components::resource::{
-    BasicResource, BasicResourceType, Combinator, ComplexResource, ComplexResourceRequest,
-    Generator, GenericResource,
-};
-use common_game::components::rocket::Rocket;
-use common_game::components::sunray::Sunray;
-use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
-use std::collections::HashMap;
-use std::time::{Duration, SystemTime};
-// features:
-// - user of the planet can choose between: fair-share resource generation between explorers or
-//   explorers priority list to assign priority levels to each explorer -> planet tracks explorer requests to estimate resources usage
-// - [probably cheating by game rules] speculative resource generation to prevent sunray waste (all cells are full),
-//   based on generation requests history of specific explorers.
+    /// Returns the state of the AI covered by the changes.
+    fn replicate(&self) -> Replica {
+        Replica {
+            paused: self.paused,
+            quotas: self
+                .overrides
+                .iter()
+                .map(|(explorer_id, quota)| (*explorer_id, quota.quota))
+                .collect(),
+            tags: self.tags.clone(),
+            reservations: self.reservations.clone(),
+            unreachable: self.unreachable.clone(),
+            banned: self.banned.clone(),
+            disabled_resources: self.disabled_resources.clone(),
+            pending: self.pending.clone(),
+            backoffs: self.backoffs.clone(),
+        }
+    }
 
-/// Struct for tracking statistics about the
-/// generation requests made by an explorer to the planet.
-struct StatsRecord {
-    /// Usage score. Tracks the generation requests rate.
-    score: f32,
-    /// Timestamp of latest generation request.
-    last_req: SystemTime,
-}
+    /// Records a capability query of `explorer_id` in the statistics, charging its cost
+    /// to the policy limiting the explorer.
+    fn record_capability_poll(&mut self, explorer_id: ExplorerId, combinations: bool) {
+        self.stats
+            .update(|stats| stats.record_capability_poll(explorer_id, combinations));
+        let kind = if combinations {
+            MessageKind::SupportedCombinations
+        } else {
+            MessageKind::SupportedResources
+        };
+        self.charge_message(explorer_id, kind);
+    }
 
-impl Default for StatsRecord {
-    fn default() -> Self {
-        StatsRecord {
-            score: 0.0,
-            last_req: SystemTime::now(),
+    /// Whether the energy poll of `explorer_id` must be coalesced into the previous
+    /// answer, as it arrived less than the poll interval after it. Counts it if so.
+    fn throttle_energy_poll(&mut self, explorer_id: ExplorerId) -> bool {
+        let Some(interval) = self.energy_poll_interval else {
+            return false;
+        };
+        let now = self.now();
+        let throttled = self.energy_polls.get(&explorer_id).is_some_and(|answered| {
+            now.duration_since(*answered)
+                .map_or(true, |elapsed| elapsed < interval)
+        });
+        if throttled {
+            self.stats
+                .update(|stats| stats.record_throttled_poll(explorer_id));
+        } else {
+            self.energy_polls.insert(explorer_id, now);
         }
+        throttled
     }
-}
 
-pub struct AI {
-    explorer_stats: HashMap<u32, StatsRecord>,
-    limit_mode: ExplorerRequestLimit,
-}
+    /// Counts and reports `msg`, a message the AI doesn't handle, and answers it with
+    /// the fallback handler.
+    fn handle_unhandled(&mut self, msg: &ExplorerToPlanet) -> Option<PlanetToExplorer> {
+        let message = fallback::variant_name(msg);
+        self.stats
+            .update(|stats| stats.record_unhandled_message(&message));
+        self.emit(Event::UnhandledMessage {
+            explorer_id: msg.explorer_id().into(),
+            message,
+        });
+        self.fallback.handle(msg)
+    }
 
-impl AI {
-    const CONTENTION_WINDOW: Duration = Duration::from_secs(3);
-    const DECAY_RATE: f32 = 0.5;
-    const INACTIVE_TIMESPAN: Duration = Duration::new(Self::CONTENTION_WINDOW.as_secs(), 0);
-    const ALLOWED_REQ_BURST: f32 = 3.0;
+    /// Charges the cost of a message of `kind` other than a generation or combination
+    /// request to the policy limiting `explorer_id`.
+    fn charge_message(&mut self, explorer_id: ExplorerId, kind: MessageKind) {
+        let cost = self.costs.cost(kind);
+        if cost > 0.0 {
+            let tags = self.tags.tags_of(explorer_id);
+            let now = self.now();
+            self.policy_of_mut(explorer_id)
+                .charge(explorer_id, &tags, cost, now);
+        }
+    }
 
-    /// Creates a new AI instance.
+    /// Handles a basic resource generation request, applying the limit policy configured
+    /// for the policy arm the explorer belongs to.
     ///
-    /// This constructor initializes an empty AI struct that implements
-    /// the planet's behavior through the [`PlanetAI`] trait.
-    ///
-    /// # Returns
-    /// A new `AI` instance ready to be passed to [`Planet::new`](common_game::components::planet::Planet::new).
+    /// If a shadow policy is configured, it's evaluated on the same request and its
+    /// hypothetical decision is recorded in the statistics, without being enforced.
     ///
-    /// # Examples
-    /// ```
-    /// use rustrelli::ExplorerRequestLimit;
-    /// use rustrelli::planet::AI;
+    /// # Arguments
+    /// * `state` - Planet state, providing the energy cells.
+    /// * `generator` - Generator used to produce the resource.
+    /// * `explorer_id` - The explorer requesting the resource.
+    /// * `resource` - The requested resource type.
     ///
-    /// let ai = AI::new(ExplorerRequestLimit::None);
-    /// ```
-    pub fn new(limit_mode: ExplorerRequestLimit) -> Self {
-        AI {
-            explorer_stats: HashMap::new(),
-            limit_mode,
+    /// # Returns
+    /// The generated resource, or why the request was refused: e.g. no charged cell,
+    /// a resource missing from the generation rules or a limit vetoing it.
+    fn handle_generation(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+        offered: bool,
+    ) -> Result<BasicResource, DenialReason> {
+        self.check_banned(explorer_id)?;
+        self.check_supported(resource)?;
+        self.check_enabled(resource)?;
+        self.check_paused()?;
+        let charged = charged_cells(state);
+        // A critical explorer may take a cell decided for another one
+        let victim = match self.check_energy(explorer_id, charged) {
+            Err(DenialReason::Reserved) => Some(
+                self.preemptible(explorer_id)
+                    .ok_or(DenialReason::Reserved)?,
+            ),
+            available => available.map(|()| None)?,
+        };
+        if let Err(cause) = self.check_delivery(explorer_id) {
+            // Don't waste a cell on a resource the explorer won't receive
+            self.emit(Event::Undeliverable { explorer_id, cause });
+            return Err(DenialReason::Undeliverable);
+        }
+        let (cell, cell_index) = state.full_cell().ok_or(DenialReason::NoEnergy)?;
+        let now = self.now();
+        self.expire_batches(now);
+
+        // The rest of a granted batch was already decided: claim one of its cells.
+        if let Some(batch) = self.batches.get_mut(&explorer_id) {
+            batch.remaining -= 1;
+            if batch.remaining == 0 {
+                self.batches.remove(&explorer_id);
+            }
+            self.cancel_reservation(explorer_id);
+            self.discharge(
+                cell_index,
+                explorer_id,
+                Product::Basic(resource),
+                Source::Batch,
+            );
+            return Ok(make_basic_resource(resource, cell, generator));
+        }
+        // Offered cells were decided when offered: the request confirms the claim.
+        if self
+            .offers
+            .get(&explorer_id)
+            .is_some_and(|offer| offer.confirmed_by(resource, now))
+        {
+            self.offers.remove(&explorer_id);
+            self.cancel_reservation(explorer_id);
+            self.stats.update(|stats| stats.record_claim_confirmed());
+            self.discharge(
+                cell_index,
+                explorer_id,
+                Product::Basic(resource),
+                Source::Offer,
+            );
+            return Ok(make_basic_resource(resource, cell, generator));
+        }
+        // The explorer moved on to another resource
+        if self.offers.remove(&explorer_id).is_some() {
+            self.cancel_reservation(explorer_id);
+            self.stats.update(|stats| stats.record_claim_expired());
+        }
+        // Leased cells were decided when the lease was granted.
+        if let Some(lease) = self
+            .lease
+            .as_mut()
+            .filter(|lease| lease.claimed_by(explorer_id, resource, now))
+        {
+            lease.remaining -= 1;
+            if lease.remaining == 0 {
+                self.lease = None;
+                self.emit(Event::LeaseEnded {
+                    explorer_id,
+                    unclaimed: 0,
+                });
+            }
+            self.cancel_reservation(explorer_id);
+            self.discharge(
+                cell_index,
+                explorer_id,
+                Product::Basic(resource),
+                Source::Lease,
+            );
+            return Ok(make_basic_resource(resource, cell, generator));
+        }
+
+        self.activate_incoming(now);
+        let request = Request {
+            now,
+            units: match victim {
+                Some(_) => 1,
+                None => self.batch_units(explorer_id, charged),
+            },
+            ..self.request(explorer_id, resource)
+        };
+        let decision = if self.is_warming_up(now) {
+            // Only observe the demand until the policy is calibrated
+            self.policy_of_mut(explorer_id)
+                .record(&request, Decision::Grant);
+            Decision::Grant
+        } else {
+            self.policy_of_mut(explorer_id).admit(&request)
+        };
+        if self.is_planet_wide(explorer_id)
+            && let Some(incoming) = self.incoming.as_mut()
+        {
+            incoming.policy.record(&request, decision);
+        }
+
+        if self.shadow.is_some()
+            && let Some(started) = self.begin_extra(Extra::Speculation)
+            && let Some(shadow) = self.shadow.as_mut()
+        {
+            let shadow_decision = shadow.admit(&request);
+            self.stats
+                .update(|stats| stats.record_shadow(decision, shadow_decision));
+            self.end_extra(Extra::Speculation, started);
+        }
+
+        match decision {
+            // Discharge the cell and produce the resource.
+            Decision::Grant
+                if victim.is_none()
+                    && offered
+                    && request.units == 1
+                    && self.is_contended(explorer_id, charged) =>
+            {
+                // Keep the cell until the explorer confirms it's still there
+                let window = self
+                    .claim_config
+                    .map_or(Duration::ZERO, |config| config.window);
+                self.offers.insert(
+                    explorer_id,
+                    Offer {
+                        resource,
+                        expires: now + window,
+                    },
+                );
+                self.timers
+                    .schedule(now + window, Expiry::Offer(explorer_id));
+                self.reserve(explorer_id);
+                self.stats.update(|stats| stats.record_claim_offered());
+                Err(DenialReason::Offered)
+            }
+            Decision::Grant => {
+                if let Some(victim) = victim {
+                    self.preempt(victim);
+                }
+                self.cancel_reservation(explorer_id);
+                if let (Some(config), true) = (self.batch_config, request.units > 1) {
+                    // Keep the rest of the batch for the next requests of the series
+                    self.batches.insert(
+                        explorer_id,
+                        Batch {
+                            remaining: request.units - 1,
+                            expires: now + config.window,
+                        },
+                    );
+                    self.timers
+                        .schedule(now + config.window, Expiry::Batch(explorer_id));
+                    for _ in 1..request.units {
+                        self.reserve(explorer_id);
+                    }
+                }
+                self.discharge(
+                    cell_index,
+                    explorer_id,
+                    Product::Basic(resource),
+                    Source::Request,
+                );
+                Ok(make_basic_resource(resource, cell, generator))
+            }
+            // The planet refused the request due to policy limits,
+            // preserving the energy cell for a "fairer" user.
+            Decision::Deny(reason) => Err(reason),
         }
     }
 
-    /// Applies linear decay to the usage scores of all tracked explorers.
+    /// Handles a complex resource combination request, if the planet has combination
+    /// rules. Combinations consume a charged cell, so they're admitted like generation
+    /// requests: they respect bans, pauses, reservations, backoffs, the delivery of the
+    /// answer and the limit policy. A combination the combinator then fails still counts
+    /// against the limits of the explorer.
     ///
-    /// This method iterates through every explorer in the statistics map and reduces their
-    /// score proportional to the time elapsed since their last request. The decay is calculated
-    /// using `Self::DECAY_RATE`.
-    ///
-    /// The score is clamped at `0.0` to prevent negative usage values. If the elapsed time
-    /// cannot be determined (e.g., due to system time errors), `Self::INACTIVE_TIMESPAN`
-    /// is used as a fallback duration.
-    fn decay_scores(&mut self) {
-        for (_, stats) in self.explorer_stats.iter_mut() {
-            stats.score = 0.0_f32.max(
-                stats.score
-                    - Self::DECAY_RATE
-                        * stats
-                            .last_req
-                            .elapsed()
-                            .unwrap_or(Self::INACTIVE_TIMESPAN)
-                            .as_secs_f32(),
-            )
+    /// # Returns
+    /// The combined resource, or why the request was refused together with the
+    /// resources given by the explorer.
+    fn handle_combination(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        combinator: &Combinator,
+        explorer_id: ExplorerId,
+        request: ComplexResourceRequest,
+    ) -> Result<ComplexResource, (RefusalReason, GenericResource, GenericResource)> {
+        let refuse = |reason, request| {
+            let (first, second) = extract_generic_resources(request);
+            Err((reason, first, second))
+        };
+        let complex = complex_type(&request);
+        let recipes = &self.capabilities(generator, combinator).combinations;
+        let (any, supported) = (!recipes.is_empty(), recipes.contains(&complex));
+        if !any {
+            return refuse(RefusalReason::NoRecipes, request);
+        }
+        if !supported {
+            return refuse(RefusalReason::UnsupportedRecipe(complex), request);
+        }
+
+        let charged = charged_cells(state);
+        let now = self.now();
+        let available = self.check_backoff(explorer_id, now).and_then(|()| {
+            let outcome = self
+                .check_banned(explorer_id)
+                .and_then(|()| self.check_paused())
+                .and_then(|()| self.check_energy(explorer_id, charged))
+                .and_then(|()| self.admit_combination(explorer_id, complex, now));
+            self.record_backoff(explorer_id, now, outcome.is_ok());
+            outcome
+        });
+        let decided = |denial| Change::Combination {
+            at: now,
+            explorer_id,
+            resource: complex,
+            denial,
+        };
+        let (cell, cell_index) = match (available, state.full_cell()) {
+            (Ok(()), Some(full)) => full,
+            (available, _) => {
+                let reason = available.err().unwrap_or(DenialReason::NoEnergy);
+                self.apply(decided(Some(reason)));
+                return refuse(RefusalReason::Denied(reason), request);
+            }
+        };
+        let combined = make_complex_resource(request, cell, combinator);
+        if !state.cell(cell_index).is_charged() {
+            self.discharge(
+                cell_index,
+                explorer_id,
+                Product::Complex(complex),
+                Source::Request,
+            );
+        }
+        if combined.is_ok() {
+            self.apply(decided(None));
         }
+        combined
     }
 
-    /// Increments the usage score for a specific explorer by the standard request cost.
-    ///
-    /// This represents the "heat" added to an explorer's tracking profile when they
-    /// perform an action (like requesting a resource). The cost is currently fixed at `1.0`.
-    ///
-    /// # Arguments
-    /// * `explorer_id` - The unique identifier of the explorer incurring the cost.
-    ///
-    /// # Notes
-    /// This method uses `and_modify`, so it will **do nothing** if the `explorer_id`
-    /// is not already present in `self.explorer_stats`. The explorer must be registered
-    /// before costs can be added.
-    fn add_req_cost(&mut self, explorer_id: u32) {
-        self.explorer_stats
-            .entry(explorer_id)
-            .and_modify(|stats| stats.score += 1.0);
+    /// Decides on a combination of `complex` by `explorer_id` at `now` with the limit
+    /// policy, once the explorer can receive the answer.
+    fn admit_combination(
+        &mut self,
+        explorer_id: ExplorerId,
+        complex: ComplexResourceType,
+        now: SystemTime,
+    ) -> Result<(), DenialReason> {
+        if let Err(cause) = self.check_delivery(explorer_id) {
+            // Don't waste a cell on a resource the explorer won't receive
+            self.emit(Event::Undeliverable { explorer_id, cause });
+            return Err(DenialReason::Undeliverable);
+        }
+        let request = Request {
+            now,
+            ..self.request(explorer_id, complex)
+        };
+        let decision = if self.is_warming_up(now) {
+            self.policy_of_mut(explorer_id)
+                .record(&request, Decision::Grant);
+            Decision::Grant
+        } else {
+            self.policy_of_mut(explorer_id).admit(&request)
+        };
+        if self.is_planet_wide(explorer_id)
+            && let Some(incoming) = self.incoming.as_mut()
+        {
+            incoming.policy.record(&request, decision);
+        }
+        match decision {
+            Decision::Grant => Ok(()),
+            Decision::Deny(reason) => Err(reason),
+        }
     }
 
-    /// Retrieves the current usage score for a specific explorer.
-    ///
-    /// # Arguments
-    /// * `explorer_id` - The unique identifier of the explorer to look up.
+    /// Removes the pending request to serve next with one of `charged` cells from the
+    /// queue: the oldest one of the holder of the oldest reservation, if any, otherwise
+    /// the oldest one of an explorer with the highest policy priority, drawn by weight
+    /// among the explorers tied (see [`pending`](crate::pending#ordering)).
     ///
     /// # Returns
-    /// * `Some(f32)` - The current score if the explorer is being tracked.
-    /// * `None` - If the explorer is not found in the statistics.
-    fn score(&self, explorer_id: u32) -> Option<f32> {
-        self.explorer_stats
-            .get(&explorer_id)
-            .map(|stats| stats.score)
+    /// `None` if the queue is empty or all charged cells are reserved by explorers
+    /// without pending requests.
+    fn pop_pending(&mut self, charged: usize) -> Option<PendingRequest> {
+        let index = self.next_pending(charged)?;
+        let entry = *self.pending.as_ref()?.entries().get(index)?;
+        self.apply(Change::Unqueued { index });
+        Some(entry)
     }
 
-    /// Calculates the average usage score across all currently tracked explorers.
-    ///
-    /// This metric is useful for determining the dynamic threshold for rate limiting.
+    /// Returns the index of the pending request to serve next with one of `charged`
+    /// cells, see [`Self::pop_pending`].
+    fn next_pending(&mut self, charged: usize) -> Option<usize> {
+        let queue = self.pending.as_ref()?;
+        let reserved = self.reservations.iter().find_map(|holder| {
+            queue
+                .entries()
+                .iter()
+                .position(|entry| entry.explorer_id == *holder)
+        });
+        if reserved.is_some() {
+            return reserved;
+        }
+        if charged <= self.reservations.len() {
+            return None;
+        }
+
+        // The oldest request of each explorer tied at the highest priority
+        let mut tied: Vec<(usize, ExplorerId, f64)> = Vec::new();
+        let mut top = f32::NEG_INFINITY;
+        for (index, entry) in queue.entries().iter().enumerate() {
+            let request = self.request(entry.explorer_id, entry.resource);
+            let priority = self.policy_of(entry.explorer_id).priority(&request);
+            match priority.total_cmp(&top) {
+                Ordering::Less => continue,
+                Ordering::Greater => {
+                    top = priority;
+                    tied.clear();
+                }
+                Ordering::Equal => {}
+            }
+            let explorer_id = entry.explorer_id;
+            if tied.iter().all(|(_, tied_id, _)| *tied_id != explorer_id) {
+                tied.push((index, explorer_id, f64::from(request.weight)));
+            }
+        }
+
+        match tied.as_slice() {
+            [] => None,
+            [(index, _, _)] => Some(*index),
+            _ => {
+                let weights: Vec<f64> = tied.iter().map(|(_, _, weight)| *weight).collect();
+                Some(tied[self.tie_break.choose_weighted(&weights)].0)
+            }
+        }
+    }
+
+    /// Drops the pending requests that waited longer than the timeout at `now`.
+    fn expire_pending(&mut self, now: SystemTime) {
+        if self.pending.is_some() {
+            self.apply(Change::PendingExpired { at: now });
+        }
+    }
+
+    /// Reports the pending requests dropped at `now`, having waited longer than the
+    /// timeout.
+    fn record_expired(&self, now: SystemTime, expired: Vec<PendingRequest>) {
+        for entry in expired {
+            let waited = now.duration_since(entry.queued_at).unwrap_or_default();
+            self.stats.update(|stats| {
+                stats.record_expired();
+                stats.record_residency(entry.explorer_id, waited);
+            });
+            self.record_occupancy(entry.explorer_id);
+            self.emit(Event::QueueTimeout {
+                explorer_id: entry.explorer_id,
+                waited,
+            });
+        }
+    }
+
+    /// Records the number of pending requests of `explorer_id` in the statistics.
+    fn record_occupancy(&self, explorer_id: ExplorerId) {
+        let count = self
+            .pending
+            .as_ref()
+            .map_or(0, |queue| queue.occupancy(explorer_id));
+        self.stats
+            .update(|stats| stats.record_pending_occupancy(explorer_id, count));
+    }
+
+    /// Serves the pending requests while charged cells are available, sending the
+    /// produced resources to the host. Requests denied by the limit policy are dropped.
     ///
-    /// # Returns
-    /// The arithmetic mean of all scores. Returns `NaN` if `self.explorer_stats` is empty.
-    fn avg_score(&self) -> f32 {
-        let mut sum = 0.0_f32;
+    /// Stops while the host isn't keeping up with the fulfillments, and disables
+    /// deferred fulfillment if the host dropped their receiver. Resources that can't be
+    /// delivered end up in the dead letters.
+    fn serve_pending(&mut self, state: &mut PlanetState, generator: &Generator) {
+        loop {
+            let charged = charged_cells(state);
+            let blocked = self.outbox.as_ref().is_none_or(Outbox::is_blocked);
+            if charged == 0 || self.pending.is_none() || blocked || self.paused.is_some() {
+                break;
+            }
+            let Some(entry) = self.pop_pending(charged) else {
+                break;
+            };
+            let residency = self
+                .now()
+                .duration_since(entry.queued_at)
+                .unwrap_or_default();
+            self.stats
+                .update(|stats| stats.record_residency(entry.explorer_id, residency));
+            let outcome =
+                self.decide_generation(state, generator, entry.explorer_id, entry.resource, false);
+            let receipt = self.record_generation(
+                entry.explorer_id,
+                entry.resource,
+                outcome.as_ref().err().copied(),
+            );
+
+            if let (Ok(resource), Some(receipt), Some(outbox)) =
+                (outcome, receipt, self.outbox.as_mut())
+            {
+                outbox.send(Fulfillment {
+                    explorer_id: entry.explorer_id,
+                    resource,
+                    receipt,
+                });
+                self.check_outbox();
+            }
+        }
+    }
+
+    /// Iterates over the enforced policies: the planet-wide one, the arms' ones
+    /// and the individual quotas.
+    fn policies(&self) -> impl Iterator<Item = &dyn RequestLimitPolicy> {
+        std::iter::once(self.policy.as_ref())
+            .chain(self.arms.iter().map(|arm| arm.policy.as_ref()))
+            .chain(self.overrides.values().map(|quota| quota.policy.as_ref()))
+    }
 
-        for (_, stats) in self.explorer_stats.iter() {
-            sum += stats.score
+    /// Returns the capabilities of the planet, computing them if the AI wasn't started.
+    fn capabilities(&mut self, generator: &Generator, combinator: &Combinator) -> &Capabilities {
+        if self.capabilities.is_none() {
+            self.update_capabilities(generator, combinator);
         }
-        sum / self.explorer_stats.len() as f32
+        self.capabilities
+            .get_or_insert_with(|| Capabilities::new(generator, combinator))
     }
 
-    /// Counts the number of explorers considered "active" at this moment.
+    /// Computes the capabilities of the planet, bumping the capability generation if
+    /// they differ from the known ones. A change is reported as an
+    /// [`Event::CapabilitiesChanged`].
+    fn update_capabilities(&mut self, generator: &Generator, combinator: &Combinator) {
+        let capabilities = Capabilities::new(generator, combinator);
+        let known = self.capabilities.replace(capabilities);
+        if known.as_ref() == self.capabilities.as_ref() {
+            return;
+        }
+        self.capability_generation += 1;
+        let generation = self.capability_generation;
+        self.stats
+            .update(|stats| stats.record_capability_generation(generation));
+        if known.is_some() {
+            self.emit(Event::CapabilitiesChanged { generation });
+        }
+    }
+
+    /// Returns the capability generation of the planet: zero until the AI computes the
+    /// capabilities of the planet, then bumped each time they change.
     ///
-    /// An explorer is defined as active if the time elapsed since their last request
-    /// is less than the defined `Self::CONTENTION_WINDOW`.
+    /// Integrators answering capability queries out-of-band cache the answers along
+    /// with the generation, and drop them when it changes.
+    pub fn capability_generation(&self) -> u64 {
+        self.capability_generation
+    }
+
+    /// Keeps `entry` if it's a grant or an epoch and policies warm up, forgetting the
+    /// entries older than the warm-up.
+    fn remember(&mut self, entry: JournalEntry) {
+        let Some(warm_up) = self.policy_warm_up else {
+            return;
+        };
+        if matches!(
+            entry,
+            JournalEntry::Generation { denial: None, .. } | JournalEntry::Epoch { .. }
+        ) {
+            self.recent.push_back(entry);
+        }
+        while self.recent.front().is_some_and(|oldest| {
+            entry
+                .at()
+                .duration_since(oldest.at())
+                .is_ok_and(|age| age > warm_up)
+        }) {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Stores the final snapshots to the storage, if any: the cell timeline, if
+    /// recorded, and the statistics, with the `serde` feature.
+    fn persist(&mut self) {
+        let Some(storage) = self.storage.as_mut() else {
+            return;
+        };
+        let mut snapshots = Vec::new();
+        if let Some(timeline) = self
+            .stats
+            .read(|stats| stats.cell_timeline().map(CellTimeline::to_json))
+        {
+            snapshots.push((storage::CELL_TIMELINE, Ok(timeline.into_bytes())));
+        }
+        #[cfg(feature = "serde")]
+        snapshots.push((
+            storage::STATS,
+            self.stats
+                .read(storage::StatsSnapshot::write)
+                .map_err(std::io::Error::from),
+        ));
+        let failures: Vec<_> = snapshots
+            .into_iter()
+            .filter_map(|(key, snapshot)| {
+                let error = snapshot.and_then(|data| storage.put(key, &data)).err()?;
+                Some(Event::StorageFailed {
+                    key: key.to_string(),
+                    error: error.to_string(),
+                })
+            })
+            .collect();
+        for failure in failures {
+            self.emit(failure);
+        }
+    }
+
+    /// Writes `entry` to the journal, if enabled.
+    fn journal(&mut self, entry: JournalEntry) {
+        if let Some(journal) = self.journal.as_mut() {
+            let written = journal.write(&entry);
+            self.check_journal(written);
+        }
+    }
+
+    /// Stops journaling if a write to the journal failed.
+    fn check_journal(&mut self, written: std::io::Result<()>) {
+        if let Err(error) = written {
+            self.journal = None;
+            self.emit(Event::JournalFailed {
+                error: error.to_string(),
+            });
+        }
+    }
+
+    /// Reports a violation of `invariant`, if any, then fails a debug assertion.
     ///
-    /// # Returns
-    /// The count of explorers who have interacted with the planet recently enough to
-    /// be considered competitors for resources.
-    fn active_explorers(&self) -> u32 {
-        self.explorer_stats
-            .iter()
-            .filter(|(_, stats)| {
-                stats.last_req.elapsed().unwrap_or(Self::INACTIVE_TIMESPAN)
-                    < Self::CONTENTION_WINDOW
+    /// See the [`invariants`](crate::invariants) module.
+    fn check_invariant(&self, invariant: Invariant, checked: Result<(), String>) {
+        if let Err(details) = checked {
+            self.emit(Event::InvariantViolated {
+                invariant,
+                details: details.clone(),
+            });
+            debug_assert!(false, "{invariant:?} violated: {details}");
+        }
+    }
+
+    /// Checks the invariants holding between two messages, if enabled in this build.
+    fn check_invariants(&self, state: &PlanetState) {
+        if !invariants::ENABLED {
+            return;
+        }
+        let Some(started) = self.begin_extra(Extra::Audit) else {
+            return;
+        };
+        let checked = self.energy.check(charged_cells(state));
+        self.check_invariant(Invariant::EnergyConservation, checked);
+        if let Some(queue) = &self.pending {
+            self.check_invariant(Invariant::QueueAccounting, queue.check());
+        }
+        if let Some(replica) = &self.replica {
+            self.check_invariant(Invariant::ChangeReplay, replica.check(&self.replicate()));
+        }
+        self.end_extra(Extra::Audit, started);
+    }
+
+    /// Handles a generation request like [`Self::handle_generation`], checking that it
+    /// discharged a single cell if granted, if enabled in this build.
+    fn decide_generation(
+        &mut self,
+        state: &mut PlanetState,
+        generator: &Generator,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+        offered: bool,
+    ) -> Result<BasicResource, DenialReason> {
+        let before = self.energy.discharged();
+        let outcome = self.handle_generation(state, generator, explorer_id, resource, offered);
+        if invariants::ENABLED {
+            let checked =
+                invariants::check_discharge(before, self.energy.discharged(), outcome.is_ok());
+            self.check_invariant(Invariant::SingleDischarge, checked);
+        }
+        outcome
+    }
+
+    /// Counts the denials in a row of `explorer_id`, reporting the streaks reaching the
+    /// alert length. An offer neither extends nor ends the streak.
+    fn record_streak(&mut self, explorer_id: ExplorerId, denial: Option<DenialReason>) {
+        let streak = match denial {
+            Some(DenialReason::Offered) => return,
+            Some(_) => {
+                let streak = self.streaks.entry(explorer_id).or_default();
+                *streak += 1;
+                *streak
+            }
+            None => {
+                self.streaks.remove(&explorer_id);
+                0
+            }
+        };
+        self.stats
+            .update(|stats| stats.record_streak(explorer_id, streak));
+        if self.streak_alert == Some(streak) {
+            self.emit(Event::DenialStreak {
+                explorer_id,
+                streak,
+            });
+        }
+    }
+
+    /// Records a change of the energy cell `cell`.
+    fn record_cell(&mut self, cell: usize, change: CellChange) {
+        self.apply(Change::Cell {
+            at: self.now(),
+            cell,
+            change,
+        });
+    }
+
+    /// Records the discharge of the energy cell `cell` to produce `resource` for
+    /// `explorer_id`, entitled to it by `source`.
+    fn discharge(
+        &mut self,
+        cell: usize,
+        explorer_id: ExplorerId,
+        resource: Product,
+        source: Source,
+    ) {
+        self.record_cell(
+            cell,
+            CellChange::Discharged(Provenance {
+                explorer_id,
+                resource,
+                source,
+            }),
+        );
+    }
+
+    /// Publishes the distribution of the fair-share usage scores to the shared
+    /// statistics. It visits every active explorer, so it runs after sunrays only.
+    fn observe_scores(&self) {
+        let now = self.now();
+        let mut histogram = ScoreHistogram::default();
+        for policy in self.policies() {
+            policy.record_scores(now, &mut histogram);
+        }
+        self.stats
+            .update(|stats| stats.set_score_histogram(histogram));
+    }
+
+    /// Counts the `charged` cells in an answer about the available energy, leaving out
+    /// the stockpile not served yet unless `shown`: see the
+    /// [`stockpile`](crate::stockpile) module.
+    fn shown_cells(&self, charged: usize, shown: bool) -> usize {
+        if shown {
+            return charged;
+        }
+        let unserved = self.stats.read(|stats| stats.stockpile().remaining_total());
+        let unserved = usize::try_from(unserved)
+            .unwrap_or(usize::MAX)
+            .min(self.seeded_cells.len());
+        charged.saturating_sub(unserved)
+    }
+
+    /// Publishes the current planet state, enriched with the AI information,
+    /// to the shared statistics.
+    fn observe_state(&mut self, state: &PlanetState) {
+        let now = self.now();
+        self.charged_cells = charged_cells(state);
+        if let Some(view) = &self.query_view {
+            let shown = self.stockpile_visibility.to_explorers();
+            view.observe_charged_cells(self.shown_cells(self.charged_cells, shown));
+        }
+        self.check_invariants(state);
+        let monitored = self.shown_cells(
+            self.charged_cells,
+            self.stockpile_visibility.to_monitoring(),
+        );
+        let mut hidden = self.charged_cells - monitored;
+        #[cfg(feature = "otel")]
+        self.otel.observe_charged_cells(monitored);
+        #[cfg(feature = "metrics-facade")]
+        self.metrics.observe_charged_cells(monitored);
+        let tracked_explorers = self.policies().map(|p| p.tracked_explorers()).sum();
+        let active_explorers = self.policies().map(|p| p.active_explorers(now)).sum();
+
+        // Updated in place: this runs for every message, so it must not allocate.
+        self.stats.update(|stats| {
+            stats.observe_state(|observed| {
+                observed.energy_cells.clear();
+                observed.energy_cells.extend(state.cells_iter().map(|cell| {
+                    let hide = cell.is_charged() && hidden > 0;
+                    hidden -= usize::from(hide);
+                    cell.is_charged() && !hide
+                }));
+                observed.charged_cells_count = monitored;
+                if observed.limit_mode != self.limit_mode {
+                    observed.limit_mode = self.limit_mode.clone();
+                }
+                observed.tracked_explorers = tracked_explorers;
+                observed.active_explorers = active_explorers;
+                observed.pending_requests = self.pending.as_ref().map_or(0, |queue| queue.len());
+                observed.paused = self.paused.is_some();
             })
-            .count() as u32
+        });
     }
 }
 
@@ -182,20 +2980,50 @@ impl PlanetAI for AI {
     fn handle_sunray(
         &mut self,
         state: &mut PlanetState,
-        _generator: &Generator,
+        generator: &Generator,
         _combinator: &Combinator,
         sunray: Sunray,
     ) {
+        #[cfg(feature = "profiling")]
+        let _timer = Timer::start(&self.stats, Handler::Sunray);
+        self.before_message(state);
+        self.apply(Change::Sunray { at: self.now() });
+        let charged_cell = state.empty_cell().map(|(_, index)| index);
         state.charge_cell(sunray);
+        if let Some(cell) = charged_cell {
+            self.record_cell(cell, CellChange::Charged);
+        }
+        self.serve_pending(state, generator);
+        self.observe_state(state);
+        self.observe_scores();
+
+        if let Some(window) = self.load_window {
+            self.emit(Event::Load(self.stats.load(window)));
+        }
+        if let Some(size) = self.leaderboard_size {
+            self.events
+                .emit(Event::Leaderboard(self.stats.leaderboard(size)));
+        }
     }
 
     fn handle_asteroid(
         &mut self,
-        _state: &mut PlanetState,
+        state: &mut PlanetState,
         _generator: &Generator,
         _combinator: &Combinator,
     ) -> Option<Rocket> {
-        // Type D planets cannot build rockets, so they will be destroyed by asteroids
+        #[cfg(feature = "profiling")]
+        let _timer = Timer::start(&self.stats, Handler::Asteroid);
+        self.before_message(state);
+        // Type D planets cannot build rockets, so they will be destroyed by asteroids.
+        // The orchestrator kills destroyed planets without stopping them: store now
+        if let Some(journal) = self.journal.as_mut() {
+            let _ = journal.flush();
+        }
+        self.publish_stats(self.now(), true);
+        self.persist();
+        let totals = self.stats.read(Stats::totals);
+        self.lifecycle(LifecycleStage::DestroyedByAsteroid { totals });
         None
     }
 
@@ -205,9 +3033,99 @@ impl PlanetAI for AI {
         _generator: &Generator,
         _combinator: &Combinator,
     ) -> DummyPlanetState {
+        #[cfg(feature = "profiling")]
+        let _timer = Timer::start(&self.stats, Handler::InternalState);
+        self.before_message(state);
+        self.observe_state(state);
         state.to_dummy()
     }
 
+    fn on_explorer_arrival(
+        &mut self,
+        _state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+        explorer_id: u32,
+    ) {
+        self.register(explorer_id.into());
+    }
+
+    fn on_explorer_departure(
+        &mut self,
+        _state: &mut PlanetState,
+        _generator: &Generator,
+        _combinator: &Combinator,
+        explorer_id: u32,
+    ) {
+        let explorer_id = ExplorerId::from(explorer_id);
+        self.registered.remove(&explorer_id);
+        if let Some(notices) = &self.gateway_notices {
+            let _ = notices.send(Notice::Departed(explorer_id));
+        }
+        self.emit(Event::Registration {
+            explorer_id,
+            transition: Transition::Departed,
+        });
+    }
+
+    fn on_start(&mut self, state: &PlanetState, generator: &Generator, combinator: &Combinator) {
+        #[cfg(feature = "profiling")]
+        let _timer = Timer::start(&self.stats, Handler::Start);
+        self.update_capabilities(generator, combinator);
+        if self.journal.is_none()
+            && let Some(storage) = self.storage.as_mut()
+        {
+            match storage.append(storage::JOURNAL) {
+                Ok(out) => self.journal = Some(JournalWriter::new(out)),
+                Err(error) => self.emit(Event::JournalFailed {
+                    error: error.to_string(),
+                }),
+            }
+        }
+        if let Some(journal) = self.journal.as_mut() {
+            // Seeded before any journaled message, so replay starts with the stockpile
+            let cells = state.cells_iter().count();
+            let stocked: u32 = self.stockpile.iter().map(|(_, amount)| amount).sum();
+            let charged = (charged_cells(state) + stocked as usize).min(cells);
+            let started = journal.start(cells, charged);
+            self.check_journal(started);
+        }
+
+        self.energy.start(charged_cells(state));
+        if invariants::ENABLED {
+            self.replica = Some(self.replicate());
+        }
+        if let Some(view) = &self.query_view {
+            view.observe_charged_cells(charged_cells(state));
+            view.set_running(true);
+        }
+
+        // Game time of the policy schedules starts with the planet AI
+        let now = self.now();
+        self.for_each_policy(|policy| policy.start(now));
+        self.warm_up_until = self.warm_up.map(|warm_up| now + warm_up);
+        self.game_start = Some(now);
+        self.lifecycle(LifecycleStage::Started);
+    }
+
+    fn on_stop(&mut self, _state: &PlanetState, _generator: &Generator, _combinator: &Combinator) {
+        #[cfg(feature = "profiling")]
+        let _timer = Timer::start(&self.stats, Handler::Stop);
+        if let Some(view) = &self.query_view {
+            view.set_running(false);
+        }
+        if let Some(journal) = self.journal.as_mut() {
+            let _ = journal.flush();
+        }
+        if let Some(timeout) = self.drain_timeout {
+            self.drain(timeout);
+        }
+        self.publish_stats(self.now(), true);
+        self.persist();
+        let totals = self.stats.read(Stats::totals);
+        self.lifecycle(LifecycleStage::Stopped { totals });
+    }
+
     fn handle_explorer_msg(
         &mut self,
         state: &mut PlanetState,
@@ -215,93 +3133,148 @@ impl PlanetAI for AI {
         combinator: &Combinator,
         msg: ExplorerToPlanet,
     ) -> Option<PlanetToExplorer> {
-        match msg {
+        #[cfg(feature = "profiling")]
+        let _timer = Timer::start(&self.stats, Handler::of(&msg));
+        let Some(explorer_id) = self.admit(&msg) else {
+            return self.deny_untracked(msg);
+        };
+        if let Some(budget) = &self.budget {
+            budget.start(Instant::now());
+        }
+        #[cfg(feature = "otel")]
+        let span = self.otel.start_request(&msg);
+        #[cfg(feature = "metrics-facade")]
+        let (message, started) = (message_name(&msg), std::time::Instant::now());
+        self.before_message(state);
+        // Serves the requests buffered while paused, once resumed
+        self.serve_pending(state, generator);
+        let response = match msg {
             ExplorerToPlanet::SupportedResourceRequest { .. } => {
+                self.record_capability_poll(explorer_id, false);
                 Some(PlanetToExplorer::SupportedResourceResponse {
-                    resource_list: generator.all_available_recipes(),
+                    resource_list: self.capabilities(generator, combinator).resources.clone(),
                 })
             }
 
             ExplorerToPlanet::SupportedCombinationRequest { .. } => {
+                self.record_capability_poll(explorer_id, true);
                 Some(PlanetToExplorer::SupportedCombinationResponse {
-                    combination_list: combinator.all_available_recipes(),
+                    combination_list: self
+                        .capabilities(generator, combinator)
+                        .combinations
+                        .clone(),
                 })
             }
 
-            ExplorerToPlanet::GenerateResourceRequest {
-                explorer_id,
-                resource,
-            } => {
-                if let Some((cell, _)) = state.full_cell() {
-                    match self.limit_mode {
-                        ExplorerRequestLimit::None => {
-                            return Some(PlanetToExplorer::GenerateResourceResponse {
-                                resource: Some(make_basic_resource(resource, cell, generator)),
-                            });
-                        }
-                        ExplorerRequestLimit::FairShare => {}
+            ExplorerToPlanet::GenerateResourceRequest { resource, .. } => {
+                let now = self.now();
+                let game_time = self
+                    .game_start
+                    .and_then(|start| now.duration_since(start).ok());
+                self.stats.update(|stats| {
+                    stats.record_arrival(explorer_id, now);
+                    stats.record_affinity(explorer_id, resource);
+                    if let Some(at) = game_time {
+                        stats.record_demand(at, resource);
                     }
-
-                    // Add explorer_id entry to map if not already present
-                    // then updates time of latest request.
-                    self.explorer_stats
-                        .entry(explorer_id)
-                        .and_modify(|stats| stats.last_req = SystemTime::now())
-                        .or_default();
-
-                    // Apply the "Leaky Bucket" logic.
-                    // First decay the score based on the time elapsed since the
-                    // *previous* request (rewarding idle time), then add the cost of the *current* request.
-                    self.decay_scores();
-                    self.add_req_cost(explorer_id);
-
-                    // Calculate Dynamic Tolerance.
-                    // We adjust strictness based on contention.
-                    // - Low contention (few active explorers): High tolerance. We allow bursts to maximize energy usage.
-                    // - High contention (many active explorers): Low tolerance. We enforce strict equality to prevent hogging.
-                    let active_explorers = self.active_explorers();
-                    let tolerance: f32 = 1.0 + Self::ALLOWED_REQ_BURST / active_explorers as f32;
-
-                    // Access to energy is granted if either:
-                    // A) The explorer is the sole active user (Max Utilization Strategy).
-                    //    We never want to waste energy if only one explorer is asking for it.
-                    // B) The explorer's usage score is within the calculated tolerance of the group average.
-                    let result = if active_explorers == 1
-                        || self.score(explorer_id).unwrap() <= self.avg_score() * tolerance
-                    {
-                        // ACCESS GRANTED: Discharge the cell and produce the resource.
-                        Some(make_basic_resource(resource, cell, generator))
-                    } else {
-                        // ACCESS DENIED: Rate limit exceeded.
-                        // We return `None` to indicate the planet refused the request due to policy limits,
-                        // preserving the energy cell for a "fairer" user.
-                        None
+                });
+                if self
+                    .misconduct
+                    .as_mut()
+                    .is_some_and(|tracker| tracker.arrival(explorer_id, now))
+                {
+                    self.record_misconduct(explorer_id, Signal::Burst);
+                }
+                if self.paused == Some(PauseMode::Drop) {
+                    self.stats
+                        .update(|stats| stats.record_paused(PauseMode::Drop));
+                    None
+                } else {
+                    let outcome = self.check_backoff(explorer_id, now).and_then(|()| {
+                        let outcome =
+                            self.decide_generation(state, generator, explorer_id, resource, true);
+                        // An offer isn't a denial: the explorer is expected to confirm it
+                        let denied = outcome
+                            .as_ref()
+                            .is_err_and(|reason| *reason != DenialReason::Offered);
+                        self.record_backoff(explorer_id, now, !denied);
+                        outcome
+                    });
+                    self.record_generation(explorer_id, resource, outcome.as_ref().err().copied());
+                    if outcome.is_ok() {
+                        self.stats
+                            .update(|stats| stats.record_received(explorer_id, resource));
+                    }
+                    let deferred = match outcome {
+                        Err(DenialReason::NoEnergy) => true,
+                        Err(DenialReason::Paused) => self.paused == Some(PauseMode::Buffer),
+                        _ => false,
                     };
+                    // The host couldn't route the fulfillment of an explorer sharing the
+                    // overflow bucket: answer it right away
+                    let deferred = deferred && explorer_id != ExplorerId::Overflow;
+                    if matches!(outcome, Err(DenialReason::Paused)) {
+                        let mode = match self.pending {
+                            Some(_) if deferred => PauseMode::Buffer,
+                            _ => PauseMode::Reject,
+                        };
+                        self.stats.update(|stats| stats.record_paused(mode));
+                    }
+                    if deferred && self.pending.is_some() {
+                        self.apply(Change::Queued {
+                            at: now,
+                            explorer_id,
+                            resource,
+                        });
+                    }
+                    self.observe_state(state);
 
-                    Some(PlanetToExplorer::GenerateResourceResponse { resource: result })
-                } else {
-                    Some(PlanetToExplorer::GenerateResourceResponse { resource: None })
+                    Some(PlanetToExplorer::GenerateResourceResponse {
+                        resource: outcome.ok(),
+                    })
                 }
             }
 
             ExplorerToPlanet::CombineResourceRequest { msg, .. } => {
-                let input_resources = extract_generic_resources(msg);
+                let complex_response = self
+                    .handle_combination(state, generator, combinator, explorer_id, msg)
+                    .map_err(|(reason, first, second)| {
+                        (self.refusals.format(&reason), first, second)
+                    });
+                self.observe_state(state);
 
-                Some(PlanetToExplorer::CombineResourceResponse {
-                    complex_response: Err((
-                        "This planet type can't combine resources.".to_string(),
-                        input_resources.0,
-                        input_resources.1,
-                    )),
-                })
+                Some(PlanetToExplorer::CombineResourceResponse { complex_response })
             }
 
             ExplorerToPlanet::AvailableEnergyCellRequest { .. } => {
-                Some(PlanetToExplorer::AvailableEnergyCellResponse {
-                    available_cells: state.to_dummy().charged_cells_count as u32,
-                })
+                if self.throttle_energy_poll(explorer_id) {
+                    None
+                } else {
+                    self.charge_message(explorer_id, MessageKind::AvailableEnergy);
+                    let shown = self.stockpile_visibility.to_explorers();
+                    Some(PlanetToExplorer::AvailableEnergyCellResponse {
+                        available_cells: self.shown_cells(charged_cells(state), shown) as u32,
+                    })
+                }
             }
+
+            // Variants added to the protocol after this version
+            #[allow(unreachable_patterns)]
+            msg => self.handle_unhandled(&msg),
+        };
+        #[cfg(feature = "otel")]
+        self.otel.end_request(span, response.as_ref());
+        #[cfg(feature = "metrics-facade")]
+        self.metrics.record_request(message, started.elapsed());
+        if let Some(budget) = &self.budget {
+            budget.finish();
+        }
+
+        #[cfg(feature = "chaos")]
+        if self.chaos.as_ref().is_some_and(Chaos::drops_response) {
+            return None;
         }
+        response
     }
 }
 
@@ -311,6 +3284,11 @@ impl Default for AI {
     }
 }
 
+/// Counts the charged cells of `state`, without building a `DummyPlanetState`.
+fn charged_cells(state: &PlanetState) -> usize {
+    state.cells_iter().filter(|cell| cell.is_charged()).count()
+}
+
 /// Generates a basic resource based on the specified type.
 ///
 /// This helper function uses the provided [`Generator`] and [`EnergyCell`] to produce
@@ -343,6 +3321,62 @@ fn make_basic_resource(
     }
 }
 
+/// Returns the type of the complex resource requested by `request`.
+pub(crate) fn complex_type(request: &ComplexResourceRequest) -> ComplexResourceType {
+    match request {
+        ComplexResourceRequest::Water(..) => ComplexResourceType::Water,
+        ComplexResourceRequest::Diamond(..) => ComplexResourceType::Diamond,
+        ComplexResourceRequest::Life(..) => ComplexResourceType::Life,
+        ComplexResourceRequest::Robot(..) => ComplexResourceType::Robot,
+        ComplexResourceRequest::Dolphin(..) => ComplexResourceType::Dolphin,
+        ComplexResourceRequest::AIPartner(..) => ComplexResourceType::AIPartner,
+    }
+}
+
+/// Combines the two resources of `request` into a complex resource, discharging `cell`.
+///
+/// # Returns
+/// The combined resource, or the error of the [`Combinator`] together with the two
+/// resources of the request, wrapped as by [`extract_generic_resources`].
+fn make_complex_resource(
+    request: ComplexResourceRequest,
+    cell: &mut EnergyCell,
+    combinator: &Combinator,
+) -> Result<ComplexResource, (RefusalReason, GenericResource, GenericResource)> {
+    let failed = |error, request| {
+        let (first, second) = extract_generic_resources(request);
+        (RefusalReason::CombinatorFailed(error), first, second)
+    };
+    use ComplexResourceRequest as Request;
+
+    match request {
+        Request::Water(h, o) => combinator
+            .make_water(h, o, cell)
+            .map(ComplexResource::Water)
+            .map_err(|(e, h, o)| failed(e, Request::Water(h, o))),
+        Request::Diamond(c1, c2) => combinator
+            .make_diamond(c1, c2, cell)
+            .map(ComplexResource::Diamond)
+            .map_err(|(e, c1, c2)| failed(e, Request::Diamond(c1, c2))),
+        Request::Life(w, c) => combinator
+            .make_life(w, c, cell)
+            .map(ComplexResource::Life)
+            .map_err(|(e, w, c)| failed(e, Request::Life(w, c))),
+        Request::Robot(s, l) => combinator
+            .make_robot(s, l, cell)
+            .map(ComplexResource::Robot)
+            .map_err(|(e, s, l)| failed(e, Request::Robot(s, l))),
+        Request::Dolphin(w, l) => combinator
+            .make_dolphin(w, l, cell)
+            .map(ComplexResource::Dolphin)
+            .map_err(|(e, w, l)| failed(e, Request::Dolphin(w, l))),
+        Request::AIPartner(r, d) => combinator
+            .make_aipartner(r, d, cell)
+            .map(ComplexResource::AIPartner)
+            .map_err(|(e, r, d)| failed(e, Request::AIPartner(r, d))),
+    }
+}
+
 /// Extracts the two resources from a complex resource request.
 ///
 /// This helper function deconstructs a [`ComplexResourceRequest`] and wraps each
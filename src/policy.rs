@@ -0,0 +1,4384 @@
+//! Explorer request limiting policies.
+//!
+//! A policy decides whether a resource generation request should be served when the
+//! planet has a charged energy cell available. Each [`ExplorerRequestLimit`] mode is
+//! implemented by a policy tracking its own state, so several independent instances
+//! can be evaluated side by side (e.g. an enforced policy and a shadow one).
+//!
+//! Modes can be stacked through the [`Policy`] combinators: for example a global cap
+//! AND a per-explorer fair share AND a per-resource cap, each able to veto a grant.
+//!
+//! ## Evaluation
+//!
+//! Deciding on a request is split in two steps: the decision is first evaluated
+//! without touching the policy state, then the final outcome is recorded. This way
+//! a limiter vetoed by another one doesn't account a grant that never happened.
+//!
+//! ## Sharing
+//!
+//! Every planet builds its own policy instances from a [`Policy`] description, so an
+//! explorer could dodge its limits by rotating its requests across identical planets.
+//! Planets hosted in the same process can share the state of a policy instead, through
+//! a [`SharedPolicy`].
+//!
+//! ## Migration
+//!
+//! When the planet-wide policy is replaced at runtime, the old policy exports the
+//! usage history it accumulated as a [`PolicyState`], which the new policy imports
+//! where meaningful: the grants still in a per-explorer window, the usage scores and
+//! the grants of the current epoch. Each policy only exports and imports what its own
+//! state can express, the rest starts from scratch.
+
+use crate::stats::ScoreHistogram;
+use crate::supply::SunrayRate;
+use crate::tags::Tag;
+use crate::timeline::Product;
+use crate::{ExplorerId, ExplorerRequestLimit, Quota};
+use common_game::components::resource::BasicResourceType;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A resource generation request, as seen by a policy.
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// The explorer requesting the resource.
+    pub explorer_id: ExplorerId,
+    /// The requested resource: a basic one to generate, or a complex one to combine.
+    pub resource: Product,
+    /// Time the request is handled at.
+    pub now: SystemTime,
+    /// Tags attached to the explorer.
+    pub tags: Arc<BTreeSet<Tag>>,
+    /// Weight of the explorer, derived from its tags. An explorer with weight 2
+    /// is entitled to twice the fair share of an explorer with weight 1.
+    pub weight: f32,
+    /// Number of resources requested at once: greater than 1 when a batch of
+    /// requests is decided as a whole.
+    pub units: u32,
+    /// Cost of each unit requested, as priced by the
+    /// [`CostModel`](crate::cost::CostModel) of the planet.
+    pub cost: f32,
+    /// Requests of the explorer denied in a row before this one (see
+    /// [`Stats::streaks`](crate::stats::Stats::streaks)), for policies favoring the
+    /// explorers denied for long.
+    pub streak: u32,
+}
+
+/// Outcome of a policy evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Decision {
+    /// The request can be served.
+    Grant,
+    /// The request must be refused for the given reason.
+    Deny(DenialReason),
+}
+
+impl Decision {
+    /// Returns `true` if the decision is [`Decision::Grant`].
+    pub fn is_grant(&self) -> bool {
+        matches!(self, Decision::Grant)
+    }
+}
+
+/// Why a resource generation request was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DenialReason {
+    /// The planet has no charged energy cell.
+    NoEnergy,
+    /// The explorer used more than its fair share of energy.
+    FairShareExceeded,
+    /// The explorer used up its quota for the current window.
+    QuotaExceeded,
+    /// The planet granted the maximum number of resources for the current window.
+    GlobalCapReached,
+    /// The planet granted the maximum number of resources of the requested type
+    /// for the current window.
+    ResourceCapReached,
+    /// The explorer used up its budget for the current epoch.
+    EpochBudgetExhausted,
+    /// The only charged cells are reserved by other explorers.
+    Reserved,
+    /// The resource couldn't have been delivered to the explorer.
+    Undeliverable,
+    /// The host paused the handling of explorer requests.
+    Paused,
+    /// The host banned the explorer.
+    Banned,
+    /// The planet has no generation rule for the requested resource.
+    UnsupportedResource,
+    /// Another active explorer is behind in the stride schedule.
+    NotItsTurn,
+    /// Another active explorer bid more idle credits for the cell.
+    Outbid,
+    /// The explorer doesn't have the credits to pay for the request.
+    InsufficientCredits,
+    /// The explorer retried before the time advised after its last denial.
+    RetriedTooEarly,
+    /// The host disabled the generation of the requested resource for a while.
+    ResourceDisabled,
+    /// The cell was offered to the explorer, and is reserved until its next request
+    /// confirms the claim (see [`crate::claim`]).
+    Offered,
+    /// The explorer got more than its target share of the grants (see
+    /// [`Policy::TargetShares`]).
+    TargetShareExceeded,
+    /// The planet tracks as many explorers as it's configured to, and denies the others
+    /// (see [`Untracked::Deny`](crate::registration::Untracked::Deny)).
+    Untracked,
+}
+
+/// Decision taken by a single limit mode, as part of a [`DecisionTrace`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Verdict {
+    /// The limit mode.
+    pub limit: ExplorerRequestLimit,
+    /// What the limit decided on its own.
+    pub decision: Decision,
+}
+
+/// Explanation of the decision the planet would take on a generation request.
+///
+/// Returned by [`AI::would_grant`](crate::planet::AI::would_grant).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DecisionTrace {
+    /// The explorer requesting the resource.
+    pub explorer_id: ExplorerId,
+    /// The requested resource type.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource"))]
+    pub resource: BasicResourceType,
+    /// Name of the policy arm limiting the explorer.
+    pub arm: String,
+    /// Charged energy cells, as last observed by the AI.
+    pub charged_cells: usize,
+    /// Decision of each limit mode of the arm policy, in declaration order.
+    ///
+    /// Every mode is listed, even those a composition wouldn't evaluate because
+    /// an earlier one already decided. Modes scoped to tags the explorer doesn't
+    /// carry are omitted.
+    pub verdicts: Vec<Verdict>,
+    /// Decision the shadow policy would take, if one is configured.
+    pub shadow: Option<Decision>,
+    /// Final decision: denied with [`DenialReason::NoEnergy`] if no cell is charged,
+    /// the arm policy decision otherwise.
+    pub decision: Decision,
+}
+
+/// Description of the policy limiting explorer generation requests.
+///
+/// A policy is either a single [`ExplorerRequestLimit`] mode or a composition of
+/// other policies.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::{ExplorerRequestLimit, Quota};
+/// use rustrelli::policy::Policy;
+///
+/// // Fair share between explorers, but no more than 10 resources every 5 seconds
+/// // and no more than 4 resources of the same type every 5 seconds.
+/// let policy = Policy::all_of([
+///     ExplorerRequestLimit::GlobalCap(Quota::new(10, Duration::from_secs(5))),
+///     ExplorerRequestLimit::FairShare,
+///     ExplorerRequestLimit::ResourceCap(Quota::new(4, Duration::from_secs(5))),
+/// ]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Policy {
+    /// A single limit mode.
+    Limit(ExplorerRequestLimit),
+    /// Grants a request only if every policy grants it. The first denying policy
+    /// vetoes the request with its reason.
+    AllOf(Vec<Policy>),
+    /// Grants a request if at least one policy grants it. If all deny, the reason
+    /// of the first one is reported.
+    AnyOf(Vec<Policy>),
+    /// Applies a sequence of policies over game time, one phase after the other.
+    Schedule(Vec<Phase>),
+    /// Applies a policy only to the explorers carrying a tag. Requests of the other
+    /// explorers are granted.
+    ForTag(Tag, Box<Policy>),
+    /// A policy whose state is shared by every planet configured with it.
+    Shared(SharedPolicy),
+    /// Grants the requests of each active explorer that got less than `share` of the
+    /// grants over the last `window`, and leaves the others to `policy`.
+    MinShare {
+        share: f32,
+        window: Duration,
+        policy: Box<Policy>,
+    },
+    /// Limits the explorers carrying a tag with key `key` to `quota`, shared by all the
+    /// explorers with the same value. Each explorer without such a tag has its own
+    /// allowance.
+    Pooled { key: String, quota: Quota },
+    /// Steers the long-run shares of the grants toward a target share of each explorer,
+    /// see [`Policy::target_shares`].
+    TargetShares(BTreeMap<ExplorerId, f32>),
+}
+
+impl Policy {
+    /// Creates a policy granting a request only if all `policies` grant it.
+    ///
+    /// An empty list grants every request.
+    pub fn all_of<P: Into<Policy>>(policies: impl IntoIterator<Item = P>) -> Self {
+        Policy::AllOf(policies.into_iter().map(Into::into).collect())
+    }
+
+    /// Creates a policy granting a request if any of `policies` grants it.
+    ///
+    /// An empty list grants every request.
+    pub fn any_of<P: Into<Policy>>(policies: impl IntoIterator<Item = P>) -> Self {
+        Policy::AnyOf(policies.into_iter().map(Into::into).collect())
+    }
+
+    /// Whether the decisions on the requests of an explorer only depend on its own
+    /// requests, so that the state can be split by explorer.
+    pub(crate) fn is_per_explorer(&self) -> bool {
+        match self {
+            Policy::Limit(limit) => matches!(
+                limit,
+                ExplorerRequestLimit::None
+                    | ExplorerRequestLimit::Quota(_)
+                    | ExplorerRequestLimit::EpochBudget(_)
+                    | ExplorerRequestLimit::EpochBudgetWith(_)
+                    | ExplorerRequestLimit::Credits(_)
+            ),
+            Policy::AllOf(policies) | Policy::AnyOf(policies) => {
+                policies.iter().all(Policy::is_per_explorer)
+            }
+            Policy::Schedule(phases) => phases.iter().all(|phase| phase.policy.is_per_explorer()),
+            Policy::ForTag(_, policy) => policy.is_per_explorer(),
+            Policy::Shared(_)
+            | Policy::MinShare { .. }
+            | Policy::Pooled { .. }
+            | Policy::TargetShares(_) => false,
+        }
+    }
+
+    /// Creates a policy applying `phases` one after the other.
+    ///
+    /// Game time starts when the planet AI is started. The last phase never ends.
+    /// An empty schedule grants every request.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use rustrelli::ExplorerRequestLimit;
+    /// use rustrelli::policy::{Phase, Policy};
+    ///
+    /// let policy = Policy::schedule([
+    ///     // Lenient opening
+    ///     Phase::new(ExplorerRequestLimit::None).lasting(Duration::from_secs(120)),
+    ///     // Strict mid-game, until the host signals the endgame
+    ///     Phase::new(ExplorerRequestLimit::FairShare),
+    ///     // Free-for-all endgame
+    ///     Phase::new(ExplorerRequestLimit::None),
+    /// ]);
+    /// ```
+    pub fn schedule(phases: impl IntoIterator<Item = Phase>) -> Self {
+        Policy::Schedule(phases.into_iter().collect())
+    }
+
+    /// Creates a policy applying `policy` only to the explorers carrying `tag`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use rustrelli::{ExplorerRequestLimit, Quota};
+    /// use rustrelli::policy::Policy;
+    /// use rustrelli::tags::Tag;
+    ///
+    /// // Fair share for everyone, and bots can't get more than 1 resource per second.
+    /// let policy = Policy::all_of([
+    ///     Policy::from(ExplorerRequestLimit::FairShare),
+    ///     Policy::for_tag(
+    ///         Tag::new("kind", "bot"),
+    ///         ExplorerRequestLimit::Quota(Quota::new(1, Duration::from_secs(1))),
+    ///     ),
+    /// ]);
+    /// ```
+    pub fn for_tag(tag: Tag, policy: impl Into<Policy>) -> Self {
+        Policy::ForTag(tag, Box::new(policy.into()))
+    }
+
+    /// Creates a policy guaranteeing each active explorer at least `share` of the grants
+    /// over a sliding `window`, before `policy` decides on the other requests.
+    ///
+    /// An explorer is active if it requested resources within the window. When the
+    /// active explorers can't all get `share` of the grants, each one is guaranteed an
+    /// equal share instead. The guarantee still needs a charged cell.
+    ///
+    /// # Panics
+    /// Panics if `share` isn't between 0 and 1, or if `window` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use rustrelli::ExplorerRequestLimit;
+    /// use rustrelli::policy::Policy;
+    ///
+    /// // Fair share, but every explorer gets at least 10% of the grants of the last minute
+    /// let policy = Policy::min_share(
+    ///     0.1,
+    ///     Duration::from_secs(60),
+    ///     ExplorerRequestLimit::FairShare,
+    /// );
+    /// ```
+    pub fn min_share(share: f32, window: Duration, policy: impl Into<Policy>) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&share),
+            "Minimum share must be between 0 and 1"
+        );
+        assert!(!window.is_zero(), "Window must be greater than zero");
+        Policy::MinShare {
+            share,
+            window,
+            policy: Box::new(policy.into()),
+        }
+    }
+
+    /// Creates a policy limiting teams of explorers to a single `quota` each: the
+    /// explorers carrying a tag with key `key` and the same value draw from the same
+    /// allowance. Explorers without a tag with key `key` have their own allowance.
+    ///
+    /// Unlike [`for_tag`](Policy::for_tag), which limits each explorer of a tag, the
+    /// members of a pool spend from the same allowance: a team running several bots
+    /// gets as many resources as a team running one. Members are tagged with
+    /// [`PlanetConfig::with_tag`](crate::PlanetConfig::with_tag) or
+    /// [`PlanetConfig::with_pool`](crate::PlanetConfig::with_pool), or through the
+    /// admin channel while the planet is running.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use rustrelli::{PlanetConfig, Quota};
+    /// use rustrelli::policy::Policy;
+    /// use rustrelli::tags::Tag;
+    ///
+    /// // Each team gets at most 5 resources every 10 seconds, whatever its size
+    /// let config = PlanetConfig::new(1)
+    ///     .with_request_limit(Policy::pooled("team", Quota::new(5, Duration::from_secs(10))))
+    ///     .with_pool(Tag::new("team", "red"), [7, 8, 9])
+    ///     .with_pool(Tag::new("team", "blue"), [10]);
+    /// ```
+    pub fn pooled(key: impl Into<String>, quota: Quota) -> Self {
+        Policy::Pooled {
+            key: key.into(),
+            quota,
+        }
+    }
+
+    /// Creates a policy steering the long-run shares of the grants toward the target
+    /// share of each explorer in `targets`, for games needing an asymmetric but
+    /// controlled distribution of the energy.
+    ///
+    /// A request is granted if its explorer wouldn't get more than its target share of
+    /// the grants of the active explorers, give or take a grant. Targets are scaled to the
+    /// active explorers: with targets of 40/30/30, an explorer targeted at 40% gets 4
+    /// grants out of 7 while the last one is away. Explorers without a target share
+    /// what the targets leave equally. The pending requests of the explorers furthest
+    /// behind their target are served first.
+    ///
+    /// Shares count every grant since the policy started. How far each explorer ended
+    /// up from its target is reported by the journal analysis (see
+    /// [`Analysis::with_targets`](crate::analyzer::Analysis::with_targets)).
+    ///
+    /// # Panics
+    /// Panics if a share isn't between 0 and 1, or if the shares add up to more than 1.
+    ///
+    /// # Examples
+    /// ```
+    /// use rustrelli::PlanetConfig;
+    /// use rustrelli::policy::Policy;
+    ///
+    /// // The hero gets 40% of the energy, the two sidekicks 30% each
+    /// let config = PlanetConfig::new(1)
+    ///     .with_request_limit(Policy::target_shares([(1, 0.4), (2, 0.3), (3, 0.3)]));
+    /// ```
+    pub fn target_shares(targets: impl IntoIterator<Item = (u32, f32)>) -> Self {
+        let targets: BTreeMap<ExplorerId, f32> = targets
+            .into_iter()
+            .map(|(explorer_id, share)| (ExplorerId::from(explorer_id), share))
+            .collect();
+        assert!(
+            targets.values().all(|share| (0.0..=1.0).contains(share)),
+            "Target shares must be between 0 and 1"
+        );
+        assert!(
+            targets.values().sum::<f32>() <= 1.0 + Targeted::TOLERANCE,
+            "Target shares must add up to at most 1"
+        );
+        Policy::TargetShares(targets)
+    }
+
+    /// Creates a new policy instance implementing this description, with an empty state.
+    pub fn build(&self) -> Box<dyn RequestLimitPolicy> {
+        match self {
+            Policy::Limit(limit) => limit.build(),
+            Policy::AllOf(policies) => Box::new(Composite::new(Combination::AllOf, policies)),
+            Policy::AnyOf(policies) => Box::new(Composite::new(Combination::AnyOf, policies)),
+            Policy::Schedule(phases) => Box::new(Scheduled::new(phases)),
+            Policy::ForTag(tag, policy) => Box::new(TagScoped {
+                tag: tag.clone(),
+                policy: policy.build(),
+            }),
+            Policy::Shared(shared) => Box::new(Shared {
+                shards: shared.shards.clone(),
+            }),
+            Policy::MinShare {
+                share,
+                window,
+                policy,
+            } => Box::new(Guaranteed {
+                share: *share,
+                window: *window,
+                policy: policy.build(),
+                grants: VecDeque::new(),
+                recent: BTreeSet::new(),
+                last_request: HashMap::new(),
+            }),
+            Policy::Pooled { key, quota } => {
+                Box::new(QuotaLimit::new(*quota, QuotaScope::Pool(key.clone())))
+            }
+            Policy::TargetShares(targets) => Box::new(Targeted {
+                targets: targets.clone(),
+                grants: HashMap::new(),
+                recent: BTreeSet::new(),
+                last_request: HashMap::new(),
+            }),
+        }
+    }
+}
+
+/// A policy whose state is shared by several planets in the same process.
+///
+/// Clones of a shared policy refer to the same state: configure each planet with a
+/// clone, and a request granted by one planet is accounted by all of them. Game time,
+/// epochs, phases and resets apply to the shared state too.
+///
+/// Decisions are atomic when the shared policy is the whole planet policy: compose
+/// it with other policies only if an occasional race between planets is acceptable.
+///
+/// When the policy limits each explorer independently of the others (quotas, epoch
+/// budgets and their compositions), the state is split by explorer into shards with
+/// their own lock, so planets serving different explorers rarely wait for each other.
+/// Other policies, like [`FairShare`](ExplorerRequestLimit::FairShare), need the whole
+/// state for each decision and keep a single shard.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::{ExplorerRequestLimit, PlanetConfig, Quota};
+/// use rustrelli::policy::SharedPolicy;
+///
+/// // At most 3 resources every 10 seconds for each explorer, on the two planets together
+/// let shared = SharedPolicy::new(ExplorerRequestLimit::Quota(Quota::new(
+///     3,
+///     Duration::from_secs(10),
+/// )));
+/// let first = PlanetConfig::new(1).with_request_limit(shared.clone());
+/// let second = PlanetConfig::new(2).with_request_limit(shared);
+/// ```
+#[derive(Clone)]
+pub struct SharedPolicy {
+    description: Box<Policy>,
+    shards: Arc<[Mutex<Box<dyn RequestLimitPolicy>>]>,
+}
+
+impl SharedPolicy {
+    /// Number of shards of the policies limiting each explorer independently.
+    pub const DEFAULT_SHARDS: usize = 16;
+
+    /// Creates a shared instance of `policy`, with an empty state split in
+    /// [`Self::DEFAULT_SHARDS`] shards if possible.
+    pub fn new(policy: impl Into<Policy>) -> Self {
+        Self::with_shards(policy, Self::DEFAULT_SHARDS)
+    }
+
+    /// Creates a shared instance of `policy`, with an empty state split in `shards`
+    /// shards if possible, in a single one otherwise.
+    ///
+    /// # Panics
+    /// Panics if `shards` is zero.
+    pub fn with_shards(policy: impl Into<Policy>, shards: usize) -> Self {
+        assert!(shards > 0, "A shared policy needs at least one shard");
+        let policy = policy.into();
+        let shards = if policy.is_per_explorer() { shards } else { 1 };
+        SharedPolicy {
+            shards: (0..shards).map(|_| Mutex::new(policy.build())).collect(),
+            description: Box::new(policy),
+        }
+    }
+
+    /// Returns the number of shards the state is split in.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the description of the shared policy.
+    pub fn policy(&self) -> &Policy {
+        &self.description
+    }
+}
+
+impl fmt::Debug for SharedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedPolicy")
+            .field(&self.description)
+            .finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SharedPolicy {
+    /// Serializes the description of the shared policy, not its state.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.description.serialize(serializer)
+    }
+}
+
+impl PartialEq for SharedPolicy {
+    /// Shared policies are equal if they share the same state.
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shards, &other.shards)
+    }
+}
+
+impl From<SharedPolicy> for Policy {
+    fn from(shared: SharedPolicy) -> Self {
+        Policy::Shared(shared)
+    }
+}
+
+/// A phase of a [`Policy::Schedule`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Phase {
+    pub(crate) policy: Policy,
+    pub(crate) duration: Option<Duration>,
+}
+
+impl Phase {
+    /// Creates a phase applying `policy` until the host moves to the next phase
+    /// with [`AdminCommand::NextPhase`](crate::admin::AdminCommand::NextPhase).
+    pub fn new(policy: impl Into<Policy>) -> Self {
+        Phase {
+            policy: policy.into(),
+            duration: None,
+        }
+    }
+
+    /// Makes the phase end automatically `duration` after it started.
+    /// The host can still end it earlier.
+    pub fn lasting(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+}
+
+impl From<ExplorerRequestLimit> for Policy {
+    fn from(limit: ExplorerRequestLimit) -> Self {
+        Policy::Limit(limit)
+    }
+}
+
+/// Usage history carried over from a replaced policy to its successor. Opaque outside
+/// the crate: policies written elsewhere export and import nothing.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PolicyState {
+    /// Recent grants, with the explorer granted, oldest first.
+    pub(crate) grants: Vec<(SystemTime, ExplorerId)>,
+    /// Usage score of each explorer, as of the export.
+    pub(crate) scores: HashMap<ExplorerId, f32>,
+    /// Grants of each explorer in the current epoch.
+    pub(crate) epoch_grants: HashMap<ExplorerId, u32>,
+}
+
+impl PolicyState {
+    /// Takes the parts of `other` missing from this state, so that the history exported
+    /// by several policies tracking the same grants isn't counted twice.
+    fn merge(&mut self, other: PolicyState) {
+        if self.grants.is_empty() {
+            self.grants = other.grants;
+        }
+        if self.scores.is_empty() {
+            self.scores = other.scores;
+        }
+        if self.epoch_grants.is_empty() {
+            self.epoch_grants = other.epoch_grants;
+        }
+    }
+}
+
+/// Common interface of the request limiting policies.
+///
+/// Implementations written outside the crate check the invariants the planet AI relies
+/// on with the `policy_test_kit` module of the `test-kit` feature.
+pub trait RequestLimitPolicy: Send + fmt::Debug {
+    /// Decides whether `request` can be served, without changing the policy state.
+    fn evaluate(&self, request: &Request) -> Decision;
+
+    /// Records `request` in the policy state, together with the final `decision`
+    /// taken on it (which may differ from the evaluated one, when composed).
+    fn record(&mut self, request: &Request, decision: Decision);
+
+    /// Appends the decision of each limit mode composing the policy on `request`
+    /// to `verdicts`, without changing the policy state.
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>);
+
+    /// Evaluates `request` and records it with the evaluated decision.
+    fn admit(&mut self, request: &Request) -> Decision {
+        let decision = self.evaluate(request);
+        self.record(request, decision);
+        decision
+    }
+
+    /// Returns how urgently `request` should be served when several requests compete
+    /// for a cell: the higher, the sooner.
+    fn priority(&self, _request: &Request) -> f32 {
+        0.0
+    }
+
+    /// Charges `cost` to `explorer_id`, carrying `tags`, for a message other than a
+    /// generation request (e.g. a capability poll) handled at `now`.
+    fn charge(
+        &mut self,
+        _explorer_id: ExplorerId,
+        _tags: &BTreeSet<Tag>,
+        _cost: f32,
+        _now: SystemTime,
+    ) {
+    }
+
+    /// Resets the per-epoch allowances, as a new game epoch starts at `now`, keeping
+    /// the fraction the policy is configured to carry over.
+    fn advance_epoch(&mut self, _now: SystemTime) {}
+
+    /// Performs the bookkeeping due at `now` between requests, e.g. forgetting what
+    /// slid out of the windows. Must not change any decision.
+    fn tick(&mut self, _now: SystemTime) {}
+
+    /// Updates the supply-aware state with the sunray arrival rate, estimated after
+    /// each sunray.
+    fn observe_supply(&mut self, _rate: &SunrayRate) {}
+
+    /// Starts game time at `now`.
+    fn start(&mut self, _now: SystemTime) {}
+
+    /// Moves schedules to their next phase at `now`.
+    fn next_phase(&mut self, _now: SystemTime) {}
+
+    /// Forgets the requests of `explorer_id`, or of every explorer if `None`,
+    /// restoring their allowances.
+    fn reset(&mut self, _explorer_id: Option<ExplorerId>) {}
+
+    /// Adds the usage history accumulated up to `now` to `state`, as the policy is
+    /// replaced (see the [module documentation](self#migration)).
+    fn export_state(&self, _now: SystemTime, _state: &mut PolicyState) {}
+
+    /// Accounts the usage history of a replaced policy, exported at `now`. Called on
+    /// a fresh policy, just started.
+    fn import_state(&mut self, _now: SystemTime, _state: &PolicyState) {}
+
+    /// Number of explorers whose requests are tracked by the policy.
+    fn tracked_explorers(&self) -> usize {
+        0
+    }
+
+    /// Number of explorers that requested resources recently, as of `now`.
+    fn active_explorers(&self, _now: SystemTime) -> usize {
+        0
+    }
+
+    /// Adds the usage scores of the explorers active at `now` to `histogram`, for the
+    /// policies scoring explorers.
+    fn record_scores(&self, _now: SystemTime, _histogram: &mut ScoreHistogram) {}
+}
+
+impl ExplorerRequestLimit {
+    /// Creates a new policy instance implementing this mode, with an empty state.
+    pub fn build(&self) -> Box<dyn RequestLimitPolicy> {
+        match self {
+            ExplorerRequestLimit::None => Box::new(Unlimited),
+            ExplorerRequestLimit::FairShare => Box::new(FairShare::default()),
+            ExplorerRequestLimit::FairShareWith(config) => Box::new(FairShare::new(*config)),
+            ExplorerRequestLimit::Quota(quota) => {
+                Box::new(QuotaLimit::new(*quota, QuotaScope::Explorer))
+            }
+            ExplorerRequestLimit::GlobalCap(quota) => {
+                Box::new(QuotaLimit::new(*quota, QuotaScope::Global))
+            }
+            ExplorerRequestLimit::ResourceCap(quota) => {
+                Box::new(QuotaLimit::new(*quota, QuotaScope::Resource))
+            }
+            ExplorerRequestLimit::EpochBudget(budget) => {
+                Box::new(EpochBudget::new(EpochBudgetConfig::new(*budget)))
+            }
+            ExplorerRequestLimit::EpochBudgetWith(config) => Box::new(EpochBudget::new(*config)),
+            ExplorerRequestLimit::Stride => Box::new(Stride::default()),
+            ExplorerRequestLimit::Auction => Box::new(Auction::default()),
+            ExplorerRequestLimit::Credits(config) => Box::new(Credits::new(*config)),
+        }
+    }
+}
+
+/// A set of explorers limited by their own policy, instead of the planet-wide one.
+///
+/// Arms allow controlled experiments on which policy yields better game dynamics:
+/// explorers are split into disjoint arms, each with separate statistics
+/// (see [`Stats::arms`](crate::stats::Stats::arms)). Explorers that don't belong to
+/// any arm are limited by the planet-wide policy and accounted to the
+/// [`PolicyArm::DEFAULT`] arm.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::{ExplorerRequestLimit, PlanetConfig, Quota};
+/// use rustrelli::policy::PolicyArm;
+///
+/// let config = PlanetConfig::new(1)
+///     .with_request_limit(ExplorerRequestLimit::FairShare)
+///     .with_policy_arm(PolicyArm::new(
+///         "quota",
+///         ExplorerRequestLimit::Quota(Quota::new(3, Duration::from_secs(10))),
+///         [2, 4, 6],
+///     ));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyArm {
+    pub(crate) name: String,
+    pub(crate) policy: Policy,
+    pub(crate) explorers: HashSet<ExplorerId>,
+}
+
+impl PolicyArm {
+    /// Name of the arm grouping the explorers not assigned to any other arm.
+    pub const DEFAULT: &'static str = "default";
+
+    /// Creates an arm named `name` limiting `explorers` with `policy`.
+    pub fn new(
+        name: impl Into<String>,
+        policy: impl Into<Policy>,
+        explorers: impl IntoIterator<Item = u32>,
+    ) -> Self {
+        PolicyArm {
+            name: name.into(),
+            policy: policy.into(),
+            explorers: explorers.into_iter().map(ExplorerId::from).collect(),
+        }
+    }
+
+    /// Returns the name of the arm.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Policy granting every request.
+#[derive(Debug)]
+pub(crate) struct Unlimited;
+
+impl RequestLimitPolicy for Unlimited {
+    fn evaluate(&self, _request: &Request) -> Decision {
+        Decision::Grant
+    }
+
+    fn record(&mut self, _request: &Request, _decision: Decision) {}
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        verdicts.push(Verdict {
+            limit: ExplorerRequestLimit::None,
+            decision: self.evaluate(request),
+        });
+    }
+}
+
+/// How the usage scores of a [`FairShare`](ExplorerRequestLimit::FairShare) policy
+/// decay over time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Decay {
+    /// Scores decrease by 0.5 per second, down to zero.
+    ///
+    /// Every explorer idle long enough ends up with a zero score, however much it used
+    /// before.
+    #[default]
+    Linear,
+    /// Scores halve every `half_life`, so a long-idle explorer keeps scoring lower than
+    /// a briefly idle one.
+    Exponential { half_life: Duration },
+}
+
+impl Decay {
+    /// Exponential decay halving the scores every `half_life`.
+    ///
+    /// # Panics
+    /// Panics if `half_life` is zero.
+    pub fn half_life(half_life: Duration) -> Self {
+        assert!(!half_life.is_zero(), "Half-life must be greater than zero");
+        Decay::Exponential { half_life }
+    }
+}
+
+/// Settings of a [`FairShare`](ExplorerRequestLimit::FairShare) policy, used through
+/// [`ExplorerRequestLimit::FairShareWith`].
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::ExplorerRequestLimit;
+/// use rustrelli::policy::{Decay, FairShareConfig};
+///
+/// let limit = ExplorerRequestLimit::FairShareWith(
+///     FairShareConfig::default().with_decay(Decay::half_life(Duration::from_secs(2))),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FairShareConfig {
+    /// How the usage scores decay, [`Decay::Linear`] by default.
+    pub decay: Decay,
+    /// Half-life of the exponentially weighted moving average the score of the
+    /// requester is smoothed with before the admission comparison, if any.
+    pub smoothing: Option<Duration>,
+    /// Fraction of the threshold an explorer's score must fall below it to be granted
+    /// again after a denial, or rise above it to be denied again after a grant.
+    pub hysteresis: f32,
+    /// Fraction of the scores carried over to the next epoch, if a new epoch scales
+    /// them down. Scores are kept whole by default.
+    pub carryover: Option<f32>,
+}
+
+impl FairShareConfig {
+    pub fn with_decay(mut self, decay: Decay) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    /// Compares a moving average of the scores to the threshold instead of the scores
+    /// themselves, the weight of a past score halving every `half_life`.
+    ///
+    /// A bot whose score oscillates right around the threshold then stays granted or
+    /// denied over a few consecutive requests, instead of flip-flopping on each one.
+    ///
+    /// # Panics
+    /// Panics if `half_life` is zero.
+    pub fn with_smoothing(mut self, half_life: Duration) -> Self {
+        assert!(!half_life.is_zero(), "Half-life must be greater than zero");
+        self.smoothing = Some(half_life);
+        self
+    }
+
+    /// Keeps the latest decision on an explorer until its score crosses the threshold
+    /// by `margin`, a fraction of the threshold: a denied explorer is granted again
+    /// once its score falls to `(1 - margin)` times the threshold, a granted one is
+    /// denied once it rises over `(1 + margin)` times the threshold.
+    ///
+    /// # Panics
+    /// Panics if `margin` isn't between 0 and 1.
+    pub fn with_hysteresis(mut self, margin: f32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&margin),
+            "Hysteresis margin must be between 0 and 1"
+        );
+        self.hysteresis = margin;
+        self
+    }
+
+    /// Carries `fraction` of the scores over to the next epoch: 0 gives every explorer
+    /// a clean slate at each epoch, 1 keeps the scores whole, like the default.
+    ///
+    /// # Panics
+    /// Panics if `fraction` isn't between 0 and 1.
+    pub fn with_carryover(mut self, fraction: f32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "Carryover must be between 0 and 1"
+        );
+        self.carryover = Some(fraction);
+        self
+    }
+}
+
+/// Struct for tracking statistics about the
+/// generation requests made by an explorer to the planet.
+#[derive(Debug)]
+struct StatsRecord {
+    /// Time the usage score decays to zero, in nanoseconds since [`UNIX_EPOCH`].
+    ///
+    /// The score decays linearly, so this single instant encodes it at any time:
+    /// `DECAY_RATE * (cooled_at - now)` until `cooled_at`, zero afterwards. Unused with
+    /// exponential decay.
+    cooled_at: u64,
+    /// Timestamp of latest generation request, in nanoseconds since [`UNIX_EPOCH`].
+    last_req: u64,
+}
+
+/// Policy sharing energy cells usage equally between active explorers.
+///
+/// Uses an algorithm similar to [Token Bucket](https://en.wikipedia.org/wiki/Token_bucket).
+///
+/// Scores decay lazily: instead of decaying every score on every request, explorers
+/// are indexed by the time their score reaches zero and by the time of their latest
+/// request, so the sum of the scores and the number of active explorers are kept up
+/// to date in `O(log n)` per decision, whatever the number of tracked explorers.
+#[derive(Default)]
+pub(crate) struct FairShare {
+    config: FairShareConfig,
+    explorer_stats: HashMap<ExplorerId, StatsRecord>,
+    /// Explorers whose score was positive at the latest update, by `cooled_at`.
+    heated: BTreeSet<(u64, ExplorerId)>,
+    /// Sum of the `cooled_at` of the explorers in `heated`.
+    heated_sum: u128,
+    /// Explorers that may still be active, by `last_req`.
+    recent: BTreeSet<(u64, ExplorerId)>,
+    /// The scores, if they decay exponentially. `heated` is unused then.
+    exponential: Option<ExponentialScores>,
+    /// Smoothed score of each explorer as of its latest request, and the time of that
+    /// request, if smoothing is enabled.
+    smoothed: HashMap<ExplorerId, (f32, u64)>,
+    /// Explorers whose latest request this policy denied, if hysteresis is enabled.
+    denied: HashSet<ExplorerId>,
+}
+
+/// Inputs of a [`FairShare`] decision, as the request would leave them.
+///
+/// Built from the raw scores with [`Self::new`], which makes every input well-defined
+/// (no division by zero, no NaN), so that [`Self::admits`] is a total function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ContentionContext {
+    /// Score of the requester, the request included: finite and non-negative, or
+    /// infinite if it can't be measured.
+    score: f32,
+    /// Average score of the tracked explorers: finite and non-negative.
+    average: f32,
+    /// Active explorers, the requester included: at least 1.
+    active_explorers: usize,
+}
+
+impl ContentionContext {
+    /// Context of a requester scoring `score`, among `tracked` explorers scoring `sum`
+    /// in total, `active_explorers` of them active.
+    ///
+    /// Counts are raised to 1, as the requester is always one of them. Rounding errors
+    /// making scores negative are clamped to zero. A score that isn't a number counts as
+    /// infinite, so that the requester is denied unless it's alone; a sum that isn't
+    /// finite is ignored, leaving the requester to compare with an idle group.
+    fn new(score: f32, sum: f32, tracked: usize, active_explorers: usize) -> Self {
+        let score = if score.is_nan() {
+            f32::INFINITY
+        } else {
+            score.max(0.0)
+        };
+        let sum = if sum.is_finite() { sum.max(0.0) } else { 0.0 };
+        ContentionContext {
+            score,
+            average: sum / tracked.max(1) as f32,
+            active_explorers: active_explorers.max(1),
+        }
+    }
+
+    /// Tolerance over the average score, adjusted to the contention:
+    /// - Low contention (few active explorers): high tolerance, allowing bursts to
+    ///   maximize energy usage.
+    /// - High contention (many active explorers): low tolerance, enforcing strict
+    ///   equality to prevent hogging.
+    fn tolerance(&self) -> f32 {
+        1.0 + FairShare::ALLOWED_REQ_BURST / self.active_explorers as f32
+    }
+
+    /// Whether the requester is granted access to energy, the threshold scaled by
+    /// `margin`: either
+    /// - it's the sole active user (max utilization strategy): energy is never wasted
+    ///   when only one explorer is asking for it, or
+    /// - its score is within the tolerance of the group average.
+    fn admits(&self, margin: f32) -> bool {
+        self.active_explorers == 1 || self.score <= self.average * self.tolerance() * margin
+    }
+}
+
+/// Usage scores decaying exponentially.
+///
+/// Their sum is kept scaled as of a base time, so that it decays with a single
+/// multiplication: `scaled_sum * 2^(-(now - base) / half_life)`.
+#[derive(Debug)]
+struct ExponentialScores {
+    /// Half-life of the scores, in nanoseconds.
+    half_life: f64,
+    /// Score of each explorer that requested resources, and the time it was updated at.
+    scores: HashMap<ExplorerId, (f64, u64)>,
+    /// Sum of the scores, each scaled from its update time to `base`.
+    scaled_sum: f64,
+    base: u64,
+}
+
+impl ExponentialScores {
+    /// Half-lives after which the base moves forward, keeping the scaling factors
+    /// within the precision of an `f64`.
+    const REBASE_HALF_LIVES: f64 = 16.0;
+
+    fn new(half_life: Duration) -> Self {
+        ExponentialScores {
+            half_life: half_life.as_nanos() as f64,
+            scores: HashMap::new(),
+            scaled_sum: 0.0,
+            base: 0,
+        }
+    }
+
+    /// Factor a score decays by from `from` to `to`.
+    fn factor(&self, from: u64, to: u64) -> f64 {
+        (-(to as f64 - from as f64) / self.half_life).exp2()
+    }
+
+    fn score(&self, explorer_id: ExplorerId, now: u64) -> f64 {
+        self.scores
+            .get(&explorer_id)
+            .map_or(0.0, |(score, at)| score * self.factor(*at, now.max(*at)))
+    }
+
+    fn total(&self, now: u64) -> f64 {
+        (self.scaled_sum * self.factor(self.base, now.max(self.base))).max(0.0)
+    }
+
+    /// Adds `cost` to the score of `explorer_id` at `now`.
+    fn heat(&mut self, explorer_id: ExplorerId, cost: f64, now: u64) {
+        if now.saturating_sub(self.base) as f64 > Self::REBASE_HALF_LIVES * self.half_life {
+            self.scaled_sum = self.total(now);
+            self.base = now;
+        }
+        let score = self.score(explorer_id, now) + cost;
+        self.remove(explorer_id);
+        let at = now.max(self.base);
+        self.scaled_sum += score * self.factor(at, self.base);
+        self.scores.insert(explorer_id, (score, at));
+    }
+
+    /// Scales every score by `fraction`.
+    fn scale(&mut self, fraction: f64) {
+        for (score, _) in self.scores.values_mut() {
+            *score *= fraction;
+        }
+        self.scaled_sum *= fraction;
+    }
+
+    fn remove(&mut self, explorer_id: ExplorerId) {
+        if let Some((score, at)) = self.scores.remove(&explorer_id) {
+            self.scaled_sum -= score * self.factor(at, self.base);
+        }
+        if self.scores.is_empty() {
+            // Clears the rounding errors
+            self.scaled_sum = 0.0;
+        }
+    }
+}
+
+impl FairShare {
+    const CONTENTION_WINDOW: Duration = Duration::from_secs(3);
+    const DECAY_RATE: f32 = 0.5;
+    const ALLOWED_REQ_BURST: f32 = 3.0;
+
+    fn new(config: FairShareConfig) -> Self {
+        FairShare {
+            config,
+            exponential: match config.decay {
+                Decay::Linear => None,
+                Decay::Exponential { half_life } => Some(ExponentialScores::new(half_life)),
+            },
+            ..FairShare::default()
+        }
+    }
+
+    /// The limit mode of this policy, as reported in the verdicts.
+    fn limit(&self) -> ExplorerRequestLimit {
+        if self.config == FairShareConfig::default() {
+            ExplorerRequestLimit::FairShare
+        } else {
+            ExplorerRequestLimit::FairShareWith(self.config)
+        }
+    }
+
+    /// Converts `time` to nanoseconds since [`UNIX_EPOCH`], saturating before it.
+    fn nanos(time: SystemTime) -> u64 {
+        time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| {
+            elapsed.as_nanos().try_into().unwrap_or(u64::MAX)
+        })
+    }
+
+    /// Returns the score in `stats` after applying linear decay up to `now`.
+    ///
+    /// The score is reduced proportionally to the time elapsed since the latest request,
+    /// using `Self::DECAY_RATE`, and clamped at `0.0` to prevent negative usage values.
+    fn decayed_score(stats: &StatsRecord, now: u64) -> f32 {
+        Self::DECAY_RATE * stats.cooled_at.saturating_sub(now) as f32 / 1e9
+    }
+
+    /// Returns the moving average of the scores of `explorer_id`, once its score reaches
+    /// `score` at `now`: `score` itself if smoothing is disabled.
+    fn smoothed_score(&self, explorer_id: ExplorerId, score: f32, now: u64) -> f32 {
+        let (Some(half_life), Some((average, at))) =
+            (self.config.smoothing, self.smoothed.get(&explorer_id))
+        else {
+            return score;
+        };
+        let elapsed = now.saturating_sub(*at) as f64;
+        let kept = (-elapsed / half_life.as_nanos() as f64).exp2() as f32;
+        average * kept + score * (1.0 - kept)
+    }
+
+    /// Returns the score of `explorer_id` at `now`, zero if it isn't tracked.
+    fn score(&self, explorer_id: ExplorerId, now: u64) -> f32 {
+        match &self.exponential {
+            Some(scores) => scores.score(explorer_id, now) as f32,
+            None => self
+                .explorer_stats
+                .get(&explorer_id)
+                .map_or(0.0, |stats| Self::decayed_score(stats, now)),
+        }
+    }
+
+    /// Returns the sum of the scores of all tracked explorers at `now`.
+    ///
+    /// Explorers cooled down since the latest update are skipped from the front of
+    /// `heated`: they're few, as every update removes them.
+    fn total_score(&self, now: u64) -> f32 {
+        if let Some(scores) = &self.exponential {
+            return scores.total(now) as f32;
+        }
+        let (mut sum, mut count) = (self.heated_sum, self.heated.len() as u128);
+        for (cooled_at, _) in self.heated.iter().take_while(|(at, _)| *at <= now) {
+            sum -= u128::from(*cooled_at);
+            count -= 1;
+        }
+        Self::DECAY_RATE * (sum - u128::from(now) * count) as f32 / 1e9
+    }
+
+    /// Returns the number of explorers whose latest request is within the contention
+    /// window at `now`, skipping the stale ones like [`Self::total_score`].
+    fn recent_count(&self, now: u64) -> usize {
+        let window = Self::CONTENTION_WINDOW.as_nanos() as u64;
+        let stale = self
+            .recent
+            .iter()
+            .take_while(|(last_req, _)| last_req.saturating_add(window) <= now)
+            .count();
+        self.recent.len() - stale
+    }
+
+    /// Drops the explorers cooled down or out of the contention window at `now`
+    /// from the indexes.
+    fn prune(&mut self, now: u64) {
+        while let Some(&(cooled_at, explorer_id)) = self.heated.first() {
+            if cooled_at > now {
+                break;
+            }
+            self.heated.remove(&(cooled_at, explorer_id));
+            self.heated_sum -= u128::from(cooled_at);
+        }
+        let window = Self::CONTENTION_WINDOW.as_nanos() as u64;
+        while let Some(&(last_req, _)) = self.recent.first() {
+            if last_req.saturating_add(window) > now {
+                break;
+            }
+            self.recent.pop_first();
+        }
+    }
+
+    /// Increments the usage score for a specific explorer by `cost`, tracking it
+    /// if it wasn't. If `request` is set, its latest request moves to `now`.
+    ///
+    /// This represents the "heat" added to an explorer's tracking profile when they
+    /// perform an action (like requesting a resource).
+    fn heat(&mut self, explorer_id: ExplorerId, cost: f32, now: u64, request: bool) {
+        self.prune(now);
+        let stats = self
+            .explorer_stats
+            .entry(explorer_id)
+            .or_insert_with(|| StatsRecord {
+                cooled_at: now,
+                last_req: now,
+            });
+
+        if let Some(scores) = self.exponential.as_mut() {
+            scores.heat(explorer_id, f64::from(cost.max(0.0)), now);
+        } else {
+            if self.heated.remove(&(stats.cooled_at, explorer_id)) {
+                self.heated_sum -= u128::from(stats.cooled_at);
+            }
+            let heat = (cost.max(0.0) / Self::DECAY_RATE * 1e9) as u64;
+            stats.cooled_at = stats.cooled_at.max(now).saturating_add(heat);
+            if stats.cooled_at > now {
+                self.heated.insert((stats.cooled_at, explorer_id));
+                self.heated_sum += u128::from(stats.cooled_at);
+            }
+        }
+
+        self.recent.remove(&(stats.last_req, explorer_id));
+        if request {
+            stats.last_req = now;
+        }
+        self.recent.insert((stats.last_req, explorer_id));
+    }
+
+    /// Scales the score of every explorer, and its moving average, by `fraction` at
+    /// `now`.
+    fn scale(&mut self, fraction: f32, now: u64) {
+        self.prune(now);
+        self.smoothed
+            .values_mut()
+            .for_each(|(average, _)| *average *= fraction);
+        if let Some(scores) = self.exponential.as_mut() {
+            scores.scale(f64::from(fraction));
+            return;
+        }
+        for (cooled_at, explorer_id) in std::mem::take(&mut self.heated) {
+            self.heated_sum -= u128::from(cooled_at);
+            let remaining = (cooled_at.saturating_sub(now) as f64 * f64::from(fraction)) as u64;
+            let cooled_at = now.saturating_add(remaining);
+            if let Some(stats) = self.explorer_stats.get_mut(&explorer_id) {
+                stats.cooled_at = cooled_at;
+            }
+            if cooled_at > now {
+                self.heated.insert((cooled_at, explorer_id));
+                self.heated_sum += u128::from(cooled_at);
+            }
+        }
+    }
+
+    /// Cost of each unit requested, divided by the explorer weight: an explorer with
+    /// weight 2 heats up half as fast as one with weight 1.
+    fn request_cost(request: &Request) -> f32 {
+        request.cost * request.units as f32 / request.weight
+    }
+}
+
+impl fmt::Debug for FairShare {
+    /// Shows the mode and the score of each tracked explorer, as of the latest request.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let now = self
+            .explorer_stats
+            .values()
+            .map(|stats| stats.last_req)
+            .max()
+            .unwrap_or_default();
+        let scores: BTreeMap<_, _> = self
+            .explorer_stats
+            .keys()
+            .map(|explorer_id| (*explorer_id, self.score(*explorer_id, now)))
+            .collect();
+        f.debug_struct("FairShare")
+            .field("mode", &self.limit())
+            .field("scores", &scores)
+            .field("active_explorers", &self.recent_count(now))
+            .field("denied", &self.denied)
+            .finish()
+    }
+}
+
+impl RequestLimitPolicy for FairShare {
+    /// Evaluates the "Leaky Bucket" logic on the state the request would produce:
+    /// every score decays up to `now`, the requester's latest request moves to `now`
+    /// and the cost of the *current* request is added to the requester's score.
+    fn evaluate(&self, request: &Request) -> Decision {
+        let now = Self::nanos(request.now);
+        let window = Self::CONTENTION_WINDOW.as_nanos() as u64;
+        let requester = self.explorer_stats.get(&request.explorer_id);
+
+        let previous = self.score(request.explorer_id, now);
+        let score = previous + Self::request_cost(request);
+        let smoothed = self.smoothed_score(request.explorer_id, score, now);
+        let sum = self.total_score(now) - previous + score;
+        let tracked = self.explorer_stats.len() + usize::from(requester.is_none());
+        let was_active = requester.is_some_and(|stats| stats.last_req.saturating_add(window) > now);
+        let active_explorers = self
+            .recent_count(now)
+            .saturating_sub(usize::from(was_active))
+            + 1;
+        let context = ContentionContext::new(smoothed, sum, tracked, active_explorers);
+
+        // The latest decision sticks until the score crosses the threshold by the margin
+        let margin = if self.denied.contains(&request.explorer_id) {
+            1.0 - self.config.hysteresis
+        } else {
+            1.0 + self.config.hysteresis
+        };
+        if context.admits(margin) {
+            // ACCESS GRANTED: the cell can be discharged to produce the resource.
+            Decision::Grant
+        } else {
+            // ACCESS DENIED: Rate limit exceeded.
+            // The energy cell is preserved for a "fairer" user.
+            Decision::Deny(DenialReason::FairShareExceeded)
+        }
+    }
+
+    /// Updates the state as described in [`Self::evaluate`]. The request cost is
+    /// added whatever the decision, so denied spam keeps counting against the explorer.
+    fn record(&mut self, request: &Request, _decision: Decision) {
+        if self.config.hysteresis > 0.0 {
+            // The decision of this policy alone, whatever the composed one
+            if self.evaluate(request).is_grant() {
+                self.denied.remove(&request.explorer_id);
+            } else {
+                self.denied.insert(request.explorer_id);
+            }
+        }
+        let cost = Self::request_cost(request);
+        let now = Self::nanos(request.now);
+        self.heat(request.explorer_id, cost, now, true);
+        if self.config.smoothing.is_some() {
+            let score = self.score(request.explorer_id, now);
+            let smoothed = self.smoothed_score(request.explorer_id, score, now);
+            self.smoothed.insert(request.explorer_id, (smoothed, now));
+        }
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        verdicts.push(Verdict {
+            limit: self.limit(),
+            decision: self.evaluate(request),
+        });
+    }
+
+    /// Explorers with the lowest usage score come first.
+    fn priority(&self, request: &Request) -> f32 {
+        -self.score(request.explorer_id, Self::nanos(request.now))
+    }
+
+    /// Adds `cost` to the usage score, tracking the explorer if it wasn't.
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        _tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        self.heat(explorer_id, cost, Self::nanos(now), false);
+    }
+
+    /// Scales the scores down to their carried over fraction, if any.
+    fn advance_epoch(&mut self, now: SystemTime) {
+        if let Some(fraction) = self.config.carryover {
+            self.scale(fraction, Self::nanos(now));
+        }
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        self.prune(Self::nanos(now));
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => {
+                if let Some(stats) = self.explorer_stats.remove(&explorer_id) {
+                    if self.heated.remove(&(stats.cooled_at, explorer_id)) {
+                        self.heated_sum -= u128::from(stats.cooled_at);
+                    }
+                    self.recent.remove(&(stats.last_req, explorer_id));
+                }
+                if let Some(scores) = self.exponential.as_mut() {
+                    scores.remove(explorer_id);
+                }
+                self.smoothed.remove(&explorer_id);
+                self.denied.remove(&explorer_id);
+            }
+            None => *self = FairShare::new(self.config),
+        }
+    }
+
+    /// Exports the scores of the tracked explorers.
+    fn export_state(&self, now: SystemTime, state: &mut PolicyState) {
+        let now = Self::nanos(now);
+        state.scores.extend(
+            self.explorer_stats
+                .keys()
+                .map(|explorer_id| (*explorer_id, self.score(*explorer_id, now)))
+                .filter(|(_, score)| *score > 0.0),
+        );
+    }
+
+    /// Carries the scores over, or else replays the grants as requests of unit cost.
+    fn import_state(&mut self, now: SystemTime, state: &PolicyState) {
+        if state.scores.is_empty() {
+            for (at, explorer_id) in &state.grants {
+                self.heat(*explorer_id, 1.0, Self::nanos(*at), true);
+            }
+            return;
+        }
+        let now = Self::nanos(now);
+        for (explorer_id, score) in &state.scores {
+            self.heat(*explorer_id, *score, now, false);
+        }
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.explorer_stats.len()
+    }
+
+    /// Counts the number of explorers considered "active" at this moment.
+    ///
+    /// An explorer is defined as active if the time elapsed since their last request
+    /// is less than the defined `Self::CONTENTION_WINDOW`.
+    ///
+    /// # Returns
+    /// The count of explorers who have interacted with the planet recently enough to
+    /// be considered competitors for resources.
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        self.recent_count(Self::nanos(now))
+    }
+
+    /// Adds the ratio of each active explorer score to the threshold of
+    /// [`Self::evaluate`].
+    fn record_scores(&self, now: SystemTime, histogram: &mut ScoreHistogram) {
+        let now = Self::nanos(now);
+        let window = Self::CONTENTION_WINDOW.as_nanos() as u64;
+        let active_explorers = self.recent_count(now).max(1);
+        let tolerance = 1.0 + Self::ALLOWED_REQ_BURST / active_explorers as f32;
+        let avg_score = self.total_score(now) / self.explorer_stats.len().max(1) as f32;
+        let threshold = avg_score * tolerance;
+        let active = self
+            .recent
+            .iter()
+            .skip_while(|(last_req, _)| last_req.saturating_add(window) <= now);
+        for (_, explorer_id) in active {
+            let score = self.score(*explorer_id, now);
+            histogram.record(if threshold > 0.0 {
+                score / threshold
+            } else {
+                0.0
+            });
+        }
+    }
+}
+
+/// Pass and latest request of an explorer scheduled by a [`Stride`] policy.
+#[derive(Debug)]
+struct StrideRecord {
+    /// Virtual time of the explorer, in millionths of a standard request.
+    pass: u64,
+    /// Timestamp of latest generation request, in nanoseconds since [`UNIX_EPOCH`].
+    last_req: u64,
+}
+
+/// Policy serving the active explorers in turn, each grant moving the pass of the
+/// explorer forward by its stride: the cost of the request divided by the explorer
+/// weight. Over a contended period, each explorer gets a share of the grants
+/// proportional to its weight.
+///
+/// An explorer stops competing [`Self::CONTENTION_WINDOW`] after its latest request.
+/// When it competes again, its pass catches up with the lowest pass of the active
+/// explorers, so that idling doesn't build up credit.
+#[derive(Debug, Default)]
+pub(crate) struct Stride {
+    explorers: HashMap<ExplorerId, StrideRecord>,
+    /// Explorers that may still be active, by `pass`.
+    passes: BTreeSet<(u64, ExplorerId)>,
+    /// Explorers that may still be active, by `last_req`.
+    recent: BTreeSet<(u64, ExplorerId)>,
+}
+
+impl Stride {
+    const CONTENTION_WINDOW: Duration = Duration::from_secs(3);
+    /// Pass units per standard request.
+    const PASS_SCALE: f32 = 1e6;
+
+    /// Whether `explorer_id` requested resources within the contention window at `now`.
+    fn is_active(&self, explorer_id: ExplorerId, now: u64) -> bool {
+        let window = Self::CONTENTION_WINDOW.as_nanos() as u64;
+        self.explorers.get(&explorer_id).is_some_and(|record| {
+            record.last_req.saturating_add(window) > now
+                && self.recent.contains(&(record.last_req, explorer_id))
+        })
+    }
+
+    /// Returns the lowest pass of the explorers active at `now` other than
+    /// `explorer_id`, skipping the stale ones: they're few, as every update removes them.
+    fn lowest_other_pass(&self, explorer_id: ExplorerId, now: u64) -> Option<u64> {
+        self.passes
+            .iter()
+            .find(|(_, id)| *id != explorer_id && self.is_active(*id, now))
+            .map(|(pass, _)| *pass)
+    }
+
+    /// Returns the pass of `explorer_id` at `now`: its own if it's active, otherwise
+    /// caught up with the lowest pass of the active explorers.
+    fn pass(&self, explorer_id: ExplorerId, now: u64) -> u64 {
+        let own = self
+            .explorers
+            .get(&explorer_id)
+            .map_or(0, |record| record.pass);
+        if self.is_active(explorer_id, now) {
+            own
+        } else {
+            own.max(self.lowest_other_pass(explorer_id, now).unwrap_or(0))
+        }
+    }
+
+    /// Drops the explorers out of the contention window at `now` from the indexes,
+    /// keeping their pass.
+    fn prune(&mut self, now: u64) {
+        let window = Self::CONTENTION_WINDOW.as_nanos() as u64;
+        while let Some(&(last_req, explorer_id)) = self.recent.first() {
+            if last_req.saturating_add(window) > now {
+                break;
+            }
+            self.recent.pop_first();
+            self.passes
+                .remove(&(self.explorers[&explorer_id].pass, explorer_id));
+        }
+    }
+
+    /// Moves the pass of `explorer_id` forward by `stride` standard requests. If
+    /// `request` is set, its latest request moves to `now`.
+    fn advance(&mut self, explorer_id: ExplorerId, stride: f32, now: u64, request: bool) {
+        self.prune(now);
+        let pass = self.pass(explorer_id, now);
+        let active = self.is_active(explorer_id, now);
+        let record = self.explorers.entry(explorer_id).or_insert(StrideRecord {
+            pass,
+            last_req: now,
+        });
+        if active {
+            self.passes.remove(&(record.pass, explorer_id));
+            self.recent.remove(&(record.last_req, explorer_id));
+        }
+        record.pass = pass.saturating_add((stride.max(0.0) * Self::PASS_SCALE) as u64);
+        if request {
+            record.last_req = now;
+        }
+        if active || request {
+            self.passes.insert((record.pass, explorer_id));
+            self.recent.insert((record.last_req, explorer_id));
+        }
+    }
+}
+
+impl RequestLimitPolicy for Stride {
+    /// Grants the request if no other active explorer has a lower pass.
+    fn evaluate(&self, request: &Request) -> Decision {
+        let now = FairShare::nanos(request.now);
+        let pass = self.pass(request.explorer_id, now);
+        match self.lowest_other_pass(request.explorer_id, now) {
+            Some(lowest) if lowest < pass => Decision::Deny(DenialReason::NotItsTurn),
+            _ => Decision::Grant,
+        }
+    }
+
+    /// Moves the pass of the explorer forward by its stride if the request was
+    /// granted. Either way, the explorer is active until the end of the contention
+    /// window.
+    fn record(&mut self, request: &Request, decision: Decision) {
+        let stride = if decision.is_grant() {
+            FairShare::request_cost(request)
+        } else {
+            0.0
+        };
+        self.advance(
+            request.explorer_id,
+            stride,
+            FairShare::nanos(request.now),
+            true,
+        );
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        verdicts.push(Verdict {
+            limit: ExplorerRequestLimit::Stride,
+            decision: self.evaluate(request),
+        });
+    }
+
+    /// Explorers with the lowest pass come first.
+    fn priority(&self, request: &Request) -> f32 {
+        -(self.pass(request.explorer_id, FairShare::nanos(request.now)) as f32)
+    }
+
+    /// Moves the pass forward by `cost`, tracking the explorer if it wasn't, without
+    /// making it active.
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        _tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        self.advance(explorer_id, cost, FairShare::nanos(now), false);
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        self.prune(FairShare::nanos(now));
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => {
+                if let Some(record) = self.explorers.remove(&explorer_id) {
+                    self.passes.remove(&(record.pass, explorer_id));
+                    self.recent.remove(&(record.last_req, explorer_id));
+                }
+            }
+            None => *self = Stride::default(),
+        }
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.explorers.len()
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        let now = FairShare::nanos(now);
+        self.recent
+            .iter()
+            .filter(|(_, explorer_id)| self.is_active(*explorer_id, now))
+            .count()
+    }
+}
+
+/// Settings of an idle-credit ledger, used through [`ExplorerRequestLimit::Credits`].
+///
+/// # Examples
+/// ```
+/// use rustrelli::ExplorerRequestLimit;
+/// use rustrelli::policy::CreditConfig;
+///
+/// // 1 credit every 2 seconds, up to 5, half of them carried over to the next epoch.
+/// let limit = ExplorerRequestLimit::Credits(CreditConfig::new(0.5, 5.0).with_carryover(0.5));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CreditConfig {
+    /// Credits earned per second.
+    pub rate: f32,
+    /// Maximum balance of an explorer: credits earned over it are lost.
+    pub cap: f32,
+    /// Fraction of the balances carried over to the next epoch.
+    pub carryover: f32,
+}
+
+impl CreditConfig {
+    /// Ledger earning `rate` credits per second up to `cap`, balances emptied at each
+    /// new epoch.
+    ///
+    /// # Panics
+    /// Panics if `rate` or `cap` isn't a positive finite number.
+    pub fn new(rate: f32, cap: f32) -> Self {
+        assert!(
+            rate.is_finite() && rate > 0.0,
+            "Credit rate must be a positive finite number"
+        );
+        assert!(
+            cap.is_finite() && cap > 0.0,
+            "Credit cap must be a positive finite number"
+        );
+        CreditConfig {
+            rate,
+            cap,
+            carryover: 0.0,
+        }
+    }
+
+    /// Carries `fraction` of the balances over to the next epoch.
+    ///
+    /// # Panics
+    /// Panics if `fraction` isn't between 0 and 1.
+    pub fn with_carryover(mut self, fraction: f32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "Carryover must be between 0 and 1"
+        );
+        self.carryover = fraction;
+        self
+    }
+}
+
+/// Credit balances of the explorers, earned over time.
+///
+/// Explorers are tracked from their first message, with an empty balance. Balances are
+/// updated lazily: each one is stored as of its latest update, and credits earned since
+/// are added when read.
+#[derive(Debug)]
+struct CreditLedger {
+    config: CreditConfig,
+    /// Balance of each explorer, and the time it was updated at, in nanoseconds since
+    /// [`UNIX_EPOCH`]. Negative if the explorer is in debt.
+    balances: HashMap<ExplorerId, (f32, u64)>,
+}
+
+impl CreditLedger {
+    fn new(config: CreditConfig) -> Self {
+        CreditLedger {
+            config,
+            balances: HashMap::new(),
+        }
+    }
+
+    /// Returns the balance of `explorer_id` at `now`, zero if it isn't tracked.
+    fn balance(&self, explorer_id: ExplorerId, now: u64) -> f32 {
+        self.balances
+            .get(&explorer_id)
+            .map_or(0.0, |(balance, at)| {
+                let earned = self.config.rate * now.saturating_sub(*at) as f32 / 1e9;
+                (balance + earned).min(self.config.cap.max(*balance))
+            })
+    }
+
+    /// Spends `amount` credits of `explorer_id` at `now`, running into debt if its
+    /// balance doesn't cover them, and tracking it if it wasn't.
+    fn spend(&mut self, explorer_id: ExplorerId, amount: f32, now: u64) {
+        let balance = self.balance(explorer_id, now) - amount.max(0.0);
+        let at = self
+            .balances
+            .get(&explorer_id)
+            .map_or(now, |(_, at)| now.max(*at));
+        self.balances.insert(explorer_id, (balance, at));
+    }
+
+    /// Keeps the carried over fraction of every balance as of `now`.
+    fn carry_over(&mut self, now: u64) {
+        let carryover = self.config.carryover;
+        for explorer_id in self.balances.keys().copied().collect::<Vec<_>>() {
+            let balance = self.balance(explorer_id, now);
+            self.balances.insert(
+                explorer_id,
+                (balance.min(0.0) + balance.max(0.0) * carryover, now),
+            );
+        }
+    }
+
+    fn remove(&mut self, explorer_id: ExplorerId) {
+        self.balances.remove(&explorer_id);
+    }
+
+    fn len(&self) -> usize {
+        self.balances.len()
+    }
+}
+
+/// Policy granting a request if the explorer has the credits to pay for it.
+///
+/// Explorers earn credits over time, up to a cap, and pay the cost of each granted
+/// request, so an explorer can only burst after saving up.
+#[derive(Debug)]
+pub(crate) struct Credits {
+    ledger: CreditLedger,
+}
+
+impl Credits {
+    fn new(config: CreditConfig) -> Self {
+        Credits {
+            ledger: CreditLedger::new(config),
+        }
+    }
+}
+
+impl RequestLimitPolicy for Credits {
+    fn evaluate(&self, request: &Request) -> Decision {
+        let balance = self
+            .ledger
+            .balance(request.explorer_id, FairShare::nanos(request.now));
+        if balance >= FairShare::request_cost(request) {
+            Decision::Grant
+        } else {
+            Decision::Deny(DenialReason::InsufficientCredits)
+        }
+    }
+
+    /// Pays the cost of the request if it was granted, tracking the explorer if it
+    /// wasn't.
+    fn record(&mut self, request: &Request, decision: Decision) {
+        let cost = if decision.is_grant() {
+            FairShare::request_cost(request)
+        } else {
+            0.0
+        };
+        self.ledger
+            .spend(request.explorer_id, cost, FairShare::nanos(request.now));
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        verdicts.push(Verdict {
+            limit: ExplorerRequestLimit::Credits(self.ledger.config),
+            decision: self.evaluate(request),
+        });
+    }
+
+    /// The richest explorers come first.
+    fn priority(&self, request: &Request) -> f32 {
+        self.ledger
+            .balance(request.explorer_id, FairShare::nanos(request.now))
+    }
+
+    /// Pays `cost`, running into debt if the balance doesn't cover it.
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        _tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        self.ledger.spend(explorer_id, cost, FairShare::nanos(now));
+    }
+
+    fn advance_epoch(&mut self, now: SystemTime) {
+        self.ledger.carry_over(FairShare::nanos(now));
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => self.ledger.remove(explorer_id),
+            None => self.ledger = CreditLedger::new(self.ledger.config),
+        }
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.ledger.len()
+    }
+}
+
+/// Bidder in an [`Auction`].
+#[derive(Debug)]
+struct Bidder {
+    /// Timestamp of latest generation request, in nanoseconds since [`UNIX_EPOCH`].
+    last_req: u64,
+    /// Weight of the explorer at its latest request, breaking ties between bids.
+    weight: f32,
+}
+
+/// Policy auctioning each charged cell between the active explorers.
+///
+/// Explorers earn [`Self::CREDITS`] in an idle-credit ledger and bid all of them: a
+/// request is granted if no other active explorer bids more, the winner paying its
+/// whole bid. Equal bids go to the explorer with the highest weight, then to the
+/// requester.
+///
+/// Since credits are earned at the same rate by everyone, the explorer granted the
+/// longest ago wins, unless it wasted its credits on paid messages.
+#[derive(Debug)]
+pub(crate) struct Auction {
+    credits: CreditLedger,
+    bidders: HashMap<ExplorerId, Bidder>,
+    /// Explorers that may still be active, by `last_req`.
+    recent: BTreeSet<(u64, ExplorerId)>,
+}
+
+impl Default for Auction {
+    fn default() -> Self {
+        Auction {
+            credits: CreditLedger::new(Self::CREDITS),
+            bidders: HashMap::new(),
+            recent: BTreeSet::new(),
+        }
+    }
+}
+
+impl Auction {
+    const CONTENTION_WINDOW: Duration = Duration::from_secs(3);
+    /// Credits earned by the bidders: 0.5 per second, up to 3, kept across epochs.
+    const CREDITS: CreditConfig = CreditConfig {
+        rate: 0.5,
+        cap: 3.0,
+        carryover: 1.0,
+    };
+
+    /// Iterates over the explorers whose latest request is within the contention window
+    /// at `now`, skipping the stale ones: they're few, as every update removes them.
+    fn active(&self, now: u64) -> impl Iterator<Item = (ExplorerId, &Bidder)> {
+        let window = Self::CONTENTION_WINDOW.as_nanos() as u64;
+        self.recent
+            .iter()
+            .skip_while(move |(last_req, _)| last_req.saturating_add(window) <= now)
+            .map(|(_, explorer_id)| (*explorer_id, &self.bidders[explorer_id]))
+    }
+
+    /// Drops the explorers out of the contention window at `now` from `recent`,
+    /// keeping their credits.
+    fn prune(&mut self, now: u64) {
+        let window = Self::CONTENTION_WINDOW.as_nanos() as u64;
+        while let Some(&(last_req, _)) = self.recent.first() {
+            if last_req.saturating_add(window) > now {
+                break;
+            }
+            self.recent.pop_first();
+        }
+    }
+}
+
+impl RequestLimitPolicy for Auction {
+    fn evaluate(&self, request: &Request) -> Decision {
+        let now = FairShare::nanos(request.now);
+        let bid = self.credits.balance(request.explorer_id, now);
+        let outbid = self
+            .active(now)
+            .filter(|(explorer_id, _)| *explorer_id != request.explorer_id)
+            .any(|(explorer_id, bidder)| {
+                let other = self.credits.balance(explorer_id, now);
+                other > bid || (other == bid && bidder.weight > request.weight)
+            });
+        if outbid {
+            Decision::Deny(DenialReason::Outbid)
+        } else {
+            Decision::Grant
+        }
+    }
+
+    /// The winner pays its whole bid. Either way, the explorer bids until the end of
+    /// the contention window.
+    fn record(&mut self, request: &Request, decision: Decision) {
+        let now = FairShare::nanos(request.now);
+        self.prune(now);
+        let bid = if decision.is_grant() {
+            self.credits.balance(request.explorer_id, now)
+        } else {
+            0.0
+        };
+        self.credits.spend(request.explorer_id, bid, now);
+        let bidder = self.bidders.entry(request.explorer_id).or_insert(Bidder {
+            last_req: 0,
+            weight: request.weight,
+        });
+        self.recent.remove(&(bidder.last_req, request.explorer_id));
+        bidder.last_req = now;
+        bidder.weight = request.weight;
+        self.recent.insert((now, request.explorer_id));
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        verdicts.push(Verdict {
+            limit: ExplorerRequestLimit::Auction,
+            decision: self.evaluate(request),
+        });
+    }
+
+    /// The highest bids come first.
+    fn priority(&self, request: &Request) -> f32 {
+        self.credits
+            .balance(request.explorer_id, FairShare::nanos(request.now))
+    }
+
+    /// Pays `cost` from the credits, running into debt if they don't cover it.
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        _tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        self.credits.spend(explorer_id, cost, FairShare::nanos(now));
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        self.prune(FairShare::nanos(now));
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => {
+                self.credits.remove(explorer_id);
+                if let Some(bidder) = self.bidders.remove(&explorer_id) {
+                    self.recent.remove(&(bidder.last_req, explorer_id));
+                }
+            }
+            None => *self = Auction::default(),
+        }
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.credits.len()
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        self.active(FairShare::nanos(now)).count()
+    }
+}
+
+/// What a [`QuotaLimit`] allowance applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum QuotaScope {
+    /// Each explorer has its own allowance.
+    Explorer,
+    /// All explorers share a single allowance.
+    Global,
+    /// Each resource type has its own allowance, shared by all explorers.
+    Resource,
+    /// The explorers tagged with the same value for this key share an allowance.
+    /// Explorers without such a tag have their own allowance.
+    Pool(String),
+}
+
+/// Key of an allowance tracked by a [`QuotaLimit`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum QuotaKey {
+    Explorer(ExplorerId),
+    Global,
+    Resource(Product),
+    Pool(String),
+}
+
+/// Policy granting at most [`Quota::max_grants`] resources in any
+/// [`Quota::window`] long interval, for each allowance in its scope.
+#[derive(Debug)]
+pub(crate) struct QuotaLimit {
+    quota: Quota,
+    scope: QuotaScope,
+    /// Times of the grants in the current window, per allowance, oldest first.
+    grants: HashMap<QuotaKey, VecDeque<SystemTime>>,
+}
+
+impl QuotaLimit {
+    pub(crate) fn new(quota: Quota, scope: QuotaScope) -> Self {
+        QuotaLimit {
+            quota,
+            scope,
+            grants: HashMap::new(),
+        }
+    }
+
+    /// Returns the key of the allowance `request` is charged to.
+    fn key(&self, request: &Request) -> QuotaKey {
+        match &self.scope {
+            QuotaScope::Explorer => QuotaKey::Explorer(request.explorer_id),
+            QuotaScope::Global => QuotaKey::Global,
+            QuotaScope::Resource => QuotaKey::Resource(request.resource),
+            QuotaScope::Pool(key) => request
+                .tags
+                .iter()
+                .find(|tag| tag.key == *key)
+                .map_or(QuotaKey::Explorer(request.explorer_id), |tag| {
+                    QuotaKey::Pool(tag.value.clone())
+                }),
+        }
+    }
+
+    /// Returns `true` if a grant happened at `time` is still inside the window at `now`.
+    fn in_window(&self, time: SystemTime, now: SystemTime) -> bool {
+        now.duration_since(time)
+            .map_or(true, |age| age < self.quota.window)
+    }
+}
+
+impl RequestLimitPolicy for QuotaLimit {
+    fn evaluate(&self, request: &Request) -> Decision {
+        let used = self.grants.get(&self.key(request)).map_or(0, |grants| {
+            grants
+                .iter()
+                .filter(|time| self.in_window(**time, request.now))
+                .count()
+        });
+
+        if used + request.units as usize <= self.quota.max_grants as usize {
+            Decision::Grant
+        } else {
+            Decision::Deny(match self.scope {
+                QuotaScope::Explorer | QuotaScope::Pool(_) => DenialReason::QuotaExceeded,
+                QuotaScope::Global => DenialReason::GlobalCapReached,
+                QuotaScope::Resource => DenialReason::ResourceCapReached,
+            })
+        }
+    }
+
+    fn record(&mut self, request: &Request, decision: Decision) {
+        let key = self.key(request);
+        let window = self.quota.window;
+        let grants = self.grants.entry(key).or_default();
+
+        // Forget the grants that slid out of the window.
+        while let Some(oldest) = grants.front() {
+            match request.now.duration_since(*oldest) {
+                Ok(age) if age >= window => grants.pop_front(),
+                _ => break,
+            };
+        }
+
+        if decision.is_grant() {
+            grants.extend(std::iter::repeat_n(request.now, request.units as usize));
+        }
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        let limit = match self.scope {
+            QuotaScope::Explorer | QuotaScope::Pool(_) => ExplorerRequestLimit::Quota(self.quota),
+            QuotaScope::Global => ExplorerRequestLimit::GlobalCap(self.quota),
+            QuotaScope::Resource => ExplorerRequestLimit::ResourceCap(self.quota),
+        };
+        verdicts.push(Verdict {
+            limit,
+            decision: self.evaluate(request),
+        });
+    }
+
+    /// Forgets the grants that slid out of the window, and the allowances left empty.
+    fn tick(&mut self, now: SystemTime) {
+        let window = self.quota.window;
+        self.grants.retain(|_, grants| {
+            while grants
+                .front()
+                .is_some_and(|oldest| now.duration_since(*oldest).is_ok_and(|age| age >= window))
+            {
+                grants.pop_front();
+            }
+            !grants.is_empty()
+        });
+    }
+
+    /// Allowances shared between explorers are only restored when every explorer is reset.
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => {
+                self.grants.remove(&QuotaKey::Explorer(explorer_id));
+            }
+            None => self.grants.clear(),
+        }
+    }
+
+    /// Only per-explorer allowances tell which explorer was granted.
+    fn export_state(&self, now: SystemTime, state: &mut PolicyState) {
+        for (key, grants) in &self.grants {
+            if let QuotaKey::Explorer(explorer_id) = key {
+                state.grants.extend(
+                    grants
+                        .iter()
+                        .filter(|at| self.in_window(**at, now))
+                        .map(|at| (*at, *explorer_id)),
+                );
+            }
+        }
+        state.grants.sort();
+    }
+
+    /// The grants still in the window are charged to their explorer, or to the global
+    /// allowance. Resource and pool allowances start from scratch, as the grants don't
+    /// tell their resource or tags.
+    fn import_state(&mut self, now: SystemTime, state: &PolicyState) {
+        let per_explorer = match self.scope {
+            QuotaScope::Explorer => true,
+            QuotaScope::Global => false,
+            QuotaScope::Resource | QuotaScope::Pool(_) => return,
+        };
+        for (at, explorer_id) in &state.grants {
+            if self.in_window(*at, now) {
+                let key = if per_explorer {
+                    QuotaKey::Explorer(*explorer_id)
+                } else {
+                    QuotaKey::Global
+                };
+                self.grants.entry(key).or_default().push_back(*at);
+            }
+        }
+    }
+
+    /// Pools count as a single explorer.
+    fn tracked_explorers(&self) -> usize {
+        match self.scope {
+            QuotaScope::Explorer | QuotaScope::Pool(_) => self.grants.len(),
+            QuotaScope::Global | QuotaScope::Resource => 0,
+        }
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        match self.scope {
+            QuotaScope::Explorer | QuotaScope::Pool(_) => self
+                .grants
+                .values()
+                .filter(|grants| {
+                    grants
+                        .back()
+                        .is_some_and(|latest| self.in_window(*latest, now))
+                })
+                .count(),
+            QuotaScope::Global | QuotaScope::Resource => 0,
+        }
+    }
+}
+
+/// Settings of an [`EpochBudget`](ExplorerRequestLimit::EpochBudget) policy, used
+/// through [`ExplorerRequestLimit::EpochBudgetWith`].
+///
+/// # Examples
+/// ```
+/// use rustrelli::ExplorerRequestLimit;
+/// use rustrelli::policy::EpochBudgetConfig;
+///
+/// // 10 resources per epoch, half of the grants of an epoch counted against the next.
+/// let limit = ExplorerRequestLimit::EpochBudgetWith(EpochBudgetConfig::new(10).with_carryover(0.5));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EpochBudgetConfig {
+    /// Resources granted to each explorer per epoch.
+    pub budget: u32,
+    /// Fraction of the grants of an epoch counted against the budget of the next one.
+    pub carryover: f32,
+}
+
+impl EpochBudgetConfig {
+    /// Budget of `budget` resources per epoch, restored whole at each new epoch.
+    pub fn new(budget: u32) -> Self {
+        EpochBudgetConfig {
+            budget,
+            carryover: 0.0,
+        }
+    }
+
+    /// Counts `fraction` of the grants of an epoch against the budget of the next one,
+    /// rounded down: 0 restores the whole budget at each epoch, 1 never restores it.
+    ///
+    /// # Panics
+    /// Panics if `fraction` isn't between 0 and 1.
+    pub fn with_carryover(mut self, fraction: f32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "Carryover must be between 0 and 1"
+        );
+        self.carryover = fraction;
+        self
+    }
+}
+
+/// Policy granting each explorer at most `budget` resources per game epoch.
+///
+/// Unlike [`FairShare`] scores, allowances don't decay over time: they are only
+/// restored when the host advances the epoch.
+#[derive(Debug)]
+pub(crate) struct EpochBudget {
+    config: EpochBudgetConfig,
+    /// Grants of each explorer in the current epoch, carried over ones included.
+    used: HashMap<ExplorerId, u32>,
+}
+
+impl EpochBudget {
+    pub(crate) fn new(config: EpochBudgetConfig) -> Self {
+        EpochBudget {
+            config,
+            used: HashMap::new(),
+        }
+    }
+
+    /// The limit mode of this policy, as reported in the verdicts.
+    fn limit(&self) -> ExplorerRequestLimit {
+        if self.config == EpochBudgetConfig::new(self.config.budget) {
+            ExplorerRequestLimit::EpochBudget(self.config.budget)
+        } else {
+            ExplorerRequestLimit::EpochBudgetWith(self.config)
+        }
+    }
+}
+
+impl RequestLimitPolicy for EpochBudget {
+    fn evaluate(&self, request: &Request) -> Decision {
+        let used = self.used.get(&request.explorer_id).copied().unwrap_or(0);
+        if used + request.units <= self.config.budget {
+            Decision::Grant
+        } else {
+            Decision::Deny(DenialReason::EpochBudgetExhausted)
+        }
+    }
+
+    fn record(&mut self, request: &Request, decision: Decision) {
+        if decision.is_grant() {
+            *self.used.entry(request.explorer_id).or_default() += request.units;
+        }
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        verdicts.push(Verdict {
+            limit: self.limit(),
+            decision: self.evaluate(request),
+        });
+    }
+
+    fn advance_epoch(&mut self, _now: SystemTime) {
+        let carryover = f64::from(self.config.carryover);
+        self.used.retain(|_, used| {
+            *used = (f64::from(*used) * carryover) as u32;
+            *used > 0
+        });
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => {
+                self.used.remove(&explorer_id);
+            }
+            None => self.used.clear(),
+        }
+    }
+
+    fn export_state(&self, _now: SystemTime, state: &mut PolicyState) {
+        state.epoch_grants.extend(&self.used);
+    }
+
+    fn import_state(&mut self, _now: SystemTime, state: &PolicyState) {
+        self.used.extend(&state.epoch_grants);
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.used.len()
+    }
+}
+
+/// Policy applying the phases of a [`Policy::Schedule`] one after the other.
+///
+/// Each phase has its own policy instance, so a phase starts with a fresh state.
+#[derive(Debug)]
+pub(crate) struct Scheduled {
+    phases: Vec<(Box<dyn RequestLimitPolicy>, Option<Duration>)>,
+    /// Index of the current phase.
+    current: usize,
+    /// Start time of the current phase. `None` until game time starts, which
+    /// happens at the first request if the policy wasn't explicitly started.
+    started: Option<SystemTime>,
+}
+
+impl Scheduled {
+    fn new(phases: &[Phase]) -> Self {
+        Scheduled {
+            phases: phases
+                .iter()
+                .map(|phase| (phase.policy.build(), phase.duration))
+                .collect(),
+            current: 0,
+            started: None,
+        }
+    }
+
+    /// Returns the phase in effect at `now` and its start time, following the
+    /// time-driven transitions from the current phase.
+    fn phase_at(&self, now: SystemTime) -> (usize, SystemTime) {
+        let mut current = self.current;
+        let mut started = self.started.unwrap_or(now);
+
+        while current + 1 < self.phases.len() {
+            let Some(duration) = self.phases[current].1 else {
+                break;
+            };
+            match now.duration_since(started) {
+                Ok(elapsed) if elapsed >= duration => {
+                    started += duration;
+                    current += 1;
+                }
+                _ => break,
+            }
+        }
+        (current, started)
+    }
+}
+
+impl RequestLimitPolicy for Scheduled {
+    fn evaluate(&self, request: &Request) -> Decision {
+        let (current, _) = self.phase_at(request.now);
+        self.phases
+            .get(current)
+            .map_or(Decision::Grant, |(policy, _)| policy.evaluate(request))
+    }
+
+    fn record(&mut self, request: &Request, decision: Decision) {
+        let (current, started) = self.phase_at(request.now);
+        self.current = current;
+        self.started = Some(started);
+
+        if let Some((policy, _)) = self.phases.get_mut(current) {
+            policy.record(request, decision);
+        }
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        let (current, _) = self.phase_at(request.now);
+        match self.phases.get(current) {
+            Some((policy, _)) => policy.explain(request, verdicts),
+            None => verdicts.push(Verdict {
+                limit: ExplorerRequestLimit::None,
+                decision: Decision::Grant,
+            }),
+        }
+    }
+
+    fn priority(&self, request: &Request) -> f32 {
+        let (current, _) = self.phase_at(request.now);
+        self.phases
+            .get(current)
+            .map_or(0.0, |(policy, _)| policy.priority(request))
+    }
+
+    fn advance_epoch(&mut self, now: SystemTime) {
+        for (policy, _) in self.phases.iter_mut() {
+            policy.advance_epoch(now);
+        }
+    }
+
+    fn start(&mut self, now: SystemTime) {
+        self.current = 0;
+        self.started = Some(now);
+        for (policy, _) in self.phases.iter_mut() {
+            policy.start(now);
+        }
+    }
+
+    fn next_phase(&mut self, now: SystemTime) {
+        let (current, _) = self.phase_at(now);
+        self.current = (current + 1).min(self.phases.len().saturating_sub(1));
+        self.started = Some(now);
+        for (policy, _) in self.phases.iter_mut() {
+            policy.next_phase(now);
+        }
+    }
+
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        let (current, _) = self.phase_at(now);
+        if let Some((policy, _)) = self.phases.get_mut(current) {
+            policy.charge(explorer_id, tags, cost, now);
+        }
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        for (policy, _) in self.phases.iter_mut() {
+            policy.tick(now);
+        }
+    }
+
+    fn observe_supply(&mut self, rate: &SunrayRate) {
+        for (policy, _) in self.phases.iter_mut() {
+            policy.observe_supply(rate);
+        }
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        for (policy, _) in self.phases.iter_mut() {
+            policy.reset(explorer_id);
+        }
+    }
+
+    /// Only the current phase carries history: the next ones start fresh anyway.
+    fn export_state(&self, now: SystemTime, state: &mut PolicyState) {
+        if let Some((policy, _)) = self.phases.get(self.phase_at(now).0) {
+            policy.export_state(now, state);
+        }
+    }
+
+    fn import_state(&mut self, now: SystemTime, state: &PolicyState) {
+        let (current, _) = self.phase_at(now);
+        if let Some((policy, _)) = self.phases.get_mut(current) {
+            policy.import_state(now, state);
+        }
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.phases
+            .get(self.current)
+            .map_or(0, |(policy, _)| policy.tracked_explorers())
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        self.phases
+            .get(self.phase_at(now).0)
+            .map_or(0, |(policy, _)| policy.active_explorers(now))
+    }
+
+    fn record_scores(&self, now: SystemTime, histogram: &mut ScoreHistogram) {
+        if let Some((policy, _)) = self.phases.get(self.phase_at(now).0) {
+            policy.record_scores(now, histogram);
+        }
+    }
+}
+
+/// Policy applying `policy` only to the explorers carrying `tag`.
+#[derive(Debug)]
+pub(crate) struct TagScoped {
+    tag: Tag,
+    policy: Box<dyn RequestLimitPolicy>,
+}
+
+impl TagScoped {
+    fn applies_to(&self, request: &Request) -> bool {
+        request.tags.contains(&self.tag)
+    }
+}
+
+impl RequestLimitPolicy for TagScoped {
+    fn evaluate(&self, request: &Request) -> Decision {
+        if self.applies_to(request) {
+            self.policy.evaluate(request)
+        } else {
+            Decision::Grant
+        }
+    }
+
+    fn record(&mut self, request: &Request, decision: Decision) {
+        if self.applies_to(request) {
+            self.policy.record(request, decision);
+        }
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        if self.applies_to(request) {
+            self.policy.explain(request, verdicts);
+        }
+    }
+
+    fn priority(&self, request: &Request) -> f32 {
+        if self.applies_to(request) {
+            self.policy.priority(request)
+        } else {
+            0.0
+        }
+    }
+
+    fn advance_epoch(&mut self, now: SystemTime) {
+        self.policy.advance_epoch(now);
+    }
+
+    fn start(&mut self, now: SystemTime) {
+        self.policy.start(now);
+    }
+
+    fn next_phase(&mut self, now: SystemTime) {
+        self.policy.next_phase(now);
+    }
+
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        if tags.contains(&self.tag) {
+            self.policy.charge(explorer_id, tags, cost, now);
+        }
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        self.policy.tick(now);
+    }
+
+    fn observe_supply(&mut self, rate: &SunrayRate) {
+        self.policy.observe_supply(rate);
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        self.policy.reset(explorer_id);
+    }
+
+    fn export_state(&self, now: SystemTime, state: &mut PolicyState) {
+        self.policy.export_state(now, state);
+    }
+
+    /// The history of the explorers without the tag is imported too, but never
+    /// consulted.
+    fn import_state(&mut self, now: SystemTime, state: &PolicyState) {
+        self.policy.import_state(now, state);
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.policy.tracked_explorers()
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        self.policy.active_explorers(now)
+    }
+
+    fn record_scores(&self, now: SystemTime, histogram: &mut ScoreHistogram) {
+        self.policy.record_scores(now, histogram);
+    }
+}
+
+/// Returns the requests of `recent`, keyed by time then explorer, made after `since`.
+fn requested_after(
+    recent: &BTreeSet<(SystemTime, ExplorerId)>,
+    since: SystemTime,
+) -> impl Iterator<Item = &(SystemTime, ExplorerId)> {
+    // The overflow bucket sorts after every explorer
+    recent.range((
+        Bound::Excluded((since, ExplorerId::Overflow)),
+        Bound::Unbounded,
+    ))
+}
+
+/// Policy granting the active explorers under their minimum share of the grants, and
+/// leaving the other requests to `policy`.
+#[derive(Debug)]
+struct Guaranteed {
+    share: f32,
+    window: Duration,
+    policy: Box<dyn RequestLimitPolicy>,
+    /// Grants within the window, oldest first, by explorer.
+    grants: VecDeque<(SystemTime, ExplorerId)>,
+    /// Explorers that requested resources, by latest request.
+    recent: BTreeSet<(SystemTime, ExplorerId)>,
+    last_request: HashMap<ExplorerId, SystemTime>,
+}
+
+impl Guaranteed {
+    /// Whether `request` falls under the guarantee: its explorer got less than its
+    /// minimum share of the grants within the window.
+    fn guaranteed(&self, request: &Request) -> bool {
+        let since = request.now.checked_sub(self.window).unwrap_or(UNIX_EPOCH);
+        let (mut total, mut own) = (0, 0);
+        for (_, explorer_id) in self.grants.iter().rev().take_while(|(at, _)| *at > since) {
+            total += 1;
+            own += usize::from(*explorer_id == request.explorer_id);
+        }
+        if total == 0 {
+            return false;
+        }
+        let requester_active = self
+            .last_request
+            .get(&request.explorer_id)
+            .is_some_and(|at| *at > since);
+        let active = requested_after(&self.recent, since).count() + usize::from(!requester_active);
+        let share = self.share.min(1.0 / active as f32);
+        (own as f32) < share * total as f32
+    }
+
+    /// Forgets the grants and requests out of the window at `now`.
+    fn prune(&mut self, now: SystemTime) {
+        let since = now.checked_sub(self.window).unwrap_or(UNIX_EPOCH);
+        while self.grants.front().is_some_and(|(at, _)| *at <= since) {
+            self.grants.pop_front();
+        }
+        while let Some(&(at, explorer_id)) = self.recent.first() {
+            if at > since {
+                break;
+            }
+            self.recent.pop_first();
+            self.last_request.remove(&explorer_id);
+        }
+    }
+}
+
+impl RequestLimitPolicy for Guaranteed {
+    fn evaluate(&self, request: &Request) -> Decision {
+        if self.guaranteed(request) {
+            Decision::Grant
+        } else {
+            self.policy.evaluate(request)
+        }
+    }
+
+    fn record(&mut self, request: &Request, decision: Decision) {
+        self.policy.record(request, decision);
+        self.prune(request.now);
+        if decision.is_grant() {
+            for _ in 0..request.units {
+                self.grants.push_back((request.now, request.explorer_id));
+            }
+        }
+        if let Some(previous) = self.last_request.insert(request.explorer_id, request.now) {
+            self.recent.remove(&(previous, request.explorer_id));
+        }
+        self.recent.insert((request.now, request.explorer_id));
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        self.policy.explain(request, verdicts);
+    }
+
+    /// Explorers under their minimum share come first.
+    fn priority(&self, request: &Request) -> f32 {
+        if self.guaranteed(request) {
+            f32::INFINITY
+        } else {
+            self.policy.priority(request)
+        }
+    }
+
+    fn advance_epoch(&mut self, now: SystemTime) {
+        self.policy.advance_epoch(now);
+    }
+
+    fn start(&mut self, now: SystemTime) {
+        self.policy.start(now);
+    }
+
+    fn next_phase(&mut self, now: SystemTime) {
+        self.policy.next_phase(now);
+    }
+
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        self.policy.charge(explorer_id, tags, cost, now);
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        self.prune(now);
+        self.policy.tick(now);
+    }
+
+    fn observe_supply(&mut self, rate: &SunrayRate) {
+        self.policy.observe_supply(rate);
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => {
+                self.grants.retain(|(_, granted)| *granted != explorer_id);
+                if let Some(at) = self.last_request.remove(&explorer_id) {
+                    self.recent.remove(&(at, explorer_id));
+                }
+            }
+            None => {
+                self.grants.clear();
+                self.recent.clear();
+                self.last_request.clear();
+            }
+        }
+        self.policy.reset(explorer_id);
+    }
+
+    /// The grants in the guarantee window are exported if the wrapped policy doesn't
+    /// export any.
+    fn export_state(&self, now: SystemTime, state: &mut PolicyState) {
+        self.policy.export_state(now, state);
+        if state.grants.is_empty() {
+            state.grants.extend(self.grants.iter().copied());
+        }
+    }
+
+    fn import_state(&mut self, now: SystemTime, state: &PolicyState) {
+        self.grants.extend(state.grants.iter().copied());
+        self.prune(now);
+        self.policy.import_state(now, state);
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.policy.tracked_explorers()
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        self.policy.active_explorers(now)
+    }
+
+    fn record_scores(&self, now: SystemTime, histogram: &mut ScoreHistogram) {
+        self.policy.record_scores(now, histogram);
+    }
+}
+
+/// Policy steering the long-run shares of the grants toward the targets of a
+/// [`Policy::TargetShares`].
+#[derive(Debug)]
+struct Targeted {
+    targets: BTreeMap<ExplorerId, f32>,
+    /// Grants of each explorer since the policy started.
+    grants: HashMap<ExplorerId, u64>,
+    /// Explorers that requested resources, by latest request.
+    recent: BTreeSet<(SystemTime, ExplorerId)>,
+    last_request: HashMap<ExplorerId, SystemTime>,
+}
+
+impl Targeted {
+    /// Rounding error allowed on the sum of the target shares.
+    const TOLERANCE: f32 = 1e-4;
+    /// Grants an explorer may get ahead of its target share, so that the explorers
+    /// take turns instead of waiting for each other.
+    const MAX_LEAD: f64 = 1.0 + Self::TOLERANCE as f64;
+
+    /// Grants the explorer of `request` would be ahead of its target share among the
+    /// active explorers, once granted: negative if it would still be behind.
+    fn lead(&self, request: &Request) -> f64 {
+        let since = request
+            .now
+            .checked_sub(FairShare::CONTENTION_WINDOW)
+            .unwrap_or(UNIX_EPOCH);
+        let mut active: Vec<ExplorerId> = requested_after(&self.recent, since)
+            .map(|(_, explorer_id)| *explorer_id)
+            .filter(|explorer_id| *explorer_id != request.explorer_id)
+            .collect();
+        active.push(request.explorer_id);
+
+        let untargeted = active
+            .iter()
+            .filter(|explorer_id| !self.targets.contains_key(explorer_id))
+            .count();
+        let left = (1.0 - self.targets.values().sum::<f32>()).max(0.0);
+        let target = |explorer_id: &ExplorerId| {
+            f64::from(
+                self.targets
+                    .get(explorer_id)
+                    .copied()
+                    .unwrap_or(left / untargeted.max(1) as f32),
+            )
+        };
+        let grants =
+            |explorer_id: &ExplorerId| self.grants.get(explorer_id).copied().unwrap_or(0) as f64;
+
+        let targets: f64 = active.iter().map(target).sum();
+        let share = if targets > 0.0 {
+            target(&request.explorer_id) / targets
+        } else {
+            1.0 / active.len() as f64
+        };
+        let units = f64::from(request.units);
+        let total = active.iter().map(grants).sum::<f64>() + units;
+        grants(&request.explorer_id) + units - share * total
+    }
+
+    /// Forgets the requests out of the contention window at `now`.
+    fn prune(&mut self, now: SystemTime) {
+        let since = now
+            .checked_sub(FairShare::CONTENTION_WINDOW)
+            .unwrap_or(UNIX_EPOCH);
+        while let Some(&(at, explorer_id)) = self.recent.first() {
+            if at > since {
+                break;
+            }
+            self.recent.pop_first();
+            self.last_request.remove(&explorer_id);
+        }
+    }
+}
+
+impl RequestLimitPolicy for Targeted {
+    fn evaluate(&self, request: &Request) -> Decision {
+        if self.lead(request) <= Self::MAX_LEAD {
+            Decision::Grant
+        } else {
+            Decision::Deny(DenialReason::TargetShareExceeded)
+        }
+    }
+
+    fn record(&mut self, request: &Request, decision: Decision) {
+        self.prune(request.now);
+        if decision.is_grant() {
+            *self.grants.entry(request.explorer_id).or_default() += u64::from(request.units);
+        }
+        if let Some(previous) = self.last_request.insert(request.explorer_id, request.now) {
+            self.recent.remove(&(previous, request.explorer_id));
+        }
+        self.recent.insert((request.now, request.explorer_id));
+    }
+
+    /// Targets aren't a limit mode: the decision isn't explained.
+    fn explain(&self, _request: &Request, _verdicts: &mut Vec<Verdict>) {}
+
+    /// Explorers furthest behind their target come first.
+    fn priority(&self, request: &Request) -> f32 {
+        -self.lead(request) as f32
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        self.prune(now);
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => {
+                self.grants.remove(&explorer_id);
+                if let Some(at) = self.last_request.remove(&explorer_id) {
+                    self.recent.remove(&(at, explorer_id));
+                }
+            }
+            None => {
+                self.grants.clear();
+                self.recent.clear();
+                self.last_request.clear();
+            }
+        }
+    }
+
+    /// Counts the recent grants of the replaced policy.
+    fn import_state(&mut self, _now: SystemTime, state: &PolicyState) {
+        for (_, explorer_id) in &state.grants {
+            *self.grants.entry(*explorer_id).or_default() += 1;
+        }
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        let ungranted = self
+            .last_request
+            .keys()
+            .filter(|explorer_id| !self.grants.contains_key(explorer_id));
+        self.grants.len() + ungranted.count()
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        let since = now
+            .checked_sub(FairShare::CONTENTION_WINDOW)
+            .unwrap_or(UNIX_EPOCH);
+        requested_after(&self.recent, since).count()
+    }
+}
+
+/// A planet view of a [`SharedPolicy`].
+#[derive(Debug)]
+struct Shared {
+    shards: Arc<[Mutex<Box<dyn RequestLimitPolicy>>]>,
+}
+
+impl Shared {
+    /// Locks the shard holding the state of `explorer_id`. Policies are left consistent
+    /// between calls, so a lock poisoned by a panicking planet is recovered.
+    fn lock(&self, explorer_id: ExplorerId) -> MutexGuard<'_, Box<dyn RequestLimitPolicy>> {
+        let shard = (explorer_id.ordinal() % self.shards.len() as u64) as usize;
+        Self::lock_shard(&self.shards[shard])
+    }
+
+    fn lock_shard(
+        shard: &Mutex<Box<dyn RequestLimitPolicy>>,
+    ) -> MutexGuard<'_, Box<dyn RequestLimitPolicy>> {
+        shard
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Applies `update` to every shard, one after the other.
+    fn for_each_shard(&self, mut update: impl FnMut(&mut dyn RequestLimitPolicy)) {
+        for shard in self.shards.iter() {
+            update(Self::lock_shard(shard).as_mut());
+        }
+    }
+
+    /// Sums `count` over every shard.
+    fn sum_shards(&self, count: impl Fn(&dyn RequestLimitPolicy) -> usize) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| count(Self::lock_shard(shard).as_ref()))
+            .sum()
+    }
+}
+
+impl RequestLimitPolicy for Shared {
+    fn evaluate(&self, request: &Request) -> Decision {
+        self.lock(request.explorer_id).evaluate(request)
+    }
+
+    fn record(&mut self, request: &Request, decision: Decision) {
+        self.lock(request.explorer_id).record(request, decision);
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        self.lock(request.explorer_id).explain(request, verdicts);
+    }
+
+    /// Evaluates and records under the same lock, so that concurrent planets
+    /// can't both be granted the last allowance.
+    fn admit(&mut self, request: &Request) -> Decision {
+        self.lock(request.explorer_id).admit(request)
+    }
+
+    fn priority(&self, request: &Request) -> f32 {
+        self.lock(request.explorer_id).priority(request)
+    }
+
+    fn advance_epoch(&mut self, now: SystemTime) {
+        self.for_each_shard(|policy| policy.advance_epoch(now));
+    }
+
+    fn start(&mut self, now: SystemTime) {
+        self.for_each_shard(|policy| policy.start(now));
+    }
+
+    fn next_phase(&mut self, now: SystemTime) {
+        self.for_each_shard(|policy| policy.next_phase(now));
+    }
+
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        self.lock(explorer_id).charge(explorer_id, tags, cost, now);
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        self.for_each_shard(|policy| policy.tick(now));
+    }
+
+    fn observe_supply(&mut self, rate: &SunrayRate) {
+        self.for_each_shard(|policy| policy.observe_supply(rate));
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        match explorer_id {
+            Some(explorer_id) => self.lock(explorer_id).reset(Some(explorer_id)),
+            None => self.for_each_shard(|policy| policy.reset(None)),
+        }
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.sum_shards(|policy| policy.tracked_explorers())
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        self.sum_shards(|policy| policy.active_explorers(now))
+    }
+
+    /// Each shard scores its explorers against its own threshold.
+    fn record_scores(&self, now: SystemTime, histogram: &mut ScoreHistogram) {
+        self.for_each_shard(|policy| policy.record_scores(now, histogram));
+    }
+}
+
+/// How a [`Composite`] policy combines the decisions of its members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Combination {
+    /// Grants a request only if every member grants it.
+    AllOf,
+    /// Grants a request if at least one member grants it.
+    AnyOf,
+}
+
+/// Policy combining the decisions of its members.
+///
+/// Every member records the final decision, so a limit vetoed by another one
+/// doesn't account a grant that never happened.
+#[derive(Debug)]
+pub(crate) struct Composite {
+    combination: Combination,
+    members: Vec<Box<dyn RequestLimitPolicy>>,
+}
+
+impl Composite {
+    pub(crate) fn new(combination: Combination, policies: &[Policy]) -> Self {
+        Composite {
+            combination,
+            members: policies.iter().map(Policy::build).collect(),
+        }
+    }
+}
+
+impl RequestLimitPolicy for Composite {
+    fn evaluate(&self, request: &Request) -> Decision {
+        let mut decisions = self.members.iter().map(|policy| policy.evaluate(request));
+
+        match self.combination {
+            Combination::AllOf => decisions
+                .find(|decision| !decision.is_grant())
+                .unwrap_or(Decision::Grant),
+            Combination::AnyOf => {
+                let mut first_denial = None;
+                for decision in decisions {
+                    if decision.is_grant() {
+                        return Decision::Grant;
+                    }
+                    first_denial.get_or_insert(decision);
+                }
+                first_denial.unwrap_or(Decision::Grant)
+            }
+        }
+    }
+
+    fn record(&mut self, request: &Request, decision: Decision) {
+        for policy in self.members.iter_mut() {
+            policy.record(request, decision);
+        }
+    }
+
+    fn explain(&self, request: &Request, verdicts: &mut Vec<Verdict>) {
+        for policy in self.members.iter() {
+            policy.explain(request, verdicts);
+        }
+    }
+
+    fn priority(&self, request: &Request) -> f32 {
+        self.members
+            .iter()
+            .map(|policy| policy.priority(request))
+            .sum()
+    }
+
+    fn advance_epoch(&mut self, now: SystemTime) {
+        for policy in self.members.iter_mut() {
+            policy.advance_epoch(now);
+        }
+    }
+
+    fn start(&mut self, now: SystemTime) {
+        for policy in self.members.iter_mut() {
+            policy.start(now);
+        }
+    }
+
+    fn next_phase(&mut self, now: SystemTime) {
+        for policy in self.members.iter_mut() {
+            policy.next_phase(now);
+        }
+    }
+
+    fn charge(
+        &mut self,
+        explorer_id: ExplorerId,
+        tags: &BTreeSet<Tag>,
+        cost: f32,
+        now: SystemTime,
+    ) {
+        for policy in self.members.iter_mut() {
+            policy.charge(explorer_id, tags, cost, now);
+        }
+    }
+
+    fn tick(&mut self, now: SystemTime) {
+        for policy in self.members.iter_mut() {
+            policy.tick(now);
+        }
+    }
+
+    fn observe_supply(&mut self, rate: &SunrayRate) {
+        for policy in self.members.iter_mut() {
+            policy.observe_supply(rate);
+        }
+    }
+
+    fn reset(&mut self, explorer_id: Option<ExplorerId>) {
+        for policy in self.members.iter_mut() {
+            policy.reset(explorer_id);
+        }
+    }
+
+    /// Members record the same grants, so each part of the history is taken from the
+    /// first member exporting it.
+    fn export_state(&self, now: SystemTime, state: &mut PolicyState) {
+        for policy in self.members.iter() {
+            let mut exported = PolicyState::default();
+            policy.export_state(now, &mut exported);
+            state.merge(exported);
+        }
+    }
+
+    fn import_state(&mut self, now: SystemTime, state: &PolicyState) {
+        for policy in self.members.iter_mut() {
+            policy.import_state(now, state);
+        }
+    }
+
+    fn tracked_explorers(&self) -> usize {
+        self.members
+            .iter()
+            .map(|policy| policy.tracked_explorers())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn active_explorers(&self, now: SystemTime) -> usize {
+        self.members
+            .iter()
+            .map(|policy| policy.active_explorers(now))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn record_scores(&self, now: SystemTime, histogram: &mut ScoreHistogram) {
+        for policy in self.members.iter() {
+            policy.record_scores(now, histogram);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the request limiting policies.
+
+    use super::*;
+
+    // ============================================================================
+    // Test Helper
+    // ============================================================================
+
+    fn request(explorer_id: u32, millis: u64) -> Request {
+        Request {
+            explorer_id: explorer_id.into(),
+            resource: Product::Basic(BasicResourceType::Oxygen),
+            now: UNIX_EPOCH + Duration::from_millis(millis),
+            tags: Arc::default(),
+            weight: 1.0,
+            units: 1,
+            cost: 1.0,
+            streak: 0,
+        }
+    }
+
+    fn tagged_request(explorer_id: u32, millis: u64, tag: Tag, weight: f32) -> Request {
+        Request {
+            tags: Arc::new(BTreeSet::from([tag])),
+            weight,
+            ..request(explorer_id, millis)
+        }
+    }
+
+    // ============================================================================
+    // Tests: FairShare
+    // ============================================================================
+
+    /// **Scenario:** Two explorers request resources, then the policy is printed
+    /// **Validates:** Debug output shows the mode and the score of each explorer
+    #[test]
+    fn test_fair_share_debug_shows_scores() {
+        let mut policy = FairShare::default();
+        policy.admit(&request(1, 0));
+        policy.admit(&request(1, 0));
+        policy.admit(&request(2, 1000));
+
+        assert_eq!(
+            format!("{policy:?}"),
+            "FairShare { mode: FairShare, scores: {Explorer(1): 1.5, Explorer(2): 1.0}, \
+             active_explorers: 2, denied: {} }"
+        );
+    }
+
+    /// **Scenario:** A single explorer spams requests
+    /// **Validates:** Sole active explorer is always granted (max utilization)
+    #[test]
+    fn test_fair_share_sole_explorer_always_granted() {
+        let mut policy = FairShare::default();
+
+        for i in 0..20 {
+            assert!(policy.admit(&request(1, i)).is_grant());
+        }
+    }
+
+    /// **Scenario:** Three active explorers, one spamming, two requesting once
+    /// **Validates:** Spammer is eventually denied for exceeding its fair share
+    #[test]
+    fn test_fair_share_denies_hog() {
+        let mut policy = FairShare::default();
+
+        assert!(policy.admit(&request(2, 0)).is_grant());
+        assert!(policy.admit(&request(3, 0)).is_grant());
+        let denied = (1..20).any(|i| {
+            policy.admit(&request(1, i)) == Decision::Deny(DenialReason::FairShareExceeded)
+        });
+
+        assert!(denied, "Hog should exceed its share");
+        assert_eq!(policy.tracked_explorers(), 3);
+    }
+
+    /// **Scenario:** One explorer requests 10 times while three others request once,
+    /// then every explorer stays idle for a minute
+    /// **Validates:**
+    /// - Only the hog's score is above the threshold
+    /// - Idle explorers leave the histogram
+    #[test]
+    fn test_fair_share_score_histogram() {
+        let mut policy = FairShare::default();
+        for explorer_id in 2..5 {
+            policy.admit(&request(explorer_id, 0));
+        }
+        for i in 0..10 {
+            policy.admit(&request(1, i));
+        }
+
+        let mut histogram = ScoreHistogram::default();
+        policy.record_scores(UNIX_EPOCH + Duration::from_millis(10), &mut histogram);
+        assert_eq!(histogram.counts, [3, 0, 0, 0, 0, 1, 0, 0]);
+        assert_eq!(histogram.above_threshold(), 1);
+
+        let mut histogram = ScoreHistogram::default();
+        policy.record_scores(UNIX_EPOCH + Duration::from_secs(60), &mut histogram);
+        assert_eq!(histogram.total(), 0);
+    }
+
+    /// **Scenario:** Explorers heat up at different times, then some cool down
+    /// **Validates:**
+    /// - The lazily maintained total matches the sum of the decayed scores
+    /// - Cooled down and idle explorers stop counting, reset ones are forgotten
+    #[test]
+    fn test_fair_share_lazy_decay() {
+        let mut policy = FairShare::default();
+        for i in 0..4 {
+            policy.admit(&request(1, 0));
+            policy.admit(&request(2, 1000 * i));
+        }
+        policy.admit(&request(3, 5000));
+
+        let now = FairShare::nanos(UNIX_EPOCH + Duration::from_millis(5000));
+        let expected: f32 = policy
+            .explorer_stats
+            .values()
+            .map(|stats| FairShare::decayed_score(stats, now))
+            .sum();
+        assert!((policy.total_score(now) - expected).abs() < 1e-3);
+        assert_eq!(
+            policy.priority(&request(1, 8000)),
+            0.0,
+            "4 points cool in 8s"
+        );
+        assert_eq!(
+            policy.active_explorers(UNIX_EPOCH + Duration::from_secs(5)),
+            2
+        );
+
+        policy.reset(Some(ExplorerId::new(2)));
+        assert_eq!(policy.tracked_explorers(), 2);
+        assert_eq!(
+            policy.active_explorers(UNIX_EPOCH + Duration::from_secs(5)),
+            1
+        );
+        assert!((policy.total_score(now) - 2.5).abs() < 1e-3);
+    }
+
+    /// **Scenario:** With exponential decay of half-life 1s, explorer 1 requests 8 times
+    /// then idles for 10s, while explorer 2 requests once 9s later
+    /// **Validates:**
+    /// - Scores halve every half-life
+    /// - The long-idle heavy user keeps a positive score, below the one of the recent
+    ///   user, where linear decay would have zeroed it
+    /// - The total matches the sum of the scores, across rebases
+    #[test]
+    fn test_fair_share_exponential_decay() {
+        let mut policy = FairShare::new(
+            FairShareConfig::default().with_decay(Decay::half_life(Duration::from_secs(1))),
+        );
+        for _ in 0..8 {
+            policy.admit(&request(1, 0));
+        }
+        let at = |millis| FairShare::nanos(UNIX_EPOCH + Duration::from_millis(millis));
+        assert!((policy.score(ExplorerId::new(1), at(0)) - 8.0).abs() < 1e-3);
+        assert!((policy.score(ExplorerId::new(1), at(2000)) - 2.0).abs() < 1e-3);
+
+        policy.admit(&request(2, 9000));
+        let (heavy, recent) = (
+            policy.score(ExplorerId::new(1), at(10_000)),
+            policy.score(ExplorerId::new(2), at(10_000)),
+        );
+        assert!(heavy > 0.0, "Exponential scores never reach zero");
+        assert!(heavy < recent);
+
+        // Far past the rebase threshold
+        policy.admit(&request(1, 60_000));
+        let total = policy.total_score(at(60_500));
+        let expected = policy.score(ExplorerId::new(1), at(60_500))
+            + policy.score(ExplorerId::new(2), at(60_500));
+        assert!((total - expected).abs() < 1e-4);
+        assert_eq!(
+            policy.limit(),
+            ExplorerRequestLimit::FairShareWith(policy.config)
+        );
+    }
+
+    /// **Scenario:** Explorers 2 and 3 request every 400ms, while explorer 1 requests
+    /// every 100ms, right around its fair share, with and without smoothing of
+    /// half-life 1s
+    /// **Validates:** The smoothed decisions on the requests of explorer 1 flip between
+    /// grant and deny less often
+    #[test]
+    fn test_fair_share_smoothing_reduces_flips() {
+        let flips = |config: FairShareConfig| {
+            let mut policy = FairShare::new(config);
+            let mut previous = None;
+            let mut flips = 0;
+            for i in 0..200 {
+                let millis = 100 * i;
+                if i % 4 == 0 {
+                    policy.admit(&request(2, millis));
+                    policy.admit(&request(3, millis));
+                }
+                let granted = policy.admit(&request(1, millis)).is_grant();
+                flips += usize::from(previous.is_some_and(|previous| previous != granted));
+                previous = Some(granted);
+            }
+            flips
+        };
+
+        let raw = flips(FairShareConfig::default());
+        let smoothed = flips(FairShareConfig::default().with_smoothing(Duration::from_secs(1)));
+        assert!(smoothed < raw, "{smoothed} flips smoothed, {raw} raw");
+    }
+
+    /// **Scenario:** Same explorers as above, with and without a hysteresis of 2%
+    /// **Validates:**
+    /// - Decisions flip less often with hysteresis
+    /// - A denied explorer is granted again once its score is low enough
+    #[test]
+    fn test_fair_share_hysteresis_reduces_flips() {
+        let decisions = |config: FairShareConfig| {
+            let mut policy = FairShare::new(config);
+            (0..200)
+                .map(|i| {
+                    let millis = 100 * i;
+                    if i % 4 == 0 {
+                        policy.admit(&request(2, millis));
+                        policy.admit(&request(3, millis));
+                    }
+                    policy.admit(&request(1, millis)).is_grant()
+                })
+                .collect::<Vec<_>>()
+        };
+        let flips = |decisions: &[bool]| {
+            decisions
+                .windows(2)
+                .filter(|pair| pair[0] != pair[1])
+                .count()
+        };
+
+        let raw = decisions(FairShareConfig::default());
+        let sticky = decisions(FairShareConfig::default().with_hysteresis(0.02));
+        assert!(flips(&sticky) > 0, "Explorer 1 should be granted again");
+        assert!(flips(&sticky) < flips(&raw));
+    }
+
+    /// **Scenario:** 10k explorers registered over a game, a few hundred of them
+    /// requesting at any time, the others churning away
+    /// **Validates:** An admission decision takes less than 5µs in release builds
+    ///
+    /// Run with `cargo test --release -- --ignored bench_fair_share_10k_explorers`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_fair_share_10k_explorers() {
+        const EXPLORERS: u32 = 10_000;
+        const DECISIONS: u32 = 200_000;
+        let mut policy = FairShare::default();
+        for explorer_id in 0..EXPLORERS {
+            policy.admit(&request(explorer_id, u64::from(explorer_id)));
+        }
+
+        let start = std::time::Instant::now();
+        for i in 0..DECISIONS {
+            // A sliding group of 500 explorers is active, one request per millisecond
+            let explorer_id = (i / 50 + i % 500) % EXPLORERS;
+            policy.admit(&request(explorer_id, u64::from(EXPLORERS + i)));
+        }
+        let per_decision = start.elapsed() / DECISIONS;
+
+        assert_eq!(policy.tracked_explorers(), EXPLORERS as usize);
+        if !cfg!(debug_assertions) {
+            assert!(
+                per_decision < Duration::from_micros(5),
+                "{per_decision:?} per decision"
+            );
+        }
+    }
+
+    /// **Scenario:** Contexts built from degenerate inputs: no tracked explorer, no
+    /// active explorer, negative or non-finite scores
+    /// **Validates:** Every input is made well-defined, and the decision follows
+    #[test]
+    fn test_contention_context_is_total() {
+        let empty = ContentionContext::new(1.0, 1.0, 0, 0);
+        assert_eq!((empty.average, empty.active_explorers), (1.0, 1));
+        assert!(empty.admits(1.0), "A sole explorer is always granted");
+
+        let rounded = ContentionContext::new(-1e-6, -1e-6, 2, 2);
+        assert_eq!((rounded.score, rounded.average), (0.0, 0.0));
+        assert!(rounded.admits(1.0));
+
+        let unmeasured = ContentionContext::new(f32::NAN, 4.0, 2, 2);
+        assert_eq!(unmeasured.score, f32::INFINITY);
+        assert!(!unmeasured.admits(1.0));
+        assert!(ContentionContext::new(f32::NAN, 4.0, 2, 1).admits(1.0));
+
+        for sum in [f32::NAN, f32::INFINITY] {
+            let context = ContentionContext::new(1.0, sum, 2, 2);
+            assert_eq!(context.average, 0.0);
+            assert!(!context.admits(1.0));
+        }
+    }
+
+    /// **Scenario:** Explorer 1 scores 5, the average 2 among 2 then 4 active explorers
+    /// **Validates:**
+    /// - The tolerance shrinks as the contention grows
+    /// - The margin shifts the threshold
+    #[test]
+    fn test_contention_context_tolerance() {
+        let calm = ContentionContext::new(5.0, 4.0, 2, 2);
+        assert_eq!(calm.tolerance(), 2.5);
+        assert!(calm.admits(1.0));
+        assert!(!calm.admits(0.9));
+
+        let busy = ContentionContext::new(5.0, 8.0, 4, 4);
+        assert_eq!(busy.tolerance(), 1.75);
+        assert!(!busy.admits(1.0));
+        assert!(busy.admits(1.5));
+    }
+
+    // ============================================================================
+    // Tests: Quota
+    // ============================================================================
+
+    /// **Scenario:** Explorer requests more than its quota, then waits for the window
+    /// **Validates:**
+    /// - Requests beyond `max_grants` in the window are denied
+    /// - Allowance is restored once old grants slide out of the window
+    #[test]
+    fn test_quota_sliding_window() {
+        let mut policy =
+            QuotaLimit::new(Quota::new(2, Duration::from_secs(1)), QuotaScope::Explorer);
+
+        assert!(policy.admit(&request(1, 0)).is_grant());
+        assert!(policy.admit(&request(1, 100)).is_grant());
+        assert_eq!(
+            policy.admit(&request(1, 200)),
+            Decision::Deny(DenialReason::QuotaExceeded)
+        );
+        assert!(
+            policy.admit(&request(2, 200)).is_grant(),
+            "Quota is per explorer"
+        );
+        assert!(policy.admit(&request(1, 1000)).is_grant());
+        assert_eq!(
+            policy.admit(&request(1, 1050)),
+            Decision::Deny(DenialReason::QuotaExceeded)
+        );
+    }
+
+    /// **Scenario:** Two explorers use their quota, then the planet idles past the window
+    /// **Validates:**
+    /// - A tick forgets the explorers whose grants all slid out of the window
+    /// - Ticking doesn't change the next decisions
+    #[test]
+    fn test_quota_tick_forgets_idle_explorers() {
+        let mut policy =
+            QuotaLimit::new(Quota::new(1, Duration::from_secs(1)), QuotaScope::Explorer);
+        assert!(policy.admit(&request(1, 0)).is_grant());
+        assert!(policy.admit(&request(2, 500)).is_grant());
+
+        policy.tick(UNIX_EPOCH + Duration::from_millis(1200));
+        assert_eq!(policy.tracked_explorers(), 1);
+        assert_eq!(
+            policy.admit(&request(2, 1300)),
+            Decision::Deny(DenialReason::QuotaExceeded)
+        );
+        assert!(policy.admit(&request(1, 1300)).is_grant());
+    }
+
+    // ============================================================================
+    // Tests: Composition
+    // ============================================================================
+
+    /// **Scenario:** Explorer quota stacked with a global cap of 2 grants
+    /// **Validates:**
+    /// - The global cap vetoes requests the explorer quota would allow
+    /// - A vetoed request doesn't consume the explorer quota
+    #[test]
+    fn test_all_of_veto() {
+        let window = Duration::from_secs(60);
+        let mut policy = Policy::all_of([
+            ExplorerRequestLimit::Quota(Quota::new(2, window)),
+            ExplorerRequestLimit::GlobalCap(Quota::new(2, window)),
+        ])
+        .build();
+
+        assert!(policy.admit(&request(1, 0)).is_grant());
+        assert!(policy.admit(&request(2, 0)).is_grant());
+        assert_eq!(
+            policy.admit(&request(1, 1)),
+            Decision::Deny(DenialReason::GlobalCapReached)
+        );
+        assert_eq!(
+            policy.admit(&request(1, 60_000)),
+            Decision::Grant,
+            "Vetoed request must not count against the explorer quota"
+        );
+    }
+
+    /// **Scenario:** Any of two quotas, one exhausted
+    /// **Validates:** Grants while one member allows, reports the first reason when none does
+    #[test]
+    fn test_any_of() {
+        let window = Duration::from_secs(60);
+        let mut policy = Policy::any_of([
+            ExplorerRequestLimit::Quota(Quota::new(1, window)),
+            ExplorerRequestLimit::ResourceCap(Quota::new(2, window)),
+        ])
+        .build();
+
+        assert!(policy.admit(&request(1, 0)).is_grant());
+        assert!(policy.admit(&request(1, 1)).is_grant());
+        assert_eq!(
+            policy.admit(&request(1, 2)),
+            Decision::Deny(DenialReason::QuotaExceeded)
+        );
+    }
+
+    /// **Scenario:** Evaluating a FairShare request twice without recording
+    /// **Validates:** Evaluation doesn't change the policy state
+    #[test]
+    fn test_evaluate_is_side_effect_free() {
+        let mut policy = FairShare::default();
+        policy.admit(&request(1, 0));
+
+        assert_eq!(policy.evaluate(&request(2, 1)), Decision::Grant);
+        assert_eq!(policy.tracked_explorers(), 1);
+    }
+
+    /// **Scenario:** Explorers 1 and 2 use up a global cap of 4 grants, then slow
+    /// explorer 3 requests twice, with a minimum share of 20%
+    /// **Validates:**
+    /// - The explorer under its minimum share is granted despite the cap
+    /// - Once it got its share, the cap applies to it again
+    #[test]
+    fn test_min_share_guarantees_slow_explorer() {
+        let window = Duration::from_secs(10);
+        let mut policy = Policy::min_share(
+            0.2,
+            window,
+            ExplorerRequestLimit::GlobalCap(Quota::new(4, window)),
+        )
+        .build();
+        for i in 0..4 {
+            assert!(policy.admit(&request(1 + i % 2, i.into())).is_grant());
+        }
+        assert!(!policy.admit(&request(1, 5)).is_grant());
+
+        assert!(policy.admit(&request(3, 6)).is_grant());
+        assert_eq!(
+            policy.admit(&request(3, 7)),
+            Decision::Deny(DenialReason::GlobalCapReached)
+        );
+    }
+
+    /// **Scenario:** Explorers targeted at 40/30/30 request every millisecond in turn,
+    /// then explorer 1 leaves, then untargeted explorer 4 competes with explorer 1
+    /// **Validates:**
+    /// - Grants converge to the target shares
+    /// - Targets are scaled to the active explorers
+    /// - Untargeted explorers share what the targets leave, here nothing past the one
+    ///   grant any explorer may be ahead by
+    #[test]
+    fn test_target_shares_steer_grants() {
+        let mut policy = Policy::target_shares([(1, 0.4), (2, 0.3), (3, 0.3)]).build();
+        let mut grants = [0_u32; 5];
+        let mut run = |explorers: &[u32], from: u64, policy: &mut Box<dyn RequestLimitPolicy>| {
+            grants = [0; 5];
+            for i in from..from + 1000 {
+                let explorer_id = explorers[i as usize % explorers.len()];
+                if policy.admit(&request(explorer_id, i)).is_grant() {
+                    grants[explorer_id as usize] += 1;
+                }
+            }
+            grants
+        };
+
+        let grants = run(&[1, 2, 3], 0, &mut policy);
+        let total: u32 = grants.iter().sum();
+        for (explorer_id, target) in [(1, 0.4), (2, 0.3), (3, 0.3)] {
+            let share = f64::from(grants[explorer_id]) / f64::from(total);
+            assert!((share - target).abs() < 0.01, "{grants:?}");
+        }
+        let grants = run(&[2, 3], 10_000, &mut policy);
+        assert_eq!(grants[2], grants[3]);
+        assert_eq!(
+            policy.evaluate(&request(4, 20_000)),
+            Decision::Grant,
+            "Explorer 4 alone is granted"
+        );
+        let grants = run(&[1, 4], 30_000, &mut policy);
+        assert_eq!(&grants[1..], [500, 0, 0, 1]);
+    }
+
+    /// **Scenario:** Explorer 1 (weight 1) and explorer 2 (weight 3) both request every
+    /// millisecond under the stride scheduler, then explorer 3 joins late
+    /// **Validates:**
+    /// - Grants are shared in proportion to the weights
+    /// - The late explorer starts at the lowest pass instead of catching up from zero
+    #[test]
+    fn test_stride_shares_grants_by_weight() {
+        let gold = Tag::new("tier", "gold");
+        let mut policy = ExplorerRequestLimit::Stride.build();
+        let mut grants = [0; 4];
+        for i in 0..400 {
+            let request = match i % 2 {
+                0 => request(1, i),
+                _ => tagged_request(2, i, gold.clone(), 3.0),
+            };
+            if policy.admit(&request).is_grant() {
+                grants[request.explorer_id.raw().unwrap() as usize] += 1;
+            }
+        }
+        let ratio = grants[1] as f32 / grants[2] as f32;
+        assert!((ratio - 1.0 / 3.0).abs() < 0.02, "{:?}", grants);
+
+        for i in 400..410 {
+            if policy.admit(&request(3, i)).is_grant() {
+                grants[3] += 1;
+            }
+        }
+        assert_eq!(grants[3], 1);
+    }
+
+    /// **Scenario:** Explorers 1 and 2 request in turn under the auction, then explorers
+    /// 3 and 4, denied for lack of energy, wait until their credits are capped
+    /// **Validates:**
+    /// - The explorer idle the longest outbids the latest winner, alternating grants
+    /// - Equal bids go to the explorer with the highest weight
+    #[test]
+    fn test_auction_grants_highest_bid() {
+        let mut policy = ExplorerRequestLimit::Auction.build();
+        let mut grants = [0u32; 3];
+        for i in 0..100 {
+            let explorer_id = 1 + (i % 2) as u32;
+            if policy.admit(&request(explorer_id, i)).is_grant() {
+                grants[explorer_id as usize] += 1;
+            }
+        }
+        assert!(grants[1].abs_diff(grants[2]) <= 1, "{:?}", grants);
+
+        let gold = Tag::new("tier", "gold");
+        let no_energy = Decision::Deny(DenialReason::NoEnergy);
+        for millis in [0, 5000] {
+            policy.record(&request(3, millis), no_energy);
+            policy.record(&tagged_request(4, millis, gold.clone(), 2.0), no_energy);
+        }
+        assert_eq!(
+            policy.evaluate(&request(3, 7000)),
+            Decision::Deny(DenialReason::Outbid)
+        );
+        assert!(
+            policy
+                .evaluate(&tagged_request(4, 7000, gold, 2.0))
+                .is_grant()
+        );
+    }
+
+    /// **Scenario:** Explaining a composed policy where the first limit denies
+    /// **Validates:** Every limit mode is reported, in declaration order
+    #[test]
+    fn test_explain_lists_every_limit() {
+        let quota = Quota::new(1, Duration::from_secs(60));
+        let mut policy = Policy::all_of([
+            ExplorerRequestLimit::GlobalCap(quota),
+            ExplorerRequestLimit::FairShare,
+        ])
+        .build();
+        policy.admit(&request(1, 0));
+
+        let mut verdicts = Vec::new();
+        policy.explain(&request(2, 1), &mut verdicts);
+
+        assert_eq!(
+            verdicts,
+            vec![
+                Verdict {
+                    limit: ExplorerRequestLimit::GlobalCap(quota),
+                    decision: Decision::Deny(DenialReason::GlobalCapReached),
+                },
+                Verdict {
+                    limit: ExplorerRequestLimit::FairShare,
+                    decision: Decision::Grant,
+                },
+            ]
+        );
+    }
+
+    /// **Scenario:** Explorer exhausting its epoch budget, then a new epoch starts
+    /// **Validates:**
+    /// - Requests past the budget are denied, however old the grants are
+    /// - Advancing the epoch restores the budget
+    #[test]
+    fn test_epoch_budget() {
+        let mut policy = ExplorerRequestLimit::EpochBudget(1).build();
+
+        assert!(policy.admit(&request(1, 0)).is_grant());
+        assert_eq!(
+            policy.admit(&request(1, 3_600_000)),
+            Decision::Deny(DenialReason::EpochBudgetExhausted)
+        );
+        assert!(policy.admit(&request(2, 3_600_000)).is_grant());
+
+        policy.advance_epoch(UNIX_EPOCH + Duration::from_millis(3_600_000));
+        assert!(policy.admit(&request(1, 3_600_001)).is_grant());
+    }
+
+    /// **Scenario:** Explorer saving up credits at 1 per second, capped at 2, half of
+    /// them carried over to the next epoch
+    /// **Validates:**
+    /// - New explorers start with no credits
+    /// - Idle explorers save up to the cap, then burst until they're out of credits
+    /// - A new epoch keeps the carried over fraction of the balance
+    #[test]
+    fn test_credits_accrue_and_carry_over() {
+        let config = CreditConfig::new(1.0, 2.0).with_carryover(0.5);
+        let mut policy = ExplorerRequestLimit::Credits(config).build();
+        let insufficient = Decision::Deny(DenialReason::InsufficientCredits);
+
+        assert_eq!(policy.admit(&request(1, 0)), insufficient);
+        assert!(policy.admit(&request(1, 5000)).is_grant());
+        assert!(policy.admit(&request(1, 5000)).is_grant());
+        assert_eq!(policy.admit(&request(1, 5000)), insufficient);
+
+        policy.advance_epoch(UNIX_EPOCH + Duration::from_secs(7));
+        assert!(policy.admit(&request(1, 7000)).is_grant());
+        assert_eq!(policy.admit(&request(1, 7000)), insufficient);
+    }
+
+    /// **Scenario:** Explorer 1 requests every 10ms while explorers 2 and 3 request
+    /// every 40ms, for a second before and after a new epoch, with 0%, 50% and 100% of
+    /// the epoch carried over by fair share scores and epoch budgets
+    /// **Validates:**
+    /// - The first epoch is the same whatever the carryover
+    /// - The more is carried over, the fewer requests of explorer 1 are granted in the
+    ///   second epoch: a full carryover of the scores matches the default, a full
+    ///   carryover of the budget never restores it
+    #[test]
+    fn test_epoch_carryover() {
+        let contention = |limit: ExplorerRequestLimit| {
+            let mut policy = limit.build();
+            let mut grants = [0; 2];
+            for (epoch, grants) in grants.iter_mut().enumerate() {
+                let start = 1000 * epoch as u64;
+                if epoch > 0 {
+                    policy.advance_epoch(UNIX_EPOCH + Duration::from_millis(start));
+                }
+                for i in 0..100 {
+                    let millis = start + 10 * i;
+                    if i % 4 == 0 {
+                        policy.admit(&request(2, millis));
+                        policy.admit(&request(3, millis));
+                    }
+                    *grants += u32::from(policy.admit(&request(1, millis)).is_grant());
+                }
+            }
+            grants
+        };
+
+        let scores = [0.0, 0.5, 1.0].map(|fraction| {
+            contention(ExplorerRequestLimit::FairShareWith(
+                FairShareConfig::default().with_carryover(fraction),
+            ))
+        });
+        assert!(scores.iter().all(|grants| grants[0] == scores[0][0]));
+        assert!(scores[0][1] > scores[1][1] && scores[1][1] > scores[2][1]);
+        assert_eq!(scores[2], contention(ExplorerRequestLimit::FairShare));
+
+        let budgets = [0.0, 0.5, 1.0].map(|fraction| {
+            contention(ExplorerRequestLimit::EpochBudgetWith(
+                EpochBudgetConfig::new(20).with_carryover(fraction),
+            ))
+        });
+        assert_eq!(budgets, [[20, 20], [20, 10], [20, 0]]);
+    }
+
+    /// **Scenario:** Schedule of a timed lenient phase, a strict phase ended by the host
+    /// and a free-for-all phase
+    /// **Validates:**
+    /// - The first phase ends after its duration
+    /// - The untimed phase lasts until the next phase is signaled
+    #[test]
+    fn test_schedule_transitions() {
+        let mut policy = Policy::schedule([
+            Phase::new(ExplorerRequestLimit::None).lasting(Duration::from_secs(10)),
+            Phase::new(ExplorerRequestLimit::EpochBudget(0)),
+            Phase::new(ExplorerRequestLimit::None),
+        ])
+        .build();
+        policy.start(request(1, 0).now);
+
+        assert!(policy.admit(&request(1, 9_999)).is_grant());
+        assert_eq!(
+            policy.admit(&request(1, 10_000)),
+            Decision::Deny(DenialReason::EpochBudgetExhausted)
+        );
+        assert!(!policy.admit(&request(1, 3_600_000)).is_grant());
+
+        policy.next_phase(request(1, 3_600_000).now);
+        assert!(policy.admit(&request(1, 3_600_001)).is_grant());
+    }
+
+    /// **Scenario:** Two explorers exhausting their quota, then one of them is reset
+    /// **Validates:**
+    /// - Only the reset explorer gets its allowance back
+    /// - A full reset restores every allowance
+    #[test]
+    fn test_reset() {
+        let mut policy =
+            ExplorerRequestLimit::Quota(Quota::new(1, Duration::from_secs(60))).build();
+        policy.admit(&request(1, 0));
+        policy.admit(&request(2, 0));
+
+        policy.reset(Some(ExplorerId::new(1)));
+        assert!(policy.evaluate(&request(1, 1)).is_grant());
+        assert!(!policy.evaluate(&request(2, 1)).is_grant());
+
+        policy.reset(None);
+        assert!(policy.evaluate(&request(2, 1)).is_grant());
+    }
+
+    /// **Scenario:** With a minimum share of 50% over 10s, the overflow bucket requests at
+    /// 1s, explorer 1 is granted at 6s and explorer 2 at 6s and 7s, then explorer 1
+    /// requests just inside the window of the overflow request, and exactly at its edge
+    /// **Validates:**
+    /// - Inside the window, the 3 active explorers share the guarantee: explorer 1
+    ///   already got its third of the grants
+    /// - At the edge, the overflow bucket isn't active anymore: explorer 1 is under half
+    ///   of the grants, and guaranteed
+    #[test]
+    fn test_min_share_window_edge() {
+        let mut policy = Policy::min_share(
+            0.5,
+            Duration::from_secs(10),
+            ExplorerRequestLimit::FairShare,
+        )
+        .build();
+        policy.record(
+            &Request {
+                explorer_id: ExplorerId::Overflow,
+                ..request(0, 1_000)
+            },
+            Decision::Deny(DenialReason::FairShareExceeded),
+        );
+        for (explorer_id, millis) in [(1, 6_000), (2, 6_000), (2, 7_000)] {
+            policy.record(&request(explorer_id, millis), Decision::Grant);
+        }
+
+        assert_ne!(policy.priority(&request(1, 10_999)), f32::INFINITY);
+        assert_eq!(policy.priority(&request(1, 11_000)), f32::INFINITY);
+    }
+
+    /// **Scenario:** With target shares, explorer 1 and the overflow bucket request at
+    /// the same time, then the active explorers are counted just inside the contention
+    /// window, and exactly at its edge
+    /// **Validates:**
+    /// - Both are active inside the window
+    /// - Neither is at the edge, the overflow bucket included
+    #[test]
+    fn test_target_shares_window_edge() {
+        let mut policy = Policy::TargetShares(BTreeMap::new()).build();
+        policy.admit(&request(1, 0));
+        policy.admit(&Request {
+            explorer_id: ExplorerId::Overflow,
+            ..request(0, 0)
+        });
+
+        let edge = UNIX_EPOCH + FairShare::CONTENTION_WINDOW;
+        assert_eq!(policy.active_explorers(edge - Duration::from_millis(1)), 2);
+        assert_eq!(policy.active_explorers(edge), 0);
+    }
+
+    // ============================================================================
+    // Tests: Tags
+    // ============================================================================
+
+    /// **Scenario:** Quota scoped to a tag, requests from tagged and untagged explorers
+    /// **Validates:** Only tagged explorers are limited
+    #[test]
+    fn test_tag_scoped_policy() {
+        let bot = Tag::new("kind", "bot");
+        let mut policy = Policy::for_tag(
+            bot.clone(),
+            ExplorerRequestLimit::Quota(Quota::new(1, Duration::from_secs(60))),
+        )
+        .build();
+
+        assert!(
+            policy
+                .admit(&tagged_request(1, 0, bot.clone(), 1.0))
+                .is_grant()
+        );
+        assert!(!policy.admit(&tagged_request(1, 1, bot, 1.0)).is_grant());
+        assert!(policy.admit(&request(2, 2)).is_grant());
+        assert!(policy.admit(&request(2, 3)).is_grant());
+    }
+
+    /// **Scenario:** Pooled quota of 2, explorers 1 and 2 on team red, explorer 3 on
+    /// team blue, explorer 4 without a team
+    /// **Validates:**
+    /// - Members of a team spend from the same allowance
+    /// - Other teams and explorers without a team have their own allowance
+    #[test]
+    fn test_pooled_quota() {
+        let red = Tag::new("team", "red");
+        let blue = Tag::new("team", "blue");
+        let mut policy = Policy::pooled("team", Quota::new(2, Duration::from_secs(60))).build();
+
+        assert!(
+            policy
+                .admit(&tagged_request(1, 0, red.clone(), 1.0))
+                .is_grant()
+        );
+        assert!(
+            policy
+                .admit(&tagged_request(2, 1, red.clone(), 1.0))
+                .is_grant()
+        );
+        assert_eq!(
+            policy.admit(&tagged_request(1, 2, red.clone(), 1.0)),
+            Decision::Deny(DenialReason::QuotaExceeded)
+        );
+        assert!(!policy.admit(&tagged_request(2, 3, red, 1.0)).is_grant());
+
+        assert!(
+            policy
+                .admit(&tagged_request(3, 4, blue.clone(), 1.0))
+                .is_grant()
+        );
+        assert!(policy.admit(&tagged_request(3, 5, blue, 1.0)).is_grant());
+        assert!(policy.admit(&request(4, 6)).is_grant());
+        assert!(policy.admit(&request(4, 7)).is_grant());
+        assert!(!policy.admit(&request(4, 8)).is_grant());
+    }
+
+    /// **Scenario:** Under FairShare, two explorers request three times as often as five
+    /// others; one of the two has weight 2
+    /// **Validates:** The weighted explorer is granted more than the unweighted one
+    #[test]
+    fn test_fair_share_weight() {
+        let gold = Tag::new("tier", "gold");
+        let mut policy = FairShare::default();
+        let (mut gold_grants, mut plain_grants) = (0, 0);
+
+        for tick in 0..100 {
+            let now = tick * 100;
+            for _ in 0..3 {
+                if policy
+                    .admit(&tagged_request(0, now, gold.clone(), 2.0))
+                    .is_grant()
+                {
+                    gold_grants += 1;
+                }
+                if policy.admit(&request(1, now)).is_grant() {
+                    plain_grants += 1;
+                }
+            }
+            for explorer_id in 2..7 {
+                policy.admit(&request(explorer_id, now));
+            }
+        }
+
+        assert!(
+            gold_grants > plain_grants,
+            "Weighted explorer should be granted more: {} vs {}",
+            gold_grants,
+            plain_grants
+        );
+    }
+
+    /// **Scenario:** FairShare priority of a heavy user and a newcomer
+    /// **Validates:** The explorer with the lower score has the higher priority
+    #[test]
+    fn test_fair_share_priority() {
+        let mut policy = FairShare::default();
+        for i in 0..5 {
+            policy.admit(&request(1, i));
+        }
+
+        assert!(policy.priority(&request(2, 10)) > policy.priority(&request(1, 10)));
+    }
+
+    /// **Scenario:** Batch requests against a quota of 3
+    /// **Validates:** A batch is granted only if the quota allows all of its units
+    #[test]
+    fn test_quota_batch_all_or_nothing() {
+        let mut policy =
+            ExplorerRequestLimit::Quota(Quota::new(3, Duration::from_secs(60))).build();
+        let batch = |units| Request {
+            units,
+            ..request(1, 0)
+        };
+
+        assert!(policy.admit(&batch(2)).is_grant());
+        assert_eq!(
+            policy.admit(&batch(2)),
+            Decision::Deny(DenialReason::QuotaExceeded),
+            "Only 1 unit left"
+        );
+        assert!(policy.admit(&batch(1)).is_grant());
+    }
+
+    // ============================================================================
+    // Tests: Sharing
+    // ============================================================================
+
+    /// **Scenario:** Two instances built from the same shared quota admit requests of
+    /// the same explorer in turn
+    /// **Validates:** The quota is consumed by both instances together
+    #[test]
+    fn test_shared_policy_state() {
+        let shared = SharedPolicy::new(ExplorerRequestLimit::Quota(Quota::new(
+            2,
+            Duration::from_secs(60),
+        )));
+        let mut first = Policy::from(shared.clone()).build();
+        let mut second = Policy::from(shared.clone()).build();
+
+        assert!(first.admit(&request(1, 0)).is_grant());
+        assert!(second.admit(&request(1, 1)).is_grant());
+        assert_eq!(
+            first.admit(&request(1, 2)),
+            Decision::Deny(DenialReason::QuotaExceeded)
+        );
+        assert!(second.admit(&request(2, 3)).is_grant());
+        assert_eq!(Policy::from(shared.clone()), Policy::Shared(shared));
+    }
+
+    /// **Scenario:** Shared per-explorer quota and shared FairShare, with explorers
+    /// landing on different shards
+    /// **Validates:**
+    /// - Only per-explorer policies are split in shards
+    /// - Counts and resets cover every shard
+    #[test]
+    fn test_shared_policy_shards() {
+        let quota = ExplorerRequestLimit::Quota(Quota::new(1, Duration::from_secs(60)));
+        let shared = SharedPolicy::with_shards(quota, 4);
+        assert_eq!(shared.shards(), 4);
+        assert_eq!(
+            SharedPolicy::new(ExplorerRequestLimit::FairShare).shards(),
+            1,
+            "FairShare needs every score for a decision"
+        );
+
+        let mut policy = Policy::from(shared).build();
+        for explorer_id in 0..8 {
+            assert!(policy.admit(&request(explorer_id, 0)).is_grant());
+            assert!(!policy.admit(&request(explorer_id, 1)).is_grant());
+        }
+        assert_eq!(policy.tracked_explorers(), 8);
+
+        policy.reset(None);
+        assert!(policy.admit(&request(5, 2)).is_grant());
+        assert_eq!(policy.tracked_explorers(), 1);
+    }
+
+    // ============================================================================
+    // Tests: Conformance
+    // ============================================================================
+
+    /// **Scenario:** Every limit mode and combinator runs the conformance test kit
+    /// **Validates:** Every policy upholds the invariants of
+    /// [`policy_test_kit`](crate::policy_test_kit)
+    #[test]
+    fn test_policies_conform() {
+        let quota = Quota::new(5, Duration::from_secs(1));
+        let limits = [
+            ExplorerRequestLimit::None,
+            ExplorerRequestLimit::FairShare,
+            ExplorerRequestLimit::FairShareWith(
+                FairShareConfig::default()
+                    .with_decay(Decay::Exponential {
+                        half_life: Duration::from_secs(2),
+                    })
+                    .with_smoothing(Duration::from_secs(1))
+                    .with_hysteresis(0.1)
+                    .with_carryover(0.5),
+            ),
+            ExplorerRequestLimit::Quota(quota),
+            ExplorerRequestLimit::GlobalCap(quota),
+            ExplorerRequestLimit::ResourceCap(quota),
+            ExplorerRequestLimit::EpochBudget(20),
+            ExplorerRequestLimit::EpochBudgetWith(EpochBudgetConfig::new(20).with_carryover(0.5)),
+            ExplorerRequestLimit::Stride,
+            ExplorerRequestLimit::Auction,
+            ExplorerRequestLimit::Credits(CreditConfig::new(5.0, 10.0)),
+        ];
+        let mut policies: Vec<Policy> = limits.into_iter().map(Policy::from).collect();
+        policies.extend([
+            Policy::all_of([
+                ExplorerRequestLimit::FairShare,
+                ExplorerRequestLimit::GlobalCap(quota),
+            ]),
+            Policy::any_of([
+                ExplorerRequestLimit::Quota(quota),
+                ExplorerRequestLimit::EpochBudget(20),
+            ]),
+            Policy::schedule([
+                Phase::new(ExplorerRequestLimit::None).lasting(Duration::from_millis(500)),
+                Phase::new(ExplorerRequestLimit::FairShare),
+            ]),
+            Policy::for_tag(Tag::new("kind", "bot"), ExplorerRequestLimit::Quota(quota)),
+            Policy::min_share(0.2, Duration::from_secs(1), ExplorerRequestLimit::Stride),
+            Policy::pooled("team", quota),
+            Policy::target_shares([(1, 0.5), (2, 0.2)]),
+        ]);
+
+        for policy in policies {
+            crate::policy_test_kit::run_all(|| policy.build());
+        }
+        // Instances of a shared policy share their state, so each one is shared anew
+        crate::policy_test_kit::run_all(|| {
+            Policy::from(SharedPolicy::with_shards(
+                ExplorerRequestLimit::Quota(quota),
+                2,
+            ))
+            .build()
+        });
+    }
+
+    // ============================================================================
+    // Tests: Migration
+    // ============================================================================
+
+    /// Replaces `old` with a fresh `new` policy at `millis`, like
+    /// [`AI::set_policy`](crate::planet::AI::set_policy).
+    fn migrate(
+        old: &dyn RequestLimitPolicy,
+        new: impl Into<Policy>,
+        millis: u64,
+    ) -> Box<dyn RequestLimitPolicy> {
+        let now = UNIX_EPOCH + Duration::from_millis(millis);
+        let mut state = PolicyState::default();
+        old.export_state(now, &mut state);
+        let mut policy = new.into().build();
+        policy.start(now);
+        policy.import_state(now, &state);
+        policy
+    }
+
+    /// **Scenario:** Explorer 1 granted twice under a quota of 3, then the quota is
+    /// lowered to 2
+    /// **Validates:**
+    /// - The grants in the window count against the new quota
+    /// - Grants out of the new window are dropped
+    #[test]
+    fn test_migrate_quota_to_quota() {
+        let mut old = ExplorerRequestLimit::Quota(Quota::new(3, Duration::from_secs(60))).build();
+        assert!(old.admit(&request(1, 0)).is_grant());
+        assert!(old.admit(&request(1, 5_000)).is_grant());
+
+        let mut new = migrate(
+            old.as_ref(),
+            ExplorerRequestLimit::Quota(Quota::new(2, Duration::from_secs(60))),
+            10_000,
+        );
+        assert_eq!(
+            new.admit(&request(1, 10_000)),
+            Decision::Deny(DenialReason::QuotaExceeded)
+        );
+        assert!(new.admit(&request(2, 10_000)).is_grant());
+
+        let mut shorter = migrate(
+            old.as_ref(),
+            ExplorerRequestLimit::Quota(Quota::new(1, Duration::from_secs(8))),
+            10_000,
+        );
+        assert!(
+            !shorter.admit(&request(1, 10_000)).is_grant(),
+            "The grant at 5s is still in the window"
+        );
+        assert!(shorter.admit(&request(1, 13_000)).is_grant());
+    }
+
+    /// **Scenario:** Explorers 1 and 2 granted under their quotas, then replaced by a
+    /// global cap of 3
+    /// **Validates:** Every carried grant counts against the global cap
+    #[test]
+    fn test_migrate_quota_to_global_cap() {
+        let window = Duration::from_secs(60);
+        let mut old = ExplorerRequestLimit::Quota(Quota::new(3, window)).build();
+        assert!(old.admit(&request(1, 0)).is_grant());
+        assert!(old.admit(&request(2, 0)).is_grant());
+
+        let mut new = migrate(
+            old.as_ref(),
+            ExplorerRequestLimit::GlobalCap(Quota::new(3, window)),
+            1000,
+        );
+        assert!(new.admit(&request(3, 1000)).is_grant());
+        assert_eq!(
+            new.admit(&request(3, 1001)),
+            Decision::Deny(DenialReason::GlobalCapReached)
+        );
+    }
+
+    /// **Scenario:** Explorer 1 granted 5 times and explorers 2 and 3 once under a
+    /// quota, then replaced by FairShare
+    /// **Validates:** The grants are replayed into scores, so explorer 1 comes after
+    /// explorer 2 and is denied as the hog
+    #[test]
+    fn test_migrate_quota_to_fair_share() {
+        let mut old = ExplorerRequestLimit::Quota(Quota::new(10, Duration::from_secs(60))).build();
+        for i in 0..5 {
+            assert!(old.admit(&request(1, i)).is_grant());
+        }
+        assert!(old.admit(&request(2, 5)).is_grant());
+        assert!(old.admit(&request(3, 5)).is_grant());
+
+        let mut new = migrate(old.as_ref(), ExplorerRequestLimit::FairShare, 10);
+        assert_eq!(new.tracked_explorers(), 3);
+        assert!(new.priority(&request(1, 10)) < new.priority(&request(2, 10)));
+        assert_eq!(
+            new.admit(&request(1, 10)),
+            Decision::Deny(DenialReason::FairShareExceeded)
+        );
+    }
+
+    /// **Scenario:** FairShare with two explorers, replaced by FairShare with
+    /// hysteresis
+    /// **Validates:** The scores are carried over unchanged
+    #[test]
+    fn test_migrate_fair_share_to_fair_share() {
+        let mut old = ExplorerRequestLimit::FairShare.build();
+        old.admit(&request(1, 0));
+        old.admit(&request(1, 0));
+        old.admit(&request(2, 1000));
+
+        let config = FairShareConfig::default().with_hysteresis(0.1);
+        let new = migrate(
+            old.as_ref(),
+            ExplorerRequestLimit::FairShareWith(config),
+            1000,
+        );
+        for explorer_id in [1, 2] {
+            let carried = new.priority(&request(explorer_id, 1000));
+            let original = old.priority(&request(explorer_id, 1000));
+            assert!((carried - original).abs() < 1e-3, "{carried} != {original}");
+        }
+    }
+
+    /// **Scenario:** Explorer 1 spends 2 of an epoch budget of 3, then the budget is
+    /// raised to 4
+    /// **Validates:** The grants of the epoch count against the new budget, until the
+    /// next epoch
+    #[test]
+    fn test_migrate_epoch_budget_to_epoch_budget() {
+        let mut old = ExplorerRequestLimit::EpochBudget(3).build();
+        assert!(old.admit(&request(1, 0)).is_grant());
+        assert!(old.admit(&request(1, 1)).is_grant());
+
+        let mut new = migrate(old.as_ref(), ExplorerRequestLimit::EpochBudget(4), 2);
+        assert!(new.admit(&request(1, 2)).is_grant());
+        assert!(new.admit(&request(1, 3)).is_grant());
+        assert_eq!(
+            new.admit(&request(1, 4)),
+            Decision::Deny(DenialReason::EpochBudgetExhausted)
+        );
+        new.advance_epoch(UNIX_EPOCH);
+        assert!(new.admit(&request(1, 5)).is_grant());
+    }
+
+    /// **Scenario:** Explorer quota stacked with another explorer quota, replaced by
+    /// a quota alone
+    /// **Validates:** Grants recorded by both members are carried over once
+    #[test]
+    fn test_migrate_composite_to_quota() {
+        let window = Duration::from_secs(60);
+        let mut old = Policy::all_of([
+            ExplorerRequestLimit::Quota(Quota::new(5, window)),
+            ExplorerRequestLimit::Quota(Quota::new(4, window)),
+        ])
+        .build();
+        assert!(old.admit(&request(1, 0)).is_grant());
+
+        let mut new = migrate(
+            old.as_ref(),
+            ExplorerRequestLimit::Quota(Quota::new(2, window)),
+            1,
+        );
+        assert!(new.admit(&request(1, 1)).is_grant());
+        assert!(!new.admit(&request(1, 2)).is_grant());
+    }
+
+    /// **Scenario:** FairShare replaced by a quota, and a quota replaced by an epoch
+    /// budget
+    /// **Validates:** History the new policy can't account starts from scratch
+    #[test]
+    fn test_migrate_unsupported_transitions() {
+        let window = Duration::from_secs(60);
+        let mut fair_share = ExplorerRequestLimit::FairShare.build();
+        fair_share.admit(&request(1, 0));
+        let mut quota = migrate(
+            fair_share.as_ref(),
+            ExplorerRequestLimit::Quota(Quota::new(1, window)),
+            1,
+        );
+        assert!(quota.admit(&request(1, 1)).is_grant());
+
+        let budget = migrate(quota.as_ref(), ExplorerRequestLimit::EpochBudget(1), 2);
+        assert_eq!(budget.tracked_explorers(), 0);
+    }
+}
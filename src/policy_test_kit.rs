@@ -0,0 +1,198 @@
+//! Conformance test kit for the request limiting policies.
+//!
+//! Whatever limits it enforces, every [`RequestLimitPolicy`] must uphold a few
+//! invariants the planet AI relies on. [`run_all`] checks them on fresh instances of a
+//! policy, driven by the same scripted workload: one explorer spamming requests while
+//! three others request now and then.
+//! - Pure evaluation: evaluating a request changes neither the decision nor the state
+//! - No negative allowances: forgetting the requests restores the decisions of a fresh
+//!   policy, and the policy never tracks more explorers than it saw
+//! - Eventual service: an explorer that stops requesting is served again, once a day
+//!   and an epoch have passed
+//! - Energy conservation: a batch of cells is only granted if a single cell would be,
+//!   and priorities are always comparable
+//!
+//! Built-in policies add a case to the conformance test of the `policy` module. Policies
+//! written outside the crate, e.g. before being contributed, run the kit from their own
+//! tests with the `test-kit` feature:
+//! ```
+//! use rustrelli::ExplorerRequestLimit;
+//! use rustrelli::policy::{Decision, Request, RequestLimitPolicy, Verdict};
+//! use rustrelli::policy_test_kit;
+//!
+//! /// Grants everything, but never more than 2 cells at once.
+//! #[derive(Debug)]
+//! struct SmallBatches;
+//!
+//! impl RequestLimitPolicy for SmallBatches {
+//!     fn evaluate(&self, request: &Request) -> Decision {
+//!         if request.units <= 2 {
+//!             Decision::Grant
+//!         } else {
+//!             Decision::Deny(rustrelli::policy::DenialReason::GlobalCapReached)
+//!         }
+//!     }
+//!
+//!     fn record(&mut self, _request: &Request, _decision: Decision) {}
+//!
+//!     fn explain(&self, _request: &Request, _verdicts: &mut Vec<Verdict>) {}
+//! }
+//!
+//! policy_test_kit::run_all(|| Box::new(SmallBatches));
+//! policy_test_kit::run_all(|| ExplorerRequestLimit::FairShare.build());
+//! ```
+
+use crate::ExplorerId;
+use crate::policy::{Request, RequestLimitPolicy};
+use crate::timeline::Product;
+use common_game::components::resource::BasicResourceType;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Time the workload starts at.
+const START: Duration = Duration::from_secs(1_000);
+/// Idle time after which every explorer must be served again.
+const IDLE: Duration = Duration::from_secs(24 * 3600);
+
+/// Checks every invariant on instances of the policy created by `build`.
+///
+/// # Panics
+/// Panics with the policy state if an invariant is broken.
+pub fn run_all<P: RequestLimitPolicy + ?Sized>(build: impl Fn() -> Box<P>) {
+    check_pure_evaluation(build().as_mut());
+    check_no_negative_allowance(build().as_mut(), build().as_mut());
+    check_eventual_service(build().as_mut());
+    check_energy_conservation(build().as_mut());
+}
+
+fn at(offset: Duration) -> SystemTime {
+    UNIX_EPOCH + START + offset
+}
+
+fn request(explorer_id: u32, millis: u64) -> Request {
+    Request {
+        explorer_id: explorer_id.into(),
+        resource: Product::Basic(BasicResourceType::Oxygen),
+        now: at(Duration::from_millis(millis)),
+        tags: Arc::default(),
+        weight: 1.0,
+        units: 1,
+        cost: 1.0,
+        streak: 0,
+    }
+}
+
+/// Explorer 1 requests every 10ms for a second, explorers 2 to 4 every 100ms.
+fn workload() -> Vec<Request> {
+    (0..100)
+        .flat_map(|step| {
+            let millis = step * 10;
+            let others = (millis % 100 == 0).then(|| (2..=4).map(move |id| request(id, millis)));
+            std::iter::once(request(1, millis)).chain(others.into_iter().flatten())
+        })
+        .collect()
+}
+
+/// Admits the workload, checking that evaluating a request twice gives the same
+/// decision without changing the tracked explorers.
+pub fn check_pure_evaluation<P: RequestLimitPolicy + ?Sized>(policy: &mut P) {
+    policy.start(at(Duration::ZERO));
+    for request in workload() {
+        let tracked = policy.tracked_explorers();
+        let decision = policy.evaluate(&request);
+        assert_eq!(
+            policy.evaluate(&request),
+            decision,
+            "Evaluation must be repeatable: {policy:?}"
+        );
+        assert_eq!(
+            policy.tracked_explorers(),
+            tracked,
+            "Evaluation must not track the explorer: {policy:?}"
+        );
+        policy.record(&request, decision);
+    }
+}
+
+/// Admits the workload on `policy`, resets it and compares its decisions with a
+/// `fresh` instance of the same policy.
+pub fn check_no_negative_allowance<P: RequestLimitPolicy + ?Sized>(policy: &mut P, fresh: &mut P) {
+    policy.start(at(Duration::ZERO));
+    fresh.start(at(Duration::ZERO));
+    let workload = workload();
+    let explorers: HashSet<ExplorerId> =
+        workload.iter().map(|request| request.explorer_id).collect();
+    for request in &workload {
+        policy.admit(request);
+        assert!(
+            policy.tracked_explorers() <= explorers.len(),
+            "Tracking explorers that never requested: {policy:?}"
+        );
+        assert!(policy.active_explorers(request.now) <= explorers.len());
+    }
+
+    policy.reset(None);
+    let after = workload.last().map_or(0, |request| {
+        request
+            .now
+            .duration_since(at(Duration::ZERO))
+            .unwrap()
+            .as_millis() as u64
+    });
+    for explorer_id in explorers {
+        let request = Request {
+            explorer_id,
+            ..request(0, after)
+        };
+        assert_eq!(
+            policy.evaluate(&request),
+            fresh.evaluate(&request),
+            "Resetting must restore the allowance of explorer {explorer_id}: {policy:?}"
+        );
+    }
+}
+
+/// Admits the workload, then checks that every explorer is served after a day without
+/// requests and a new epoch.
+pub fn check_eventual_service<P: RequestLimitPolicy + ?Sized>(policy: &mut P) {
+    policy.start(at(Duration::ZERO));
+    for request in workload() {
+        policy.admit(&request);
+    }
+
+    policy.tick(at(IDLE));
+    policy.advance_epoch(at(IDLE));
+    // A little into the new epoch, for the allowances earned over time
+    let later = (IDLE + Duration::from_secs(10)).as_millis() as u64;
+    for explorer_id in 1..=4 {
+        let request = request(explorer_id, later);
+        assert!(
+            policy.admit(&request).is_grant(),
+            "Explorer {explorer_id} must be served again: {policy:?}"
+        );
+    }
+}
+
+/// Admits the workload, checking along the way that batches of 3 cells are only
+/// granted when a single cell would be, and that priorities aren't NaN.
+pub fn check_energy_conservation<P: RequestLimitPolicy + ?Sized>(policy: &mut P) {
+    policy.start(at(Duration::ZERO));
+    for request in workload() {
+        let batch = Request {
+            units: 3,
+            ..request.clone()
+        };
+        if policy.evaluate(&batch).is_grant() {
+            assert!(
+                policy.evaluate(&request).is_grant(),
+                "Batch granted while a single cell isn't: {policy:?}"
+            );
+        }
+        assert!(
+            !policy.priority(&request).is_nan(),
+            "Priority must be comparable: {policy:?}"
+        );
+        policy.admit(&request);
+    }
+}
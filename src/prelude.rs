@@ -0,0 +1,40 @@
+//! Prelude module.
+//!
+//! Re-exports the items orchestrator code needs to create, configure and drive planets,
+//! so that a single import covers the common cases:
+//! ```
+//! use crossbeam_channel::bounded;
+//! use rustrelli::prelude::*;
+//!
+//! let (_, rx_orch) = bounded(10);
+//! let (tx_planet, _) = bounded(10);
+//! let (_, rx_expl) = bounded(10);
+//! let stats = StatsHandle::new(StatsConfig::default());
+//!
+//! let planet = create_planet_with_config(
+//!     PlanetConfig::new(1)
+//!         .with_request_limit(ExplorerRequestLimit::FairShare)
+//!         .with_stats(stats.clone()),
+//!     rx_orch,
+//!     tx_planet,
+//!     rx_expl,
+//! );
+//! assert_eq!(stats.snapshot().totals().grants, 0);
+//! ```
+//!
+//! The items of the optional features, and the less common ones, are imported from
+//! their module.
+
+pub use crate::admin::{AdminCommand, PauseMode};
+pub use crate::batch::BatchConfig;
+pub use crate::clock::{Clock, ManualClock, SystemClock};
+pub use crate::delivery::DeliveryConfig;
+pub use crate::events::{Event, EventFilter, Severity};
+pub use crate::fleet::FleetCoordinator;
+pub use crate::handle::{Health, PlanetChannels, PlanetHandle};
+pub use crate::policy::{DenialReason, Policy, PolicyArm};
+pub use crate::stats::{StatsConfig, StatsHandle};
+pub use crate::{
+    ExplorerId, ExplorerRequestLimit, PlanetConfig, Quota, RustrelliError, create_planet,
+    create_planet_custom, create_planet_with_config, create_planets, spawn_planet, spawn_planets,
+};
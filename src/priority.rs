@@ -0,0 +1,289 @@
+//! Message ordering module.
+//!
+//! The `common_game` planet loop always handles the waiting orchestrator messages before
+//! the explorer ones: a flood of explorer requests never delays sunrays, asteroids and
+//! state requests, but an orchestrator keeping its channel busy starves the explorers.
+//! With
+//! [`PlanetConfig::with_orchestrator_priority`](crate::PlanetConfig::with_orchestrator_priority),
+//! a router thread receives both channels in place of the planet, and hands their
+//! messages to the planet loop one at a time, in the order of the chosen
+//! [`OrchestratorPriority`]. The default [`OrchestratorPriority::Strict`] keeps the order
+//! of `common_game`, without a router.
+//!
+//! The router also interleaves the explorers, with
+//! [`PlanetConfig::with_fair_interleaving`](crate::PlanetConfig::with_fair_interleaving):
+//! explorer messages are sorted into a queue per explorer, and the queues take turns
+//! handing their oldest message to the planet. An explorer flooding the shared channel
+//! then only delays its own messages, as the limiter would do for its requests. The
+//! queues hold as many messages as the explorer channel, so that explorers still feel
+//! the planet backpressure.
+
+use crate::ExplorerId;
+use crate::rng::SplitMix64;
+use common_game::protocols::orchestrator_planet::OrchestratorToPlanet;
+use common_game::protocols::planet_explorer::ExplorerToPlanet;
+use common_game::utils::ID;
+use crossbeam_channel::{Receiver, Select, Sender, bounded};
+use std::collections::{HashMap, VecDeque};
+use std::thread;
+
+/// How the planet loop orders the orchestrator messages against the explorer ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrchestratorPriority {
+    /// Pending orchestrator messages are always handled before explorer ones, as
+    /// `common_game` does. Explorers are starved for as long as the orchestrator keeps
+    /// its channel busy.
+    #[default]
+    Strict,
+    /// The next message is picked at random between the orchestrator and the explorers
+    /// when both have messages waiting, so that each side gets about half of the
+    /// planet loop while the other is busy.
+    Fair,
+    /// At most this many orchestrator messages are handled in a row while an explorer
+    /// message waits, so that explorers keep a share of the planet loop.
+    Weighted(u32),
+}
+
+impl OrchestratorPriority {
+    /// Whether the orchestrator message goes first while an explorer message waits,
+    /// after `streak` orchestrator messages in a row.
+    fn orchestrator_turn(self, streak: u32, rng: &mut SplitMix64) -> bool {
+        match self {
+            OrchestratorPriority::Strict => true,
+            OrchestratorPriority::Fair => rng.next_u64() & 1 == 0,
+            OrchestratorPriority::Weighted(weight) => streak < weight,
+        }
+    }
+}
+
+/// Explorer messages received by the router, waiting for the planet.
+struct Inbox {
+    queues: HashMap<ExplorerId, VecDeque<ExplorerToPlanet>>,
+    /// Explorers with queued messages, in the order of their next turn.
+    turns: VecDeque<ExplorerId>,
+    len: usize,
+    capacity: usize,
+}
+
+impl Inbox {
+    fn new(capacity: usize) -> Self {
+        Inbox {
+            queues: HashMap::new(),
+            turns: VecDeque::new(),
+            len: 0,
+            capacity,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len >= self.capacity
+    }
+
+    fn push(&mut self, msg: ExplorerToPlanet) {
+        let explorer_id = ExplorerId::from(msg.explorer_id());
+        let queue = self.queues.entry(explorer_id).or_default();
+        if queue.is_empty() {
+            self.turns.push_back(explorer_id);
+        }
+        queue.push_back(msg);
+        self.len += 1;
+    }
+
+    /// The oldest message of the explorer whose turn it is.
+    fn pop(&mut self) -> Option<ExplorerToPlanet> {
+        let explorer_id = self.turns.pop_front()?;
+        let queue = self.queues.get_mut(&explorer_id)?;
+        let msg = queue.pop_front()?;
+        if queue.is_empty() {
+            self.queues.remove(&explorer_id);
+        } else {
+            self.turns.push_back(explorer_id);
+        }
+        self.len -= 1;
+        Some(msg)
+    }
+}
+
+/// Router handing the messages of both channels to the planet loop.
+pub(crate) struct PriorityRouter {
+    planet_id: ID,
+    priority: OrchestratorPriority,
+    /// Explorer messages the router holds at most.
+    inbox_capacity: usize,
+    from_orchestrator: Receiver<OrchestratorToPlanet>,
+    from_explorers: Receiver<ExplorerToPlanet>,
+    to_planet: (Sender<OrchestratorToPlanet>, Sender<ExplorerToPlanet>),
+}
+
+impl PriorityRouter {
+    /// Prepares a router between the given channels and the planet.
+    ///
+    /// # Returns
+    /// The router, `None` for [`OrchestratorPriority::Strict`] without interleaving which
+    /// doesn't need one, and the receivers to give to the planet. With a router, they are
+    /// rendezvous channels: a message is handed over only when the planet loop is ready
+    /// for it, which leaves the choice of the next message to the router.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn new(
+        planet_id: ID,
+        priority: OrchestratorPriority,
+        interleave: bool,
+        from_orchestrator: Receiver<OrchestratorToPlanet>,
+        from_explorers: Receiver<ExplorerToPlanet>,
+    ) -> (
+        Option<Self>,
+        Receiver<OrchestratorToPlanet>,
+        Receiver<ExplorerToPlanet>,
+    ) {
+        if priority == OrchestratorPriority::Strict && !interleave {
+            return (None, from_orchestrator, from_explorers);
+        }
+        let (to_planet_orchestrator, rx_orchestrator) = bounded(0);
+        let (to_planet_explorers, rx_explorers) = bounded(0);
+        // Without interleaving, a single message is held: the next one in arrival order
+        let inbox_capacity = match interleave {
+            true => from_explorers.capacity().unwrap_or(usize::MAX),
+            false => 1,
+        };
+        let router = PriorityRouter {
+            planet_id,
+            priority,
+            inbox_capacity,
+            from_orchestrator,
+            from_explorers,
+            to_planet: (to_planet_orchestrator, to_planet_explorers),
+        };
+        (Some(router), rx_orchestrator, rx_explorers)
+    }
+
+    /// Starts the router. It stops once the planet is gone, or both channels are
+    /// disconnected.
+    ///
+    /// # Errors
+    /// Returns a description of the problem if the thread can't be spawned.
+    pub(crate) fn start(self) -> Result<(), String> {
+        thread::Builder::new()
+            .name(format!("rustrelli-router-{}", self.planet_id))
+            .spawn(move || self.run())
+            .map(|_| ())
+            .map_err(|error| format!("Can't spawn the priority router: {error}"))
+    }
+
+    fn run(self) {
+        let mut rng = SplitMix64::new(u64::from(self.planet_id));
+        let PriorityRouter {
+            priority,
+            inbox_capacity,
+            from_orchestrator,
+            from_explorers,
+            to_planet: (to_orchestrator, to_explorers),
+            ..
+        } = self;
+        // Dropped when the matching incoming channel is disconnected, so that the
+        // planet sees the disconnection too
+        let mut orchestrator = Some((from_orchestrator, to_orchestrator));
+        let mut explorers = Some(from_explorers);
+        let mut inbox = Inbox::new(inbox_capacity);
+        // Orchestrator message received, waiting for its turn
+        let mut held = None;
+        // Orchestrator messages handed over while explorer messages wait
+        let mut streak = 0;
+
+        loop {
+            while let Some(msg) = explorers
+                .as_ref()
+                .filter(|_| !inbox.is_full())
+                .and_then(|rx| rx.try_recv().ok())
+            {
+                inbox.push(msg);
+            }
+            if held.is_none() {
+                held = orchestrator.as_ref().and_then(|(rx, _)| rx.try_recv().ok());
+            }
+
+            // Both sides waiting: the priority picks the message handed over
+            if let Some((msg, (_, to_planet))) = held.take().zip(orchestrator.as_ref()) {
+                if inbox.is_empty() || priority.orchestrator_turn(streak, &mut rng) {
+                    if to_planet.send(msg).is_err() {
+                        return;
+                    }
+                    streak += u32::from(!inbox.is_empty());
+                } else {
+                    held = Some(msg);
+                    let msg = inbox.pop().expect("Non-empty inbox");
+                    if to_explorers.send(msg).is_err() {
+                        return;
+                    }
+                    streak = 0;
+                }
+                continue;
+            }
+            if explorers.is_none() && inbox.is_empty() && orchestrator.is_none() {
+                return;
+            }
+
+            // Nothing to pick right away: wait for the next message, or for the planet
+            // to take an explorer one
+            let mut select = Select::new();
+            let orchestrator_index = orchestrator.as_ref().map(|(rx, _)| select.recv(rx));
+            let receive_index = explorers
+                .as_ref()
+                .filter(|_| !inbox.is_full())
+                .map(|rx| select.recv(rx));
+            let send_index = (!inbox.is_empty()).then(|| select.send(&to_explorers));
+            let operation = select.select();
+            let index = Some(operation.index());
+            if index == orchestrator_index {
+                let (rx, _) = orchestrator.as_ref().expect("Selected channel");
+                match operation.recv(rx) {
+                    Ok(msg) => held = Some(msg),
+                    Err(_) => orchestrator = None,
+                }
+            } else if index == receive_index {
+                match operation.recv(explorers.as_ref().expect("Selected channel")) {
+                    Ok(msg) => inbox.push(msg),
+                    Err(_) => explorers = None,
+                }
+            } else if index == send_index {
+                let msg = inbox.pop().expect("Non-empty inbox");
+                if operation.send(&to_explorers, msg).is_err() {
+                    return;
+                }
+                streak = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the explorer inbox.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Interleaving
+    // ============================================================================
+
+    /// **Scenario:** Explorer 1 queues three messages, then explorers 2 and 3 one each
+    /// **Validates:**
+    /// - Explorers take turns, in the order of their first message
+    /// - Each explorer's messages keep their order
+    #[test]
+    fn test_inbox_round_robin() {
+        let mut inbox = Inbox::new(usize::MAX);
+        for explorer_id in [1, 1, 1, 2, 3] {
+            inbox.push(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id });
+        }
+
+        let order: Vec<_> = std::iter::from_fn(|| inbox.pop())
+            .map(|msg| msg.explorer_id())
+            .collect();
+        assert_eq!(order, vec![1, 2, 3, 1, 1]);
+        assert!(inbox.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+//! Privacy module.
+//!
+//! Planets publishing their statistics on public dashboards also publish what each
+//! explorer got from them: a competing team polling the numbers often enough can tell
+//! how the others pace their requests. A [`Privacy`] setting blurs the per-explorer
+//! figures before they're published, so that dashboards still show how the planet
+//! behaves overall:
+//! - counts get [Laplace noise](https://en.wikipedia.org/wiki/Additive_noise_differential_privacy_mechanisms)
+//!   calibrated to the privacy budget `epsilon`, the lower the noisier, then are
+//!   rounded to a multiple of the bucket width
+//! - scores between 0 and 1 are rounded to [`SCORE_STEP`]
+//!
+//! The noise of a figure is drawn from the seed, the figure and its exact value: polling
+//! an unchanged figure again returns the same noisy value, rather than a fresh draw
+//! that could be averaged out. Anyone knowing the seed can compute the noise and
+//! subtract it, so [`Privacy::new`] draws a random one. Pin it with
+//! [`Privacy::with_seed`] only where the figures must be reproducible, e.g. in tests, and
+//! keep it secret then.
+//!
+//! The `http-stats` feature publishes blurred figures with
+//! [`StatsEndpoint::with_privacy`](crate::http_stats::StatsEndpoint::with_privacy).
+//! Hosts exporting statistics their own way blur them directly:
+//! ```
+//! use rustrelli::ExplorerId;
+//! use rustrelli::privacy::Privacy;
+//!
+//! let privacy = Privacy::new(0.5).with_buckets(5).with_seed(0x5EC2E7);
+//! let grants = privacy.count("grants", ExplorerId::new(7), 42);
+//! assert_eq!(grants % 5, 0);
+//! assert_eq!(grants, privacy.count("grants", ExplorerId::new(7), 42));
+//! ```
+
+use crate::ExplorerId;
+use crate::rng::{self, SplitMix64};
+
+/// Precision of the published scores.
+pub const SCORE_STEP: f64 = 0.1;
+
+/// How the per-explorer figures are blurred before being published, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Privacy {
+    epsilon: f64,
+    bucket_width: u64,
+    seed: u64,
+}
+
+impl Privacy {
+    /// Blurs counts with Laplace noise of scale `1 / epsilon`, without bucketing and
+    /// with a random seed.
+    ///
+    /// # Panics
+    /// Panics if `epsilon` isn't a positive number.
+    pub fn new(epsilon: f64) -> Self {
+        assert!(
+            epsilon > 0.0 && epsilon.is_finite(),
+            "Privacy budget must be a positive number"
+        );
+        Privacy {
+            epsilon,
+            bucket_width: 1,
+            seed: rng::random_seed(),
+        }
+    }
+
+    /// Rounds the noisy counts to a multiple of `width`, 1 for no bucketing.
+    ///
+    /// # Panics
+    /// Panics if `width` is zero.
+    pub fn with_buckets(mut self, width: u64) -> Self {
+        assert!(width > 0, "Bucket width must be greater than zero");
+        self.bucket_width = width;
+        self
+    }
+
+    /// Draws the noise from `seed` rather than a random one, so that the same figures are
+    /// blurred the same way across runs, e.g. in tests. The seed undoes the noise: keep
+    /// it secret.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Blurs the count `value` of the figure `key` of `explorer_id`, never below zero.
+    pub fn count(&self, key: &str, explorer_id: ExplorerId, value: u64) -> u64 {
+        let noisy = value as f64 + self.noise(key, explorer_id, value);
+        let width = self.bucket_width as f64;
+        ((noisy / width).round() * width).max(0.0) as u64
+    }
+
+    /// Rounds the score `value`, between 0 and 1, to [`SCORE_STEP`].
+    pub fn score(&self, value: f64) -> f64 {
+        (value / SCORE_STEP).round() * SCORE_STEP
+    }
+
+    /// Laplace noise of the figure `key` of `explorer_id` worth `value`.
+    fn noise(&self, key: &str, explorer_id: ExplorerId, value: u64) -> f64 {
+        // FNV-1a of the figure name, on top of the seed, explorer and value
+        let mut seed = self.seed
+            ^ explorer_id.ordinal().rotate_left(32)
+            ^ value.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        for byte in key.bytes() {
+            seed = (seed ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3);
+        }
+        let draw = SplitMix64::new(seed).next_f64() - 0.5;
+        let tail = (1.0 - 2.0 * draw.abs()).max(f64::MIN_POSITIVE);
+        -draw.signum() * tail.ln() / self.epsilon
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the privacy setting.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Noise
+    // ============================================================================
+
+    /// **Scenario:** The grants of 10,000 explorers, each worth 100, blurred with
+    /// budgets 1 and 0.1
+    /// **Validates:**
+    /// - The noise is centered on the value
+    /// - Its average magnitude is the Laplace scale, `1 / epsilon`
+    /// - The same figure is blurred the same way every time, by a random seed unless
+    ///   pinned
+    #[test]
+    fn test_noise_calibrated() {
+        for epsilon in [1.0, 0.1] {
+            let privacy = Privacy::new(epsilon).with_seed(42);
+            let noise: Vec<f64> = (0..10_000)
+                .map(|id| privacy.noise("grants", ExplorerId::new(id), 100))
+                .collect();
+            let mean = noise.iter().sum::<f64>() / noise.len() as f64;
+            let magnitude = noise.iter().map(|noise| noise.abs()).sum::<f64>() / noise.len() as f64;
+            assert!(mean.abs() < 0.1 / epsilon, "Mean {mean}");
+            assert!(
+                (magnitude * epsilon - 1.0).abs() < 0.1,
+                "Magnitude {magnitude}"
+            );
+        }
+
+        let privacy = Privacy::new(1.0);
+        let explorer_id = ExplorerId::new(1);
+        assert_eq!(
+            privacy.count("grants", explorer_id, 100),
+            privacy.count("grants", explorer_id, 100)
+        );
+        assert_ne!(privacy, Privacy::new(1.0));
+        assert_eq!(privacy.with_seed(42), Privacy::new(1.0).with_seed(42));
+    }
+
+    /// **Scenario:** Counts of 0 to 999 blurred with buckets of 10, and a few scores
+    /// **Validates:**
+    /// - Counts are multiples of the bucket width
+    /// - Scores are rounded to the published precision
+    #[test]
+    fn test_bucketing() {
+        let privacy = Privacy::new(0.5).with_buckets(10);
+        for value in 0..1_000 {
+            assert_eq!(privacy.count("denials", ExplorerId::new(3), value) % 10, 0);
+        }
+        assert!((privacy.score(0.87) - 0.9).abs() < 1e-9);
+        assert!((privacy.score(0.04)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,272 @@
+//! Handler profiling module, available with the `profiling` feature.
+//!
+//! The planet AI times every message handler and aggregates the durations into a
+//! [`Histogram`] per [`Handler`], read through
+//! [`Stats::timings`](crate::stats::Stats::timings). Comparing them with and without
+//! a feature (statistics, shadow policy, events...) quantifies the overhead it adds.
+//!
+//! Histograms are HDR-style: durations are counted in logarithmic buckets, each power
+//! of two split in 8 linear sub-buckets, so every recorded duration is known within
+//! 12.5% while a histogram has a fixed size whatever the range of durations.
+
+use crate::stats::StatsHandle;
+use common_game::protocols::planet_explorer::ExplorerToPlanet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Number of bits of a duration, after its most significant one, telling apart the
+/// sub-buckets of its power of two.
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+/// Buckets needed to cover every `u64` number of nanoseconds.
+const BUCKETS: usize = (64 - SUB_BUCKET_BITS as usize + 1) * SUB_BUCKETS;
+
+/// Message handler of the planet AI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Handler {
+    /// Handling of a sunray.
+    Sunray,
+    /// Handling of an asteroid.
+    Asteroid,
+    /// Handling of an internal state request of the orchestrator.
+    InternalState,
+    /// Start of the planet AI.
+    Start,
+    /// Stop of the planet AI, draining included.
+    Stop,
+    /// Handling of a `SupportedResourceRequest`.
+    SupportedResource,
+    /// Handling of a `SupportedCombinationRequest`.
+    SupportedCombination,
+    /// Handling of a `GenerateResourceRequest`.
+    GenerateResource,
+    /// Handling of a `CombineResourceRequest`.
+    CombineResource,
+    /// Handling of an `AvailableEnergyCellRequest`.
+    AvailableEnergyCell,
+    /// Handling of an explorer message unknown to the planet AI, see
+    /// [`fallback`](crate::fallback).
+    Unhandled,
+}
+
+impl Handler {
+    /// The handler of the explorer message `msg`.
+    pub(crate) fn of(msg: &ExplorerToPlanet) -> Self {
+        match msg {
+            ExplorerToPlanet::SupportedResourceRequest { .. } => Handler::SupportedResource,
+            ExplorerToPlanet::SupportedCombinationRequest { .. } => Handler::SupportedCombination,
+            ExplorerToPlanet::GenerateResourceRequest { .. } => Handler::GenerateResource,
+            ExplorerToPlanet::CombineResourceRequest { .. } => Handler::CombineResource,
+            ExplorerToPlanet::AvailableEnergyCellRequest { .. } => Handler::AvailableEnergyCell,
+            // Variants added to the protocol after this version
+            #[allow(unreachable_patterns)]
+            _ => Handler::Unhandled,
+        }
+    }
+}
+
+/// Distribution of the durations of a handler.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Histogram {
+    /// Durations counted in each bucket, in nanoseconds.
+    counts: Vec<u64>,
+    count: u64,
+    total: u128,
+    min: u64,
+    max: u64,
+}
+
+impl Histogram {
+    /// Creates an empty histogram.
+    pub fn new() -> Self {
+        Histogram {
+            counts: vec![0; BUCKETS],
+            count: 0,
+            total: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+
+    /// Records a duration. Durations longer than `u64::MAX` nanoseconds are saturated.
+    pub fn record(&mut self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[bucket_of(nanos)] += 1;
+        self.count += 1;
+        self.total += u128::from(nanos);
+        self.min = self.min.min(nanos);
+        self.max = self.max.max(nanos);
+    }
+
+    /// Number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Shortest recorded duration, exact.
+    pub fn min(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_nanos(self.min))
+    }
+
+    /// Longest recorded duration, exact.
+    pub fn max(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_nanos(self.max))
+    }
+
+    /// Mean of the recorded durations, exact.
+    pub fn mean(&self) -> Option<Duration> {
+        let mean = self.total.checked_div(u128::from(self.count))?;
+        Some(Duration::from_nanos(mean as u64))
+    }
+
+    /// Duration below which `quantile` (between 0 and 1) of the recorded durations
+    /// fall, within the precision of the buckets.
+    ///
+    /// # Panics
+    /// Panics if `quantile` isn't between 0 and 1.
+    pub fn percentile(&self, quantile: f64) -> Option<Duration> {
+        assert!(
+            (0.0..=1.0).contains(&quantile),
+            "Quantile must be between 0 and 1"
+        );
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((quantile * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        let bucket = self.counts.iter().position(|count| {
+            seen += count;
+            seen >= rank
+        })?;
+        let highest = bucket_start(bucket + 1).map_or(u64::MAX, |next| next - 1);
+        Some(Duration::from_nanos(highest.clamp(self.min, self.max)))
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Histogram")
+            .field("count", &self.count)
+            .field("min", &self.min())
+            .field("p50", &self.percentile(0.5))
+            .field("p99", &self.percentile(0.99))
+            .field("max", &self.max())
+            .finish()
+    }
+}
+
+/// Index of the bucket counting `nanos`.
+fn bucket_of(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS as u64 {
+        return nanos as usize;
+    }
+    let exponent = u64::BITS - 1 - nanos.leading_zeros();
+    let sub_bucket = (nanos >> (exponent - SUB_BUCKET_BITS)) as usize & (SUB_BUCKETS - 1);
+    (exponent - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS + sub_bucket
+}
+
+/// Lowest number of nanoseconds counted by the bucket `index`, `None` past the last one.
+fn bucket_start(index: usize) -> Option<u64> {
+    if index >= BUCKETS {
+        return None;
+    }
+    let (group, sub_bucket) = (index / SUB_BUCKETS, (index % SUB_BUCKETS) as u64);
+    Some(match group {
+        0 => sub_bucket,
+        _ => (SUB_BUCKETS as u64 + sub_bucket) << (group - 1),
+    })
+}
+
+/// Times a handler, recording its duration into the statistics when dropped.
+pub(crate) struct Timer {
+    stats: StatsHandle,
+    handler: Handler,
+    start: Instant,
+}
+
+impl Timer {
+    pub(crate) fn start(stats: &StatsHandle, handler: Handler) -> Self {
+        Timer {
+            stats: stats.clone(),
+            handler,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let handler = self.handler;
+        self.stats
+            .update(|stats| stats.record_timing(handler, elapsed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the timing histograms.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Buckets
+    // ============================================================================
+
+    /// **Scenario:** Map every bucket start back to its bucket
+    /// **Validates:**
+    /// - Buckets are contiguous, without gaps or overlaps
+    /// - The largest duration falls in the last bucket
+    #[test]
+    fn test_buckets_are_contiguous() {
+        for index in 0..BUCKETS {
+            let start = bucket_start(index).unwrap();
+            assert_eq!(bucket_of(start), index);
+            if index > 0 {
+                assert_eq!(bucket_of(start - 1), index - 1);
+            }
+        }
+        assert_eq!(bucket_of(u64::MAX), BUCKETS - 1);
+    }
+
+    // ============================================================================
+    // Tests: Percentiles
+    // ============================================================================
+
+    /// **Scenario:** Record 1 to 1000 microseconds
+    /// **Validates:**
+    /// - Count, min, max and mean are exact
+    /// - Percentiles are within the 12.5% precision of the buckets
+    #[test]
+    fn test_percentiles_within_precision() {
+        let mut histogram = Histogram::new();
+        assert_eq!(histogram.percentile(0.5), None);
+
+        for micros in 1..=1000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+
+        assert_eq!(histogram.count(), 1000);
+        assert_eq!(histogram.min(), Some(Duration::from_micros(1)));
+        assert_eq!(histogram.max(), Some(Duration::from_micros(1000)));
+        assert_eq!(histogram.mean(), Some(Duration::from_nanos(500_500)));
+        for (quantile, expected) in [(0.5, 500.0), (0.99, 990.0)] {
+            let actual = histogram.percentile(quantile).unwrap().as_secs_f64() * 1e6;
+            assert!(
+                actual >= expected && actual <= expected * 1.125,
+                "p{} = {}us",
+                quantile * 100.0,
+                actual
+            );
+        }
+        assert_eq!(histogram.percentile(1.0), histogram.max());
+    }
+}
@@ -0,0 +1,62 @@
+//! Grant receipts module.
+//!
+//! Disputes like "the planet said yes but I never got my oxygen" are settled with
+//! receipts: the planet AI numbers every granted generation request with a
+//! [`ReceiptId`], unique for the planet, and records it:
+//! - in the [journal](crate::journal), on the line of the grant
+//! - in an [`Event::Granted`](crate::events::Event::Granted), if enabled with
+//!   [`PlanetConfig::with_receipt_events`](crate::PlanetConfig::with_receipt_events)
+//! - in the [`Fulfillment`](crate::pending::Fulfillment) of a grant served from the
+//!   pending queue, and in its [`DeadLetterInfo`](crate::delivery::DeadLetterInfo) if it
+//!   couldn't be delivered
+//!
+//! The responses of the protocol can't carry the receipt of a grant answered right
+//! away. Hosts look up the receipts of the explorer around the disputed time: a grant
+//! without a dead letter was handed over, either in the response or to the host as a
+//! fulfillment, while a dead letter tells why it wasn't.
+
+use crate::ExplorerId;
+use common_game::components::resource::BasicResourceType;
+use std::fmt;
+use std::time::SystemTime;
+
+/// ID of the receipt of a grant, unique for the planet: grants are numbered from 1 in
+/// the order they're recorded.
+///
+/// Displayed as its number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct ReceiptId(u64);
+
+impl ReceiptId {
+    /// Wraps the number `id`.
+    pub const fn new(id: u64) -> Self {
+        ReceiptId(id)
+    }
+
+    /// Number of the receipt.
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ReceiptId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Receipt of a granted generation request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Receipt {
+    /// ID of the receipt.
+    pub id: ReceiptId,
+    /// The explorer the resource was granted to.
+    pub explorer_id: ExplorerId,
+    /// The granted resource.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource"))]
+    pub resource: BasicResourceType,
+    /// Time of the grant, on the planet clock.
+    pub at: SystemTime,
+}
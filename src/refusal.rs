@@ -0,0 +1,117 @@
+//! Combination refusal module.
+//!
+//! The `common_game` protocol carries the reason of a refused combination as a
+//! `String`. The planet AI decides a structured [`RefusalReason`] and renders it through
+//! a [`RefusalFormatter`], set with
+//! [`PlanetConfig::with_refusal_formatter`](crate::PlanetConfig::with_refusal_formatter).
+//!
+//! The default formatter, [`CodedRefusals`], prefixes an English description with a
+//! stable code between square brackets, which explorer bots read back with [`code`]:
+//! ```
+//! use rustrelli::policy::DenialReason;
+//! use rustrelli::refusal::{self, CodedRefusals, RefusalFormatter, RefusalReason};
+//!
+//! let message = CodedRefusals.format(&RefusalReason::Denied(DenialReason::NoEnergy));
+//! assert_eq!(message, "[no_energy] The planet has no charged energy cell.");
+//! assert_eq!(refusal::code(&message), Some("no_energy"));
+//! ```
+//!
+//! Hosts override the wording, e.g. to localize it, with any
+//! `Fn(&RefusalReason) -> String` closure.
+
+use crate::policy::DenialReason;
+use common_game::components::resource::ComplexResourceType;
+
+/// Why a combination request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefusalReason {
+    /// The planet has no combination rule.
+    NoRecipes,
+    /// The planet has no combination rule for the requested resource.
+    UnsupportedRecipe(ComplexResourceType),
+    /// The planet can't spare a charged cell for the combination.
+    Denied(DenialReason),
+    /// The `common_game` combinator refused the combination, with its own description.
+    CombinatorFailed(String),
+}
+
+impl RefusalReason {
+    /// Stable, machine-readable code of the reason, in snake case.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RefusalReason::NoRecipes => "no_recipes",
+            RefusalReason::UnsupportedRecipe(_) => "unsupported_recipe",
+            RefusalReason::Denied(reason) => match reason {
+                DenialReason::NoEnergy => "no_energy",
+                DenialReason::FairShareExceeded => "fair_share_exceeded",
+                DenialReason::QuotaExceeded => "quota_exceeded",
+                DenialReason::GlobalCapReached => "global_cap_reached",
+                DenialReason::ResourceCapReached => "resource_cap_reached",
+                DenialReason::EpochBudgetExhausted => "epoch_budget_exhausted",
+                DenialReason::Reserved => "reserved",
+                DenialReason::Undeliverable => "undeliverable",
+                DenialReason::Paused => "paused",
+                DenialReason::Banned => "banned",
+                DenialReason::UnsupportedResource => "unsupported_resource",
+                DenialReason::NotItsTurn => "not_its_turn",
+                DenialReason::Outbid => "outbid",
+                DenialReason::InsufficientCredits => "insufficient_credits",
+                DenialReason::RetriedTooEarly => "retried_too_early",
+                DenialReason::ResourceDisabled => "resource_disabled",
+                DenialReason::Offered => "offered",
+                DenialReason::TargetShareExceeded => "target_share_exceeded",
+                DenialReason::Untracked => "untracked",
+            },
+            RefusalReason::CombinatorFailed(_) => "combinator_failed",
+        }
+    }
+}
+
+/// Renders [`RefusalReason`]s into the message sent to the explorer.
+pub trait RefusalFormatter: Send {
+    /// The message explaining `reason` to the explorer.
+    fn format(&self, reason: &RefusalReason) -> String;
+}
+
+impl<F: Fn(&RefusalReason) -> String + Send> RefusalFormatter for F {
+    fn format(&self, reason: &RefusalReason) -> String {
+        self(reason)
+    }
+}
+
+/// Default [`RefusalFormatter`]: `[code] English description`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CodedRefusals;
+
+impl RefusalFormatter for CodedRefusals {
+    fn format(&self, reason: &RefusalReason) -> String {
+        let description = match reason {
+            RefusalReason::NoRecipes => "This planet type can't combine resources.".to_string(),
+            RefusalReason::UnsupportedRecipe(complex) => {
+                format!("This planet can't combine {:?}.", complex)
+            }
+            RefusalReason::Denied(DenialReason::NoEnergy) => {
+                "The planet has no charged energy cell.".to_string()
+            }
+            RefusalReason::Denied(DenialReason::Reserved) => {
+                "The only charged cells are reserved by other explorers.".to_string()
+            }
+            RefusalReason::Denied(DenialReason::Paused) => "The planet is paused.".to_string(),
+            RefusalReason::Denied(DenialReason::Banned) => {
+                "The explorer is banned from the planet.".to_string()
+            }
+            RefusalReason::Denied(reason) => format!("Combination refused: {:?}.", reason),
+            RefusalReason::CombinatorFailed(error) => error.clone(),
+        };
+        format!("[{}] {}", reason.code(), description)
+    }
+}
+
+/// Reads the code of a refusal message rendered by [`CodedRefusals`].
+///
+/// # Returns
+/// `None` if the message doesn't start with a code between square brackets.
+pub fn code(message: &str) -> Option<&str> {
+    let (code, _) = message.strip_prefix('[')?.split_once(']')?;
+    Some(code)
+}
@@ -0,0 +1,148 @@
+//! Explorer registration module.
+//!
+//! The orchestrator registers the channel of an explorer when it arrives on the
+//! planet, and removes it when it leaves. The planet loop of `common_game` replaces
+//! the channel of an explorer registered again, whether or not it left in between. The
+//! planet AI follows the new channel too: it forgets the channel clone it was watching
+//! (see [`AdminCommand::WatchExplorer`](crate::admin::AdminCommand::WatchExplorer)) and
+//! the unreachable mark of the old channel.
+//!
+//! What happens to the statistics and allowances of an explorer registered again is
+//! set with [`PlanetConfig::with_reregistration`](crate::PlanetConfig::with_reregistration),
+//! and every registration after the first one, like every departure, is reported as an
+//! [`Event::Registration`](crate::events::Event::Registration).
+//!
+//! Hosts bridging the monitoring events to bots can also have every registration
+//! reported as an [`Event::Onboarding`](crate::events::Event::Onboarding) (see
+//! [`PlanetConfig::with_onboarding_events`](crate::PlanetConfig::with_onboarding_events)),
+//! telling the rules of engagement to the explorer up front: the limit policy, the
+//! tunables it runs into and what the planet already knows about it.
+//!
+//! # Tracked explorers
+//!
+//! The planet AI keeps statistics, allowances and queues for every explorer ID it
+//! handles a message of, and keeps them after the explorer leaves. An orchestrator
+//! churning through explorer IDs would grow them without bound, so planets can cap the
+//! number of explorers they track (see
+//! [`PlanetConfig::with_max_explorers`](crate::PlanetConfig::with_max_explorers)). The
+//! first explorers to send a message are tracked, for good; the messages of the others
+//! are handled as configured by [`Untracked`], and counted in the statistics (see
+//! [`Stats::untracked_messages`](crate::stats::Stats::untracked_messages)).
+
+use crate::ExplorerId;
+use crate::tags::Tag;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// What happens to an explorer registered again, after leaving the planet or while
+/// still registered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reregistration {
+    /// The explorer carries on: its statistics and allowances are kept.
+    #[default]
+    Merge,
+    /// The explorer starts over, like a new explorer: its allowances are restored (see
+    /// [`AI::reset_quota`](crate::planet::AI::reset_quota)) and its own statistics are
+    /// cleared. The planet totals are kept.
+    Reset,
+}
+
+/// Rules of engagement of the planet for an explorer registering, reported as an
+/// [`Event::Onboarding`](crate::events::Event::Onboarding).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Onboarding {
+    /// The explorer registering.
+    pub explorer_id: ExplorerId,
+    /// Planet-wide limit policy, in its `Debug` rendering.
+    pub policy: String,
+    /// Policy arm limiting the explorer, if the planet runs an experiment.
+    pub arm: Option<String>,
+    /// Tags attached to the explorer.
+    pub tags: Vec<Tag>,
+    /// Delay advised after a denial, enforced on early retries, if backoff is enabled.
+    pub retry_after: Option<Duration>,
+    /// Most cells granted to a series of requests at once, if batch grants are enabled.
+    pub batch_size: Option<u32>,
+    /// Time given to confirm an offered cell, if two-phase grants are enabled.
+    pub claim_window: Option<Duration>,
+    /// Minimum time between two answered energy polls, if they're throttled.
+    pub energy_poll_interval: Option<Duration>,
+    /// What the planet knows of the explorer, `None` for an explorer it never saw.
+    pub history: Option<History>,
+}
+
+/// Past requests of an explorer, see [`Onboarding::history`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct History {
+    /// Generation requests granted in the current epoch.
+    pub epoch_grants: u64,
+    /// Generation requests denied in the current epoch.
+    pub epoch_denials: u64,
+    /// Resources received from the planet, basic and complex.
+    pub received: u64,
+    /// Generation requests denied since the latest grant.
+    pub streak: u32,
+}
+
+/// What happens to the messages of the explorers past the cap of tracked explorers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Untracked {
+    /// Their messages are handled as those of a single explorer,
+    /// [`ExplorerId::Overflow`]: they share its allowances and statistics. Their
+    /// requests are never queued for later (see [`pending`](crate::pending)), as the
+    /// host couldn't route the fulfillments to their real explorer.
+    Share,
+    /// Their generation and combination requests are denied with
+    /// [`DenialReason::Untracked`](crate::policy::DenialReason::Untracked), without
+    /// being charged or recorded, and their queries are answered as if the planet had
+    /// no resource, combination or charged cell to offer.
+    Deny,
+}
+
+/// Explorers tracked by the planet AI, up to a cap.
+#[derive(Debug)]
+pub(crate) struct ExplorerTracker {
+    max: usize,
+    untracked: Untracked,
+    tracked: HashSet<ExplorerId>,
+}
+
+impl ExplorerTracker {
+    pub(crate) fn new(max: usize, untracked: Untracked) -> Self {
+        ExplorerTracker {
+            max,
+            untracked,
+            tracked: HashSet::new(),
+        }
+    }
+
+    /// Admits a message of `explorer_id`, tracking the explorer if there's room for it.
+    ///
+    /// # Returns
+    /// `None` if the explorer is tracked, otherwise what happens to its message.
+    pub(crate) fn admit(&mut self, explorer_id: ExplorerId) -> Option<Untracked> {
+        if self.tracked.contains(&explorer_id) {
+            return None;
+        }
+        if self.tracked.len() < self.max {
+            self.tracked.insert(explorer_id);
+            return None;
+        }
+        Some(self.untracked)
+    }
+}
+
+/// Registration transition of an explorer, reported as an
+/// [`Event::Registration`](crate::events::Event::Registration).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Transition {
+    /// The explorer was registered again while registered: its channel was replaced.
+    Duplicate,
+    /// The explorer was registered again after leaving the planet.
+    Returned,
+    /// The explorer left the planet.
+    Departed,
+}
@@ -0,0 +1,42 @@
+//! Serialization of the foreign types embedded in the serializable types of the crate,
+//! with the `serde` feature.
+
+use common_game::components::resource::BasicResourceType;
+use serde::Serializer;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Serializes a resource type as its name, as written in the journal.
+pub(crate) fn resource<S: Serializer>(
+    resource: &BasicResourceType,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{resource:?}"))
+}
+
+/// Serializes a value of a foreign enum, like a planet type, as its name.
+pub(crate) fn name<S: Serializer, T: fmt::Debug>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{value:?}"))
+}
+
+/// Serializes a list of values of a foreign enum, like resource types, as their names.
+pub(crate) fn names<S: Serializer, T: fmt::Debug>(
+    values: &[T],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|value| format!("{value:?}")))
+}
+
+/// Serializes a map keyed by basic or complex resource type, keys as their names.
+pub(crate) fn resource_map<S: Serializer, K: fmt::Debug, V: serde::Serialize>(
+    map: &HashMap<K, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        map.iter()
+            .map(|(resource, value)| (format!("{resource:?}"), value)),
+    )
+}
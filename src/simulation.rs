@@ -0,0 +1,610 @@
+//! Monte Carlo simulation module.
+//!
+//! Default policy parameters are easier to justify with data than with intuition. A
+//! [`Simulation`] runs a [`Scenario`], i.e. the cells of a planet, a random sunray
+//! schedule and a mix of bots requesting at random times, under several limit policies
+//! and for many seeds. It reports for each policy the mean fairness and throughput over
+//! the seeds, with their 95% confidence intervals:
+//! ```
+//! use std::time::Duration;
+//! use rustrelli::ExplorerRequestLimit;
+//! use rustrelli::simulation::{Scenario, Simulation};
+//!
+//! // A greedy bot and two casual ones, sharing 5 cells charged every 100ms on average
+//! let scenario = Scenario::new(5, Duration::from_millis(100), Duration::from_secs(60))
+//!     .with_bot(1, Duration::from_millis(10))
+//!     .with_bot(2, Duration::from_millis(500))
+//!     .with_bot(3, Duration::from_millis(500));
+//! let comparison = Simulation::new(scenario, 10)
+//!     .with_policy("none", ExplorerRequestLimit::None)
+//!     .with_policy("fair share", ExplorerRequestLimit::FairShare)
+//!     .run();
+//! println!("{comparison}");
+//! ```
+//!
+//! Like [`counterfactual`](crate::analyzer::counterfactual) replays, simulations drive
+//! the policies directly, without a planet: every request has weight 1 and cost 1, and
+//! requests arriving while no cell is charged are refused without being evaluated.
+//! Every policy sees the same sunrays and requests for a given seed, so the policies
+//! are compared on the same draws. Simultaneous arrivals are resolved the same way on
+//! every run: a sunray before the requests arriving with it, and the requests of the
+//! bots in the order they were added to the scenario.
+//!
+//! # Random scenarios
+//!
+//! Hand-written scenarios only cover the cases their author thought of. A
+//! [`ScenarioGenerator`] draws random ones instead, of varying explorers, presence,
+//! resources and sunray schedules, each reproducible from its seed: fuzz-like runs check
+//! a policy against many seeds, and a failing seed replays the same scenario.
+//! ```
+//! use std::time::Duration;
+//! use rustrelli::ExplorerRequestLimit;
+//! use rustrelli::simulation::{ScenarioGenerator, Simulation};
+//!
+//! let generator = ScenarioGenerator::new(Duration::from_secs(30));
+//! for seed in 0..20 {
+//!     let scenario = generator.generate(seed);
+//!     let comparison = Simulation::new(scenario, 2)
+//!         .with_policy("fair share", ExplorerRequestLimit::FairShare)
+//!         .run();
+//!     let outcome = comparison.policies["fair share"];
+//!     assert!(outcome.utilization.mean <= 1.0, "Seed {seed} spent energy it never got");
+//! }
+//! ```
+
+use crate::ExplorerId;
+use crate::analyzer::jain_index;
+use crate::policy::{Policy, Request};
+use crate::rng::SplitMix64;
+use crate::stats::BASIC_RESOURCES;
+use crate::sunrays::Poisson;
+use crate::timeline::Product;
+use common_game::components::resource::BasicResourceType;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Quantile of the standard normal distribution for a 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+/// Durations of the random draws of a [`ScenarioGenerator`].
+const SUNRAY_INTERVALS: (Duration, Duration) = (Duration::from_millis(10), Duration::from_secs(1));
+const REQUEST_INTERVALS: (Duration, Duration) = (Duration::from_millis(5), Duration::from_secs(2));
+
+/// A bot of a [`Scenario`], requesting a resource at random times while it's on the
+/// planet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bot {
+    /// ID of the explorer the bot plays.
+    pub explorer_id: u32,
+    /// Mean time between two requests of the bot, drawn from an exponential
+    /// distribution.
+    pub mean_interval: Duration,
+    /// Resource requested by the bot.
+    pub resource: BasicResourceType,
+    /// When the bot joins the planet.
+    pub joins: Duration,
+    /// When the bot leaves the planet, if before the end of the scenario.
+    pub leaves: Option<Duration>,
+}
+
+impl Bot {
+    /// Creates a bot playing `explorer_id`, requesting Oxygen every `mean_interval` on
+    /// average for the whole scenario.
+    ///
+    /// # Panics
+    /// Panics if `mean_interval` is zero.
+    pub fn new(explorer_id: u32, mean_interval: Duration) -> Self {
+        assert!(
+            !mean_interval.is_zero(),
+            "Request interval must be greater than zero"
+        );
+        Bot {
+            explorer_id,
+            mean_interval,
+            resource: BasicResourceType::Oxygen,
+            joins: Duration::ZERO,
+            leaves: None,
+        }
+    }
+
+    /// Requests `resource` instead.
+    pub fn with_resource(mut self, resource: BasicResourceType) -> Self {
+        self.resource = resource;
+        self
+    }
+
+    /// Joins the planet at `joins`, and leaves it at `leaves` if any.
+    ///
+    /// # Panics
+    /// Panics if the bot leaves before it joins.
+    pub fn with_presence(mut self, joins: Duration, leaves: Option<Duration>) -> Self {
+        assert!(
+            leaves.is_none_or(|leaves| leaves >= joins),
+            "A bot can't leave before it joins"
+        );
+        self.joins = joins;
+        self.leaves = leaves;
+        self
+    }
+
+    /// Time of the request drawn `interval` after `last`, `Duration::MAX` once the bot
+    /// left.
+    fn next_request(&self, last: Duration, interval: Duration) -> Duration {
+        let next = last.saturating_add(interval);
+        match self.leaves {
+            Some(leaves) if next >= leaves => Duration::MAX,
+            _ => next,
+        }
+    }
+}
+
+/// Planet and bots simulated, see the [module](self) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scenario {
+    /// Energy cells of the planet, all empty at the start.
+    pub cells: u32,
+    /// Mean time between two sunrays, drawn from an exponential distribution (see
+    /// [`Poisson`]).
+    pub sunray_interval: Duration,
+    /// Simulated time.
+    pub duration: Duration,
+    pub bots: Vec<Bot>,
+}
+
+impl Scenario {
+    /// Creates a scenario without bots, of `cells` cells charged every
+    /// `sunray_interval` on average, simulated for `duration`.
+    pub fn new(cells: u32, sunray_interval: Duration, duration: Duration) -> Self {
+        Scenario {
+            cells,
+            sunray_interval,
+            duration,
+            bots: Vec::new(),
+        }
+    }
+
+    /// Adds a bot playing `explorer_id`, requesting Oxygen every `mean_interval` on
+    /// average for the whole scenario.
+    ///
+    /// # Panics
+    /// Panics if `mean_interval` is zero.
+    pub fn with_bot(self, explorer_id: u32, mean_interval: Duration) -> Self {
+        self.with_bots([Bot::new(explorer_id, mean_interval)])
+    }
+
+    /// Adds `bots`, e.g. requesting other resources or not present for the whole
+    /// scenario.
+    pub fn with_bots(mut self, bots: impl IntoIterator<Item = Bot>) -> Self {
+        self.bots.extend(bots);
+        self
+    }
+}
+
+/// Draws random [`Scenario`]s, each reproducible from its seed: see the
+/// [module](self#random-scenarios) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScenarioGenerator {
+    explorers: RangeInclusive<u32>,
+    cells: RangeInclusive<u32>,
+    duration: Duration,
+}
+
+impl ScenarioGenerator {
+    /// Creates a generator of scenarios simulated for `duration`, of 1 to 8 explorers
+    /// and 1 to 10 cells.
+    pub fn new(duration: Duration) -> Self {
+        ScenarioGenerator {
+            explorers: 1..=8,
+            cells: 1..=10,
+            duration,
+        }
+    }
+
+    /// Draws the number of explorers in `explorers`.
+    ///
+    /// # Panics
+    /// Panics if `explorers` is empty or starts at zero.
+    pub fn with_explorers(mut self, explorers: RangeInclusive<u32>) -> Self {
+        assert!(
+            !explorers.is_empty() && *explorers.start() > 0,
+            "Scenarios need at least one explorer"
+        );
+        self.explorers = explorers;
+        self
+    }
+
+    /// Draws the number of energy cells in `cells`.
+    ///
+    /// # Panics
+    /// Panics if `cells` is empty or starts at zero.
+    pub fn with_cells(mut self, cells: RangeInclusive<u32>) -> Self {
+        assert!(
+            !cells.is_empty() && *cells.start() > 0,
+            "Scenarios need at least one cell"
+        );
+        self.cells = cells;
+        self
+    }
+
+    /// Generates the scenario of `seed`:
+    /// - sunrays every 10ms to 1s on average
+    /// - explorers 1, 2, 3... requesting a random resource every 5ms to 2s on average
+    /// - half of the explorers there from the start, the others joining in the first
+    ///   half of the scenario, and one in three leaving before its end
+    ///
+    /// Mean intervals are drawn log-uniformly, so that scenarios mix greedy and casual
+    /// explorers.
+    pub fn generate(&self, seed: u64) -> Scenario {
+        let mut rng = SplitMix64::new(seed);
+        let cells = uniform(&mut rng, &self.cells);
+        let explorers = uniform(&mut rng, &self.explorers);
+        let sunray_interval = log_uniform(&mut rng, SUNRAY_INTERVALS);
+        let bots = (1..=explorers).map(|explorer_id| {
+            let mean_interval = log_uniform(&mut rng, REQUEST_INTERVALS);
+            let resource = BASIC_RESOURCES[rng.next_u64() as usize % BASIC_RESOURCES.len()];
+            let joins = if rng.next_f64() < 0.5 {
+                Duration::ZERO
+            } else {
+                self.duration.mul_f64(rng.next_f64() / 2.0)
+            };
+            let leaves = (rng.next_f64() < 1.0 / 3.0)
+                .then(|| joins + (self.duration - joins).mul_f64(rng.next_f64()));
+            Bot::new(explorer_id, mean_interval)
+                .with_resource(resource)
+                .with_presence(joins, leaves)
+        });
+        Scenario::new(cells, sunray_interval, self.duration).with_bots(bots.collect::<Vec<_>>())
+    }
+}
+
+/// Uniform draw in `range`.
+fn uniform(rng: &mut SplitMix64, range: &RangeInclusive<u32>) -> u32 {
+    let span = u64::from(range.end() - range.start()) + 1;
+    range.start() + (rng.next_u64() % span) as u32
+}
+
+/// Log-uniform draw between `low` and `high`.
+fn log_uniform(rng: &mut SplitMix64, (low, high): (Duration, Duration)) -> Duration {
+    low.mul_f64((high.as_secs_f64() / low.as_secs_f64()).powf(rng.next_f64()))
+}
+
+/// Mean of a metric over the seeds, with its 95% confidence interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Estimate {
+    pub mean: f64,
+    /// Half width of the confidence interval.
+    pub margin: f64,
+}
+
+impl Estimate {
+    /// Estimates the mean of `samples`, by the normal approximation.
+    fn new(samples: &[f64]) -> Self {
+        let count = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / count;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / (count - 1.0);
+        Estimate {
+            mean,
+            margin: Z_95 * (variance / count).sqrt(),
+        }
+    }
+
+    /// Lower bound of the confidence interval.
+    pub fn low(&self) -> f64 {
+        self.mean - self.margin
+    }
+
+    /// Upper bound of the confidence interval.
+    pub fn high(&self) -> f64 {
+        self.mean + self.margin
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.4} ± {:.4}", self.mean, self.margin)
+    }
+}
+
+/// How a policy fared over the seeds of a [`Simulation`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Outcome {
+    /// [Jain's fairness index](https://en.wikipedia.org/wiki/Fairness_measure) of the
+    /// grants between the bots.
+    pub fairness: Estimate,
+    /// Grants per second.
+    pub throughput: Estimate,
+    /// Share of the sunrays turned into grants.
+    pub utilization: Estimate,
+}
+
+/// Outcomes of the policies of a [`Simulation`], by label.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Comparison {
+    /// Seeds simulated for each policy.
+    pub seeds: u32,
+    pub policies: BTreeMap<String, Outcome>,
+}
+
+impl fmt::Display for Comparison {
+    /// Renders a table of the policies, with the confidence intervals of their metrics.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.policies.keys().map(String::len).max().unwrap_or(0);
+        write!(
+            f,
+            "{:width$} | {:16} | {:16} | utilization",
+            "policy", "fairness", "throughput"
+        )?;
+        for (label, outcome) in &self.policies {
+            write!(
+                f,
+                "\n{:width$} | {:16} | {:16} | {}",
+                label,
+                outcome.fairness.to_string(),
+                outcome.throughput.to_string(),
+                outcome.utilization
+            )?;
+        }
+        write!(f, "\n{} seeds, 95% confidence intervals", self.seeds)
+    }
+}
+
+/// Runs a [`Scenario`] under several policies, see the [module](self) documentation.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    scenario: Scenario,
+    seeds: u32,
+    policies: Vec<(String, Policy)>,
+}
+
+impl Simulation {
+    /// Creates a simulation of `scenario` for the seeds `0..seeds`, without policies.
+    ///
+    /// # Panics
+    /// Panics if `seeds` is less than 2, too few to estimate a confidence interval.
+    pub fn new(scenario: Scenario, seeds: u32) -> Self {
+        assert!(seeds >= 2, "A simulation needs at least 2 seeds");
+        Simulation {
+            scenario,
+            seeds,
+            policies: Vec::new(),
+        }
+    }
+
+    /// Adds `policy` to the compared ones, reported under `label`.
+    pub fn with_policy(mut self, label: impl Into<String>, policy: impl Into<Policy>) -> Self {
+        self.policies.push((label.into(), policy.into()));
+        self
+    }
+
+    /// Runs every seed under every policy.
+    pub fn run(&self) -> Comparison {
+        let policies = self
+            .policies
+            .iter()
+            .map(|(label, policy)| {
+                let samples: Vec<Sample> = (0..self.seeds)
+                    .map(|seed| self.run_seed(policy, seed.into()))
+                    .collect();
+                let estimate = |metric: fn(&Sample) -> f64| {
+                    Estimate::new(&samples.iter().map(metric).collect::<Vec<_>>())
+                };
+                let outcome = Outcome {
+                    fairness: estimate(|sample| sample.fairness),
+                    throughput: estimate(|sample| sample.throughput),
+                    utilization: estimate(|sample| sample.utilization),
+                };
+                (label.clone(), outcome)
+            })
+            .collect();
+        Comparison {
+            seeds: self.seeds,
+            policies,
+        }
+    }
+
+    /// Simulates the scenario under `policy` with the draws of `seed`.
+    fn run_seed(&self, policy: &Policy, seed: u64) -> Sample {
+        let scenario = &self.scenario;
+        let start = UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut policy = policy.build();
+        policy.start(start);
+
+        let mut seeds = SplitMix64::new(seed);
+        let mut sunrays = Poisson::new(scenario.sunray_interval, seeds.next_u64());
+        let mut next_sunray = sunrays.next().unwrap_or(Duration::MAX);
+        let mut bots: Vec<(Bot, Poisson, Duration)> = scenario
+            .bots
+            .iter()
+            .map(|bot| {
+                let mut requests = Poisson::new(bot.mean_interval, seeds.next_u64());
+                let first = bot.next_request(bot.joins, requests.next().unwrap_or(Duration::MAX));
+                (*bot, requests, first)
+            })
+            .collect();
+        let mut grants: BTreeMap<ExplorerId, u64> = scenario
+            .bots
+            .iter()
+            .map(|bot| (bot.explorer_id.into(), 0))
+            .collect();
+        let (mut charged, mut sunray_count) = (0, 0u64);
+        let tags = Arc::new(BTreeSet::new());
+
+        loop {
+            let next_request = bots
+                .iter_mut()
+                .min_by_key(|(_, _, next)| *next)
+                .filter(|(_, _, next)| *next < next_sunray);
+            let Some((bot, requests, next)) = next_request else {
+                if next_sunray > scenario.duration {
+                    break;
+                }
+                sunray_count += 1;
+                charged = (charged + 1).min(scenario.cells);
+                next_sunray += sunrays.next().unwrap_or(Duration::MAX);
+                continue;
+            };
+            if *next > scenario.duration {
+                break;
+            }
+            let request = Request {
+                explorer_id: bot.explorer_id.into(),
+                resource: Product::Basic(bot.resource),
+                now: at(start, *next),
+                tags: tags.clone(),
+                weight: 1.0,
+                units: 1,
+                cost: 1.0,
+                streak: 0,
+            };
+            *next = bot.next_request(*next, requests.next().unwrap_or(Duration::MAX));
+            if charged > 0 && policy.admit(&request).is_grant() {
+                charged -= 1;
+                *grants.entry(request.explorer_id).or_default() += 1;
+            }
+        }
+
+        let total: u64 = grants.values().sum();
+        Sample {
+            fairness: jain_index(grants.into_values()),
+            throughput: total as f64 / scenario.duration.as_secs_f64(),
+            utilization: total as f64 / sunray_count.max(1) as f64,
+        }
+    }
+}
+
+/// Metrics of a single simulated seed.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    fairness: f64,
+    throughput: f64,
+    utilization: f64,
+}
+
+/// Time `offset` after `start`, saturating instead of overflowing.
+fn at(start: SystemTime, offset: Duration) -> SystemTime {
+    start.checked_add(offset).unwrap_or(start)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the Monte Carlo simulations.
+
+    use super::*;
+    use crate::{ExplorerRequestLimit, Quota};
+
+    fn scenario() -> Scenario {
+        Scenario::new(5, Duration::from_millis(100), Duration::from_secs(60))
+            .with_bot(1, Duration::from_millis(10))
+            .with_bot(2, Duration::from_millis(500))
+            .with_bot(3, Duration::from_millis(500))
+    }
+
+    // ============================================================================
+    // Tests: Comparison
+    // ============================================================================
+
+    /// **Scenario:** A greedy bot and two casual ones, under no limit, fair share and a
+    /// quota of 1 grant per explorer every 10 seconds
+    /// **Validates:**
+    /// - Fair share is fairer than no limit
+    /// - The quota wastes energy, lowering the throughput
+    /// - Runs are reproducible
+    #[test]
+    fn test_compare_policies() {
+        let simulation = Simulation::new(scenario(), 10)
+            .with_policy("none", ExplorerRequestLimit::None)
+            .with_policy("fair share", ExplorerRequestLimit::FairShare)
+            .with_policy(
+                "quota",
+                ExplorerRequestLimit::Quota(Quota::new(1, Duration::from_secs(10))),
+            );
+        let comparison = simulation.run();
+        let none = comparison.policies["none"];
+        let fair_share = comparison.policies["fair share"];
+        let quota = comparison.policies["quota"];
+
+        assert!(fair_share.fairness.low() > none.fairness.high());
+        assert!(quota.throughput.high() < none.throughput.low());
+        assert!(quota.utilization.mean < 0.1);
+        assert_eq!(simulation.run(), comparison);
+    }
+
+    /// **Scenario:** Two greedy bots under no limit, one of them joining after the end of
+    /// the scenario
+    /// **Validates:** The absent bot gets nothing, whatever the seed
+    #[test]
+    fn test_bots_request_while_present() {
+        let duration = Duration::from_secs(10);
+        let scenario = Scenario::new(5, Duration::from_millis(100), duration).with_bots([
+            Bot::new(1, Duration::from_millis(10)),
+            Bot::new(2, Duration::from_millis(10)).with_presence(duration * 2, None),
+        ]);
+        let comparison = Simulation::new(scenario, 5)
+            .with_policy("none", ExplorerRequestLimit::None)
+            .run();
+        assert_eq!(comparison.policies["none"].fairness.mean, 0.5);
+        assert_eq!(comparison.policies["none"].fairness.margin, 0.0);
+    }
+
+    // ============================================================================
+    // Tests: Random scenarios
+    // ============================================================================
+
+    /// **Scenario:** Scenarios of 2 to 4 explorers and 3 cells, drawn from 50 seeds
+    /// **Validates:**
+    /// - The same seed draws the same scenario, other seeds other ones
+    /// - The draws stay within bounds, the bots leaving after they join
+    /// - Scenarios mix explorers present for the whole scenario and not
+    #[test]
+    fn test_generated_scenarios() {
+        let duration = Duration::from_secs(60);
+        let generator = ScenarioGenerator::new(duration)
+            .with_explorers(2..=4)
+            .with_cells(3..=3);
+        assert_eq!(generator.generate(7), generator.generate(7));
+        assert_ne!(generator.generate(7), generator.generate(8));
+
+        let bots: Vec<Bot> = (0..50)
+            .map(|seed| generator.generate(seed))
+            .inspect(|scenario| {
+                assert_eq!(scenario.cells, 3);
+                assert!((2..=4).contains(&scenario.bots.len()));
+                assert!(scenario.sunray_interval >= SUNRAY_INTERVALS.0);
+                assert!(scenario.sunray_interval <= SUNRAY_INTERVALS.1);
+            })
+            .flat_map(|scenario| scenario.bots)
+            .collect();
+        assert!(bots.iter().all(|bot| {
+            bot.joins <= duration / 2
+                && bot
+                    .leaves
+                    .is_none_or(|leaves| leaves >= bot.joins && leaves <= duration)
+        }));
+        assert!(
+            bots.iter()
+                .any(|bot| bot.joins.is_zero() && bot.leaves.is_none())
+        );
+        assert!(bots.iter().any(|bot| !bot.joins.is_zero()));
+        assert!(bots.iter().any(|bot| bot.leaves.is_some()));
+    }
+
+    /// **Scenario:** Samples 1, 2, 3, 4
+    /// **Validates:** The confidence interval is the mean ± 1.96 standard errors
+    #[test]
+    fn test_estimate() {
+        let estimate = Estimate::new(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(estimate.mean, 2.5);
+        let standard_error = (5.0f64 / 3.0 / 4.0).sqrt();
+        assert!((estimate.margin - 1.96 * standard_error).abs() < 1e-12);
+    }
+}
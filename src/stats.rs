@@ -0,0 +1,2231 @@
+//! Planet statistics module.
+//!
+//! This module keeps aggregate counters about what the planet AI has been doing:
+//! - Resource generation requests granted to explorers
+//! - Resource generation requests denied (no energy or rate limited), by denial reason
+//! - Sunrays received from the orchestrator
+//!
+//! Besides the all-time totals, counters are aggregated into fixed-width time buckets
+//! retained in a bounded window, so monitoring UIs can draw timelines without consuming
+//! the raw message stream.
+//!
+//! The latest observed planet state is kept next to the counters, so the host can
+//! read an [`ExtendedState`] carrying the AI information that the protocol's
+//! `DummyPlanetState` can't carry.
+//!
+//! ## Sharing
+//!
+//! The planet AI runs on the planet thread, so statistics are stored behind a
+//! [`StatsHandle`]: a cheap-to-clone shared reference that the host keeps to query
+//! the statistics while the planet is running.
+//!
+//! UIs rendering the live planet state would rather not contend for its lock: with
+//! [`PlanetConfig::with_stats_watch`](crate::PlanetConfig::with_stats_watch), the AI
+//! periodically publishes an immutable copy of the statistics to a [`StatsWatch`].
+
+use crate::ExplorerId;
+use crate::ExplorerRequestLimit;
+use crate::admin::PauseMode;
+use crate::budget::Extra;
+use crate::delivery::DeadLetterInfo;
+use crate::info::PlanetInfo;
+use crate::misconduct::{Misconduct, Signal};
+use crate::pending::Queued;
+use crate::policy::{Decision, DenialReason, Policy};
+#[cfg(feature = "profiling")]
+use crate::profiling::{Handler, Histogram};
+use crate::supply::SunrayRate;
+use crate::timeline::{CellEvent, CellTimeline};
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Configuration of the time-bucketed aggregation.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StatsConfig {
+    /// Width of a single time bucket.
+    pub bucket_width: Duration,
+    /// Maximum number of buckets retained. Older buckets are discarded.
+    pub max_buckets: usize,
+}
+
+impl Default for StatsConfig {
+    /// Ten seconds wide buckets, retained for ten minutes.
+    fn default() -> Self {
+        StatsConfig {
+            bucket_width: Duration::from_secs(10),
+            max_buckets: 60,
+        }
+    }
+}
+
+/// Event counters, used both for the all-time totals and for each time bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Counters {
+    /// Generation requests that produced a resource.
+    pub grants: u64,
+    /// Generation requests that did not produce a resource.
+    pub denials: u64,
+    /// Sunrays received from the orchestrator.
+    pub sunrays: u64,
+}
+
+/// Counters aggregated over the interval `[start, start + bucket_width)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TimeBucket {
+    /// Start of the interval covered by this bucket, aligned to a multiple of the
+    /// bucket width since [`UNIX_EPOCH`].
+    pub start: SystemTime,
+    /// Counters of the events that happened in the interval.
+    pub counters: Counters,
+}
+
+/// Demand for energy compared to the supply over a recent window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Load {
+    /// Length of the window.
+    pub window: Duration,
+    /// Generation requests received in the window, granted or denied.
+    pub requests: u64,
+    /// Sunrays received in the window, each charging a cell.
+    pub sunrays: u64,
+}
+
+impl Load {
+    /// Generation requests per charged cell: above 1, the planet is oversubscribed.
+    ///
+    /// Infinite if requests arrived but no cell was charged, zero if nothing happened.
+    pub fn factor(&self) -> f64 {
+        match (self.requests, self.sunrays) {
+            (0, _) => 0.0,
+            (_, 0) => f64::INFINITY,
+            (requests, sunrays) => requests as f64 / sunrays as f64,
+        }
+    }
+}
+
+/// Hypothetical decisions taken by the shadow policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShadowCounters {
+    /// Requests the shadow policy would have granted.
+    pub grants: u64,
+    /// Requests the shadow policy would have denied.
+    pub denials: u64,
+    /// Requests where the shadow policy disagreed with the enforced one.
+    pub disagreements: u64,
+}
+
+/// Generation outcomes of the explorers belonging to a policy arm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ArmCounters {
+    /// Generation requests that produced a resource.
+    pub grants: u64,
+    /// Generation requests that did not produce a resource.
+    pub denials: u64,
+}
+
+/// What happened to the requests offered to the pending queue (see [`crate::pending`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PendingCounters {
+    /// Requests that took a slot in the queue.
+    pub queued: u64,
+    /// Requests merged into an identical pending request.
+    pub coalesced: u64,
+    /// Requests dropped because the queue, or the share of their explorer, was full.
+    pub dropped: u64,
+    /// Pending requests dropped to make room for newer ones (see
+    /// [`Overflow::DropOldest`](crate::pending::Overflow::DropOldest)).
+    pub evicted: u64,
+    /// Pending requests dropped after waiting longer than the timeout.
+    pub expired: u64,
+}
+
+/// Generation requests received while the planet was paused, by what happened to them
+/// (see [`PauseMode`](crate::admin::PauseMode)).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PauseCounters {
+    /// Requests denied with [`DenialReason::Paused`].
+    pub rejected: u64,
+    /// Requests denied with [`DenialReason::Paused`] and queued for after resuming.
+    pub buffered: u64,
+    /// Requests left unanswered.
+    pub dropped: u64,
+}
+
+/// Outcome of the cells offered by two-phase grants (see [`crate::claim`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClaimCounters {
+    /// Cells offered to an explorer.
+    pub offered: u64,
+    /// Offers confirmed by the next request of the explorer.
+    pub confirmed: u64,
+    /// Offers not confirmed in time, or withdrawn by a request for another resource,
+    /// their cells released.
+    pub expired: u64,
+}
+
+/// Outcome of the attempts to send fulfillments to the host (see [`crate::delivery`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeliveryCounters {
+    /// Fulfillments sent to the host.
+    pub delivered: u64,
+    /// Attempts made to send a fulfillment again after a failed one.
+    pub retries: u64,
+    /// Fulfillments moved to the dead letters.
+    pub dead_lettered: u64,
+    /// Dead letters sent again on the host request.
+    pub redriven: u64,
+}
+
+/// Capability queries of an explorer. Their answers never change for a given
+/// planet, so every query after the first of each kind is a repeat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CapabilityPolls {
+    /// `SupportedResourceRequest`s received.
+    pub supported_resources: u64,
+    /// `SupportedCombinationRequest`s received.
+    pub supported_combinations: u64,
+}
+
+impl CapabilityPolls {
+    /// Queries that could have been answered by a previous response.
+    pub fn repeated(&self) -> u64 {
+        self.supported_resources.saturating_sub(1) + self.supported_combinations.saturating_sub(1)
+    }
+}
+
+/// Generation requests of an explorer denied in a row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Streak {
+    /// Requests denied since the latest grant.
+    pub current: u32,
+    /// Longest streak so far.
+    pub max: u32,
+}
+
+/// How long an explorer waited for resources: from its first denied generation request
+/// to the next grant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Wait {
+    /// When the explorer's current wait started, `None` if its latest request was
+    /// granted.
+    pub unserved_since: Option<SystemTime>,
+    /// Longest wait that ended with a grant.
+    pub longest: Duration,
+}
+
+impl Wait {
+    /// Longest wait at `now`, counting the current one.
+    pub fn longest_at(&self, now: SystemTime) -> Duration {
+        let current = self
+            .unserved_since
+            .and_then(|since| now.duration_since(since).ok())
+            .unwrap_or_default();
+        self.longest.max(current)
+    }
+}
+
+/// Wait and queue residency at which [`Satisfaction::score`] halves the grant ratio.
+pub const SATISFACTION_PATIENCE: Duration = Duration::from_secs(1);
+
+/// Outcomes of the generation requests of an explorer, summarized by
+/// [`score`](Self::score) for hosts comparing policies by what explorers get rather
+/// than by raw counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Satisfaction {
+    /// Requests granted.
+    pub grants: u64,
+    /// Requests denied.
+    pub denials: u64,
+    /// Waits that ended with a grant, see [`Wait`].
+    pub waits: u64,
+    /// Total duration of the waits that ended with a grant.
+    pub waited: Duration,
+    /// Pending requests that left the queue, served or timed out.
+    pub dequeued: u64,
+    /// Total time pending requests spent in the queue.
+    pub queued: Duration,
+}
+
+impl Satisfaction {
+    /// Share of the requests granted, `None` before the first request.
+    pub fn grant_ratio(&self) -> Option<f64> {
+        let requests = self.grants + self.denials;
+        (requests > 0).then(|| self.grants as f64 / requests as f64)
+    }
+
+    /// Average wait from a first denial to the grant, zero without any.
+    pub fn mean_wait(&self) -> Duration {
+        mean(self.waited, self.waits)
+    }
+
+    /// Average time a pending request spent in the queue, zero without any.
+    pub fn mean_residency(&self) -> Duration {
+        mean(self.queued, self.dequeued)
+    }
+
+    /// Satisfaction between 0 and 1, `None` before the first request: the grant ratio,
+    /// discounted by the average wait and queue residency, each halving it when they
+    /// reach [`SATISFACTION_PATIENCE`].
+    pub fn score(&self) -> Option<f64> {
+        let patience = SATISFACTION_PATIENCE.as_secs_f64();
+        let discount = |duration: Duration| patience / (patience + duration.as_secs_f64());
+        self.grant_ratio()
+            .map(|ratio| ratio * discount(self.mean_wait()) * discount(self.mean_residency()))
+    }
+}
+
+/// `total` divided by `count`, zero if `count` is.
+fn mean(total: Duration, count: u64) -> Duration {
+    match count {
+        0 => Duration::ZERO,
+        count => Duration::from_secs_f64(total.as_secs_f64() / count as f64),
+    }
+}
+
+/// Number of most recent intervals [`InterArrivals`] computes percentiles over.
+pub const INTER_ARRIVAL_SAMPLES: usize = 64;
+
+/// Intervals between the consecutive generation requests of an explorer, the evidence
+/// to tell bursty explorers from patient ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InterArrivals {
+    /// When the explorer's latest request arrived.
+    pub last_request: Option<SystemTime>,
+    /// The [`INTER_ARRIVAL_SAMPLES`] most recent intervals, oldest first.
+    recent: VecDeque<Duration>,
+}
+
+impl InterArrivals {
+    /// The most recent intervals, oldest first.
+    pub fn recent(&self) -> impl Iterator<Item = &Duration> {
+        self.recent.iter()
+    }
+
+    /// The `quantile` of the recent intervals (0.5 for the median), by nearest rank.
+    ///
+    /// # Returns
+    /// `None` before the second request of the explorer.
+    ///
+    /// # Panics
+    /// Panics if `quantile` is not within [0, 1].
+    pub fn percentile(&self, quantile: f64) -> Option<Duration> {
+        assert!(
+            (0.0..=1.0).contains(&quantile),
+            "Quantile must be within [0, 1]"
+        );
+        let mut sorted: Vec<_> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (quantile * sorted.len() as f64).ceil() as usize;
+        sorted.get(rank.max(1) - 1).copied()
+    }
+
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(0.5)
+    }
+
+    pub fn p95(&self) -> Option<Duration> {
+        self.percentile(0.95)
+    }
+
+    /// Records a request arrived at `now`.
+    fn record(&mut self, now: SystemTime) {
+        if let Some(last) = self.last_request.replace(now) {
+            if self.recent.len() == INTER_ARRIVAL_SAMPLES {
+                self.recent.pop_front();
+            }
+            self.recent
+                .push_back(now.duration_since(last).unwrap_or_default());
+        }
+    }
+}
+
+/// Upper bounds of the buckets of a [`ScoreHistogram`] but the last, unbounded one, as
+/// ratios of the usage score to the fair-share threshold.
+pub const SCORE_BUCKET_BOUNDS: [f32; 7] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 4.0];
+
+/// Distribution of the usage scores of the explorers active under a
+/// [`FairShare`](crate::ExplorerRequestLimit::FairShare) policy, updated after each
+/// sunray.
+///
+/// Each score is bucketed by its ratio to the threshold the explorer is denied above,
+/// so the buckets up to 1.0 hold the explorers that would be granted a resource and the
+/// others the ones that would be denied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ScoreHistogram {
+    /// Explorers in each bucket, bounded by [`SCORE_BUCKET_BOUNDS`].
+    pub counts: [u64; SCORE_BUCKET_BOUNDS.len() + 1],
+}
+
+impl ScoreHistogram {
+    /// Explorers in the histogram.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Explorers whose score is above the threshold.
+    pub fn above_threshold(&self) -> u64 {
+        let within = SCORE_BUCKET_BOUNDS.partition_point(|bound| *bound <= 1.0);
+        self.counts[within..].iter().sum()
+    }
+
+    /// Adds an explorer whose score is `ratio` times the threshold.
+    pub(crate) fn record(&mut self, ratio: f32) {
+        let bucket = SCORE_BUCKET_BOUNDS.partition_point(|bound| *bound < ratio);
+        self.counts[bucket] += 1;
+    }
+}
+
+/// Basic resource types, in the order ties between them are broken.
+pub(crate) const BASIC_RESOURCES: [BasicResourceType; 4] = [
+    BasicResourceType::Oxygen,
+    BasicResourceType::Hydrogen,
+    BasicResourceType::Carbon,
+    BasicResourceType::Silicon,
+];
+
+/// Resource types an explorer requested, telling what it tends to want.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Affinity {
+    /// Generation requests, by resource type.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource_map"))]
+    pub requests: HashMap<BasicResourceType, u64>,
+}
+
+impl Affinity {
+    /// Share of the requests of each requested type, summing to 1.
+    pub fn frequencies(&self) -> HashMap<BasicResourceType, f64> {
+        let total: u64 = self.requests.values().sum();
+        self.requests
+            .iter()
+            .map(|(resource, count)| (*resource, *count as f64 / total.max(1) as f64))
+            .collect()
+    }
+}
+
+/// Generation requests by resource type over game time, see [`Stats::demand_heatmap`].
+///
+/// Each row counts the requests of a bucket of game time since the planet AI started,
+/// the last one also counting the requests after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DemandHeatmap {
+    /// Game time covered by each row.
+    pub bucket_width: Duration,
+    /// Resource types of the columns.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::names"))]
+    pub resources: [BasicResourceType; 4],
+    /// Requests in each bucket, by resource type in the order of [`Self::resources`].
+    pub rows: Vec<[u64; 4]>,
+}
+
+impl DemandHeatmap {
+    fn new(bucket_width: Duration, buckets: usize) -> Self {
+        DemandHeatmap {
+            bucket_width,
+            resources: BASIC_RESOURCES,
+            rows: vec![[0; 4]; buckets],
+        }
+    }
+
+    /// Row of the bucket of game time `at`.
+    fn row(&self, at: Duration) -> usize {
+        let bucket = at.as_nanos() / self.bucket_width.as_nanos();
+        bucket.min(self.rows.len() as u128 - 1) as usize
+    }
+
+    /// Requests for `resource` in the bucket of game time `at`.
+    pub fn requests(&self, at: Duration, resource: BasicResourceType) -> u64 {
+        self.resources
+            .iter()
+            .position(|column| *column == resource)
+            .map_or(0, |column| self.rows[self.row(at)][column])
+    }
+
+    /// The resource type requested the most in the bucket of game time `at`, `None` if
+    /// no resource was requested then. Ties go to the first column.
+    pub fn hottest(&self, at: Duration) -> Option<BasicResourceType> {
+        let row = &self.rows[self.row(at)];
+        let (column, count) = row
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, count)| **count)?;
+        (*count > 0).then_some(self.resources[column])
+    }
+
+    /// Renders the heatmap as CSV: a row per bucket, starting with the game time the
+    /// bucket starts at in seconds, then a column per resource type.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("start_secs");
+        for resource in &self.resources {
+            csv.push_str(&format!(",{resource:?}"));
+        }
+        csv.push('\n');
+        for (index, row) in self.rows.iter().enumerate() {
+            let start = self.bucket_width * index as u32;
+            csv.push_str(&format!("{}", start.as_secs_f64()));
+            for count in row {
+                csv.push_str(&format!(",{count}"));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Counts a request for `resource` at game time `at`.
+    fn record(&mut self, at: Duration, resource: BasicResourceType) {
+        let row = self.row(at);
+        if let Some(column) = self.resources.iter().position(|column| *column == resource) {
+            self.rows[row][column] += 1;
+        }
+    }
+}
+
+/// Stockpile seeded at construction, see [`Stats::stockpile`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Stockpile {
+    /// Units seeded, one charged cell each, by resource.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource_map"))]
+    pub seeded: HashMap<BasicResourceType, u64>,
+    /// Seeded units granted to explorers, by resource: a grant discharging a seeded
+    /// cell is served from the stock of its resource while it lasts, and else from the
+    /// stock the cell was seeded for, or any stock left.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource_map"))]
+    pub served: HashMap<BasicResourceType, u64>,
+}
+
+impl Stockpile {
+    /// Seeded units of `resource` not granted yet.
+    pub fn remaining(&self, resource: BasicResourceType) -> u64 {
+        let seeded = self.seeded.get(&resource).copied().unwrap_or_default();
+        seeded.saturating_sub(self.served.get(&resource).copied().unwrap_or_default())
+    }
+
+    /// Seeded units not granted yet, of every resource.
+    pub fn remaining_total(&self) -> u64 {
+        self.seeded
+            .values()
+            .sum::<u64>()
+            .saturating_sub(self.served.values().sum::<u64>())
+    }
+}
+
+/// Resources an explorer received from the planet, see [`Stats::ledger`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Receipts {
+    /// Basic resources generated for the explorer, by type.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource_map"))]
+    pub basic: HashMap<BasicResourceType, u64>,
+    /// Complex resources combined for the explorer, by type.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource_map"))]
+    pub complex: HashMap<ComplexResourceType, u64>,
+}
+
+impl Receipts {
+    /// Total resources received, basic and complex.
+    pub fn total(&self) -> u64 {
+        self.basic.values().sum::<u64>() + self.complex.values().sum::<u64>()
+    }
+
+    /// Rows `(resource, count)` of the receipts, resources named as in the journal and
+    /// sorted by name.
+    fn rows(&self) -> Vec<(String, u64)> {
+        let basic = self
+            .basic
+            .iter()
+            .map(|(resource, count)| (format!("{resource:?}"), *count));
+        let complex = self
+            .complex
+            .iter()
+            .map(|(resource, count)| (format!("{resource:?}"), *count));
+        let mut rows: Vec<_> = basic.chain(complex).collect();
+        rows.sort();
+        rows
+    }
+}
+
+/// Explorers standing out from the statistics, see [`Stats::leaderboard`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Leaderboard {
+    /// Explorers that received the most resources, with their total, most first.
+    pub top_consumers: Vec<(ExplorerId, u64)>,
+    /// Explorers denied the most generation requests, with their denials, most first.
+    pub most_denied: Vec<(ExplorerId, u64)>,
+    /// Explorers with the best grant ratio, best first.
+    pub best_grant_ratio: Vec<GrantRatio>,
+}
+
+/// Granted generation requests of an explorer, out of its requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GrantRatio {
+    pub explorer_id: ExplorerId,
+    /// Basic resources received.
+    pub grants: u64,
+    /// Basic resources received and generation requests denied.
+    pub requests: u64,
+}
+
+impl GrantRatio {
+    /// Share of the requests granted, between 0 and 1.
+    pub fn ratio(&self) -> f64 {
+        self.grants as f64 / self.requests.max(1) as f64
+    }
+}
+
+/// Generation outcomes of each explorer in the current game epoch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EpochCounters {
+    /// Number of the current epoch, starting from 0.
+    pub epoch: u64,
+    /// Generation requests that produced a resource, by explorer.
+    pub grants: BTreeMap<ExplorerId, u64>,
+    /// Generation requests that did not produce a resource, by explorer.
+    pub denials: BTreeMap<ExplorerId, u64>,
+}
+
+/// Out-of-band view of the planet internal state, enriched with AI information.
+///
+/// Unlike the `DummyPlanetState` sent in the protocol's `InternalStateResponse`,
+/// it also reports how the AI is limiting explorers and what it has done so far.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtendedState {
+    /// Charge status of each energy cell, as last observed by the AI.
+    pub energy_cells: Vec<bool>,
+    /// Number of charged energy cells, as last observed by the AI.
+    pub charged_cells_count: usize,
+    /// Policy used to limit explorer generation requests.
+    pub limit_mode: Policy,
+    /// Number of explorers tracked by the request limiter.
+    pub tracked_explorers: usize,
+    /// Number of explorers that recently requested resources.
+    pub active_explorers: usize,
+    /// Number of requests waiting for a charged cell (see [`crate::pending`]).
+    pub pending_requests: usize,
+    /// Whether the host paused the handling of generation requests.
+    pub paused: bool,
+    /// All-time counters.
+    pub counters: Counters,
+}
+
+/// Aggregate planet statistics.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Stats {
+    config: StatsConfig,
+    totals: Counters,
+    /// All-time denials, by reason.
+    denials_by_reason: BTreeMap<DenialReason, u64>,
+    /// All-time denials, by explorer then by reason.
+    explorer_denials: BTreeMap<ExplorerId, BTreeMap<DenialReason, u64>>,
+    /// Buckets ordered from the oldest to the newest. Intervals without any
+    /// activity don't have a bucket.
+    buckets: VecDeque<TimeBucket>,
+    /// Latest observed state, `counters` excluded.
+    state: ExtendedState,
+    shadow: ShadowCounters,
+    arms: BTreeMap<String, ArmCounters>,
+    epoch: EpochCounters,
+    pending: PendingCounters,
+    paused_requests: PauseCounters,
+    stockpile: Stockpile,
+    /// Pending requests of each explorer with any.
+    pending_occupancy: BTreeMap<ExplorerId, usize>,
+    delivery: DeliveryCounters,
+    claims: ClaimCounters,
+    preemptions: u64,
+    /// Fulfillments that couldn't be delivered, oldest first.
+    dead_letters: Vec<DeadLetterInfo>,
+    /// Last time the planet AI started handling a message.
+    last_activity: Option<SystemTime>,
+    capability_polls: BTreeMap<ExplorerId, CapabilityPolls>,
+    /// Capability generation of the planet, see
+    /// [`AI::capability_generation`](crate::planet::AI::capability_generation).
+    capability_generation: u64,
+    waits: BTreeMap<ExplorerId, Wait>,
+    satisfaction: BTreeMap<ExplorerId, Satisfaction>,
+    streaks: BTreeMap<ExplorerId, Streak>,
+    inter_arrivals: BTreeMap<ExplorerId, InterArrivals>,
+    affinities: BTreeMap<ExplorerId, Affinity>,
+    score_histogram: ScoreHistogram,
+    cell_timeline: Option<CellTimeline>,
+    demand_heatmap: Option<DemandHeatmap>,
+    /// All-time requests claiming an unregistered explorer ID or the ID of another
+    /// explorer than their sender, by claimed ID.
+    spoof_attempts: BTreeMap<ExplorerId, u64>,
+    /// All-time requests received before the first registration of their explorer, by
+    /// explorer.
+    early_requests: BTreeMap<ExplorerId, u64>,
+    /// All-time misbehavior of the explorers, if scored.
+    misconduct: BTreeMap<ExplorerId, Misconduct>,
+    /// All-time energy polls left unanswered by the poll throttling, by explorer.
+    throttled_polls: BTreeMap<ExplorerId, u64>,
+    /// All-time messages of explorers past the cap of tracked explorers.
+    untracked_messages: u64,
+    /// All-time optional work skipped to keep within the latency budget, by kind.
+    budget_skips: BTreeMap<Extra, u64>,
+    /// All-time explorer messages the planet AI doesn't handle, by variant name.
+    unhandled_messages: BTreeMap<String, u64>,
+    /// Explorers banned by the host, and when their ban expires if it does.
+    bans: BTreeMap<ExplorerId, Option<SystemTime>>,
+    /// Resources disabled by the host, and when they are enabled again if they are.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::resource_map"))]
+    disabled_resources: HashMap<BasicResourceType, Option<SystemTime>>,
+    /// All-time resources received by each explorer.
+    ledger: BTreeMap<ExplorerId, Receipts>,
+    /// Latest estimate of the sunray arrival rate.
+    sunray_rate: SunrayRate,
+    /// Identity of the planet, recorded when it's created.
+    info: Option<PlanetInfo>,
+    #[cfg(feature = "profiling")]
+    timings: BTreeMap<Handler, Histogram>,
+}
+
+impl Stats {
+    /// Creates empty statistics aggregated as described by `config`.
+    ///
+    /// # Panics
+    /// Panics if `config.bucket_width` is zero.
+    pub fn new(config: StatsConfig) -> Self {
+        assert!(
+            !config.bucket_width.is_zero(),
+            "Bucket width must be greater than zero"
+        );
+        Stats {
+            config,
+            totals: Counters::default(),
+            denials_by_reason: BTreeMap::new(),
+            explorer_denials: BTreeMap::new(),
+            buckets: VecDeque::with_capacity(config.max_buckets),
+            state: ExtendedState {
+                energy_cells: Vec::new(),
+                charged_cells_count: 0,
+                limit_mode: ExplorerRequestLimit::None.into(),
+                tracked_explorers: 0,
+                active_explorers: 0,
+                pending_requests: 0,
+                paused: false,
+                counters: Counters::default(),
+            },
+            shadow: ShadowCounters::default(),
+            arms: BTreeMap::new(),
+            epoch: EpochCounters::default(),
+            pending: PendingCounters::default(),
+            paused_requests: PauseCounters::default(),
+            stockpile: Stockpile::default(),
+            claims: ClaimCounters::default(),
+            preemptions: 0,
+            pending_occupancy: BTreeMap::new(),
+            delivery: DeliveryCounters::default(),
+            dead_letters: Vec::new(),
+            last_activity: None,
+            capability_polls: BTreeMap::new(),
+            capability_generation: 0,
+            waits: BTreeMap::new(),
+            satisfaction: BTreeMap::new(),
+            streaks: BTreeMap::new(),
+            inter_arrivals: BTreeMap::new(),
+            affinities: BTreeMap::new(),
+            score_histogram: ScoreHistogram::default(),
+            cell_timeline: None,
+            demand_heatmap: None,
+            spoof_attempts: BTreeMap::new(),
+            untracked_messages: 0,
+            budget_skips: BTreeMap::new(),
+            unhandled_messages: BTreeMap::new(),
+            early_requests: BTreeMap::new(),
+            misconduct: BTreeMap::new(),
+            throttled_polls: BTreeMap::new(),
+            bans: BTreeMap::new(),
+            disabled_resources: HashMap::new(),
+            ledger: BTreeMap::new(),
+            sunray_rate: SunrayRate::default(),
+            info: None,
+            #[cfg(feature = "profiling")]
+            timings: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the generation outcomes of each policy arm that handled requests,
+    /// by arm name.
+    ///
+    /// Explorers not assigned to any arm are accounted to
+    /// [`PolicyArm::DEFAULT`](crate::policy::PolicyArm::DEFAULT).
+    pub fn arms(&self) -> &BTreeMap<String, ArmCounters> {
+        &self.arms
+    }
+
+    /// Records the outcome of a generation request handled by the policy arm `arm`.
+    pub(crate) fn record_arm(&mut self, arm: &str, granted: bool) {
+        if !self.arms.contains_key(arm) {
+            self.arms.insert(arm.to_string(), ArmCounters::default());
+        }
+        let counters = self.arms.get_mut(arm).unwrap();
+        if granted {
+            counters.grants += 1;
+        } else {
+            counters.denials += 1;
+        }
+    }
+
+    /// Returns what happened to the requests offered to the pending queue.
+    pub fn pending(&self) -> PendingCounters {
+        self.pending
+    }
+
+    /// Records what happened to a request offered to the pending queue.
+    pub(crate) fn record_queued(&mut self, queued: Queued) {
+        match queued {
+            Queued::Added => self.pending.queued += 1,
+            Queued::Coalesced => self.pending.coalesced += 1,
+            Queued::Evicted { .. } => {
+                self.pending.queued += 1;
+                self.pending.evicted += 1;
+            }
+            Queued::Dropped => self.pending.dropped += 1,
+        }
+    }
+
+    pub(crate) fn record_expired(&mut self) {
+        self.pending.expired += 1;
+    }
+
+    /// Returns the generation requests received while the planet was paused.
+    pub fn paused_requests(&self) -> PauseCounters {
+        self.paused_requests
+    }
+
+    /// Records a generation request received while the planet was paused in `mode`.
+    pub(crate) fn record_paused(&mut self, mode: PauseMode) {
+        match mode {
+            PauseMode::Reject => self.paused_requests.rejected += 1,
+            PauseMode::Buffer => self.paused_requests.buffered += 1,
+            PauseMode::Drop => self.paused_requests.dropped += 1,
+        }
+    }
+
+    /// Returns the stockpile seeded at construction (see
+    /// [`PlanetConfig::with_stockpile`](crate::PlanetConfig::with_stockpile)), and how
+    /// much of it was granted. Seeded cells aren't counted as sunrays.
+    pub fn stockpile(&self) -> &Stockpile {
+        &self.stockpile
+    }
+
+    /// Records `amount` units of `resource` seeded.
+    pub(crate) fn record_seeded(&mut self, resource: BasicResourceType, amount: u64) {
+        *self.stockpile.seeded.entry(resource).or_default() += amount;
+    }
+
+    /// Records a grant of `granted` discharging a cell seeded for `seeded`, served from
+    /// the stock of `granted` if any is left, and else from the stock of `seeded`, or
+    /// any stock left.
+    pub(crate) fn record_served(
+        &mut self,
+        granted: Option<BasicResourceType>,
+        seeded: BasicResourceType,
+    ) {
+        let stockpile = &self.stockpile;
+        let resource = granted
+            .into_iter()
+            .chain([seeded])
+            .chain(stockpile.seeded.keys().copied())
+            .find(|resource| stockpile.remaining(*resource) > 0);
+        if let Some(resource) = resource {
+            *self.stockpile.served.entry(resource).or_default() += 1;
+        }
+    }
+
+    /// Returns the outcome of the cells offered by two-phase grants.
+    pub fn claims(&self) -> ClaimCounters {
+        self.claims
+    }
+
+    /// Returns the number of cells preempted by critical explorers (see
+    /// [`PlanetConfig::with_preemption`](crate::PlanetConfig::with_preemption)).
+    pub fn preemptions(&self) -> u64 {
+        self.preemptions
+    }
+
+    pub(crate) fn record_preemption(&mut self) {
+        self.preemptions += 1;
+    }
+
+    pub(crate) fn record_claim_offered(&mut self) {
+        self.claims.offered += 1;
+    }
+
+    pub(crate) fn record_claim_confirmed(&mut self) {
+        self.claims.confirmed += 1;
+    }
+
+    pub(crate) fn record_claim_expired(&mut self) {
+        self.claims.expired += 1;
+    }
+
+    /// Returns the number of pending requests of each explorer with any.
+    pub fn pending_occupancy(&self) -> &BTreeMap<ExplorerId, usize> {
+        &self.pending_occupancy
+    }
+
+    pub(crate) fn record_pending_occupancy(&mut self, explorer_id: ExplorerId, count: usize) {
+        if count == 0 {
+            self.pending_occupancy.remove(&explorer_id);
+        } else {
+            self.pending_occupancy.insert(explorer_id, count);
+        }
+    }
+
+    pub(crate) fn clear_pending_occupancy(&mut self) {
+        self.pending_occupancy.clear();
+    }
+
+    /// Returns the outcome of the attempts to send fulfillments to the host.
+    pub fn delivery(&self) -> DeliveryCounters {
+        self.delivery
+    }
+
+    /// Returns the fulfillments that couldn't be delivered to the host, oldest first.
+    pub fn dead_letters(&self) -> &[DeadLetterInfo] {
+        &self.dead_letters
+    }
+
+    /// Records the delivery of a fulfillment of `resource` for `explorer_id`.
+    pub(crate) fn record_delivered(
+        &mut self,
+        explorer_id: ExplorerId,
+        resource: BasicResourceType,
+    ) {
+        self.delivery.delivered += 1;
+        self.record_received(explorer_id, resource);
+    }
+
+    /// Returns the all-time resources each explorer received from the planet: those
+    /// answered to its generation and combination requests, and the fulfillments of its
+    /// pending requests delivered to the host. Dead-lettered fulfillments aren't
+    /// received.
+    ///
+    /// Game scoring systems cross-check the claims of the explorers against it.
+    pub fn ledger(&self) -> &BTreeMap<ExplorerId, Receipts> {
+        &self.ledger
+    }
+
+    /// Exports the [ledger](Self::ledger) as CSV, with a `explorer_id,resource,count`
+    /// header and a row per explorer and resource type.
+    pub fn ledger_to_csv(&self) -> String {
+        let mut csv = String::from("explorer_id,resource,count\n");
+        for (explorer_id, receipts) in &self.ledger {
+            for (resource, count) in receipts.rows() {
+                csv.push_str(&format!("{explorer_id},{resource},{count}\n"));
+            }
+        }
+        csv
+    }
+
+    /// Records a basic resource received by `explorer_id`.
+    pub(crate) fn record_received(&mut self, explorer_id: ExplorerId, resource: BasicResourceType) {
+        let receipts = self.ledger.entry(explorer_id).or_default();
+        *receipts.basic.entry(resource).or_default() += 1;
+    }
+
+    /// Records a complex resource received by `explorer_id`.
+    pub(crate) fn record_received_complex(
+        &mut self,
+        explorer_id: ExplorerId,
+        resource: ComplexResourceType,
+    ) {
+        let receipts = self.ledger.entry(explorer_id).or_default();
+        *receipts.complex.entry(resource).or_default() += 1;
+    }
+
+    pub(crate) fn record_retry(&mut self) {
+        self.delivery.retries += 1;
+    }
+
+    pub(crate) fn record_dead_lettered(&mut self) {
+        self.delivery.dead_lettered += 1;
+    }
+
+    pub(crate) fn record_redriven(&mut self, count: u64) {
+        self.delivery.redriven += count;
+    }
+
+    /// Replaces the content of the dead-letter buffer.
+    pub(crate) fn set_dead_letters(&mut self, dead_letters: Vec<DeadLetterInfo>) {
+        self.dead_letters = dead_letters;
+    }
+
+    /// Returns the capability queries of each explorer that sent any, by explorer.
+    pub fn capability_polls(&self) -> &BTreeMap<ExplorerId, CapabilityPolls> {
+        &self.capability_polls
+    }
+
+    /// Records a capability query of `explorer_id`: a `SupportedCombinationRequest`
+    /// if `combinations`, a `SupportedResourceRequest` otherwise.
+    pub(crate) fn record_capability_poll(&mut self, explorer_id: ExplorerId, combinations: bool) {
+        let polls = self.capability_polls.entry(explorer_id).or_default();
+        if combinations {
+            polls.supported_combinations += 1;
+        } else {
+            polls.supported_resources += 1;
+        }
+    }
+
+    /// Returns the energy polls of each explorer coalesced into a previous answer (see
+    /// [`PlanetConfig::with_energy_poll_interval`](crate::PlanetConfig::with_energy_poll_interval)),
+    /// by explorer.
+    pub fn throttled_polls(&self) -> &BTreeMap<ExplorerId, u64> {
+        &self.throttled_polls
+    }
+
+    pub(crate) fn record_throttled_poll(&mut self, explorer_id: ExplorerId) {
+        *self.throttled_polls.entry(explorer_id).or_default() += 1;
+    }
+
+    /// Returns the capability generation of the planet: zero until the planet AI
+    /// computes the capabilities, then bumped each time they change (see
+    /// [`AI::capability_generation`](crate::planet::AI::capability_generation)).
+    pub fn capability_generation(&self) -> u64 {
+        self.capability_generation
+    }
+
+    pub(crate) fn record_capability_generation(&mut self, generation: u64) {
+        self.capability_generation = generation;
+    }
+
+    /// Returns the distribution of the durations of each handler that ran, by handler.
+    #[cfg(feature = "profiling")]
+    pub fn timings(&self) -> &BTreeMap<Handler, Histogram> {
+        &self.timings
+    }
+
+    /// Records that `handler` ran for `elapsed`.
+    #[cfg(feature = "profiling")]
+    pub(crate) fn record_timing(&mut self, handler: Handler, elapsed: Duration) {
+        self.timings.entry(handler).or_default().record(elapsed);
+    }
+
+    /// Returns how long each explorer that requested resources waited for them, by
+    /// explorer.
+    pub fn waits(&self) -> &BTreeMap<ExplorerId, Wait> {
+        &self.waits
+    }
+
+    /// Records the outcome of a generation request of `explorer_id` at `now`, to track
+    /// its wait and satisfaction.
+    pub(crate) fn record_wait(&mut self, explorer_id: ExplorerId, now: SystemTime, granted: bool) {
+        let wait = self.waits.entry(explorer_id).or_default();
+        let satisfaction = self.satisfaction.entry(explorer_id).or_default();
+        if granted {
+            satisfaction.grants += 1;
+        } else {
+            satisfaction.denials += 1;
+        }
+        match (granted, wait.unserved_since) {
+            (true, Some(since)) => {
+                let waited = now.duration_since(since).unwrap_or_default();
+                wait.longest = wait.longest.max(waited);
+                wait.unserved_since = None;
+                satisfaction.waits += 1;
+                satisfaction.waited += waited;
+            }
+            (false, None) => wait.unserved_since = Some(now),
+            _ => {}
+        }
+    }
+
+    /// Returns the satisfaction of each explorer that requested resources, by explorer.
+    pub fn satisfaction(&self) -> &BTreeMap<ExplorerId, Satisfaction> {
+        &self.satisfaction
+    }
+
+    /// Records that a pending request of `explorer_id` left the queue after `residency`.
+    pub(crate) fn record_residency(&mut self, explorer_id: ExplorerId, residency: Duration) {
+        let satisfaction = self.satisfaction.entry(explorer_id).or_default();
+        satisfaction.dequeued += 1;
+        satisfaction.queued += residency;
+    }
+
+    /// Returns the denial streaks of each explorer that was denied a request.
+    pub fn streaks(&self) -> &BTreeMap<ExplorerId, Streak> {
+        &self.streaks
+    }
+
+    /// Records that `explorer_id` was denied `current` requests in a row, zero after a
+    /// grant.
+    pub(crate) fn record_streak(&mut self, explorer_id: ExplorerId, current: u32) {
+        if current == 0 && !self.streaks.contains_key(&explorer_id) {
+            return;
+        }
+        let streak = self.streaks.entry(explorer_id).or_default();
+        streak.current = current;
+        streak.max = streak.max.max(current);
+    }
+
+    /// Returns the intervals between the generation requests of each explorer that
+    /// requested resources, by explorer.
+    pub fn inter_arrivals(&self) -> &BTreeMap<ExplorerId, InterArrivals> {
+        &self.inter_arrivals
+    }
+
+    /// Records a generation request of `explorer_id` arrived at `now`.
+    pub(crate) fn record_arrival(&mut self, explorer_id: ExplorerId, now: SystemTime) {
+        self.inter_arrivals
+            .entry(explorer_id)
+            .or_default()
+            .record(now);
+    }
+
+    /// Returns the resource types requested by each explorer that requested resources,
+    /// by explorer.
+    pub fn affinities(&self) -> &BTreeMap<ExplorerId, Affinity> {
+        &self.affinities
+    }
+
+    /// Records a generation request of `explorer_id` for `resource`.
+    pub(crate) fn record_affinity(&mut self, explorer_id: ExplorerId, resource: BasicResourceType) {
+        *self
+            .affinities
+            .entry(explorer_id)
+            .or_default()
+            .requests
+            .entry(resource)
+            .or_default() += 1;
+    }
+
+    /// Returns the demand of the explorers active at `now`, that requested resources
+    /// within `window`: the average of their request frequencies, each explorer
+    /// counting once however much it requests.
+    pub fn active_demand(
+        &self,
+        now: SystemTime,
+        window: Duration,
+    ) -> HashMap<BasicResourceType, f64> {
+        let from = now.checked_sub(window).unwrap_or(UNIX_EPOCH);
+        let active: Vec<_> = self
+            .inter_arrivals
+            .iter()
+            .filter(|(_, arrivals)| arrivals.last_request.is_some_and(|last| last >= from))
+            .filter_map(|(explorer_id, _)| self.affinities.get(explorer_id))
+            .collect();
+        let mut demand = HashMap::new();
+        for affinity in &active {
+            for (resource, frequency) in affinity.frequencies() {
+                *demand.entry(resource).or_default() += frequency / active.len() as f64;
+            }
+        }
+        demand
+    }
+
+    /// Returns the resource type the explorers active at `now` want the most (see
+    /// [`Self::active_demand`]), the one to generate speculatively.
+    ///
+    /// # Returns
+    /// `None` if no explorer requested resources within `window`.
+    pub fn wanted_resource(&self, now: SystemTime, window: Duration) -> Option<BasicResourceType> {
+        let demand = self.active_demand(now, window);
+        BASIC_RESOURCES
+            .into_iter()
+            .filter_map(|resource| demand.get(&resource).map(|share| (resource, *share)))
+            .fold(
+                None,
+                |wanted: Option<(BasicResourceType, f64)>, (resource, share)| match wanted {
+                    Some((_, best)) if best >= share => wanted,
+                    _ => Some((resource, share)),
+                },
+            )
+            .map(|(resource, _)| resource)
+    }
+
+    /// Returns the distribution of the fair-share usage scores, as of the latest sunray.
+    pub fn score_histogram(&self) -> ScoreHistogram {
+        self.score_histogram
+    }
+
+    pub(crate) fn set_score_histogram(&mut self, histogram: ScoreHistogram) {
+        self.score_histogram = histogram;
+    }
+
+    /// Returns the most recent changes of the energy cells, if the timeline is enabled
+    /// (see [`PlanetConfig::with_cell_timeline`](crate::PlanetConfig::with_cell_timeline)).
+    pub fn cell_timeline(&self) -> Option<&CellTimeline> {
+        self.cell_timeline.as_ref()
+    }
+
+    /// Starts recording the `capacity` most recent changes of the energy cells.
+    pub(crate) fn enable_cell_timeline(&mut self, capacity: usize) {
+        self.cell_timeline = Some(CellTimeline::new(capacity));
+    }
+
+    /// Records a change of an energy cell, if the timeline is enabled.
+    pub(crate) fn record_cell(&mut self, event: CellEvent) {
+        if let Some(timeline) = self.cell_timeline.as_mut() {
+            timeline.record(event);
+        }
+    }
+
+    /// Returns the generation requests by resource type over game time, if the heatmap
+    /// is enabled (see
+    /// [`PlanetConfig::with_demand_heatmap`](crate::PlanetConfig::with_demand_heatmap)).
+    pub fn demand_heatmap(&self) -> Option<&DemandHeatmap> {
+        self.demand_heatmap.as_ref()
+    }
+
+    /// Starts recording the demand heatmap, in `buckets` buckets `bucket_width` long.
+    pub(crate) fn enable_demand_heatmap(&mut self, bucket_width: Duration, buckets: usize) {
+        self.demand_heatmap = Some(DemandHeatmap::new(bucket_width, buckets));
+    }
+
+    /// Records a request for `resource` at game time `at`, if the heatmap is enabled.
+    pub(crate) fn record_demand(&mut self, at: Duration, resource: BasicResourceType) {
+        if let Some(heatmap) = self.demand_heatmap.as_mut() {
+            heatmap.record(at, resource);
+        }
+    }
+
+    /// Returns the all-time number of generation and combination requests claiming the
+    /// ID of an explorer the orchestrator didn't register on the planet, or the ID of
+    /// another explorer than the one connected on their channel, by claimed ID.
+    ///
+    /// Only counted with an [`ExplorerGateway`](crate::gateway::ExplorerGateway): the
+    /// planet loop drops the requests of unregistered explorers before the planet AI
+    /// sees them.
+    pub fn spoof_attempts(&self) -> &BTreeMap<ExplorerId, u64> {
+        &self.spoof_attempts
+    }
+
+    /// Records a request spoofing the ID `explorer_id`.
+    pub(crate) fn record_spoof_attempt(&mut self, explorer_id: ExplorerId) {
+        *self.spoof_attempts.entry(explorer_id).or_default() += 1;
+    }
+
+    /// Returns the all-time number of generation and combination requests received
+    /// before the orchestrator registered their explorer for the first time, by
+    /// explorer: requests that raced the registration rather than spoofed an ID.
+    ///
+    /// They're counted as [spoof attempts](Self::spoof_attempts) until the registration
+    /// lands. Only counted with an
+    /// [`ExplorerGateway`](crate::gateway::ExplorerGateway): the planet loop drops the
+    /// requests of unregistered explorers before the planet AI sees them.
+    pub fn early_requests(&self) -> &BTreeMap<ExplorerId, u64> {
+        &self.early_requests
+    }
+
+    /// Records a request of `explorer_id` received before its registration, once
+    /// registered.
+    pub(crate) fn record_early_request(&mut self, explorer_id: ExplorerId) {
+        *self.early_requests.entry(explorer_id).or_default() += 1;
+    }
+
+    /// Moves the spoof attempts claiming `explorer_id` to its early requests, as it's
+    /// registered for the first time.
+    pub(crate) fn reconcile_registration(&mut self, explorer_id: ExplorerId) {
+        if let Some(attempts) = self.spoof_attempts.remove(&explorer_id) {
+            *self.early_requests.entry(explorer_id).or_default() += attempts;
+        }
+    }
+
+    /// Returns the all-time misbehavior of each explorer that misbehaved, if misconduct
+    /// scoring is enabled (see [`crate::misconduct`]).
+    pub fn misconduct(&self) -> &BTreeMap<ExplorerId, Misconduct> {
+        &self.misconduct
+    }
+
+    /// Records `signal` of weight `weight` from `explorer_id`, returning its score
+    /// before and after.
+    pub(crate) fn record_misconduct(
+        &mut self,
+        explorer_id: ExplorerId,
+        signal: Signal,
+        weight: f64,
+    ) -> (f64, f64) {
+        let misconduct = self.misconduct.entry(explorer_id).or_default();
+        (misconduct.score, misconduct.record(signal, weight))
+    }
+
+    /// Forgets the spoof attempts of weight `weight` claiming `explorer_id`, as it's
+    /// registered for the first time.
+    pub(crate) fn forgive_spoofs(&mut self, explorer_id: ExplorerId, weight: f64) {
+        if let Some(misconduct) = self.misconduct.get_mut(&explorer_id) {
+            misconduct.forgive_spoofs(weight);
+        }
+    }
+
+    /// Clears the statistics of `explorer_id`, registered again as a new explorer (see
+    /// [`Reregistration::Reset`](crate::registration::Reregistration::Reset)). Planet
+    /// totals, bans, early requests and misconduct are kept.
+    pub(crate) fn reset_explorer(&mut self, explorer_id: ExplorerId) {
+        self.explorer_denials.remove(&explorer_id);
+        self.epoch.grants.remove(&explorer_id);
+        self.epoch.denials.remove(&explorer_id);
+        self.capability_polls.remove(&explorer_id);
+        self.throttled_polls.remove(&explorer_id);
+        self.waits.remove(&explorer_id);
+        self.satisfaction.remove(&explorer_id);
+        self.streaks.remove(&explorer_id);
+        self.inter_arrivals.remove(&explorer_id);
+        self.affinities.remove(&explorer_id);
+        self.ledger.remove(&explorer_id);
+    }
+
+    /// Returns the all-time number of messages of explorers past the cap of tracked
+    /// explorers, shared or denied (see
+    /// [`registration`](crate::registration#tracked-explorers)).
+    pub fn untracked_messages(&self) -> u64 {
+        self.untracked_messages
+    }
+
+    pub(crate) fn record_untracked_message(&mut self) {
+        self.untracked_messages += 1;
+    }
+
+    /// Returns the all-time number of times each kind of optional work was skipped to
+    /// keep within the latency budget (see [`crate::budget`]).
+    pub fn budget_skips(&self) -> &BTreeMap<Extra, u64> {
+        &self.budget_skips
+    }
+
+    pub(crate) fn record_budget_skip(&mut self, extra: Extra) {
+        *self.budget_skips.entry(extra).or_default() += 1;
+    }
+
+    /// Returns the all-time number of explorer messages the planet AI doesn't handle,
+    /// by variant name (see [`fallback`](crate::fallback)).
+    pub fn unhandled_messages(&self) -> &BTreeMap<String, u64> {
+        &self.unhandled_messages
+    }
+
+    /// Records an explorer message of the unhandled variant `message`.
+    pub(crate) fn record_unhandled_message(&mut self, message: &str) {
+        *self
+            .unhandled_messages
+            .entry(message.to_string())
+            .or_default() += 1;
+    }
+
+    /// Returns the explorers banned by the host, and when their ban expires if it does.
+    pub fn bans(&self) -> &BTreeMap<ExplorerId, Option<SystemTime>> {
+        &self.bans
+    }
+
+    /// Records the ban of `explorer_id` until `until`, or until unbanned if `None`.
+    pub(crate) fn record_ban(&mut self, explorer_id: ExplorerId, until: Option<SystemTime>) {
+        self.bans.insert(explorer_id, until);
+    }
+
+    /// Records that the ban of `explorer_id` was lifted or expired.
+    pub(crate) fn record_unban(&mut self, explorer_id: ExplorerId) {
+        self.bans.remove(&explorer_id);
+    }
+
+    /// Returns the resources disabled by the host, and when they are enabled again if
+    /// they are.
+    pub fn disabled_resources(&self) -> &HashMap<BasicResourceType, Option<SystemTime>> {
+        &self.disabled_resources
+    }
+
+    /// Records that `resource` is disabled until `until`, or until enabled if `None`.
+    pub(crate) fn record_disabled(
+        &mut self,
+        resource: BasicResourceType,
+        until: Option<SystemTime>,
+    ) {
+        self.disabled_resources.insert(resource, until);
+    }
+
+    /// Records that `resource` was enabled again.
+    pub(crate) fn record_enabled(&mut self, resource: BasicResourceType) {
+        self.disabled_resources.remove(&resource);
+    }
+
+    /// Returns the generation outcomes of the current epoch.
+    pub fn epoch(&self) -> &EpochCounters {
+        &self.epoch
+    }
+
+    /// Records the outcome of a generation request of `explorer_id` in the current epoch.
+    pub(crate) fn record_epoch(&mut self, explorer_id: ExplorerId, granted: bool) {
+        let counters = if granted {
+            &mut self.epoch.grants
+        } else {
+            &mut self.epoch.denials
+        };
+        *counters.entry(explorer_id).or_default() += 1;
+    }
+
+    /// Starts a new epoch, discarding the outcomes of the current one.
+    pub(crate) fn advance_epoch(&mut self) {
+        self.epoch = EpochCounters {
+            epoch: self.epoch.epoch + 1,
+            ..EpochCounters::default()
+        };
+    }
+
+    /// Returns the hypothetical decisions of the shadow policy.
+    ///
+    /// Counters stay at zero if no shadow policy is configured.
+    pub fn shadow(&self) -> ShadowCounters {
+        self.shadow
+    }
+
+    /// Records the decision of the shadow policy next to the enforced one.
+    pub(crate) fn record_shadow(&mut self, enforced: Decision, shadow: Decision) {
+        if shadow.is_grant() {
+            self.shadow.grants += 1;
+        } else {
+            self.shadow.denials += 1;
+        }
+        if enforced.is_grant() != shadow.is_grant() {
+            self.shadow.disagreements += 1;
+        }
+    }
+
+    /// Returns the latest observed planet state, together with the all-time counters.
+    ///
+    /// Energy cells are empty until the AI handles its first message.
+    pub fn extended_state(&self) -> ExtendedState {
+        ExtendedState {
+            counters: self.totals,
+            ..self.state.clone()
+        }
+    }
+
+    /// Updates the latest observed planet state in place. The `counters` field is ignored.
+    pub(crate) fn observe_state(&mut self, observe: impl FnOnce(&mut ExtendedState)) {
+        observe(&mut self.state);
+    }
+
+    /// Returns the last time the planet AI started handling a message, or `None`
+    /// if it didn't handle any yet. See the [`watchdog`](crate::watchdog) module.
+    pub fn last_activity(&self) -> Option<SystemTime> {
+        self.last_activity
+    }
+
+    /// Marks the planet AI as active at `now`.
+    pub(crate) fn record_activity(&mut self, now: SystemTime) {
+        self.last_activity = Some(now);
+    }
+
+    /// Returns the all-time totals.
+    pub fn totals(&self) -> Counters {
+        self.totals
+    }
+
+    /// Returns the retained time buckets, ordered from the oldest to the newest.
+    pub fn buckets(&self) -> impl Iterator<Item = &TimeBucket> {
+        self.buckets.iter()
+    }
+
+    /// Returns the configuration used for the aggregation.
+    pub fn config(&self) -> StatsConfig {
+        self.config
+    }
+
+    /// Records a granted generation request happened at `now`.
+    pub fn record_grant(&mut self, now: SystemTime) {
+        self.totals.grants += 1;
+        if let Some(counters) = self.bucket_mut(now) {
+            counters.grants += 1;
+        }
+    }
+
+    /// Returns the all-time number of denied generation requests, by denial reason.
+    ///
+    /// When stacked policies veto a request, the reason of the vetoing one is reported.
+    pub fn denials_by_reason(&self) -> &BTreeMap<DenialReason, u64> {
+        &self.denials_by_reason
+    }
+
+    /// Records a generation request denied at `now` for `reason`.
+    pub fn record_denial(&mut self, now: SystemTime, reason: DenialReason) {
+        self.totals.denials += 1;
+        *self.denials_by_reason.entry(reason).or_default() += 1;
+        if let Some(counters) = self.bucket_mut(now) {
+            counters.denials += 1;
+        }
+    }
+
+    /// Returns the all-time number of denied generation requests of each explorer that
+    /// was denied any, by explorer then by denial reason.
+    ///
+    /// It tells apart an explorer limited by the policy from one starved of energy.
+    pub fn explorer_denials(&self) -> &BTreeMap<ExplorerId, BTreeMap<DenialReason, u64>> {
+        &self.explorer_denials
+    }
+
+    /// Returns the `size` explorers leading each ranking, computed from the
+    /// [ledger](Self::ledger) and the [denials](Self::explorer_denials). Ties go to the
+    /// lowest explorer ID.
+    pub fn leaderboard(&self, size: usize) -> Leaderboard {
+        let mut ratios: BTreeMap<ExplorerId, GrantRatio> = BTreeMap::new();
+        for (explorer_id, receipts) in &self.ledger {
+            let grants = receipts.basic.values().sum();
+            ratios.insert(
+                *explorer_id,
+                GrantRatio {
+                    explorer_id: *explorer_id,
+                    grants,
+                    requests: grants,
+                },
+            );
+        }
+        for (explorer_id, denials) in &self.explorer_denials {
+            let ratio = ratios.entry(*explorer_id).or_insert(GrantRatio {
+                explorer_id: *explorer_id,
+                grants: 0,
+                requests: 0,
+            });
+            ratio.requests += denials.values().sum::<u64>();
+        }
+
+        let top = |mut ranking: Vec<(ExplorerId, u64)>| {
+            ranking.retain(|(_, count)| *count > 0);
+            ranking.sort_by_key(|(explorer_id, count)| (std::cmp::Reverse(*count), *explorer_id));
+            ranking.truncate(size);
+            ranking
+        };
+        let mut best_grant_ratio: Vec<_> =
+            ratios.into_values().filter(|r| r.requests > 0).collect();
+        // Compares grants / requests exactly, as grants_a * requests_b
+        best_grant_ratio.sort_by(|a, b| {
+            let (left, right) = (
+                u128::from(a.grants) * u128::from(b.requests),
+                u128::from(b.grants) * u128::from(a.requests),
+            );
+            right.cmp(&left).then(a.explorer_id.cmp(&b.explorer_id))
+        });
+        best_grant_ratio.truncate(size);
+        Leaderboard {
+            top_consumers: top(self
+                .ledger
+                .iter()
+                .map(|(explorer_id, receipts)| (*explorer_id, receipts.total()))
+                .collect()),
+            most_denied: top(self
+                .explorer_denials
+                .iter()
+                .map(|(explorer_id, denials)| (*explorer_id, denials.values().sum()))
+                .collect()),
+            best_grant_ratio,
+        }
+    }
+
+    /// Records a generation request of `explorer_id` denied for `reason`.
+    pub(crate) fn record_explorer_denial(&mut self, explorer_id: ExplorerId, reason: DenialReason) {
+        *self
+            .explorer_denials
+            .entry(explorer_id)
+            .or_default()
+            .entry(reason)
+            .or_default() += 1;
+    }
+
+    /// Records a sunray received at `now`.
+    pub fn record_sunray(&mut self, now: SystemTime) {
+        self.totals.sunrays += 1;
+        if let Some(counters) = self.bucket_mut(now) {
+            counters.sunrays += 1;
+        }
+    }
+
+    /// Returns the estimated arrival rate of the sunrays, as of the latest one (see
+    /// [`supply`](crate::supply)).
+    pub fn sunray_rate(&self) -> SunrayRate {
+        self.sunray_rate
+    }
+
+    pub(crate) fn record_sunray_rate(&mut self, rate: SunrayRate) {
+        self.sunray_rate = rate;
+    }
+
+    /// Returns the identity and configuration of the planet, if it was created by this
+    /// crate (see [`crate::info`]).
+    pub fn info(&self) -> Option<&PlanetInfo> {
+        self.info.as_ref()
+    }
+
+    pub(crate) fn record_info(&mut self, info: PlanetInfo) {
+        self.info = Some(info);
+    }
+
+    /// Records the replacement of the planet-wide limit policy in the planet info.
+    pub(crate) fn record_policy(&mut self, policy: &Policy) {
+        if let Some(info) = self.info.as_mut() {
+            info.request_limit = policy.clone();
+        }
+    }
+
+    /// Returns the load of the planet over the `window` ending at `now`.
+    ///
+    /// The window is widened to whole buckets, and can't reach further back than the
+    /// retained buckets.
+    pub fn load(&self, now: SystemTime, window: Duration) -> Load {
+        let from = now.checked_sub(window).unwrap_or(UNIX_EPOCH);
+        let (requests, sunrays) = self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.start + self.config.bucket_width > from)
+            .fold((0, 0), |(requests, sunrays), bucket| {
+                (
+                    requests + bucket.counters.grants + bucket.counters.denials,
+                    sunrays + bucket.counters.sunrays,
+                )
+            });
+        Load {
+            window,
+            requests,
+            sunrays,
+        }
+    }
+
+    /// Returns the counters of the bucket containing `now`, creating it if needed.
+    ///
+    /// Buckets that fall out of the retained window are evicted. If the clock went back
+    /// before the newest bucket, the event is accounted to the newest bucket.
+    ///
+    /// # Returns
+    /// `None` if no bucket is retained at all (`max_buckets` is zero).
+    fn bucket_mut(&mut self, now: SystemTime) -> Option<&mut Counters> {
+        if self.config.max_buckets == 0 {
+            return None;
+        }
+
+        let start = self.bucket_start(now);
+        let is_new = match self.buckets.back() {
+            Some(newest) => start > newest.start,
+            None => true,
+        };
+
+        if is_new {
+            self.buckets.push_back(TimeBucket {
+                start,
+                counters: Counters::default(),
+            });
+
+            // Evicts buckets older than the retained window.
+            let window = self.config.bucket_width * self.config.max_buckets as u32;
+            while let Some(oldest) = self.buckets.front() {
+                let expired = start
+                    .duration_since(oldest.start)
+                    .is_ok_and(|age| age >= window);
+
+                if expired || self.buckets.len() > self.config.max_buckets {
+                    self.buckets.pop_front();
+                } else {
+                    break;
+                }
+            }
+        }
+
+        self.buckets.back_mut().map(|bucket| &mut bucket.counters)
+    }
+
+    /// Aligns `now` to the start of its bucket.
+    fn bucket_start(&self, now: SystemTime) -> SystemTime {
+        let width = self.config.bucket_width.as_nanos();
+        let since_epoch = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let aligned = since_epoch - since_epoch % width;
+
+        UNIX_EPOCH + Duration::from_nanos(aligned as u64)
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new(StatsConfig::default())
+    }
+}
+
+/// Shared reference to the statistics of a planet.
+///
+/// The planet AI records events through one handle while the host keeps a clone
+/// to query them, even while the planet is running on its own thread.
+///
+/// The statistics are copy-on-write: taking a snapshot only shares the current
+/// version, and the copy is made outside the lock, by the thread taking the snapshot.
+/// The planet AI isn't stalled by snapshots however large the statistics, and only
+/// copies them when it updates a version still shared by a snapshot in progress.
+///
+/// # Examples
+/// ```
+/// use rustrelli::stats::{StatsConfig, StatsHandle};
+///
+/// let stats = StatsHandle::new(StatsConfig::default());
+/// assert_eq!(stats.snapshot().totals().grants, 0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StatsHandle(Arc<Mutex<Arc<Stats>>>);
+
+impl StatsHandle {
+    /// Creates a handle to empty statistics aggregated as described by `config`.
+    pub fn new(config: StatsConfig) -> Self {
+        StatsHandle(Arc::new(Mutex::new(Arc::new(Stats::new(config)))))
+    }
+
+    /// Returns a copy of the current statistics.
+    pub fn snapshot(&self) -> Stats {
+        Arc::unwrap_or_clone(self.shared())
+    }
+
+    /// Returns the current statistics without copying them, e.g. to serialize them on
+    /// another thread. The planet AI copies them on its next update while shared, so
+    /// the returned statistics should be dropped soon.
+    pub fn shared(&self) -> Arc<Stats> {
+        Arc::clone(&self.lock())
+    }
+
+    /// Returns the latest observed planet state, together with the all-time counters.
+    ///
+    /// See [`Stats::extended_state`].
+    pub fn extended_state(&self) -> ExtendedState {
+        self.lock().extended_state()
+    }
+
+    /// Returns the load of the planet over the `window` ending now.
+    ///
+    /// See [`Stats::load`].
+    pub fn load(&self, window: Duration) -> Load {
+        self.lock().load(SystemTime::now(), window)
+    }
+
+    /// Returns the `size` explorers leading each ranking.
+    ///
+    /// See [`Stats::leaderboard`].
+    pub fn leaderboard(&self, size: usize) -> Leaderboard {
+        self.lock().leaderboard(size)
+    }
+
+    /// Returns the last time the planet AI started handling a message.
+    ///
+    /// See [`Stats::last_activity`].
+    pub fn last_activity(&self) -> Option<SystemTime> {
+        self.lock().last_activity()
+    }
+
+    /// Returns the estimated arrival rate of the sunrays.
+    ///
+    /// See [`Stats::sunray_rate`].
+    pub fn sunray_rate(&self) -> SunrayRate {
+        self.lock().sunray_rate
+    }
+
+    /// Reads the shared statistics with `read`, without taking a snapshot.
+    pub(crate) fn read<T>(&self, read: impl FnOnce(&Stats) -> T) -> T {
+        read(&self.lock())
+    }
+
+    /// Applies `update` to the shared statistics.
+    pub(crate) fn update(&self, update: impl FnOnce(&mut Stats)) {
+        update(Arc::make_mut(&mut self.lock()))
+    }
+
+    /// Locks the shared statistics.
+    ///
+    /// Statistics are plain counters that can't be left in an inconsistent state,
+    /// so a poisoned lock is recovered instead of propagating the panic.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Arc<Stats>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Receiver of the copies of the statistics periodically published by the planet AI,
+/// like a watch channel: only the latest copy is kept.
+///
+/// Clones share the published copies, but each tells on its own whether a copy was
+/// published since it last looked.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use rustrelli::PlanetConfig;
+/// use rustrelli::stats::StatsWatch;
+///
+/// let mut watch = StatsWatch::new();
+/// let config = PlanetConfig::new(1).with_stats_watch(watch.clone(), Duration::from_secs(1));
+/// assert_eq!(watch.latest().totals().grants, 0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StatsWatch {
+    shared: Arc<WatchShared>,
+    /// Version of the latest copy this receiver looked at.
+    seen: u64,
+}
+
+#[derive(Debug, Default)]
+struct WatchShared {
+    /// The latest copy, with its version, counting from 0 for the empty statistics.
+    latest: Mutex<(u64, Arc<Stats>)>,
+    published: Condvar,
+}
+
+impl StatsWatch {
+    /// Creates a watch holding empty statistics until the first publication.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the latest published copy, marking it as seen.
+    pub fn latest(&mut self) -> Arc<Stats> {
+        let (version, stats) = self.lock().clone();
+        self.seen = version;
+        stats
+    }
+
+    /// Whether a copy was published since this receiver last looked.
+    pub fn has_changed(&self) -> bool {
+        self.lock().0 != self.seen
+    }
+
+    /// Waits up to `timeout` for a copy newer than the last seen one.
+    ///
+    /// # Returns
+    /// The new copy, marked as seen, or `None` if none was published in time.
+    pub fn wait_for_change(&mut self, timeout: Duration) -> Option<Arc<Stats>> {
+        let seen = self.seen;
+        let (version, stats) = {
+            let guard = self.lock();
+            let (guard, _) = self
+                .shared
+                .published
+                .wait_timeout_while(guard, timeout, |(version, _)| *version == seen)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            guard.clone()
+        };
+        self.seen = version;
+        (version != seen).then_some(stats)
+    }
+
+    /// Replaces the latest copy with `stats`, waking up the waiting receivers.
+    pub(crate) fn publish(&self, stats: Arc<Stats>) {
+        let mut latest = self.lock();
+        *latest = (latest.0 + 1, stats);
+        self.shared.published.notify_all();
+    }
+
+    /// Locks the latest copy, recovering a poisoned lock like [`StatsHandle`].
+    fn lock(&self) -> std::sync::MutexGuard<'_, (u64, Arc<Stats>)> {
+        self.shared
+            .latest
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the time-bucketed aggregation.
+
+    use super::*;
+
+    // ============================================================================
+    // Test Helper
+    // ============================================================================
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn small_config() -> StatsConfig {
+        StatsConfig {
+            bucket_width: Duration::from_secs(10),
+            max_buckets: 3,
+        }
+    }
+
+    // ============================================================================
+    // Tests: Time Buckets
+    // ============================================================================
+
+    /// **Scenario:** Events happen inside the same bucket interval
+    /// **Validates:**
+    /// - A single bucket is created, aligned to the bucket width
+    /// - Bucket counters and totals agree
+    #[test]
+    fn test_events_in_same_bucket() {
+        let mut stats = Stats::new(small_config());
+
+        stats.record_grant(at(101));
+        stats.record_denial(at(105), DenialReason::NoEnergy);
+        stats.record_sunray(at(109));
+
+        let buckets: Vec<_> = stats.buckets().collect();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].start, at(100), "Bucket aligned to width");
+        assert_eq!(buckets[0].counters, stats.totals());
+        assert_eq!(
+            stats.totals(),
+            Counters {
+                grants: 1,
+                denials: 1,
+                sunrays: 1
+            }
+        );
+    }
+
+    /// **Scenario:** Events keep coming after the retained window is full
+    /// **Validates:**
+    /// - At most `max_buckets` buckets are retained
+    /// - Buckets outside the window are evicted, totals are not
+    #[test]
+    fn test_window_is_bounded() {
+        let mut stats = Stats::new(small_config());
+
+        for secs in [0, 10, 20, 30, 40] {
+            stats.record_sunray(at(secs));
+        }
+
+        let starts: Vec<_> = stats.buckets().map(|bucket| bucket.start).collect();
+        assert_eq!(starts, vec![at(20), at(30), at(40)]);
+        assert_eq!(stats.totals().sunrays, 5, "Totals are never evicted");
+    }
+
+    /// **Scenario:** Long idle period between two events
+    /// **Validates:** Buckets older than the window are evicted even if fewer than `max_buckets`
+    #[test]
+    fn test_idle_gap_evicts_old_buckets() {
+        let mut stats = Stats::new(small_config());
+
+        stats.record_grant(at(0));
+        stats.record_grant(at(1000));
+
+        let starts: Vec<_> = stats.buckets().map(|bucket| bucket.start).collect();
+        assert_eq!(starts, vec![at(1000)]);
+    }
+
+    /// **Scenario:** Clock goes back before the newest bucket
+    /// **Validates:** Event is accounted to the newest bucket instead of reordering buckets
+    #[test]
+    fn test_clock_going_back() {
+        let mut stats = Stats::new(small_config());
+
+        stats.record_grant(at(50));
+        stats.record_grant(at(20));
+
+        let buckets: Vec<_> = stats.buckets().collect();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].counters.grants, 2);
+    }
+
+    // ============================================================================
+    // Tests: Load
+    // ============================================================================
+
+    /// **Scenario:** Requests and sunrays spread over three buckets
+    /// **Validates:**
+    /// - Only the buckets overlapping the window are counted
+    /// - The factor is the number of requests per sunray
+    #[test]
+    fn test_load_over_window() {
+        let mut stats = Stats::new(small_config());
+
+        stats.record_sunray(at(5));
+        stats.record_grant(at(6));
+        stats.record_sunray(at(15));
+        stats.record_grant(at(21));
+        stats.record_denial(at(22), DenialReason::NoEnergy);
+        stats.record_denial(at(23), DenialReason::NoEnergy);
+
+        let load = stats.load(at(25), Duration::from_secs(10));
+        assert_eq!((load.requests, load.sunrays), (3, 1));
+        assert_eq!(load.factor(), 3.0);
+
+        let all = stats.load(at(25), Duration::from_secs(60));
+        assert_eq!((all.requests, all.sunrays), (4, 2));
+        assert_eq!(stats.load(at(100), Duration::from_secs(10)).factor(), 0.0);
+    }
+
+    // ============================================================================
+    // Tests: Waits
+    // ============================================================================
+
+    /// **Scenario:** An explorer is denied at 10s and 12s, granted at 15s, then denied
+    /// again at 20s
+    /// **Validates:**
+    /// - The wait runs from the first denial to the grant
+    /// - The ongoing wait counts once longer than the finished ones
+    #[test]
+    fn test_waits() {
+        let mut stats = Stats::new(small_config());
+
+        stats.record_wait(ExplorerId::new(1), at(10), false);
+        stats.record_wait(ExplorerId::new(1), at(12), false);
+        stats.record_wait(ExplorerId::new(1), at(15), true);
+        stats.record_wait(ExplorerId::new(1), at(20), false);
+
+        let wait = stats.waits()[&ExplorerId::new(1)];
+        assert_eq!(wait.longest, Duration::from_secs(5));
+        assert_eq!(wait.unserved_since, Some(at(20)));
+        assert_eq!(wait.longest_at(at(22)), Duration::from_secs(5));
+        assert_eq!(wait.longest_at(at(30)), Duration::from_secs(10));
+    }
+
+    /// **Scenario:** An explorer is denied at 10s, granted at 11s and 12s, and one of its
+    /// pending requests spends 3s in the queue
+    /// **Validates:**
+    /// - The grant ratio, average wait and residency are tracked
+    /// - The score discounts the grant ratio by the wait and the residency
+    /// - No score before the first request
+    #[test]
+    fn test_satisfaction() {
+        let mut stats = Stats::new(small_config());
+        stats.record_residency(ExplorerId::new(2), Duration::from_secs(1));
+        assert_eq!(stats.satisfaction()[&ExplorerId::new(2)].score(), None);
+
+        stats.record_wait(ExplorerId::new(1), at(10), false);
+        stats.record_wait(ExplorerId::new(1), at(11), true);
+        stats.record_wait(ExplorerId::new(1), at(12), true);
+        stats.record_residency(ExplorerId::new(1), Duration::from_secs(3));
+
+        let satisfaction = stats.satisfaction()[&ExplorerId::new(1)];
+        assert_eq!(satisfaction.grant_ratio(), Some(2.0 / 3.0));
+        assert_eq!(satisfaction.mean_wait(), Duration::from_secs(1));
+        assert_eq!(satisfaction.mean_residency(), Duration::from_secs(3));
+        let score = satisfaction.score().unwrap();
+        assert!((score - 2.0 / 3.0 * 0.5 * 0.25).abs() < 1e-9, "{score}");
+    }
+
+    /// **Scenario:** An explorer requests at 0s, then 1s apart nine times, then 10s apart
+    /// **Validates:**
+    /// - No percentile before the second request
+    /// - The median is the common interval, the 95th percentile the outlier
+    /// - Only the most recent intervals are kept
+    #[test]
+    fn test_inter_arrival_percentiles() {
+        let mut stats = Stats::new(small_config());
+        stats.record_arrival(ExplorerId::new(1), at(0));
+        assert_eq!(stats.inter_arrivals()[&ExplorerId::new(1)].p50(), None);
+
+        for secs in 1..10 {
+            stats.record_arrival(ExplorerId::new(1), at(secs));
+        }
+        stats.record_arrival(ExplorerId::new(1), at(19));
+        let arrivals = &stats.inter_arrivals()[&ExplorerId::new(1)];
+        assert_eq!(arrivals.p50(), Some(Duration::from_secs(1)));
+        assert_eq!(arrivals.p95(), Some(Duration::from_secs(10)));
+
+        for secs in 0..INTER_ARRIVAL_SAMPLES as u64 {
+            stats.record_arrival(ExplorerId::new(1), at(100 + secs * 2));
+        }
+        let arrivals = &stats.inter_arrivals()[&ExplorerId::new(1)];
+        assert_eq!(arrivals.recent().count(), INTER_ARRIVAL_SAMPLES);
+        assert_eq!(arrivals.p95(), Some(Duration::from_secs(2)));
+    }
+
+    /// **Scenario:** Explorer 2 requests Silicon 10 times at 0s, explorer 1 requests
+    /// Oxygen 3 times and Carbon once at 100s
+    /// **Validates:**
+    /// - Affinities are the request frequencies of each explorer
+    /// - Only the explorers active in the window make the demand, each counting once
+    #[test]
+    fn test_affinities_and_active_demand() {
+        let mut stats = Stats::new(small_config());
+        for _ in 0..10 {
+            stats.record_affinity(ExplorerId::new(2), BasicResourceType::Silicon);
+            stats.record_arrival(ExplorerId::new(2), at(0));
+        }
+        for resource in [
+            BasicResourceType::Oxygen,
+            BasicResourceType::Carbon,
+            BasicResourceType::Oxygen,
+            BasicResourceType::Oxygen,
+        ] {
+            stats.record_affinity(ExplorerId::new(1), resource);
+            stats.record_arrival(ExplorerId::new(1), at(100));
+        }
+
+        let frequencies = stats.affinities()[&ExplorerId::new(1)].frequencies();
+        assert_eq!(frequencies[&BasicResourceType::Oxygen], 0.75);
+        assert_eq!(frequencies[&BasicResourceType::Carbon], 0.25);
+
+        let window = Duration::from_secs(10);
+        assert_eq!(
+            stats.wanted_resource(at(105), window),
+            Some(BasicResourceType::Oxygen)
+        );
+        let demand = stats.active_demand(at(105), Duration::from_secs(200));
+        assert_eq!(demand[&BasicResourceType::Silicon], 0.5);
+        assert_eq!(demand[&BasicResourceType::Oxygen], 0.375);
+        assert_eq!(stats.wanted_resource(at(500), window), None);
+    }
+
+    /// **Scenario:** Heatmap of 3 buckets of 10 seconds; Oxygen requested at 0s and
+    /// 5s, Silicon at 15s, Carbon at 25s and at 100s
+    /// **Validates:**
+    /// - Requests are counted in the bucket of their game time, the last bucket also
+    ///   counting the later ones
+    /// - The hottest resource of each bucket, none without requests
+    /// - The CSV rendering
+    #[test]
+    fn test_demand_heatmap() {
+        let mut stats = Stats::new(small_config());
+        stats.record_demand(Duration::ZERO, BasicResourceType::Oxygen);
+        assert_eq!(stats.demand_heatmap(), None, "Heatmap disabled");
+
+        stats.enable_demand_heatmap(Duration::from_secs(10), 3);
+        for (secs, resource) in [
+            (0, BasicResourceType::Oxygen),
+            (5, BasicResourceType::Oxygen),
+            (15, BasicResourceType::Silicon),
+            (25, BasicResourceType::Carbon),
+            (100, BasicResourceType::Carbon),
+        ] {
+            stats.record_demand(Duration::from_secs(secs), resource);
+        }
+
+        let heatmap = stats.demand_heatmap().unwrap();
+        let secs = Duration::from_secs;
+        assert_eq!(heatmap.requests(secs(9), BasicResourceType::Oxygen), 2);
+        assert_eq!(heatmap.requests(secs(20), BasicResourceType::Carbon), 2);
+        assert_eq!(heatmap.hottest(secs(12)), Some(BasicResourceType::Silicon));
+        assert_eq!(
+            heatmap.to_csv(),
+            "start_secs,Oxygen,Hydrogen,Carbon,Silicon\n\
+             0,2,0,0,0\n\
+             10,0,0,0,1\n\
+             20,0,0,2,0\n"
+        );
+
+        stats.enable_demand_heatmap(Duration::from_secs(10), 3);
+        assert_eq!(stats.demand_heatmap().unwrap().hottest(secs(0)), None);
+    }
+
+    // ============================================================================
+    // Tests: Registration
+    // ============================================================================
+
+    /// **Scenario:** Explorers 5 and 6 send requests before being registered, then only
+    /// explorer 5 is registered
+    /// **Validates:**
+    /// - The requests of explorer 5 move from the spoof attempts to the early requests
+    /// - Explorer 6 is still counted as a spoof attempt
+    #[test]
+    fn test_registration_reconciles_early_requests() {
+        let mut stats = Stats::new(small_config());
+        for explorer_id in [5, 5, 6] {
+            stats.record_spoof_attempt(ExplorerId::new(explorer_id));
+        }
+
+        stats.reconcile_registration(ExplorerId::new(5));
+        assert_eq!(
+            stats.early_requests(),
+            &BTreeMap::from([(ExplorerId::new(5), 2)])
+        );
+        assert_eq!(
+            stats.spoof_attempts(),
+            &BTreeMap::from([(ExplorerId::new(6), 1)])
+        );
+    }
+
+    // ============================================================================
+    // Tests: Leaderboard
+    // ============================================================================
+
+    /// **Scenario:** Explorer 1 receives 3 resources and is denied twice, explorer 2
+    /// receives 1 and is never denied, explorer 3 is only denied, once
+    /// **Validates:**
+    /// - Each ranking is ordered, ties going to the lowest ID, and truncated to the size
+    /// - Explorers without any count are left out of the rankings
+    #[test]
+    fn test_leaderboard_rankings() {
+        let mut stats = Stats::new(small_config());
+        for _ in 0..3 {
+            stats.record_received(ExplorerId::new(1), BasicResourceType::Oxygen);
+        }
+        stats.record_received(ExplorerId::new(2), BasicResourceType::Carbon);
+        for explorer_id in [1, 1, 3] {
+            stats.record_explorer_denial(ExplorerId::new(explorer_id), DenialReason::NoEnergy);
+        }
+
+        let leaderboard = stats.leaderboard(2);
+        assert_eq!(
+            leaderboard.top_consumers,
+            vec![(ExplorerId::new(1), 3), (ExplorerId::new(2), 1)]
+        );
+        assert_eq!(
+            leaderboard.most_denied,
+            vec![(ExplorerId::new(1), 2), (ExplorerId::new(3), 1)]
+        );
+        let ratios: Vec<_> = leaderboard
+            .best_grant_ratio
+            .iter()
+            .map(|ratio| (ratio.explorer_id, ratio.ratio()))
+            .collect();
+        assert_eq!(
+            ratios,
+            vec![(ExplorerId::new(2), 1.0), (ExplorerId::new(1), 0.6)]
+        );
+    }
+
+    /// **Scenario:** Serialize the statistics after explorer 2 was denied Silicon
+    /// **Validates:**
+    /// - Denial reasons and resource types are serialized by name
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_stats_serialize_as_json() {
+        let mut stats = Stats::new(small_config());
+        stats.record_affinity(ExplorerId::new(2), BasicResourceType::Silicon);
+        stats.record_denial(at(0), DenialReason::NoEnergy);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        for expected in [
+            "\"totals\":{\"grants\":0,\"denials\":1,\"sunrays\":0}",
+            "\"denials_by_reason\":{\"NoEnergy\":1}",
+            "\"affinities\":{\"2\":{\"requests\":{\"Silicon\":1}}}",
+            "\"limit_mode\":{\"Limit\":\"None\"}",
+        ] {
+            assert!(json.contains(expected), "{expected} missing from {json}");
+        }
+    }
+
+    // ============================================================================
+    // Tests: Handle
+    // ============================================================================
+
+    /// **Scenario:** A shared snapshot is held while the planet records a grant, then
+    /// dropped before the next grant
+    /// **Validates:**
+    /// - The snapshot keeps the statistics as of when it was taken
+    /// - The handle sees every update, copying the statistics only while shared
+    #[test]
+    fn test_snapshots_are_copy_on_write() {
+        let handle = StatsHandle::new(small_config());
+        let shared = handle.shared();
+        handle.update(|stats| stats.record_grant(at(0)));
+        assert_eq!(shared.totals().grants, 0);
+        assert_eq!(handle.snapshot().totals().grants, 1);
+
+        drop(shared);
+        let before = Arc::as_ptr(&handle.shared());
+        handle.update(|stats| stats.record_grant(at(1)));
+        assert_eq!(Arc::as_ptr(&handle.shared()), before, "Updated in place");
+        assert_eq!(handle.snapshot().totals().grants, 2);
+    }
+}
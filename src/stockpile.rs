@@ -0,0 +1,43 @@
+//! Stockpile module.
+//!
+//! Planets can start pre-stocked (see
+//! [`PlanetConfig::with_stockpile`](crate::PlanetConfig::with_stockpile)): each seeded
+//! unit is a charged cell, served to the first grant discharging it. Until then, the
+//! cells are speculation rather than energy the sunrays brought, and whether the answers
+//! about the available energy count them depends on the house rules. It's set with
+//! [`PlanetConfig::with_stockpile_visibility`](crate::PlanetConfig::with_stockpile_visibility),
+//! for the two audiences of these answers:
+//! - explorers, in the responses to their `AvailableEnergyCellRequest`s
+//! - monitoring, in the state published to the statistics (see
+//!   [`Stats::extended_state`](crate::stats::Stats::extended_state)) and the telemetry
+//!   gauges
+//!
+//! The orchestrator runs the game, so its `InternalStateResponse` always counts every
+//! charged cell, and the seeding itself is recorded either way (see
+//! [`Stats::stockpile`](crate::stats::Stats::stockpile)). Limit policies decide on the
+//! real charged cells.
+
+/// Which answers about the available energy count the seeded cells not served yet, see
+/// the [module documentation](self).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StockpileVisibility {
+    /// Counted in every answer.
+    #[default]
+    Visible,
+    /// Counted only in the state published to monitoring.
+    MonitoringOnly,
+    /// Counted in no answer.
+    Hidden,
+}
+
+impl StockpileVisibility {
+    /// Whether the answers to explorers count the stockpile.
+    pub(crate) fn to_explorers(self) -> bool {
+        self == StockpileVisibility::Visible
+    }
+
+    /// Whether the state published to monitoring counts the stockpile.
+    pub(crate) fn to_monitoring(self) -> bool {
+        self != StockpileVisibility::Hidden
+    }
+}
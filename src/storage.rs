@@ -0,0 +1,384 @@
+//! Storage module.
+//!
+//! What a planet persists goes through a [`StorageBackend`], set with
+//! [`PlanetConfig::with_storage`](crate::PlanetConfig::with_storage), under a few
+//! well-known keys:
+//! - [`JOURNAL`]: the [journal](crate::journal) of the planet, appended to while it
+//!   runs
+//! - [`CELL_TIMELINE`]: the JSON export of the [cell timeline](crate::timeline), the
+//!   provenance of the energy spent, if recorded, written when the planet stops or is
+//!   destroyed by an asteroid
+//! - [`STATS`]: a JSON snapshot of the [statistics](crate::stats::Stats), written when
+//!   the planet stops or is destroyed, with the `serde` feature, and read back with
+//!   [`StatsSnapshot::load`]
+//!
+//! The crate provides a [`FileStorage`], keeping each key as a file of a directory, and
+//! a [`MemoryStorage`] for tests. Hosts persisting to object storage or a database
+//! implement the trait over their own client, without the crate depending on it:
+//! ```
+//! use rustrelli::storage::{JOURNAL, MemoryStorage, StorageBackend};
+//!
+//! let mut storage = MemoryStorage::default();
+//! let host_view = storage.clone();
+//! storage.put(JOURNAL, b"rustrelli-journal 1 cells=5 charged=0\n")?;
+//! assert!(host_view.get(JOURNAL)?.is_some());
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Failures are reported as an [`Event::StorageFailed`](crate::events::Event::StorageFailed),
+//! except for the journal, which reports them like any journal write.
+//!
+//! Like the journal, the statistics snapshot records the version of its format and of
+//! the crate that wrote it, in an envelope around the statistics:
+//! ```text
+//! {"format":"rustrelli-stats","version":2,"crate":"0.1.0","stats":{...}}
+//! ```
+//! Snapshots written by older versions of the crate are migrated when read. Version 1
+//! was the bare statistics, without envelope.
+
+#[cfg(feature = "serde")]
+use crate::RustrelliError;
+#[cfg(feature = "serde")]
+use crate::stats::Stats;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Key of the journal.
+pub const JOURNAL: &str = "journal";
+/// Key of the JSON export of the cell timeline.
+pub const CELL_TIMELINE: &str = "cell-timeline.json";
+/// Key of the JSON snapshot of the statistics.
+pub const STATS: &str = "stats.json";
+
+/// `format` of the envelope of the statistics snapshot.
+#[cfg(feature = "serde")]
+const STATS_FORMAT: &str = "rustrelli-stats";
+/// Version of the statistics snapshot format, bumped on any change to it, with a
+/// migration from the previous version in [`StatsSnapshot::read`].
+#[cfg(feature = "serde")]
+const STATS_VERSION: u32 = 2;
+
+/// Where the planet persists its journal and snapshots, see the
+/// [module documentation](self).
+///
+/// Keys are plain names, without path separators.
+pub trait StorageBackend: Send {
+    /// Opens the stream stored under `key` for appending, creating it if missing.
+    fn append(&mut self, key: &str) -> io::Result<Box<dyn Write + Send>>;
+
+    /// Stores `data` under `key`, replacing what was stored there.
+    fn put(&mut self, key: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Reads what's stored under `key`, `None` if nothing is.
+    fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// A statistics snapshot read back with [`StatsSnapshot::read`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsSnapshot {
+    /// Version of the format the snapshot was written in, before its migration.
+    pub version: u32,
+    /// Version of the crate that wrote the snapshot, if known.
+    pub written_by: Option<String>,
+    /// The statistics, as serialized by the crate that wrote them.
+    pub stats: serde_json::Value,
+}
+
+#[cfg(feature = "serde")]
+impl StatsSnapshot {
+    /// Serializes `stats` in the envelope of the current version.
+    pub(crate) fn write(stats: &Stats) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(&serde_json::json!({
+            "format": STATS_FORMAT,
+            "version": STATS_VERSION,
+            "crate": env!("CARGO_PKG_VERSION"),
+            "stats": stats,
+        }))
+    }
+
+    /// Reads a statistics snapshot stored by a planet, migrating it from older formats.
+    ///
+    /// # Errors
+    /// Returns [`RustrelliError::Snapshot`] if it isn't a statistics snapshot of a
+    /// supported version.
+    pub fn read(data: &[u8]) -> Result<Self, RustrelliError> {
+        let invalid = |problem: &str| RustrelliError::Snapshot(format!("{problem} in {STATS}"));
+        let value: serde_json::Value = serde_json::from_slice(data)
+            .map_err(|error| RustrelliError::Snapshot(error.to_string()))?;
+        let serde_json::Value::Object(mut envelope) = value else {
+            return Err(invalid("No statistics"));
+        };
+        // Version 1 wrote the bare statistics.
+        if !envelope.contains_key("format") {
+            return Ok(StatsSnapshot {
+                version: 1,
+                written_by: None,
+                stats: serde_json::Value::Object(envelope),
+            });
+        }
+        if envelope["format"] != STATS_FORMAT {
+            return Err(invalid("Unknown format"));
+        }
+        let version = envelope
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| invalid("Invalid version"))?;
+        if version > STATS_VERSION {
+            return Err(RustrelliError::Snapshot(format!(
+                "Statistics snapshot version {version} is newer than the supported version \
+                 {STATS_VERSION}"
+            )));
+        }
+        let written_by = envelope
+            .get("crate")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| invalid("Invalid crate version"))?
+            .to_string();
+        let stats = envelope
+            .remove("stats")
+            .filter(serde_json::Value::is_object)
+            .ok_or_else(|| invalid("No statistics"))?;
+        Ok(StatsSnapshot {
+            version,
+            written_by: Some(written_by),
+            stats,
+        })
+    }
+
+    /// Reads the statistics snapshot stored in `storage`, `None` if there is none.
+    ///
+    /// # Errors
+    /// Returns [`RustrelliError::Snapshot`] if it can't be read, or isn't a statistics
+    /// snapshot of a supported version.
+    pub fn load(storage: &dyn StorageBackend) -> Result<Option<Self>, RustrelliError> {
+        storage
+            .get(STATS)
+            .map_err(|error| RustrelliError::Snapshot(error.to_string()))?
+            .map(|data| Self::read(&data))
+            .transpose()
+    }
+}
+
+/// Storage keeping each key as a file of a directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    /// Creates a storage in `dir`, created on the first write if missing.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FileStorage { dir: dir.into() }
+    }
+
+    /// The directory of the storage.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl StorageBackend for FileStorage {
+    /// Appends to the file through a [`BufWriter`], flushed with the journal.
+    fn append(&mut self, key: &str) -> io::Result<Box<dyn Write + Send>> {
+        fs::create_dir_all(&self.dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(key))?;
+        Ok(Box::new(BufWriter::new(file)))
+    }
+
+    /// Writes a temporary file, then renames it over the file of `key`, so that readers
+    /// never see a partial write.
+    fn put(&mut self, key: &str, data: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let partial = self.dir.join(format!(".{key}.partial"));
+        let mut file = File::create(&partial)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(partial, self.dir.join(key))
+    }
+
+    fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(key)) {
+            Ok(data) => Ok(Some(data)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Stored data, shared by the clones of a [`MemoryStorage`].
+type Objects = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+/// Storage keeping the keys in memory. Clones share the same data, so a test keeps a
+/// clone to read what the planet stored.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    objects: Objects,
+}
+
+impl MemoryStorage {
+    /// The keys stored, in order.
+    pub fn keys(&self) -> Vec<String> {
+        lock(&self.objects).keys().cloned().collect()
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn append(&mut self, key: &str) -> io::Result<Box<dyn Write + Send>> {
+        lock(&self.objects).entry(key.to_string()).or_default();
+        Ok(Box::new(MemoryAppender {
+            objects: self.objects.clone(),
+            key: key.to_string(),
+        }))
+    }
+
+    fn put(&mut self, key: &str, data: &[u8]) -> io::Result<()> {
+        lock(&self.objects).insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(lock(&self.objects).get(key).cloned())
+    }
+}
+
+/// Stream appending to a key of a [`MemoryStorage`].
+struct MemoryAppender {
+    objects: Objects,
+    key: String,
+}
+
+impl Write for MemoryAppender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        lock(&self.objects)
+            .entry(self.key.clone())
+            .or_default()
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Locks the stored data, even if a thread panicked while holding it.
+fn lock(objects: &Objects) -> MutexGuard<'_, BTreeMap<String, Vec<u8>>> {
+    objects
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the storage backends.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Backends
+    // ============================================================================
+
+    /// **Scenario:** A stream appended to twice, and a key put twice, in a directory
+    /// that doesn't exist yet
+    /// **Validates:**
+    /// - The directory is created, and appends add to the existing file
+    /// - Puts replace the file, leaving no temporary file behind
+    /// - Missing keys read as `None`
+    #[test]
+    fn test_file_storage() {
+        let dir = std::env::temp_dir().join(format!("rustrelli-storage-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut storage = FileStorage::new(dir.join("planet"));
+        for line in ["first\n", "second\n"] {
+            let mut stream = storage.append(JOURNAL).unwrap();
+            stream.write_all(line.as_bytes()).unwrap();
+            stream.flush().unwrap();
+        }
+        storage.put(STATS, b"{}").unwrap();
+        storage.put(STATS, b"{\"epoch\":1}").unwrap();
+
+        assert_eq!(
+            storage.get(JOURNAL).unwrap().as_deref(),
+            Some(&b"first\nsecond\n"[..])
+        );
+        assert_eq!(
+            storage.get(STATS).unwrap().as_deref(),
+            Some(&b"{\"epoch\":1}"[..])
+        );
+        assert_eq!(storage.get(CELL_TIMELINE).unwrap(), None);
+        assert_eq!(fs::read_dir(storage.dir()).unwrap().count(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// **Scenario:** A stream appended to through a clone of the storage
+    /// **Validates:** The writes are read back from the other clone
+    #[test]
+    fn test_memory_storage_is_shared() {
+        let storage = MemoryStorage::default();
+        let mut stream = storage.clone().append(JOURNAL).unwrap();
+        stream.write_all(b"sunray").unwrap();
+
+        assert_eq!(storage.keys(), [JOURNAL]);
+        assert_eq!(
+            storage.get(JOURNAL).unwrap().as_deref(),
+            Some(&b"sunray"[..])
+        );
+    }
+
+    // ============================================================================
+    // Tests: Statistics Snapshot
+    // ============================================================================
+
+    /// **Scenario:** Read statistics snapshots of version 1, of the current version, of
+    /// a future version, and malformed ones
+    /// **Validates:**
+    /// - Bare statistics are migrated as version 1, without the crate version
+    /// - The current envelope is read back with what it wraps
+    /// - Newer versions, unknown formats and envelopes without statistics are rejected
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_stats_snapshot_migrates_older_versions() {
+        let bare = StatsSnapshot::read(br#"{"totals":{"grants":2}}"#).unwrap();
+        assert_eq!(bare.version, 1);
+        assert_eq!(bare.written_by, None);
+        assert_eq!(bare.stats["totals"]["grants"], 2);
+
+        let stats = Stats::new(Default::default());
+        let current = StatsSnapshot::read(&StatsSnapshot::write(&stats).unwrap()).unwrap();
+        assert_eq!(current.version, STATS_VERSION);
+        assert_eq!(
+            current.written_by.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(current.stats, serde_json::to_value(&stats).unwrap());
+
+        for (data, error) in [
+            (
+                &br#"{"format":"rustrelli-stats","version":3,"crate":"9.0.0","stats":{}}"#[..],
+                "Statistics snapshot version 3 is newer than the supported version 2",
+            ),
+            (
+                br#"{"format":"journal","version":2,"crate":"0.1.0","stats":{}}"#,
+                "Unknown format in stats.json",
+            ),
+            (
+                br#"{"format":"rustrelli-stats","version":2,"crate":"0.1.0"}"#,
+                "No statistics in stats.json",
+            ),
+            (b"[1, 2]", "No statistics in stats.json"),
+        ] {
+            assert_eq!(
+                StatsSnapshot::read(data),
+                Err(RustrelliError::Snapshot(error.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Synthetic sunray schedules.
+//!
+//! Fairness experiments only mean something against a realistic energy supply. The
+//! schedules below are iterators of the intervals between two sunrays, consumed the
+//! same way by tests, soak runs and simulators:
+//! - [`FixedInterval`]: a sunray every interval, the steadiest supply
+//! - [`Poisson`]: sunrays arriving independently at a mean rate
+//! - [`Bursty`]: sunrays in bursts, separated by dark periods
+//!
+//! Random schedules are seeded, so an experiment is replayed with the same supply.
+//!
+//! # Examples
+//! ```
+//! use rustrelli::sunrays::{Bursty, FixedInterval, Poisson};
+//! use std::time::Duration;
+//!
+//! let fixed: Vec<_> = FixedInterval::new(Duration::from_millis(100)).take(3).collect();
+//! assert_eq!(fixed, vec![Duration::from_millis(100); 3]);
+//!
+//! // On average a sunray every 100ms
+//! let poisson = Poisson::new(Duration::from_millis(100), 7);
+//!
+//! // Three sunrays 10ms apart, then a second without
+//! let bursty = Bursty::new(3, Duration::from_millis(10), Duration::from_secs(1));
+//! let intervals: Vec<_> = bursty.take(3).collect();
+//! assert_eq!(intervals[1], Duration::from_millis(10));
+//! assert_eq!(intervals[2], Duration::from_secs(1));
+//! ```
+
+use crate::rng::SplitMix64;
+use std::time::Duration;
+
+/// A sunray every `interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedInterval {
+    interval: Duration,
+}
+
+impl FixedInterval {
+    pub fn new(interval: Duration) -> Self {
+        FixedInterval { interval }
+    }
+}
+
+impl Iterator for FixedInterval {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        Some(self.interval)
+    }
+}
+
+/// Sunrays of a Poisson process: intervals are drawn from an exponential distribution
+/// of mean `mean_interval`.
+#[derive(Debug, Clone)]
+pub struct Poisson {
+    mean_interval: Duration,
+    rng: SplitMix64,
+}
+
+impl Poisson {
+    /// Creates the schedule, drawing from `seed`.
+    pub fn new(mean_interval: Duration, seed: u64) -> Self {
+        Poisson {
+            mean_interval,
+            rng: SplitMix64::new(seed),
+        }
+    }
+}
+
+impl Iterator for Poisson {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        // Inverse of the cumulative distribution, 1 - u being in (0, 1]
+        let draw = -(1.0 - self.rng.next_f64()).ln();
+        Some(self.mean_interval.mul_f64(draw))
+    }
+}
+
+/// Bursts of `burst` sunrays `interval` apart, each followed by `dark` without sunray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bursty {
+    burst: u32,
+    interval: Duration,
+    dark: Duration,
+    /// Sunrays of the current burst already scheduled.
+    sent: u32,
+}
+
+impl Bursty {
+    /// Creates the schedule, starting with a burst.
+    ///
+    /// # Panics
+    /// Panics if `burst` is zero.
+    pub fn new(burst: u32, interval: Duration, dark: Duration) -> Self {
+        assert!(burst > 0, "A burst needs at least one sunray");
+        Bursty {
+            burst,
+            interval,
+            dark,
+            sent: 0,
+        }
+    }
+}
+
+impl Iterator for Bursty {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        self.sent += 1;
+        if self.sent < self.burst {
+            return Some(self.interval);
+        }
+        self.sent = 0;
+        Some(self.dark)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the sunray schedules.
+
+    use super::*;
+
+    // ============================================================================
+    // Tests: Schedules
+    // ============================================================================
+
+    /// **Scenario:** Draw 10k intervals of a Poisson schedule, twice with the same seed
+    /// **Validates:**
+    /// - The mean interval is close to the configured one
+    /// - Intervals vary, and the same seed replays them
+    #[test]
+    fn test_poisson_mean_and_replay() {
+        let mean = Duration::from_millis(100);
+        let intervals: Vec<_> = Poisson::new(mean, 11).take(10_000).collect();
+
+        let average = intervals.iter().sum::<Duration>() / 10_000;
+        assert!(
+            average > Duration::from_millis(95) && average < Duration::from_millis(105),
+            "Average interval {:?}",
+            average
+        );
+        assert!(intervals.iter().any(|interval| *interval < mean / 10));
+        assert!(intervals.iter().any(|interval| *interval > mean * 3));
+        assert_eq!(
+            intervals[..100],
+            Poisson::new(mean, 11).take(100).collect::<Vec<_>>()
+        );
+    }
+
+    /// **Scenario:** Two cycles of a bursty schedule
+    /// **Validates:** Each burst's sunrays are `interval` apart, then the dark period
+    /// separates the last one from the next burst
+    #[test]
+    fn test_bursty_cycles() {
+        let (interval, dark) = (Duration::from_millis(10), Duration::from_secs(1));
+        let intervals: Vec<_> = Bursty::new(3, interval, dark).take(6).collect();
+        assert_eq!(
+            intervals,
+            vec![interval, interval, dark, interval, interval, dark]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Energy supply module.
+//!
+//! The planet AI estimates the arrival rate of the sunrays, each charging a cell, so
+//! that the limit policies and the host can anticipate the supply of energy instead of
+//! each deriving it from the sunrays they see. The estimate is a [`SunrayRate`],
+//! updated after each sunray, handed to every policy and recorded in the statistics
+//! (see [`Stats::sunray_rate`](crate::stats::Stats::sunray_rate)):
+//! ```
+//! use std::time::Duration;
+//! use rustrelli::stats::StatsHandle;
+//!
+//! let stats = StatsHandle::default();
+//! // ... planet configured with the handle, running for a while ...
+//! let rate = stats.snapshot().sunray_rate();
+//! let expected_cells = rate.forecast(Duration::from_secs(60));
+//! ```
+//!
+//! The time between two sunrays is smoothed with an exponentially weighted moving
+//! average, and its variance along with it. Gaps much longer than the current average,
+//! like a planet left idle, are clamped so that a single one doesn't wipe the estimate.
+
+use std::time::{Duration, SystemTime};
+
+/// Weight of the latest interval in the moving averages.
+const SMOOTHING: f64 = 0.2;
+/// Intervals longer than this many times the average are clamped to it.
+const OUTLIER_FACTOR: f64 = 10.0;
+
+/// Estimated arrival rate of the sunrays.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SunrayRate {
+    /// Sunrays received so far.
+    pub count: u64,
+    /// Smoothed time between two sunrays, `None` before the second sunray.
+    pub mean_interval: Option<Duration>,
+    /// Smoothed variance of the time between two sunrays, in squared seconds.
+    pub interval_variance: f64,
+}
+
+impl SunrayRate {
+    /// Expected sunrays per second, zero before the second sunray.
+    pub fn per_second(&self) -> f64 {
+        match self.mean_interval {
+            Some(mean) if !mean.is_zero() => 1.0 / mean.as_secs_f64(),
+            _ => 0.0,
+        }
+    }
+
+    /// Standard deviation of the time between two sunrays.
+    pub fn interval_std_dev(&self) -> Duration {
+        Duration::from_secs_f64(self.interval_variance.max(0.0).sqrt())
+    }
+
+    /// Sunrays expected over the next `window`, at the estimated rate.
+    pub fn forecast(&self, window: Duration) -> f64 {
+        self.per_second() * window.as_secs_f64()
+    }
+}
+
+/// Maintains the [`SunrayRate`] from the arrival times of the sunrays.
+#[derive(Debug, Default)]
+pub(crate) struct SunrayEstimator {
+    rate: SunrayRate,
+    last: Option<SystemTime>,
+}
+
+impl SunrayEstimator {
+    pub(crate) fn rate(&self) -> SunrayRate {
+        self.rate
+    }
+
+    /// Accounts a sunray arrived at `now`, returning the updated estimate.
+    pub(crate) fn record(&mut self, now: SystemTime) -> SunrayRate {
+        self.rate.count += 1;
+        let Some(last) = self.last.replace(now) else {
+            return self.rate;
+        };
+        let interval = now.duration_since(last).unwrap_or_default().as_secs_f64();
+        let (mean, variance) = match self.rate.mean_interval {
+            None => (interval, 0.0),
+            Some(mean) => {
+                let mean = mean.as_secs_f64();
+                let interval = if mean > 0.0 {
+                    interval.min(mean * OUTLIER_FACTOR)
+                } else {
+                    interval
+                };
+                let diff = interval - mean;
+                let step = SMOOTHING * diff;
+                (
+                    mean + step,
+                    (1.0 - SMOOTHING) * (self.rate.interval_variance + diff * step),
+                )
+            }
+        };
+        self.rate.mean_interval = Some(Duration::from_secs_f64(mean));
+        self.rate.interval_variance = variance;
+        self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the sunray rate estimation.
+
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    // ============================================================================
+    // Tests: Estimation
+    // ============================================================================
+
+    /// **Scenario:** A sunray every 2 seconds
+    /// **Validates:** The rate is 0.5 per second without variance, and forecasts 5
+    /// sunrays over 10 seconds
+    #[test]
+    fn test_regular_sunrays() {
+        let mut estimator = SunrayEstimator::default();
+        assert_eq!(estimator.record(at(0)).per_second(), 0.0);
+        for secs in (2..=20).step_by(2) {
+            estimator.record(at(secs));
+        }
+
+        let rate = estimator.rate();
+        assert_eq!(rate.count, 11);
+        assert_eq!(rate.mean_interval, Some(Duration::from_secs(2)));
+        assert_eq!(rate.interval_variance, 0.0);
+        assert!((rate.forecast(Duration::from_secs(10)) - 5.0).abs() < 1e-9);
+    }
+
+    /// **Scenario:** A sunray every second, then one after an hour of silence
+    /// **Validates:** The gap is clamped to 10 times the average interval
+    #[test]
+    fn test_long_gap_is_clamped() {
+        let mut estimator = SunrayEstimator::default();
+        for secs in 0..=5 {
+            estimator.record(at(secs));
+        }
+        let rate = estimator.record(at(3605));
+
+        assert!((rate.mean_interval.unwrap().as_secs_f64() - 2.8).abs() < 1e-6);
+        assert!(rate.interval_std_dev() > Duration::ZERO);
+    }
+}
@@ -0,0 +1,108 @@
+//! Explorer tagging module.
+//!
+//! Hosts can attach free-form [`Tag`]s to explorers (team, tier, bot or human...),
+//! and write policies keying on them:
+//! - [`Policy::for_tag`](crate::policy::Policy::for_tag) limits only the explorers
+//!   carrying a tag
+//! - [`Policy::pooled`](crate::policy::Policy::pooled) makes the explorers with the same
+//!   tag value share a quota, e.g. every bot of `team=red`
+//! - Tag weights scale the share of energy [`FairShare`](crate::ExplorerRequestLimit::FairShare)
+//!   grants to the explorers carrying a tag (e.g. `tier=gold` gets 2× weight)
+
+use crate::ExplorerId;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+/// A `key=value` label attached to explorers.
+///
+/// # Examples
+/// ```
+/// use rustrelli::tags::Tag;
+///
+/// let tag = Tag::new("tier", "gold");
+/// assert_eq!(tag.to_string(), "tier=gold");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Tag {
+    /// What the tag describes, e.g. `team`.
+    pub key: String,
+    /// The value for the explorer, e.g. `red`.
+    pub value: String,
+}
+
+impl Tag {
+    /// Creates the tag `key=value`.
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Tag {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+/// Tags attached to each explorer, and the weight given to each tag.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct TagRegistry {
+    /// Shared with the requests in flight, so that handing them out doesn't allocate.
+    tags: HashMap<ExplorerId, Arc<BTreeSet<Tag>>>,
+    weights: BTreeMap<Tag, f32>,
+    /// Tags of the explorers without any tag.
+    untagged: Arc<BTreeSet<Tag>>,
+}
+
+impl TagRegistry {
+    /// Attaches `tag` to `explorer_id`.
+    pub(crate) fn tag(&mut self, explorer_id: ExplorerId, tag: Tag) {
+        let tags = self.tags.entry(explorer_id).or_default();
+        Arc::make_mut(tags).insert(tag);
+    }
+
+    /// Detaches `tag` from `explorer_id`.
+    pub(crate) fn untag(&mut self, explorer_id: ExplorerId, tag: &Tag) {
+        if let Some(tags) = self.tags.get_mut(&explorer_id) {
+            Arc::make_mut(tags).remove(tag);
+            if tags.is_empty() {
+                self.tags.remove(&explorer_id);
+            }
+        }
+    }
+
+    /// Returns the tags attached to `explorer_id`.
+    pub(crate) fn tags_of(&self, explorer_id: ExplorerId) -> Arc<BTreeSet<Tag>> {
+        self.tags
+            .get(&explorer_id)
+            .unwrap_or(&self.untagged)
+            .clone()
+    }
+
+    /// Sets the weight of the explorers carrying `tag`.
+    ///
+    /// # Panics
+    /// Panics if `weight` isn't a positive finite number.
+    pub(crate) fn set_weight(&mut self, tag: Tag, weight: f32) {
+        assert!(
+            weight.is_finite() && weight > 0.0,
+            "Tag weight must be a positive finite number, got {}",
+            weight
+        );
+        self.weights.insert(tag, weight);
+    }
+
+    /// Returns the weight of `explorer_id`: the product of the weights of its tags,
+    /// `1.0` if none of them has a weight.
+    pub(crate) fn weight_of(&self, explorer_id: ExplorerId) -> f32 {
+        self.tags.get(&explorer_id).map_or(1.0, |tags| {
+            tags.iter()
+                .filter_map(|tag| self.weights.get(tag))
+                .product()
+        })
+    }
+}
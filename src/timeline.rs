@@ -0,0 +1,303 @@
+//! Energy cell timeline module.
+//!
+//! When enabled with
+//! [`PlanetConfig::with_cell_timeline`](crate::PlanetConfig::with_cell_timeline), the
+//! planet AI records every charge and discharge of its energy cells in a bounded
+//! [`CellTimeline`] read through
+//! [`Stats::cell_timeline`](crate::stats::Stats::cell_timeline). Each charge is tagged
+//! with the arrival time of its sunray, and each discharge with its [`Provenance`]: the
+//! explorer the cell went to, the resource it produced, and whether it was drawn on a
+//! request, a batch, an offer or the lease. Visualizers render it from its JSON export:
+//! ```json
+//! {"events":[
+//!   {"at_ms":1700000000000,"cell":0,"event":"charged"},
+//!   {"at_ms":1700000000250,"cell":0,"event":"discharged","explorer_id":3,
+//!    "resource":"Oxygen","source":"request"}
+//! ]}
+//! ```
+//!
+//! Gantt charts of who benefited from which sunray draw the charged intervals of the
+//! cells instead ([`CellTimeline::intervals`]), exported as JSON or CSV:
+//! ```text
+//! cell,charged_ms,discharged_ms,explorer_id,resource,source
+//! 0,1700000000000,1700000000250,3,Oxygen,request
+//! 1,1700000000100,,,,
+//! ```
+//!
+//! The provenance is recorded once, by the planet AI as it discharges the cell, and the
+//! timeline, its exports and the [Chrome trace](crate::trace) all read it from there.
+
+use crate::ExplorerId;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Resource produced from an energy cell: requested by an explorer, or discharged to
+/// produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Product {
+    /// A generated basic resource.
+    Basic(
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::name"))]
+        BasicResourceType,
+    ),
+    /// A combined complex resource.
+    Complex(
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::name"))]
+        ComplexResourceType,
+    ),
+}
+
+impl From<BasicResourceType> for Product {
+    fn from(resource: BasicResourceType) -> Self {
+        Product::Basic(resource)
+    }
+}
+
+impl From<ComplexResourceType> for Product {
+    fn from(resource: ComplexResourceType) -> Self {
+        Product::Complex(resource)
+    }
+}
+
+impl fmt::Display for Product {
+    /// Formats the name of the resource type, like the journal does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Product::Basic(resource) => write!(f, "{resource:?}"),
+            Product::Complex(resource) => write!(f, "{resource:?}"),
+        }
+    }
+}
+
+/// What entitled the explorer to the energy cell discharged for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Source {
+    /// A request granted on its own, or a combination.
+    Request,
+    /// The rest of a batch granted to the explorer (see [`crate::batch`]).
+    Batch,
+    /// A confirmed offer (see [`crate::claim`]).
+    Offer,
+    /// The lease of the planet (see [`crate::lease`]).
+    Lease,
+}
+
+impl Source {
+    /// Name of the source in the exports.
+    fn name(self) -> &'static str {
+        match self {
+            Source::Request => "request",
+            Source::Batch => "batch",
+            Source::Offer => "offer",
+            Source::Lease => "lease",
+        }
+    }
+}
+
+/// Who an energy cell was discharged for, and why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Provenance {
+    /// The explorer the resource was produced for.
+    pub explorer_id: ExplorerId,
+    /// The resource produced.
+    pub resource: Product,
+    /// What entitled the explorer to the cell.
+    pub source: Source,
+}
+
+/// What happened to an energy cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CellChange {
+    /// A sunray charged the cell, at the time it arrived.
+    Charged,
+    /// The cell was charged from the stockpile seeded at construction.
+    Seeded,
+    /// The cell was discharged to produce a resource for an explorer.
+    Discharged(Provenance),
+}
+
+/// A change of an energy cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CellEvent {
+    /// When the cell changed, on the planet clock.
+    pub at: SystemTime,
+    /// Index of the cell.
+    pub cell: usize,
+    pub change: CellChange,
+}
+
+/// Interval an energy cell stayed charged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CellInterval {
+    /// Index of the cell.
+    pub cell: usize,
+    /// When the sunray charging the cell arrived, or when the cell was seeded, `None` if
+    /// before the oldest retained change.
+    pub charged: Option<SystemTime>,
+    /// When the cell was discharged, `None` if it's still charged.
+    pub discharged: Option<SystemTime>,
+    /// Who the cell was discharged for, if it was.
+    pub provenance: Option<Provenance>,
+}
+
+/// The most recent changes of the energy cells, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CellTimeline {
+    events: VecDeque<CellEvent>,
+    capacity: usize,
+}
+
+impl CellTimeline {
+    /// Creates an empty timeline retaining the `capacity` most recent changes.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Timeline capacity must be greater than zero");
+        CellTimeline {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// The retained changes, oldest first.
+    pub fn events(&self) -> impl Iterator<Item = &CellEvent> {
+        self.events.iter()
+    }
+
+    /// Records a change, discarding the oldest one if the timeline is full.
+    pub(crate) fn record(&mut self, event: CellEvent) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// The charged intervals of the cells, in the order they ended, the ones still
+    /// charged last.
+    pub fn intervals(&self) -> Vec<CellInterval> {
+        let mut intervals = Vec::new();
+        let mut charged_since = BTreeMap::new();
+        for event in &self.events {
+            match event.change {
+                CellChange::Charged | CellChange::Seeded => {
+                    charged_since.insert(event.cell, event.at);
+                }
+                CellChange::Discharged(provenance) => intervals.push(CellInterval {
+                    cell: event.cell,
+                    charged: charged_since.remove(&event.cell),
+                    discharged: Some(event.at),
+                    provenance: Some(provenance),
+                }),
+            }
+        }
+        intervals.extend(charged_since.into_iter().map(|(cell, at)| CellInterval {
+            cell,
+            charged: Some(at),
+            discharged: None,
+            provenance: None,
+        }));
+        intervals
+    }
+
+    /// Exports the charged intervals as JSON, times in milliseconds since the Unix
+    /// epoch, unknown times and provenances `null`.
+    pub fn intervals_to_json(&self) -> String {
+        let mut json = String::from("{\"intervals\":[");
+        for (index, interval) in self.intervals().iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let [charged, discharged, explorer_id, resource, source] = interval.fields();
+            let [charged, discharged, explorer_id] = [charged, discharged, explorer_id]
+                .map(|field| field.unwrap_or_else(|| "null".to_string()));
+            let [resource, source] = [resource, source].map(|field| {
+                field.map_or_else(|| "null".to_string(), |name| format!("\"{name}\""))
+            });
+            write!(
+                json,
+                "{{\"cell\":{},\"charged_ms\":{},\"discharged_ms\":{},\"explorer_id\":{},\
+                 \"resource\":{},\"source\":{}}}",
+                interval.cell, charged, discharged, explorer_id, resource, source
+            )
+            .unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// Exports the charged intervals as CSV with a header, times in milliseconds since
+    /// the Unix epoch, unknown times and provenances empty.
+    pub fn intervals_to_csv(&self) -> String {
+        let mut csv = String::from("cell,charged_ms,discharged_ms,explorer_id,resource,source\n");
+        for interval in self.intervals() {
+            let [charged, discharged, explorer_id, resource, source] =
+                interval.fields().map(Option::unwrap_or_default);
+            writeln!(
+                csv,
+                "{},{},{},{},{},{}",
+                interval.cell, charged, discharged, explorer_id, resource, source
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    /// Exports the retained changes as JSON, times in milliseconds since the Unix epoch.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"events\":[");
+        for (index, event) in self.events.iter().enumerate() {
+            let at_ms = millis(event.at);
+            if index > 0 {
+                json.push(',');
+            }
+            write!(json, "{{\"at_ms\":{},\"cell\":{},", at_ms, event.cell).unwrap();
+            match event.change {
+                CellChange::Charged => json.push_str("\"event\":\"charged\"}"),
+                CellChange::Seeded => json.push_str("\"event\":\"seeded\"}"),
+                CellChange::Discharged(provenance) => write!(
+                    json,
+                    "\"event\":\"discharged\",\"explorer_id\":{},\"resource\":\"{}\",\"source\":\"{}\"}}",
+                    provenance.explorer_id,
+                    provenance.resource,
+                    provenance.source.name()
+                )
+                .unwrap(),
+            }
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+impl CellInterval {
+    /// The charge and discharge times in milliseconds since the Unix epoch, and the
+    /// explorer, resource and source of the provenance, formatted for the exports.
+    fn fields(&self) -> [Option<String>; 5] {
+        [
+            self.charged.map(|at| millis(at).to_string()),
+            self.discharged.map(|at| millis(at).to_string()),
+            self.provenance
+                .map(|provenance| provenance.explorer_id.to_string()),
+            self.provenance
+                .map(|provenance| provenance.resource.to_string()),
+            self.provenance
+                .map(|provenance| provenance.source.name().to_string()),
+        ]
+    }
+}
+
+/// Converts `time` to milliseconds since [`UNIX_EPOCH`], saturating before it.
+fn millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis())
+}
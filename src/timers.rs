@@ -0,0 +1,174 @@
+//! Timer wheel module.
+//!
+//! Reservations, leases, bans, disabled resources, pending requests and policy warm-ups
+//! all expire at a known time. Rather than checking every expirable structure before
+//! each message, the planet AI schedules an [`Expiry`] on a [`TimerWheel`] when it
+//! creates one, and only handles the expiries due when a message arrives.
+//!
+//! The planet run loop is owned by `common_game`, so the wheel runs on no thread of its
+//! own: it's advanced before each message, and by
+//! [`AdminCommand::Tick`](crate::admin::AdminCommand::Tick) for hosts waking up the
+//! planet from their own select timeouts.
+//!
+//! Expiries aren't cancelled: a ban lifted early, or an offer confirmed before its
+//! deadline, leaves its expiry on the wheel, and the AI checks that the structure it
+//! names did expire when it's due.
+
+use crate::ExplorerId;
+use common_game::components::resource::BasicResourceType;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of slots of the wheel.
+const SLOTS: usize = 256;
+/// Time covered by each slot.
+const RESOLUTION: Duration = Duration::from_millis(10);
+
+/// Something of the planet AI expiring at a given time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Expiry {
+    /// The ban of an explorer.
+    Ban(ExplorerId),
+    /// The timer of a disabled resource.
+    Resource(BasicResourceType),
+    /// The lease of the planet.
+    Lease,
+    /// The cell offered to an explorer.
+    Offer(ExplorerId),
+    /// The batch granted to an explorer.
+    Batch(ExplorerId),
+    /// The timeout of a pending request.
+    Pending,
+    /// The warm-up of an incoming policy.
+    WarmUp,
+}
+
+/// Hashed timing wheel: each expiry is kept in the slot of its deadline, modulo the
+/// number of slots, so that advancing the wheel only visits the slots elapsed since it
+/// was last advanced.
+#[derive(Debug)]
+pub(crate) struct TimerWheel {
+    slots: Vec<Vec<(SystemTime, Expiry)>>,
+    /// Tick the wheel was last advanced to.
+    cursor: Option<u64>,
+    /// Number of scheduled expiries.
+    len: usize,
+}
+
+impl Default for TimerWheel {
+    fn default() -> Self {
+        TimerWheel {
+            slots: vec![Vec::new(); SLOTS],
+            cursor: None,
+            len: 0,
+        }
+    }
+}
+
+impl TimerWheel {
+    /// Tick of `at`, in resolutions since the Unix epoch.
+    fn tick(at: SystemTime) -> u64 {
+        let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        (since_epoch.as_nanos() / RESOLUTION.as_nanos()) as u64
+    }
+
+    /// Schedules `expiry` at `deadline`. Deadlines already passed are due the next time
+    /// the wheel is advanced.
+    pub(crate) fn schedule(&mut self, deadline: SystemTime, expiry: Expiry) {
+        let tick = Self::tick(deadline).max(self.cursor.unwrap_or(0));
+        self.slots[tick as usize % SLOTS].push((deadline, expiry));
+        self.len += 1;
+    }
+
+    /// Advances the wheel to `now`, removing and returning the expiries due, in the
+    /// order of their deadlines, then of their scheduling.
+    pub(crate) fn advance(&mut self, now: SystemTime) -> Vec<Expiry> {
+        let target = Self::tick(now);
+        let last = self.cursor.replace(target);
+        if self.len == 0 {
+            return Vec::new();
+        }
+
+        // Every slot may hold due expiries after a full turn, or on the first advance
+        let (cursor, elapsed) = match last {
+            Some(last) if last <= target => (last, (target - last).min(SLOTS as u64 - 1)),
+            _ => (target, SLOTS as u64 - 1),
+        };
+        let mut due = Vec::new();
+        for tick in cursor..=cursor + elapsed {
+            let slot = &mut self.slots[tick as usize % SLOTS];
+            due.extend(slot.extract_if(.., |(deadline, _)| *deadline <= now));
+        }
+        self.len -= due.len();
+        due.sort_by_key(|(deadline, _)| *deadline);
+        due.into_iter().map(|(_, expiry)| expiry).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the timer wheel.
+
+    use super::*;
+
+    fn at(millis: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(millis)
+    }
+
+    // ============================================================================
+    // Tests: Expiries
+    // ============================================================================
+
+    /// **Scenario:** Expiries scheduled 5ms, 20ms, 1s and 10s ahead, and one already
+    /// passed, while the wheel is advanced step by step
+    /// **Validates:**
+    /// - Expiries are due once their deadline is reached, not before
+    /// - Passed deadlines are due right away
+    /// - Deadlines more than a turn ahead wait for their turn
+    #[test]
+    fn test_expiries_due_at_deadline() {
+        let mut wheel = TimerWheel::default();
+        assert!(wheel.advance(at(1_000)).is_empty());
+        wheel.schedule(at(11_000), Expiry::Lease);
+        wheel.schedule(at(2_000), Expiry::Ban(ExplorerId::new(1)));
+        wheel.schedule(at(1_020), Expiry::Offer(ExplorerId::new(2)));
+        wheel.schedule(at(1_005), Expiry::Batch(ExplorerId::new(3)));
+        wheel.schedule(at(500), Expiry::Pending);
+
+        assert_eq!(wheel.advance(at(1_004)), [Expiry::Pending]);
+        assert_eq!(
+            wheel.advance(at(1_019)),
+            [Expiry::Batch(ExplorerId::new(3))]
+        );
+        assert_eq!(
+            wheel.advance(at(1_999)),
+            [Expiry::Offer(ExplorerId::new(2))]
+        );
+        assert_eq!(wheel.advance(at(10_999)), [Expiry::Ban(ExplorerId::new(1))]);
+        assert_eq!(wheel.advance(at(11_000)), [Expiry::Lease]);
+        assert_eq!(wheel.len, 0);
+    }
+
+    /// **Scenario:** Expiries scheduled at the same tick, some at the same deadline,
+    /// the wheel advanced past them
+    /// **Validates:** They're all due, in the order of their deadlines, then of their
+    /// scheduling
+    #[test]
+    fn test_expiries_ordered() {
+        let mut wheel = TimerWheel::default();
+        wheel.schedule(at(1_008), Expiry::Resource(BasicResourceType::Oxygen));
+        for explorer_id in [3, 1, 2] {
+            wheel.schedule(at(1_004), Expiry::Offer(ExplorerId::new(explorer_id)));
+        }
+        wheel.schedule(at(1_001), Expiry::WarmUp);
+        assert_eq!(
+            wheel.advance(at(1_010)),
+            [
+                Expiry::WarmUp,
+                Expiry::Offer(ExplorerId::new(3)),
+                Expiry::Offer(ExplorerId::new(1)),
+                Expiry::Offer(ExplorerId::new(2)),
+                Expiry::Resource(BasicResourceType::Oxygen),
+            ]
+        );
+    }
+}
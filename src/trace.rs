@@ -0,0 +1,285 @@
+//! Chrome trace export module.
+//!
+//! Renders the activity of a planet in the Chrome
+//! [`trace_event`](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+//! JSON format, to scrub through a game in `chrome://tracing` or
+//! [Perfetto](https://ui.perfetto.dev):
+//! - from a [`Journal`], one track per explorer, with an instant per generation
+//!   decision and a `waiting` slice from a denial to the next grant, so that starvation
+//!   periods stand out
+//! - from a [`CellTimeline`], one track per energy cell, with a `charged` slice from
+//!   each charge to the discharge, labeled with the explorer the cell went to
+//!
+//! ```
+//! use rustrelli::journal::Journal;
+//! use rustrelli::trace::ChromeTrace;
+//!
+//! let journal = Journal::read(
+//!     "rustrelli-journal 1 cells=5 charged=0\n1000 generate 7 Oxygen no_energy\n".as_bytes(),
+//! )?;
+//! let json = ChromeTrace::default().with_journal(&journal).to_json();
+//! assert!(json.starts_with("{\"traceEvents\":["));
+//! # Ok::<(), rustrelli::error::RustrelliError>(())
+//! ```
+
+use crate::ExplorerId;
+use crate::journal::{Journal, JournalEntry};
+use crate::refusal::RefusalReason;
+use crate::timeline::CellTimeline;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Process grouping the explorer tracks.
+const EXPLORERS_PID: u32 = 1;
+/// Process grouping the cell tracks.
+const CELLS_PID: u32 = 2;
+
+/// An event of the trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TraceEvent {
+    name: String,
+    pid: u32,
+    tid: u64,
+    /// Start, in microseconds since the Unix epoch.
+    ts: u128,
+    /// Duration in microseconds for a slice, `None` for an instant.
+    dur: Option<u128>,
+    /// Explorer the event relates to, on cell tracks.
+    explorer_id: Option<ExplorerId>,
+}
+
+/// Trace of the activity of a planet, exported with [`ChromeTrace::to_json`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChromeTrace {
+    events: Vec<TraceEvent>,
+    explorers: BTreeSet<ExplorerId>,
+    cells: BTreeSet<usize>,
+}
+
+impl ChromeTrace {
+    /// Adds a track per explorer of `journal`.
+    pub fn with_journal(mut self, journal: &Journal) -> Self {
+        let end = journal.entries.last().map(JournalEntry::at);
+        let mut waiting_since = BTreeMap::new();
+        for entry in &journal.entries {
+            let JournalEntry::Generation {
+                at,
+                explorer_id,
+                resource,
+                denial,
+                ..
+            } = *entry
+            else {
+                continue;
+            };
+            self.explorers.insert(explorer_id);
+            let name = match denial {
+                None => {
+                    if let Some(since) = waiting_since.remove(&explorer_id) {
+                        self.slice(
+                            "waiting",
+                            EXPLORERS_PID,
+                            explorer_id.ordinal(),
+                            since,
+                            at,
+                            None,
+                        );
+                    }
+                    format!("granted {resource:?}")
+                }
+                Some(reason) => {
+                    waiting_since.entry(explorer_id).or_insert(at);
+                    format!("denied {}", RefusalReason::Denied(reason).code())
+                }
+            };
+            self.events.push(TraceEvent {
+                name,
+                pid: EXPLORERS_PID,
+                tid: explorer_id.ordinal(),
+                ts: micros(at),
+                dur: None,
+                explorer_id: None,
+            });
+        }
+        if let Some(end) = end {
+            for (explorer_id, since) in waiting_since {
+                self.slice(
+                    "waiting",
+                    EXPLORERS_PID,
+                    explorer_id.ordinal(),
+                    since,
+                    end,
+                    None,
+                );
+            }
+        }
+        self
+    }
+
+    /// Adds a track per energy cell of `timeline`. Cells charged before the oldest
+    /// retained change are charged from it, and cells still charged are charged until
+    /// the latest one.
+    pub fn with_cell_timeline(mut self, timeline: &CellTimeline) -> Self {
+        let Some(start) = timeline.events().next().map(|event| event.at) else {
+            return self;
+        };
+        let end = timeline.events().last().map_or(start, |event| event.at);
+        for interval in timeline.intervals() {
+            self.cells.insert(interval.cell);
+            self.slice(
+                "charged",
+                CELLS_PID,
+                interval.cell as u64,
+                interval.charged.unwrap_or(start),
+                interval.discharged.unwrap_or(end),
+                interval.provenance.map(|provenance| provenance.explorer_id),
+            );
+        }
+        self
+    }
+
+    fn slice(
+        &mut self,
+        name: &str,
+        pid: u32,
+        tid: u64,
+        from: SystemTime,
+        to: SystemTime,
+        explorer_id: Option<ExplorerId>,
+    ) {
+        let ts = micros(from);
+        self.events.push(TraceEvent {
+            name: name.to_string(),
+            pid,
+            tid,
+            ts,
+            dur: Some(micros(to).saturating_sub(ts)),
+            explorer_id,
+        });
+    }
+
+    /// Exports the trace as `trace_event` JSON, tracks named after their explorer or
+    /// cell.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"traceEvents\":[");
+        let mut first = true;
+        let mut separate = |json: &mut String| {
+            if !std::mem::take(&mut first) {
+                json.push(',');
+            }
+        };
+        for (pid, name) in [(EXPLORERS_PID, "explorers"), (CELLS_PID, "energy cells")] {
+            separate(&mut json);
+            write!(
+                json,
+                "{{\"ph\":\"M\",\"name\":\"process_name\",\"pid\":{pid},\"args\":{{\"name\":\"{name}\"}}}}"
+            )
+            .unwrap();
+        }
+        let tracks = self
+            .explorers
+            .iter()
+            .map(|id| (EXPLORERS_PID, id.ordinal(), format!("explorer {id}")))
+            .chain(
+                self.cells
+                    .iter()
+                    .map(|cell| (CELLS_PID, *cell as u64, format!("cell {cell}"))),
+            );
+        for (pid, tid, name) in tracks {
+            separate(&mut json);
+            write!(
+                json,
+                "{{\"ph\":\"M\",\"name\":\"thread_name\",\"pid\":{pid},\"tid\":{tid},\"args\":{{\"name\":\"{name}\"}}}}"
+            )
+            .unwrap();
+        }
+        for event in &self.events {
+            separate(&mut json);
+            write!(
+                json,
+                "{{\"name\":\"{}\",\"pid\":{},\"tid\":{},\"ts\":{}",
+                event.name, event.pid, event.tid, event.ts
+            )
+            .unwrap();
+            match event.dur {
+                Some(dur) => write!(json, ",\"ph\":\"X\",\"dur\":{dur}").unwrap(),
+                None => json.push_str(",\"ph\":\"i\",\"s\":\"t\""),
+            }
+            if let Some(explorer_id) = event.explorer_id {
+                write!(json, ",\"args\":{{\"explorer_id\":{explorer_id}}}").unwrap();
+            }
+            json.push('}');
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+/// Converts `time` to microseconds since [`UNIX_EPOCH`], saturating before it.
+fn micros(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_micros())
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the Chrome trace export.
+
+    use super::*;
+    use crate::timeline::{CellChange, CellEvent, Product, Provenance, Source};
+    use common_game::components::resource::BasicResourceType;
+    use std::time::Duration;
+
+    // ============================================================================
+    // Tests: Export
+    // ============================================================================
+
+    /// **Scenario:** Explorer 7 is denied, then granted a cell charged a second earlier
+    /// **Validates:**
+    /// - The explorer track has its decisions as instants, and its wait as a slice
+    /// - The cell track has the charge as a slice labeled with the explorer
+    #[test]
+    fn test_trace_has_explorer_and_cell_tracks() {
+        let journal = Journal::read(
+            "rustrelli-journal 1 cells=5 charged=0\n\
+             1000 generate 7 Oxygen no_energy\n\
+             2000 sunray\n\
+             2500 generate 7 Oxygen granted\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        let mut timeline = CellTimeline::new(4);
+        for (millis, change) in [
+            (2000, CellChange::Charged),
+            (
+                2500,
+                CellChange::Discharged(Provenance {
+                    explorer_id: ExplorerId::new(7),
+                    resource: Product::Basic(BasicResourceType::Oxygen),
+                    source: Source::Request,
+                }),
+            ),
+        ] {
+            timeline.record(CellEvent {
+                at: UNIX_EPOCH + Duration::from_millis(millis),
+                cell: 0,
+                change,
+            });
+        }
+
+        let json = ChromeTrace::default()
+            .with_journal(&journal)
+            .with_cell_timeline(&timeline)
+            .to_json();
+        for expected in [
+            "{\"ph\":\"M\",\"name\":\"thread_name\",\"pid\":1,\"tid\":7,\"args\":{\"name\":\"explorer 7\"}}",
+            "{\"ph\":\"M\",\"name\":\"thread_name\",\"pid\":2,\"tid\":0,\"args\":{\"name\":\"cell 0\"}}",
+            "{\"name\":\"denied no_energy\",\"pid\":1,\"tid\":7,\"ts\":1000000,\"ph\":\"i\",\"s\":\"t\"}",
+            "{\"name\":\"waiting\",\"pid\":1,\"tid\":7,\"ts\":1000000,\"ph\":\"X\",\"dur\":1500000}",
+            "{\"name\":\"charged\",\"pid\":2,\"tid\":0,\"ts\":2000000,\"ph\":\"X\",\"dur\":500000,\"args\":{\"explorer_id\":7}}",
+        ] {
+            assert!(json.contains(expected), "{expected} missing from {json}");
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Planet watchdog module.
+//!
+//! The planet AI records a heartbeat in its statistics every time it starts handling
+//! a message (see [`Stats::last_activity`](crate::stats::Stats::last_activity)). A quiet heartbeat alone can't tell an
+//! idle planet from a stuck one: the [`Watchdog`] also looks at the channels feeding
+//! the planet, and reports an [`Event::Stalled`] when messages are waiting while the
+//! heartbeat stays quiet for too long.
+//!
+//! The watchdog runs on its own thread, so it keeps working while the planet thread
+//! is blocked.
+
+use crate::events::{Event, EventSink};
+use crate::stats::StatsHandle;
+use crossbeam_channel::{RecvTimeoutError, Sender, bounded};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Observer of a planet heartbeat, to be started with [`Watchdog::spawn`].
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use crossbeam_channel::unbounded;
+/// use rustrelli::events::Event;
+/// use rustrelli::stats::StatsHandle;
+/// use rustrelli::watchdog::Watchdog;
+///
+/// let stats = StatsHandle::default();
+/// let (tx_orch, rx_orch) = unbounded::<u32>();
+/// let (tx_events, rx_events) = unbounded::<Event>();
+///
+/// let watchdog = Watchdog::new(stats, Duration::from_secs(5))
+///     .watch(tx_orch)
+///     .with_events(tx_events)
+///     .spawn();
+/// watchdog.stop();
+/// ```
+pub struct Watchdog {
+    stats: StatsHandle,
+    stall_after: Duration,
+    /// Number of messages waiting in each watched channel.
+    probes: Vec<Box<dyn Fn() -> usize + Send>>,
+    events: EventSink,
+}
+
+impl Watchdog {
+    /// Creates a watchdog for the planet recording its statistics into `stats`,
+    /// considered stalled after `stall_after` without activity.
+    ///
+    /// # Panics
+    /// Panics if `stall_after` is zero.
+    pub fn new(stats: StatsHandle, stall_after: Duration) -> Self {
+        assert!(
+            !stall_after.is_zero(),
+            "Stall duration must be greater than zero"
+        );
+        Watchdog {
+            stats,
+            stall_after,
+            probes: Vec::new(),
+            events: EventSink::default(),
+        }
+    }
+
+    /// Watches a channel feeding the planet, through a clone of its sender.
+    pub fn watch<T: Send + 'static>(mut self, sender: Sender<T>) -> Self {
+        self.probes.push(Box::new(move || sender.len()));
+        self
+    }
+
+    /// Sets the channel the [`Event::Stalled`] alerts are sent to.
+    pub fn with_events(mut self, events: Sender<Event>) -> Self {
+        self.events = EventSink::new(events);
+        self
+    }
+
+    /// Starts observing the planet on a new thread.
+    ///
+    /// The planet is checked four times per stall duration. An alert is sent once per
+    /// stall: the watchdog alerts again only after the planet made progress.
+    pub fn spawn(self) -> WatchdogHandle {
+        let (stop, stopped) = bounded(1);
+        let started = SystemTime::now();
+        let interval = self.stall_after / 4;
+
+        let thread = thread::Builder::new()
+            .name("rustrelli-watchdog".to_string())
+            .spawn(move || {
+                let mut last_seen = None;
+                let mut alerted = false;
+                // Returns when stopped, or when the handle is dropped
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    let last_activity = self.stats.last_activity();
+                    if last_activity != last_seen {
+                        last_seen = last_activity;
+                        alerted = false;
+                    }
+
+                    let idle_for = SystemTime::now()
+                        .duration_since(last_activity.unwrap_or(started))
+                        .unwrap_or(Duration::ZERO);
+                    let queued_messages: usize = self.probes.iter().map(|probe| probe()).sum();
+                    if !alerted && idle_for >= self.stall_after && queued_messages > 0 {
+                        self.events.emit(Event::Stalled {
+                            idle_for,
+                            queued_messages,
+                        });
+                        alerted = true;
+                    }
+                }
+            })
+            .expect("Failed to spawn the watchdog thread");
+
+        WatchdogHandle { stop, thread }
+    }
+}
+
+/// Handle to a running [`Watchdog`]. Dropping it stops the watchdog.
+pub struct WatchdogHandle {
+    stop: Sender<()>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl WatchdogHandle {
+    /// Stops the watchdog and waits for its thread to exit.
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+    }
+}
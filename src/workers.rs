@@ -0,0 +1,303 @@
+//! Query worker pool module.
+//!
+//! With [`PlanetConfig::with_query_workers`](crate::PlanetConfig::with_query_workers),
+//! explorer messages go through a dispatcher thread before reaching the planet:
+//! - Read-only queries (supported resources and combinations, available energy) of the
+//!   explorers whose channel the host shared with
+//!   [`AdminCommand::WatchExplorer`](crate::admin::AdminCommand::WatchExplorer) are
+//!   answered by a pool of worker threads
+//! - Every other message is forwarded to the planet loop, in arrival order
+//!
+//! Queries then don't wait behind generation requests, at the cost of freshness:
+//! available energy is the one last observed by the planet AI. Some queries are still
+//! left to the planet loop:
+//! - Every query while the planet AI isn't running, so that the planet answers them as
+//!   stopped
+//! - Queries charged to the limiter, if a cost is set for them (see
+//!   [`PlanetConfig::with_cost_model`](crate::PlanetConfig::with_cost_model))
+//! - Available energy queries, if they're throttled (see
+//!   [`PlanetConfig::with_energy_poll_interval`](crate::PlanetConfig::with_energy_poll_interval))
+//! - Queries arriving while [`QUEUE_PER_WORKER`] queries per worker are already
+//!   waiting, so that explorers flooding the pool still feel the planet backpressure
+//!
+//! The workers record the capability polls they answer in the statistics once their
+//! queue is empty, so that a burst of queries takes the statistics lock once.
+
+use crate::ExplorerId;
+use crate::PlanetConfig;
+use crate::cost::MessageKind;
+use crate::stats::StatsHandle;
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use common_game::utils::ID;
+use crossbeam_channel::{Receiver, Sender, TrySendError, bounded, unbounded};
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+/// Queries waiting for each worker, past which the dispatcher leaves them to the planet
+/// loop.
+pub const QUEUE_PER_WORKER: usize = 64;
+
+/// What the planet AI shares with the query workers.
+#[derive(Debug, Default)]
+pub(crate) struct SharedView {
+    /// Senders of the explorers whose queries can be answered by the workers.
+    explorers: RwLock<HashMap<ExplorerId, Sender<PlanetToExplorer>>>,
+    /// Whether the planet AI is running.
+    running: AtomicBool,
+    /// Charged cells, as last observed by the planet AI.
+    charged_cells: AtomicU32,
+}
+
+impl SharedView {
+    /// Lets the workers answer the queries of `explorer_id` through `sender`.
+    pub(crate) fn watch(&self, explorer_id: ExplorerId, sender: Sender<PlanetToExplorer>) {
+        self.explorers
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(explorer_id, sender);
+    }
+
+    /// Leaves the queries of `explorer_id` to the planet loop.
+    pub(crate) fn forget(&self, explorer_id: ExplorerId) {
+        self.explorers
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(&explorer_id);
+    }
+
+    /// Records whether the planet AI is running.
+    pub(crate) fn set_running(&self, running: bool) {
+        self.running.store(running, Ordering::Release);
+    }
+
+    /// Records the charged cells observed by the planet AI.
+    pub(crate) fn observe_charged_cells(&self, charged: usize) {
+        self.charged_cells.store(
+            u32::try_from(charged).unwrap_or(u32::MAX),
+            Ordering::Release,
+        );
+    }
+}
+
+/// The planet AI's side of a [`SharedView`]. Dropping it leaves the queries to the
+/// planet loop, as a killed planet doesn't stop its AI.
+#[derive(Debug)]
+pub(crate) struct ViewHandle(Arc<SharedView>);
+
+impl ViewHandle {
+    pub(crate) fn new(view: Arc<SharedView>) -> Self {
+        ViewHandle(view)
+    }
+}
+
+impl Deref for ViewHandle {
+    type Target = SharedView;
+
+    fn deref(&self) -> &SharedView {
+        &self.0
+    }
+}
+
+impl Drop for ViewHandle {
+    fn drop(&mut self) {
+        self.0.set_running(false);
+    }
+}
+
+/// What the workers need to answer queries.
+struct Context {
+    resources: HashSet<BasicResourceType>,
+    combinations: HashSet<ComplexResourceType>,
+    stats: StatsHandle,
+    view: Arc<SharedView>,
+    /// Whether capability queries are charged to the limiter, by the planet AI.
+    charged_polls: bool,
+    /// Whether available energy queries are charged to the limiter, or throttled, by the
+    /// planet AI.
+    planet_energy_polls: bool,
+}
+
+impl Context {
+    /// The sender to answer `msg` with, if it's a query the workers can answer.
+    fn answerable(&self, msg: &ExplorerToPlanet) -> Option<Sender<PlanetToExplorer>> {
+        if !self.view.running.load(Ordering::Acquire) {
+            return None;
+        }
+        let explorer_id = match msg {
+            ExplorerToPlanet::SupportedResourceRequest { explorer_id }
+            | ExplorerToPlanet::SupportedCombinationRequest { explorer_id }
+                if !self.charged_polls =>
+            {
+                *explorer_id
+            }
+            ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id }
+                if !self.planet_energy_polls =>
+            {
+                *explorer_id
+            }
+            _ => return None,
+        };
+        let explorers = self
+            .view
+            .explorers
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        explorers.get(&ExplorerId::from(explorer_id)).cloned()
+    }
+
+    /// Answers `msg`, a query of an explorer, adding the capability poll it makes to
+    /// `polls`, by explorer and whether it's about combinations.
+    fn answer(
+        &self,
+        msg: ExplorerToPlanet,
+        polls: &mut HashMap<(ExplorerId, bool), u64>,
+    ) -> Option<PlanetToExplorer> {
+        match msg {
+            ExplorerToPlanet::SupportedResourceRequest { explorer_id } => {
+                *polls.entry((explorer_id.into(), false)).or_default() += 1;
+                Some(PlanetToExplorer::SupportedResourceResponse {
+                    resource_list: self.resources.clone(),
+                })
+            }
+            ExplorerToPlanet::SupportedCombinationRequest { explorer_id } => {
+                *polls.entry((explorer_id.into(), true)).or_default() += 1;
+                Some(PlanetToExplorer::SupportedCombinationResponse {
+                    combination_list: self.combinations.clone(),
+                })
+            }
+            ExplorerToPlanet::AvailableEnergyCellRequest { .. } => {
+                Some(PlanetToExplorer::AvailableEnergyCellResponse {
+                    available_cells: self.view.charged_cells.load(Ordering::Acquire),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Records the capability `polls` in the statistics.
+    fn record_polls(&self, polls: &mut HashMap<(ExplorerId, bool), u64>) {
+        if polls.is_empty() {
+            return;
+        }
+        self.stats.update(|stats| {
+            for ((explorer_id, combinations), count) in polls.drain() {
+                for _ in 0..count {
+                    stats.record_capability_poll(explorer_id, combinations);
+                }
+            }
+        });
+    }
+}
+
+/// Channels and threads of a query worker pool, ready to be started once the planet
+/// is built.
+pub(crate) struct QueryWorkers {
+    planet_id: ID,
+    workers: usize,
+    from_explorers: Receiver<ExplorerToPlanet>,
+    to_planet: Sender<ExplorerToPlanet>,
+    context: Context,
+}
+
+impl QueryWorkers {
+    /// Prepares `workers` workers answering the queries received from `from_explorers`.
+    ///
+    /// # Returns
+    /// The pool, and the receiver to give to the planet in place of `from_explorers`.
+    /// It has the same capacity, so that explorers still feel the planet backpressure.
+    pub(crate) fn new(
+        config: &PlanetConfig,
+        workers: usize,
+        from_explorers: Receiver<ExplorerToPlanet>,
+        gen_rules: &[BasicResourceType],
+        comb_rules: &[ComplexResourceType],
+        view: Arc<SharedView>,
+    ) -> (Self, Receiver<ExplorerToPlanet>) {
+        let (to_planet, from_dispatcher) = match from_explorers.capacity() {
+            Some(capacity) => bounded(capacity),
+            None => unbounded(),
+        };
+        let context = Context {
+            resources: gen_rules.iter().copied().collect(),
+            combinations: comb_rules.iter().copied().collect(),
+            stats: config.stats.clone(),
+            view,
+            charged_polls: config.cost(MessageKind::SupportedResources) > 0.0
+                || config.cost(MessageKind::SupportedCombinations) > 0.0,
+            planet_energy_polls: config.cost(MessageKind::AvailableEnergy) > 0.0
+                || config.energy_poll_interval.is_some(),
+        };
+        let pool = QueryWorkers {
+            planet_id: config.id,
+            workers,
+            from_explorers,
+            to_planet,
+            context,
+        };
+        (pool, from_dispatcher)
+    }
+
+    /// Starts the dispatcher and the workers. They stop once every explorer sender is
+    /// dropped, or the planet is gone.
+    ///
+    /// # Errors
+    /// Returns a description of the problem if a thread can't be spawned.
+    pub(crate) fn start(self) -> Result<(), String> {
+        let QueryWorkers {
+            planet_id,
+            workers,
+            from_explorers,
+            to_planet,
+            context,
+        } = self;
+        let context = Arc::new(context);
+        let (to_workers, queries) =
+            bounded::<(ExplorerToPlanet, Sender<PlanetToExplorer>)>(QUEUE_PER_WORKER * workers);
+
+        for worker in 0..workers {
+            let queries = queries.clone();
+            let context = context.clone();
+            thread::Builder::new()
+                .name(format!("rustrelli-queries-{planet_id}-{worker}"))
+                .spawn(move || {
+                    let mut polls = HashMap::new();
+                    for (msg, explorer) in queries.iter() {
+                        let response = context.answer(msg, &mut polls);
+                        if queries.is_empty() {
+                            context.record_polls(&mut polls);
+                        }
+                        if let Some(response) = response {
+                            // A departed explorer doesn't need its answer anymore
+                            let _ = explorer.send(response);
+                        }
+                    }
+                    context.record_polls(&mut polls);
+                })
+                .map_err(|error| format!("Can't spawn the query workers: {error}"))?;
+        }
+
+        thread::Builder::new()
+            .name(format!("rustrelli-dispatcher-{planet_id}"))
+            .spawn(move || {
+                for msg in from_explorers.iter() {
+                    let msg = match context.answerable(&msg) {
+                        Some(explorer) => match to_workers.try_send((msg, explorer)) {
+                            Ok(()) => continue,
+                            Err(TrySendError::Full((msg, _))) => msg,
+                            Err(TrySendError::Disconnected((msg, _))) => msg,
+                        },
+                        None => msg,
+                    };
+                    if to_planet.send(msg).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(|error| format!("Can't spawn the query dispatcher: {error}"))?;
+        Ok(())
+    }
+}
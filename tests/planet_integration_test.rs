@@ -7,81 +7,62 @@
 //!
 //! Each test documents its scenario and validation goals.
 
-use common_game::components::resource::BasicResourceType;
+use common_game::components::asteroid::Asteroid;
+use common_game::components::planet::PlanetType;
+use common_game::components::resource::{
+    BasicResource, BasicResourceType, ComplexResourceRequest, ComplexResourceType, GenericResource,
+};
 use common_game::components::sunray::Sunray;
 use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
 use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
-use crossbeam_channel::{Receiver, Sender, unbounded};
-use rustrelli::{ExplorerRequestLimit, create_planet};
+use crossbeam_channel::{Receiver, Sender, bounded, select, unbounded};
+use rustrelli::admin::{AdminCommand, PauseMode};
+use rustrelli::backoff::BackoffConfig;
+use rustrelli::batch::BatchConfig;
+use rustrelli::budget::Extra;
+use rustrelli::claim::ClaimConfig;
+use rustrelli::clock::ClockAnomaly;
+use rustrelli::compliance::{Attestation, RuleBending};
+use rustrelli::cost::FixedCosts;
+use rustrelli::delivery::{DeadLetterCause, DeliveryConfig};
+use rustrelli::events::{
+    DeliveryFailure, Event, EventFilter, LifecycleStage, Severity, ShutdownReport,
+};
+use rustrelli::fleet::FleetCoordinator;
+use rustrelli::gateway::ExplorerGateway;
+use rustrelli::journal::{Journal, JournalEntry};
+use rustrelli::lease::{LeaseConfig, LeaseRefusal};
+use rustrelli::misconduct::{Misconduct, MisconductConfig, Signal};
+use rustrelli::pending::Overflow;
+use rustrelli::policy::{DenialReason, Policy, PolicyArm, SharedPolicy};
+use rustrelli::priority::OrchestratorPriority;
+use rustrelli::receipt::{Receipt, ReceiptId};
+use rustrelli::refusal::{self, RefusalReason};
+use rustrelli::registration::{History, Onboarding, Reregistration, Transition, Untracked};
+use rustrelli::stats::{Counters, StatsConfig, StatsHandle, StatsWatch, Streak};
+use rustrelli::stockpile::StockpileVisibility;
+use rustrelli::storage::{CELL_TIMELINE, JOURNAL, MemoryStorage, StorageBackend};
+use rustrelli::sunrays::Bursty;
+use rustrelli::tags::Tag;
+use rustrelli::timeline::{CellChange, CellTimeline, Product, Provenance, Source};
+use rustrelli::watchdog::Watchdog;
+use rustrelli::{
+    ExplorerId, ExplorerRequestLimit, PlanetChannels, PlanetConfig, Quota, RustrelliError,
+    create_planet_custom, create_planet_with_config, create_planets, spawn_planet, spawn_planets,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-// ============================================================================
-// Test Helpers
-// ============================================================================
-
-#[allow(clippy::type_complexity)]
-fn setup_test_planet() -> (
-    Sender<OrchestratorToPlanet>,
-    Receiver<PlanetToOrchestrator>,
-    Sender<ExplorerToPlanet>,
-    thread::JoinHandle<Result<(), String>>,
-) {
-    let (tx_orch_to_planet, rx_orch_to_planet) = unbounded();
-    let (tx_planet_to_orch, rx_planet_to_orch) = unbounded();
-    let (tx_expl_to_planet, rx_expl_to_planet) = unbounded();
-
-    let mut planet = create_planet(
-        1,
-        rx_orch_to_planet,
-        tx_planet_to_orch,
-        rx_expl_to_planet,
-        ExplorerRequestLimit::None,
-    );
-
-    let handle = thread::spawn(move || planet.run());
-
-    tx_orch_to_planet
-        .send(OrchestratorToPlanet::StartPlanetAI)
-        .unwrap();
-    rx_planet_to_orch.recv().unwrap();
-    thread::sleep(Duration::from_millis(50));
-
-    (
-        tx_orch_to_planet,
-        rx_planet_to_orch,
-        tx_expl_to_planet,
-        handle,
-    )
-}
-
-fn register_explorer(
-    explorer_id: u32,
-    tx_orch: &Sender<OrchestratorToPlanet>,
-    rx_orch: &Receiver<PlanetToOrchestrator>,
-) -> Receiver<PlanetToExplorer> {
-    let (tx_planet_to_expl, rx_planet_to_expl) = unbounded();
-    tx_orch
-        .send(OrchestratorToPlanet::IncomingExplorerRequest {
-            explorer_id,
-            new_sender: tx_planet_to_expl,
-        })
-        .unwrap();
-    let _ = rx_orch.recv_timeout(Duration::from_millis(200));
-    rx_planet_to_expl
-}
+use std::time::{Duration, SystemTime};
+use test_util::bots::{ExplorerStrategy, GreedySpammer, PeriodicPoller, PoliteBackoff, run_bots};
+use test_util::fairness::{assert_no_starvation, assert_shares_within};
+use test_util::{
+    TIMEOUT, TestPlanetFixture, charge_cells, next_event, register_explorer, sent_events,
+    setup_configured_planet, setup_test_planet,
+};
 
-fn charge_cells(
-    count: usize,
-    tx_orch: &Sender<OrchestratorToPlanet>,
-    rx_orch: &Receiver<PlanetToOrchestrator>,
-) {
-    for _ in 0..count {
-        tx_orch
-            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
-            .unwrap();
-        let _ = rx_orch.recv_timeout(Duration::from_millis(200));
-    }
-}
+mod test_util;
 
 // ============================================================================
 // Tests: Planet State & Configuration
@@ -163,6 +144,52 @@ fn test_explorer_capability_queries() {
     }
 }
 
+/// **Scenario:** Planet is started, stopped and started again
+/// **Validates:**
+/// - The capability generation is zero before the capabilities are known, then one
+/// - Restarting the planet with the same rules doesn't bump it
+#[test]
+fn test_capability_generation_is_stable() {
+    let stats = StatsHandle::default();
+    let (tx_events, rx_events) = unbounded();
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (_tx_expl, rx_expl_to_planet) = unbounded();
+    let mut planet = create_planet_with_config(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_events(tx_events),
+        rx_orch_to_planet,
+        tx_planet_to_orch,
+        rx_expl_to_planet,
+    );
+    assert_eq!(stats.snapshot().capability_generation(), 0);
+    // A stopped planet waits for the next start within the same run
+    let runner = thread::spawn(move || planet.run());
+
+    for command in [
+        OrchestratorToPlanet::StartPlanetAI,
+        OrchestratorToPlanet::StopPlanetAI,
+        OrchestratorToPlanet::StartPlanetAI,
+    ] {
+        tx_orch.send(command).unwrap();
+        rx_orch.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(stats.snapshot().capability_generation(), 1);
+    }
+    assert!(
+        !sent_events(&rx_events)
+            .iter()
+            .any(|event| matches!(event, Event::CapabilitiesChanged { .. }))
+    );
+
+    tx_orch.send(OrchestratorToPlanet::KillPlanet).unwrap();
+    assert!(matches!(
+        rx_orch.recv_timeout(TIMEOUT),
+        Ok(PlanetToOrchestrator::KillPlanetResult { .. })
+    ));
+    assert_eq!(runner.join().unwrap(), Ok(()));
+}
+
 /// **Scenario:** Explorer leaves, tries to communicate
 /// **Validates:** Planet doesn't respond to removed explorers
 #[test]
@@ -198,22 +225,277 @@ fn test_explorer_removal() {
 /// **Validates:** Each gets isolated responses on their channel
 #[test]
 fn test_multiple_explorers() {
-    let (tx_orch, rx_orch, tx_expl, _) = setup_test_planet();
-
-    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
-    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+    let fixture = TestPlanetFixture::builder().explorers([1, 2]).build();
 
     // Both query simultaneously
-    tx_expl
-        .send(ExplorerToPlanet::SupportedResourceRequest { explorer_id: 1 })
-        .unwrap();
-    tx_expl
-        .send(ExplorerToPlanet::SupportedResourceRequest { explorer_id: 2 })
-        .unwrap();
+    for explorer_id in [1, 2] {
+        fixture
+            .to_planet
+            .send(ExplorerToPlanet::SupportedResourceRequest { explorer_id })
+            .unwrap();
+    }
 
     // Both receive responses
-    assert!(rx_expl1.recv_timeout(Duration::from_millis(200)).is_ok());
-    assert!(rx_expl2.recv_timeout(Duration::from_millis(200)).is_ok());
+    assert!(
+        fixture
+            .explorer(1)
+            .recv_timeout(Duration::from_millis(200))
+            .is_ok()
+    );
+    assert!(
+        fixture
+            .explorer(2)
+            .recv_timeout(Duration::from_millis(200))
+            .is_ok()
+    );
+}
+
+/// **Scenario:** FairShare planet charging capability queries; three explorers get a
+/// resource each, while a fourth one polls the capabilities instead of caching them
+/// **Validates:**
+/// - The queries of each explorer are counted, repeats included
+/// - The poller's first generation request is denied, its polls counting as usage
+#[test]
+fn test_capability_polls_are_counted_and_charged() {
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::FairShare)
+        .configure(|config| config.with_capability_poll_cost(1.0))
+        .explorers(1..=4)
+        .charged_cells(4)
+        .build();
+
+    for explorer_id in 2..=4 {
+        assert!(
+            fixture
+                .generate(explorer_id, BasicResourceType::Oxygen)
+                .is_some()
+        );
+    }
+
+    for combinations in [false, false, false, true, true] {
+        let explorer_id = 1;
+        fixture.request(if combinations {
+            ExplorerToPlanet::SupportedCombinationRequest { explorer_id }
+        } else {
+            ExplorerToPlanet::SupportedResourceRequest { explorer_id }
+        });
+    }
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_none(),
+        "Polls should count against the fair share"
+    );
+
+    let snapshot = fixture.stats.snapshot();
+    let polls = snapshot.capability_polls()[&ExplorerId::new(1)];
+    assert_eq!(polls.supported_resources, 3);
+    assert_eq!(polls.supported_combinations, 2);
+    assert_eq!(polls.repeated(), 3);
+    assert!(
+        !snapshot
+            .capability_polls()
+            .contains_key(&ExplorerId::new(2))
+    );
+}
+
+/// **Scenario:** FairShare planet charging energy polls; two explorers get a resource
+/// each, while a third one polls the available energy in a loop
+/// **Validates:**
+/// - The poller's generation request is denied, its polls counting as usage
+/// - A quiet explorer is still granted a resource
+#[test]
+fn test_energy_polls_are_charged() {
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::FairShare)
+        .configure(|config| config.with_energy_poll_cost(0.5))
+        .explorers(1..=3)
+        .charged_cells(4)
+        .build();
+
+    for explorer_id in 2..=3 {
+        assert!(
+            fixture
+                .generate(explorer_id, BasicResourceType::Oxygen)
+                .is_some()
+        );
+    }
+    for _ in 0..10 {
+        fixture.request(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 });
+    }
+
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_none(),
+        "Polls should count against the fair share"
+    );
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_some());
+}
+
+/// **Scenario:** Energy polls answered once a second; explorer 1 polls 6 times in a
+/// row, then again a second later, while explorer 2 polls once
+/// **Validates:**
+/// - The polls sooner than a second after an answer are left unanswered and counted
+/// - Other explorers are throttled separately
+/// - A poll is answered again once the interval has passed
+#[test]
+fn test_energy_poll_throttling() {
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .configure(|config| config.with_energy_poll_interval(Duration::from_secs(1)))
+        .explorers(1..=2)
+        .charged_cells(2)
+        .build();
+
+    let poll = |explorer_id| ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id };
+    fixture.request(poll(1));
+    for _ in 0..5 {
+        fixture.to_planet.send(poll(1)).unwrap();
+    }
+    assert!(matches!(
+        fixture.request(poll(2)),
+        PlanetToExplorer::AvailableEnergyCellResponse { available_cells: 2 }
+    ));
+    assert!(
+        fixture.explorer(1).try_recv().is_err(),
+        "Rapid polls should be coalesced"
+    );
+    assert_eq!(
+        fixture.stats.snapshot().throttled_polls(),
+        &BTreeMap::from([(ExplorerId::new(1), 5)])
+    );
+
+    fixture.advance(Duration::from_secs(1));
+    assert!(matches!(
+        fixture.request(poll(1)),
+        PlanetToExplorer::AvailableEnergyCellResponse { available_cells: 2 }
+    ));
+}
+
+/// **Scenario:** Demand heatmap of minute-long buckets; Oxygen requested twice in the
+/// first minute of the game, Carbon once in the second
+/// **Validates:** Each request is counted in the bucket of its game time
+#[test]
+fn test_demand_heatmap() {
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .configure(|config| config.with_demand_heatmap(Duration::from_secs(60), 10))
+        .explorers([1])
+        .build();
+
+    fixture.generate(1, BasicResourceType::Oxygen);
+    fixture.advance(Duration::from_secs(30));
+    fixture.generate(1, BasicResourceType::Oxygen);
+    fixture.advance(Duration::from_secs(40));
+    fixture.generate(1, BasicResourceType::Carbon);
+
+    let snapshot = fixture.stats.snapshot();
+    let heatmap = snapshot.demand_heatmap().unwrap();
+    assert_eq!(heatmap.rows[0], [2, 0, 0, 0]);
+    assert_eq!(
+        heatmap.hottest(Duration::from_secs(70)),
+        Some(BasicResourceType::Carbon)
+    );
+}
+
+/// **Scenario:** FairShare planet pricing Silicon at 4; explorer 1 gets Silicon while
+/// explorers 2 and 3 get Oxygen, then explorer 1 asks for Oxygen
+/// **Validates:**
+/// - The cost model prices generation requests per resource
+/// - The expensive resource counts against the fair share of its requester
+#[test]
+fn test_cost_model_prices_resources() {
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::FairShare)
+        .configure(|config| {
+            config.with_cost_model(
+                FixedCosts::default().with_resource(BasicResourceType::Silicon, 4.0),
+            )
+        })
+        .explorers(1..=3)
+        .charged_cells(4)
+        .build();
+
+    assert!(fixture.generate(1, BasicResourceType::Silicon).is_some());
+    for explorer_id in 2..=3 {
+        assert!(
+            fixture
+                .generate(explorer_id, BasicResourceType::Oxygen)
+                .is_some()
+        );
+    }
+
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_none(),
+        "Silicon should count four times against the fair share"
+    );
+}
+
+/// **Scenario:** Quota planet on a manual clock; an explorer uses its quota, then the
+/// clock moves past the window
+/// **Validates:**
+/// - Requests over the quota are denied while the clock stands still
+/// - The quota is available again once the window has passed, without sleeping
+#[test]
+fn test_manual_clock_steps_through_quota_window() {
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::Quota(Quota::new(
+            1,
+            Duration::from_secs(60),
+        )))
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .explorers([1])
+        .charged_cells(2)
+        .build();
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+
+    fixture.advance(Duration::from_secs(61));
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+}
+
+/// **Scenario:** Planet clock at 1000s set back to 900s, then failing, then repaired
+/// and set to 2000s, a sunray received at each step
+/// **Validates:**
+/// - The planet time stands still while the clock is behind
+/// - The planet time keeps moving forward while the clock fails
+/// - Each anomaly and the recovery are reported once
+#[test]
+fn test_clock_anomalies_are_contained_and_reported() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let (tx_events, rx_events) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(start)
+        .configure(move |config| config.with_events(tx_events))
+        .build();
+    let clock = fixture.clock.clone().expect("Manual clock");
+    let planet_time = || fixture.stats.snapshot().last_activity().unwrap();
+
+    clock.set(SystemTime::UNIX_EPOCH + Duration::from_secs(900));
+    fixture.charge(1);
+    assert_eq!(planet_time(), start);
+    assert_eq!(
+        next_event(&rx_events),
+        Some(Event::Clock(ClockAnomaly::WentBack {
+            by: Duration::from_secs(100)
+        }))
+    );
+
+    clock.fail("Time server unreachable");
+    fixture.charge(1);
+    assert!(planet_time() >= start);
+    assert!(matches!(
+        next_event(&rx_events),
+        Some(Event::Clock(ClockAnomaly::Failed { error })) if error.contains("unreachable")
+    ));
+
+    clock.repair();
+    clock.set(start + Duration::from_secs(1_000));
+    fixture.charge(1);
+    assert_eq!(planet_time(), start + Duration::from_secs(1_000));
+    assert_eq!(
+        next_event(&rx_events),
+        Some(Event::Clock(ClockAnomaly::Recovered))
+    );
+    assert!(sent_events(&rx_events).is_empty());
 }
 
 // ============================================================================
@@ -295,6 +577,26 @@ fn test_all_cells_can_be_charged() {
     }
 }
 
+/// **Scenario:** Planet with two explorers receives a burst of three sunrays, then a
+/// short dark period
+/// **Validates:** Cells charged by the burst serve three requests, the next one is
+/// denied until sunrays come back
+#[test]
+fn test_bursty_sunrays_charge_cells() {
+    let fixture = TestPlanetFixture::builder().explorers([1, 2]).build();
+    let schedule = Bursty::new(3, Duration::from_millis(1), Duration::from_millis(20));
+
+    fixture.charge_on(schedule, 3);
+    for explorer_id in [1, 2, 1] {
+        assert!(
+            fixture
+                .generate(explorer_id, BasicResourceType::Oxygen)
+                .is_some()
+        );
+    }
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_none());
+}
+
 // ============================================================================
 // Tests: Resource Generation & Energy Consumption
 // ============================================================================
@@ -441,3 +743,3940 @@ fn test_availability_query_after_charging() {
         _ => panic!("Expected AvailableEnergyCellResponse"),
     }
 }
+
+// ============================================================================
+// Tests: Statistics
+// ============================================================================
+
+/// **Scenario:** Planet with shared stats handle charges a cell, grants and denies
+/// **Validates:** Totals and the current time bucket count 1 sunray, 1 grant, 1 denial
+#[test]
+fn test_stats_record_sunrays_and_generation() {
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, tx_expl, _) =
+        setup_configured_planet(PlanetConfig::new(1).with_stats(stats.clone()));
+
+    let explorer_id = 42;
+    let rx_expl = register_explorer(explorer_id, &tx_orch, &rx_orch);
+    charge_cells(1, &tx_orch, &rx_orch);
+
+    for _ in 0..2 {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Carbon,
+            })
+            .unwrap();
+        assert!(rx_expl.recv_timeout(Duration::from_millis(200)).is_ok());
+    }
+
+    let snapshot = stats.snapshot();
+    let totals = snapshot.totals();
+    assert_eq!(totals.sunrays, 1);
+    assert_eq!(totals.grants, 1);
+    assert_eq!(totals.denials, 1);
+    let buckets: Vec<_> = snapshot.buckets().collect();
+    assert!(!buckets.is_empty());
+    assert_eq!(buckets.iter().map(|b| b.counters.grants).sum::<u64>(), 1);
+    assert!(snapshot.last_activity().is_some());
+}
+
+/// **Scenario:** Host reads the extended state of a FairShare planet after 2 sunrays
+/// **Validates:**
+/// - Charged cells are reported out-of-band
+/// - Limit mode and counters are included
+#[test]
+fn test_extended_state_reports_ai_information() {
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, _, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_request_limit(ExplorerRequestLimit::FairShare)
+            .with_stats(stats.clone()),
+    );
+
+    charge_cells(2, &tx_orch, &rx_orch);
+
+    let state = stats.extended_state();
+    assert_eq!(state.energy_cells.len(), 5);
+    assert_eq!(state.charged_cells_count, 2);
+    assert_eq!(state.limit_mode, ExplorerRequestLimit::FairShare.into());
+    assert_eq!(state.tracked_explorers, 0);
+    assert_eq!(state.counters.sunrays, 2);
+}
+
+/// **Scenario:** Unlimited planet with a FairShare shadow policy, one explorer hogging
+/// **Validates:**
+/// - Every request is granted (shadow never enforced)
+/// - Shadow denials are recorded as disagreements with the enforced policy
+#[test]
+fn test_shadow_policy_is_recorded_but_not_enforced() {
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_shadow_limit(ExplorerRequestLimit::FairShare)
+            .with_stats(stats.clone()),
+    );
+    let receivers: Vec<_> = (1..=3)
+        .map(|explorer_id| register_explorer(explorer_id, &tx_orch, &rx_orch))
+        .collect();
+
+    let requests = [2, 3, 1, 1, 1, 1, 1, 1];
+    for explorer_id in requests {
+        charge_cells(1, &tx_orch, &rx_orch);
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Hydrogen,
+            })
+            .unwrap();
+        match receivers[explorer_id as usize - 1].recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => {
+                assert!(resource.is_some(), "Shadow policy must not be enforced");
+            }
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    }
+
+    let snapshot = stats.snapshot();
+    let shadow = snapshot.shadow();
+    assert_eq!(snapshot.totals().grants, requests.len() as u64);
+    assert_eq!(shadow.grants + shadow.denials, requests.len() as u64);
+    assert!(shadow.denials > 0, "FairShare would have denied the hog");
+    assert_eq!(shadow.disagreements, shadow.denials);
+}
+
+/// **Scenario:** Planets with a FairShare shadow policy and a latency budget of 1ns,
+/// then 1s, each granting a request
+/// **Validates:**
+/// - The request is granted either way
+/// - Past the budget, the shadow policy is skipped and the skip counted
+/// - Within the budget, nothing is skipped
+#[test]
+fn test_latency_budget_skips_shadow_policy() {
+    for (budget, skipped) in [
+        (Duration::from_nanos(1), true),
+        (Duration::from_secs(1), false),
+    ] {
+        let fixture = TestPlanetFixture::builder()
+            .configure(move |config| {
+                config
+                    .with_shadow_limit(ExplorerRequestLimit::FairShare)
+                    .with_latency_budget(budget)
+            })
+            .explorers([1])
+            .charged_cells(1)
+            .build();
+        assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+
+        let stats = fixture.stats.snapshot();
+        assert_eq!(stats.shadow().grants, u64::from(!skipped));
+        assert_eq!(
+            stats.budget_skips().get(&Extra::Speculation).is_some(),
+            skipped
+        );
+        if !skipped {
+            assert!(stats.budget_skips().is_empty());
+        }
+    }
+}
+
+/// **Scenario:** Explorer 2 is in a Quota arm (1 grant per minute), explorer 1 is not
+/// **Validates:**
+/// - Arm explorers are limited by the arm policy, others by the planet-wide one
+/// - Outcomes are accounted separately per arm
+#[test]
+fn test_policy_arms_limit_and_account_separately() {
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_policy_arm(PolicyArm::new(
+                "quota",
+                ExplorerRequestLimit::Quota(Quota::new(1, Duration::from_secs(60))),
+                [2],
+            ))
+            .with_stats(stats.clone()),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+    charge_cells(5, &tx_orch, &rx_orch);
+
+    let generate = |explorer_id: u32, rx_expl: &Receiver<PlanetToExplorer>| {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Silicon,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+
+    assert!(generate(2, &rx_expl2));
+    assert!(!generate(2, &rx_expl2), "Quota arm allows 1 grant");
+    assert!(generate(1, &rx_expl1));
+    assert!(generate(1, &rx_expl1), "Default arm is unlimited");
+
+    let snapshot = stats.snapshot();
+    let arms = snapshot.arms();
+    assert_eq!(arms["quota"].grants, 1);
+    assert_eq!(arms["quota"].denials, 1);
+    assert_eq!(arms[PolicyArm::DEFAULT].grants, 2);
+    assert_eq!(arms[PolicyArm::DEFAULT].denials, 0);
+}
+
+/// **Scenario:** Explorer quota stacked with a global cap, then requests with no energy
+/// **Validates:**
+/// - The global cap vetoes a request the explorer quota allows
+/// - Denials are counted by the reason of the vetoing limit
+#[test]
+fn test_stacked_policies_report_veto_reason() {
+    let window = Duration::from_secs(60);
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_request_limit(Policy::all_of([
+                ExplorerRequestLimit::GlobalCap(Quota::new(2, window)),
+                ExplorerRequestLimit::Quota(Quota::new(2, window)),
+            ]))
+            .with_stats(stats.clone()),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+    charge_cells(3, &tx_orch, &rx_orch);
+
+    let generate = |explorer_id: u32, rx_expl: &Receiver<PlanetToExplorer>| {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+
+    assert!(generate(1, &rx_expl1));
+    assert!(generate(1, &rx_expl1));
+    assert!(!generate(2, &rx_expl2), "Global cap reached");
+
+    let snapshot = stats.snapshot();
+    assert_eq!(
+        snapshot
+            .denials_by_reason()
+            .get(&DenialReason::GlobalCapReached),
+        Some(&1)
+    );
+    assert_eq!(
+        snapshot
+            .denials_by_reason()
+            .get(&DenialReason::QuotaExceeded),
+        None
+    );
+}
+
+/// **Scenario:** Explorer exhausting its epoch budget, then the host advances the epoch
+/// **Validates:**
+/// - The budget is restored once the admin command is applied
+/// - Per-epoch statistics restart from zero in the new epoch
+#[test]
+fn test_epoch_budget_reset_by_admin_command() {
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_request_limit(ExplorerRequestLimit::EpochBudget(1))
+            .with_stats(stats.clone())
+            .with_admin(rx_admin),
+    );
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+    charge_cells(3, &tx_orch, &rx_orch);
+
+    let generate = || {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource: BasicResourceType::Hydrogen,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+
+    assert!(generate());
+    assert!(!generate(), "Epoch budget exhausted");
+    assert_eq!(
+        stats.snapshot().epoch().denials.get(&ExplorerId::new(1)),
+        Some(&1)
+    );
+
+    tx_admin.send(AdminCommand::AdvanceEpoch).unwrap();
+    assert!(generate(), "New epoch restores the budget");
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.epoch().epoch, 1);
+    assert_eq!(snapshot.epoch().grants.get(&ExplorerId::new(1)), Some(&1));
+    assert_eq!(snapshot.epoch().denials.get(&ExplorerId::new(1)), None);
+}
+
+/// **Scenario:** Planet denying every request, host assigns an explorer its own quota
+/// **Validates:** The individual quota is consulted instead of the planet-wide policy
+#[test]
+fn test_individual_quota_overrides_policy() {
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_request_limit(ExplorerRequestLimit::EpochBudget(0))
+            .with_admin(rx_admin),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+    charge_cells(3, &tx_orch, &rx_orch);
+
+    tx_admin
+        .send(AdminCommand::SetQuota {
+            explorer_id: 1,
+            quota: Quota::new(1, Duration::from_secs(60)),
+        })
+        .unwrap();
+
+    let generate = |explorer_id: u32, rx_expl: &Receiver<PlanetToExplorer>| {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Carbon,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+
+    assert!(generate(1, &rx_expl1), "Individual quota allows 1 grant");
+    assert!(!generate(1, &rx_expl1));
+    assert!(!generate(2, &rx_expl2), "Planet-wide policy still applies");
+}
+
+/// **Scenario:** Epoch budget of 1; explorers 7 and 42 request without energy, then
+/// explorer 42 requests twice once cells are charged
+/// **Validates:** Denials are counted by explorer and by reason, telling the energy
+/// shortage apart from the exhausted budget
+#[test]
+fn test_explorer_denials_split_by_reason() {
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::EpochBudget(1))
+        .explorers([7, 42])
+        .build();
+    assert!(fixture.generate(7, BasicResourceType::Oxygen).is_none());
+    assert!(fixture.generate(42, BasicResourceType::Oxygen).is_none());
+    fixture.charge(2);
+    assert!(fixture.generate(42, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(42, BasicResourceType::Oxygen).is_none());
+
+    let stats = fixture.stats.snapshot();
+    let denials = stats.explorer_denials();
+    assert_eq!(
+        denials[&ExplorerId::new(42)],
+        BTreeMap::from([
+            (DenialReason::NoEnergy, 1),
+            (DenialReason::EpochBudgetExhausted, 1)
+        ])
+    );
+    assert_eq!(
+        denials[&ExplorerId::new(7)],
+        BTreeMap::from([(DenialReason::NoEnergy, 1)])
+    );
+}
+
+/// **Scenario:** Planet recording its cell timeline; two sunrays, then explorer 3 is
+/// granted a resource
+/// **Validates:**
+/// - Each charge is recorded for its cell, the discharge with its provenance
+/// - The JSON export lists the changes in order
+/// - The interval exports pair the charge of cell 0 with its provenance, and leave cell 1
+///   open
+#[test]
+fn test_cell_timeline_records_charges_and_discharges() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(start)
+        .configure(|config| config.with_cell_timeline(16))
+        .explorers([3])
+        .charged_cells(2)
+        .build();
+    fixture.advance(Duration::from_millis(250));
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_some());
+
+    let stats = fixture.stats.snapshot();
+    let timeline = stats.cell_timeline().expect("Timeline enabled");
+    let changes: Vec<_> = timeline
+        .events()
+        .map(|event| (event.cell, event.change))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (0, CellChange::Charged),
+            (1, CellChange::Charged),
+            (
+                0,
+                CellChange::Discharged(Provenance {
+                    explorer_id: ExplorerId::new(3),
+                    resource: Product::Basic(BasicResourceType::Oxygen),
+                    source: Source::Request,
+                })
+            ),
+        ]
+    );
+    assert_eq!(
+        timeline.to_json(),
+        "{\"events\":[\
+         {\"at_ms\":1000000,\"cell\":0,\"event\":\"charged\"},\
+         {\"at_ms\":1000000,\"cell\":1,\"event\":\"charged\"},\
+         {\"at_ms\":1000250,\"cell\":0,\"event\":\"discharged\",\"explorer_id\":3,\
+         \"resource\":\"Oxygen\",\"source\":\"request\"}]}"
+    );
+    assert_eq!(
+        timeline.intervals_to_csv(),
+        "cell,charged_ms,discharged_ms,explorer_id,resource,source\n\
+         0,1000000,1000250,3,Oxygen,request\n\
+         1,1000000,,,,\n"
+    );
+    assert_eq!(
+        timeline.intervals_to_json(),
+        "{\"intervals\":[\
+         {\"cell\":0,\"charged_ms\":1000000,\"discharged_ms\":1000250,\"explorer_id\":3,\
+         \"resource\":\"Oxygen\",\"source\":\"request\"},\
+         {\"cell\":1,\"charged_ms\":1000000,\"discharged_ms\":null,\"explorer_id\":null,\
+         \"resource\":null,\"source\":null}]}"
+    );
+}
+
+/// Journal writer appending to a buffer shared with the test.
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// **Scenario:** Planet writing a journal; a sunray, then explorer 3 requests two
+/// resources
+/// **Validates:**
+/// - The header gives the cells of the planet, charged when it started
+/// - The sunray and both decisions are journaled in order, on the planet clock
+#[test]
+fn test_journal_records_sunrays_and_decisions() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let journal = SharedBuffer(buffer.clone());
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(start)
+        .configure(|config| config.with_journal(journal))
+        .explorers([3])
+        .charged_cells(1)
+        .build();
+    fixture.advance(Duration::from_millis(250));
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(3, BasicResourceType::Carbon).is_none());
+
+    let journal = Journal::read(buffer.lock().unwrap().as_slice()).unwrap();
+    assert_eq!((journal.cells, journal.charged), (5, 0));
+    let later = start + Duration::from_millis(250);
+    assert_eq!(
+        journal.entries,
+        vec![
+            JournalEntry::Sunray { at: start },
+            JournalEntry::Generation {
+                at: later,
+                explorer_id: ExplorerId::new(3),
+                resource: BasicResourceType::Oxygen,
+                denial: None,
+                receipt: Some(ReceiptId::new(1)),
+            },
+            JournalEntry::Generation {
+                at: later,
+                explorer_id: ExplorerId::new(3),
+                resource: BasicResourceType::Carbon,
+                denial: Some(DenialReason::NoEnergy),
+                receipt: None,
+            },
+        ]
+    );
+}
+
+/// **Scenario:** Planet persisting to an in-memory storage with its cell timeline
+/// recorded; explorer 3 is granted a resource, then the planet stops
+/// **Validates:**
+/// - The journal is appended to the storage while the planet runs
+/// - The cell timeline is stored when the planet stops, and the statistics with the
+///   `serde` feature
+/// - The statistics are loaded back from the storage as they were when stored
+#[test]
+fn test_storage_persists_journal_and_timeline() {
+    let storage = MemoryStorage::default();
+    let persisted = storage.clone();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_storage(storage).with_cell_timeline(8))
+        .explorers([3])
+        .charged_cells(1)
+        .build();
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_some());
+    let journal = Journal::read(persisted.get(JOURNAL).unwrap().unwrap().as_slice()).unwrap();
+    assert_eq!(journal.entries.len(), 2);
+    assert_eq!(persisted.get(CELL_TIMELINE).unwrap(), None);
+
+    fixture
+        .orchestrator
+        .send(OrchestratorToPlanet::StopPlanetAI)
+        .unwrap();
+    fixture.from_planet.recv_timeout(TIMEOUT).unwrap();
+    // The planet answers the stop before stopping the AI: kill it to wait for the AI
+    fixture
+        .orchestrator
+        .send(OrchestratorToPlanet::KillPlanet)
+        .unwrap();
+    assert_eq!(fixture.handle.join().unwrap(), Ok(()));
+    let timeline = persisted
+        .get(CELL_TIMELINE)
+        .unwrap()
+        .expect("Timeline stored");
+    let expected = fixture
+        .stats
+        .snapshot()
+        .cell_timeline()
+        .map(CellTimeline::to_json);
+    assert_eq!(String::from_utf8(timeline).ok(), expected);
+    #[cfg(feature = "serde")]
+    {
+        let mut snapshot = rustrelli::storage::StatsSnapshot::load(&persisted)
+            .unwrap()
+            .expect("Statistics stored");
+        assert_eq!(
+            snapshot.written_by.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        let mut stats = serde_json::to_value(fixture.stats.snapshot()).unwrap();
+        // The handler timings also count the stop, recorded once the snapshot is stored
+        snapshot.stats["timings"].take();
+        stats["timings"].take();
+        assert_eq!(snapshot.stats, stats);
+    }
+}
+
+/// **Scenario:** Planet persisting to an in-memory storage with its cell timeline
+/// recorded; explorer 3 is granted a resource, then an asteroid destroys the planet
+/// **Validates:**
+/// - The cell timeline is stored before the asteroid is acknowledged, without a stop
+/// - The statistics are stored too with the `serde` feature
+#[test]
+fn test_storage_persists_on_destruction() {
+    let storage = MemoryStorage::default();
+    let persisted = storage.clone();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_storage(storage).with_cell_timeline(8))
+        .explorers([3])
+        .charged_cells(1)
+        .build();
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_some());
+
+    fixture
+        .orchestrator
+        .send(OrchestratorToPlanet::Asteroid(Asteroid::default()))
+        .unwrap();
+    assert!(matches!(
+        fixture.from_planet.recv_timeout(TIMEOUT),
+        Ok(PlanetToOrchestrator::AsteroidAck { rocket: None, .. })
+    ));
+    let timeline = persisted
+        .get(CELL_TIMELINE)
+        .unwrap()
+        .expect("Timeline stored");
+    let expected = fixture
+        .stats
+        .snapshot()
+        .cell_timeline()
+        .map(CellTimeline::to_json);
+    assert_eq!(String::from_utf8(timeline).ok(), expected);
+    #[cfg(feature = "serde")]
+    assert!(
+        rustrelli::storage::StatsSnapshot::load(&persisted)
+            .unwrap()
+            .is_some()
+    );
+
+    fixture
+        .orchestrator
+        .send(OrchestratorToPlanet::KillPlanet)
+        .unwrap();
+    assert_eq!(fixture.handle.join().unwrap(), Ok(()));
+}
+
+/// **Scenario:** Planet with load events; a sunray, three requests, then another sunray
+/// **Validates:**
+/// - A load event follows each sunray
+/// - It reports the requests per charged cell over the window
+#[test]
+fn test_load_events_after_sunrays() {
+    let (tx_events, rx_events) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_events(tx_events)
+            .with_load_events(Duration::from_secs(60)),
+    );
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+    let next_load = || match next_event(&rx_events) {
+        Some(Event::Load(load)) => load,
+        other => panic!("Expected a load event, got {:?}", other),
+    };
+
+    charge_cells(1, &tx_orch, &rx_orch);
+    assert_eq!(next_load().factor(), 0.0);
+
+    for _ in 0..3 {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+        rx_expl.recv_timeout(Duration::from_millis(200)).unwrap();
+    }
+    charge_cells(1, &tx_orch, &rx_orch);
+    let load = next_load();
+    assert_eq!((load.requests, load.sunrays), (3, 2));
+    assert_eq!(load.factor(), 1.5);
+}
+
+/// **Scenario:** Planet with load events filtered out, then let through at runtime
+/// **Validates:**
+/// - Events below the minimum severity aren't sent over the channel
+/// - The host adjusts the filter of the running planet through its clone
+#[test]
+fn test_event_filter_mutes_load_events_at_runtime() {
+    let (tx_events, rx_events) = unbounded();
+    let filter = EventFilter::new(Severity::Warn);
+    let (tx_orch, rx_orch, _, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_events(tx_events)
+            .with_event_filter(filter.clone())
+            .with_load_events(Duration::from_secs(60)),
+    );
+
+    charge_cells(1, &tx_orch, &rx_orch);
+    assert!(rx_events.try_recv().is_err(), "Load events are muted");
+
+    filter.set_min_severity(Severity::Debug);
+    charge_cells(1, &tx_orch, &rx_orch);
+    match rx_events.recv_timeout(Duration::from_millis(200)) {
+        Ok(event @ Event::Load(_)) => assert_eq!(event.severity(), Severity::Debug),
+        other => panic!("Expected a load event, got {:?}", other),
+    }
+}
+
+/// **Scenario:** Planet sending a load event per sunray over a channel of 4 events
+/// the host doesn't read, then drains by one event, then entirely
+/// **Validates:**
+/// - Once the channel is full, one event in 3 is sent, the others are counted as
+///   dropped
+/// - Events are sent again as usual once the host drained half of the channel
+#[test]
+fn test_saturated_events_channel_is_sampled() {
+    let (tx_events, rx_events) = bounded(4);
+    let filter = EventFilter::default();
+    let (tx_orch, rx_orch, _, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_events(tx_events)
+            .with_event_filter(filter.clone())
+            .with_event_sampling(3)
+            .with_load_events(Duration::from_secs(60)),
+    );
+
+    while filter.dropped() == 0 {
+        charge_cells(1, &tx_orch, &rx_orch);
+    }
+    assert!(filter.is_sampling());
+    rx_events.recv().unwrap();
+    charge_cells(3, &tx_orch, &rx_orch);
+    assert_eq!(filter.dropped(), 3, "Two load events sampled out");
+    assert_eq!(rx_events.len(), 4, "One load event sent");
+
+    let drained = rx_events.try_iter().count();
+    charge_cells(1, &tx_orch, &rx_orch);
+    assert_eq!(drained, 4);
+    assert!(!filter.is_sampling());
+    assert!(matches!(rx_events.try_recv(), Ok(Event::Load(_))));
+    assert_eq!(filter.dropped(), 3);
+}
+
+/// **Scenario:** Planet with load events but no events channel, on a host using `log`
+/// **Validates:** The load event after a sunray is logged as a debug record
+#[cfg(all(feature = "log", not(feature = "tracing")))]
+#[test]
+fn test_events_are_logged_without_channel() {
+    use std::sync::Mutex;
+
+    struct CapturingLogger(Mutex<Vec<(log::Level, String)>>);
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == "rustrelli::events"
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                let mut records = self.0.lock().unwrap();
+                records.push((record.level(), record.args().to_string()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger(Mutex::new(Vec::new()));
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let (tx_orch, rx_orch, _, _) =
+        setup_configured_planet(PlanetConfig::new(1).with_load_events(Duration::from_secs(60)));
+    charge_cells(1, &tx_orch, &rx_orch);
+
+    let records = LOGGER.0.lock().unwrap();
+    assert!(
+        records
+            .iter()
+            .any(|(level, message)| *level == log::Level::Debug && message.starts_with("Load(")),
+        "{:?}",
+        records
+    );
+}
+
+/// **Scenario:** Streak alert after 3 denials, an explorer is denied 4 requests in a
+/// row for lack of energy, then granted one
+/// **Validates:**
+/// - The streak is reported once, when it reaches the threshold
+/// - The statistics keep the longest streak after the grant
+#[test]
+fn test_denial_streaks() {
+    let (tx_events, rx_events) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_streak_alert(3).with_events(tx_events))
+        .explorers([1])
+        .build();
+
+    for _ in 0..4 {
+        assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    }
+    fixture.charge(1);
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+
+    assert_eq!(
+        sent_events(&rx_events),
+        [Event::DenialStreak {
+            explorer_id: ExplorerId::new(1),
+            streak: 3,
+        }]
+    );
+    assert_eq!(
+        fixture.stats.snapshot().streaks()[&ExplorerId::new(1)],
+        Streak { current: 0, max: 4 }
+    );
+}
+
+/// **Scenario:** Onboarding events enabled with backoff; explorer 1, tagged, registers,
+/// is denied a resource, then registers again
+/// **Validates:**
+/// - The first registration reports the rules of engagement, without history
+/// - Registering again reports the history of the explorer
+#[test]
+fn test_onboarding_events() {
+    let (tx_events, rx_events) = unbounded();
+    let mut fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_onboarding_events()
+                .with_backoff(BackoffConfig::new([Duration::from_millis(1)]))
+                .with_tag(1, Tag::new("team", "red"))
+                .with_events(tx_events)
+        })
+        .explorers([1])
+        .build();
+    let onboarding = Onboarding {
+        explorer_id: ExplorerId::new(1),
+        policy: format!("{:?}", Policy::from(ExplorerRequestLimit::None)),
+        arm: None,
+        tags: vec![Tag::new("team", "red")],
+        retry_after: Some(Duration::from_millis(1)),
+        batch_size: None,
+        claim_window: None,
+        energy_poll_interval: None,
+        history: None,
+    };
+    assert_eq!(
+        sent_events(&rx_events),
+        [Event::Onboarding(onboarding.clone())]
+    );
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    fixture.register(1);
+    let history = History {
+        epoch_denials: 1,
+        streak: 1,
+        ..History::default()
+    };
+    assert_eq!(
+        sent_events(&rx_events),
+        [
+            Event::Onboarding(Onboarding {
+                history: Some(history),
+                ..onboarding
+            }),
+            Event::Registration {
+                explorer_id: ExplorerId::new(1),
+                transition: Transition::Duplicate,
+            },
+        ]
+    );
+}
+
+/// **Scenario:** Profiled planet receives a sunray and a generation request
+/// **Validates:** Each handler that ran has its own timing histogram
+#[cfg(feature = "profiling")]
+#[test]
+fn test_profiled_handlers_are_timed() {
+    use rustrelli::profiling::Handler;
+
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, tx_expl, _) =
+        setup_configured_planet(PlanetConfig::new(1).with_stats(stats.clone()));
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+    charge_cells(1, &tx_orch, &rx_orch);
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 1,
+            resource: BasicResourceType::Oxygen,
+        })
+        .unwrap();
+    rx_expl.recv_timeout(Duration::from_millis(200)).unwrap();
+
+    let snapshot = stats.snapshot();
+    let timings = snapshot.timings();
+    for handler in [Handler::Start, Handler::Sunray, Handler::GenerateResource] {
+        assert_eq!(timings[&handler].count(), 1, "{:?}", handler);
+    }
+    assert!(!timings.contains_key(&Handler::Asteroid));
+}
+
+// ============================================================================
+// Tests: Deferred Fulfillment
+// ============================================================================
+
+/// **Scenario:** Two explorers request a resource while no cell is charged, the
+/// first one already got a resource, then a single sunray arrives
+/// **Validates:**
+/// - Requests are answered right away with no resource
+/// - The pending request of the least served explorer is fulfilled first,
+///   regardless of arrival order
+#[test]
+fn test_deferred_fulfillment_follows_policy_priority() {
+    let (tx_fulfill, rx_fulfill) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_request_limit(ExplorerRequestLimit::FairShare)
+            .with_deferred_fulfillment(4, tx_fulfill),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+
+    let generate = |explorer_id: u32, rx_expl: &Receiver<PlanetToExplorer>| {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+
+    charge_cells(1, &tx_orch, &rx_orch);
+    assert!(generate(1, &rx_expl1));
+
+    assert!(!generate(1, &rx_expl1), "No charged cell");
+    assert!(!generate(2, &rx_expl2), "No charged cell");
+    assert!(rx_fulfill.try_recv().is_err());
+
+    charge_cells(1, &tx_orch, &rx_orch);
+    let fulfillment = rx_fulfill
+        .recv_timeout(Duration::from_millis(200))
+        .expect("Pending request should be fulfilled");
+    assert_eq!(fulfillment.explorer_id, 2);
+    assert_eq!(fulfillment.resource.get_type(), BasicResourceType::Oxygen);
+    assert!(rx_fulfill.try_recv().is_err(), "Only one cell was charged");
+}
+
+/// **Scenario:** Host reserves the next cell for an explorer, another explorer requests
+/// the only charged cell, then both have pending requests when a cell is charged
+/// **Validates:**
+/// - The other explorer is denied with the `Reserved` reason
+/// - The reservation holder is served first
+#[test]
+fn test_reservation_denies_others_and_serves_holder_first() {
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_fulfill, rx_fulfill) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_admin(rx_admin)
+            .with_deferred_fulfillment(4, tx_fulfill),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+    charge_cells(1, &tx_orch, &rx_orch);
+    tx_admin
+        .send(AdminCommand::Reserve { explorer_id: 1 })
+        .unwrap();
+
+    let generate = |explorer_id: u32, rx_expl: &Receiver<PlanetToExplorer>| {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Silicon,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+
+    assert!(!generate(2, &rx_expl2), "The only charged cell is reserved");
+    assert_eq!(
+        stats
+            .snapshot()
+            .denials_by_reason()
+            .get(&DenialReason::Reserved),
+        Some(&1)
+    );
+
+    // Explorer 1 uses its reservation, then both wait for the next cell
+    assert!(generate(1, &rx_expl1));
+    tx_admin
+        .send(AdminCommand::Reserve { explorer_id: 1 })
+        .unwrap();
+    assert!(!generate(2, &rx_expl2), "No charged cell");
+    assert!(!generate(1, &rx_expl1), "No charged cell");
+
+    charge_cells(1, &tx_orch, &rx_orch);
+    let fulfillment = rx_fulfill
+        .recv_timeout(Duration::from_millis(200))
+        .expect("Pending request should be fulfilled");
+    assert_eq!(fulfillment.explorer_id, 1);
+}
+
+/// **Scenario:** With coalescing enabled, an explorer repeats the same request while
+/// no cell is charged, then asks for another resource
+/// **Validates:**
+/// - Duplicates don't take new slots in the queue
+/// - Requests for different resources are still queued separately
+#[test]
+fn test_pending_coalescing() {
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_fulfill, _rx_fulfill) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_deferred_fulfillment(2, tx_fulfill)
+            .with_pending_coalescing(true),
+    );
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+
+    for resource in [
+        BasicResourceType::Carbon,
+        BasicResourceType::Carbon,
+        BasicResourceType::Carbon,
+        BasicResourceType::Hydrogen,
+    ] {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource,
+            })
+            .unwrap();
+        let _ = rx_expl.recv_timeout(Duration::from_millis(200));
+    }
+
+    let pending = stats.snapshot().pending();
+    assert_eq!(pending.queued, 2);
+    assert_eq!(pending.coalesced, 2);
+    assert_eq!(pending.dropped, 0);
+}
+
+/// **Scenario:** Queue of 3 requests, at most 2 per explorer dropping the oldest, an
+/// explorer sends 3 requests while no cell is charged, then another explorer 2, then a
+/// third explorer 1
+/// **Validates:**
+/// - The first explorer never holds more than 2 slots
+/// - Once the queue is full, the second explorer only replaces its own request
+/// - The third explorer, with nothing queued, can't evict the others: its request is
+///   dropped
+/// - The occupancy of each explorer is reported
+#[test]
+fn test_pending_explorer_cap() {
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_fulfill, _rx_fulfill) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_deferred_fulfillment(3, tx_fulfill)
+            .with_pending_explorer_cap(2)
+            .with_pending_overflow(Overflow::DropOldest),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+    let rx_expl3 = register_explorer(3, &tx_orch, &rx_orch);
+
+    for (explorer_id, rx_expl) in [
+        (1, &rx_expl1),
+        (1, &rx_expl1),
+        (1, &rx_expl1),
+        (2, &rx_expl2),
+        (2, &rx_expl2),
+        (3, &rx_expl3),
+    ] {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+        let _ = rx_expl.recv_timeout(Duration::from_millis(200));
+    }
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.pending().queued, 5);
+    assert_eq!(snapshot.pending().evicted, 2);
+    assert_eq!(snapshot.pending().dropped, 1);
+    assert_eq!(
+        snapshot.pending_occupancy().iter().collect::<Vec<_>>(),
+        [(&ExplorerId::new(1), &2), (&ExplorerId::new(2), &1)]
+    );
+}
+
+/// Grants and fulfillments of a planet handling a random sequence of sunrays and
+/// contended requests of 4 explorers, drawn from `seed`.
+fn contended_outcomes(seed: u64) -> (Vec<bool>, Vec<ExplorerId>) {
+    let (tx_fulfill, rx_fulfill) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::FairShare)
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .configure(move |config| {
+            config
+                .with_deferred_fulfillment(8, tx_fulfill)
+                .with_batch_grants(BatchConfig::new(2, Duration::from_millis(50)))
+                .with_tie_break_seed(seed)
+        })
+        .explorers(1..=4)
+        .build();
+
+    // Linear congruential draws, the same on every run of the seed
+    let mut state = seed;
+    let mut draw = |bound: u64| {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) % bound
+    };
+    let mut grants = Vec::new();
+    for _ in 0..60 {
+        fixture.advance(Duration::from_millis(draw(20)));
+        match draw(5) {
+            0 => fixture.charge(1),
+            explorer_id => grants.push(
+                fixture
+                    .generate(explorer_id as u32, BasicResourceType::Oxygen)
+                    .is_some(),
+            ),
+        }
+    }
+    let fulfilled = rx_fulfill
+        .try_iter()
+        .map(|fulfillment| fulfillment.explorer_id)
+        .collect();
+    (grants, fulfilled)
+}
+
+/// **Scenario:** Random sequences of sunrays and requests of 4 explorers under fair
+/// share, with batches and deferred fulfillment, each sequence handled twice by a new
+/// planet
+/// **Validates:**
+/// - The same requests are granted, and the pending ones served in the same order,
+///   whatever the iteration order of the maps of each planet
+/// - The sequences do contend for cells
+#[test]
+fn test_contended_outcomes_are_reproducible() {
+    let mut contended = false;
+    for seed in 0..5 {
+        let outcomes = contended_outcomes(seed);
+        assert_eq!(outcomes, contended_outcomes(seed), "Seed {seed}");
+        contended |= outcomes.0.contains(&false) && !outcomes.1.is_empty();
+    }
+    assert!(contended);
+}
+
+/// **Scenario:** Pending timeout of 10 seconds, an explorer requests while no cell is
+/// charged, another one 30 seconds later, then a cell is charged
+/// **Validates:**
+/// - The request of the first explorer expires, reported as a queue timeout
+/// - The cell goes to the second explorer
+/// - The time spent in the queue counts in the satisfaction of both explorers
+#[test]
+fn test_pending_timeout() {
+    let (tx_fulfill, rx_fulfill) = unbounded();
+    let (tx_events, rx_events) = unbounded();
+    let mut fixture = TestPlanetFixture::builder()
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .configure(move |config| {
+            config
+                .with_deferred_fulfillment(2, tx_fulfill)
+                .with_pending_timeout(Duration::from_secs(10))
+                .with_events(tx_events)
+        })
+        .build();
+    fixture.register(1);
+    fixture.register(2);
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    fixture.advance(Duration::from_secs(30));
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_none());
+    assert_eq!(
+        next_event(&rx_events),
+        Some(Event::QueueTimeout {
+            explorer_id: ExplorerId::new(1),
+            waited: Duration::from_secs(30),
+        })
+    );
+
+    fixture.charge(1);
+    let fulfillment = rx_fulfill
+        .recv_timeout(Duration::from_millis(200))
+        .expect("Pending request should be fulfilled");
+    assert_eq!(fulfillment.explorer_id, 2);
+    let snapshot = fixture.stats.snapshot();
+    let pending = snapshot.pending();
+    assert_eq!(pending.queued, 2);
+    assert_eq!(pending.expired, 1);
+    let satisfaction = snapshot.satisfaction();
+    assert_eq!(
+        satisfaction[&ExplorerId::new(1)].mean_residency(),
+        Duration::from_secs(30)
+    );
+    assert_eq!(satisfaction[&ExplorerId::new(1)].grant_ratio(), Some(0.0));
+    assert_eq!(satisfaction[&ExplorerId::new(2)].dequeued, 1);
+    assert_eq!(satisfaction[&ExplorerId::new(2)].score(), Some(0.5));
+}
+
+/// **Scenario:** Batch grants of up to 2 cells, an explorer starts a series while
+/// 3 cells are charged, a competitor requests in between
+/// **Validates:**
+/// - The batch cells are kept for the rest of the series
+/// - The competitor only gets the cell outside the batch
+#[test]
+fn test_batch_grants_are_not_leaked_to_competitors() {
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1).with_batch_grants(BatchConfig::new(2, Duration::from_secs(5))),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+    charge_cells(3, &tx_orch, &rx_orch);
+
+    let generate = |explorer_id: u32, rx_expl: &Receiver<PlanetToExplorer>| {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+
+    assert!(generate(1, &rx_expl1), "Batch of 2 granted");
+    assert!(generate(2, &rx_expl2), "One cell is outside the batch");
+    assert!(!generate(2, &rx_expl2), "Last cell belongs to the batch");
+    assert!(generate(1, &rx_expl1), "Series completed");
+}
+
+/// **Scenario:** Batch grants of up to 2 cells recorded in the cell timeline, an
+/// explorer requests twice while 2 cells are charged
+/// **Validates:** The first cell is tagged as granted on request, the second as drawn on
+/// the batch, both with the explorer and resource
+#[test]
+fn test_cell_timeline_records_batch_provenance() {
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_batch_grants(BatchConfig::new(2, Duration::from_secs(5)))
+                .with_cell_timeline(8)
+        })
+        .explorers([1])
+        .charged_cells(2)
+        .build();
+    assert!(fixture.generate(1, BasicResourceType::Carbon).is_some());
+    assert!(fixture.generate(1, BasicResourceType::Carbon).is_some());
+
+    let stats = fixture.stats.snapshot();
+    let sources: Vec<_> = stats
+        .cell_timeline()
+        .expect("Timeline enabled")
+        .intervals()
+        .into_iter()
+        .map(|interval| interval.provenance.expect("Cell discharged"))
+        .collect();
+    assert_eq!(
+        sources,
+        [Source::Request, Source::Batch].map(|source| Provenance {
+            explorer_id: ExplorerId::new(1),
+            resource: Product::Basic(BasicResourceType::Carbon),
+            source,
+        })
+    );
+}
+
+/// **Scenario:** Two-phase grants confirmed within a second, a single cell charged
+/// twice: the first time the explorer confirms its offer, the second time it
+/// disconnects and a competitor requests after the window
+/// **Validates:**
+/// - The last cell is only offered, and reserved until the explorer confirms it
+/// - An offer not confirmed in time releases the cell
+#[test]
+fn test_two_phase_grants() {
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .configure(|config| config.with_claims(ClaimConfig::new(Duration::from_secs(1))))
+        .explorers([1, 2])
+        .charged_cells(1)
+        .build();
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_none());
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_some(),
+        "Claim confirmed"
+    );
+
+    fixture.charge(1);
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    fixture.advance(Duration::from_secs(2));
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_none());
+    assert!(
+        fixture.generate(2, BasicResourceType::Oxygen).is_some(),
+        "Released cell claimed by the competitor"
+    );
+
+    let snapshot = fixture.stats.snapshot();
+    let claims = snapshot.claims();
+    assert_eq!(
+        (claims.offered, claims.confirmed, claims.expired),
+        (3, 2, 1)
+    );
+    assert_eq!(
+        snapshot.explorer_denials()[&ExplorerId::new(2)],
+        BTreeMap::from([(DenialReason::Reserved, 1), (DenialReason::Offered, 1)])
+    );
+}
+
+/// **Scenario:** Two-phase grants and a rescue explorer preempting, a single cell
+/// charged: explorer 1 is offered the cell, then explorer 2 and the rescue explorer 3
+/// request it before explorer 1 confirms
+/// **Validates:**
+/// - Other explorers are denied the offered cell
+/// - The rescue explorer preempts it, withdrawing the offer, and the preemption is
+///   counted
+#[test]
+fn test_critical_explorer_preempts_offer() {
+    let rescue = Tag::new("role", "rescue");
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_claims(ClaimConfig::new(Duration::from_secs(60)))
+                .with_preemption(rescue.clone())
+                .with_tag(3, rescue)
+        })
+        .explorers(1..=3)
+        .charged_cells(1)
+        .build();
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_none());
+    assert!(
+        fixture.generate(3, BasicResourceType::Oxygen).is_some(),
+        "Offered cell preempted"
+    );
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_none(),
+        "Offer withdrawn"
+    );
+
+    let snapshot = fixture.stats.snapshot();
+    assert_eq!(snapshot.preemptions(), 1);
+    assert_eq!(
+        snapshot.explorer_denials()[&ExplorerId::new(2)],
+        BTreeMap::from([(DenialReason::Reserved, 1)])
+    );
+}
+
+/// **Scenario:** With backoff enforced, an explorer denied for lack of energy retries
+/// right after a cell is charged, then once its advised delay elapsed
+/// **Validates:**
+/// - The early retry is denied without discharging the cell
+/// - The retry after the advised time is granted
+#[test]
+fn test_backoff_denies_early_retries() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config.with_backoff(BackoffConfig::exponential(
+                Duration::from_secs(1),
+                Duration::from_secs(8),
+            ))
+        })
+        .manual_clock(start)
+        .explorers([1])
+        .build();
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    fixture.charge(1);
+    fixture.advance(Duration::from_millis(500));
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    fixture.advance(Duration::from_secs(2));
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+
+    let denials = fixture.stats.snapshot().denials_by_reason().clone();
+    assert_eq!(
+        denials,
+        BTreeMap::from([
+            (DenialReason::NoEnergy, 1),
+            (DenialReason::RetriedTooEarly, 1)
+        ])
+    );
+}
+
+/// **Scenario:** With backoff and misconduct scoring alerting at 2, an explorer
+/// denied for lack of energy retries 5ms later, then 500ms later
+/// **Validates:**
+/// - Early retries and bursts add their weights to the misconduct score
+/// - The score reaching the alert threshold is reported once
+#[test]
+fn test_misconduct_is_scored() {
+    let (tx_events, rx_events) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_backoff(BackoffConfig::new([Duration::from_secs(1)]))
+                .with_misconduct(MisconductConfig::default().with_alert(2.0))
+                .with_events(tx_events)
+        })
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1000))
+        .explorers([1])
+        .build();
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    fixture.advance(Duration::from_millis(5));
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    fixture.advance(Duration::from_millis(500));
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+
+    assert_eq!(
+        fixture.stats.snapshot().misconduct()[&ExplorerId::new(1)],
+        Misconduct {
+            spoofs: 0,
+            early_retries: 2,
+            bursts: 1,
+            undeliverable: 0,
+            score: 2.5,
+        }
+    );
+    let alerts: Vec<_> = sent_events(&rx_events)
+        .into_iter()
+        .filter(|event| matches!(event, Event::Misconduct { .. }))
+        .collect();
+    assert_eq!(
+        alerts,
+        [Event::Misconduct {
+            explorer_id: ExplorerId::new(1),
+            signal: Signal::EarlyRetry,
+        }]
+    );
+}
+
+/// **Scenario:** Planet pre-stocked with 2 oxygen and 1 carbon, asked for 4 oxygen
+/// without any sunray
+/// **Validates:**
+/// - The seeded cells are charged before the first message, without counting sunrays
+/// - Grants are served from the stock of their resource while it lasts, and then from
+///   the stock of the seeded cell they discharge
+/// - Seeded cells show in the cell timeline
+#[test]
+fn test_stockpile_is_seeded() {
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_stockpile([
+                    (BasicResourceType::Oxygen, 2),
+                    (BasicResourceType::Carbon, 1),
+                ])
+                .with_cell_timeline(10)
+        })
+        .explorers([1])
+        .build();
+
+    for _ in 0..3 {
+        assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    }
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_none(),
+        "Only the seeded cells were charged"
+    );
+
+    let stats = fixture.stats.snapshot();
+    assert_eq!(stats.totals().sunrays, 0);
+    let stockpile = stats.stockpile();
+    assert_eq!(stockpile.seeded[&BasicResourceType::Oxygen], 2);
+    assert_eq!(stockpile.served[&BasicResourceType::Oxygen], 2);
+    assert_eq!(stockpile.served[&BasicResourceType::Carbon], 1);
+    assert_eq!(stockpile.remaining_total(), 0);
+    let seeded = stats
+        .cell_timeline()
+        .unwrap()
+        .events()
+        .filter(|event| event.change == CellChange::Seeded)
+        .count();
+    assert_eq!(seeded, 3);
+}
+
+/// **Scenario:** Planets pre-stocked with 2 oxygen, one for each stockpile visibility,
+/// receive a sunray; an explorer asks for the available energy
+/// **Validates:**
+/// - Explorers see the 2 unserved seeded cells only if the stockpile is visible
+/// - Monitoring sees them unless the stockpile is hidden
+/// - The orchestrator always sees the 3 charged cells
+#[test]
+fn test_stockpile_visibility() {
+    for (visibility, to_explorer, to_monitoring) in [
+        (StockpileVisibility::Visible, 3, 3),
+        (StockpileVisibility::MonitoringOnly, 1, 3),
+        (StockpileVisibility::Hidden, 1, 1),
+    ] {
+        let fixture = TestPlanetFixture::builder()
+            .configure(move |config| {
+                config
+                    .with_stockpile([(BasicResourceType::Oxygen, 2)])
+                    .with_stockpile_visibility(visibility)
+            })
+            .explorers([1])
+            .charged_cells(1)
+            .build();
+
+        assert!(matches!(
+            fixture.request(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 }),
+            PlanetToExplorer::AvailableEnergyCellResponse { available_cells }
+                if available_cells == to_explorer
+        ));
+        let state = fixture.stats.snapshot().extended_state();
+        assert_eq!(state.charged_cells_count, to_monitoring, "{visibility:?}");
+        assert_eq!(
+            state
+                .energy_cells
+                .iter()
+                .filter(|charged| **charged)
+                .count(),
+            to_monitoring
+        );
+        fixture
+            .orchestrator
+            .send(OrchestratorToPlanet::InternalStateRequest)
+            .unwrap();
+        assert!(matches!(
+            fixture.from_planet.recv_timeout(TIMEOUT),
+            Ok(PlanetToOrchestrator::InternalStateResponse { planet_state, .. })
+                if planet_state.charged_cells_count == 3
+        ));
+    }
+}
+
+/// **Scenario:** Planets pre-stocked with 1 oxygen, the stockpile hidden from explorers,
+/// grant carbon from the seeded cell, then receive a sunray
+/// **Validates:**
+/// - The seeded cell counts as served, though its resource wasn't granted
+/// - Explorers and monitoring see the cell charged by the sunray
+#[test]
+fn test_stockpile_served_by_other_resource() {
+    for visibility in [
+        StockpileVisibility::MonitoringOnly,
+        StockpileVisibility::Hidden,
+    ] {
+        let fixture = TestPlanetFixture::builder()
+            .configure(move |config| {
+                config
+                    .with_stockpile([(BasicResourceType::Oxygen, 1)])
+                    .with_stockpile_visibility(visibility)
+            })
+            .explorers([1])
+            .build();
+
+        assert!(fixture.generate(1, BasicResourceType::Carbon).is_some());
+        fixture.charge(1);
+
+        let stats = fixture.stats.snapshot();
+        assert_eq!(stats.stockpile().remaining_total(), 0, "{visibility:?}");
+        assert!(matches!(
+            fixture.request(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 }),
+            PlanetToExplorer::AvailableEnergyCellResponse { available_cells: 1 }
+        ));
+        assert_eq!(
+            fixture
+                .stats
+                .snapshot()
+                .extended_state()
+                .charged_cells_count,
+            1
+        );
+    }
+}
+
+/// **Scenario:** Planet in compliance mode configured with a stockpile of 2 oxygen,
+/// asked for oxygen without any sunray
+/// **Validates:**
+/// - The stockpile isn't seeded: the request is denied
+/// - The info attests compliance, listing the stockpile as disabled
+#[test]
+fn test_compliance_disables_stockpile() {
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_compliance()
+                .with_stockpile([(BasicResourceType::Oxygen, 2)])
+        })
+        .explorers([1])
+        .build();
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    let stats = fixture.stats.snapshot();
+    assert!(stats.stockpile().seeded.is_empty());
+    assert_eq!(
+        stats.info().and_then(|info| info.compliance.clone()),
+        Some(Attestation {
+            disabled: vec![RuleBending::Stockpile],
+        })
+    );
+}
+
+/// **Scenario:** Planet configured with a stockpile of 6 oxygen, more than its 5 cells,
+/// and a cell timeline
+/// **Validates:**
+/// - The planet isn't created, the stockpile reported as the problem
+/// - The statistics shared with the host are left untouched: no info, no timeline
+#[test]
+fn test_rejected_configuration_leaves_stats_untouched() {
+    let stats = StatsHandle::default();
+    let (tx_orch_to_planet, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, _rx_planet_to_orch) = unbounded();
+    let (_tx_expl_to_planet, rx_expl_to_planet) = unbounded();
+    let planets = create_planets(
+        vec![
+            PlanetConfig::new(1)
+                .with_stats(stats.clone())
+                .with_cell_timeline(16)
+                .with_stockpile([(BasicResourceType::Oxygen, 6)]),
+        ],
+        vec![PlanetChannels {
+            from_orchestrator: rx_orch_to_planet,
+            to_orchestrator: tx_planet_to_orch,
+            from_explorers: rx_expl_to_planet,
+            control: tx_orch_to_planet,
+        }],
+    );
+
+    assert!(matches!(
+        &planets[0],
+        Err(RustrelliError::Config(message)) if message.contains("Stockpile")
+    ));
+    let snapshot = stats.snapshot();
+    assert!(snapshot.info().is_none());
+    assert!(snapshot.cell_timeline().is_none());
+}
+
+/// **Scenario:** A sunray reaches the planet every 2 seconds of planet time
+/// **Validates:** The statistics estimate half a sunray per second
+#[test]
+fn test_sunray_rate_is_estimated() {
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1000))
+        .build();
+    for _ in 0..5 {
+        fixture.charge(1);
+        fixture.advance(Duration::from_secs(2));
+    }
+
+    let rate = fixture.stats.sunray_rate();
+    assert_eq!(rate.count, 5);
+    assert!((rate.per_second() - 0.5).abs() < 1e-9);
+    assert!((rate.forecast(Duration::from_secs(60)) - 30.0).abs() < 1e-6);
+}
+
+/// **Scenario:** With a 10 second warm-up and a quota of 1 resource every 5 seconds, an
+/// explorer requests twice right after the start, then twice after the warm-up
+/// **Validates:**
+/// - The quota isn't enforced during the warm-up
+/// - The quota is enforced after the warm-up
+#[test]
+fn test_warm_up_grants_freely() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::Quota(Quota::new(
+            1,
+            Duration::from_secs(5),
+        )))
+        .configure(|config| config.with_warm_up(Duration::from_secs(10)))
+        .manual_clock(start)
+        .explorers([1])
+        .charged_cells(4)
+        .build();
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    fixture.advance(Duration::from_secs(10));
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+}
+
+/// **Scenario:** Explorer 1 leases 2 of the 3 charged cells for 10 seconds, explorer 2
+/// also asks for a lease, then both request resources before and after the lease expires
+/// **Validates:**
+/// - A single lease is active at a time
+/// - Leased cells are only generated for the holder
+/// - Unclaimed leased cells return to the common pool when the lease expires
+#[test]
+fn test_lease_reserves_cells_until_it_expires() {
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_events, rx_events) = unbounded();
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_admin(rx_admin)
+                .with_events(tx_events)
+                .with_leases(LeaseConfig::new(2, Duration::from_secs(10)))
+        })
+        .manual_clock(start)
+        .explorers([1, 2])
+        .charged_cells(3)
+        .build();
+
+    for explorer_id in [1, 2] {
+        tx_admin
+            .send(AdminCommand::Lease {
+                explorer_id,
+                resource: BasicResourceType::Oxygen,
+                cells: 5,
+                duration: Duration::from_secs(60),
+            })
+            .unwrap();
+    }
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_some());
+    assert_eq!(
+        sent_events(&rx_events),
+        [
+            Event::LeaseGranted {
+                explorer_id: ExplorerId::new(1),
+                cells: 2,
+                expires: start + Duration::from_secs(10),
+            },
+            Event::LeaseRefused {
+                explorer_id: ExplorerId::new(2),
+                refusal: LeaseRefusal::Active {
+                    holder: ExplorerId::new(1)
+                },
+            },
+        ]
+    );
+    assert!(
+        fixture.generate(2, BasicResourceType::Oxygen).is_none(),
+        "The remaining cells are leased"
+    );
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+
+    fixture.advance(Duration::from_secs(10));
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_some());
+    assert_eq!(
+        sent_events(&rx_events),
+        [Event::LeaseEnded {
+            explorer_id: ExplorerId::new(1),
+            unclaimed: 1,
+        }]
+    );
+}
+
+// ============================================================================
+// Tests: Backpressure
+// ============================================================================
+
+/// **Scenario:** Explorer with a watched channel of capacity 1 doesn't read its
+/// responses while requesting twice
+/// **Validates:**
+/// - The second request is refused without discharging a cell
+/// - A diagnostic event is emitted
+#[test]
+fn test_full_explorer_channel_does_not_consume_cells() {
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_events, rx_events) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_admin(rx_admin)
+            .with_events(tx_events),
+    );
+    let (tx_planet_to_expl, rx_expl) = bounded(1);
+    tx_orch
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 1,
+            new_sender: tx_planet_to_expl.clone(),
+        })
+        .unwrap();
+    let _ = rx_orch.recv_timeout(Duration::from_millis(200));
+    tx_admin
+        .send(AdminCommand::WatchExplorer {
+            explorer_id: 1,
+            sender: tx_planet_to_expl,
+        })
+        .unwrap();
+    charge_cells(2, &tx_orch, &rx_orch);
+
+    for _ in 0..2 {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource: BasicResourceType::Carbon,
+            })
+            .unwrap();
+    }
+
+    assert_eq!(
+        next_event(&rx_events),
+        Some(Event::Undeliverable {
+            explorer_id: ExplorerId::new(1),
+            cause: DeliveryFailure::ChannelFull
+        })
+    );
+    for expected in [true, false] {
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => {
+                assert_eq!(resource.is_some(), expected)
+            }
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    }
+
+    tx_orch
+        .send(OrchestratorToPlanet::InternalStateRequest)
+        .unwrap();
+    match rx_orch.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToOrchestrator::InternalStateResponse { planet_state, .. }) => {
+            assert_eq!(planet_state.charged_cells_count, 1, "Only one cell used");
+        }
+        _ => panic!("Expected InternalStateResponse"),
+    }
+}
+
+/// **Scenario:** Two pending requests are served while the host doesn't drain the
+/// fulfillments channel, which holds a single fulfillment
+/// **Validates:**
+/// - The second fulfillment is retried on the next message, then dead-lettered once
+///   the attempts are exhausted
+/// - The dead letter and the delivery counters are visible in the statistics
+#[test]
+fn test_undelivered_fulfillment_is_retried_then_dead_lettered() {
+    let stats = StatsHandle::default();
+    let (tx_fulfill, rx_fulfill) = bounded(1);
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_deferred_fulfillment(4, tx_fulfill)
+            .with_delivery(DeliveryConfig {
+                max_attempts: 2,
+                dead_letter_capacity: 4,
+            }),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+
+    let generate = |explorer_id: u32, rx_expl: &Receiver<PlanetToExplorer>| {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Hydrogen,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+
+    assert!(!generate(1, &rx_expl1), "No charged cell");
+    assert!(!generate(2, &rx_expl2), "No charged cell");
+    charge_cells(2, &tx_orch, &rx_orch);
+
+    let delivery = stats.snapshot().delivery();
+    assert_eq!((delivery.delivered, delivery.retries), (1, 0));
+    assert!(stats.snapshot().dead_letters().is_empty(), "Still retrying");
+
+    tx_orch
+        .send(OrchestratorToPlanet::InternalStateRequest)
+        .unwrap();
+    let _ = rx_orch.recv_timeout(Duration::from_millis(200));
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.delivery().retries, 1);
+    assert_eq!(snapshot.delivery().dead_lettered, 1);
+    let dead_letters = snapshot.dead_letters();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].resource, BasicResourceType::Hydrogen);
+    assert_eq!(dead_letters[0].attempts, 2);
+    assert_eq!(dead_letters[0].cause, DeadLetterCause::AttemptsExhausted);
+
+    // Tied explorers are served in a drawn order
+    let dead_lettered = dead_letters[0].explorer_id;
+    assert_eq!(
+        rx_fulfill.try_recv().map(|f| f.explorer_id),
+        Ok(ExplorerId::new(3 - dead_lettered.raw().unwrap()))
+    );
+    assert!(rx_fulfill.try_recv().is_err());
+}
+
+/// **Scenario:** Two pending requests are granted while the fulfillments channel holds
+/// a single fulfillment, on a planet reporting its grants
+/// **Validates:**
+/// - Each grant is reported with a receipt of its own
+/// - The delivered fulfillment and the dead letter carry the receipt of their grant
+#[test]
+fn test_grant_receipts_trace_fulfillments_and_dead_letters() {
+    let stats = StatsHandle::default();
+    let (tx_events, rx_events) = unbounded();
+    let (tx_fulfill, rx_fulfill) = bounded(1);
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_events(tx_events)
+            .with_receipt_events()
+            .with_deferred_fulfillment(4, tx_fulfill)
+            .with_delivery(DeliveryConfig {
+                max_attempts: 1,
+                dead_letter_capacity: 4,
+            }),
+    );
+    for explorer_id in [1, 2] {
+        let rx_expl = register_explorer(explorer_id, &tx_orch, &rx_orch);
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Hydrogen,
+            })
+            .unwrap();
+        assert!(
+            rx_expl.recv_timeout(TIMEOUT).is_ok(),
+            "Deferred request answered"
+        );
+    }
+    charge_cells(2, &tx_orch, &rx_orch);
+
+    let granted: Vec<Receipt> = sent_events(&rx_events)
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Granted(receipt) => Some(receipt),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        granted.iter().map(|receipt| receipt.id).collect::<Vec<_>>(),
+        [ReceiptId::new(1), ReceiptId::new(2)]
+    );
+    assert!(
+        granted
+            .iter()
+            .all(|receipt| receipt.resource == BasicResourceType::Hydrogen)
+    );
+
+    let fulfillment = rx_fulfill.try_recv().expect("First fulfillment delivered");
+    let dead_letters = stats.snapshot().dead_letters().to_vec();
+    assert_eq!(dead_letters.len(), 1);
+    for (explorer_id, receipt) in [
+        (fulfillment.explorer_id, fulfillment.receipt),
+        (dead_letters[0].explorer_id, dead_letters[0].receipt),
+    ] {
+        assert!(
+            granted
+                .iter()
+                .any(|granted| granted.id == receipt && granted.explorer_id == explorer_id),
+            "Receipt {receipt} issued to explorer {explorer_id}"
+        );
+    }
+    assert_ne!(fulfillment.receipt, dead_letters[0].receipt);
+}
+
+/// **Scenario:** A fulfillment is dead-lettered while the host doesn't drain the
+/// fulfillments channel, then the host catches up and re-drives the dead letters
+/// **Validates:**
+/// - The re-driven fulfillment carries the resource produced for the explorer
+/// - The dead-letter buffer is emptied
+#[test]
+fn test_dead_letters_are_redriven_on_admin_command() {
+    let stats = StatsHandle::default();
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_fulfill, rx_fulfill) = bounded(1);
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_admin(rx_admin)
+            .with_deferred_fulfillment(4, tx_fulfill)
+            .with_delivery(DeliveryConfig {
+                max_attempts: 1,
+                dead_letter_capacity: 4,
+            }),
+    );
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+
+    for _ in 0..2 {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource: BasicResourceType::Silicon,
+            })
+            .unwrap();
+        let _ = rx_expl.recv_timeout(Duration::from_millis(200));
+    }
+    charge_cells(2, &tx_orch, &rx_orch);
+    assert_eq!(stats.snapshot().dead_letters().len(), 1);
+
+    assert!(rx_fulfill.try_recv().is_ok());
+    tx_admin
+        .send(AdminCommand::RedriveDeadLetters {
+            explorer_id: Some(1),
+        })
+        .unwrap();
+    tx_orch
+        .send(OrchestratorToPlanet::InternalStateRequest)
+        .unwrap();
+    let _ = rx_orch.recv_timeout(Duration::from_millis(200));
+
+    let fulfillment = rx_fulfill
+        .try_recv()
+        .expect("Dead letter should be sent again");
+    assert_eq!(fulfillment.explorer_id, 1);
+    assert_eq!(fulfillment.resource.get_type(), BasicResourceType::Silicon);
+    let snapshot = stats.snapshot();
+    assert!(snapshot.dead_letters().is_empty());
+    assert_eq!(snapshot.delivery().redriven, 1);
+}
+
+/// **Scenario:** Host bans explorer 3, which requests a resource, then unbans it and
+/// asks for a statistics snapshot before its next request
+/// **Validates:**
+/// - Requests of a banned explorer are denied with the `Banned` reason
+/// - Unbanned explorers are granted resources again
+/// - The snapshot is taken when the commands are applied, before the next request
+#[test]
+fn test_admin_ban_and_stats_snapshot() {
+    let (tx_admin, rx_admin) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_admin(rx_admin))
+        .explorers([3])
+        .charged_cells(1)
+        .build();
+
+    tx_admin
+        .send(AdminCommand::BanExplorer {
+            explorer_id: 3,
+            duration: None,
+        })
+        .unwrap();
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_none());
+
+    let (tx_snapshot, rx_snapshot) = bounded(1);
+    tx_admin
+        .send(AdminCommand::Unban { explorer_id: 3 })
+        .unwrap();
+    tx_admin
+        .send(AdminCommand::SnapshotStats { reply: tx_snapshot })
+        .unwrap();
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_some());
+
+    let snapshot = rx_snapshot
+        .recv_timeout(Duration::from_millis(200))
+        .unwrap();
+    assert_eq!(
+        snapshot.denials_by_reason().get(&DenialReason::Banned),
+        Some(&1)
+    );
+    assert_eq!(snapshot.totals().grants, 0);
+    assert_eq!(fixture.stats.snapshot().totals().grants, 1);
+}
+
+/// **Scenario:** Tournament setup sent as a single batch: explorers 1 and 2 banned,
+/// explorer 3 assigned a quota of one grant and tagged, then a snapshot requested
+/// **Validates:**
+/// - Every command of the batch is applied before the next request
+/// - Nested batches are applied in order
+#[test]
+fn test_admin_batch_applied_at_once() {
+    let (tx_admin, rx_admin) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_admin(rx_admin))
+        .explorers(1..=3)
+        .charged_cells(2)
+        .build();
+
+    let (tx_snapshot, rx_snapshot) = bounded(1);
+    tx_admin
+        .send(AdminCommand::batch([
+            AdminCommand::ban_list([1, 2], None),
+            AdminCommand::assign_quotas([(3, Quota::new(1, Duration::from_secs(60)))]),
+            AdminCommand::import_tags([(3, Tag::new("team", "red"))]),
+            AdminCommand::SnapshotStats { reply: tx_snapshot },
+        ]))
+        .unwrap();
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_none());
+
+    let snapshot = rx_snapshot
+        .recv_timeout(Duration::from_millis(200))
+        .unwrap();
+    assert_eq!(snapshot.bans().keys().copied().collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(snapshot.totals().denials, 0);
+    let denials = fixture.stats.snapshot().explorer_denials().clone();
+    assert_eq!(
+        denials[&ExplorerId::new(1)],
+        BTreeMap::from([(DenialReason::Banned, 1)])
+    );
+    assert_eq!(denials[&ExplorerId::new(3)].len(), 1);
+}
+
+/// **Scenario:** Quota of one grant per explorer, the host benchmarks the planet
+/// before explorer 1 requests twice
+/// **Validates:**
+/// - The report of the benchmark is sent back, both parts timed
+/// - The benchmark doesn't use up the quota of the explorer, nor record any request
+#[test]
+fn test_admin_benchmark_leaves_state_untouched() {
+    let (tx_admin, rx_admin) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::Quota(Quota::new(
+            1,
+            Duration::from_secs(60),
+        )))
+        .configure(|config| config.with_admin(rx_admin))
+        .explorers([1])
+        .charged_cells(2)
+        .build();
+
+    let (tx_report, rx_report) = bounded(1);
+    tx_admin
+        .send(AdminCommand::Benchmark {
+            duration: Duration::from_millis(20),
+            reply: tx_report,
+        })
+        .unwrap();
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+
+    let report = rx_report.recv_timeout(Duration::from_millis(200)).unwrap();
+    assert!(report.policy.decisions > 0, "{report:?}");
+    assert!(report.admission.decisions > 0, "{report:?}");
+    let totals = fixture.stats.snapshot().totals();
+    assert_eq!((totals.grants, totals.denials), (1, 1));
+}
+
+/// **Scenario:** Referee bans explorer 3 for a minute, then the planet clock moves past
+/// the end of the ban
+/// **Validates:**
+/// - Requests are denied with the `Banned` reason while the ban lasts
+/// - The ban and its expiry are reported in the statistics until it expires
+#[test]
+fn test_admin_ban_expires() {
+    let (tx_admin, rx_admin) = unbounded();
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_admin(rx_admin))
+        .manual_clock(start)
+        .explorers([3])
+        .charged_cells(1)
+        .build();
+
+    tx_admin
+        .send(AdminCommand::BanExplorer {
+            explorer_id: 3,
+            duration: Some(Duration::from_secs(60)),
+        })
+        .unwrap();
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_none());
+    assert_eq!(
+        fixture.stats.snapshot().bans(),
+        &BTreeMap::from([(ExplorerId::new(3), Some(start + Duration::from_secs(60)))])
+    );
+
+    fixture.advance(Duration::from_secs(60));
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_some());
+    let snapshot = fixture.stats.snapshot();
+    assert!(snapshot.bans().is_empty());
+    assert_eq!(
+        snapshot.denials_by_reason().get(&DenialReason::Banned),
+        Some(&1)
+    );
+}
+
+/// **Scenario:** Host disables silicon for 5 minutes, explorer 1 requests silicon and
+/// oxygen, then the planet clock moves past the end of the timer
+/// **Validates:**
+/// - Requests for the disabled resource are denied with the `ResourceDisabled` reason
+/// - Other resources are still generated
+/// - The resource is enabled again when its timer elapses
+#[test]
+fn test_admin_disable_resource() {
+    let (tx_admin, rx_admin) = unbounded();
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_admin(rx_admin))
+        .manual_clock(start)
+        .explorers([1])
+        .charged_cells(2)
+        .build();
+
+    tx_admin
+        .send(AdminCommand::DisableResource {
+            resource: BasicResourceType::Silicon,
+            duration: Some(Duration::from_secs(300)),
+        })
+        .unwrap();
+    assert!(fixture.generate(1, BasicResourceType::Silicon).is_none());
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert_eq!(
+        fixture.stats.snapshot().disabled_resources(),
+        &HashMap::from([(
+            BasicResourceType::Silicon,
+            Some(start + Duration::from_secs(300))
+        )])
+    );
+
+    fixture.advance(Duration::from_secs(300));
+    assert!(fixture.generate(1, BasicResourceType::Silicon).is_some());
+    let snapshot = fixture.stats.snapshot();
+    assert!(snapshot.disabled_resources().is_empty());
+    assert_eq!(
+        snapshot
+            .denials_by_reason()
+            .get(&DenialReason::ResourceDisabled),
+        Some(&1)
+    );
+}
+
+/// **Scenario:** With an explorer gateway and spoof protection, explorer 2 leaves the
+/// planet, then a request claiming its ID arrives before explorer 1 requests the only
+/// charged cell
+/// **Validates:**
+/// - The request claiming the departed explorer's ID doesn't discharge the cell
+/// - It's counted as a spoof attempt, unlike the requests of registered explorers
+#[test]
+fn test_requests_claiming_departed_explorer_are_dropped() {
+    let gateway = ExplorerGateway::new();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_explorer_gateway(gateway)
+                .with_spoof_protection()
+        })
+        .explorers([1, 2])
+        .charged_cells(1)
+        .build();
+
+    fixture
+        .orchestrator
+        .send(OrchestratorToPlanet::OutgoingExplorerRequest { explorer_id: 2 })
+        .unwrap();
+    fixture.from_planet.recv_timeout(TIMEOUT).unwrap();
+    fixture
+        .to_planet
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 2,
+            resource: BasicResourceType::Oxygen,
+        })
+        .unwrap();
+
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    let stats = fixture.stats.snapshot();
+    assert_eq!(stats.totals().grants, 1);
+    assert_eq!(stats.spoof_attempts().get(&ExplorerId::new(2)), Some(&1));
+    assert!(!stats.spoof_attempts().contains_key(&ExplorerId::new(1)));
+}
+
+/// **Scenario:** With an explorer gateway and spoof protection, registered explorer 1
+/// sends a request claiming the ID of registered explorer 2 on its own channel, then
+/// one with its own ID
+/// **Validates:**
+/// - The request claiming explorer 2 is dropped without discharging the cell, and
+///   counted as a spoof attempt
+/// - The request with its own ID is granted
+#[test]
+fn test_gateway_catches_requests_claiming_another_explorer() {
+    let gateway = ExplorerGateway::new();
+    let to_planet = gateway.connect(1);
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_explorer_gateway(gateway)
+                .with_spoof_protection()
+        })
+        .explorers([1, 2])
+        .charged_cells(1)
+        .build();
+
+    for explorer_id in [2, 1] {
+        to_planet
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+    }
+    assert!(matches!(
+        fixture.explorer(1).recv_timeout(TIMEOUT),
+        Ok(PlanetToExplorer::GenerateResourceResponse { resource: Some(_) })
+    ));
+    assert!(fixture.explorer(2).try_recv().is_err());
+    let stats = fixture.stats.snapshot();
+    assert_eq!(stats.totals().grants, 1);
+    assert_eq!(stats.spoof_attempts().get(&ExplorerId::new(2)), Some(&1));
+}
+
+/// **Scenario:** FairShare planet behind a gateway; explorers 2 to 4 get a resource
+/// each, while explorer 1 sends 5 generation requests before the orchestrator
+/// registers it
+/// **Validates:**
+/// - The requests are counted as early requests of explorer 1, not as spoof attempts
+/// - Their cost is charged once explorer 1 is registered: its next request is denied
+#[test]
+fn test_requests_racing_the_registration_are_reconciled() {
+    let gateway = ExplorerGateway::new();
+    let early = gateway.connect(1);
+    let mut fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::FairShare)
+        .configure(|config| config.with_explorer_gateway(gateway))
+        .explorers(2..=4)
+        .charged_cells(4)
+        .build();
+    for explorer_id in 2..=4 {
+        assert!(
+            fixture
+                .generate(explorer_id, BasicResourceType::Oxygen)
+                .is_some()
+        );
+    }
+
+    for _ in 0..5 {
+        early
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+    }
+    thread::sleep(Duration::from_millis(50));
+    assert!(fixture.register(1).try_recv().is_err());
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_none(),
+        "Early requests should count against the fair share"
+    );
+
+    let stats = fixture.stats.snapshot();
+    assert_eq!(stats.early_requests().get(&ExplorerId::new(1)), Some(&5));
+    assert!(stats.spoof_attempts().is_empty());
+}
+
+/// **Scenario:** With at most 2 tracked explorers and the others sharing a bucket,
+/// explorers 1, 2 and 3 each request a resource, then explorer 4
+/// **Validates:**
+/// - The first two explorers are tracked on their own
+/// - The requests of the others are granted to the shared bucket, and counted
+#[test]
+fn test_untracked_explorers_share_a_bucket() {
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_max_explorers(2, Untracked::Share))
+        .explorers([1, 2, 3, 4])
+        .charged_cells(4)
+        .build();
+    for explorer_id in 1..=4 {
+        assert!(
+            fixture
+                .generate(explorer_id, BasicResourceType::Oxygen)
+                .is_some()
+        );
+    }
+
+    let stats = fixture.stats.snapshot();
+    let ledger = stats.ledger();
+    assert_eq!(
+        ledger.keys().copied().collect::<Vec<_>>(),
+        [ExplorerId::new(1), ExplorerId::new(2), ExplorerId::Overflow]
+    );
+    assert_eq!(ledger[&ExplorerId::Overflow].total(), 2);
+    assert_eq!(stats.untracked_messages(), 2);
+}
+
+/// **Scenario:** With at most 2 tracked explorers sharing a bucket and a queue for
+/// deferred fulfillment, explorers 1 and 2 poll the planet, explorer 3 requests a
+/// resource while no cell is charged, then a cell is charged
+/// **Validates:**
+/// - The request of explorer 3 is answered with no resource
+/// - It isn't queued, so the charged cell isn't fulfilled to the shared bucket
+#[test]
+fn test_untracked_explorers_are_not_deferred() {
+    let (tx_fulfill, rx_fulfill) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_max_explorers(2, Untracked::Share)
+                .with_deferred_fulfillment(4, tx_fulfill)
+        })
+        .explorers([1, 2, 3])
+        .build();
+    for explorer_id in 1..=2 {
+        fixture.request(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id });
+    }
+    assert!(fixture.generate(3, BasicResourceType::Oxygen).is_none());
+
+    fixture.charge(1);
+    assert!(
+        rx_fulfill.recv_timeout(Duration::from_millis(100)).is_err(),
+        "Untracked requests shouldn't be queued"
+    );
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+}
+
+/// **Scenario:** With at most 1 tracked explorer and the others denied, explorer 1
+/// requests a resource, then explorer 2 requests two and queries the planet
+/// **Validates:**
+/// - The requests of explorer 2 are denied while a cell is charged
+/// - Its queries are answered as if the planet had nothing to offer
+/// - Nothing is recorded for explorer 2 but the untracked messages
+#[test]
+fn test_untracked_explorers_are_denied() {
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| config.with_max_explorers(1, Untracked::Deny))
+        .explorers([1, 2])
+        .charged_cells(2)
+        .build();
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(2, BasicResourceType::Oxygen).is_none());
+    assert!(fixture.generate(2, BasicResourceType::Carbon).is_none());
+    match fixture.request(ExplorerToPlanet::SupportedResourceRequest { explorer_id: 2 }) {
+        PlanetToExplorer::SupportedResourceResponse { resource_list } => {
+            assert!(resource_list.is_empty());
+        }
+        _ => panic!("Expected SupportedResourceResponse"),
+    }
+    match fixture.request(ExplorerToPlanet::SupportedCombinationRequest { explorer_id: 2 }) {
+        PlanetToExplorer::SupportedCombinationResponse { combination_list } => {
+            assert!(combination_list.is_empty());
+        }
+        _ => panic!("Expected SupportedCombinationResponse"),
+    }
+    match fixture.request(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 2 }) {
+        PlanetToExplorer::AvailableEnergyCellResponse { available_cells } => {
+            assert_eq!(available_cells, 0);
+        }
+        _ => panic!("Expected AvailableEnergyCellResponse"),
+    }
+
+    let stats = fixture.stats.snapshot();
+    assert_eq!(stats.totals().grants, 1);
+    assert_eq!(stats.totals().denials, 0);
+    assert!(!stats.explorer_denials().contains_key(&ExplorerId::new(2)));
+    assert_eq!(stats.untracked_messages(), 5);
+}
+
+/// **Scenario:** Explorer 1 is granted two Oxygen and explorer 2 a Carbon, then explorer
+/// 2 is denied for lack of energy
+/// **Validates:**
+/// - The ledger counts the resources each explorer received, by type
+/// - Denied requests aren't counted, and the CSV export has a row per type
+#[test]
+fn test_ledger_counts_received_resources() {
+    let fixture = TestPlanetFixture::builder()
+        .explorers([1, 2])
+        .charged_cells(3)
+        .build();
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(2, BasicResourceType::Carbon).is_some());
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(2, BasicResourceType::Carbon).is_none());
+
+    let stats = fixture.stats.snapshot();
+    let ledger = stats.ledger();
+    assert_eq!(
+        ledger[&ExplorerId::new(1)].basic[&BasicResourceType::Oxygen],
+        2
+    );
+    assert_eq!(ledger[&ExplorerId::new(2)].total(), 1);
+    assert_eq!(
+        stats.ledger_to_csv(),
+        "explorer_id,resource,count\n1,Oxygen,2\n2,Carbon,1\n"
+    );
+}
+
+/// **Scenario:** With reregistrations resetting explorers and a quota of 1, explorer 1 is
+/// granted a resource, registered twice, leaves and comes back
+/// **Validates:**
+/// - The duplicate registration is reported as a warning, and clears the ledger
+/// - The return is reported, and the explorer starts over with a fresh quota
+#[test]
+fn test_reregistration_resets_explorer() {
+    let (tx_events, rx_events) = unbounded();
+    let mut fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::Quota(Quota::new(
+            1,
+            Duration::from_secs(60),
+        )))
+        .configure(|config| {
+            config
+                .with_events(tx_events)
+                .with_reregistration(Reregistration::Reset)
+        })
+        .explorers([1])
+        .charged_cells(3)
+        .build();
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+
+    fixture.register(1);
+    let duplicate = sent_events(&rx_events).remove(0);
+    assert_eq!(
+        duplicate,
+        Event::Registration {
+            explorer_id: ExplorerId::new(1),
+            transition: Transition::Duplicate,
+        }
+    );
+    assert_eq!(duplicate.severity(), Severity::Warn);
+    assert!(
+        !fixture
+            .stats
+            .snapshot()
+            .ledger()
+            .contains_key(&ExplorerId::new(1))
+    );
+
+    fixture
+        .orchestrator
+        .send(OrchestratorToPlanet::OutgoingExplorerRequest { explorer_id: 1 })
+        .unwrap();
+    fixture.from_planet.recv_timeout(TIMEOUT).unwrap();
+    fixture.register(1);
+    assert_eq!(
+        sent_events(&rx_events),
+        [
+            Event::Registration {
+                explorer_id: ExplorerId::new(1),
+                transition: Transition::Departed,
+            },
+            Event::Registration {
+                explorer_id: ExplorerId::new(1),
+                transition: Transition::Returned,
+            },
+        ]
+    );
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_some(),
+        "The quota starts over"
+    );
+    assert_eq!(
+        fixture.stats.snapshot().ledger()[&ExplorerId::new(1)].total(),
+        1
+    );
+}
+
+/// **Scenario:** Host pauses the planet in buffer mode, an explorer requests the only
+/// charged cell, then the host resumes the planet
+/// **Validates:**
+/// - The request is denied with the `Paused` reason without discharging the cell
+/// - The buffered request is fulfilled after resuming
+#[test]
+fn test_paused_requests_are_buffered_until_resumed() {
+    let stats = StatsHandle::default();
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_fulfill, rx_fulfill) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_admin(rx_admin)
+            .with_deferred_fulfillment(4, tx_fulfill),
+    );
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+    charge_cells(1, &tx_orch, &rx_orch);
+
+    tx_admin
+        .send(AdminCommand::Pause {
+            mode: PauseMode::Buffer,
+        })
+        .unwrap();
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 1,
+            resource: BasicResourceType::Oxygen,
+        })
+        .unwrap();
+    match rx_expl.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => assert!(resource.is_none()),
+        _ => panic!("Expected GenerateResourceResponse"),
+    }
+    let snapshot = stats.snapshot();
+    assert_eq!(
+        snapshot.denials_by_reason().get(&DenialReason::Paused),
+        Some(&1)
+    );
+    assert!(snapshot.extended_state().paused);
+    assert_eq!(snapshot.extended_state().charged_cells_count, 1);
+    assert!(rx_fulfill.try_recv().is_err());
+
+    tx_admin.send(AdminCommand::Resume).unwrap();
+    tx_expl
+        .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 })
+        .unwrap();
+    match rx_expl.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::AvailableEnergyCellResponse { available_cells }) => {
+            assert_eq!(available_cells, 0, "Cell used by the buffered request")
+        }
+        _ => panic!("Expected AvailableEnergyCellResponse"),
+    }
+    let fulfillment = rx_fulfill
+        .try_recv()
+        .expect("Buffered request should be fulfilled");
+    assert_eq!(fulfillment.explorer_id, 1);
+}
+
+/// **Scenario:** Host pauses the planet in each mode in turn, an explorer requesting a
+/// resource every time
+/// **Validates:**
+/// - Rejected, buffered and dropped requests are counted apart
+/// - Dropped requests are left unanswered and aren't counted as denials
+#[test]
+fn test_pause_modes_are_accounted_apart() {
+    let stats = StatsHandle::default();
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_fulfill, _rx_fulfill) = unbounded();
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_admin(rx_admin)
+            .with_deferred_fulfillment(4, tx_fulfill),
+    );
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+
+    for mode in [PauseMode::Reject, PauseMode::Buffer, PauseMode::Drop] {
+        tx_admin.send(AdminCommand::Pause { mode }).unwrap();
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+        let answered = rx_expl.recv_timeout(Duration::from_millis(200)).is_ok();
+        assert_eq!(answered, mode != PauseMode::Drop, "{mode:?}");
+    }
+
+    let snapshot = stats.snapshot();
+    let paused = snapshot.paused_requests();
+    assert_eq!(
+        (paused.rejected, paused.buffered, paused.dropped),
+        (1, 1, 1)
+    );
+    assert_eq!(
+        snapshot.denials_by_reason().get(&DenialReason::Paused),
+        Some(&2)
+    );
+}
+
+/// **Scenario:** Watchdog observes a planet that never handles messages, first with
+/// an empty channel, then with a message waiting
+/// **Validates:**
+/// - An idle planet with nothing to do isn't reported
+/// - A single `Stalled` alert is sent once a message waits for too long
+#[test]
+fn test_watchdog_reports_stalled_planet() {
+    let stats = StatsHandle::default();
+    let (tx_orch, _rx_orch) = unbounded();
+    let (tx_events, rx_events) = unbounded();
+    let watchdog = Watchdog::new(stats.clone(), Duration::from_millis(40))
+        .watch(tx_orch.clone())
+        .with_events(tx_events)
+        .spawn();
+
+    assert!(rx_events.recv_timeout(Duration::from_millis(120)).is_err());
+    assert_eq!(stats.snapshot().last_activity(), None);
+
+    tx_orch
+        .send(OrchestratorToPlanet::InternalStateRequest)
+        .unwrap();
+    match rx_events.recv_timeout(Duration::from_millis(200)) {
+        Ok(Event::Stalled {
+            idle_for,
+            queued_messages,
+        }) => {
+            assert!(idle_for >= Duration::from_millis(40));
+            assert_eq!(queued_messages, 1);
+        }
+        _ => panic!("Expected Stalled event"),
+    }
+    assert!(rx_events.recv_timeout(Duration::from_millis(120)).is_err());
+    watchdog.stop();
+}
+
+/// Planet with the given priority and explorer 1 registered, in lockstep with the
+/// orchestrator: the planet→orchestrator channel holds a single response, so each
+/// response read lets the planet handle one more orchestrator message.
+///
+/// Two state requests are sent, so that the planet is blocked on its second response
+/// until the caller reads the first one.
+#[allow(clippy::type_complexity)]
+fn setup_lockstep_planet(
+    priority: OrchestratorPriority,
+) -> (
+    Sender<OrchestratorToPlanet>,
+    Receiver<PlanetToOrchestrator>,
+    Sender<ExplorerToPlanet>,
+    Receiver<PlanetToExplorer>,
+    thread::JoinHandle<Result<(), String>>,
+) {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = bounded(1);
+    let (tx_expl, rx_expl_to_planet) = unbounded();
+    let mut planet = create_planet_with_config(
+        PlanetConfig::new(1).with_orchestrator_priority(priority),
+        rx_orch_to_planet,
+        tx_planet_to_orch,
+        rx_expl_to_planet,
+    );
+    let runner = thread::spawn(move || planet.run());
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    rx_orch.recv_timeout(TIMEOUT).unwrap();
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+
+    for _ in 0..2 {
+        tx_orch
+            .send(OrchestratorToPlanet::InternalStateRequest)
+            .unwrap();
+    }
+    thread::sleep(Duration::from_millis(50));
+    (tx_orch, rx_orch, tx_expl, rx_expl, runner)
+}
+
+/// Kills a planet set up by [`setup_lockstep_planet`], reading the responses it is
+/// blocked on.
+fn kill_lockstep_planet(
+    tx_orch: &Sender<OrchestratorToPlanet>,
+    rx_orch: &Receiver<PlanetToOrchestrator>,
+    runner: thread::JoinHandle<Result<(), String>>,
+) {
+    tx_orch.send(OrchestratorToPlanet::KillPlanet).unwrap();
+    while !matches!(
+        rx_orch.recv_timeout(TIMEOUT),
+        Ok(PlanetToOrchestrator::KillPlanetResult { .. })
+    ) {}
+    assert_eq!(runner.join().unwrap(), Ok(()));
+}
+
+/// **Scenario:** Planet with strict orchestrator priority; an explorer floods it with
+/// energy queries, then the orchestrator requests the internal state
+/// **Validates:**
+/// - The state response doesn't wait for the flood to be handled
+/// - Every explorer query is still answered afterwards
+#[test]
+fn test_strict_priority_orchestrator_skips_explorer_flood() {
+    const FLOOD: usize = 2000;
+    let (tx_orch, rx_orch, tx_expl, rx_expl, runner) =
+        setup_lockstep_planet(OrchestratorPriority::Strict);
+
+    for _ in 0..FLOOD {
+        tx_expl
+            .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 })
+            .unwrap();
+    }
+    tx_orch
+        .send(OrchestratorToPlanet::InternalStateRequest)
+        .unwrap();
+    // The planet picks its next message once the first response is read: the state
+    // request, which then blocks it until the second response is read
+    for _ in 0..2 {
+        assert!(matches!(
+            rx_orch.recv_timeout(Duration::from_secs(1)),
+            Ok(PlanetToOrchestrator::InternalStateResponse { .. })
+        ));
+    }
+    let answered = rx_expl.len();
+    assert!(
+        answered < FLOOD / 2,
+        "{answered} explorer queries answered first"
+    );
+    assert!(matches!(
+        rx_orch.recv_timeout(Duration::from_secs(1)),
+        Ok(PlanetToOrchestrator::InternalStateResponse { .. })
+    ));
+
+    for _ in 0..FLOOD {
+        assert!(matches!(
+            rx_expl.recv_timeout(Duration::from_secs(1)),
+            Ok(PlanetToExplorer::AvailableEnergyCellResponse { .. })
+        ));
+    }
+    kill_lockstep_planet(&tx_orch, &rx_orch, runner);
+}
+
+/// Orchestrator state requests answered before an explorer query sent behind `FLOOD` of
+/// them, with the planet [in lockstep](setup_lockstep_planet) with the orchestrator.
+fn state_requests_before_explorer_query(priority: OrchestratorPriority) -> usize {
+    const FLOOD: usize = 200;
+    let (tx_orch, rx_orch, tx_expl, rx_expl, runner) = setup_lockstep_planet(priority);
+
+    for _ in 2..FLOOD {
+        tx_orch
+            .send(OrchestratorToPlanet::InternalStateRequest)
+            .unwrap();
+    }
+    tx_expl
+        .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 })
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    let mut answered = 0;
+    loop {
+        select! {
+            recv(rx_expl) -> msg => {
+                assert!(matches!(msg, Ok(PlanetToExplorer::AvailableEnergyCellResponse { .. })));
+                break;
+            }
+            recv(rx_orch) -> msg => {
+                assert!(matches!(msg, Ok(PlanetToOrchestrator::InternalStateResponse { .. })));
+                answered += 1;
+            }
+            default(TIMEOUT) => panic!("Explorer query never answered"),
+        }
+    }
+    kill_lockstep_planet(&tx_orch, &rx_orch, runner);
+    answered
+}
+
+/// **Scenario:** The orchestrator keeps the planet busy with state requests while an
+/// explorer query waits, under each priority
+/// **Validates:**
+/// - Strict priority answers the whole flood first, as `common_game` does
+/// - Fair priority answers the explorer within a few state requests
+/// - Weighted priority answers it after its weight
+#[test]
+fn test_orchestrator_priorities_order_a_busy_orchestrator() {
+    let strict = state_requests_before_explorer_query(OrchestratorPriority::Strict);
+    assert!(strict >= 199, "{strict} state requests answered first");
+    let fair = state_requests_before_explorer_query(OrchestratorPriority::Fair);
+    assert!(fair < 50, "{fair} state requests answered first");
+    let weighted = state_requests_before_explorer_query(OrchestratorPriority::Weighted(10));
+    assert!(
+        (9..=13).contains(&weighted),
+        "{weighted} state requests answered first"
+    );
+}
+
+/// **Scenario:** Planet with fair interleaving; explorer 1 floods it with energy
+/// queries, then explorer 2 sends a single one
+/// **Validates:**
+/// - Explorer 2 is answered without waiting for the flood to be handled
+/// - Every query of explorer 1 is still answered afterwards
+#[test]
+fn test_fair_interleaving_flood_doesnt_crowd_out_others() {
+    const FLOOD: usize = 2000;
+    let (tx_orch, rx_orch, tx_expl, _handle) =
+        setup_configured_planet(PlanetConfig::new(1).with_fair_interleaving());
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+
+    for _ in 0..FLOOD {
+        tx_expl
+            .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 })
+            .unwrap();
+    }
+    tx_expl
+        .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 2 })
+        .unwrap();
+    assert!(matches!(
+        rx_expl2.recv_timeout(Duration::from_secs(1)),
+        Ok(PlanetToExplorer::AvailableEnergyCellResponse { .. })
+    ));
+    let answered = rx_expl1.len();
+    assert!(
+        answered < FLOOD / 2,
+        "{answered} queries of explorer 1 answered first"
+    );
+
+    for _ in 0..FLOOD {
+        assert!(matches!(
+            rx_expl1.recv_timeout(Duration::from_secs(1)),
+            Ok(PlanetToExplorer::AvailableEnergyCellResponse { .. })
+        ));
+    }
+}
+
+// ============================================================================
+// Tests: Graceful Shutdown
+// ============================================================================
+
+/// **Scenario:** Planet with graceful shutdown is stopped while a fulfillment waits
+/// for room in the fulfillments channel and a request is still pending
+/// **Validates:**
+/// - The waiting fulfillment is delivered once the host makes room, within the timeout
+/// - The final report lists the unserved request and the per-explorer outcomes
+#[test]
+fn test_shutdown_drains_fulfillments_and_reports() {
+    let (tx_events, rx_events) = unbounded();
+    let (tx_fulfill, rx_fulfill) = bounded(1);
+    let (tx_orch, rx_orch, tx_expl, handle) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_events(tx_events)
+            .with_deferred_fulfillment(4, tx_fulfill)
+            .with_delivery(DeliveryConfig {
+                max_attempts: 10,
+                dead_letter_capacity: 4,
+            })
+            .with_shutdown_drain(Duration::from_secs(1)),
+    );
+    let receivers: Vec<_> = (1..=3)
+        .map(|explorer_id| register_explorer(explorer_id, &tx_orch, &rx_orch))
+        .collect();
+
+    for (explorer_id, rx_expl) in (1..=3).zip(&receivers) {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Carbon,
+            })
+            .unwrap();
+        let _ = rx_expl.recv_timeout(Duration::from_millis(200));
+    }
+    charge_cells(2, &tx_orch, &rx_orch);
+
+    let host = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        (0..2)
+            .map(|_| rx_fulfill.recv_timeout(Duration::from_millis(500)).unwrap())
+            .map(|fulfillment| fulfillment.explorer_id)
+            .collect::<Vec<_>>()
+    });
+    tx_orch.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    // Tied explorers are served in a drawn order
+    let served = host.join().unwrap();
+    let unserved: Vec<u32> = (1..=3)
+        .filter(|id| !served.contains(&ExplorerId::new(*id)))
+        .collect();
+    assert_eq!(unserved.len(), 1);
+
+    let report = loop {
+        match rx_events.recv_timeout(Duration::from_millis(500)) {
+            Ok(Event::Stopped(report)) => break report,
+            Ok(_) => continue,
+            Err(_) => panic!("Expected Stopped event"),
+        }
+    };
+    let ShutdownReport {
+        epoch,
+        unserved_requests,
+        dead_letters,
+        ..
+    } = report;
+    assert_eq!(unserved_requests, unserved);
+    assert!(dead_letters.is_empty());
+    for explorer_id in served {
+        assert_eq!(epoch.grants.get(&explorer_id), Some(&1));
+    }
+
+    // A stopped planet waits for the next start: kill it to end its run
+    tx_orch.send(OrchestratorToPlanet::KillPlanet).unwrap();
+    assert_eq!(handle.join().unwrap(), Ok(()));
+}
+
+/// **Scenario:** Planet with graceful shutdown is paused, resumed after a sunray, then
+/// stopped
+/// **Validates:**
+/// - Every lifecycle stage is reported, in order
+/// - The terminal stage carries the all-time counters
+#[test]
+fn test_lifecycle_events() {
+    let (tx_events, rx_events) = unbounded();
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_orch, rx_orch, _tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_events(tx_events)
+            .with_admin(rx_admin)
+            .with_shutdown_drain(Duration::from_millis(10)),
+    );
+    tx_admin
+        .send(AdminCommand::Pause {
+            mode: PauseMode::Reject,
+        })
+        .unwrap();
+    charge_cells(1, &tx_orch, &rx_orch);
+    tx_admin.send(AdminCommand::Resume).unwrap();
+    tx_admin.send(AdminCommand::Resume).unwrap();
+    charge_cells(1, &tx_orch, &rx_orch);
+    tx_orch.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+
+    let mut stages = Vec::new();
+    while let Ok(event) = rx_events.recv_timeout(TIMEOUT) {
+        if let Event::Lifecycle { stage, .. } = event {
+            stages.push(stage);
+        }
+    }
+    assert_eq!(
+        stages,
+        [
+            LifecycleStage::Constructed,
+            LifecycleStage::Started,
+            LifecycleStage::Paused(PauseMode::Reject),
+            LifecycleStage::Resumed,
+            LifecycleStage::Draining,
+            LifecycleStage::Stopped {
+                totals: Counters {
+                    sunrays: 2,
+                    ..Counters::default()
+                }
+            },
+        ],
+        "Resuming twice is reported once"
+    );
+}
+
+// ============================================================================
+// Tests: Spawned Planets
+// ============================================================================
+
+/// **Scenario:** Planet is spawned, started, stopped and joined through its handle
+/// **Validates:**
+/// - The spawned planet answers the orchestrator
+/// - Stopping and killing through the handle ends the run loop
+#[test]
+fn test_spawned_planet_is_stopped_through_handle() {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (_tx_expl, rx_expl_to_planet) = unbounded();
+
+    let handle = spawn_planet(
+        PlanetConfig::new(7),
+        PlanetChannels {
+            from_orchestrator: rx_orch_to_planet,
+            to_orchestrator: tx_planet_to_orch,
+            from_explorers: rx_expl_to_planet,
+            control: tx_orch.clone(),
+        },
+    );
+    assert_eq!(handle.id(), 7);
+
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    assert!(matches!(
+        rx_orch.recv_timeout(Duration::from_millis(200)),
+        Ok(PlanetToOrchestrator::StartPlanetAIResult { planet_id: 7 })
+    ));
+
+    handle.stop();
+    handle.kill();
+    assert_eq!(handle.join(), Ok(()));
+}
+
+/// **Scenario:** Host replaces the policy of a spawned planet through its handle,
+/// then pauses it
+/// **Validates:**
+/// - The new policy is enforced and reported in the metrics snapshot
+/// - Requests received while paused are denied with the `Paused` reason
+#[test]
+fn test_planet_handle_controls_running_planet() {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (tx_expl, rx_expl_to_planet) = unbounded();
+    let handle = spawn_planet(
+        PlanetConfig::new(1),
+        PlanetChannels {
+            from_orchestrator: rx_orch_to_planet,
+            to_orchestrator: tx_planet_to_orch,
+            from_explorers: rx_expl_to_planet,
+            control: tx_orch.clone(),
+        },
+    );
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    let _ = rx_orch.recv_timeout(Duration::from_millis(200));
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+
+    let quota = ExplorerRequestLimit::Quota(Quota::new(1, Duration::from_secs(60)));
+    handle.set_policy(quota);
+    charge_cells(3, &tx_orch, &rx_orch);
+
+    let generate = || {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource: BasicResourceType::Oxygen,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+    assert!(generate());
+    assert!(!generate(), "Quota of the new policy");
+
+    handle.pause(PauseMode::Reject);
+    assert!(!generate());
+    let snapshot = handle.metrics_snapshot();
+    assert_eq!(snapshot.extended_state().limit_mode, Policy::from(quota));
+    let denials = snapshot.denials_by_reason();
+    assert_eq!(denials.get(&DenialReason::QuotaExceeded), Some(&1));
+    assert_eq!(denials.get(&DenialReason::Paused), Some(&1));
+
+    handle.kill();
+    assert_eq!(handle.join(), Ok(()));
+}
+
+/// **Scenario:** With a 10 second policy warm-up, explorer 1 is granted 2 cells, then
+/// the host replaces the unlimited policy with a quota of 2 grants a minute
+/// **Validates:**
+/// - The incoming policy isn't enforced during the warm-up
+/// - Once enforced, it accounts the grants from before the replacement and from the
+///   warm-up
+#[test]
+fn test_replaced_policy_warms_up() {
+    let (tx_admin, rx_admin) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .configure(|config| {
+            config
+                .with_admin(rx_admin)
+                .with_policy_warm_up(Duration::from_secs(10))
+        })
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1000))
+        .explorers([1])
+        .charged_cells(4)
+        .build();
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_some());
+
+    let quota = ExplorerRequestLimit::Quota(Quota::new(2, Duration::from_secs(60)));
+    tx_admin
+        .send(AdminCommand::SetPolicy {
+            policy: quota.into(),
+        })
+        .unwrap();
+    fixture.advance(Duration::from_secs(5));
+    assert!(
+        fixture.generate(1, BasicResourceType::Oxygen).is_some(),
+        "Warming up"
+    );
+    assert_eq!(
+        fixture.stats.snapshot().extended_state().limit_mode,
+        ExplorerRequestLimit::None.into()
+    );
+
+    // Replaced with the request 5 seconds in
+    fixture.advance(Duration::from_secs(10));
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    let snapshot = fixture.stats.snapshot();
+    assert_eq!(snapshot.extended_state().limit_mode, Policy::from(quota));
+    assert_eq!(
+        snapshot
+            .denials_by_reason()
+            .get(&DenialReason::QuotaExceeded),
+        Some(&1)
+    );
+}
+
+/// **Scenario:** Spawned FairShare planet is queried for its info, then its policy is
+/// replaced
+/// **Validates:**
+/// - The info describes the Type D planet as configured
+/// - The replaced policy is reported in the info
+#[test]
+fn test_planet_handle_reports_info() {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (_tx_expl, rx_expl_to_planet) = unbounded();
+    let handle = spawn_planet(
+        PlanetConfig::new(7).with_request_limit(ExplorerRequestLimit::FairShare),
+        PlanetChannels {
+            from_orchestrator: rx_orch_to_planet,
+            to_orchestrator: tx_planet_to_orch,
+            from_explorers: rx_expl_to_planet,
+            control: tx_orch.clone(),
+        },
+    );
+
+    let info = handle.info();
+    assert_eq!(info.id, 7);
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(matches!(info.planet_type, PlanetType::D));
+    assert_eq!(info.generation_rules.len(), 4);
+    assert!(info.combination_rules.is_empty());
+    assert_eq!(info.request_limit, ExplorerRequestLimit::FairShare.into());
+    assert_eq!(
+        info.features.contains(&"serde"),
+        cfg!(feature = "serde"),
+        "Features of the build"
+    );
+
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    let _ = rx_orch.recv_timeout(TIMEOUT);
+    handle.set_policy(ExplorerRequestLimit::EpochBudget(3));
+    charge_cells(1, &tx_orch, &rx_orch);
+    assert_eq!(
+        handle
+            .metrics_snapshot()
+            .info()
+            .map(|info| &info.request_limit),
+        Some(&ExplorerRequestLimit::EpochBudget(3).into())
+    );
+
+    handle.kill();
+    assert_eq!(handle.join(), Ok(()));
+}
+
+/// **Scenario:** Spawned planet publishing its statistics to a watch after every
+/// message receives two sunrays, then stops
+/// **Validates:**
+/// - The handle hands out the watch, woken up by each publication
+/// - The copy published when the planet stops counts every sunray
+#[test]
+fn test_stats_watch_receives_published_copies() {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (_tx_expl, rx_expl_to_planet) = unbounded();
+    let handle = spawn_planet(
+        PlanetConfig::new(1).with_stats_watch(StatsWatch::new(), Duration::ZERO),
+        PlanetChannels {
+            from_orchestrator: rx_orch_to_planet,
+            to_orchestrator: tx_planet_to_orch,
+            from_explorers: rx_expl_to_planet,
+            control: tx_orch.clone(),
+        },
+    );
+    let mut watch = handle.stats_watch().expect("Watch configured");
+    assert!(!watch.has_changed());
+
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    let _ = rx_orch.recv_timeout(TIMEOUT);
+    charge_cells(2, &tx_orch, &rx_orch);
+    assert!(watch.wait_for_change(TIMEOUT).is_some());
+
+    handle.stop();
+    let _ = rx_orch.recv_timeout(TIMEOUT);
+    let latest = watch.wait_for_change(TIMEOUT).expect("Published on stop");
+    assert_eq!(latest.totals().sunrays, 2);
+    assert!(!watch.has_changed());
+
+    handle.kill();
+    assert_eq!(handle.join(), Ok(()));
+}
+
+/// **Scenario:** Supervisor probes the health of a spawned planet while it runs, then
+/// after it's killed
+/// **Validates:**
+/// - A running planet reports its heartbeat and empty channels, and is alive
+/// - A killed planet isn't running anymore, without a panic
+#[test]
+fn test_planet_handle_reports_health() {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (tx_expl, rx_expl_to_planet) = unbounded();
+    let handle = spawn_planet(
+        PlanetConfig::new(1),
+        PlanetChannels {
+            from_orchestrator: rx_orch_to_planet,
+            to_orchestrator: tx_planet_to_orch,
+            from_explorers: rx_expl_to_planet,
+            control: tx_orch.clone(),
+        },
+    )
+    .watch_explorers(tx_expl);
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    let _ = rx_orch.recv_timeout(TIMEOUT);
+    charge_cells(1, &tx_orch, &rx_orch);
+
+    let health = handle.health();
+    assert!(health.running);
+    assert!(health.last_activity.is_some());
+    assert_eq!(
+        (
+            health.orchestrator_queue,
+            health.explorer_queue,
+            health.admin_queue
+        ),
+        (0, Some(0), 0)
+    );
+    assert!(health.is_alive(SystemTime::now(), Duration::from_secs(1)));
+
+    handle.kill();
+    let deadline = std::time::Instant::now() + TIMEOUT;
+    while !handle.is_finished() && std::time::Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(5));
+    }
+    let health = handle.health();
+    assert!(!health.running);
+    assert_eq!(health.panic, None);
+    assert!(!health.is_alive(SystemTime::now(), Duration::from_secs(1)));
+    assert_eq!(handle.join(), Ok(()));
+}
+
+/// **Scenario:** Host spawns a cluster of three planets: a valid one, one with an
+/// invalid policy arm and one reusing the ID of the first
+/// **Validates:**
+/// - Only the valid planet is spawned
+/// - Each invalid planet is reported with its own error
+#[test]
+fn test_spawn_planets_reports_errors_per_planet() {
+    // The planets answering the orchestrator need the receivers alive until joined
+    let (channel_sets, _rx_planets_to_orch): (Vec<_>, Vec<_>) = (0..3)
+        .map(|_| {
+            let (tx_orch_to_planet, rx_orch_to_planet) = unbounded();
+            let (tx_planet_to_orch, rx_planet_to_orch) = unbounded();
+            let (_tx_expl_to_planet, rx_expl_to_planet) = unbounded();
+            let channels = PlanetChannels {
+                from_orchestrator: rx_orch_to_planet,
+                to_orchestrator: tx_planet_to_orch,
+                from_explorers: rx_expl_to_planet,
+                control: tx_orch_to_planet,
+            };
+            (channels, rx_planet_to_orch)
+        })
+        .unzip();
+    let invalid_arm = PolicyArm::new(PolicyArm::DEFAULT, ExplorerRequestLimit::FairShare, [1]);
+
+    let mut handles = spawn_planets(
+        vec![
+            PlanetConfig::new(1),
+            PlanetConfig::new(2).with_policy_arm(invalid_arm),
+            PlanetConfig::new(1),
+        ],
+        channel_sets,
+    );
+
+    assert!(handles[1].as_ref().is_err_and(
+        |error| matches!(error, RustrelliError::Config(message) if message.contains("policy arm"))
+    ));
+    assert!(handles[2].as_ref().is_err_and(
+        |error| matches!(error, RustrelliError::Config(message) if message.contains("Duplicate"))
+    ));
+    let handle = handles.remove(0).expect("First planet is valid");
+    assert_eq!(handle.id(), 1);
+    handle.kill();
+    assert_eq!(handle.join(), Ok(()));
+}
+
+/// **Scenario:** Coordinator advances the epoch of two spawned planets, the first one
+/// receiving sunrays during the barrier, the second one idle until after it
+/// **Validates:**
+/// - The busy planet acknowledges the hold, the idle one is reported as missed
+/// - Both planets apply the new epoch before their next message, and are resumed
+#[test]
+fn test_fleet_coordinator_advances_epochs_in_lockstep() {
+    let planets: Vec<_> = (1..=2)
+        .map(|id| {
+            let (tx_orch, rx_orch_to_planet) = unbounded();
+            let (tx_planet_to_orch, rx_orch) = unbounded();
+            let (_tx_expl, rx_expl_to_planet) = unbounded();
+            let handle = spawn_planet(
+                PlanetConfig::new(id),
+                PlanetChannels {
+                    from_orchestrator: rx_orch_to_planet,
+                    to_orchestrator: tx_planet_to_orch,
+                    from_explorers: rx_expl_to_planet,
+                    control: tx_orch.clone(),
+                },
+            );
+            tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+            let _ = rx_orch.recv_timeout(TIMEOUT);
+            (handle, tx_orch, rx_orch)
+        })
+        .collect();
+    let handles: Vec<_> = planets.iter().map(|(handle, _, _)| handle).collect();
+
+    let broadcast = thread::scope(|scope| {
+        let coordinator = scope.spawn(|| {
+            FleetCoordinator::new(handles.iter().copied())
+                .with_timeout(Duration::from_millis(100))
+                .advance_epoch()
+        });
+        let (_, tx_orch, rx_orch) = &planets[0];
+        while !coordinator.is_finished() {
+            charge_cells(1, tx_orch, rx_orch);
+        }
+        coordinator.join().unwrap()
+    });
+    assert_eq!(broadcast.reached, [1]);
+    assert_eq!(broadcast.missed, [2]);
+
+    for (handle, tx_orch, rx_orch) in &planets {
+        charge_cells(1, tx_orch, rx_orch);
+        let state = handle.stats().snapshot();
+        assert_eq!(state.epoch().epoch, 1, "Planet {}", handle.id());
+        assert!(!state.extended_state().paused, "Planet {}", handle.id());
+    }
+    for (handle, _, _) in planets {
+        handle.kill();
+        assert_eq!(handle.join(), Ok(()));
+    }
+}
+
+/// **Scenario:** Two planets share a quota of 1 resource per minute; an explorer
+/// registered to both rotates its requests across them
+/// **Validates:** The second planet denies the request already granted by the first
+#[test]
+fn test_shared_policy_across_planets() {
+    let shared = SharedPolicy::new(ExplorerRequestLimit::Quota(Quota::new(
+        1,
+        Duration::from_secs(60),
+    )));
+    let planets: Vec<_> = (1..=2)
+        .map(|id| {
+            let (tx_orch, rx_orch, tx_expl, _) =
+                setup_configured_planet(PlanetConfig::new(id).with_request_limit(shared.clone()));
+            let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+            charge_cells(1, &tx_orch, &rx_orch);
+            (tx_orch, tx_expl, rx_expl)
+        })
+        .collect();
+
+    let granted: Vec<bool> = planets
+        .iter()
+        .map(|(_, tx_expl, rx_expl)| {
+            tx_expl
+                .send(ExplorerToPlanet::GenerateResourceRequest {
+                    explorer_id: 1,
+                    resource: BasicResourceType::Carbon,
+                })
+                .unwrap();
+            match rx_expl.recv_timeout(Duration::from_millis(200)) {
+                Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource.is_some(),
+                _ => panic!("Expected GenerateResourceResponse"),
+            }
+        })
+        .collect();
+
+    assert_eq!(granted, vec![true, false]);
+}
+
+/// **Scenario:** Planet with query workers; one explorer is watched by the host, the
+/// other isn't; the planet is then killed
+/// **Validates:**
+/// - Queries and generation requests are answered while the planet runs
+/// - The workers stop answering once the planet is gone
+#[test]
+fn test_query_workers_answer_watched_explorers() {
+    let (tx_admin, rx_admin) = unbounded();
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, tx_expl, handle) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_query_workers(2)
+            .with_admin(rx_admin)
+            .with_stats(stats.clone()),
+    );
+    let rx_expl1 = register_explorer(1, &tx_orch, &rx_orch);
+    let rx_expl2 = register_explorer(2, &tx_orch, &rx_orch);
+    let (tx_planet_to_expl1, rx_watched) = unbounded();
+    tx_orch
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 3,
+            new_sender: tx_planet_to_expl1.clone(),
+        })
+        .unwrap();
+    let _ = rx_orch.recv_timeout(Duration::from_millis(200));
+    tx_admin
+        .send(AdminCommand::WatchExplorer {
+            explorer_id: 3,
+            sender: tx_planet_to_expl1,
+        })
+        .unwrap();
+    charge_cells(1, &tx_orch, &rx_orch);
+
+    let available = |explorer_id: u32, rx_expl: &Receiver<PlanetToExplorer>| {
+        // The dispatcher is gone too once it found the planet gone
+        tx_expl
+            .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id })
+            .ok()?;
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::AvailableEnergyCellResponse { available_cells }) => {
+                Some(available_cells)
+            }
+            _ => None,
+        }
+    };
+    assert_eq!(available(3, &rx_watched), Some(1));
+    assert_eq!(available(2, &rx_expl2), Some(1));
+    tx_expl
+        .send(ExplorerToPlanet::SupportedResourceRequest { explorer_id: 3 })
+        .unwrap();
+    match rx_watched.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::SupportedResourceResponse { resource_list }) => {
+            assert_eq!(resource_list.len(), 4)
+        }
+        _ => panic!("Expected SupportedResourceResponse"),
+    }
+    assert_eq!(
+        stats.snapshot().capability_polls()[&ExplorerId::new(3)].supported_resources,
+        1
+    );
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 1,
+            resource: BasicResourceType::Oxygen,
+        })
+        .unwrap();
+    assert!(matches!(
+        rx_expl1.recv_timeout(Duration::from_millis(200)),
+        Ok(PlanetToExplorer::GenerateResourceResponse { resource: Some(_) })
+    ));
+
+    tx_orch.send(OrchestratorToPlanet::KillPlanet).unwrap();
+    handle.join().unwrap().unwrap();
+    assert_eq!(available(3, &rx_watched), None, "The planet is gone");
+    assert_eq!(available(2, &rx_expl2), None, "The planet is gone");
+}
+
+/// **Scenario:** Planet with query workers and throttled energy polls; a watched
+/// explorer polls twice in a row, then queries the stopped planet
+/// **Validates:**
+/// - The second poll is coalesced into the first answer, as without workers
+/// - Queries of a stopped planet are answered as stopped
+#[test]
+fn test_query_workers_defer_to_throttled_or_stopped_planet() {
+    let (tx_admin, rx_admin) = unbounded();
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, tx_expl, handle) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_query_workers(2)
+            .with_energy_poll_interval(Duration::from_secs(60))
+            .with_admin(rx_admin)
+            .with_stats(stats.clone()),
+    );
+    let (tx_planet_to_expl, rx_watched) = unbounded();
+    tx_orch
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id: 3,
+            new_sender: tx_planet_to_expl.clone(),
+        })
+        .unwrap();
+    let _ = rx_orch.recv_timeout(TIMEOUT);
+    tx_admin
+        .send(AdminCommand::WatchExplorer {
+            explorer_id: 3,
+            sender: tx_planet_to_expl,
+        })
+        .unwrap();
+
+    for _ in 0..2 {
+        tx_expl
+            .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 3 })
+            .unwrap();
+    }
+    assert!(matches!(
+        rx_watched.recv_timeout(TIMEOUT),
+        Ok(PlanetToExplorer::AvailableEnergyCellResponse { .. })
+    ));
+    assert!(rx_watched.recv_timeout(TIMEOUT).is_err());
+    assert_eq!(stats.snapshot().throttled_polls()[&ExplorerId::new(3)], 1);
+
+    tx_orch.send(OrchestratorToPlanet::StopPlanetAI).unwrap();
+    rx_orch.recv_timeout(TIMEOUT).unwrap();
+    tx_expl
+        .send(ExplorerToPlanet::SupportedResourceRequest { explorer_id: 3 })
+        .unwrap();
+    assert!(matches!(
+        rx_watched.recv_timeout(TIMEOUT),
+        Ok(PlanetToExplorer::Stopped)
+    ));
+
+    tx_orch.send(OrchestratorToPlanet::KillPlanet).unwrap();
+    assert_eq!(handle.join().unwrap(), Ok(()));
+}
+
+/// **Scenario:** Chaos planet dropping every response; an explorer generates a
+/// resource, then the orchestrator requests the internal state
+/// **Validates:**
+/// - The explorer gets no response, while the request was still handled
+/// - Orchestrator messages are unaffected
+#[cfg(feature = "chaos")]
+#[test]
+fn test_chaos_drops_explorer_responses() {
+    use rustrelli::chaos::ChaosConfig;
+
+    let stats = StatsHandle::new(StatsConfig::default());
+    let (tx_orch, rx_orch, tx_expl, _) = setup_configured_planet(
+        PlanetConfig::new(1)
+            .with_stats(stats.clone())
+            .with_chaos(ChaosConfig::new(3).with_dropped_responses(1.0)),
+    );
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+    charge_cells(1, &tx_orch, &rx_orch);
+    tx_expl
+        .send(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id: 1,
+            resource: BasicResourceType::Oxygen,
+        })
+        .unwrap();
+    assert!(rx_expl.recv_timeout(Duration::from_millis(200)).is_err());
+
+    tx_orch
+        .send(OrchestratorToPlanet::InternalStateRequest)
+        .unwrap();
+    match rx_orch.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToOrchestrator::InternalStateResponse { planet_state, .. }) => {
+            assert_eq!(planet_state.charged_cells_count, 0)
+        }
+        _ => panic!("Expected InternalStateResponse"),
+    }
+    assert_eq!(stats.snapshot().totals().grants, 1);
+}
+
+// ============================================================================
+// Tests: Virtual Explorers
+// ============================================================================
+
+fn reference_bots() -> Vec<(u32, Box<dyn ExplorerStrategy>)> {
+    vec![
+        (
+            1,
+            Box::new(GreedySpammer {
+                resource: BasicResourceType::Oxygen,
+            }),
+        ),
+        (
+            2,
+            Box::new(PoliteBackoff::new(BasicResourceType::Oxygen, 8)),
+        ),
+        (
+            3,
+            Box::new(PeriodicPoller::new(BasicResourceType::Oxygen, 4)),
+        ),
+    ]
+}
+
+/// **Scenario:** The reference bots play 600 ticks on a manual clock, with a sunray
+/// every tick, against a planet without limit then against a fair share planet
+/// **Validates:**
+/// - Without limit, the greedy spammer gets the most resources and the periodic
+///   poller almost none
+/// - Fair share takes resources from the spammer and gives them to the poller
+#[test]
+fn test_bots_against_limit_policies() {
+    let play = |limit: ExplorerRequestLimit| {
+        let fixture = TestPlanetFixture::builder()
+            .request_limit(limit)
+            .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+            .explorers([1, 2, 3])
+            .build();
+        run_bots(&fixture, &mut reference_bots(), 600, 1)
+    };
+    let unlimited = play(ExplorerRequestLimit::None);
+    let fair = play(ExplorerRequestLimit::FairShare);
+
+    assert!(unlimited[&1].grants > unlimited[&2].grants);
+    assert!(unlimited[&2].grants > unlimited[&3].grants);
+    assert!(unlimited[&3].grants <= 1);
+    assert!(fair[&1].grants < unlimited[&1].grants);
+    assert!(fair[&3].grants > 10 * unlimited[&3].grants.max(1));
+}
+
+/// **Scenario:** Three greedy spammers play 300 ticks on a fair share planet, with a
+/// sunray every tick
+/// **Validates:**
+/// - Each spammer gets a third of the resources
+/// - None of them waits more than a second for a resource
+#[test]
+fn test_fair_share_serves_equal_spammers_equally() {
+    let fixture = TestPlanetFixture::builder()
+        .request_limit(ExplorerRequestLimit::FairShare)
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .explorers([1, 2, 3])
+        .build();
+    let mut bots: Vec<(u32, Box<dyn ExplorerStrategy>)> = (1..=3)
+        .map(|explorer_id| {
+            let bot: Box<dyn ExplorerStrategy> = Box::new(GreedySpammer {
+                resource: BasicResourceType::Oxygen,
+            });
+            (explorer_id, bot)
+        })
+        .collect();
+    run_bots(&fixture, &mut bots, 300, 1);
+
+    let snapshot = fixture.stats.snapshot();
+    assert_shares_within(&snapshot, 0.05);
+    assert_no_starvation(&snapshot, fixture.now(), Duration::from_secs(1));
+}
+
+/// **Scenario:** The reference bots play 300 ticks on a planet without limit
+/// **Validates:** The starvation of the periodic poller is reported
+#[test]
+#[should_panic(expected = "starved")]
+fn test_starvation_without_limit_is_reported() {
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .explorers([1, 2, 3])
+        .build();
+    run_bots(&fixture, &mut reference_bots(), 300, 1);
+
+    assert_no_starvation(
+        &fixture.stats.snapshot(),
+        fixture.now(),
+        Duration::from_secs(5),
+    );
+}
+
+// ============================================================================
+// Tests: Combination
+// ============================================================================
+
+/// **Scenario:** Type A planet built with an Oxygen generation rule only; an explorer
+/// requests Carbon, then Oxygen, with a single charged cell
+/// **Validates:**
+/// - The unsupported resource is refused without discharging the cell
+/// - The supported resource is still generated
+#[test]
+fn test_unsupported_resource_is_refused() {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (tx_expl, rx_expl_to_planet) = unbounded();
+    let mut planet = create_planet_custom(
+        1,
+        PlanetType::A,
+        vec![BasicResourceType::Oxygen],
+        vec![],
+        ExplorerRequestLimit::None,
+        (rx_orch_to_planet, tx_planet_to_orch, rx_expl_to_planet),
+    )
+    .unwrap();
+    thread::spawn(move || planet.run());
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    rx_orch.recv().unwrap();
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+    charge_cells(1, &tx_orch, &rx_orch);
+
+    let generate = |resource| {
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse { resource }) => resource,
+            _ => panic!("Expected GenerateResourceResponse"),
+        }
+    };
+    assert!(generate(BasicResourceType::Carbon).is_none());
+    assert!(matches!(
+        generate(BasicResourceType::Oxygen),
+        Some(BasicResource::Oxygen(_))
+    ));
+}
+
+/// **Scenario:** Type B planet built with the rustrelli AI and a Water combination
+/// rule; an explorer generates hydrogen and oxygen, then combines them
+/// **Validates:**
+/// - The combination is served with the charged cell
+/// - Without a charged cell, the combination is refused with a coded reason and the
+///   resources returned
+#[test]
+fn test_custom_planet_combines_resources() {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (tx_expl, rx_expl_to_planet) = unbounded();
+    let mut planet = create_planet_custom(
+        1,
+        PlanetType::B,
+        vec![BasicResourceType::Hydrogen, BasicResourceType::Oxygen],
+        vec![ComplexResourceType::Water],
+        ExplorerRequestLimit::None,
+        (rx_orch_to_planet, tx_planet_to_orch, rx_expl_to_planet),
+    )
+    .unwrap();
+    thread::spawn(move || planet.run());
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    rx_orch.recv().unwrap();
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+
+    let generate = |resource| {
+        charge_cells(1, &tx_orch, &rx_orch);
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::GenerateResourceResponse {
+                resource: Some(resource),
+            }) => resource,
+            _ => panic!("Expected a generated resource"),
+        }
+    };
+    let (BasicResource::Hydrogen(hydrogen), BasicResource::Oxygen(oxygen)) = (
+        generate(BasicResourceType::Hydrogen),
+        generate(BasicResourceType::Oxygen),
+    ) else {
+        panic!("Unexpected resource types");
+    };
+
+    let combine = |hydrogen, oxygen| {
+        tx_expl
+            .send(ExplorerToPlanet::CombineResourceRequest {
+                explorer_id: 1,
+                msg: ComplexResourceRequest::Water(hydrogen, oxygen),
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(Duration::from_millis(200)) {
+            Ok(PlanetToExplorer::CombineResourceResponse { complex_response }) => complex_response,
+            _ => panic!("Expected CombineResourceResponse"),
+        }
+    };
+    let Err((
+        reason,
+        GenericResource::BasicResources(BasicResource::Hydrogen(hydrogen)),
+        GenericResource::BasicResources(BasicResource::Oxygen(oxygen)),
+    )) = combine(hydrogen, oxygen)
+    else {
+        panic!("No charged cell: resources should be returned");
+    };
+    assert_eq!(refusal::code(&reason), Some("no_energy"));
+
+    charge_cells(1, &tx_orch, &rx_orch);
+    match combine(hydrogen, oxygen) {
+        Ok(water) => assert_eq!(water.get_type(), ComplexResourceType::Water),
+        Err((reason, ..)) => panic!("Combination refused: {}", reason),
+    }
+}
+
+/// **Scenario:** Type B planet with a Water combination rule and a quota of 2 grants a
+/// minute; an explorer generates hydrogen and oxygen, then combines them
+/// **Validates:**
+/// - The combination is refused by the quota, with the resources returned
+/// - The charged cell isn't spent
+#[test]
+fn test_combination_respects_request_limit() {
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (tx_expl, rx_expl_to_planet) = unbounded();
+    let mut planet = create_planet_custom(
+        1,
+        PlanetType::B,
+        vec![BasicResourceType::Hydrogen, BasicResourceType::Oxygen],
+        vec![ComplexResourceType::Water],
+        ExplorerRequestLimit::Quota(Quota::new(2, Duration::from_secs(60))),
+        (rx_orch_to_planet, tx_planet_to_orch, rx_expl_to_planet),
+    )
+    .unwrap();
+    let handle = thread::spawn(move || planet.run());
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+    rx_orch.recv().unwrap();
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+
+    let generate = |resource| {
+        charge_cells(1, &tx_orch, &rx_orch);
+        tx_expl
+            .send(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id: 1,
+                resource,
+            })
+            .unwrap();
+        match rx_expl.recv_timeout(TIMEOUT) {
+            Ok(PlanetToExplorer::GenerateResourceResponse {
+                resource: Some(resource),
+            }) => resource,
+            _ => panic!("Expected a generated resource"),
+        }
+    };
+    let (BasicResource::Hydrogen(hydrogen), BasicResource::Oxygen(oxygen)) = (
+        generate(BasicResourceType::Hydrogen),
+        generate(BasicResourceType::Oxygen),
+    ) else {
+        panic!("Unexpected resource types");
+    };
+
+    charge_cells(1, &tx_orch, &rx_orch);
+    tx_expl
+        .send(ExplorerToPlanet::CombineResourceRequest {
+            explorer_id: 1,
+            msg: ComplexResourceRequest::Water(hydrogen, oxygen),
+        })
+        .unwrap();
+    match rx_expl.recv_timeout(TIMEOUT) {
+        Ok(PlanetToExplorer::CombineResourceResponse {
+            complex_response:
+                Err((
+                    reason,
+                    GenericResource::BasicResources(BasicResource::Hydrogen(_)),
+                    GenericResource::BasicResources(BasicResource::Oxygen(_)),
+                )),
+        }) => assert_eq!(refusal::code(&reason), Some("quota_exceeded")),
+        _ => panic!("Expected a combination refused with the resources returned"),
+    }
+    tx_expl
+        .send(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 })
+        .unwrap();
+    assert!(matches!(
+        rx_expl.recv_timeout(TIMEOUT),
+        Ok(PlanetToExplorer::AvailableEnergyCellResponse { available_cells: 1 })
+    ));
+
+    tx_orch.send(OrchestratorToPlanet::KillPlanet).unwrap();
+    assert_eq!(handle.join().unwrap(), Ok(()));
+}
+
+/// **Scenario:** Type D planet configured with a localized refusal formatter
+/// **Validates:**
+/// - The combination is refused with the structured reason rendered by the formatter
+#[test]
+fn test_combination_refusal_uses_custom_formatter() {
+    let config =
+        PlanetConfig::new(1).with_refusal_formatter(|reason: &RefusalReason| match reason {
+            RefusalReason::NoRecipes => "Questo pianeta non combina risorse.".to_string(),
+            other => other.code().to_string(),
+        });
+    let (tx_orch, rx_orch, tx_expl, _handle) = setup_configured_planet(config);
+    let rx_expl = register_explorer(1, &tx_orch, &rx_orch);
+
+    let [first, second] =
+        [BasicResourceType::Hydrogen, BasicResourceType::Oxygen].map(|resource| {
+            charge_cells(1, &tx_orch, &rx_orch);
+            tx_expl
+                .send(ExplorerToPlanet::GenerateResourceRequest {
+                    explorer_id: 1,
+                    resource,
+                })
+                .unwrap();
+            match rx_expl.recv_timeout(Duration::from_millis(200)) {
+                Ok(PlanetToExplorer::GenerateResourceResponse {
+                    resource: Some(resource),
+                }) => resource,
+                _ => panic!("Expected a generated resource"),
+            }
+        });
+    let (BasicResource::Hydrogen(hydrogen), BasicResource::Oxygen(oxygen)) = (first, second) else {
+        panic!("Unexpected resource types");
+    };
+
+    tx_expl
+        .send(ExplorerToPlanet::CombineResourceRequest {
+            explorer_id: 1,
+            msg: ComplexResourceRequest::Water(hydrogen, oxygen),
+        })
+        .unwrap();
+    match rx_expl.recv_timeout(Duration::from_millis(200)) {
+        Ok(PlanetToExplorer::CombineResourceResponse {
+            complex_response: Err((reason, ..)),
+        }) => assert_eq!(reason, "Questo pianeta non combina risorse."),
+        _ => panic!("Expected a refused combination"),
+    }
+}
+
+// ============================================================================
+// Tests: State Changes
+// ============================================================================
+
+/// **Scenario:** With deferred fulfillment, a pending timeout and backoff, the host
+/// pauses and resumes the planet, tags explorers, assigns a quota, reserves a cell and
+/// marks an explorer unreachable, while requests are queued, retried too early and
+/// expire, then a cell is granted to the reservation holder
+/// **Validates:**
+/// - Replaying the changes rebuilds the state of the AI after each message: no
+///   invariant is violated, and the planet stops cleanly
+/// - The queued requests expired
+#[test]
+fn test_changes_replay_to_the_live_state() {
+    let (tx_admin, rx_admin) = unbounded();
+    let (tx_fulfill, rx_fulfill) = unbounded();
+    let (tx_events, rx_events) = unbounded();
+    let fixture = TestPlanetFixture::builder()
+        .manual_clock(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .configure(move |config| {
+            config
+                .with_admin(rx_admin)
+                .with_deferred_fulfillment(4, tx_fulfill)
+                .with_pending_timeout(Duration::from_secs(10))
+                .with_backoff(BackoffConfig::new([Duration::from_secs(1)]))
+                .with_events(tx_events)
+        })
+        .explorers([1, 2])
+        .build();
+    let admin = |command| tx_admin.send(command).unwrap();
+    let red = Tag::new("team", "red");
+
+    admin(AdminCommand::Pause {
+        mode: PauseMode::Buffer,
+    });
+    assert!(fixture.generate(1, BasicResourceType::Oxygen).is_none());
+    admin(AdminCommand::Resume);
+    admin(AdminCommand::Tag {
+        explorer_id: 2,
+        tag: red.clone(),
+    });
+    admin(AdminCommand::SetQuota {
+        explorer_id: 2,
+        quota: Quota::new(5, Duration::from_secs(60)),
+    });
+    admin(AdminCommand::Reserve { explorer_id: 2 });
+    admin(AdminCommand::ExplorerUnreachable { explorer_id: 1 });
+    assert!(fixture.generate(2, BasicResourceType::Carbon).is_none());
+    fixture.advance(Duration::from_millis(500));
+    assert!(
+        fixture.generate(2, BasicResourceType::Carbon).is_none(),
+        "Retried too early"
+    );
+
+    fixture.advance(Duration::from_secs(30));
+    admin(AdminCommand::ExplorerReachable { explorer_id: 1 });
+    admin(AdminCommand::Untag {
+        explorer_id: 2,
+        tag: red,
+    });
+    admin(AdminCommand::Tick);
+    fixture.request(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id: 1 });
+    fixture.charge(2);
+    assert!(fixture.generate(2, BasicResourceType::Carbon).is_some());
+    assert!(rx_fulfill.try_recv().is_err());
+
+    fixture
+        .orchestrator
+        .send(OrchestratorToPlanet::KillPlanet)
+        .unwrap();
+    assert_eq!(fixture.handle.join().unwrap(), Ok(()));
+    assert!(
+        !sent_events(&rx_events)
+            .iter()
+            .any(|event| matches!(event, Event::InvariantViolated { .. }))
+    );
+    assert_eq!(fixture.stats.snapshot().pending().expired, 2);
+}
@@ -0,0 +1,295 @@
+//! Soak test of a planet running for hours.
+//!
+//! The test is ignored by default, run it with:
+//! ```text
+//! cargo test --release --test soak_test -- --ignored --nocapture
+//! ```
+//!
+//! The planet receives sunrays on a day and night cycle, while explorers keep arriving,
+//! polling, generating and leaving. The test checks that the planet never panics and
+//! that its memory stays bounded, the statistics evicting their old buckets.
+//!
+//! Environment variables:
+//! - `RUSTRELLI_SOAK_SECS`: duration of the run, two hours by default
+//! - `RUSTRELLI_SOAK_SNAPSHOT_SECS`: interval between snapshots, a minute by default
+//! - `RUSTRELLI_SOAK_OUT`: CSV file the snapshots are written to,
+//!   `target/soak-<timestamp>.csv` by default
+
+use common_game::components::resource::BasicResourceType;
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use rustrelli::stats::{Stats, StatsConfig, StatsHandle};
+use rustrelli::{ExplorerRequestLimit, PlanetConfig, create_planet_with_config};
+use std::f64::consts::PI;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// ============================================================================
+// Test Configuration
+// ============================================================================
+
+/// Explorer IDs are recycled from this pool, bounding the per-explorer state.
+const EXPLORER_POOL: u32 = 64;
+/// Explorers on the planet at any time.
+const PRESENT_EXPLORERS: usize = 16;
+/// Length of a day, from a sunray every 20ms at noon to one every 250ms at midnight.
+const DAY: Duration = Duration::from_secs(60);
+const TICK: Duration = Duration::from_millis(5);
+const STATS_CONFIG: StatsConfig = StatsConfig {
+    bucket_width: Duration::from_secs(1),
+    max_buckets: 30,
+};
+/// Growth of the resident memory tolerated after the first snapshot.
+const MEMORY_SLACK: u64 = 32 * 1024 * 1024;
+
+fn env_secs(name: &str, default: u64) -> Duration {
+    let secs = std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default);
+    Duration::from_secs(secs)
+}
+
+// ============================================================================
+// Test Helpers
+// ============================================================================
+
+/// Xorshift generator, enough to vary the explorer behavior reproducibly.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// Interval between sunrays at `elapsed` into the run.
+fn sunray_interval(elapsed: Duration) -> Duration {
+    let phase = (elapsed.as_secs_f64() / DAY.as_secs_f64()) * 2.0 * PI;
+    let daylight = (1.0 - phase.cos()) / 2.0;
+    Duration::from_millis(250).mul_f64(1.0 - daylight) + Duration::from_millis(20).mul_f64(daylight)
+}
+
+/// Resident memory of the process, where `/proc` is available.
+fn resident_memory() -> Option<u64> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+fn snapshot_path() -> PathBuf {
+    match std::env::var("RUSTRELLI_SOAK_OUT") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("target")
+                .join(format!("soak-{timestamp}.csv"))
+        }
+    }
+}
+
+fn write_snapshot(out: &mut impl Write, elapsed: Duration, stats: &Stats, rss: Option<u64>) {
+    let totals = stats.totals();
+    let state = stats.extended_state();
+    writeln!(
+        out,
+        "{},{},{},{},{},{},{},{},{}",
+        elapsed.as_secs(),
+        totals.sunrays,
+        totals.grants,
+        totals.denials,
+        stats.buckets().count(),
+        stats.capability_polls().len(),
+        stats.dead_letters().len(),
+        state.charged_cells_count,
+        rss.map_or(String::new(), |rss| rss.to_string()),
+    )
+    .unwrap();
+    out.flush().unwrap();
+}
+
+/// Explorers coming and going, all answered on the same channel.
+struct Explorers {
+    present: Vec<u32>,
+    next_id: u32,
+    to_explorers: Sender<PlanetToExplorer>,
+    responses: Receiver<PlanetToExplorer>,
+}
+
+impl Explorers {
+    fn new() -> Self {
+        let (to_explorers, responses) = unbounded();
+        Explorers {
+            present: Vec::new(),
+            next_id: 0,
+            to_explorers,
+            responses,
+        }
+    }
+
+    /// Brings a new explorer, replacing a random one if the planet is crowded.
+    fn churn(&mut self, rng: &mut Rng, tx_orch: &Sender<OrchestratorToPlanet>) {
+        if self.present.len() >= PRESENT_EXPLORERS {
+            let leaving = self
+                .present
+                .swap_remove(rng.below(self.present.len() as u64) as usize);
+            tx_orch
+                .send(OrchestratorToPlanet::OutgoingExplorerRequest {
+                    explorer_id: leaving,
+                })
+                .unwrap();
+        }
+        let explorer_id = self.next_id % EXPLORER_POOL;
+        self.next_id += 1;
+        if self.present.contains(&explorer_id) {
+            return;
+        }
+        self.present.push(explorer_id);
+        tx_orch
+            .send(OrchestratorToPlanet::IncomingExplorerRequest {
+                explorer_id,
+                new_sender: self.to_explorers.clone(),
+            })
+            .unwrap();
+    }
+
+    /// Sends a random message from a random explorer.
+    fn act(&self, rng: &mut Rng, tx_expl: &Sender<ExplorerToPlanet>) {
+        let Some(&explorer_id) = self
+            .present
+            .get(rng.below(PRESENT_EXPLORERS as u64) as usize)
+        else {
+            return;
+        };
+        let msg = match rng.below(10) {
+            0 => ExplorerToPlanet::SupportedResourceRequest { explorer_id },
+            1 => ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id },
+            _ => ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: BasicResourceType::Oxygen,
+            },
+        };
+        tx_expl.send(msg).unwrap();
+    }
+}
+
+// ============================================================================
+// Tests: Soak
+// ============================================================================
+
+/// **Scenario:** Planet under fair share runs for hours, with sunrays on a day and
+/// night cycle and explorers churning
+/// **Validates:**
+/// - The planet thread never panics nor stops on its own
+/// - Statistics keep a bounded number of buckets and of tracked explorers
+/// - Resident memory stays flat once warmed up
+#[test]
+#[ignore = "runs for hours, see the module documentation"]
+fn soak_planet_for_hours() {
+    let duration = env_secs("RUSTRELLI_SOAK_SECS", 2 * 60 * 60);
+    let snapshot_interval = env_secs("RUSTRELLI_SOAK_SNAPSHOT_SECS", 60);
+    let path = snapshot_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    let mut out = BufWriter::new(File::create(&path).unwrap());
+    writeln!(
+        out,
+        "elapsed_secs,sunrays,grants,denials,buckets,polling_explorers,dead_letters,charged_cells,rss_bytes"
+    )
+    .unwrap();
+    println!(
+        "Soaking for {:?}, snapshots in {}",
+        duration,
+        path.display()
+    );
+
+    let (tx_orch, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_orch) = unbounded();
+    let (tx_expl, rx_expl_to_planet) = unbounded();
+    let stats = StatsHandle::new(STATS_CONFIG);
+    let mut planet = create_planet_with_config(
+        PlanetConfig::new(1)
+            .with_request_limit(ExplorerRequestLimit::FairShare)
+            .with_stats(stats.clone()),
+        rx_orch_to_planet,
+        tx_planet_to_orch,
+        rx_expl_to_planet,
+    );
+    let planet = thread::spawn(move || planet.run());
+    tx_orch.send(OrchestratorToPlanet::StartPlanetAI).unwrap();
+
+    let mut rng = Rng(0x5EED);
+    let mut explorers = Explorers::new();
+    let start = Instant::now();
+    let (mut next_sunray, mut next_churn, mut next_snapshot) = (start, start, start);
+    let mut warm_memory = None;
+
+    while start.elapsed() < duration {
+        let now = Instant::now();
+        assert!(!planet.is_finished(), "The planet stopped on its own");
+        if now >= next_sunray {
+            tx_orch
+                .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+                .unwrap();
+            next_sunray = now + sunray_interval(start.elapsed());
+        }
+        if now >= next_churn {
+            explorers.churn(&mut rng, &tx_orch);
+            next_churn = now + Duration::from_millis(200 + rng.below(800));
+        }
+        for _ in 0..rng.below(4) {
+            explorers.act(&mut rng, &tx_expl);
+        }
+        if now >= next_snapshot {
+            let snapshot = stats.snapshot();
+            let rss = resident_memory();
+            write_snapshot(&mut out, start.elapsed(), &snapshot, rss);
+            assert!(snapshot.buckets().count() <= STATS_CONFIG.max_buckets);
+            assert!(snapshot.capability_polls().len() <= EXPLORER_POOL as usize);
+            match (warm_memory, rss) {
+                (None, _) if !start.elapsed().is_zero() => warm_memory = rss,
+                (Some(warm), Some(rss)) => assert!(
+                    rss <= warm + MEMORY_SLACK,
+                    "Resident memory grew from {warm} to {rss} bytes"
+                ),
+                _ => {}
+            }
+            next_snapshot = now + snapshot_interval;
+        }
+        // Responses are only drained, explorers don't react to them
+        while explorers.responses.try_recv().is_ok() {}
+        while rx_orch.try_recv().is_ok() {}
+        thread::sleep(TICK);
+    }
+
+    write_snapshot(
+        &mut out,
+        start.elapsed(),
+        &stats.snapshot(),
+        resident_memory(),
+    );
+    tx_orch.send(OrchestratorToPlanet::KillPlanet).unwrap();
+    assert!(
+        rx_orch
+            .iter()
+            .any(|msg| matches!(msg, PlanetToOrchestrator::KillPlanetResult { .. }))
+    );
+    planet.join().unwrap().unwrap();
+}
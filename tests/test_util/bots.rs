@@ -0,0 +1,171 @@
+//! Virtual explorer bots.
+//!
+//! Each bot plays an [`ExplorerStrategy`] against a [`TestPlanetFixture`], so that tests
+//! compare how the limit policies fare against each style of explorer:
+//! - [`GreedySpammer`]: requests a resource every tick, whatever the answers
+//! - [`PoliteBackoff`]: backs off exponentially after each denial
+//! - [`PeriodicPoller`]: checks the available energy periodically, and only requests a
+//!   resource when a cell is charged
+
+use super::TestPlanetFixture;
+use common_game::components::resource::BasicResourceType;
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Time a tick lasts, on the manual clock of the fixture if any.
+pub const TICK: Duration = Duration::from_millis(100);
+
+/// Behavior of a virtual explorer, ticked by [`run_bots`].
+pub trait ExplorerStrategy {
+    /// The message to send at `tick`, if any.
+    fn next_message(&mut self, explorer_id: u32, tick: u64) -> Option<ExplorerToPlanet>;
+
+    /// Feedback of the planet, to the message sent at `tick`.
+    fn on_response(&mut self, _response: &PlanetToExplorer, _tick: u64) {}
+}
+
+/// Requests `resource` at every tick.
+pub struct GreedySpammer {
+    pub resource: BasicResourceType,
+}
+
+impl ExplorerStrategy for GreedySpammer {
+    fn next_message(&mut self, explorer_id: u32, _tick: u64) -> Option<ExplorerToPlanet> {
+        Some(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource: self.resource,
+        })
+    }
+}
+
+/// Requests `resource`, waiting twice as many ticks after each consecutive denial, up
+/// to `max_backoff`.
+pub struct PoliteBackoff {
+    pub resource: BasicResourceType,
+    pub max_backoff: u64,
+    backoff: u64,
+    next_tick: u64,
+}
+
+impl PoliteBackoff {
+    pub fn new(resource: BasicResourceType, max_backoff: u64) -> Self {
+        PoliteBackoff {
+            resource,
+            max_backoff,
+            backoff: 0,
+            next_tick: 0,
+        }
+    }
+}
+
+impl ExplorerStrategy for PoliteBackoff {
+    fn next_message(&mut self, explorer_id: u32, tick: u64) -> Option<ExplorerToPlanet> {
+        (tick >= self.next_tick).then_some(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource: self.resource,
+        })
+    }
+
+    fn on_response(&mut self, response: &PlanetToExplorer, tick: u64) {
+        if let PlanetToExplorer::GenerateResourceResponse { resource } = response {
+            self.backoff = match resource {
+                Some(_) => 0,
+                None => (self.backoff * 2).clamp(1, self.max_backoff),
+            };
+            self.next_tick = tick + 1 + self.backoff;
+        }
+    }
+}
+
+/// Asks for the available energy every `period` ticks, and requests `resource` at the
+/// next tick when a cell is charged.
+pub struct PeriodicPoller {
+    pub resource: BasicResourceType,
+    pub period: u64,
+    cells_seen: bool,
+}
+
+impl PeriodicPoller {
+    pub fn new(resource: BasicResourceType, period: u64) -> Self {
+        PeriodicPoller {
+            resource,
+            period,
+            cells_seen: false,
+        }
+    }
+}
+
+impl ExplorerStrategy for PeriodicPoller {
+    fn next_message(&mut self, explorer_id: u32, tick: u64) -> Option<ExplorerToPlanet> {
+        if std::mem::take(&mut self.cells_seen) {
+            return Some(ExplorerToPlanet::GenerateResourceRequest {
+                explorer_id,
+                resource: self.resource,
+            });
+        }
+        tick.is_multiple_of(self.period)
+            .then_some(ExplorerToPlanet::AvailableEnergyCellRequest { explorer_id })
+    }
+
+    fn on_response(&mut self, response: &PlanetToExplorer, _tick: u64) {
+        if let PlanetToExplorer::AvailableEnergyCellResponse { available_cells } = response {
+            self.cells_seen = *available_cells > 0;
+        }
+    }
+}
+
+/// What a bot got out of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BotOutcome {
+    /// Messages sent.
+    pub messages: u64,
+    /// Generation requests sent.
+    pub requests: u64,
+    /// Resources received.
+    pub grants: u64,
+}
+
+/// Runs `bots` on the fixture for `ticks` ticks, with a sunray every `sunray_every`
+/// ticks. Bots act in turn within a tick, each waiting for its response, the first
+/// one changing at every tick. The manual clock of the fixture, if any, moves by
+/// [`TICK`] at every tick.
+///
+/// # Returns
+/// The outcome of each bot, by explorer ID.
+///
+/// # Panics
+/// Panics if an explorer isn't registered on the fixture.
+pub fn run_bots(
+    fixture: &TestPlanetFixture,
+    bots: &mut [(u32, Box<dyn ExplorerStrategy>)],
+    ticks: u64,
+    sunray_every: u64,
+) -> BTreeMap<u32, BotOutcome> {
+    let mut outcomes: BTreeMap<u32, BotOutcome> = BTreeMap::new();
+    for tick in 0..ticks {
+        if let Some(clock) = &fixture.clock {
+            clock.advance(TICK);
+        }
+        if tick.is_multiple_of(sunray_every) {
+            fixture.charge(1);
+        }
+        let first = (tick % bots.len().max(1) as u64) as usize;
+        bots.rotate_left(first);
+        for (explorer_id, bot) in bots.iter_mut() {
+            let Some(msg) = bot.next_message(*explorer_id, tick) else {
+                continue;
+            };
+            let outcome = outcomes.entry(*explorer_id).or_default();
+            outcome.messages += 1;
+            let response = fixture.request(msg);
+            if let PlanetToExplorer::GenerateResourceResponse { resource } = &response {
+                outcome.requests += 1;
+                outcome.grants += u64::from(resource.is_some());
+            }
+            bot.on_response(&response, tick);
+        }
+        bots.rotate_right(first);
+    }
+    outcomes
+}
@@ -0,0 +1,101 @@
+//! Fairness assertions on a statistics snapshot.
+//!
+//! They check outcomes rather than message flows, and fail with a table of every
+//! explorer so that the unfair one stands out:
+//! - [`assert_shares_within`]: explorers got equal shares of the epoch grants
+//! - [`assert_shares_match`]: explorers got the given shares of the epoch grants
+//! - [`assert_no_starvation`]: no explorer waited longer than a window for a grant
+
+use rustrelli::ExplorerId;
+use rustrelli::stats::Stats;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::time::{Duration, SystemTime};
+
+/// Share of the epoch grants each explorer that requested resources got.
+pub fn shares(stats: &Stats) -> BTreeMap<ExplorerId, f64> {
+    let epoch = stats.epoch();
+    let total: u64 = epoch.grants.values().sum();
+    epoch
+        .grants
+        .keys()
+        .chain(epoch.denials.keys())
+        .map(|explorer_id| {
+            let grants = epoch.grants.get(explorer_id).copied().unwrap_or_default();
+            (*explorer_id, grants as f64 / total.max(1) as f64)
+        })
+        .collect()
+}
+
+/// Asserts that every explorer that requested resources in the epoch got an equal
+/// share of the grants, within `epsilon`.
+#[track_caller]
+pub fn assert_shares_within(stats: &Stats, epsilon: f64) {
+    let actual = shares(stats);
+    let equal = 1.0 / actual.len().max(1) as f64;
+    let expected = actual
+        .keys()
+        .map(|explorer_id| (*explorer_id, equal))
+        .collect();
+    assert_shares_match(stats, &expected, epsilon);
+}
+
+/// Asserts that each explorer got its `expected` share of the epoch grants, within
+/// `epsilon`. Explorers missing from `expected` are expected to get nothing.
+#[track_caller]
+pub fn assert_shares_match(stats: &Stats, expected: &BTreeMap<ExplorerId, f64>, epsilon: f64) {
+    let actual = shares(stats);
+    let epoch = stats.epoch();
+    let mut failed = false;
+    let mut table = String::from("explorer | grants | denials | share  | expected\n");
+    for explorer_id in actual
+        .keys()
+        .chain(expected.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        let share = actual.get(explorer_id).copied().unwrap_or_default();
+        let target = expected.get(explorer_id).copied().unwrap_or_default();
+        let off = (share - target).abs() > epsilon;
+        failed |= off;
+        writeln!(
+            table,
+            "{:>8} | {:>6} | {:>7} | {:.4} | {:.4}{}",
+            explorer_id,
+            epoch.grants.get(explorer_id).copied().unwrap_or_default(),
+            epoch.denials.get(explorer_id).copied().unwrap_or_default(),
+            share,
+            target,
+            if off { "  <- off" } else { "" }
+        )
+        .unwrap();
+    }
+    assert!(
+        !failed,
+        "Shares of the grants off by more than {epsilon}:\n{table}"
+    );
+}
+
+/// Asserts that no explorer waited longer than `window` for a grant, counting waits
+/// still ongoing at `now` (the time of the planet clock).
+#[track_caller]
+pub fn assert_no_starvation(stats: &Stats, now: SystemTime, window: Duration) {
+    let mut starved = false;
+    let mut table = String::from("explorer | longest wait | waiting\n");
+    for (explorer_id, wait) in stats.waits() {
+        let longest = wait.longest_at(now);
+        starved |= longest > window;
+        writeln!(
+            table,
+            "{:>8} | {:>12?} | {}{}",
+            explorer_id,
+            longest,
+            wait.unserved_since.is_some(),
+            if longest > window { "  <- starved" } else { "" }
+        )
+        .unwrap();
+    }
+    assert!(
+        !starved,
+        "Explorers waited longer than {window:?} for a grant:\n{table}"
+    );
+}
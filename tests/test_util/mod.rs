@@ -0,0 +1,343 @@
+//! Helpers shared by the integration tests.
+//!
+//! [`TestPlanetFixture::builder`] sets up a running planet in one call: limit mode,
+//! manual clock, charged cells and registered explorers. The lower-level helpers
+//! ([`setup_test_planet`], [`setup_configured_planet`], [`register_explorer`] and
+//! [`charge_cells`]) remain for tests that drive the raw channels.
+//!
+//! The [`bots`] submodule plays virtual explorers against a fixture, and the
+//! [`fairness`] one asserts how fairly they were served.
+//!
+//! Each test crate uses part of the helpers only.
+#![allow(dead_code)]
+
+pub mod bots;
+pub mod fairness;
+
+use common_game::components::resource::{BasicResource, BasicResourceType};
+use common_game::components::sunray::Sunray;
+use common_game::protocols::orchestrator_planet::{OrchestratorToPlanet, PlanetToOrchestrator};
+use common_game::protocols::planet_explorer::{ExplorerToPlanet, PlanetToExplorer};
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use rustrelli::clock::{Clock, ManualClock};
+use rustrelli::events::Event;
+use rustrelli::policy::Policy;
+use rustrelli::stats::{StatsConfig, StatsHandle};
+use rustrelli::{ExplorerRequestLimit, PlanetConfig, create_planet, create_planet_with_config};
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long the helpers wait for a response of the planet.
+pub const TIMEOUT: Duration = Duration::from_millis(200);
+
+// ============================================================================
+// Channel Helpers
+// ============================================================================
+
+#[allow(clippy::type_complexity)]
+pub fn setup_test_planet() -> (
+    Sender<OrchestratorToPlanet>,
+    Receiver<PlanetToOrchestrator>,
+    Sender<ExplorerToPlanet>,
+    thread::JoinHandle<Result<(), String>>,
+) {
+    let (tx_orch_to_planet, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_planet_to_orch) = unbounded();
+    let (tx_expl_to_planet, rx_expl_to_planet) = unbounded();
+
+    let mut planet = create_planet(
+        1,
+        rx_orch_to_planet,
+        tx_planet_to_orch,
+        rx_expl_to_planet,
+        ExplorerRequestLimit::None,
+    );
+
+    let handle = thread::spawn(move || planet.run());
+
+    tx_orch_to_planet
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    rx_planet_to_orch.recv().unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    (
+        tx_orch_to_planet,
+        rx_planet_to_orch,
+        tx_expl_to_planet,
+        handle,
+    )
+}
+
+#[allow(clippy::type_complexity)]
+pub fn setup_configured_planet(
+    config: PlanetConfig,
+) -> (
+    Sender<OrchestratorToPlanet>,
+    Receiver<PlanetToOrchestrator>,
+    Sender<ExplorerToPlanet>,
+    thread::JoinHandle<Result<(), String>>,
+) {
+    let (tx_orch_to_planet, rx_orch_to_planet) = unbounded();
+    let (tx_planet_to_orch, rx_planet_to_orch) = unbounded();
+    let (tx_expl_to_planet, rx_expl_to_planet) = unbounded();
+
+    let mut planet = create_planet_with_config(
+        config,
+        rx_orch_to_planet,
+        tx_planet_to_orch,
+        rx_expl_to_planet,
+    );
+
+    let handle = thread::spawn(move || planet.run());
+
+    tx_orch_to_planet
+        .send(OrchestratorToPlanet::StartPlanetAI)
+        .unwrap();
+    rx_planet_to_orch.recv().unwrap();
+
+    (
+        tx_orch_to_planet,
+        rx_planet_to_orch,
+        tx_expl_to_planet,
+        handle,
+    )
+}
+
+pub fn register_explorer(
+    explorer_id: u32,
+    tx_orch: &Sender<OrchestratorToPlanet>,
+    rx_orch: &Receiver<PlanetToOrchestrator>,
+) -> Receiver<PlanetToExplorer> {
+    let (tx_planet_to_expl, rx_planet_to_expl) = unbounded();
+    tx_orch
+        .send(OrchestratorToPlanet::IncomingExplorerRequest {
+            explorer_id,
+            new_sender: tx_planet_to_expl,
+        })
+        .unwrap();
+    let _ = rx_orch.recv_timeout(TIMEOUT);
+    rx_planet_to_expl
+}
+
+/// Receives the next event other than an [`Event::Lifecycle`], waiting up to
+/// [`TIMEOUT`].
+pub fn next_event(events: &Receiver<Event>) -> Option<Event> {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        match events.recv_deadline(deadline) {
+            Ok(Event::Lifecycle { .. }) => continue,
+            received => return received.ok(),
+        }
+    }
+}
+
+/// Events sent so far, other than the [`Event::Lifecycle`] ones.
+pub fn sent_events(events: &Receiver<Event>) -> Vec<Event> {
+    events
+        .try_iter()
+        .filter(|event| !matches!(event, Event::Lifecycle { .. }))
+        .collect()
+}
+
+pub fn charge_cells(
+    count: usize,
+    tx_orch: &Sender<OrchestratorToPlanet>,
+    rx_orch: &Receiver<PlanetToOrchestrator>,
+) {
+    for _ in 0..count {
+        tx_orch
+            .send(OrchestratorToPlanet::Sunray(Sunray::default()))
+            .unwrap();
+        let _ = rx_orch.recv_timeout(TIMEOUT);
+    }
+}
+
+// ============================================================================
+// Planet Fixture
+// ============================================================================
+
+/// Running planet with its channels, statistics and registered explorers.
+pub struct TestPlanetFixture {
+    pub orchestrator: Sender<OrchestratorToPlanet>,
+    pub from_planet: Receiver<PlanetToOrchestrator>,
+    pub to_planet: Sender<ExplorerToPlanet>,
+    pub stats: StatsHandle,
+    /// The clock of the planet, if the builder set a manual one.
+    pub clock: Option<ManualClock>,
+    pub handle: thread::JoinHandle<Result<(), String>>,
+    explorers: HashMap<u32, Receiver<PlanetToExplorer>>,
+}
+
+impl TestPlanetFixture {
+    pub fn builder() -> TestPlanetFixtureBuilder {
+        TestPlanetFixtureBuilder::default()
+    }
+
+    /// The channel the planet answers `explorer_id` on.
+    ///
+    /// # Panics
+    /// Panics if the explorer isn't registered.
+    pub fn explorer(&self, explorer_id: u32) -> &Receiver<PlanetToExplorer> {
+        self.explorers
+            .get(&explorer_id)
+            .unwrap_or_else(|| panic!("Explorer {explorer_id} isn't registered"))
+    }
+
+    /// Registers `explorer_id` on the planet.
+    pub fn register(&mut self, explorer_id: u32) -> &Receiver<PlanetToExplorer> {
+        let rx = register_explorer(explorer_id, &self.orchestrator, &self.from_planet);
+        self.explorers
+            .entry(explorer_id)
+            .insert_entry(rx)
+            .into_mut()
+    }
+
+    /// Charges `count` cells, waiting for each sunray to be acknowledged.
+    pub fn charge(&self, count: usize) {
+        charge_cells(count, &self.orchestrator, &self.from_planet);
+    }
+
+    /// Sends `count` sunrays following `schedule` (see [`rustrelli::sunrays`]), sleeping
+    /// each interval before the next sunray, and moving the manual clock too if any.
+    pub fn charge_on(&self, schedule: impl Iterator<Item = Duration>, count: usize) {
+        for interval in schedule.take(count) {
+            thread::sleep(interval);
+            if let Some(clock) = &self.clock {
+                clock.advance(interval);
+            }
+            self.charge(1);
+        }
+    }
+
+    /// Current time of the planet clock.
+    pub fn now(&self) -> SystemTime {
+        self.clock
+            .as_ref()
+            .map_or_else(SystemTime::now, |clock| clock.now())
+    }
+
+    /// Moves the manual clock of the planet forward.
+    ///
+    /// # Panics
+    /// Panics if the builder didn't set a manual clock.
+    pub fn advance(&self, duration: Duration) {
+        self.clock
+            .as_ref()
+            .expect("The fixture has no manual clock")
+            .advance(duration);
+    }
+
+    /// Sends `msg` and waits for the response to its explorer.
+    ///
+    /// # Panics
+    /// Panics if the explorer isn't registered or gets no response in time.
+    pub fn request(&self, msg: ExplorerToPlanet) -> PlanetToExplorer {
+        let rx = self.explorer(msg.explorer_id());
+        self.to_planet.send(msg).unwrap();
+        rx.recv_timeout(TIMEOUT).expect("Explorer response")
+    }
+
+    /// Requests `resource` for `explorer_id`.
+    ///
+    /// # Returns
+    /// The generated resource, `None` if the request was denied.
+    pub fn generate(&self, explorer_id: u32, resource: BasicResourceType) -> Option<BasicResource> {
+        match self.request(ExplorerToPlanet::GenerateResourceRequest {
+            explorer_id,
+            resource,
+        }) {
+            PlanetToExplorer::GenerateResourceResponse { resource } => resource,
+            other => panic!("Expected GenerateResourceResponse, got {:?}", other),
+        }
+    }
+}
+
+/// Builder of a [`TestPlanetFixture`].
+pub struct TestPlanetFixtureBuilder {
+    request_limit: Policy,
+    stats_config: StatsConfig,
+    clock: Option<ManualClock>,
+    configure: Box<dyn FnOnce(PlanetConfig) -> PlanetConfig>,
+    charged_cells: usize,
+    explorers: Vec<u32>,
+}
+
+impl Default for TestPlanetFixtureBuilder {
+    fn default() -> Self {
+        TestPlanetFixtureBuilder {
+            request_limit: ExplorerRequestLimit::None.into(),
+            stats_config: StatsConfig::default(),
+            clock: None,
+            configure: Box::new(|config| config),
+            charged_cells: 0,
+            explorers: Vec::new(),
+        }
+    }
+}
+
+impl TestPlanetFixtureBuilder {
+    pub fn request_limit(mut self, request_limit: impl Into<Policy>) -> Self {
+        self.request_limit = request_limit.into();
+        self
+    }
+
+    pub fn stats_config(mut self, stats_config: StatsConfig) -> Self {
+        self.stats_config = stats_config;
+        self
+    }
+
+    /// Gives the planet a manual clock, stopped at `start`.
+    pub fn manual_clock(mut self, start: SystemTime) -> Self {
+        self.clock = Some(ManualClock::new(start));
+        self
+    }
+
+    /// Applies any other setting to the configuration of the planet.
+    pub fn configure(
+        mut self,
+        configure: impl FnOnce(PlanetConfig) -> PlanetConfig + 'static,
+    ) -> Self {
+        self.configure = Box::new(configure);
+        self
+    }
+
+    pub fn charged_cells(mut self, count: usize) -> Self {
+        self.charged_cells = count;
+        self
+    }
+
+    pub fn explorers(mut self, explorer_ids: impl IntoIterator<Item = u32>) -> Self {
+        self.explorers.extend(explorer_ids);
+        self
+    }
+
+    /// Starts the planet, registers the explorers then charges the cells.
+    pub fn build(self) -> TestPlanetFixture {
+        let stats = StatsHandle::new(self.stats_config);
+        let mut config = PlanetConfig::new(1)
+            .with_request_limit(self.request_limit)
+            .with_stats(stats.clone());
+        if let Some(clock) = &self.clock {
+            config = config.with_clock(clock.clone());
+        }
+        let (orchestrator, from_planet, to_planet, handle) =
+            setup_configured_planet((self.configure)(config));
+
+        let mut fixture = TestPlanetFixture {
+            orchestrator,
+            from_planet,
+            to_planet,
+            stats,
+            clock: self.clock,
+            handle,
+            explorers: HashMap::new(),
+        };
+        for explorer_id in self.explorers {
+            fixture.register(explorer_id);
+        }
+        fixture.charge(self.charged_cells);
+        fixture
+    }
+}